@@ -6,7 +6,7 @@ mod desktop_chrome;
 
 use crate::{
     auth_gate::AuthGate,
-    critical_pages::{Customers, Login, Overview, Register},
+    critical_pages::{Customers, Login, Overview, Register, ResetPassword},
     functional_pages::{
         APIKeys, Billing, Evaluation, Guardrails, Logs, Models, Playground, Providers, Routes,
         Settings, Team,
@@ -57,6 +57,9 @@ pub enum Route {
     Login {},
     #[route("/register")]
     Register {},
+    /// Target of the password reset email
+    #[route("/reset-password?:token")]
+    ResetPassword { token: String },
 }
 
 #[component]
@@ -8,12 +8,27 @@ pub struct CurrentUser {
     pub username: String,
     #[serde(default)]
     pub roles: Vec<String>,
+    /// Permission names granted by `roles`; `*` grants everything
+    #[serde(default)]
+    pub permissions: Vec<String>,
+}
+
+impl CurrentUser {
+    /// Whether the user may perform actions guarded by `permission`, e.g. `channels:write`
+    pub fn can(&self, permission: &str) -> bool {
+        self.permissions.iter().any(|p| p == "*" || p == permission)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ClientState {
     pub last_username: Option<String>,
     pub auth_token: Option<String>,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    /// Access token expiry (Unix seconds)
+    #[serde(default)]
+    pub token_expires_at: Option<i64>,
     pub user_info: Option<String>,
 }
 
@@ -106,13 +121,20 @@ impl AuthContext {
         self.user.read().clone()
     }
 
-    pub fn set(mut self, token: String, user: CurrentUser, remember: bool) {
-        *self.token.write() = Some(token.clone());
+    /// Whether the signed-in user holds `permission`; hides actions the server would refuse
+    pub fn can(&self, permission: &str) -> bool {
+        self.user.read().as_ref().is_some_and(|user| user.can(permission))
+    }
+
+    pub fn set(mut self, auth: &AuthData, user: CurrentUser, remember: bool) {
+        *self.token.write() = Some(auth.token.clone());
         *self.user.write() = Some(user.clone());
         if remember {
             ClientState {
                 last_username: Some(user.username.clone()),
-                auth_token: Some(token),
+                auth_token: Some(auth.token.clone()),
+                refresh_token: Some(auth.refresh_token.clone()).filter(|t| !t.is_empty()),
+                token_expires_at: Some(auth.expires_at).filter(|at| *at > 0),
                 user_info: serde_json::to_string(&user).ok(),
             }
             .save();
@@ -126,10 +148,62 @@ impl AuthContext {
         *self.user.write() = None;
         ClientState::clear();
     }
+
+    /// Revoke the session on the server, then forget it locally
+    pub async fn sign_out(self) {
+        if let Some(token) = self.token() {
+            let _ = AuthService::logout(&token).await;
+        }
+        self.clear();
+    }
+
+    /// Swap the refresh token for a new pair when the access token is about
+    /// to expire. A rejected refresh token signs the user out.
+    async fn refresh_if_due(self) {
+        let state = ClientState::load();
+        let (Some(refresh_token), Some(expires_at)) = (state.refresh_token, state.token_expires_at) else {
+            return;
+        };
+        if expires_at - unix_now() > REFRESH_MARGIN_SECS || self.user().is_none() {
+            return;
+        }
+        match AuthService::refresh(&refresh_token).await {
+            Ok(auth) => {
+                let user = CurrentUser {
+                    id: auth.id.clone(),
+                    username: auth.username.clone(),
+                    roles: auth.roles.clone(),
+                    permissions: auth.permissions.clone(),
+                };
+                self.set(&auth, user, true);
+            }
+            Err(RefreshError::Rejected) => self.clear(),
+            // Server unreachable, try again on the next tick
+            Err(RefreshError::Unavailable(_)) => {}
+        }
+    }
+}
+
+/// Refresh this long before the access token expires
+const REFRESH_MARGIN_SECS: i64 = 120;
+const REFRESH_CHECK_INTERVAL_SECS: u64 = 30;
+
+fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
 }
 
 pub fn use_init_auth() -> AuthContext {
-    use_context_provider(AuthContext::new)
+    let auth = use_context_provider(AuthContext::new);
+    use_future(move || async move {
+        loop {
+            tokio::time::sleep(tokio::time::Duration::from_secs(REFRESH_CHECK_INTERVAL_SECS)).await;
+            auth.refresh_if_due().await;
+        }
+    });
+    auth
 }
 
 pub fn use_auth() -> AuthContext {
@@ -156,6 +230,7 @@ fn auth_token_from_disk() -> Option<String> {
 }
 
 fn with_auth(request: RequestBuilder) -> RequestBuilder {
+    let request = with_step_up(request);
     if let Some(token) = auth_token_from_disk() {
         request.header("Authorization", format!("Bearer {token}"))
     } else {
@@ -163,6 +238,26 @@ fn with_auth(request: RequestBuilder) -> RequestBuilder {
     }
 }
 
+/// Authenticator code typed into the console top bar by users with
+/// two-factor authentication, needed for sensitive admin changes
+static STEP_UP_CODE: std::sync::Mutex<String> = std::sync::Mutex::new(String::new());
+
+pub fn set_step_up_code(code: &str) {
+    if let Ok(mut current) = STEP_UP_CODE.lock() {
+        *current = code.trim().to_string();
+    }
+}
+
+/// Send the step-up code, when one was entered, as `X-TOTP-Code`
+pub(crate) fn with_step_up(request: RequestBuilder) -> RequestBuilder {
+    let code = STEP_UP_CODE.lock().map(|code| code.clone()).unwrap_or_default();
+    if code.is_empty() {
+        request
+    } else {
+        request.header("X-TOTP-Code", code)
+    }
+}
+
 fn with_token(request: RequestBuilder, token: &str) -> RequestBuilder {
     request.header("Authorization", format!("Bearer {token}"))
 }
@@ -206,11 +301,33 @@ async fn decode_unit(response: Response) -> Result<(), String> {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthData {
+    #[serde(default)]
     pub id: String,
+    #[serde(default)]
     pub username: String,
     #[serde(default)]
     pub roles: Vec<String>,
+    #[serde(default)]
+    pub permissions: Vec<String>,
+    #[serde(default)]
     pub token: String,
+    #[serde(default)]
+    pub refresh_token: String,
+    #[serde(default)]
+    pub expires_at: i64,
+    /// The password was accepted but a TOTP or recovery code is still due;
+    /// finish with `AuthService::login_2fa` and `challenge`
+    #[serde(default)]
+    pub two_factor_required: bool,
+    #[serde(default)]
+    pub challenge: String,
+}
+
+#[derive(Debug)]
+pub enum RefreshError {
+    /// The session was revoked or expired
+    Rejected,
+    Unavailable(String),
 }
 
 pub struct AuthService;
@@ -226,6 +343,16 @@ impl AuthService {
         decode_envelope(response).await
     }
 
+    pub async fn login_2fa(challenge: &str, code: &str) -> Result<AuthData, String> {
+        let response = Client::new()
+            .post(url("/api/auth/login/2fa"))
+            .json(&serde_json::json!({ "challenge": challenge, "code": code }))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        decode_envelope(response).await
+    }
+
     pub async fn register(username: &str, password: &str, email: Option<&str>) -> Result<AuthData, String> {
         let response = Client::new()
             .post(url("/api/auth/register"))
@@ -236,6 +363,27 @@ impl AuthService {
         decode_envelope(response).await
     }
 
+    pub async fn refresh(refresh_token: &str) -> Result<AuthData, RefreshError> {
+        let response = Client::new()
+            .post(url("/api/auth/refresh"))
+            .json(&serde_json::json!({ "refresh_token": refresh_token }))
+            .send()
+            .await
+            .map_err(|e| RefreshError::Unavailable(e.to_string()))?;
+        if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+            return Err(RefreshError::Rejected);
+        }
+        decode_envelope(response).await.map_err(RefreshError::Unavailable)
+    }
+
+    pub async fn logout(token: &str) -> Result<(), String> {
+        let response = with_token(Client::new().post(url("/console/api/auth/logout")), token)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        decode_unit(response).await
+    }
+
     pub async fn forgot_password(email: &str) -> Result<(), String> {
         let response = Client::new()
             .post(url("/api/auth/forgot-password"))
@@ -245,6 +393,389 @@ impl AuthService {
             .map_err(|e| e.to_string())?;
         decode_unit(response).await
     }
+
+    /// Set a new password with the token from a reset email
+    pub async fn reset_password(token: &str, new_password: &str) -> Result<(), String> {
+        let response = Client::new()
+            .post(url("/api/auth/reset-password"))
+            .json(&serde_json::json!({ "token": token, "new_password": new_password }))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        decode_unit(response).await
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct TwoFactorStatus {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub pending: bool,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub recovery_codes_remaining: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct TotpSetup {
+    pub secret: String,
+    pub otpauth_uri: String,
+    #[serde(default)]
+    pub qr_svg: String,
+}
+
+#[derive(Deserialize)]
+struct RecoveryCodes {
+    recovery_codes: Vec<String>,
+}
+
+pub struct TwoFactorService;
+
+impl TwoFactorService {
+    pub async fn status() -> Result<TwoFactorStatus, String> {
+        let response = with_auth(Client::new().get(url("/console/api/auth/2fa")))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        decode_envelope(response).await
+    }
+
+    pub async fn setup() -> Result<TotpSetup, String> {
+        let response = with_auth(Client::new().post(url("/console/api/auth/2fa/setup")))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        decode_envelope(response).await
+    }
+
+    /// Returns the recovery codes, shown once
+    pub async fn enable(code: &str) -> Result<Vec<String>, String> {
+        let response = with_auth(Client::new().post(url("/console/api/auth/2fa/enable")))
+            .json(&serde_json::json!({ "code": code }))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        decode_envelope::<RecoveryCodes>(response).await.map(|v| v.recovery_codes)
+    }
+
+    pub async fn disable(code: &str) -> Result<(), String> {
+        let response = with_auth(Client::new().post(url("/console/api/auth/2fa/disable")))
+            .json(&serde_json::json!({ "code": code }))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        decode_unit(response).await
+    }
+
+    pub async fn regenerate_recovery_codes(code: &str) -> Result<Vec<String>, String> {
+        let response = with_auth(Client::new().post(url("/console/api/auth/2fa/recovery-codes")))
+            .json(&serde_json::json!({ "code": code }))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        decode_envelope::<RecoveryCodes>(response).await.map(|v| v.recovery_codes)
+    }
+}
+
+/// Management API key of the signed-in user, without the key itself
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct ManagementKey {
+    pub id: String,
+    pub name: String,
+    pub prefix: String,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    #[serde(default)]
+    pub allowed_ips: Vec<String>,
+    #[serde(default)]
+    pub created_at: i64,
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+    #[serde(default)]
+    pub last_used_at: Option<i64>,
+    #[serde(default)]
+    pub last_used_ip: Option<String>,
+    #[serde(default)]
+    pub active: bool,
+}
+
+/// A freshly created key; `secret` is shown once
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct CreatedManagementKey {
+    #[serde(flatten)]
+    pub key: ManagementKey,
+    pub secret: String,
+}
+
+pub struct ManagementKeyService;
+
+impl ManagementKeyService {
+    pub async fn list() -> Result<Vec<ManagementKey>, String> {
+        let response = with_auth(Client::new().get(url("/console/api/management-keys")))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        decode_envelope(response).await
+    }
+
+    pub async fn create(
+        name: &str,
+        scopes: &[String],
+        allowed_ips: &[String],
+        expires_in_days: Option<i64>,
+    ) -> Result<CreatedManagementKey, String> {
+        let expires_at = expires_in_days.map(|days| unix_now() + days * 86_400);
+        let response = with_auth(Client::new().post(url("/console/api/management-keys")))
+            .json(&serde_json::json!({
+                "name": name,
+                "scopes": scopes,
+                "allowed_ips": allowed_ips,
+                "expires_at": expires_at,
+            }))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        decode_envelope(response).await
+    }
+
+    pub async fn revoke(id: &str) -> Result<(), String> {
+        let response = with_auth(Client::new().delete(url(&format!("/console/api/management-keys/{id}"))))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        decode_unit(response).await
+    }
+}
+
+/// A provider that can take top-ups, with the wallet currencies it charges in
+#[derive(Debug, Clone, Deserialize, PartialEq, Default)]
+pub struct PaymentProviderInfo {
+    pub name: String,
+    #[serde(default)]
+    pub currencies: Vec<String>,
+    /// Can charge saved payment methods, for auto top-up
+    #[serde(default)]
+    pub saved_methods: bool,
+}
+
+/// Enabled providers and checkout limits, in minor units
+#[derive(Debug, Clone, Deserialize, PartialEq, Default)]
+pub struct PaymentOptions {
+    #[serde(default)]
+    pub providers: Vec<PaymentProviderInfo>,
+    #[serde(default)]
+    pub min_amount: i64,
+    #[serde(default)]
+    pub max_amount: i64,
+}
+
+/// An online top-up; amounts are in the minor unit of `currency`
+#[derive(Debug, Clone, Deserialize, PartialEq, Default)]
+pub struct Payment {
+    pub id: String,
+    pub provider: String,
+    pub amount: i64,
+    pub currency: String,
+    #[serde(default)]
+    pub refunded_amount: i64,
+    pub status: String,
+    #[serde(default)]
+    pub created_at: i64,
+    #[serde(default)]
+    pub paid_at: Option<i64>,
+}
+
+/// A started checkout: open `url`, or scan `qr_svg` when `kind` is `qr_code`
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct PaymentCheckout {
+    pub payment: Payment,
+    pub url: String,
+    pub kind: String,
+    #[serde(default)]
+    pub qr_svg: Option<String>,
+}
+
+/// A card saved under the signed-in user's provider customer
+#[derive(Debug, Clone, Deserialize, PartialEq, Default)]
+pub struct SavedMethod {
+    pub id: String,
+    #[serde(default)]
+    pub brand: String,
+    #[serde(default)]
+    pub last4: String,
+    #[serde(default)]
+    pub exp_month: u32,
+    #[serde(default)]
+    pub exp_year: u32,
+}
+
+pub struct PaymentService;
+
+impl PaymentService {
+    pub async fn options() -> Result<PaymentOptions, String> {
+        let response = with_auth(Client::new().get(url("/console/api/payments/options")))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        decode_envelope(response).await
+    }
+
+    pub async fn list() -> Result<Vec<Payment>, String> {
+        let response = with_auth(Client::new().get(url("/console/api/payments")))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        decode_envelope(response).await
+    }
+
+    /// `save_method` keeps the card for auto top-up
+    pub async fn checkout(provider: &str, amount: i64, currency: &str, save_method: bool) -> Result<PaymentCheckout, String> {
+        let response = with_auth(Client::new().post(url("/console/api/payments/checkout")))
+            .json(&serde_json::json!({
+                "provider": provider,
+                "amount": amount,
+                "currency": currency,
+                "save_method": save_method,
+            }))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        decode_envelope(response).await
+    }
+
+    pub async fn saved_methods(provider: &str) -> Result<Vec<SavedMethod>, String> {
+        let response = with_auth(Client::new().get(url("/console/api/payments/methods")))
+            .query(&[("provider", provider)])
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        decode_envelope(response).await
+    }
+}
+
+/// Auto top-up of the signed-in user; `amount` is in minor units of `currency`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct AutoTopup {
+    pub provider: String,
+    pub method: String,
+    pub amount: i64,
+    pub currency: String,
+}
+
+/// How the signed-in user is notified; only the owner sees the webhook secret
+#[derive(Debug, Clone, Deserialize, PartialEq, Default)]
+pub struct NotificationSettings {
+    #[serde(default)]
+    pub email_enabled: i32,
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    #[serde(default)]
+    pub webhook_secret: Option<String>,
+    #[serde(default)]
+    pub auto_topup_provider: Option<String>,
+    #[serde(default)]
+    pub auto_topup_method: Option<String>,
+    #[serde(default)]
+    pub auto_topup_amount: Option<i64>,
+    #[serde(default)]
+    pub auto_topup_currency: Option<String>,
+}
+
+impl NotificationSettings {
+    pub fn auto_topup(&self) -> Option<AutoTopup> {
+        Some(AutoTopup {
+            provider: self.auto_topup_provider.clone()?,
+            method: self.auto_topup_method.clone()?,
+            amount: self.auto_topup_amount?,
+            currency: self.auto_topup_currency.clone()?,
+        })
+    }
+}
+
+/// A wallet threshold (`token` empty) or an API key threshold. `value` is a
+/// percent of the key quota or nanodollars left, per `kind`.
+#[derive(Debug, Clone, Deserialize, PartialEq, Default)]
+pub struct NotificationThreshold {
+    pub id: String,
+    #[serde(default)]
+    pub token: Option<String>,
+    #[serde(default)]
+    pub token_hint: Option<String>,
+    #[serde(default)]
+    pub currency: Option<String>,
+    pub kind: String,
+    pub value: i64,
+    #[serde(default)]
+    pub triggered_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Default)]
+pub struct Notifications {
+    #[serde(default)]
+    pub settings: NotificationSettings,
+    #[serde(default)]
+    pub thresholds: Vec<NotificationThreshold>,
+}
+
+pub struct NotificationService;
+
+impl NotificationService {
+    pub async fn get() -> Result<Notifications, String> {
+        let response = with_auth(Client::new().get(url("/console/api/notifications")))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        decode_envelope(response).await
+    }
+
+    pub async fn save(
+        email_enabled: bool,
+        webhook_url: &str,
+        rotate_secret: bool,
+        auto_topup: Option<&AutoTopup>,
+    ) -> Result<NotificationSettings, String> {
+        let response = with_auth(Client::new().put(url("/console/api/notifications")))
+            .json(&serde_json::json!({
+                "email_enabled": email_enabled,
+                "webhook_url": webhook_url,
+                "rotate_secret": rotate_secret,
+                "auto_topup": auto_topup,
+            }))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        decode_envelope(response).await
+    }
+
+    pub async fn add_threshold(
+        token: Option<&str>,
+        currency: Option<&str>,
+        kind: &str,
+        value: i64,
+    ) -> Result<NotificationThreshold, String> {
+        let response = with_auth(Client::new().post(url("/console/api/notifications/thresholds")))
+            .json(&serde_json::json!({
+                "token": token,
+                "currency": currency,
+                "kind": kind,
+                "value": value,
+            }))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        decode_envelope(response).await
+    }
+
+    pub async fn delete_threshold(id: &str) -> Result<(), String> {
+        let response =
+            with_auth(Client::new().delete(url(&format!("/console/api/notifications/thresholds/{id}"))))
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+        decode_unit(response).await
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
@@ -258,6 +789,8 @@ pub struct User {
     #[serde(default)]
     pub role: String,
     #[serde(default)]
+    pub roles: Vec<String>,
+    #[serde(default)]
     pub balance_usd: i64,
     #[serde(default)]
     pub balance_cny: i64,
@@ -2,6 +2,6 @@ mod auth;
 mod customers_portable;
 mod dashboard;
 
-pub use auth::{Login, Register};
+pub use auth::{Login, Register, ResetPassword};
 pub use customers_portable::Customers;
 pub use dashboard::Overview;
@@ -15,6 +15,9 @@ pub fn Login() -> Element {
 
     let mut username = use_signal(move || last_username);
     let mut password = use_signal(String::new);
+    // Set once the password is accepted for an account with 2FA
+    let mut challenge = use_signal(String::new);
+    let mut second_factor = use_signal(String::new);
     let mut recovery_open = use_signal(|| false);
     let mut recovery_email = use_signal(String::new);
     let mut loading = use_signal(|| false);
@@ -56,6 +59,44 @@ pub fn Login() -> Element {
                             class: "auth-form",
                             onsubmit: move |event| {
                                 event.prevent_default();
+                                if !challenge().is_empty() {
+                                    let code = second_factor().trim().to_string();
+                                    if code.is_empty() {
+                                        is_error.set(true);
+                                        status.set("Enter the code from your authenticator app.".to_string());
+                                        return;
+                                    }
+                                    loading.set(true);
+                                    is_error.set(false);
+                                    status.set("Verifying…".to_string());
+                                    let nav = navigator;
+                                    spawn(async move {
+                                        match AuthService::login_2fa(&challenge(), &code).await {
+                                            Ok(response) => {
+                                                let user = CurrentUser {
+                                                    id: response.id.clone(),
+                                                    username: response.username.clone(),
+                                                    roles: response.roles.clone(),
+                                                    permissions: response.permissions.clone(),
+                                                };
+                                                auth.set(&response, user, true);
+                                                loading.set(false);
+                                                status.set(String::new());
+                                                nav.replace(Route::Overview {});
+                                            }
+                                            Err(error) => {
+                                                // A challenge dies after a few wrong codes, so
+                                                // start over from the password step
+                                                loading.set(false);
+                                                is_error.set(true);
+                                                second_factor.set(String::new());
+                                                challenge.set(String::new());
+                                                status.set(format!("Verification failed: {error}. Sign in again."));
+                                            }
+                                        }
+                                    });
+                                    return;
+                                }
                                 let user_name = username().trim().to_string();
                                 let user_password = password();
                                 if user_name.is_empty() || user_password.is_empty() {
@@ -69,13 +110,20 @@ pub fn Login() -> Element {
                                 let nav = navigator.clone();
                                 spawn(async move {
                                     match AuthService::login(&user_name, &user_password).await {
+                                        Ok(response) if response.two_factor_required => {
+                                            loading.set(false);
+                                            is_error.set(false);
+                                            challenge.set(response.challenge);
+                                            status.set("Enter the 6-digit code from your authenticator app, or one of your recovery codes.".to_string());
+                                        }
                                         Ok(response) => {
                                             let user = CurrentUser {
-                                                id: response.id,
-                                                username: response.username,
-                                                roles: response.roles,
+                                                id: response.id.clone(),
+                                                username: response.username.clone(),
+                                                roles: response.roles.clone(),
+                                                permissions: response.permissions.clone(),
                                             };
-                                            auth.set(response.token, user, true);
+                                            auth.set(&response, user, true);
                                             loading.set(false);
                                             status.set(String::new());
                                             nav.replace(Route::Overview {});
@@ -88,44 +136,61 @@ pub fn Login() -> Element {
                                     }
                                 });
                             },
-                            div { class: "field",
-                                label { "Username" }
-                                input {
-                                    class: "input",
-                                    r#type: "text",
-                                    autocomplete: "username",
-                                    required: true,
-                                    value: "{username}",
-                                    placeholder: "Your BurnCloud username",
-                                    disabled: loading(),
-                                    oninput: move |event| username.set(event.value()),
+                            if !challenge().is_empty() {
+                                div { class: "field",
+                                    label { "Authentication code" }
+                                    input {
+                                        class: "input",
+                                        r#type: "text",
+                                        autocomplete: "one-time-code",
+                                        inputmode: "numeric",
+                                        required: true,
+                                        value: "{second_factor}",
+                                        placeholder: "123456 or a recovery code",
+                                        disabled: loading(),
+                                        oninput: move |event| second_factor.set(event.value()),
+                                    }
                                 }
-                            }
-
-                            div { class: "field",
-                                div { class: "row between",
-                                    label { "Password" }
-                                    button {
-                                        r#type: "button",
-                                        class: "button button-ghost button-sm",
+                            } else {
+                                div { class: "field",
+                                    label { "Username" }
+                                    input {
+                                        class: "input",
+                                        r#type: "text",
+                                        autocomplete: "username",
+                                        required: true,
+                                        value: "{username}",
+                                        placeholder: "Your BurnCloud username",
                                         disabled: loading(),
-                                        onclick: move |_| {
-                                            recovery_open.set(!recovery_open());
-                                            recovery_status.set(String::new());
-                                            recovery_error.set(false);
-                                        },
-                                        "Forgot password?"
+                                        oninput: move |event| username.set(event.value()),
                                     }
                                 }
-                                input {
-                                    class: "input",
-                                    r#type: "password",
-                                    autocomplete: "current-password",
-                                    required: true,
-                                    value: "{password}",
-                                    placeholder: "Enter your password",
-                                    disabled: loading(),
-                                    oninput: move |event| password.set(event.value()),
+
+                                div { class: "field",
+                                    div { class: "row between",
+                                        label { "Password" }
+                                        button {
+                                            r#type: "button",
+                                            class: "button button-ghost button-sm",
+                                            disabled: loading(),
+                                            onclick: move |_| {
+                                                recovery_open.set(!recovery_open());
+                                                recovery_status.set(String::new());
+                                                recovery_error.set(false);
+                                            },
+                                            "Forgot password?"
+                                        }
+                                    }
+                                    input {
+                                        class: "input",
+                                        r#type: "password",
+                                        autocomplete: "current-password",
+                                        required: true,
+                                        value: "{password}",
+                                        placeholder: "Enter your password",
+                                        disabled: loading(),
+                                        oninput: move |event| password.set(event.value()),
+                                    }
                                 }
                             }
 
@@ -138,7 +203,7 @@ pub fn Login() -> Element {
                                 class: "button button-primary button-lg",
                                 style: "width:100%",
                                 disabled: loading(),
-                                if loading() { "Signing in…" } else { "Sign in to Console" }
+                                if loading() { "Signing in…" } else if !challenge().is_empty() { "Verify Code" } else { "Sign in to Console" }
                             }
                         }
 
@@ -272,11 +337,12 @@ pub fn Register() -> Element {
                                     match AuthService::register(&user_name, &user_password, email_arg).await {
                                         Ok(response) => {
                                             let user = CurrentUser {
-                                                id: response.id,
-                                                username: response.username,
-                                                roles: response.roles,
+                                                id: response.id.clone(),
+                                                username: response.username.clone(),
+                                                roles: response.roles.clone(),
+                                                permissions: response.permissions.clone(),
                                             };
-                                            auth.set(response.token, user, true);
+                                            auth.set(&response, user, true);
                                             loading.set(false);
                                             status.set(String::new());
                                             nav.replace(Route::Overview {});
@@ -365,6 +431,131 @@ pub fn Register() -> Element {
     }
 }
 
+/// Opened from the password reset email; `token` comes from its link
+#[component]
+pub fn ResetPassword(token: String) -> Element {
+    let mut password = use_signal(String::new);
+    let mut confirm = use_signal(String::new);
+    let mut loading = use_signal(|| false);
+    let mut done = use_signal(|| false);
+    let mut status = use_signal(String::new);
+    let mut is_error = use_signal(|| false);
+    let missing_token = token.trim().is_empty();
+
+    rsx! {
+        div { class: "auth-page",
+            header { class: "auth-header",
+                Link { to: Route::Home {}, class: "brand-link",
+                    Logo {}
+                    span { class: "brand-name", "BurnCloud" }
+                }
+                div { class: "auth-header-note",
+                    span { "Remembered it?" }
+                    Link { to: Route::Login {}, class: "strong", "Sign in" }
+                }
+            }
+
+            main { class: "auth-main",
+                div { class: "auth-wrap",
+                    div { class: "auth-intro",
+                        Badge { text: "BurnCloud Account", tone: "brand" }
+                        h1 { "Choose a new password" }
+                        p { "The link in your email works once. Every session of the account is signed out after the reset." }
+                    }
+
+                    div { class: "card auth-card",
+                        if missing_token {
+                            div { class: "terminal auth-status auth-status-error",
+                                "This reset link is incomplete. Request a new one from the Sign In page."
+                            }
+                        } else if done() {
+                            div { class: "terminal auth-status", "{status}" }
+                            Link { to: Route::Login {}, class: "button button-primary button-lg", style: "width:100%", "Sign in" }
+                        } else {
+                            form {
+                                class: "auth-form",
+                                onsubmit: move |event| {
+                                    event.prevent_default();
+                                    let new_password = password();
+                                    if new_password.len() < 8 {
+                                        is_error.set(true);
+                                        status.set("Password must contain at least 8 characters.".to_string());
+                                        return;
+                                    }
+                                    if new_password != confirm() {
+                                        is_error.set(true);
+                                        status.set("The passwords do not match.".to_string());
+                                        return;
+                                    }
+                                    loading.set(true);
+                                    is_error.set(false);
+                                    status.set("Resetting password…".to_string());
+                                    let token = token.clone();
+                                    spawn(async move {
+                                        match AuthService::reset_password(&token, &new_password).await {
+                                            Ok(()) => {
+                                                loading.set(false);
+                                                done.set(true);
+                                                status.set("Your password was reset. Sign in with the new password.".to_string());
+                                            }
+                                            Err(error) => {
+                                                loading.set(false);
+                                                is_error.set(true);
+                                                status.set(format!("Password reset failed: {error}"));
+                                            }
+                                        }
+                                    });
+                                },
+                                div { class: "field",
+                                    label { "New password" }
+                                    input {
+                                        class: "input",
+                                        r#type: "password",
+                                        autocomplete: "new-password",
+                                        required: true,
+                                        value: "{password}",
+                                        placeholder: "At least 8 characters",
+                                        disabled: loading(),
+                                        oninput: move |event| password.set(event.value()),
+                                    }
+                                }
+
+                                div { class: "field",
+                                    label { "Confirm new password" }
+                                    input {
+                                        class: "input",
+                                        r#type: "password",
+                                        autocomplete: "new-password",
+                                        required: true,
+                                        value: "{confirm}",
+                                        placeholder: "Repeat the new password",
+                                        disabled: loading(),
+                                        oninput: move |event| confirm.set(event.value()),
+                                    }
+                                }
+
+                                if !status().is_empty() {
+                                    div { class: if is_error() { "terminal auth-status auth-status-error" } else { "terminal auth-status" }, "{status}" }
+                                }
+
+                                button {
+                                    r#type: "submit",
+                                    class: "button button-primary button-lg",
+                                    style: "width:100%",
+                                    disabled: loading(),
+                                    if loading() { "Resetting…" } else { "Reset Password" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            AuthFooter { alternate: "register" }
+        }
+    }
+}
+
 #[component]
 fn AuthFooter(alternate: &'static str) -> Element {
     rsx! {
@@ -1,7 +1,7 @@
 use dioxus::prelude::*;
 
 use crate::{
-    backend::{User, UserService},
+    backend::{use_auth, User, UserService},
     components::Icon,
 };
 
@@ -90,6 +90,7 @@ fn is_staff_role(role: &str) -> bool {
 
 #[component]
 pub fn Customers() -> Element {
+    let can_topup = use_auth().can("billing:topup");
     let mut users = use_resource(move || async move { UserService::list().await });
     let mut query = use_signal(String::new);
     let mut default_status_only = use_signal(|| false);
@@ -346,6 +347,7 @@ pub fn Customers() -> Element {
                                                     td { class: "right",
                                                         button {
                                                             class: "button button-secondary button-sm",
+                                                            disabled: !can_topup,
                                                             onclick: move |_| {
                                                                 amount.set("100".to_string());
                                                                 currency.set(
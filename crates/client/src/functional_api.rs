@@ -1,7 +1,8 @@
 use reqwest::{Client, RequestBuilder};
-use serde::{Deserialize, Serialize};
+use serde::{de::IgnoredAny, Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-use crate::backend::{server_root, Channel, ClientState};
+use crate::backend::{server_root, with_step_up, Channel, ClientState};
 
 fn url(path: &str) -> String {
     format!("{}{}", server_root(), path)
@@ -78,7 +79,7 @@ pub async fn save_security_filters(filters: &SecurityFilters) -> Result<Security
         "blacklist_enabled": filters.blacklist_enabled,
         "custom_rules": filters.custom_rules,
     });
-    let request = authenticated(Client::new().put(url("/console/api/monitor/security/filters")))?.json(&body);
+    let request = with_step_up(authenticated(Client::new().put(url("/console/api/monitor/security/filters")))?).json(&body);
     response_json(request).await
 }
 
@@ -100,11 +101,14 @@ pub async fn circuit_breaker_status() -> Result<serde_json::Value, String> {
     if response.success { Ok(response.data) } else { Err(response.message.unwrap_or_else(|| "Circuit breaker status request failed".to_string())) }
 }
 
-pub async fn emergency_circuit_break(reason: &str) -> Result<serde_json::Value, String> {
+/// `totp_code` is the step-up code required from accounts with two-factor
+/// authentication; when empty, the code from the top bar is sent if any.
+pub async fn emergency_circuit_break(reason: &str, totp_code: &str) -> Result<(), String> {
     let request = authenticated(Client::new().post(url("/console/api/monitor/security/emergency-circuit-break")))?
         .json(&serde_json::json!({ "reason": reason }));
+    let request = if totp_code.is_empty() { with_step_up(request) } else { request.header("X-TOTP-Code", totp_code) };
     let response: EnvelopeValue = response_json(request).await?;
-    if response.success { Ok(response.data) } else { Err(response.message.unwrap_or_else(|| "Emergency circuit break failed".to_string())) }
+    if response.success { Ok(()) } else { Err(response.message.unwrap_or_else(|| "Emergency circuit break failed".to_string())) }
 }
 
 pub async fn cache_stats() -> Result<serde_json::Value, String> {
@@ -178,3 +182,79 @@ pub async fn update_channel_preserving_reservations(channel: &Channel) -> Result
         Err(updated.message.unwrap_or_else(|| "Provider update failed".to_string()))
     }
 }
+
+#[derive(Debug, Clone, Deserialize, Default, PartialEq)]
+pub struct AuditEntry {
+    #[serde(default)] pub id: i64,
+    #[serde(default)] pub created_at: i64,
+    #[serde(default)] pub actor_id: Option<String>,
+    #[serde(default)] pub actor_name: Option<String>,
+    #[serde(default)] pub source: String,
+    #[serde(default)] pub ip: Option<String>,
+    #[serde(default)] pub action: String,
+    #[serde(default)] pub target_type: String,
+    #[serde(default)] pub target_id: Option<String>,
+    /// Changed field names; the before/after values are only in the export
+    #[serde(default)] pub changes: Option<BTreeMap<String, IgnoredAny>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default, PartialEq)]
+pub struct AuditPage {
+    #[serde(default)] pub entries: Vec<AuditEntry>,
+    #[serde(default)] pub total: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct AuditEnvelope {
+    #[serde(default)] success: bool,
+    #[serde(default)] data: AuditPage,
+    #[serde(default)] message: Option<String>,
+}
+
+/// Filters shared by the audit list and export; empty fields are not sent.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuditQuery {
+    pub actor: String,
+    pub action: String,
+    pub target_type: String,
+    pub since: String,
+    pub until: String,
+}
+
+impl AuditQuery {
+    fn params(&self) -> Vec<(&'static str, String)> {
+        [
+            ("actor", &self.actor),
+            ("action", &self.action),
+            ("target_type", &self.target_type),
+            ("since", &self.since),
+            ("until", &self.until),
+        ]
+        .into_iter()
+        .filter(|(_, value)| !value.trim().is_empty())
+        .map(|(name, value)| (name, value.trim().to_string()))
+        .collect()
+    }
+}
+
+pub async fn audit_log(query: &AuditQuery, limit: i64, offset: i64) -> Result<AuditPage, String> {
+    let request = authenticated(Client::new().get(url("/console/api/audit")))?
+        .query(&query.params())
+        .query(&[("limit", limit), ("offset", offset)]);
+    let response: AuditEnvelope = response_json(request).await?;
+    if response.success { Ok(response.data) } else { Err(response.message.unwrap_or_else(|| "Audit log request failed".to_string())) }
+}
+
+/// The full matching trail as CSV text
+pub async fn export_audit_csv(query: &AuditQuery) -> Result<String, String> {
+    let request = authenticated(Client::new().get(url("/console/api/audit/export")))?
+        .query(&query.params())
+        .query(&[("format", "csv")]);
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    let status = response.status();
+    let text = response.text().await.map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!("API request failed ({status}): {text}"));
+    }
+    Ok(text)
+}
@@ -2,7 +2,7 @@ use dioxus::prelude::*;
 
 use crate::{
     app::Route,
-    backend::use_auth,
+    backend::{set_step_up_code, use_auth, TwoFactorService},
     components::{Icon, Logo},
 };
 
@@ -24,6 +24,7 @@ fn page_title(route: &Route) -> &'static str {
         Route::Home {} | Route::Landing {} => "Home",
         Route::Login {} => "Sign In",
         Route::Register {} => "Register",
+        Route::ResetPassword { .. } => "Reset Password",
     }
 }
 
@@ -85,6 +86,10 @@ pub fn FunctionalConsoleLayout() -> Element {
     let roles = user.as_ref().map(|u| u.roles.join(", ")).unwrap_or_default();
     let mut search = use_signal(String::new);
     let mut search_status = use_signal(String::new);
+    let mut step_up = use_signal(String::new);
+    let two_factor = use_resource(|| async {
+        TwoFactorService::status().await.map(|status| status.enabled).unwrap_or(false)
+    });
 
     rsx! {
         div { class:"console-shell",
@@ -123,8 +128,8 @@ pub fn FunctionalConsoleLayout() -> Element {
                             button {
                                 class:"nav-item",
                                 style:"width:100%;text-align:left",
-                                onclick:move |_| {
-                                    auth.clear();
+                                onclick:move |_| async move {
+                                    auth.sign_out().await;
                                     navigator.replace(Route::Login {});
                                 },
                                 Icon { name:"logout" }
@@ -162,6 +167,21 @@ pub fn FunctionalConsoleLayout() -> Element {
                                 }
                             }
                         }
+                        if two_factor().unwrap_or(false) {
+                            div { class:"global-search", title:"Authenticator code, required for sensitive admin changes",
+                                Icon { name:"shield" }
+                                input {
+                                    r#type:"text",
+                                    autocomplete:"one-time-code",
+                                    placeholder:"2FA code",
+                                    value:"{step_up}",
+                                    oninput:move |evt| {
+                                        set_step_up_code(&evt.value());
+                                        step_up.set(evt.value());
+                                    }
+                                }
+                            }
+                        }
                         div { class:"top-actions",
                             Link { to:Route::Home {}, class:"tiny-link", Icon { name:"globe" } span { "Landing Page" } }
                             div { class:"env-chip", title:"BurnCloud server is configured; live health is shown on Overview", span { class:"green-dot" } "Server Configured" }
@@ -1,10 +1,12 @@
 use dioxus::prelude::*;
 
 use crate::{
-    backend::{billing_summary, use_auth, BillingSummary},
+    backend::{billing_summary, use_auth, BillingSummary, PaymentCheckout, PaymentService},
     components::Icon,
 };
 
+use super::settings::utc_time;
+
 fn compact(n: i64) -> String {
     if n.abs() >= 1_000_000_000 {
         format!("{:.2}B", n as f64 / 1_000_000_000.0)
@@ -122,6 +124,8 @@ pub fn Billing() -> Element {
                 }
             }
 
+            TopUp {}
+
             if loading {
                 div { class: "card product-empty",
                     div { class: "product-empty-inner",
@@ -251,3 +255,180 @@ pub fn Billing() -> Element {
         }
     }
 }
+
+/// `1234` minor units of `code` as `"12.34 USD"`
+pub(super) fn minor_amount(amount: i64, code: &str) -> String {
+    format!("{}.{:02} {code}", amount / 100, amount % 100)
+}
+
+/// `"12.34"` as `1234` minor units
+pub(super) fn parse_amount(text: &str) -> Option<i64> {
+    let (whole, fraction) = text.trim().split_once('.').unwrap_or((text.trim(), ""));
+    if whole.is_empty() || fraction.len() > 2 || !whole.bytes().all(|b| b.is_ascii_digit()) || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let cents = format!("{fraction:0<2}").parse::<i64>().ok()?;
+    whole.parse::<i64>().ok()?.checked_mul(100)?.checked_add(cents)
+}
+
+/// Online top-up through the configured payment providers, with the
+/// signed-in user's payment history
+#[component]
+fn TopUp() -> Element {
+    let options_resource = use_resource(PaymentService::options);
+    let mut payments_resource = use_resource(PaymentService::list);
+    let mut provider = use_signal(String::new);
+    let mut currency = use_signal(String::new);
+    let mut amount = use_signal(|| "20.00".to_string());
+    let mut save_method = use_signal(|| false);
+    let mut busy = use_signal(|| false);
+    let mut error = use_signal(String::new);
+    let mut checkout = use_signal(|| None::<PaymentCheckout>);
+
+    let options = options_resource.read().clone().and_then(Result::ok).unwrap_or_default();
+    if options.providers.is_empty() {
+        return rsx! {};
+    }
+    let selected = options
+        .providers
+        .iter()
+        .find(|p| p.name == provider())
+        .or_else(|| options.providers.first())
+        .cloned()
+        .unwrap_or_default();
+    let selected_currency = if selected.currencies.contains(&currency()) {
+        currency()
+    } else {
+        selected.currencies.first().cloned().unwrap_or_default()
+    };
+    let limits = format!(
+        "Between {} and {} per top-up.",
+        minor_amount(options.min_amount, &selected_currency),
+        minor_amount(options.max_amount, &selected_currency)
+    );
+    let payments_snapshot = payments_resource.read().clone();
+    let payments_error = payments_snapshot.as_ref().and_then(|result| result.as_ref().err().cloned());
+    let payments = payments_snapshot.and_then(Result::ok).unwrap_or_default();
+
+    rsx! {
+        div { class: "card card-pad stack-lg",
+            div { class: "product-section-head",
+                div {
+                    h3 { "Top up" }
+                    p { "Add funds to your wallet online. The balance is credited as soon as the provider confirms the payment." }
+                }
+                button { class: "button button-ghost button-sm", onclick: move |_| payments_resource.restart(), "Refresh" }
+            }
+            if !error().is_empty() { div { class: "terminal auth-status auth-status-error", "{error}" } }
+
+            div { class: "grid-2",
+                div { class: "field",
+                    label { "Provider" }
+                    select { class: "select", value: "{selected.name}", disabled: busy(), onchange: move |event| provider.set(event.value()),
+                        for option in options.providers.iter() {
+                            option { key: "{option.name}", value: "{option.name}", "{option.name}" }
+                        }
+                    }
+                }
+                div { class: "field",
+                    label { "Currency" }
+                    select { class: "select", value: "{selected_currency}", disabled: busy(), onchange: move |event| currency.set(event.value()),
+                        for code in selected.currencies.iter() {
+                            option { key: "{code}", value: "{code}", "{code}" }
+                        }
+                    }
+                }
+                div { class: "field",
+                    label { "Amount" }
+                    input { class: "input mono", value: "{amount}", placeholder: "20.00", disabled: busy(), oninput: move |event| amount.set(event.value()) }
+                    span { class: "small muted", "{limits}" }
+                }
+            }
+            if selected.saved_methods {
+                label { class: "row gap-2 small",
+                    input { r#type: "checkbox", checked: save_method(), disabled: busy(), onchange: move |_| save_method.set(!save_method()) }
+                    span { "Save this card for auto top-up" }
+                }
+            }
+            div { class: "row",
+                button {
+                    class: "button button-primary",
+                    disabled: busy(),
+                    onclick: {
+                        let provider_name = selected.name.clone();
+                        let code = selected_currency.clone();
+                        let save = selected.saved_methods && save_method();
+                        move |_| {
+                            let Some(minor) = parse_amount(&amount()) else {
+                                error.set("Enter an amount such as 20.00.".to_string());
+                                return;
+                            };
+                            let provider_name = provider_name.clone();
+                            let code = code.clone();
+                            busy.set(true);
+                            error.set(String::new());
+                            checkout.set(None);
+                            spawn(async move {
+                                match PaymentService::checkout(&provider_name, minor, &code, save).await {
+                                    Ok(started) => {
+                                        checkout.set(Some(started));
+                                        payments_resource.restart();
+                                    }
+                                    Err(message) => error.set(format!("Could not start the payment: {message}")),
+                                }
+                                busy.set(false);
+                            });
+                        }
+                    },
+                    if busy() { "Starting…" } else { "Pay" }
+                }
+            }
+
+            if let Some(started) = checkout() {
+                div { class: "product-note stack",
+                    if let Some(svg) = started.qr_svg.clone() {
+                        p { class: "small strong", "Scan with the {started.payment.provider} app to pay {minor_amount(started.payment.amount, &started.payment.currency)}." }
+                        div { dangerous_inner_html: "{svg}" }
+                    } else {
+                        p { class: "small strong", "Continue to {started.payment.provider} to pay {minor_amount(started.payment.amount, &started.payment.currency)}." }
+                        a { class: "button button-secondary", href: "{started.url}", target: "_blank", rel: "noopener", "Open payment page" }
+                    }
+                    span { class: "small muted", "Refresh the history once the payment is complete." }
+                }
+            }
+
+            if let Some(message) = payments_error {
+                code { class: "terminal", "{message}" }
+            } else if payments.is_empty() {
+                p { class: "small muted", "No online payments yet." }
+            } else {
+                div { class: "table-wrap",
+                    table { class: "data-table",
+                        thead {
+                            tr {
+                                th { "Created" }
+                                th { "Provider" }
+                                th { class: "right", "Amount" }
+                                th { class: "right", "Refunded" }
+                                th { "Status" }
+                            }
+                        }
+                        tbody {
+                            for payment in payments {
+                                tr { key: "{payment.id}",
+                                    td { class: "small", {utc_time(payment.created_at)} }
+                                    td { "{payment.provider}" }
+                                    td { class: "right tabular", {minor_amount(payment.amount, &payment.currency)} }
+                                    td { class: "right tabular",
+                                        if payment.refunded_amount > 0 { {minor_amount(payment.refunded_amount, &payment.currency)} } else { "—" }
+                                    }
+                                    td { span { class: "badge", "{payment.status.to_uppercase()}" } }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -2,6 +2,7 @@ use dioxus::prelude::*;
 
 use crate::{
     app::Route,
+    backend::use_auth,
     components::Icon,
     functional_api::{
         circuit_breaker_status, emergency_circuit_break, risk_events, save_security_filters,
@@ -27,6 +28,9 @@ fn event_tone(event_type: &str) -> &'static str {
 
 #[component]
 pub fn Guardrails() -> Element {
+    let auth = use_auth();
+    let can_save_policy = auth.can("security:write");
+    let can_trip = auth.can("security:break");
     let mut summary_resource = use_resource(move || async move { security_summary().await });
     let mut filters_resource = use_resource(move || async move { security_filters().await });
     let mut events_resource = use_resource(move || async move { risk_events().await });
@@ -36,6 +40,7 @@ pub fn Guardrails() -> Element {
     let mut synced = use_signal(|| false);
     let mut new_rule = use_signal(String::new);
     let mut reason = use_signal(String::new);
+    let mut totp_code = use_signal(String::new);
     let mut confirm_trip = use_signal(|| false);
     let mut busy = use_signal(|| false);
     let mut notice = use_signal(String::new);
@@ -340,7 +345,7 @@ pub fn Guardrails() -> Element {
 
                         button {
                             class: "button button-primary",
-                            disabled: busy() || !filters_ready || !policy_dirty,
+                            disabled: busy() || !can_save_policy || !filters_ready || !policy_dirty,
                             onclick: move |_| {
                                 let payload = filter_state();
                                 busy.set(true);
@@ -492,6 +497,18 @@ pub fn Guardrails() -> Element {
                         oninput: move |event| reason.set(event.value()),
                     }
                 }
+                div { class: "field",
+                    label { "Authenticator code" }
+                    input {
+                        class: "input",
+                        r#type: "text",
+                        autocomplete: "one-time-code",
+                        value: "{totp_code}",
+                        disabled: busy(),
+                        placeholder: "Required when two-factor authentication is enabled",
+                        oninput: move |event| totp_code.set(event.value()),
+                    }
+                }
                 label { class: "row gap-2 small", style: "align-items:flex-start",
                     input {
                         r#type: "checkbox",
@@ -504,16 +521,18 @@ pub fn Guardrails() -> Element {
                 div { class: "row",
                     button {
                         class: "button button-danger",
-                        disabled: busy() || reason().trim().is_empty() || !confirm_trip(),
+                        disabled: busy() || !can_trip || reason().trim().is_empty() || !confirm_trip(),
                         onclick: move |_| {
                             let incident_reason = reason().trim().to_string();
+                            let code = totp_code().trim().to_string();
                             busy.set(true);
                             error.set(String::new());
                             spawn(async move {
-                                match emergency_circuit_break(&incident_reason).await {
+                                match emergency_circuit_break(&incident_reason, &code).await {
                                     Ok(_) => {
                                         notice.set("Emergency traffic stop accepted by the router.".to_string());
                                         reason.set(String::new());
+                                        totp_code.set(String::new());
                                         confirm_trip.set(false);
                                         breaker_resource.restart();
                                     }
@@ -3,7 +3,7 @@ use std::collections::BTreeSet;
 use dioxus::prelude::*;
 
 use crate::{
-    backend::{Channel, ChannelService},
+    backend::{use_auth, Channel, ChannelService},
     components::Icon,
     functional_api::update_channel_preserving_reservations,
 };
@@ -61,6 +61,7 @@ fn model_list(channel: &Channel) -> Vec<String> {
 
 #[component]
 pub fn Providers() -> Element {
+    let can_write = use_auth().can("channels:write");
     let mut resource = use_resource(move || async move { ChannelService::list(100).await });
     let mut editing = use_signal(|| None::<Channel>);
     let mut pending_delete = use_signal(|| None::<Channel>);
@@ -138,25 +139,27 @@ pub fn Providers() -> Element {
                         onclick: move |_| resource.restart(),
                         if is_loading { "Refreshing…" } else { "Refresh" }
                     }
-                    button {
-                        class: "button button-primary",
-                        onclick: move |_| {
-                            editing.set(Some(Channel::default()));
-                            name.set(String::new());
-                            provider_type.set(1);
-                            credential.set(String::new());
-                            base_url.set(String::new());
-                            models.set(String::new());
-                            group.set("default".to_string());
-                            weight.set(100);
-                            priority.set(0);
-                            rpm_cap.set(String::new());
-                            tpm_cap.set(String::new());
-                            notice.set(String::new());
-                            error.set(String::new());
-                        },
-                        Icon { name: "plus" }
-                        "Add Provider"
+                    if can_write {
+                        button {
+                            class: "button button-primary",
+                            onclick: move |_| {
+                                editing.set(Some(Channel::default()));
+                                name.set(String::new());
+                                provider_type.set(1);
+                                credential.set(String::new());
+                                base_url.set(String::new());
+                                models.set(String::new());
+                                group.set("default".to_string());
+                                weight.set(100);
+                                priority.set(0);
+                                rpm_cap.set(String::new());
+                                tpm_cap.set(String::new());
+                                notice.set(String::new());
+                                error.set(String::new());
+                            },
+                            Icon { name: "plus" }
+                            "Add Provider"
+                        }
                     }
                 }
             }
@@ -216,25 +219,27 @@ pub fn Providers() -> Element {
                         div { class: "product-empty-icon", Icon { name: "providers" } }
                         h3 { "Connect your first upstream provider" }
                         p { "A provider supplies the model endpoint and credentials BurnCloud needs before Models, Routes, Playground, and real traffic can work." }
-                        button {
-                            class: "button button-primary",
-                            onclick: move |_| {
-                                editing.set(Some(Channel::default()));
-                                name.set(String::new());
-                                provider_type.set(1);
-                                credential.set(String::new());
-                                base_url.set(String::new());
-                                models.set(String::new());
-                                group.set("default".to_string());
-                                weight.set(100);
-                                priority.set(0);
-                                rpm_cap.set(String::new());
-                                tpm_cap.set(String::new());
-                                notice.set(String::new());
-                                error.set(String::new());
-                            },
-                            Icon { name: "plus" }
-                            "Add first provider"
+                        if can_write {
+                            button {
+                                class: "button button-primary",
+                                onclick: move |_| {
+                                    editing.set(Some(Channel::default()));
+                                    name.set(String::new());
+                                    provider_type.set(1);
+                                    credential.set(String::new());
+                                    base_url.set(String::new());
+                                    models.set(String::new());
+                                    group.set("default".to_string());
+                                    weight.set(100);
+                                    priority.set(0);
+                                    rpm_cap.set(String::new());
+                                    tpm_cap.set(String::new());
+                                    notice.set(String::new());
+                                    error.set(String::new());
+                                },
+                                Icon { name: "plus" }
+                                "Add first provider"
+                            }
                         }
                     }
                 }
@@ -312,33 +317,35 @@ pub fn Providers() -> Element {
                                                 }
                                                 td { class: "small muted mono provider-capacity", "{capacity}" }
                                                 td { class: "right",
-                                                    div { class: "action-menu",
-                                                        button {
-                                                            class: "button button-ghost button-sm",
-                                                            title: if channel.status == 1 { "Edit provider" } else { "Inactive providers are protected from edits because the current server update would reactivate them" },
-                                                            disabled: channel.status != 1,
-                                                            onclick: move |_| {
-                                                                name.set(edit_channel.name.clone());
-                                                                provider_type.set(edit_channel.type_);
-                                                                credential.set(String::new());
-                                                                base_url.set(edit_channel.base_url.clone().unwrap_or_default());
-                                                                models.set(edit_channel.models.clone());
-                                                                group.set(edit_channel.group.clone());
-                                                                weight.set(edit_channel.weight);
-                                                                priority.set(edit_channel.priority);
-                                                                rpm_cap.set(edit_channel.rpm_cap.map(|value| value.to_string()).unwrap_or_default());
-                                                                tpm_cap.set(edit_channel.tpm_cap.map(|value| value.to_string()).unwrap_or_default());
-                                                                notice.set(String::new());
-                                                                error.set(String::new());
-                                                                editing.set(Some(edit_channel.clone()));
-                                                            },
-                                                            "Edit"
-                                                        }
-                                                        button {
-                                                            class: "button button-ghost button-sm danger",
-                                                            disabled: busy(),
-                                                            onclick: move |_| pending_delete.set(Some(delete_channel.clone())),
-                                                            "Delete"
+                                                    if can_write {
+                                                        div { class: "action-menu",
+                                                            button {
+                                                                class: "button button-ghost button-sm",
+                                                                title: if channel.status == 1 { "Edit provider" } else { "Inactive providers are protected from edits because the current server update would reactivate them" },
+                                                                disabled: channel.status != 1,
+                                                                onclick: move |_| {
+                                                                    name.set(edit_channel.name.clone());
+                                                                    provider_type.set(edit_channel.type_);
+                                                                    credential.set(String::new());
+                                                                    base_url.set(edit_channel.base_url.clone().unwrap_or_default());
+                                                                    models.set(edit_channel.models.clone());
+                                                                    group.set(edit_channel.group.clone());
+                                                                    weight.set(edit_channel.weight);
+                                                                    priority.set(edit_channel.priority);
+                                                                    rpm_cap.set(edit_channel.rpm_cap.map(|value| value.to_string()).unwrap_or_default());
+                                                                    tpm_cap.set(edit_channel.tpm_cap.map(|value| value.to_string()).unwrap_or_default());
+                                                                    notice.set(String::new());
+                                                                    error.set(String::new());
+                                                                    editing.set(Some(edit_channel.clone()));
+                                                                },
+                                                                "Edit"
+                                                            }
+                                                            button {
+                                                                class: "button button-ghost button-sm danger",
+                                                                disabled: busy(),
+                                                                onclick: move |_| pending_delete.set(Some(delete_channel.clone())),
+                                                                "Delete"
+                                                            }
                                                         }
                                                     }
                                                 }
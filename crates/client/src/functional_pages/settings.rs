@@ -1,11 +1,18 @@
 use dioxus::prelude::*;
 
+use super::analytics::{minor_amount, parse_amount};
 use crate::{
-    backend::{server_root, system_metrics, use_auth, SystemMetrics},
+    backend::{
+        server_root, system_metrics, use_auth, AutoTopup, ManagementKeyService, NotificationService, PaymentService,
+        SavedMethod, SystemMetrics, TokenService, TotpSetup, TwoFactorService,
+    },
     components::Icon,
-    functional_api::{cache_stats, clear_cache},
+    functional_api::{audit_log, cache_stats, clear_cache, export_audit_csv, AuditEntry, AuditQuery},
 };
 
+/// Entries per page of the audit table.
+const AUDIT_PAGE: i64 = 25;
+
 #[component]
 pub fn Settings() -> Element {
     let auth = use_auth();
@@ -29,6 +36,9 @@ pub fn Settings() -> Element {
     };
 
     let roles = user.as_ref().map(|user| user.roles.join(", ")).unwrap_or_else(|| "-".to_string());
+    let can_read_audit = auth.can("audit:read");
+    let can_read_cache = auth.can("monitor:read");
+    let can_clear_cache = auth.can("cache:write");
     let username = user.as_ref().map(|user| user.username.clone()).unwrap_or_else(|| "-".to_string());
     let user_id = user.as_ref().map(|user| user.id.clone()).unwrap_or_else(|| "-".to_string());
     let memory_used_gib = metrics.memory.used as f64 / 1024.0 / 1024.0 / 1024.0;
@@ -100,61 +110,929 @@ pub fn Settings() -> Element {
                 }
             }
 
-            div { class: "card card-pad stack-lg",
-                div { class: "product-section-head",
-                    div {
-                        h3 { "Application cache" }
-                        p { "Cache is an operational implementation detail, so raw server statistics are available on demand instead of dominating the page." }
+            TwoFactorCard {}
+
+            ManagementKeysCard {}
+
+            NotificationsCard {}
+
+            if can_read_audit { AuditLogCard {} }
+
+            if can_read_cache {
+                div { class: "card card-pad stack-lg",
+                    div { class: "product-section-head",
+                        div {
+                            h3 { "Application cache" }
+                            p { "Cache is an operational implementation detail, so raw server statistics are available on demand instead of dominating the page." }
+                        }
+                        button { class: "button button-ghost button-sm", onclick: move |_| cache_resource.restart(), "Refresh cache" }
+                    }
+                    details {
+                        summary { class: "small strong", style: "cursor:pointer", "View raw cache statistics" }
+                        pre { class: "terminal", style: "margin-top:12px;white-space:pre-wrap;max-height:300px;overflow:auto", "{cache_text}" }
+                    }
+                }
+            }
+
+            if can_clear_cache {
+                div { class: "card card-pad stack-lg danger-zone",
+                    div { class: "product-section-head",
+                        div {
+                            h3 { class: "danger", "Cache maintenance" }
+                            p { "Clear application cache only when you are troubleshooting stale runtime state or following an operational procedure." }
+                        }
+                        span { class: "badge badge-error", "MAINTENANCE" }
+                    }
+                    p { class: "small muted", "Clearing cache does not delete customers, providers, API keys, or router logs, but it can temporarily change runtime behavior while caches warm again." }
+                    label { class: "row gap-2 small", style: "align-items:flex-start",
+                        input { r#type: "checkbox", checked: confirm_clear(), onchange: move |_| confirm_clear.set(!confirm_clear()) }
+                        span { "I understand this is a live maintenance operation on the connected BurnCloud server." }
+                    }
+                    button {
+                        class: "button button-primary",
+                        disabled: busy() || !confirm_clear(),
+                        onclick: move |_| {
+                            busy.set(true);
+                            error.set(String::new());
+                            notice.set("Clearing BurnCloud application cache…".to_string());
+                            spawn(async move {
+                                match clear_cache().await {
+                                    Ok(()) => {
+                                        notice.set("BurnCloud application cache cleared.".to_string());
+                                        confirm_clear.set(false);
+                                        cache_resource.restart();
+                                    }
+                                    Err(message) => {
+                                        notice.set(String::new());
+                                        error.set(format!("Cache clear failed: {message}"));
+                                    }
+                                }
+                                busy.set(false);
+                            });
+                        },
+                        if busy() { "Clearing…" } else { "Clear Application Cache" }
+                    }
+                }
+            }
+
+            div { class: "product-note",
+                "General appearance preferences and arbitrary gateway defaults are intentionally absent because the current BurnCloud server does not expose a general settings CRUD API. The console only presents settings it can actually read or change."
+            }
+        }
+    }
+}
+
+/// TOTP enrollment for the signed-in account: setup shows a QR code, the
+/// first code enables it and the recovery codes are shown once.
+#[component]
+fn TwoFactorCard() -> Element {
+    let mut status_resource = use_resource(move || async move { TwoFactorService::status().await });
+    let mut setup = use_signal(|| None::<TotpSetup>);
+    let mut code = use_signal(String::new);
+    let mut recovery_codes = use_signal(Vec::<String>::new);
+    let mut busy = use_signal(|| false);
+    let mut notice = use_signal(String::new);
+    let mut error = use_signal(String::new);
+
+    let status_result = status_resource.read().clone();
+    let status = status_result.clone().and_then(Result::ok).unwrap_or_default();
+    let status_error = status_result.as_ref().and_then(|result| result.as_ref().err().cloned());
+    let badge = if status.enabled {
+        ("badge badge-success", "ENABLED")
+    } else if status.required {
+        ("badge badge-error", "REQUIRED")
+    } else {
+        ("badge", "OFF")
+    };
+    let codes_text = recovery_codes().join("\n");
+
+    rsx! {
+        div { class: "card card-pad stack-lg",
+            div { class: "product-section-head",
+                div {
+                    h3 { "Two-factor authentication" }
+                    p { "Require a code from an authenticator app at sign-in and before sensitive actions such as the emergency circuit break." }
+                }
+                span { class: badge.0, "{badge.1}" }
+            }
+            if let Some(message) = status_error {
+                code { class: "terminal", "{message}" }
+            }
+            if status.required && !status.enabled {
+                div { class: "terminal auth-status auth-status-error", "An administrator requires two-factor authentication for this account. Other console pages stay locked until it is set up." }
+            }
+            if !notice().is_empty() { div { class: "terminal auth-status", "{notice}" } }
+            if !error().is_empty() { div { class: "terminal auth-status auth-status-error", "{error}" } }
+
+            if let Some(pending) = setup() {
+                div { class: "grid-2",
+                    div { dangerous_inner_html: "{pending.qr_svg}" }
+                    div { class: "stack",
+                        p { class: "small muted", "Scan the QR code with your authenticator app, or enter the secret manually, then confirm with the current code." }
+                        div { class: "receipt-row", label { "Secret" } strong { class: "mono", "{pending.secret}" } }
+                    }
+                }
+            }
+            if !recovery_codes().is_empty() {
+                div { class: "stack",
+                    p { class: "small strong", "Recovery codes — store them somewhere safe. Each works once and they will not be shown again." }
+                    pre { class: "terminal", style: "white-space:pre-wrap", "{codes_text}" }
+                }
+            }
+            if status.enabled {
+                div { class: "receipt-row", label { "Recovery codes left" } strong { "{status.recovery_codes_remaining}" } }
+            }
+
+            if status.enabled || setup().is_some() {
+                div { class: "field",
+                    label { if status.enabled { "Authenticator or recovery code" } else { "Code from your authenticator app" } }
+                    input {
+                        class: "input",
+                        r#type: "text",
+                        autocomplete: "one-time-code",
+                        value: "{code}",
+                        placeholder: "123456",
+                        disabled: busy(),
+                        oninput: move |event| code.set(event.value()),
+                    }
+                }
+            }
+
+            div { class: "row gap-2",
+                if !status.enabled && setup().is_none() {
+                    button {
+                        class: "button button-primary",
+                        disabled: busy(),
+                        onclick: move |_| {
+                            busy.set(true);
+                            error.set(String::new());
+                            notice.set(String::new());
+                            recovery_codes.set(Vec::new());
+                            spawn(async move {
+                                match TwoFactorService::setup().await {
+                                    Ok(value) => setup.set(Some(value)),
+                                    Err(message) => error.set(format!("Setup failed: {message}")),
+                                }
+                                busy.set(false);
+                            });
+                        },
+                        "Set Up Two-Factor Authentication"
+                    }
+                }
+                if setup().is_some() {
+                    button {
+                        class: "button button-primary",
+                        disabled: busy() || code().trim().is_empty(),
+                        onclick: move |_| {
+                            let value = code().trim().to_string();
+                            busy.set(true);
+                            error.set(String::new());
+                            spawn(async move {
+                                match TwoFactorService::enable(&value).await {
+                                    Ok(codes) => {
+                                        setup.set(None);
+                                        code.set(String::new());
+                                        recovery_codes.set(codes);
+                                        notice.set("Two-factor authentication enabled.".to_string());
+                                        status_resource.restart();
+                                    }
+                                    Err(message) => error.set(format!("Enable failed: {message}")),
+                                }
+                                busy.set(false);
+                            });
+                        },
+                        "Enable"
+                    }
+                }
+                if status.enabled {
+                    button {
+                        class: "button button-secondary",
+                        disabled: busy() || code().trim().is_empty(),
+                        onclick: move |_| {
+                            let value = code().trim().to_string();
+                            busy.set(true);
+                            error.set(String::new());
+                            spawn(async move {
+                                match TwoFactorService::regenerate_recovery_codes(&value).await {
+                                    Ok(codes) => {
+                                        code.set(String::new());
+                                        recovery_codes.set(codes);
+                                        notice.set("New recovery codes issued; the old ones no longer work.".to_string());
+                                        status_resource.restart();
+                                    }
+                                    Err(message) => error.set(format!("Could not issue recovery codes: {message}")),
+                                }
+                                busy.set(false);
+                            });
+                        },
+                        "New Recovery Codes"
+                    }
+                    if !status.required {
+                        button {
+                            class: "button button-ghost",
+                            disabled: busy() || code().trim().is_empty(),
+                            onclick: move |_| {
+                                let value = code().trim().to_string();
+                                busy.set(true);
+                                error.set(String::new());
+                                spawn(async move {
+                                    match TwoFactorService::disable(&value).await {
+                                        Ok(()) => {
+                                            code.set(String::new());
+                                            recovery_codes.set(Vec::new());
+                                            notice.set("Two-factor authentication disabled.".to_string());
+                                            status_resource.restart();
+                                        }
+                                        Err(message) => error.set(format!("Disable failed: {message}")),
+                                    }
+                                    busy.set(false);
+                                });
+                            },
+                            "Disable"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Comma- or whitespace-separated entries of a text field.
+fn split_entries(text: &str) -> Vec<String> {
+    text.split(|c: char| c == ',' || c.is_whitespace()).filter(|v| !v.is_empty()).map(str::to_string).collect()
+}
+
+/// Long-lived keys for scripts against the management API. A key is shown
+/// once on creation and holds at most the scopes chosen here.
+#[component]
+fn ManagementKeysCard() -> Element {
+    let auth = use_auth();
+    let held = auth.user().map(|user| user.permissions.join(", ")).unwrap_or_default();
+    let mut keys_resource = use_resource(move || async move { ManagementKeyService::list().await });
+    let mut name = use_signal(String::new);
+    let mut scopes = use_signal(String::new);
+    let mut allowed_ips = use_signal(String::new);
+    let mut expires_days = use_signal(String::new);
+    let mut secret = use_signal(String::new);
+    let mut busy = use_signal(|| false);
+    let mut notice = use_signal(String::new);
+    let mut error = use_signal(String::new);
+
+    let keys_result = keys_resource.read().clone();
+    let keys = keys_result.clone().and_then(Result::ok).unwrap_or_default();
+    let keys_error = keys_result.as_ref().and_then(|result| result.as_ref().err().cloned());
+
+    rsx! {
+        div { class: "card card-pad stack-lg",
+            div { class: "product-section-head",
+                div {
+                    h3 { "Management API keys" }
+                    p { "Let scripts call the management API as you, limited to the permissions you pick. Every request made with a key is recorded in the audit log." }
+                }
+                button { class: "button button-ghost button-sm", onclick: move |_| keys_resource.restart(), "Refresh" }
+            }
+            if let Some(message) = keys_error {
+                code { class: "terminal", "{message}" }
+            }
+            if !notice().is_empty() { div { class: "terminal auth-status", "{notice}" } }
+            if !error().is_empty() { div { class: "terminal auth-status auth-status-error", "{error}" } }
+            if !secret().is_empty() {
+                div { class: "stack",
+                    p { class: "small strong", "Copy the key now — it will not be shown again." }
+                    pre { class: "terminal", style: "white-space:pre-wrap", "{secret}" }
+                }
+            }
+
+            div { class: "grid-2",
+                div { class: "field",
+                    label { "Name" }
+                    input { class: "input", value: "{name}", placeholder: "provisioning", disabled: busy(), oninput: move |event| name.set(event.value()) }
+                }
+                div { class: "field",
+                    label { "Scopes" }
+                    input { class: "input mono", value: "{scopes}", placeholder: "channels:read, logs:read", disabled: busy(), oninput: move |event| scopes.set(event.value()) }
+                    span { class: "small muted", "You hold: {held}" }
+                }
+                div { class: "field",
+                    label { "Allowed addresses (optional)" }
+                    input { class: "input mono", value: "{allowed_ips}", placeholder: "10.0.0.0/8, 203.0.113.7", disabled: busy(), oninput: move |event| allowed_ips.set(event.value()) }
+                }
+                div { class: "field",
+                    label { "Expires after days (optional)" }
+                    input { class: "input", r#type: "number", min: "1", value: "{expires_days}", placeholder: "Never", disabled: busy(), oninput: move |event| expires_days.set(event.value()) }
+                }
+            }
+            div { class: "row",
+                button {
+                    class: "button button-primary",
+                    disabled: busy() || name().trim().is_empty() || scopes().trim().is_empty(),
+                    onclick: move |_| {
+                        let key_name = name().trim().to_string();
+                        let key_scopes = split_entries(&scopes());
+                        let key_ips = split_entries(&allowed_ips());
+                        let expires_in_days = match expires_days().trim() {
+                            "" => None,
+                            days => match days.parse::<i64>() {
+                                Ok(days) if days > 0 => Some(days),
+                                _ => {
+                                    error.set("Expiry must be a positive number of days.".to_string());
+                                    return;
+                                }
+                            },
+                        };
+                        busy.set(true);
+                        error.set(String::new());
+                        notice.set(String::new());
+                        spawn(async move {
+                            match ManagementKeyService::create(&key_name, &key_scopes, &key_ips, expires_in_days).await {
+                                Ok(created) => {
+                                    secret.set(created.secret);
+                                    name.set(String::new());
+                                    scopes.set(String::new());
+                                    allowed_ips.set(String::new());
+                                    expires_days.set(String::new());
+                                    keys_resource.restart();
+                                }
+                                Err(message) => error.set(format!("Could not create key: {message}")),
+                            }
+                            busy.set(false);
+                        });
+                    },
+                    if busy() { "Creating…" } else { "Create Key" }
+                }
+            }
+
+            if keys.is_empty() {
+                p { class: "small muted", "No management keys yet." }
+            } else {
+                div { class: "table-wrap",
+                    table { class: "data-table",
+                        thead {
+                            tr {
+                                th { "Name" }
+                                th { "Key" }
+                                th { "Scopes" }
+                                th { "Addresses" }
+                                th { "Last used" }
+                                th { "Expires" }
+                                th { class: "right", "" }
+                            }
+                        }
+                        tbody {
+                            for key in keys {
+                                tr { key: "{key.id}",
+                                    td { class: "strong", "{key.name}" }
+                                    td { class: "mono small", "{key.prefix}…" }
+                                    td { class: "mono small", "{key.scopes.join(\", \")}" }
+                                    td { class: "mono small",
+                                        if key.allowed_ips.is_empty() { "any" } else { "{key.allowed_ips.join(\", \")}" }
+                                    }
+                                    td { class: "small",
+                                        match key.last_used_at {
+                                            Some(at) => format!("{} {}", utc_time(at), key.last_used_ip.clone().unwrap_or_default()),
+                                            None => "never".to_string(),
+                                        }
+                                    }
+                                    td { class: "small", {key.expires_at.map(utc_time).unwrap_or_else(|| "never".to_string())} }
+                                    td { class: "right",
+                                        if key.active {
+                                            button {
+                                                class: "button button-ghost button-sm danger",
+                                                disabled: busy(),
+                                                onclick: {
+                                                    let id = key.id.clone();
+                                                    move |_| {
+                                                        let id = id.clone();
+                                                        busy.set(true);
+                                                        error.set(String::new());
+                                                        spawn(async move {
+                                                            match ManagementKeyService::revoke(&id).await {
+                                                                Ok(()) => {
+                                                                    notice.set("Key revoked.".to_string());
+                                                                    keys_resource.restart();
+                                                                }
+                                                                Err(message) => error.set(format!("Could not revoke key: {message}")),
+                                                            }
+                                                            busy.set(false);
+                                                        });
+                                                    }
+                                                },
+                                                "Revoke"
+                                            }
+                                        } else {
+                                            span { class: "badge", "INACTIVE" }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Nanodollars per cent, for absolute thresholds entered in dollars.
+const NANO_PER_CENT: i64 = 10_000_000;
+
+fn saved_method_label(method: &SavedMethod) -> String {
+    format!("{} •••• {} ({:02}/{})", method.brand, method.last4, method.exp_month, method.exp_year)
+}
+
+/// Low-balance and key quota alerts of the signed-in user: email, a signed
+/// webhook, an optional auto top-up and the thresholds that trigger them.
+#[component]
+fn NotificationsCard() -> Element {
+    let auth = use_auth();
+    let user_id = auth.user().map(|user| user.id).unwrap_or_default();
+    let mut notifications_resource = use_resource(move || async move { NotificationService::get().await });
+    let options_resource = use_resource(PaymentService::options);
+    let keys_resource = use_resource(TokenService::list);
+    let mut topup_provider = use_signal(String::new);
+    let methods_resource = use_resource(move || async move {
+        let provider = topup_provider();
+        if provider.is_empty() {
+            Ok(Vec::new())
+        } else {
+            PaymentService::saved_methods(&provider).await
+        }
+    });
+    let mut email_enabled = use_signal(|| None::<bool>);
+    let mut webhook_url = use_signal(|| None::<String>);
+    let mut topup_method = use_signal(String::new);
+    let mut topup_amount = use_signal(String::new);
+    let mut topup_currency = use_signal(String::new);
+    let mut target = use_signal(|| "wallet:USD".to_string());
+    let mut kind = use_signal(|| "absolute".to_string());
+    let mut value = use_signal(String::new);
+    let mut busy = use_signal(|| false);
+    let mut notice = use_signal(String::new);
+    let mut error = use_signal(String::new);
+
+    let notifications_result = notifications_resource.read().clone();
+    let notifications = notifications_result.clone().and_then(Result::ok).unwrap_or_default();
+    let notifications_error = notifications_result.as_ref().and_then(|result| result.as_ref().err().cloned());
+    let settings = notifications.settings.clone();
+    let options = options_resource.read().clone().and_then(Result::ok).unwrap_or_default();
+    let keys: Vec<_> = keys_resource
+        .read()
+        .clone()
+        .and_then(Result::ok)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|key| key.user_id == user_id)
+        .collect();
+    let methods_result = methods_resource.read().clone();
+    let methods_error = methods_result.as_ref().and_then(|result| result.as_ref().err().cloned());
+    let methods = methods_result.and_then(Result::ok).unwrap_or_default();
+    let email_checked = email_enabled().unwrap_or(settings.email_enabled != 0);
+    let webhook_text = webhook_url().unwrap_or_else(|| settings.webhook_url.clone().unwrap_or_default());
+    let secret = settings.webhook_secret.clone().unwrap_or_default();
+    let current_topup = settings
+        .auto_topup()
+        .map(|topup| format!("{} via {}", minor_amount(topup.amount, &topup.currency), topup.provider))
+        .unwrap_or_else(|| "off".to_string());
+    let value_hint = if kind() == "percent" { "Percent of the key quota left, 1-99" } else { "Amount left, e.g. 5.00" };
+
+    let submitted_url = webhook_text.clone();
+    let save = move |rotate_secret: bool| {
+        let email = email_checked;
+        let url = submitted_url.clone();
+        let auto_topup = if topup_method().is_empty() {
+            None
+        } else {
+            let Some(amount) = parse_amount(&topup_amount()) else {
+                error.set("Enter the auto top-up amount like 20.00.".to_string());
+                return;
+            };
+            Some(AutoTopup {
+                provider: topup_provider(),
+                method: topup_method(),
+                amount,
+                currency: topup_currency(),
+            })
+        };
+        busy.set(true);
+        error.set(String::new());
+        notice.set(String::new());
+        spawn(async move {
+            match NotificationService::save(email, &url, rotate_secret, auto_topup.as_ref()).await {
+                Ok(_) => {
+                    notice.set("Notification settings saved.".to_string());
+                    email_enabled.set(None);
+                    webhook_url.set(None);
+                    notifications_resource.restart();
+                }
+                Err(message) => error.set(format!("Could not save settings: {message}")),
+            }
+            busy.set(false);
+        });
+    };
+    let mut save_plain = save.clone();
+    let mut save_rotated = save;
+
+    rsx! {
+        div { class: "card card-pad stack-lg",
+            div { class: "product-section-head",
+                div {
+                    h3 { "Balance notifications" }
+                    p { "Hear about a low wallet balance or an API key running out of quota before requests start failing. Each threshold notifies once when it is crossed and again only after the balance recovers." }
+                }
+                button { class: "button button-ghost button-sm", onclick: move |_| notifications_resource.restart(), "Refresh" }
+            }
+            if let Some(message) = notifications_error {
+                code { class: "terminal", "{message}" }
+            }
+            if !notice().is_empty() { div { class: "terminal auth-status", "{notice}" } }
+            if !error().is_empty() { div { class: "terminal auth-status auth-status-error", "{error}" } }
+
+            label { class: "row gap-2 small",
+                input { r#type: "checkbox", checked: email_checked, disabled: busy(), onchange: move |_| email_enabled.set(Some(!email_checked)) }
+                span { "Email me when a threshold is crossed" }
+            }
+            div { class: "field",
+                label { "Webhook URL (optional)" }
+                input { class: "input mono", value: "{webhook_text}", placeholder: "https://example.com/burncloud-alerts", disabled: busy(), oninput: move |event| webhook_url.set(Some(event.value())) }
+                if !secret.is_empty() {
+                    span { class: "small muted", "Signed with X-BurnCloud-Signature using " code { class: "mono", "{secret}" } }
+                }
+            }
+            if options.providers.iter().any(|provider| provider.saved_methods) {
+                div { class: "stack",
+                    p { class: "small", "Auto top-up: " strong { "{current_topup}" } }
+                    div { class: "grid-2",
+                        div { class: "field",
+                            label { "Provider" }
+                            select { class: "select", value: "{topup_provider}", disabled: busy(), onchange: move |event| {
+                                    topup_method.set(String::new());
+                                    topup_provider.set(event.value());
+                                },
+                                option { value: "", "Choose a provider" }
+                                for provider in options.providers.iter().filter(|provider| provider.saved_methods) {
+                                    option { key: "{provider.name}", value: "{provider.name}", "{provider.name}" }
+                                }
+                            }
+                        }
+                        div { class: "field",
+                            label { "Saved card" }
+                            select { class: "select", value: "{topup_method}", disabled: busy() || methods.is_empty(), onchange: move |event| topup_method.set(event.value()),
+                                option { value: "", "Off" }
+                                for method in methods.iter() {
+                                    option { key: "{method.id}", value: "{method.id}", {saved_method_label(method)} }
+                                }
+                            }
+                        }
+                        div { class: "field",
+                            label { "Amount" }
+                            input { class: "input", value: "{topup_amount}", placeholder: "20.00", disabled: busy(), oninput: move |event| topup_amount.set(event.value()) }
+                        }
+                        div { class: "field",
+                            label { "Currency" }
+                            select { class: "select", value: "{topup_currency}", disabled: busy(), onchange: move |event| topup_currency.set(event.value()),
+                                option { value: "", "Choose a currency" }
+                                option { value: "USD", "USD" }
+                                option { value: "CNY", "CNY" }
+                            }
+                        }
+                    }
+                    if let Some(message) = methods_error {
+                        code { class: "terminal", "{message}" }
+                    } else if !topup_provider().is_empty() && methods.is_empty() {
+                        span { class: "small muted", "No saved cards yet. Top up once with \"Save this card for auto top-up\" checked." }
                     }
-                    button { class: "button button-ghost button-sm", onclick: move |_| cache_resource.restart(), "Refresh cache" }
+                    span { class: "small muted", "Choose Off as the card to turn auto top-up off when saving." }
                 }
-                details {
-                    summary { class: "small strong", style: "cursor:pointer", "View raw cache statistics" }
-                    pre { class: "terminal", style: "margin-top:12px;white-space:pre-wrap;max-height:300px;overflow:auto", "{cache_text}" }
+            }
+            div { class: "row",
+                button { class: "button button-primary", disabled: busy(), onclick: move |_| save_plain(false), "Save" }
+                if !secret.is_empty() {
+                    button { class: "button button-secondary", disabled: busy(), onclick: move |_| save_rotated(true), "Save and rotate secret" }
                 }
             }
 
-            div { class: "card card-pad stack-lg danger-zone",
-                div { class: "product-section-head",
-                    div {
-                        h3 { class: "danger", "Cache maintenance" }
-                        p { "Clear application cache only when you are troubleshooting stale runtime state or following an operational procedure." }
+            div { class: "grid-2",
+                div { class: "field",
+                    label { "Watch" }
+                    select { class: "select", value: "{target}", disabled: busy(), onchange: move |event| {
+                            let watched = event.value();
+                            if watched.starts_with("wallet:") {
+                                kind.set("absolute".to_string());
+                            }
+                            target.set(watched);
+                        },
+                        option { value: "wallet:USD", "USD wallet" }
+                        option { value: "wallet:CNY", "CNY wallet" }
+                        for key in keys.iter() {
+                            option { key: "{key.token}", value: "{key.token}", "API key {key.token}" }
+                        }
                     }
-                    span { class: "badge badge-error", "MAINTENANCE" }
                 }
-                p { class: "small muted", "Clearing cache does not delete customers, providers, API keys, or router logs, but it can temporarily change runtime behavior while caches warm again." }
-                label { class: "row gap-2 small", style: "align-items:flex-start",
-                    input { r#type: "checkbox", checked: confirm_clear(), onchange: move |_| confirm_clear.set(!confirm_clear()) }
-                    span { "I understand this is a live maintenance operation on the connected BurnCloud server." }
+                div { class: "field",
+                    label { "Notify when" }
+                    select { class: "select", value: "{kind}", disabled: busy() || target().starts_with("wallet:"), onchange: move |event| kind.set(event.value()),
+                        option { value: "absolute", "Amount left is at most" }
+                        option { value: "percent", "Percent of quota left is at most" }
+                    }
+                }
+                div { class: "field",
+                    label { "Threshold" }
+                    input { class: "input", value: "{value}", placeholder: "{value_hint}", disabled: busy(), oninput: move |event| value.set(event.value()) }
                 }
+            }
+            div { class: "row",
                 button {
                     class: "button button-primary",
-                    disabled: busy() || !confirm_clear(),
+                    disabled: busy() || value().trim().is_empty(),
                     onclick: move |_| {
+                        let watched = target();
+                        let threshold_kind = kind();
+                        let parsed = if threshold_kind == "percent" {
+                            value().trim().parse::<i64>().ok()
+                        } else {
+                            parse_amount(&value()).and_then(|cents| cents.checked_mul(NANO_PER_CENT))
+                        };
+                        let Some(threshold_value) = parsed else {
+                            error.set(format!("Threshold: {value_hint}."));
+                            return;
+                        };
                         busy.set(true);
                         error.set(String::new());
-                        notice.set("Clearing BurnCloud application cache…".to_string());
+                        notice.set(String::new());
                         spawn(async move {
-                            match clear_cache().await {
-                                Ok(()) => {
-                                    notice.set("BurnCloud application cache cleared.".to_string());
-                                    confirm_clear.set(false);
-                                    cache_resource.restart();
+                            let result = match watched.strip_prefix("wallet:") {
+                                Some(code) => NotificationService::add_threshold(None, Some(code), &threshold_kind, threshold_value).await,
+                                None => NotificationService::add_threshold(Some(&watched), None, &threshold_kind, threshold_value).await,
+                            };
+                            match result {
+                                Ok(_) => {
+                                    value.set(String::new());
+                                    notifications_resource.restart();
                                 }
-                                Err(message) => {
-                                    notice.set(String::new());
-                                    error.set(format!("Cache clear failed: {message}"));
+                                Err(message) => error.set(format!("Could not add threshold: {message}")),
+                            }
+                            busy.set(false);
+                        });
+                    },
+                    "Add Threshold"
+                }
+            }
+
+            if notifications.thresholds.is_empty() {
+                p { class: "small muted", "No thresholds yet." }
+            } else {
+                div { class: "table-wrap",
+                    table { class: "data-table",
+                        thead {
+                            tr {
+                                th { "Watching" }
+                                th { "Notify at" }
+                                th { "State" }
+                                th { class: "right", "" }
+                            }
+                        }
+                        tbody {
+                            for threshold in notifications.thresholds {
+                                tr { key: "{threshold.id}",
+                                    td { class: "mono small",
+                                        match (&threshold.token_hint, &threshold.currency) {
+                                            (Some(hint), _) => format!("API key {hint}"),
+                                            (None, Some(code)) => format!("{code} wallet"),
+                                            (None, None) => "API key (deleted)".to_string(),
+                                        }
+                                    }
+                                    td { class: "small",
+                                        if threshold.kind == "percent" {
+                                            "{threshold.value}% of quota left"
+                                        } else {
+                                            {format!("{} left", minor_amount(threshold.value / NANO_PER_CENT, threshold.currency.as_deref().unwrap_or("USD")))}
+                                        }
+                                    }
+                                    td { class: "small",
+                                        match threshold.triggered_at {
+                                            Some(at) => format!("crossed {}", utc_time(at)),
+                                            None => "armed".to_string(),
+                                        }
+                                    }
+                                    td { class: "right",
+                                        button {
+                                            class: "button button-ghost button-sm danger",
+                                            disabled: busy(),
+                                            onclick: {
+                                                let id = threshold.id.clone();
+                                                move |_| {
+                                                    let id = id.clone();
+                                                    busy.set(true);
+                                                    error.set(String::new());
+                                                    spawn(async move {
+                                                        match NotificationService::delete_threshold(&id).await {
+                                                            Ok(()) => notifications_resource.restart(),
+                                                            Err(message) => error.set(format!("Could not delete threshold: {message}")),
+                                                        }
+                                                        busy.set(false);
+                                                    });
+                                                }
+                                            },
+                                            "Delete"
+                                        }
+                                    }
                                 }
                             }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// `YYYY-MM-DD HH:MM:SS` in UTC.
+pub(super) fn utc_time(timestamp: i64) -> String {
+    let days = timestamp.div_euclid(86_400);
+    let seconds = timestamp.rem_euclid(86_400);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}", seconds / 3_600, seconds % 3_600 / 60, seconds % 60)
+}
+
+/// `data:` URL that downloads `text` as a CSV file.
+fn csv_data_url(text: &str) -> String {
+    let mut url = String::from("data:text/csv;charset=utf-8,");
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+            url.push(byte as char);
+        } else {
+            url.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    url
+}
+
+fn audit_actor(entry: &AuditEntry) -> String {
+    entry.actor_name.clone().or_else(|| entry.actor_id.clone()).unwrap_or_else(|| "-".to_string())
+}
+
+fn audit_changed_fields(entry: &AuditEntry) -> String {
+    match entry.changes.as_ref() {
+        Some(fields) if !fields.is_empty() => fields.keys().cloned().collect::<Vec<_>>().join(", "),
+        _ => "-".to_string(),
+    }
+}
+
+/// Compliance view of the administrative audit trail: who changed what,
+/// from where, with filters and a CSV export of the matching entries.
+#[component]
+fn AuditLogCard() -> Element {
+    let mut draft = use_signal(AuditQuery::default);
+    let mut query = use_signal(AuditQuery::default);
+    let mut offset = use_signal(|| 0_i64);
+    let mut export_href = use_signal(String::new);
+    let mut busy = use_signal(|| false);
+    let mut error = use_signal(String::new);
+    let mut resource = use_resource(move || {
+        let query = query();
+        let offset = offset();
+        async move { audit_log(&query, AUDIT_PAGE, offset).await }
+    });
+
+    let result = resource.read().clone();
+    let load_error = result.as_ref().and_then(|result| result.as_ref().err().cloned());
+    let loading = result.is_none();
+    let page = result.and_then(Result::ok).unwrap_or_default();
+    let first = if page.total == 0 { 0 } else { offset() + 1 };
+    let last = (offset() + page.entries.len() as i64).min(page.total);
+    let has_next = offset() + AUDIT_PAGE < page.total;
+
+    rsx! {
+        div { class: "card card-pad stack-lg",
+            div { class: "product-section-head",
+                div {
+                    h3 { "Compliance: audit log" }
+                    p { "Every administrative change made in the console or with the burncloud CLI, with the actor, source address and the fields that changed. Secrets are never recorded." }
+                }
+                span { class: "badge", "APPEND-ONLY" }
+            }
+            if !error().is_empty() { div { class: "terminal auth-status auth-status-error", "{error}" } }
+
+            div { class: "grid-2",
+                div { class: "field",
+                    label { "Actor" }
+                    input { class: "input", value: "{draft().actor}", placeholder: "user id or name", oninput: move |event| draft.write().actor = event.value() }
+                }
+                div { class: "field",
+                    label { "Action" }
+                    input { class: "input", value: "{draft().action}", placeholder: "channel.update or token.", oninput: move |event| draft.write().action = event.value() }
+                }
+                div { class: "field",
+                    label { "Since" }
+                    input { class: "input", value: "{draft().since}", placeholder: "2026-01-01 or unix seconds", oninput: move |event| draft.write().since = event.value() }
+                }
+                div { class: "field",
+                    label { "Until" }
+                    input { class: "input", value: "{draft().until}", placeholder: "2026-02-01 or unix seconds", oninput: move |event| draft.write().until = event.value() }
+                }
+            }
+            div { class: "row gap-2",
+                button {
+                    class: "button button-secondary",
+                    onclick: move |_| {
+                        export_href.set(String::new());
+                        offset.set(0);
+                        query.set(draft());
+                        resource.restart();
+                    },
+                    "Apply Filters"
+                }
+                button {
+                    class: "button button-ghost",
+                    disabled: busy(),
+                    onclick: move |_| {
+                        let filters = query();
+                        busy.set(true);
+                        error.set(String::new());
+                        spawn(async move {
+                            match export_audit_csv(&filters).await {
+                                Ok(text) => export_href.set(csv_data_url(&text)),
+                                Err(message) => error.set(format!("Export failed: {message}")),
+                            }
                             busy.set(false);
                         });
                     },
-                    if busy() { "Clearing…" } else { "Clear Application Cache" }
+                    if busy() { "Exporting…" } else { "Export CSV" }
+                }
+                if !export_href().is_empty() {
+                    a { class: "button button-primary", href: "{export_href}", download: "audit.csv", "Download audit.csv" }
                 }
             }
 
-            div { class: "product-note",
-                "General appearance preferences, arbitrary gateway defaults, and notification settings are intentionally absent because the current BurnCloud server does not expose a general settings CRUD API. The console only presents settings it can actually read or change."
+            if loading {
+                div { class: "small muted", "Loading audit log…" }
+            } else if let Some(message) = load_error {
+                code { class: "terminal", "{message}" }
+            } else if page.entries.is_empty() {
+                div { class: "small muted", "No audit entries match these filters." }
+            } else {
+                div { class: "table-wrap",
+                    table { class: "data-table",
+                        thead { tr {
+                            th { "Time (UTC)" }
+                            th { "Actor" }
+                            th { "Source" }
+                            th { "IP" }
+                            th { "Action" }
+                            th { "Target" }
+                            th { "Changed" }
+                        } }
+                        tbody {
+                            for entry in page.entries {
+                                {
+                                    let time = utc_time(entry.created_at);
+                                    let actor = audit_actor(&entry);
+                                    let ip = entry.ip.clone().unwrap_or_else(|| "-".to_string());
+                                    let target = match &entry.target_id {
+                                        Some(id) => format!("{} {id}", entry.target_type),
+                                        None => entry.target_type.clone(),
+                                    };
+                                    let changed = audit_changed_fields(&entry);
+                                    rsx! {
+                                        tr { key: "{entry.id}",
+                                            td { class: "mono muted", "{time}" }
+                                            td { "{actor}" }
+                                            td { span { class: "badge badge-neutral", "{entry.source}" } }
+                                            td { class: "mono muted", "{ip}" }
+                                            td { class: "mono table-primary", "{entry.action}" }
+                                            td { class: "mono", "{target}" }
+                                            td { class: "small muted", "{changed}" }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                div { class: "row gap-2", style: "align-items:center",
+                    span { class: "small muted", "{first}–{last} of {page.total}" }
+                    button {
+                        class: "button button-ghost button-sm",
+                        disabled: offset() == 0,
+                        onclick: move |_| offset.set((offset() - AUDIT_PAGE).max(0)),
+                        "Previous"
+                    }
+                    button {
+                        class: "button button-ghost button-sm",
+                        disabled: !has_next,
+                        onclick: move |_| offset.set(offset() + AUDIT_PAGE),
+                        "Next"
+                    }
+                }
             }
         }
     }
@@ -6,9 +6,48 @@ pub const WS_PATH: &str = "/ws";
 /// Dev-only fallback when `JWT_SECRET` is unset. Must match token signing (`UserService`).
 pub const DEFAULT_JWT_SECRET: &str = "burncloud-default-secret-change-in-production";
 
+/// Shortest `JWT_SECRET` accepted in production (HS256 wants at least 256 bits).
+pub const MIN_JWT_SECRET_LEN: usize = 32;
+
 /// Resolve JWT signing/verification secret from the environment.
 pub fn jwt_secret() -> String {
-    std::env::var("JWT_SECRET").unwrap_or_else(|_| DEFAULT_JWT_SECRET.to_string())
+    std::env::var("JWT_SECRET")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| DEFAULT_JWT_SECRET.to_string())
+}
+
+/// `true` when `BURNCLOUD_ENV` is `production` (or `prod`).
+pub fn is_production() -> bool {
+    std::env::var("BURNCLOUD_ENV")
+        .map(|v| {
+            matches!(
+                v.trim().to_ascii_lowercase().as_str(),
+                "production" | "prod"
+            )
+        })
+        .unwrap_or(false)
+}
+
+/// Check the configured JWT secret. Outside production only a warning is
+/// returned for the default secret; in production an unset, default or short
+/// secret is an error and the server must not start.
+pub fn validate_jwt_secret() -> Result<Option<String>, String> {
+    let secret = std::env::var("JWT_SECRET").unwrap_or_default();
+    let problem = if secret.is_empty() || secret == DEFAULT_JWT_SECRET {
+        Some("JWT_SECRET is not set, console tokens are signed with the public default secret")
+    } else if secret.len() < MIN_JWT_SECRET_LEN {
+        Some("JWT_SECRET is shorter than 32 characters")
+    } else {
+        None
+    };
+    match problem {
+        Some(problem) if is_production() => Err(format!(
+            "{problem}. Refusing to start with BURNCLOUD_ENV=production"
+        )),
+        Some(problem) => Ok(Some(problem.to_string())),
+        None => Ok(None),
+    }
 }
 
 // Helper to get base URL
@@ -1,7 +1,9 @@
 pub mod constants;
 pub mod error;
+pub mod model_policy;
 pub mod price_u64;
 pub mod pricing_config;
+pub mod rate_limit;
 pub mod repository;
 pub mod types;
 
@@ -9,9 +11,11 @@ pub use repository::CrudRepository;
 
 pub use constants::*;
 pub use error::*;
+pub use model_policy::{ModelPolicy, ModelSpendCap};
 pub use price_u64::{
     calculate_cost_safe, dollars_to_nano, nano_to_dollars, rate_to_scaled, scaled_to_rate,
     NANO_PER_DOLLAR, RATE_SCALE,
 };
 pub use pricing_config::*;
+pub use rate_limit::RateLimits;
 pub use types::*;
@@ -0,0 +1,184 @@
+//! Per-token model restrictions and spend caps.
+//!
+//! A [`ModelPolicy`] is stored as JSON on `router_tokens.model_policy` and
+//! enforced by the router before channel selection:
+//!
+//! ```json
+//! {
+//!   "allow": ["gpt-4o*", "claude-*"],
+//!   "deny": ["*-preview"],
+//!   "caps": [{ "model": "gpt-4o*", "daily": 5000000000, "monthly": 100000000000 }]
+//! }
+//! ```
+//!
+//! Patterns are globs where `*` matches any run of characters and `?` matches
+//! a single character. Matching is ASCII case-insensitive. Cap amounts are
+//! nanodollars; days and months are UTC calendar periods.
+//!
+//! # Example
+//! ```
+//! use burncloud_common::model_policy::ModelPolicy;
+//!
+//! let policy = ModelPolicy::parse(r#"{"allow":["gpt-4o*"],"deny":["*-audio-*"]}"#).unwrap();
+//! assert!(policy.allows("gpt-4o-mini"));
+//! assert!(!policy.allows("gpt-4o-audio-preview"));
+//! assert!(!policy.allows("o1"));
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+/// Allow/deny lists and spend caps for a single token.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelPolicy {
+    /// Model globs the token may call. Empty means every model.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<String>,
+    /// Model globs the token may never call. Takes precedence over `allow`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<String>,
+    /// Spend caps; every cap whose glob matches the requested model applies.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub caps: Vec<ModelSpendCap>,
+}
+
+/// Spend cap over all models matching `model`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelSpendCap {
+    /// Model glob the cap applies to.
+    pub model: String,
+    /// Maximum spend per UTC day in nanodollars.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily: Option<i64>,
+    /// Maximum spend per UTC calendar month in nanodollars.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monthly: Option<i64>,
+}
+
+impl ModelPolicy {
+    /// Parse and validate a policy from its JSON representation.
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let policy: Self =
+            serde_json::from_str(json).map_err(|e| format!("invalid model policy: {e}"))?;
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    /// Reject empty patterns and negative cap amounts.
+    pub fn validate(&self) -> Result<(), String> {
+        let patterns = self
+            .allow
+            .iter()
+            .chain(&self.deny)
+            .chain(self.caps.iter().map(|cap| &cap.model));
+        for pattern in patterns {
+            if pattern.trim().is_empty() {
+                return Err("model patterns must not be empty".to_string());
+            }
+        }
+        for cap in &self.caps {
+            if cap.daily.is_none() && cap.monthly.is_none() {
+                return Err(format!(
+                    "cap for '{}' needs a daily or monthly amount",
+                    cap.model
+                ));
+            }
+            if cap.daily.is_some_and(|v| v < 0) || cap.monthly.is_some_and(|v| v < 0) {
+                return Err(format!("cap for '{}' must not be negative", cap.model));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the policy places no restriction at all.
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty() && self.caps.is_empty()
+    }
+
+    /// Whether the token may call `model`.
+    pub fn allows(&self, model: &str) -> bool {
+        if self.deny.iter().any(|p| glob_match(p, model)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|p| glob_match(p, model))
+    }
+
+    /// Caps that apply to `model`.
+    pub fn caps_for<'a>(&'a self, model: &'a str) -> impl Iterator<Item = &'a ModelSpendCap> {
+        self.caps
+            .iter()
+            .filter(move |cap| glob_match(&cap.model, model))
+    }
+}
+
+/// Match `text` against a glob `pattern` (`*` and `?`), ASCII case-insensitive.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    // Position of the last '*' in the pattern and the text index it was tried at
+    let mut star: Option<(usize, usize)> = None;
+
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi].eq_ignore_ascii_case(&t[ti])) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((star_pi, star_ti)) = star {
+            // Let the last '*' absorb one more character and retry
+            pi = star_pi + 1;
+            ti = star_ti + 1;
+            star = Some((star_pi, star_ti + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("gpt-4o*", "gpt-4o"));
+        assert!(glob_match("gpt-4o*", "GPT-4o-mini"));
+        assert!(glob_match("*-preview", "o1-preview"));
+        assert!(glob_match("claude-?-*", "claude-3-opus"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "aXXbYYc"));
+        assert!(!glob_match("a*b*c", "aXXbYY"));
+        assert!(!glob_match("gpt-4o", "gpt-4o-mini"));
+        assert!(!glob_match("?", ""));
+    }
+
+    #[test]
+    fn test_deny_takes_precedence() {
+        let policy = ModelPolicy {
+            allow: vec!["gpt-*".to_string()],
+            deny: vec!["gpt-4.5*".to_string()],
+            caps: vec![],
+        };
+        assert!(policy.allows("gpt-4o"));
+        assert!(!policy.allows("gpt-4.5-preview"));
+        assert!(!policy.allows("claude-3-opus"));
+        assert!(ModelPolicy::default().allows("anything"));
+    }
+
+    #[test]
+    fn test_caps_for_and_validation() {
+        let policy = ModelPolicy::parse(
+            r#"{"caps":[{"model":"gpt-4o*","daily":10},{"model":"*","monthly":100}]}"#,
+        )
+        .unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(policy.caps_for("gpt-4o-mini").count(), 2);
+        assert_eq!(policy.caps_for("o1").count(), 1);
+
+        assert!(ModelPolicy::parse(r#"{"caps":[{"model":"x"}]}"#).is_err());
+        assert!(ModelPolicy::parse(r#"{"caps":[{"model":"x","daily":-1}]}"#).is_err());
+        assert!(ModelPolicy::parse(r#"{"allow":[""]}"#).is_err());
+        assert!(ModelPolicy::parse("not json").is_err());
+    }
+}
@@ -0,0 +1,42 @@
+//! Per-token and per-user request rate limits.
+//!
+//! The same [`RateLimits`] shape is stored on `router_tokens` and on
+//! `user_accounts`; the router enforces both for every request. `None` means
+//! the dimension is unlimited.
+
+use serde::{Deserialize, Serialize};
+
+/// Requests per minute, tokens per minute and concurrent in-flight requests.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimits {
+    /// Requests per minute.
+    #[serde(default)]
+    pub rpm_limit: Option<i32>,
+    /// Prompt + completion tokens per minute.
+    #[serde(default)]
+    pub tpm_limit: Option<i64>,
+    /// Requests (including open streams) in flight at once.
+    #[serde(default)]
+    pub max_concurrency: Option<i32>,
+}
+
+impl RateLimits {
+    /// Whether no dimension is limited.
+    pub fn is_unlimited(&self) -> bool {
+        self.rpm_limit.is_none() && self.tpm_limit.is_none() && self.max_concurrency.is_none()
+    }
+
+    /// Limits must be positive; use `None` for unlimited.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.rpm_limit.is_some_and(|v| v <= 0) {
+            return Err("rpm_limit must be positive".to_string());
+        }
+        if self.tpm_limit.is_some_and(|v| v <= 0) {
+            return Err("tpm_limit must be positive".to_string());
+        }
+        if self.max_concurrency.is_some_and(|v| v <= 0) {
+            return Err("max_concurrency must be positive".to_string());
+        }
+        Ok(())
+    }
+}
@@ -19,6 +19,9 @@ pub use crate::price_u64::{
 };
 
 /// Supported currencies for pricing
+///
+/// Only USD and CNY have their own balance columns; amounts in any other
+/// currency are converted into the USD wallet (see [`Currency::wallet`]).
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Currency {
@@ -26,15 +29,46 @@ pub enum Currency {
     USD,
     CNY,
     EUR,
+    JPY,
+    GBP,
+    HKD,
+    KRW,
+    SGD,
+    AUD,
+    CAD,
+    CHF,
 }
 
 impl Currency {
+    /// Every supported currency, in display order
+    pub const ALL: [Currency; 11] = [
+        Currency::USD,
+        Currency::CNY,
+        Currency::EUR,
+        Currency::JPY,
+        Currency::GBP,
+        Currency::HKD,
+        Currency::KRW,
+        Currency::SGD,
+        Currency::AUD,
+        Currency::CAD,
+        Currency::CHF,
+    ];
+
     /// Get the currency symbol
     pub fn symbol(&self) -> &'static str {
         match self {
             Currency::USD => "$",
             Currency::CNY => "¥",
             Currency::EUR => "€",
+            Currency::JPY => "¥",
+            Currency::GBP => "£",
+            Currency::HKD => "HK$",
+            Currency::KRW => "₩",
+            Currency::SGD => "S$",
+            Currency::AUD => "A$",
+            Currency::CAD => "C$",
+            Currency::CHF => "CHF",
         }
     }
 
@@ -44,6 +78,22 @@ impl Currency {
             Currency::USD => "USD",
             Currency::CNY => "CNY",
             Currency::EUR => "EUR",
+            Currency::JPY => "JPY",
+            Currency::GBP => "GBP",
+            Currency::HKD => "HKD",
+            Currency::KRW => "KRW",
+            Currency::SGD => "SGD",
+            Currency::AUD => "AUD",
+            Currency::CAD => "CAD",
+            Currency::CHF => "CHF",
+        }
+    }
+
+    /// Balance column a top-up in this currency is credited to
+    pub fn wallet(&self) -> Currency {
+        match self {
+            Currency::CNY => Currency::CNY,
+            _ => Currency::USD,
         }
     }
 }
@@ -58,12 +108,10 @@ impl FromStr for Currency {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_uppercase().as_str() {
-            "USD" | "usd" => Ok(Currency::USD),
-            "CNY" | "cny" => Ok(Currency::CNY),
-            "EUR" | "eur" => Ok(Currency::EUR),
-            _ => Err(format!("Unknown currency: {}", s)),
-        }
+        Currency::ALL
+            .into_iter()
+            .find(|c| c.code().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("Unknown currency: {}", s))
     }
 }
 
@@ -115,6 +163,25 @@ pub struct MultiCurrencyPrice {
     pub output_price: i64,
 }
 
+impl MultiCurrencyPrice {
+    /// Re-express the price in `to`, given the `currency -> to` rate scaled
+    /// by 10^9. Saturates instead of overflowing.
+    pub fn convert(&self, to: Currency, rate_scaled: i64) -> MultiCurrencyPrice {
+        if to == self.currency {
+            return self.clone();
+        }
+        let scale = |nano: i64| {
+            let v = nano as i128 * rate_scaled as i128 / crate::price_u64::RATE_SCALE as i128;
+            v.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+        };
+        MultiCurrencyPrice {
+            currency: to,
+            input_price: scale(self.input_price),
+            output_price: scale(self.output_price),
+        }
+    }
+}
+
 /// Exchange rate for currency conversion
 /// Rate is stored as scaled i64 (rate * 10^9) for precision
 /// Note: Using i64 instead of u64 for PostgreSQL BIGINT compatibility
@@ -783,7 +850,21 @@ mod currency_tests {
             Currency::from_str("eur").unwrap_or_else(|e| panic!("Failed to parse currency: {e}")),
             Currency::EUR
         );
-        assert!(Currency::from_str("GBP").is_err());
+        assert_eq!(
+            Currency::from_str("gbp").unwrap_or_else(|e| panic!("Failed to parse currency: {e}")),
+            Currency::GBP
+        );
+        assert!(Currency::from_str("XYZ").is_err());
+        for currency in Currency::ALL {
+            assert_eq!(Currency::from_str(currency.code()), Ok(currency));
+        }
+    }
+
+    #[test]
+    fn test_currency_wallet() {
+        assert_eq!(Currency::USD.wallet(), Currency::USD);
+        assert_eq!(Currency::CNY.wallet(), Currency::CNY);
+        assert_eq!(Currency::JPY.wallet(), Currency::USD);
     }
 
     #[test]
@@ -825,4 +906,19 @@ mod currency_tests {
             .unwrap_or_else(|e| panic!("Failed to serialize price: {e}"));
         assert!(json.contains("\"currency\":\"cny\""));
     }
+
+    #[test]
+    fn test_multi_currency_price_convert() {
+        let price = MultiCurrencyPrice {
+            currency: Currency::USD,
+            input_price: 2_000_000_000,
+            output_price: 8_000_000_000,
+        };
+        // 1 USD = 150 JPY
+        let jpy = price.convert(Currency::JPY, 150_000_000_000);
+        assert_eq!(jpy.currency, Currency::JPY);
+        assert_eq!(jpy.input_price, 300_000_000_000);
+        assert_eq!(jpy.output_price, 1_200_000_000_000);
+        assert_eq!(price.convert(Currency::USD, 0).input_price, 2_000_000_000);
+    }
 }
@@ -0,0 +1,155 @@
+//! `billing_exchange_rates` (current rate per pair) and
+//! `billing_exchange_rate_history` (every proposed rate, applied or refused).
+//!
+//! Rates are scaled by 10^9, matching `burncloud_common::rate_to_scaled`.
+
+use burncloud_common::types::ExchangeRate;
+use burncloud_database::{adapt_sql, Database, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::common::current_timestamp;
+
+/// One entry of the exchange rate audit trail
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ExchangeRateHistory {
+    pub id: i32,
+    pub from_currency: String,
+    pub to_currency: String,
+    /// Rate scaled by 10^9
+    pub rate: i64,
+    /// Provider name, or `manual` for operator edits
+    pub source: String,
+    /// 0 when the sanity bound refused the rate
+    pub applied: i32,
+    pub note: Option<String>,
+    pub recorded_at: i64,
+}
+
+pub struct BillingExchangeRateModel;
+
+impl BillingExchangeRateModel {
+    pub async fn list(db: &Database) -> Result<Vec<ExchangeRate>> {
+        let conn = db.get_connection()?;
+        let rates = sqlx::query_as(
+            "SELECT id, from_currency, to_currency, rate, updated_at FROM billing_exchange_rates \
+             ORDER BY from_currency, to_currency",
+        )
+        .fetch_all(conn.pool())
+        .await?;
+        Ok(rates)
+    }
+
+    /// Current scaled rate for `from -> to`, direct rows only
+    pub async fn get(db: &Database, from: &str, to: &str) -> Result<Option<i64>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "SELECT rate FROM billing_exchange_rates WHERE from_currency = ? AND to_currency = ?",
+        );
+        let rate = sqlx::query_scalar(&sql)
+            .bind(from)
+            .bind(to)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(rate)
+    }
+
+    /// Set the current rate and append an applied history row
+    pub async fn upsert(
+        db: &Database,
+        from: &str,
+        to: &str,
+        rate_scaled: i64,
+        source: &str,
+    ) -> Result<()> {
+        let conn = db.get_connection()?;
+        let now = current_timestamp();
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "INSERT INTO billing_exchange_rates (from_currency, to_currency, rate, updated_at) \
+             VALUES (?, ?, ?, ?) \
+             ON CONFLICT(from_currency, to_currency) DO UPDATE SET \
+             rate = excluded.rate, updated_at = excluded.updated_at",
+        );
+        sqlx::query(&sql)
+            .bind(from)
+            .bind(to)
+            .bind(rate_scaled)
+            .bind(now)
+            .execute(conn.pool())
+            .await?;
+        Self::record(db, from, to, rate_scaled, source, true, None).await
+    }
+
+    /// Append a history row without touching the current rate
+    pub async fn record(
+        db: &Database,
+        from: &str,
+        to: &str,
+        rate_scaled: i64,
+        source: &str,
+        applied: bool,
+        note: Option<&str>,
+    ) -> Result<()> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "INSERT INTO billing_exchange_rate_history \
+             (from_currency, to_currency, rate, source, applied, note, recorded_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+        );
+        sqlx::query(&sql)
+            .bind(from)
+            .bind(to)
+            .bind(rate_scaled)
+            .bind(source)
+            .bind(applied as i32)
+            .bind(note)
+            .bind(current_timestamp())
+            .execute(conn.pool())
+            .await?;
+        Ok(())
+    }
+
+    /// History of a pair, newest first
+    pub async fn history(
+        db: &Database,
+        from: &str,
+        to: &str,
+        limit: i64,
+    ) -> Result<Vec<ExchangeRateHistory>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "SELECT id, from_currency, to_currency, rate, source, applied, note, recorded_at \
+             FROM billing_exchange_rate_history WHERE from_currency = ? AND to_currency = ? \
+             ORDER BY recorded_at DESC, id DESC LIMIT ?",
+        );
+        let rows = sqlx::query_as(&sql)
+            .bind(from)
+            .bind(to)
+            .bind(limit)
+            .fetch_all(conn.pool())
+            .await?;
+        Ok(rows)
+    }
+
+    /// Rate that was in effect at `at` (unix seconds), for auditing a billed
+    /// conversion after the current rate has moved on.
+    pub async fn rate_at(db: &Database, from: &str, to: &str, at: i64) -> Result<Option<i64>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "SELECT rate FROM billing_exchange_rate_history \
+             WHERE from_currency = ? AND to_currency = ? AND applied = 1 AND recorded_at <= ? \
+             ORDER BY recorded_at DESC, id DESC LIMIT 1",
+        );
+        let rate = sqlx::query_scalar(&sql)
+            .bind(from)
+            .bind(to)
+            .bind(at)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(rate)
+    }
+}
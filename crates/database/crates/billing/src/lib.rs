@@ -1,12 +1,14 @@
 //! Database billing crate for BurnCloud
 //!
 //! This crate aggregates all billing_ domain tables: billing_prices,
-//! billing_tiered_prices, billing_exchange_rates.
+//! billing_tiered_prices, billing_exchange_rates, billing_exchange_rate_history.
 
+mod billing_exchange_rate;
 mod billing_price;
 mod billing_tiered_price;
 mod common;
 
+pub use billing_exchange_rate::{BillingExchangeRateModel, ExchangeRateHistory};
 pub use billing_price::BillingPriceModel;
 pub use billing_tiered_price::BillingTieredPriceModel;
 pub use common::current_timestamp;
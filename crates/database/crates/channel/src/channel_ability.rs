@@ -126,4 +126,28 @@ impl ChannelAbilityModel {
 
         Ok(models.into_iter().map(|(m,)| m).collect())
     }
+
+    /// List distinct models with at least one enabled channel in `group`
+    ///
+    /// Used by /v1/models to show only the models a caller can route to.
+    pub async fn list_distinct_models_by_group(db: &Database, group: &str) -> Result<Vec<String>> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let group_col = if is_postgres { "\"group\"" } else { "`group`" };
+        let enabled_lit = if is_postgres { "true" } else { "1" };
+
+        let sql = format!(
+            "SELECT DISTINCT model FROM channel_abilities WHERE {} = {} AND enabled = {} ORDER BY model",
+            group_col,
+            ph(is_postgres, 1),
+            enabled_lit
+        );
+
+        let models: Vec<(String,)> = sqlx::query_as(&sql)
+            .bind(group)
+            .fetch_all(conn.pool())
+            .await?;
+
+        Ok(models.into_iter().map(|(m,)| m).collect())
+    }
 }
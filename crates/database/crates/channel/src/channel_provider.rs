@@ -1,8 +1,20 @@
 use crate::common::current_timestamp;
+use crate::key_cipher::{encrypt_key, is_encrypted, is_masked_key, Keyring};
 use burncloud_common::types::Channel;
 use burncloud_database::{adapt_sql, ph, phs, Database, Result};
 use sqlx::Row;
 
+/// Outcome of a pass over stored channel keys.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct KeyMigration {
+    /// Plaintext keys that were encrypted
+    pub encrypted: usize,
+    /// Keys moved from a previous master key onto the current one
+    pub rewrapped: usize,
+    /// Keys that could not be read with any configured master key
+    pub failed: usize,
+}
+
 pub struct ChannelProviderModel;
 
 impl ChannelProviderModel {
@@ -38,6 +50,7 @@ impl ChannelProviderModel {
             )
         };
 
+        let key = encrypt_key(&channel.key)?;
         let now = current_timestamp();
         channel.created_time = Some(now);
 
@@ -46,7 +59,7 @@ impl ChannelProviderModel {
 
         let query = sqlx::query(&sql)
             .bind(channel.type_)
-            .bind(&channel.key)
+            .bind(&key)
             .bind(channel.status)
             .bind(&channel.name)
             .bind(channel.weight)
@@ -86,6 +99,8 @@ impl ChannelProviderModel {
         Ok(id)
     }
 
+    /// Update a channel. An empty or masked `key` keeps the stored key, so
+    /// edit forms that only ever saw the masked value do not overwrite it.
     pub async fn update(db: &Database, channel: &Channel) -> Result<()> {
         let conn = db.get_connection()?;
         let pool = conn.pool();
@@ -99,16 +114,21 @@ impl ChannelProviderModel {
             &format!(
                 r#"
             UPDATE channel_providers
-            SET {} = ?, key = ?, status = ?, name = ?, weight = ?, base_url = ?, models = ?, {} = ?, priority = ?, param_override = ?, header_override = ?, api_version = ?, pricing_region = ?, rpm_cap = ?, tpm_cap = ?, reservation_green = ?, reservation_yellow = ?, reservation_red = ?
+            SET {} = ?, key = COALESCE(?, key), status = ?, name = ?, weight = ?, base_url = ?, models = ?, {} = ?, priority = ?, param_override = ?, header_override = ?, api_version = ?, pricing_region = ?, rpm_cap = ?, tpm_cap = ?, reservation_green = ?, reservation_yellow = ?, reservation_red = ?
             WHERE id = ?
             "#,
                 type_col, group_col
             ),
         );
+        let key = if channel.key.is_empty() || is_masked_key(&channel.key) {
+            None
+        } else {
+            Some(encrypt_key(&channel.key)?)
+        };
 
         sqlx::query(&sql)
             .bind(channel.type_)
-            .bind(&channel.key)
+            .bind(key)
             .bind(channel.status)
             .bind(&channel.name)
             .bind(channel.weight)
@@ -233,6 +253,73 @@ impl ChannelProviderModel {
         Ok(channels)
     }
 
+    /// Number of channel keys stored encrypted
+    pub async fn count_encrypted_keys(db: &Database) -> Result<i64> {
+        let conn = db.get_connection()?;
+        let (count,): (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM channel_providers WHERE key LIKE 'enc:v1:%'")
+                .fetch_one(conn.pool())
+                .await?;
+        Ok(count)
+    }
+
+    /// Encrypt plaintext keys and, with `rewrap`, move keys wrapped by a
+    /// previous master key onto the current one. Each row is updated on its
+    /// own and only while it still holds the value that was read, so the pass
+    /// is safe to interrupt, to race with edits, and to simply run again.
+    pub async fn reencrypt_keys(
+        db: &Database,
+        keyring: &Keyring,
+        rewrap: bool,
+    ) -> Result<KeyMigration> {
+        let conn = db.get_connection()?;
+        let pool = conn.pool();
+        let is_postgres = db.kind() == "postgres";
+
+        let rows: Vec<(i32, String)> =
+            sqlx::query_as("SELECT id, key FROM channel_providers ORDER BY id")
+                .fetch_all(pool)
+                .await?;
+        let sql = adapt_sql(
+            is_postgres,
+            "UPDATE channel_providers SET key = ? WHERE id = ? AND key = ?",
+        );
+
+        let mut report = KeyMigration::default();
+        for (id, stored) in rows {
+            let was_encrypted = is_encrypted(&stored);
+            if was_encrypted && !rewrap {
+                continue;
+            }
+            let replacement = match keyring.rewrap(&stored) {
+                Ok(Some(replacement)) => replacement,
+                Ok(None) => continue,
+                Err(e) => {
+                    tracing::warn!(channel_id = id, "Cannot re-encrypt channel key: {}", e);
+                    report.failed += 1;
+                    continue;
+                }
+            };
+            let updated = sqlx::query(&sql)
+                .bind(&replacement)
+                .bind(id)
+                .bind(&stored)
+                .execute(pool)
+                .await?
+                .rows_affected();
+            if updated == 0 {
+                // Edited or deleted meanwhile, the new value is already encrypted
+                continue;
+            }
+            if was_encrypted {
+                report.rewrapped += 1;
+            } else {
+                report.encrypted += 1;
+            }
+        }
+        Ok(report)
+    }
+
     pub async fn sync_abilities(db: &Database, channel: &Channel) -> Result<()> {
         let conn = db.get_connection()?;
         let pool = conn.pool();
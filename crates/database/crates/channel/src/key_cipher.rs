@@ -0,0 +1,331 @@
+//! Envelope encryption for upstream channel API keys.
+//!
+//! Every key is sealed with its own random data key (AES-256-GCM) and that
+//! data key is wrapped with the master key. Stored values look like
+//! `enc:v1:<master key id>:<wrapped data key>:<sealed key>`, where the id is a
+//! short fingerprint of the master key. Rotating the master key therefore
+//! only re-wraps the data keys and never touches the sealed API keys.
+//!
+//! The master key is 32 bytes of hex, read from `MASTER_KEY` or from the file
+//! named by `MASTER_KEY_FILE`. While rotating, `MASTER_KEY_PREVIOUS` lists the
+//! retired keys (comma separated) so values they wrapped stay readable until
+//! `burncloud channel rekey` has moved them onto the current key.
+//!
+//! Encryption is opt-in: without `MASTER_KEY` or `MASTER_KEY_FILE`, new keys
+//! are stored as given. The `burncloud` binary configures a key on first run.
+
+use aes_gcm::aead::Aead;
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+use base64::Engine;
+use burncloud_database::{DatabaseError, Result};
+use sha2::{Digest, Sha256};
+use std::sync::OnceLock;
+
+const PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 12;
+const MASK: &str = "****";
+
+fn invalid(message: impl Into<String>) -> DatabaseError {
+    DatabaseError::InvalidData {
+        message: message.into(),
+    }
+}
+
+struct MasterKey {
+    id: String,
+    cipher: Aes256Gcm,
+}
+
+impl MasterKey {
+    fn new(bytes: &[u8; 32]) -> Self {
+        Self {
+            id: hex::encode(&Sha256::digest(bytes)[..4]),
+            cipher: Aes256Gcm::new(bytes.into()),
+        }
+    }
+}
+
+/// The current master key plus any retired ones still accepted for reading.
+pub struct Keyring {
+    current: MasterKey,
+    previous: Vec<MasterKey>,
+}
+
+impl Keyring {
+    pub fn new(current: &[u8; 32], previous: &[[u8; 32]]) -> Self {
+        Self {
+            current: MasterKey::new(current),
+            previous: previous.iter().map(MasterKey::new).collect(),
+        }
+    }
+
+    /// Load the keyring from `MASTER_KEY` / `MASTER_KEY_FILE` and
+    /// `MASTER_KEY_PREVIOUS`.
+    pub fn from_env() -> Result<Self> {
+        Self::load_env().map_err(invalid)
+    }
+
+    fn load_env() -> std::result::Result<Self, String> {
+        let current = match std::env::var("MASTER_KEY") {
+            Ok(value) if !value.trim().is_empty() => parse_master_key(&value, "MASTER_KEY")?,
+            _ => match std::env::var("MASTER_KEY_FILE") {
+                Ok(path) if !path.trim().is_empty() => {
+                    let value = std::fs::read_to_string(path.trim())
+                        .map_err(|e| format!("cannot read MASTER_KEY_FILE {path}: {e}"))?;
+                    parse_master_key(&value, "MASTER_KEY_FILE")?
+                }
+                _ => {
+                    return Err(
+                        "no master key configured, set MASTER_KEY or MASTER_KEY_FILE".to_string(),
+                    )
+                }
+            },
+        };
+        let previous = std::env::var("MASTER_KEY_PREVIOUS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(|value| parse_master_key(value, "MASTER_KEY_PREVIOUS"))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(Self::new(&current, &previous))
+    }
+
+    /// Fingerprint of the current master key, as written into stored values.
+    pub fn current_id(&self) -> &str {
+        &self.current.id
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let data_key: [u8; 32] = rand::random();
+        let sealed = seal(&Aes256Gcm::new((&data_key).into()), plaintext.as_bytes())?;
+        let wrapped = seal(&self.current.cipher, &data_key)?;
+        Ok(format!("{PREFIX}{}:{wrapped}:{sealed}", self.current.id))
+    }
+
+    /// Decrypt a stored value. Values without the envelope prefix are
+    /// plaintext rows that have not been migrated yet and pass through.
+    pub fn decrypt(&self, stored: &str) -> Result<String> {
+        let Some(envelope) = Envelope::parse(stored)? else {
+            return Ok(stored.to_string());
+        };
+        let data_key = self.unwrap_data_key(&envelope)?;
+        let plaintext = open(
+            &Aes256Gcm::new_from_slice(&data_key).map_err(|_| corrupt())?,
+            envelope.sealed,
+        )?;
+        String::from_utf8(plaintext).map_err(|_| corrupt())
+    }
+
+    /// The value `stored` should be replaced with so it is wrapped by the
+    /// current master key, or `None` when it already is (or is empty).
+    pub fn rewrap(&self, stored: &str) -> Result<Option<String>> {
+        let Some(envelope) = Envelope::parse(stored)? else {
+            return (!stored.is_empty())
+                .then(|| self.encrypt(stored))
+                .transpose();
+        };
+        if envelope.key_id == self.current.id {
+            return Ok(None);
+        }
+        let wrapped = seal(&self.current.cipher, &self.unwrap_data_key(&envelope)?)?;
+        Ok(Some(format!(
+            "{PREFIX}{}:{wrapped}:{}",
+            self.current.id, envelope.sealed
+        )))
+    }
+
+    fn unwrap_data_key(&self, envelope: &Envelope) -> Result<Vec<u8>> {
+        let master = std::iter::once(&self.current)
+            .chain(&self.previous)
+            .find(|key| key.id == envelope.key_id)
+            .ok_or_else(|| {
+                invalid(format!(
+                    "channel key was encrypted with unknown master key {}",
+                    envelope.key_id
+                ))
+            })?;
+        open(&master.cipher, envelope.wrapped)
+    }
+}
+
+struct Envelope<'a> {
+    key_id: &'a str,
+    wrapped: &'a str,
+    sealed: &'a str,
+}
+
+impl<'a> Envelope<'a> {
+    fn parse(stored: &'a str) -> Result<Option<Self>> {
+        let Some(body) = stored.strip_prefix(PREFIX) else {
+            return Ok(None);
+        };
+        let mut parts = body.splitn(3, ':');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(key_id), Some(wrapped), Some(sealed)) => Ok(Some(Self {
+                key_id,
+                wrapped,
+                sealed,
+            })),
+            _ => Err(corrupt()),
+        }
+    }
+}
+
+fn corrupt() -> DatabaseError {
+    invalid("channel key ciphertext is corrupt or was encrypted with another key")
+}
+
+fn seal(cipher: &Aes256Gcm, plaintext: &[u8]) -> Result<String> {
+    let nonce: [u8; NONCE_LEN] = rand::random();
+    let mut out = nonce.to_vec();
+    out.extend(
+        cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext)
+            .map_err(|_| invalid("channel key encryption failed"))?,
+    );
+    Ok(STANDARD_NO_PAD.encode(out))
+}
+
+fn open(cipher: &Aes256Gcm, encoded: &str) -> Result<Vec<u8>> {
+    let bytes = STANDARD_NO_PAD.decode(encoded).map_err(|_| corrupt())?;
+    if bytes.len() < NONCE_LEN {
+        return Err(corrupt());
+    }
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| corrupt())
+}
+
+fn parse_master_key(value: &str, source: &str) -> std::result::Result<[u8; 32], String> {
+    hex::decode(value.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| format!("{source} must be 64 hex characters (32 bytes)"))
+}
+
+static KEYRING: OnceLock<std::result::Result<Keyring, String>> = OnceLock::new();
+
+/// The process-wide keyring, loaded from the environment on first use.
+pub fn keyring() -> Result<&'static Keyring> {
+    KEYRING
+        .get_or_init(Keyring::load_env)
+        .as_ref()
+        .map_err(|message| invalid(message.clone()))
+}
+
+/// Whether a stored value carries the encryption envelope.
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(PREFIX)
+}
+
+/// Whether a master key is configured, usable or not
+pub fn encryption_enabled() -> bool {
+    ["MASTER_KEY", "MASTER_KEY_FILE"]
+        .iter()
+        .any(|name| std::env::var(name).is_ok_and(|value| !value.trim().is_empty()))
+}
+
+/// Why keys cannot be encrypted although a master key is configured
+pub fn encryption_config_error() -> Option<String> {
+    if !encryption_enabled() {
+        return None;
+    }
+    keyring().err().map(|e| e.to_string())
+}
+
+/// Encrypt a key for storage. Empty and already encrypted values are kept,
+/// and so is every key while encryption is not enabled.
+pub fn encrypt_key(key: &str) -> Result<String> {
+    if key.is_empty() || is_encrypted(key) || !encryption_enabled() {
+        return Ok(key.to_string());
+    }
+    keyring()?.encrypt(key)
+}
+
+/// Recover the plaintext of a stored key.
+pub fn decrypt_key(stored: &str) -> Result<String> {
+    if !is_encrypted(stored) {
+        return Ok(stored.to_string());
+    }
+    keyring()?.decrypt(stored)
+}
+
+/// Display form of a stored key: the first and last four characters of long
+/// keys, nothing recognisable otherwise.
+pub fn mask_key(stored: &str) -> String {
+    if stored.is_empty() {
+        return String::new();
+    }
+    let chars: Vec<char> = match decrypt_key(stored) {
+        Ok(plaintext) => plaintext.chars().collect(),
+        Err(_) => Vec::new(),
+    };
+    if chars.len() < 12 {
+        return MASK.repeat(2);
+    }
+    let head: String = chars[..4].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{head}{MASK}{tail}")
+}
+
+/// Whether a submitted key is a masked display value rather than a real key.
+pub fn is_masked_key(value: &str) -> bool {
+    value.contains(MASK)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: [u8; 32] = [7; 32];
+    const NEW: [u8; 32] = [9; 32];
+
+    #[test]
+    fn round_trips_and_passes_plaintext_through() {
+        let keyring = Keyring::new(&NEW, &[]);
+        let stored = keyring.encrypt("sk-live-abcdef123456").unwrap();
+        assert!(is_encrypted(&stored));
+        assert!(!stored.contains("abcdef"));
+        assert_ne!(stored, keyring.encrypt("sk-live-abcdef123456").unwrap());
+        assert_eq!(keyring.decrypt(&stored).unwrap(), "sk-live-abcdef123456");
+        assert_eq!(keyring.decrypt("sk-plain").unwrap(), "sk-plain");
+    }
+
+    #[test]
+    fn rewrap_moves_values_to_the_current_key() {
+        let old = Keyring::new(&OLD, &[]);
+        let stored = old.encrypt("sk-rotate-me").unwrap();
+
+        let rotated = Keyring::new(&NEW, &[OLD]);
+        let rewrapped = rotated.rewrap(&stored).unwrap().unwrap();
+        assert!(rewrapped.contains(rotated.current_id()));
+        assert_eq!(rotated.rewrap(&rewrapped).unwrap(), None);
+        assert_eq!(rotated.rewrap("").unwrap(), None);
+        assert!(rotated.rewrap("sk-plain").unwrap().is_some());
+
+        let new_only = Keyring::new(&NEW, &[]);
+        assert_eq!(new_only.decrypt(&rewrapped).unwrap(), "sk-rotate-me");
+        assert!(new_only.decrypt(&stored).is_err());
+    }
+
+    #[test]
+    fn rejects_tampered_values() {
+        let keyring = Keyring::new(&NEW, &[]);
+        let mut stored = keyring.encrypt("sk-live-abcdef123456").unwrap();
+        let last = stored.pop().unwrap();
+        stored.push(if last == 'A' { 'B' } else { 'A' });
+        assert!(keyring.decrypt(&stored).is_err());
+        assert!(keyring.decrypt("enc:v1:broken").is_err());
+    }
+
+    #[test]
+    fn masks_plaintext_keys() {
+        assert_eq!(mask_key("sk-live-abcdef123456"), "sk-l****3456");
+        assert_eq!(mask_key("short"), "********");
+        assert_eq!(mask_key(""), "");
+        assert!(is_masked_key(&mask_key("sk-live-abcdef123456")));
+    }
+}
@@ -1,7 +1,8 @@
 //! Database operations for model_ domain (model capability metadata).
 //!
 //! This crate is the canonical home of the `model_capabilities` table
-//! (HuggingFace-style metadata). Other entities that used to live under the
+//! (HuggingFace-style metadata) and of `model_instances`, the locally served
+//! models started through the inference service. Other entities that used to live under the
 //! legacy `database-models` crate have been moved to their proper domain
 //! crates:
 //!   - `user_api_key.rs` → `database-user`
@@ -11,9 +12,11 @@
 
 mod common;
 mod model_capability;
+mod model_instance;
 
 pub use common::current_timestamp;
 pub use model_capability::{ModelDatabase, ModelInfo};
+pub use model_instance::{ModelInstance, ModelInstanceModel};
 
 /// Spec-aligned alias: `model_capabilities` row type.
 /// The actual metadata is loaded into `ModelInfo` — this alias matches the
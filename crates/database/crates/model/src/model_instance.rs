@@ -0,0 +1,176 @@
+use burncloud_database::{Database, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+
+use crate::common::current_timestamp;
+
+/// A locally served model (`model_instances` row).
+///
+/// `status` mirrors the in-process `InstanceStatus` of the inference service:
+/// `starting`, `running`, `unloaded` (idle, restarted on the next request),
+/// `stopped` or `failed`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelInstance {
+    pub model_id: String,
+    /// Absolute path of the GGUF file being served
+    pub file_path: String,
+    pub port: i32,
+    /// OS process id of the inference server, when one is alive
+    pub pid: Option<i64>,
+    /// Channel registered in `channel_providers` for this instance
+    pub channel_id: Option<i32>,
+    pub status: String,
+    pub last_error: Option<String>,
+    pub started_at: Option<i64>,
+    pub updated_at: i64,
+}
+
+pub struct ModelInstanceModel;
+
+const SELECT_COLUMNS: &str =
+    "model_id, file_path, port, pid, channel_id, status, last_error, started_at, updated_at";
+
+impl ModelInstanceModel {
+    /// Insert or replace the instance record for `instance.model_id`.
+    pub async fn upsert(db: &Database, instance: &ModelInstance) -> Result<()> {
+        let conn = db.get_connection()?;
+        let sql = if db.kind() == "postgres" {
+            r#"
+            INSERT INTO model_instances
+                (model_id, file_path, port, pid, channel_id, status, last_error, started_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (model_id) DO UPDATE SET
+                file_path = EXCLUDED.file_path,
+                port = EXCLUDED.port,
+                pid = EXCLUDED.pid,
+                channel_id = EXCLUDED.channel_id,
+                status = EXCLUDED.status,
+                last_error = EXCLUDED.last_error,
+                started_at = EXCLUDED.started_at,
+                updated_at = EXCLUDED.updated_at
+            "#
+        } else {
+            r#"
+            INSERT INTO model_instances
+                (model_id, file_path, port, pid, channel_id, status, last_error, started_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT (model_id) DO UPDATE SET
+                file_path = excluded.file_path,
+                port = excluded.port,
+                pid = excluded.pid,
+                channel_id = excluded.channel_id,
+                status = excluded.status,
+                last_error = excluded.last_error,
+                started_at = excluded.started_at,
+                updated_at = excluded.updated_at
+            "#
+        };
+        sqlx::query(sql)
+            .bind(&instance.model_id)
+            .bind(&instance.file_path)
+            .bind(instance.port)
+            .bind(instance.pid)
+            .bind(instance.channel_id)
+            .bind(&instance.status)
+            .bind(&instance.last_error)
+            .bind(instance.started_at)
+            .bind(current_timestamp())
+            .execute(conn.pool())
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get(db: &Database, model_id: &str) -> Result<Option<ModelInstance>> {
+        let conn = db.get_connection()?;
+        let sql = if db.kind() == "postgres" {
+            format!("SELECT {SELECT_COLUMNS} FROM model_instances WHERE model_id = $1")
+        } else {
+            format!("SELECT {SELECT_COLUMNS} FROM model_instances WHERE model_id = ?")
+        };
+        let row = sqlx::query(&sql)
+            .bind(model_id)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(row.map(|r| Self::from_row(&r)))
+    }
+
+    pub async fn list(db: &Database) -> Result<Vec<ModelInstance>> {
+        let conn = db.get_connection()?;
+        let sql = format!("SELECT {SELECT_COLUMNS} FROM model_instances ORDER BY model_id");
+        let rows = sqlx::query(&sql).fetch_all(conn.pool()).await?;
+        Ok(rows.iter().map(Self::from_row).collect())
+    }
+
+    /// Update the lifecycle status. Leaving `running` clears the pid so a
+    /// stale id is never signalled later.
+    pub async fn update_status(
+        db: &Database,
+        model_id: &str,
+        status: &str,
+        last_error: Option<&str>,
+    ) -> Result<()> {
+        let conn = db.get_connection()?;
+        let sql = if db.kind() == "postgres" {
+            r#"UPDATE model_instances
+               SET status = $1, last_error = $2, updated_at = $3,
+                   pid = CASE WHEN $4 IN ('running', 'starting') THEN pid ELSE NULL END
+               WHERE model_id = $5"#
+        } else {
+            r#"UPDATE model_instances
+               SET status = ?, last_error = ?, updated_at = ?,
+                   pid = CASE WHEN ? IN ('running', 'starting') THEN pid ELSE NULL END
+               WHERE model_id = ?"#
+        };
+        sqlx::query(sql)
+            .bind(status)
+            .bind(last_error)
+            .bind(current_timestamp())
+            .bind(status)
+            .bind(model_id)
+            .execute(conn.pool())
+            .await?;
+        Ok(())
+    }
+
+    /// Record the channel registered for this instance.
+    pub async fn set_channel(db: &Database, model_id: &str, channel_id: i32) -> Result<()> {
+        let conn = db.get_connection()?;
+        let sql = if db.kind() == "postgres" {
+            "UPDATE model_instances SET channel_id = $1, updated_at = $2 WHERE model_id = $3"
+        } else {
+            "UPDATE model_instances SET channel_id = ?, updated_at = ? WHERE model_id = ?"
+        };
+        sqlx::query(sql)
+            .bind(channel_id)
+            .bind(current_timestamp())
+            .bind(model_id)
+            .execute(conn.pool())
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete(db: &Database, model_id: &str) -> Result<()> {
+        let conn = db.get_connection()?;
+        let sql = if db.kind() == "postgres" {
+            "DELETE FROM model_instances WHERE model_id = $1"
+        } else {
+            "DELETE FROM model_instances WHERE model_id = ?"
+        };
+        sqlx::query(sql).bind(model_id).execute(conn.pool()).await?;
+        Ok(())
+    }
+
+    fn from_row(r: &sqlx::any::AnyRow) -> ModelInstance {
+        ModelInstance {
+            model_id: r.get("model_id"),
+            file_path: r.get("file_path"),
+            port: r.get("port"),
+            pid: r.get("pid"),
+            channel_id: r.get("channel_id"),
+            status: r.get("status"),
+            last_error: r.get("last_error"),
+            started_at: r.get("started_at"),
+            updated_at: r.get("updated_at"),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+/// Tests for `ModelInstanceModel` — persistence of locally served models.
+use burncloud_database::create_database_with_url;
+use burncloud_database_model::{ModelInstance, ModelInstanceModel};
+use tempfile::NamedTempFile;
+
+async fn create_test_db() -> (burncloud_database::Database, NamedTempFile) {
+    let tmp = NamedTempFile::new().unwrap_or_else(|e| panic!("failed to create temp file: {e}"));
+    let url = format!("sqlite://{}?mode=rwc", tmp.path().display());
+    let db = create_database_with_url(&url)
+        .await
+        .unwrap_or_else(|e| panic!("failed to initialize test database: {e}"));
+    (db, tmp)
+}
+
+fn running_instance(model_id: &str, port: i32) -> ModelInstance {
+    ModelInstance {
+        model_id: model_id.to_string(),
+        file_path: format!("/data/{model_id}/model-Q4_K_M.gguf"),
+        port,
+        pid: Some(4242),
+        channel_id: Some(7),
+        status: "running".to_string(),
+        last_error: None,
+        started_at: Some(1_700_000_000),
+        updated_at: 0,
+    }
+}
+
+#[tokio::test]
+async fn test_upsert_get_and_list() {
+    let (db, _tmp) = create_test_db().await;
+
+    ModelInstanceModel::upsert(&db, &running_instance("qwen", 18080))
+        .await
+        .unwrap();
+    ModelInstanceModel::upsert(&db, &running_instance("llama", 18081))
+        .await
+        .unwrap();
+
+    // Upserting the same model replaces the previous row.
+    ModelInstanceModel::upsert(&db, &running_instance("qwen", 18090))
+        .await
+        .unwrap();
+
+    let qwen = ModelInstanceModel::get(&db, "qwen").await.unwrap().unwrap();
+    assert_eq!(qwen.port, 18090);
+    assert_eq!(qwen.pid, Some(4242));
+    assert_eq!(qwen.channel_id, Some(7));
+    assert!(qwen.updated_at > 0);
+
+    let all = ModelInstanceModel::list(&db).await.unwrap();
+    let ids: Vec<&str> = all.iter().map(|i| i.model_id.as_str()).collect();
+    assert_eq!(ids, vec!["llama", "qwen"]);
+}
+
+#[tokio::test]
+async fn test_update_status_clears_pid_when_not_running() {
+    let (db, _tmp) = create_test_db().await;
+    ModelInstanceModel::upsert(&db, &running_instance("qwen", 18080))
+        .await
+        .unwrap();
+
+    ModelInstanceModel::update_status(&db, "qwen", "failed", Some("exit code 1"))
+        .await
+        .unwrap();
+
+    let qwen = ModelInstanceModel::get(&db, "qwen").await.unwrap().unwrap();
+    assert_eq!(qwen.status, "failed");
+    assert_eq!(qwen.last_error.as_deref(), Some("exit code 1"));
+    assert_eq!(qwen.pid, None);
+}
+
+#[tokio::test]
+async fn test_delete() {
+    let (db, _tmp) = create_test_db().await;
+    ModelInstanceModel::upsert(&db, &running_instance("qwen", 18080))
+        .await
+        .unwrap();
+
+    ModelInstanceModel::delete(&db, "qwen").await.unwrap();
+
+    assert!(ModelInstanceModel::get(&db, "qwen")
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_set_channel_keeps_status() {
+    let (db, _tmp) = create_test_db().await;
+    let mut instance = running_instance("qwen", 18080);
+    instance.channel_id = None;
+    instance.status = "starting".to_string();
+    ModelInstanceModel::upsert(&db, &instance).await.unwrap();
+
+    ModelInstanceModel::set_channel(&db, "qwen", 12)
+        .await
+        .unwrap();
+
+    let qwen = ModelInstanceModel::get(&db, "qwen").await.unwrap().unwrap();
+    assert_eq!(qwen.channel_id, Some(12));
+    assert_eq!(qwen.status, "starting");
+    assert_eq!(qwen.pid, Some(4242));
+}
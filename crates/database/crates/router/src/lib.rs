@@ -7,25 +7,40 @@
 //! - [`token`] - Token management (RouterToken, RouterTokenModel)
 //! - [`log`] - Router logs, usage stats and balance deduction (RouterLog, RouterLogModel, BalanceModel)
 //! - [`router_video_task`] - Router video task persistence (RouterVideoTask, RouterVideoTaskModel)
+//! - [`org`] - Organization wallet admission and settlement (OrgBillingModel)
+//! - [`rollup`] - Minute/hour/day usage rollups (UsageRollupModel)
+//! - [`virtual_model`] - Virtual model fallback chains (VirtualModel, VirtualModelModel)
 
+use burncloud_common::RateLimits;
 use burncloud_database::{adapt_sql, phs, Database, Result};
 
 pub mod log;
+pub mod org;
+pub mod retention;
+pub mod rollup;
 pub mod router_video_task;
 pub mod token;
+pub mod virtual_model;
 
 // Re-export common types.
 pub use log::{
     get_billing_summary, get_billing_summary_for_user, get_usage_stats, get_usage_stats_by_model,
-    BalanceModel, BillingModelSummary, BillingSummary, CandidateInfo, FailoverAttempt,
+    BalanceModel, BillingModelSummary, BillingSummary, CandidateInfo, FailoverAttempt, LogFilter,
     ModelUsageStats, RouterLog, RouterLogModel, RouterRequestLog, RouterRequestLogModel,
-    StoragePolicy, UsageStats,
+    StoragePolicy, TokenModelSpend, UsageStats,
+};
+pub use org::{OrgAdmission, OrgBillingModel};
+pub use retention::LogRetentionModel;
+pub use rollup::{
+    RollupCounters, RollupDimension, RollupGranularity, RollupQuery, RollupRebuildReport,
+    RollupRow, UsageRollupModel,
 };
 pub use router_video_task::{RouterVideoTask, RouterVideoTaskModel};
 pub use token::{
-    RouterToken, RouterTokenModel, RouterTokenRepository, RouterTokenValidationResult,
-    TokenRotationResult,
+    token_hash, RouterToken, RouterTokenModel, RouterTokenRepository, RouterTokenValidationResult,
+    TokenQuota, TokenRotationResult,
 };
+pub use virtual_model::{ParamOverride, VirtualModel, VirtualModelHop, VirtualModelModel};
 
 /// Result of [`RouterDatabase::validate_token_and_get_info`].
 ///
@@ -49,11 +64,34 @@ pub struct TokenValidationInfo {
     pub used_quota: i64,
     pub order_type: Option<String>,
     pub price_cap: Option<i64>,
+    /// Owning organization of the token (`router_tokens.org_id`), if any.
+    pub org_id: Option<String>,
+    /// Model policy JSON (`router_tokens.model_policy`), if any.
+    pub model_policy: Option<String>,
+    /// Rate limits stored on the `router_tokens` row.
+    pub token_limits: RateLimits,
+    /// Rate limits of the owning user (`user_accounts`).
+    pub user_limits: RateLimits,
 }
 
 /// Tuple shape of the SELECT inside [`RouterDatabase::validate_token_and_get_info`].
 /// Aliased so the row type does not trip `clippy::type_complexity`.
-type TokenValidationRow = (String, String, i64, i64, Option<String>, Option<i64>);
+type TokenValidationRow = (
+    String,
+    String,
+    i64,
+    i64,
+    Option<String>,
+    Option<i64>,
+    Option<String>,
+    Option<String>,
+    Option<i32>,
+    Option<i64>,
+    Option<i32>,
+    Option<i32>,
+    Option<i64>,
+    Option<i32>,
+);
 
 /// Router database operations
 pub struct RouterDatabase;
@@ -198,7 +236,9 @@ impl RouterDatabase {
         let query = format!(
             r#"
             SELECT u.id, u.{}, t.remain_quota, t.used_quota,
-                   rt.order_type, rt.price_cap_nanodollars
+                   rt.order_type, rt.price_cap_nanodollars, rt.org_id,
+                   rt.model_policy, rt.rpm_limit, rt.tpm_limit, rt.max_concurrency,
+                   u.rpm_limit, u.tpm_limit, u.max_concurrency
             FROM user_api_keys t
             JOIN user_accounts u ON t.user_id = u.id
             LEFT JOIN router_tokens rt ON rt.token = t.key
@@ -213,7 +253,22 @@ impl RouterDatabase {
             .await?;
 
         Ok(row.map(
-            |(user_id, group, remain_quota, used_quota, order_type, price_cap)| {
+            |(
+                user_id,
+                group,
+                remain_quota,
+                used_quota,
+                order_type,
+                price_cap,
+                org_id,
+                model_policy,
+                token_rpm,
+                token_tpm,
+                token_concurrency,
+                user_rpm,
+                user_tpm,
+                user_concurrency,
+            )| {
                 TokenValidationInfo {
                     user_id,
                     group,
@@ -221,6 +276,18 @@ impl RouterDatabase {
                     used_quota,
                     order_type,
                     price_cap,
+                    org_id,
+                    model_policy,
+                    token_limits: RateLimits {
+                        rpm_limit: token_rpm,
+                        tpm_limit: token_tpm,
+                        max_concurrency: token_concurrency,
+                    },
+                    user_limits: RateLimits {
+                        rpm_limit: user_rpm,
+                        tpm_limit: user_tpm,
+                        max_concurrency: user_concurrency,
+                    },
                 }
             },
         ))
@@ -245,10 +312,10 @@ impl RouterDatabase {
              cache_write_tokens, audio_input_tokens, audio_output_tokens, image_tokens, embedding_tokens,
              input_cost, output_cost, cache_read_cost, cache_write_cost,
              audio_cost, image_cost, video_cost, reasoning_cost, embedding_cost,
-             layer_decision, traffic_color, cost_status, error_type)
+             layer_decision, traffic_color, cost_status, error_type, token_hash, served_model, currency)
             VALUES ({})
             "#,
-            phs(is_postgres, 32)
+            phs(is_postgres, 35)
         );
 
         sqlx::query(&sql)
@@ -284,6 +351,9 @@ impl RouterDatabase {
             .bind(&log.traffic_color)
             .bind(&log.cost_status)
             .bind(&log.error_type)
+            .bind(&log.token_hash)
+            .bind(&log.served_model)
+            .bind(&log.currency)
             .execute(conn.pool())
             .await?;
 
@@ -328,6 +398,19 @@ impl RouterDatabase {
 
     // ============== Balance delegations ==============
 
+    pub async fn org_admission(db: &Database, org_id: &str) -> Result<OrgAdmission> {
+        OrgBillingModel::admission(db, org_id).await
+    }
+
+    pub async fn charge_org(
+        db: &Database,
+        org_id: &str,
+        cost_nano: i64,
+        usd_to_cny_nano: Option<i64>,
+    ) -> Result<bool> {
+        OrgBillingModel::charge(db, org_id, cost_nano, usd_to_cny_nano).await
+    }
+
     pub async fn deduct_usd(db: &Database, user_id: &str, cost_nano: i64) -> Result<bool> {
         BalanceModel::deduct_usd(db, user_id, cost_nano).await
     }
@@ -7,6 +7,8 @@ use burncloud_database::{adapt_sql, ph, phs, Database, DatabaseError, Result};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
+use crate::rollup::{RollupDimension, RollupGranularity, RollupQuery, UsageRollupModel};
+
 /// Router log entry
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct RouterLog {
@@ -78,6 +80,17 @@ pub struct RouterLog {
     // "router_reject", or NULL for successful requests.
     #[sqlx(default)]
     pub error_type: Option<String>,
+    // md5 of the bearer token (migration 0021), for per-token spend counters.
+    #[sqlx(default)]
+    pub token_hash: Option<String>,
+    // Concrete model that answered (migration 0025). Differs from `model`
+    // when a virtual model fell through its chain; equal otherwise.
+    #[sqlx(default)]
+    pub served_model: Option<String>,
+    // Currency of `cost` and the per-type costs (migration 0036), that of
+    // the price that billed the request. NULL means USD.
+    #[sqlx(default)]
+    pub currency: Option<String>,
     pub created_at: Option<String>,
 }
 
@@ -104,6 +117,163 @@ pub struct ModelUsageStats {
     pub cost_nano: i64,
 }
 
+/// Per-model spend of one token since the start of the current month and day
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TokenModelSpend {
+    /// Model that served, see [`RouterLog::served_model`]
+    pub model: String,
+    /// Spend since `month_start`, in nanodollars
+    pub monthly_nano: i64,
+    /// Spend since `day_start`, in nanodollars
+    pub daily_nano: i64,
+}
+
+/// Filters for [`RouterLogModel::search`]. Unset fields match every row.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LogFilter {
+    pub user_id: Option<String>,
+    /// md5 of the bearer token, see [`crate::token_hash`], or a prefix of it
+    pub token_hash: Option<String>,
+    pub model: Option<String>,
+    pub upstream_id: Option<String>,
+    pub status_code: Option<i32>,
+    pub error_type: Option<String>,
+    pub layer_decision: Option<String>,
+    pub traffic_color: Option<String>,
+    pub cost_status: Option<String>,
+    /// Inclusive lower bound on `created_at`, UTC `YYYY-MM-DD HH:MM:SS`
+    pub since: Option<String>,
+    /// Exclusive upper bound on `created_at`, UTC `YYYY-MM-DD HH:MM:SS`
+    pub until: Option<String>,
+    pub min_latency_ms: Option<i64>,
+    pub max_latency_ms: Option<i64>,
+    /// Minimum cost in nanodollars
+    pub min_cost: Option<i64>,
+    /// Maximum cost in nanodollars
+    pub max_cost: Option<i64>,
+    /// Substring of `request_id`, `path` or `model`
+    pub search: Option<String>,
+}
+
+/// A value bound to one placeholder of a [`LogFilter`] condition.
+enum FilterValue<'a> {
+    Text(&'a str),
+    Owned(String),
+    Int(i64),
+}
+
+/// Escapes the `LIKE` wildcards in `term` for use with `ESCAPE '\'`, so
+/// `%` and `_` match themselves.
+fn like_escape(term: &str) -> String {
+    let mut escaped = String::with_capacity(term.len());
+    for c in term.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+impl LogFilter {
+    /// SQL conditions for the set filters, with placeholders numbered from 1,
+    /// and the values to bind in order.
+    fn conditions(&self, is_postgres: bool) -> (Vec<String>, Vec<FilterValue<'_>>) {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+
+        let text_filters = [
+            ("user_id", &self.user_id),
+            ("model", &self.model),
+            ("upstream_id", &self.upstream_id),
+            ("error_type", &self.error_type),
+            ("layer_decision", &self.layer_decision),
+            ("traffic_color", &self.traffic_color),
+            ("cost_status", &self.cost_status),
+        ];
+        for (column, value) in text_filters {
+            if let Some(v) = value {
+                values.push(FilterValue::Text(v));
+                conditions.push(format!("{column} = {}", ph(is_postgres, values.len())));
+            }
+        }
+
+        if let Some(prefix) = &self.token_hash {
+            values.push(FilterValue::Owned(format!("{}%", like_escape(prefix))));
+            conditions.push(format!(
+                "token_hash LIKE {} ESCAPE '\\'",
+                ph(is_postgres, values.len())
+            ));
+        }
+
+        let int_filters = [
+            ("status_code", "=", self.status_code.map(i64::from)),
+            ("latency_ms", ">=", self.min_latency_ms),
+            ("latency_ms", "<=", self.max_latency_ms),
+            ("cost", ">=", self.min_cost),
+            ("cost", "<=", self.max_cost),
+        ];
+        for (column, op, value) in int_filters {
+            if let Some(v) = value {
+                values.push(FilterValue::Int(v));
+                conditions.push(format!("{column} {op} {}", ph(is_postgres, values.len())));
+            }
+        }
+
+        for (op, value) in [(">=", &self.since), ("<", &self.until)] {
+            if let Some(v) = value {
+                values.push(FilterValue::Text(v));
+                conditions.push(created_at_compare(is_postgres, op, values.len()));
+            }
+        }
+
+        if let Some(term) = &self.search {
+            let pattern = format!("%{}%", like_escape(term));
+            // SQLite `?` placeholders are positional, so bind the term per column
+            let matches: Vec<String> = ["request_id", "path", "model"]
+                .iter()
+                .map(|column| {
+                    values.push(FilterValue::Owned(pattern.clone()));
+                    format!(
+                        "{column} LIKE {} ESCAPE '\\'",
+                        ph(is_postgres, values.len())
+                    )
+                })
+                .collect();
+            conditions.push(format!("({})", matches.join(" OR ")));
+        }
+
+        (conditions, values)
+    }
+}
+
+pub(crate) const LOG_COLUMNS: &str = "id, request_id, user_id, path, upstream_id, status_code, latency_ms, prompt_tokens, completion_tokens, cost, model, cache_read_tokens, reasoning_tokens, pricing_region, video_tokens, cache_write_tokens, audio_input_tokens, audio_output_tokens, image_tokens, embedding_tokens, input_cost, output_cost, cache_read_cost, cache_write_cost, audio_cost, image_cost, video_cost, reasoning_cost, embedding_cost, layer_decision, traffic_color, cost_status, error_type, token_hash, served_model, currency";
+
+pub(crate) const REQUEST_LOG_COLUMNS: &str = "id, request_id, request_body, request_headers, response_body, response_status, stream_chunk_count, stream_first_chunk_latency_ms, stream_last_chunk_latency_ms, candidates, candidates_count, affinity_key, affinity_hit_channel_id, failover_history, storage_policy";
+
+/// `created_at` as text. The Any driver cannot decode TIMESTAMP columns
+/// (Postgres, and `router_request_logs` on SQLite).
+pub(crate) fn created_at_text(is_postgres: bool) -> &'static str {
+    if is_postgres {
+        "to_char(created_at, 'YYYY-MM-DD HH24:MI:SS') AS created_at"
+    } else {
+        "CAST(created_at AS TEXT) AS created_at"
+    }
+}
+
+/// `created_at {op} value`, with the UTC `YYYY-MM-DD HH:MM:SS` value bound
+/// at placeholder `index`.
+pub(crate) fn created_at_compare(is_postgres: bool, op: &str, index: usize) -> String {
+    if is_postgres {
+        format!(
+            "created_at {op} CAST({} AS TIMESTAMP)",
+            ph(is_postgres, index)
+        )
+    } else {
+        format!("created_at {op} {}", ph(is_postgres, index))
+    }
+}
+
 pub struct RouterLogModel;
 
 impl RouterLogModel {
@@ -121,10 +291,10 @@ impl RouterLogModel {
              cache_write_tokens, audio_input_tokens, audio_output_tokens, image_tokens, embedding_tokens,
              input_cost, output_cost, cache_read_cost, cache_write_cost,
              audio_cost, image_cost, video_cost, reasoning_cost, embedding_cost,
-             layer_decision, traffic_color, cost_status, error_type)
+             layer_decision, traffic_color, cost_status, error_type, token_hash, served_model, currency)
             VALUES ({})
             "#,
-            phs(is_postgres, 32)
+            phs(is_postgres, 35)
         );
 
         sqlx::query(&sql)
@@ -160,6 +330,9 @@ impl RouterLogModel {
             .bind(&log.traffic_color)
             .bind(&log.cost_status)
             .bind(&log.error_type)
+            .bind(&log.token_hash)
+            .bind(&log.served_model)
+            .bind(&log.currency)
             .execute(conn.pool())
             .await?;
 
@@ -263,6 +436,79 @@ impl RouterLogModel {
         Ok(logs)
     }
 
+    /// Logs matching `filter`, newest first.
+    ///
+    /// Pages are keyed on `id`: pass the last id of the previous page as
+    /// `before_id` to continue, which stays stable while new logs are
+    /// inserted. `offset` is applied after the cursor and is only meant for
+    /// page-number callers.
+    pub async fn search(
+        db: &Database,
+        filter: &LogFilter,
+        before_id: Option<i64>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<RouterLog>> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+
+        let (mut conditions, values) = filter.conditions(is_postgres);
+        let mut param_index = values.len() + 1;
+        if before_id.is_some() {
+            conditions.push(format!("id < {}", ph(is_postgres, param_index)));
+            param_index += 1;
+        }
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+
+        let sql = format!(
+            "SELECT {LOG_COLUMNS}, {} FROM router_logs {} ORDER BY id DESC LIMIT {} OFFSET {}",
+            created_at_text(is_postgres),
+            where_clause,
+            ph(is_postgres, param_index),
+            ph(is_postgres, param_index + 1)
+        );
+
+        let mut query = sqlx::query_as::<_, RouterLog>(&sql);
+        for value in values {
+            query = match value {
+                FilterValue::Text(v) => query.bind(v),
+                FilterValue::Owned(v) => query.bind(v),
+                FilterValue::Int(v) => query.bind(v),
+            };
+        }
+        if let Some(id) = before_id {
+            query = query.bind(id);
+        }
+
+        let logs = query
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(conn.pool())
+            .await?;
+        Ok(logs)
+    }
+
+    /// Get a single log by request_id
+    pub async fn get_by_request_id(db: &Database, request_id: &str) -> Result<Option<RouterLog>> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+
+        let sql = format!(
+            "SELECT {LOG_COLUMNS}, {} FROM router_logs WHERE request_id = {}",
+            created_at_text(is_postgres),
+            ph(is_postgres, 1)
+        );
+        let log = sqlx::query_as::<_, RouterLog>(&sql)
+            .bind(request_id)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(log)
+    }
+
     /// Get total usage by user
     pub async fn get_usage_by_user(db: &Database, user_id: &str) -> Result<(i64, i64)> {
         let conn = db.get_connection()?;
@@ -278,63 +524,90 @@ impl RouterLogModel {
 
         Ok((row.0.unwrap_or(0), row.1.unwrap_or(0)))
     }
-}
 
-/// Get aggregated usage statistics for a user over a time period
-/// Period can be: "day", "week", "month"
-pub async fn get_usage_stats(db: &Database, user_id: &str, period: &str) -> Result<UsageStats> {
-    let conn = db.get_connection()?;
-    let is_postgres = db.kind() == "postgres";
+    /// Per-model spend of a token since `month_start` (and `day_start`), both
+    /// unix seconds. `token_hashes` lists every hash the token has logged under
+    /// (the current key and, after a rotation, the previous one).
+    pub async fn token_model_spend(
+        db: &Database,
+        token_hashes: &[String],
+        month_start: i64,
+        day_start: i64,
+    ) -> Result<Vec<TokenModelSpend>> {
+        if token_hashes.is_empty() {
+            return Ok(Vec::new());
+        }
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let epoch = if is_postgres {
+            "EXTRACT(EPOCH FROM created_at)::BIGINT"
+        } else {
+            "CAST(strftime('%s', created_at) AS BIGINT)"
+        };
+        let hash_list = (0..token_hashes.len())
+            .map(|i| ph(is_postgres, i + 2))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let sql = format!(
+            r#"
+            SELECT COALESCE(served_model, model) AS model,
+                   CAST(COALESCE(SUM(cost), 0) AS BIGINT) AS monthly_nano,
+                   CAST(COALESCE(SUM(CASE WHEN {epoch} >= {day} THEN cost ELSE 0 END), 0) AS BIGINT) AS daily_nano
+            FROM router_logs
+            WHERE token_hash IN ({hash_list}) AND COALESCE(served_model, model) IS NOT NULL
+              AND created_at IS NOT NULL AND {epoch} >= {month}
+            GROUP BY COALESCE(served_model, model)
+            "#,
+            day = ph(is_postgres, 1),
+            month = ph(is_postgres, token_hashes.len() + 2),
+        );
 
-    // Calculate time threshold based on period
+        let mut query = sqlx::query_as::<_, TokenModelSpend>(&sql).bind(day_start);
+        for hash in token_hashes {
+            query = query.bind(hash);
+        }
+        let rows = query.bind(month_start).fetch_all(conn.pool()).await?;
+        Ok(rows)
+    }
+}
+
+/// Start of the rollup window for a usage period, with the granularity fine
+/// enough to cover it. Period can be: "day", "week", "month"
+fn usage_window(period: &str) -> Result<(RollupGranularity, i64)> {
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .map_err(|e| DatabaseError::Query(format!("Time error: {}", e)))?
         .as_secs() as i64;
 
-    let threshold = match period {
-        "day" => now - 24 * 60 * 60,
-        "week" => now - 7 * 24 * 60 * 60,
-        _ => now - 30 * 24 * 60 * 60, // Default to month for any other input
-    };
+    Ok(match period {
+        "day" => (RollupGranularity::Minute, now - 24 * 60 * 60),
+        "week" => (RollupGranularity::Hour, now - 7 * 24 * 60 * 60),
+        _ => (RollupGranularity::Hour, now - 30 * 24 * 60 * 60), // Default to month for any other input
+    })
+}
 
-    let time_filter = if is_postgres {
-        format!(
-            "EXTRACT(EPOCH FROM created_at)::BIGINT >= {}",
-            ph(is_postgres, 2)
-        )
-    } else {
-        format!(
-            "CAST(strftime('%s', created_at) AS BIGINT) >= {}",
-            ph(is_postgres, 2)
-        )
-    };
-
-    let sql = format!(
-        r#"
-        SELECT
-            COUNT(*) as total_requests,
-            COALESCE(SUM(prompt_tokens), 0) as total_prompt_tokens,
-            COALESCE(SUM(completion_tokens), 0) as total_completion_tokens,
-            COALESCE(SUM(cost), 0) as total_cost
-        FROM router_logs
-        WHERE user_id = {} AND created_at IS NOT NULL AND {}
-        "#,
-        ph(is_postgres, 1),
-        time_filter
-    );
-
-    let row: (Option<i64>, Option<i64>, Option<i64>, Option<i64>) = sqlx::query_as(&sql)
-        .bind(user_id)
-        .bind(threshold)
-        .fetch_one(conn.pool())
-        .await?;
+/// Get aggregated usage statistics for a user over a time period
+/// Period can be: "day", "week", "month"
+///
+/// Reads `usage_rollups`; the window starts at the bucket containing the
+/// period start.
+pub async fn get_usage_stats(db: &Database, user_id: &str, period: &str) -> Result<UsageStats> {
+    let (granularity, threshold) = usage_window(period)?;
+    let mut query = RollupQuery::new(granularity, granularity.bucket_start(threshold));
+    query.user_id = Some(user_id.to_string());
+
+    let totals = UsageRollupModel::query(db, &query)
+        .await?
+        .into_iter()
+        .next()
+        .map(|row| row.counters)
+        .unwrap_or_default();
 
     Ok(UsageStats {
-        total_requests: row.0.unwrap_or(0),
-        total_prompt_tokens: row.1.unwrap_or(0),
-        total_completion_tokens: row.2.unwrap_or(0),
-        total_cost_nano: row.3.unwrap_or(0),
+        total_requests: totals.requests,
+        total_prompt_tokens: totals.prompt_tokens,
+        total_completion_tokens: totals.completion_tokens,
+        total_cost_nano: totals.cost_nano,
     })
 }
 
@@ -345,76 +618,26 @@ pub async fn get_usage_stats_by_model(
     user_id: &str,
     period: &str,
 ) -> Result<Vec<ModelUsageStats>> {
-    let conn = db.get_connection()?;
-    let is_postgres = db.kind() == "postgres";
-
-    // Calculate time threshold — same logic as get_usage_stats
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map_err(|e| DatabaseError::Query(format!("Time error: {}", e)))?
-        .as_secs() as i64;
-
-    let threshold = match period {
-        "day" => now - 24 * 60 * 60,
-        "week" => now - 7 * 24 * 60 * 60,
-        _ => now - 30 * 24 * 60 * 60,
-    };
+    let (granularity, threshold) = usage_window(period)?;
+    let mut query = RollupQuery::new(granularity, granularity.bucket_start(threshold));
+    query.user_id = Some(user_id.to_string());
+    query.group_by = vec![RollupDimension::Model];
 
-    let time_filter = if is_postgres {
-        format!(
-            "EXTRACT(EPOCH FROM created_at)::BIGINT >= {}",
-            ph(is_postgres, 2)
-        )
-    } else {
-        "strftime('%s', created_at) >= CAST(? AS TEXT)".to_string()
-    };
-
-    let sql = format!(
-        r#"
-        SELECT
-            COALESCE(model, 'Unknown') as model,
-            COUNT(*) as requests,
-            COALESCE(SUM(prompt_tokens), 0) as prompt_tokens,
-            COALESCE(SUM(completion_tokens), 0) as completion_tokens,
-            COALESCE(SUM(cache_read_tokens), 0) as cache_read_tokens,
-            COALESCE(SUM(reasoning_tokens), 0) as reasoning_tokens,
-            COALESCE(SUM(cost), 0) as cost
-        FROM router_logs
-        WHERE user_id = {} AND created_at IS NOT NULL AND {}
-        GROUP BY model
-        ORDER BY cost DESC
-        "#,
-        ph(is_postgres, 1),
-        time_filter
-    );
-
-    let rows: Vec<(String, i64, i64, i64, i64, i64, i64)> = sqlx::query_as(&sql)
-        .bind(user_id)
-        .bind(threshold.to_string())
-        .fetch_all(conn.pool())
-        .await?;
-
-    Ok(rows
+    Ok(UsageRollupModel::query(db, &query)
+        .await?
         .into_iter()
-        .map(
-            |(
-                model,
-                requests,
-                prompt_tokens,
-                completion_tokens,
-                cache_read_tokens,
-                reasoning_tokens,
-                cost_nano,
-            )| ModelUsageStats {
-                model,
-                requests,
-                prompt_tokens,
-                completion_tokens,
-                cache_read_tokens,
-                reasoning_tokens,
-                cost_nano,
-            },
-        )
+        .map(|row| {
+            let model = row.model.filter(|m| !m.is_empty());
+            ModelUsageStats {
+                model: model.unwrap_or_else(|| "Unknown".to_string()),
+                requests: row.counters.requests,
+                prompt_tokens: row.counters.prompt_tokens,
+                completion_tokens: row.counters.completion_tokens,
+                cache_read_tokens: row.counters.cache_read_tokens,
+                reasoning_tokens: row.counters.reasoning_tokens,
+                cost_nano: row.counters.cost_nano,
+            }
+        })
         .collect())
 }
 
@@ -442,138 +665,57 @@ pub struct BillingSummary {
     pub total_cost_usd: f64,
 }
 
-/// Get aggregate billing summary grouped by model for internal reconciliation.
-///
-/// start/end are optional YYYY-MM-DD date strings.
-/// Pre-migration rows (model IS NULL) are counted separately.
-pub async fn get_billing_summary(
+/// Unix seconds at midnight UTC of a `YYYY-MM-DD` date.
+fn day_start(date: &str) -> Result<i64> {
+    chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|t| t.and_utc().timestamp())
+        .ok_or_else(|| {
+            DatabaseError::Query(format!("Invalid date '{}', expected YYYY-MM-DD", date))
+        })
+}
+
+/// Billing summary over day rollups, optionally for one user.
+async fn billing_summary(
     db: &Database,
+    user_id: Option<&str>,
     start: Option<&str>,
     end: Option<&str>,
 ) -> Result<BillingSummary> {
-    let conn = db.get_connection()?;
-    let is_postgres = db.kind() == "postgres";
-
-    // Build date filter clause
-    // SQLite: created_at is TEXT (CURRENT_TIMESTAMP → "YYYY-MM-DD HH:MM:SS")
-    //         use strftime to extract date portion for correct comparison
-    // PostgreSQL: created_at is TIMESTAMP, cast to date
-    let (date_filter, date_cast_start, date_cast_end) = match (start, end) {
-        (Some(_), Some(_)) if is_postgres => (
-            format!(
-                "AND created_at::date >= {}::date AND created_at::date <= {}::date",
-                ph(is_postgres, 1),
-                ph(is_postgres, 2)
-            ),
-            true,
-            true,
-        ),
-        (Some(_), None) if is_postgres => (
-            format!("AND created_at::date >= {}::date", ph(is_postgres, 1)),
-            true,
-            false,
-        ),
-        (None, Some(_)) if is_postgres => (
-            format!("AND created_at::date <= {}::date", ph(is_postgres, 1)),
-            false,
-            true,
-        ),
-        (Some(_), Some(_)) => (
-            format!(
-                "AND strftime('%Y-%m-%d', created_at) >= {} AND strftime('%Y-%m-%d', created_at) <= {}",
-                ph(is_postgres, 1),
-                ph(is_postgres, 2)
-            ),
-            true,
-            true,
-        ),
-        (Some(_), None) => (
-            format!(
-                "AND strftime('%Y-%m-%d', created_at) >= {}",
-                ph(is_postgres, 1)
-            ),
-            true,
-            false,
-        ),
-        (None, Some(_)) => (
-            format!(
-                "AND strftime('%Y-%m-%d', created_at) <= {}",
-                ph(is_postgres, 1)
-            ),
-            false,
-            true,
-        ),
-        (None, None) => (String::new(), false, false),
-    };
-
-    // Count pre-migration (NULL model) rows
-    let null_model_sql = format!(
-        "SELECT COUNT(*) FROM router_logs WHERE model IS NULL {}",
-        date_filter
-    );
-    let mut null_query = sqlx::query_scalar::<_, i64>(&null_model_sql);
-    if date_cast_start {
-        null_query = null_query.bind(start.unwrap_or(""));
-    }
-    if date_cast_end {
-        null_query = null_query.bind(end.unwrap_or(""));
-    }
-    let pre_migration_requests: i64 = null_query.fetch_one(conn.pool()).await?;
-
-    // Main query: GROUP BY model (exclude NULL model rows from model breakdown)
-    let main_sql = format!(
-        r#"
-        SELECT
+    let since = start.map(day_start).transpose()?.unwrap_or(0);
+    let mut query = RollupQuery::new(RollupGranularity::Day, since);
+    // end is inclusive: stop before the following day
+    query.until = end
+        .map(day_start)
+        .transpose()?
+        .map(|t| t + RollupGranularity::Day.seconds());
+    query.user_id = user_id.map(str::to_string);
+    query.group_by = vec![RollupDimension::Model];
+    let rows = UsageRollupModel::query(db, &query).await?;
+
+    let mut pre_migration_requests = 0;
+    let mut total_cost_nano: i64 = 0;
+    let mut models = Vec::with_capacity(rows.len());
+    for row in rows {
+        let c = row.counters;
+        // Pre-migration rows (model IS NULL) roll up under an empty model
+        let model = row.model.unwrap_or_default();
+        if model.is_empty() {
+            pre_migration_requests += c.requests;
+            continue;
+        }
+        total_cost_nano = total_cost_nano.saturating_add(c.cost_nano);
+        models.push(BillingModelSummary {
             model,
-            COUNT(*) as requests,
-            COALESCE(SUM(prompt_tokens), 0) as prompt_tokens,
-            COALESCE(SUM(cache_read_tokens), 0) as cache_read_tokens,
-            COALESCE(SUM(completion_tokens), 0) as completion_tokens,
-            COALESCE(SUM(reasoning_tokens), 0) as reasoning_tokens,
-            COALESCE(SUM(cost), 0) as cost_nano
-        FROM router_logs
-        WHERE model IS NOT NULL {}
-        GROUP BY model
-        ORDER BY cost_nano DESC
-        "#,
-        date_filter
-    );
-
-    let mut main_query = sqlx::query_as::<_, (String, i64, i64, i64, i64, i64, i64)>(&main_sql);
-    if date_cast_start {
-        main_query = main_query.bind(start.unwrap_or(""));
+            requests: c.requests,
+            prompt_tokens: c.prompt_tokens,
+            cache_read_tokens: c.cache_read_tokens,
+            completion_tokens: c.completion_tokens,
+            reasoning_tokens: c.reasoning_tokens,
+            cost_usd: c.cost_nano as f64 / 1_000_000_000.0,
+        });
     }
-    if date_cast_end {
-        main_query = main_query.bind(end.unwrap_or(""));
-    }
-    let rows = main_query.fetch_all(conn.pool()).await?;
-
-    let mut total_cost_nano: i64 = 0;
-    let models: Vec<BillingModelSummary> = rows
-        .into_iter()
-        .map(
-            |(
-                model,
-                requests,
-                prompt_tokens,
-                cache_read_tokens,
-                completion_tokens,
-                reasoning_tokens,
-                cost_nano,
-            )| {
-                total_cost_nano = total_cost_nano.saturating_add(cost_nano);
-                BillingModelSummary {
-                    model,
-                    requests,
-                    prompt_tokens,
-                    cache_read_tokens,
-                    completion_tokens,
-                    reasoning_tokens,
-                    cost_usd: cost_nano as f64 / 1_000_000_000.0,
-                }
-            },
-        )
-        .collect();
 
     Ok(BillingSummary {
         period_start: start.map(|s| s.to_string()),
@@ -584,6 +726,18 @@ pub async fn get_billing_summary(
     })
 }
 
+/// Get aggregate billing summary grouped by model for internal reconciliation.
+///
+/// start/end are optional YYYY-MM-DD date strings (UTC days, both inclusive).
+/// Pre-migration rows (model IS NULL) are counted separately.
+pub async fn get_billing_summary(
+    db: &Database,
+    start: Option<&str>,
+    end: Option<&str>,
+) -> Result<BillingSummary> {
+    billing_summary(db, None, start, end).await
+}
+
 /// Get per-user billing summary grouped by model.
 /// Mirrors `get_billing_summary` but filters by `user_id`.
 pub async fn get_billing_summary_for_user(
@@ -592,136 +746,7 @@ pub async fn get_billing_summary_for_user(
     start: Option<&str>,
     end: Option<&str>,
 ) -> Result<BillingSummary> {
-    let conn = db.get_connection()?;
-    let is_postgres = db.kind() == "postgres";
-
-    // Build date filter clause (same pattern as get_billing_summary)
-    let (date_filter, date_cast_start, date_cast_end) = match (start, end) {
-        (Some(_), Some(_)) if is_postgres => (
-            format!(
-                "AND created_at::date >= {}::date AND created_at::date <= {}::date",
-                ph(is_postgres, 2),
-                ph(is_postgres, 3)
-            ),
-            true,
-            true,
-        ),
-        (Some(_), None) if is_postgres => (
-            format!("AND created_at::date >= {}::date", ph(is_postgres, 2)),
-            true,
-            false,
-        ),
-        (None, Some(_)) if is_postgres => (
-            format!("AND created_at::date <= {}::date", ph(is_postgres, 2)),
-            false,
-            true,
-        ),
-        (Some(_), Some(_)) => (
-            format!(
-                "AND strftime('%Y-%m-%d', created_at) >= {} AND strftime('%Y-%m-%d', created_at) <= {}",
-                ph(is_postgres, 2),
-                ph(is_postgres, 3)
-            ),
-            true,
-            true,
-        ),
-        (Some(_), None) => (
-            format!(
-                "AND strftime('%Y-%m-%d', created_at) >= {}",
-                ph(is_postgres, 2)
-            ),
-            true,
-            false,
-        ),
-        (None, Some(_)) => (
-            format!(
-                "AND strftime('%Y-%m-%d', created_at) <= {}",
-                ph(is_postgres, 2)
-            ),
-            false,
-            true,
-        ),
-        (None, None) => (String::new(), false, false),
-    };
-
-    // Count pre-migration (NULL model) rows for this user
-    let null_model_sql = format!(
-        "SELECT COUNT(*) FROM router_logs WHERE model IS NULL AND user_id = {} {}",
-        ph(is_postgres, 1),
-        date_filter
-    );
-    let mut null_query = sqlx::query_scalar::<_, i64>(&null_model_sql).bind(user_id);
-    if date_cast_start {
-        null_query = null_query.bind(start.unwrap_or(""));
-    }
-    if date_cast_end {
-        null_query = null_query.bind(end.unwrap_or(""));
-    }
-    let pre_migration_requests = null_query.fetch_one(conn.pool()).await?;
-
-    // Per-model aggregation for this user
-    let model_sql = format!(
-        r#"
-        SELECT
-            model,
-            COUNT(*) as requests,
-            COALESCE(SUM(prompt_tokens), 0) as prompt_tokens,
-            COALESCE(SUM(cache_read_tokens), 0) as cache_read_tokens,
-            COALESCE(SUM(completion_tokens), 0) as completion_tokens,
-            COALESCE(SUM(reasoning_tokens), 0) as reasoning_tokens,
-            COALESCE(SUM(cost), 0) as cost_nano
-        FROM router_logs
-        WHERE model IS NOT NULL AND user_id = {} {}
-        GROUP BY model
-        ORDER BY cost_nano DESC
-        "#,
-        ph(is_postgres, 1),
-        date_filter
-    );
-    let mut model_query =
-        sqlx::query_as::<_, (String, i64, i64, i64, i64, i64, i64)>(&model_sql).bind(user_id);
-    if date_cast_start {
-        model_query = model_query.bind(start.unwrap_or(""));
-    }
-    if date_cast_end {
-        model_query = model_query.bind(end.unwrap_or(""));
-    }
-    let rows = model_query.fetch_all(conn.pool()).await?;
-
-    let mut total_cost_nano: i64 = 0;
-    let models: Vec<BillingModelSummary> = rows
-        .into_iter()
-        .map(
-            |(
-                model,
-                requests,
-                prompt_tokens,
-                cache_read_tokens,
-                completion_tokens,
-                reasoning_tokens,
-                cost_nano,
-            )| {
-                total_cost_nano = total_cost_nano.saturating_add(cost_nano);
-                BillingModelSummary {
-                    model,
-                    requests,
-                    prompt_tokens,
-                    cache_read_tokens,
-                    completion_tokens,
-                    reasoning_tokens,
-                    cost_usd: cost_nano as f64 / 1_000_000_000.0,
-                }
-            },
-        )
-        .collect();
-
-    Ok(BillingSummary {
-        period_start: start.map(|s| s.to_string()),
-        period_end: end.map(|s| s.to_string()),
-        pre_migration_requests,
-        total_cost_usd: total_cost_nano as f64 / 1_000_000_000.0,
-        models,
-    })
+    billing_summary(db, Some(user_id), start, end).await
 }
 
 /// Storage policy for request logs (controls verbosity).
@@ -822,6 +847,67 @@ pub struct CandidateInfo {
     pub name: String,
     pub protocol: String,
     pub priority: i32,
+    /// Virtual-model hop this candidate serves, when the request named one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+/// `router_request_logs` row with its boolean flags read as integers
+/// (SQLite stores them as INTEGER, which the Any driver won't decode as bool).
+#[derive(FromRow)]
+pub(crate) struct RequestLogRow {
+    id: i64,
+    request_id: String,
+    request_body: Option<String>,
+    request_body_truncated: i64,
+    request_headers: Option<String>,
+    response_body: Option<String>,
+    response_body_truncated: i64,
+    response_status: Option<i32>,
+    stream_chunk_count: i32,
+    stream_first_chunk_latency_ms: Option<i64>,
+    stream_last_chunk_latency_ms: Option<i64>,
+    candidates: Option<String>,
+    candidates_count: i32,
+    affinity_key: Option<String>,
+    affinity_hit_channel_id: Option<i32>,
+    failover_history: Option<String>,
+    storage_policy: String,
+    created_at: Option<String>,
+}
+
+impl From<RequestLogRow> for RouterRequestLog {
+    fn from(row: RequestLogRow) -> Self {
+        Self {
+            id: row.id,
+            request_id: row.request_id,
+            request_body: row.request_body,
+            request_body_truncated: row.request_body_truncated != 0,
+            request_headers: row.request_headers,
+            response_body: row.response_body,
+            response_body_truncated: row.response_body_truncated != 0,
+            response_status: row.response_status,
+            stream_chunk_count: row.stream_chunk_count,
+            stream_first_chunk_latency_ms: row.stream_first_chunk_latency_ms,
+            stream_last_chunk_latency_ms: row.stream_last_chunk_latency_ms,
+            candidates: row.candidates,
+            candidates_count: row.candidates_count,
+            affinity_key: row.affinity_key,
+            affinity_hit_channel_id: row.affinity_hit_channel_id,
+            failover_history: row.failover_history,
+            storage_policy: row.storage_policy,
+            created_at: row.created_at,
+        }
+    }
+}
+
+/// Boolean `column` of `router_request_logs` as a 0/1 integer.
+pub(crate) fn flag_as_int(is_postgres: bool, column: &str) -> String {
+    if is_postgres {
+        format!("CAST(CASE WHEN {column} THEN 1 ELSE 0 END AS BIGINT) AS {column}")
+    } else {
+        column.to_string()
+    }
 }
 
 pub struct RouterRequestLogModel;
@@ -874,16 +960,19 @@ impl RouterRequestLogModel {
         let is_postgres = db.kind() == "postgres";
 
         let sql = format!(
-            "SELECT * FROM router_request_logs WHERE request_id = {}",
+            "SELECT {REQUEST_LOG_COLUMNS}, {}, {}, {} FROM router_request_logs WHERE request_id = {}",
+            flag_as_int(is_postgres, "request_body_truncated"),
+            flag_as_int(is_postgres, "response_body_truncated"),
+            created_at_text(is_postgres),
             ph(is_postgres, 1)
         );
 
-        let log = sqlx::query_as::<_, RouterRequestLog>(&sql)
+        let row = sqlx::query_as::<_, RequestLogRow>(&sql)
             .bind(request_id)
             .fetch_optional(conn.pool())
             .await?;
 
-        Ok(log)
+        Ok(row.map(RouterRequestLog::from))
     }
 
     /// Delete request logs older than a threshold (for cleanup)
@@ -0,0 +1,164 @@
+//! Organization wallet admission and settlement.
+//!
+//! Tokens with `router_tokens.org_id` set spend from the owning organization's
+//! shared wallet (`user_organizations`) instead of the creator's account.
+//! Admission is a cheap pre-check; settlement happens after the upstream
+//! response is priced, mirroring per-token quota settlement.
+
+use burncloud_database::{adapt_sql, Database, Result};
+
+/// Outcome of [`OrgBillingModel::admission`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrgAdmission {
+    Allowed,
+    /// Organization does not exist.
+    NotFound,
+    /// Organization has been disabled.
+    Disabled,
+    /// Both USD and CNY balances are exhausted.
+    InsufficientBalance,
+    /// Settled spend has reached the organization's spend limit.
+    SpendLimitReached,
+}
+
+pub struct OrgBillingModel;
+
+impl OrgBillingModel {
+    /// Check whether an org-owned token may start a new request.
+    pub async fn admission(db: &Database, org_id: &str) -> Result<OrgAdmission> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "SELECT balance_usd, balance_cny, spend_limit, spent, status FROM user_organizations WHERE id = ?",
+        );
+        let row: Option<(i64, i64, i64, i64, i32)> = sqlx::query_as(&sql)
+            .bind(org_id)
+            .fetch_optional(conn.pool())
+            .await?;
+
+        let Some((balance_usd, balance_cny, spend_limit, spent, status)) = row else {
+            return Ok(OrgAdmission::NotFound);
+        };
+        if status != 1 {
+            return Ok(OrgAdmission::Disabled);
+        }
+        if spend_limit >= 0 && spent >= spend_limit {
+            return Ok(OrgAdmission::SpendLimitReached);
+        }
+        if balance_usd <= 0 && balance_cny <= 0 {
+            return Ok(OrgAdmission::InsufficientBalance);
+        }
+        Ok(OrgAdmission::Allowed)
+    }
+
+    /// Charge a settled cost (USD nanodollars) to the organization's wallet.
+    ///
+    /// USD is spent first; the remainder is converted to CNY with
+    /// `usd_to_cny_nano` (rate scaled by 10^9). The request has already been
+    /// served, so a shortfall is recorded as a negative USD balance, which
+    /// blocks further admission until the wallet is topped up.
+    ///
+    /// Concurrent settlements for the same org never overwrite each other:
+    /// the common case is a single conditional USD debit, and the CNY
+    /// fallback only applies if the balances it was computed from are still
+    /// current, retrying otherwise.
+    ///
+    /// Returns `Ok(false)` when the charge overdrew the wallet.
+    pub async fn charge(
+        db: &Database,
+        org_id: &str,
+        cost_nano: i64,
+        usd_to_cny_nano: Option<i64>,
+    ) -> Result<bool> {
+        if cost_nano <= 0 {
+            return Ok(true);
+        }
+
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+
+        let usd_sql = adapt_sql(
+            is_postgres,
+            "UPDATE user_organizations SET balance_usd = balance_usd - ?, spent = spent + ? WHERE id = ? AND balance_usd >= ?",
+        );
+        let rows = sqlx::query(&usd_sql)
+            .bind(cost_nano)
+            .bind(cost_nano)
+            .bind(org_id)
+            .bind(cost_nano)
+            .execute(conn.pool())
+            .await?
+            .rows_affected();
+        if rows == 1 {
+            return Ok(true);
+        }
+
+        let balances_sql = adapt_sql(
+            is_postgres,
+            "SELECT balance_usd, balance_cny FROM user_organizations WHERE id = ?",
+        );
+        let split_sql = adapt_sql(
+            is_postgres,
+            "UPDATE user_organizations SET balance_usd = balance_usd - ?, balance_cny = balance_cny - ?, spent = spent + ? \
+             WHERE id = ? AND balance_usd = ? AND balance_cny = ?",
+        );
+        loop {
+            let Some((balance_usd, balance_cny)) = sqlx::query_as::<_, (i64, i64)>(&balances_sql)
+                .bind(org_id)
+                .fetch_optional(conn.pool())
+                .await?
+            else {
+                return Ok(false);
+            };
+
+            let (usd_delta, cny_delta, covered) = split_charge(
+                cost_nano,
+                balance_usd,
+                balance_cny,
+                usd_to_cny_nano.filter(|rate| *rate > 0),
+            );
+
+            let rows = sqlx::query(&split_sql)
+                .bind(usd_delta)
+                .bind(cny_delta)
+                .bind(cost_nano)
+                .bind(org_id)
+                .bind(balance_usd)
+                .bind(balance_cny)
+                .execute(conn.pool())
+                .await?
+                .rows_affected();
+            if rows == 1 {
+                return Ok(covered);
+            }
+            // Another settlement moved the balances since they were read.
+        }
+    }
+}
+
+/// Split a USD cost into (usd_deduction, cny_deduction, fully_covered).
+fn split_charge(
+    cost_nano: i64,
+    balance_usd: i64,
+    balance_cny: i64,
+    usd_to_cny_nano: Option<i64>,
+) -> (i64, i64, bool) {
+    let usd_available = balance_usd.max(0);
+    if usd_available >= cost_nano {
+        return (cost_nano, 0, true);
+    }
+
+    let Some(rate) = usd_to_cny_nano else {
+        return (cost_nano, 0, false);
+    };
+    let remaining_usd = cost_nano - usd_available;
+    let required_cny = (remaining_usd as i128 * rate as i128 / 1_000_000_000) as i64;
+    let cny_available = balance_cny.max(0);
+    if cny_available >= required_cny {
+        return (usd_available, required_cny, true);
+    }
+
+    // Spend all CNY, carry the unconverted shortfall as USD debt.
+    let cny_in_usd = (cny_available as i128 * 1_000_000_000 / rate as i128) as i64;
+    (cost_nano - cny_in_usd, cny_available, false)
+}
@@ -0,0 +1,273 @@
+//! Batch queries backing log retention: select expired rows, delete them
+//! after they have been archived, and re-insert archived rows on restore.
+//!
+//! Cutoffs are UTC timestamps formatted as `YYYY-MM-DD HH:MM:SS`, the format
+//! SQLite's `CURRENT_TIMESTAMP` writes, so the comparison can use the
+//! `created_at` indexes on both backends.
+
+use burncloud_database::{ph, phs, Database, Result};
+
+use crate::log::{
+    created_at_compare, created_at_text, flag_as_int, RequestLogRow, RouterLog, RouterRequestLog,
+    LOG_COLUMNS, REQUEST_LOG_COLUMNS,
+};
+
+/// Rows per `DELETE ... WHERE id IN (...)` statement.
+const DELETE_CHUNK: usize = 500;
+
+/// `created_at < cutoff` with the cutoff bound at placeholder `index`.
+fn before_cutoff(is_postgres: bool, index: usize) -> String {
+    created_at_compare(is_postgres, "<", index)
+}
+
+pub struct LogRetentionModel;
+
+impl LogRetentionModel {
+    /// Up to `limit` router logs created before `cutoff` with `id > after_id`,
+    /// ordered by id.
+    pub async fn expired_logs(
+        db: &Database,
+        cutoff: &str,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Vec<RouterLog>> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let sql = format!(
+            "SELECT {LOG_COLUMNS}, {} FROM router_logs WHERE {} AND id > {} ORDER BY id LIMIT {}",
+            created_at_text(is_postgres),
+            before_cutoff(is_postgres, 1),
+            ph(is_postgres, 2),
+            ph(is_postgres, 3)
+        );
+        let rows = sqlx::query_as::<_, RouterLog>(&sql)
+            .bind(cutoff)
+            .bind(after_id)
+            .bind(limit)
+            .fetch_all(conn.pool())
+            .await?;
+        Ok(rows)
+    }
+
+    /// Up to `limit` request/response bodies created before `cutoff` with
+    /// `id > after_id`, ordered by id.
+    pub async fn expired_request_logs(
+        db: &Database,
+        cutoff: &str,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Vec<RouterRequestLog>> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let sql = format!(
+            "SELECT {REQUEST_LOG_COLUMNS}, {}, {}, {} FROM router_request_logs WHERE {} AND id > {} ORDER BY id LIMIT {}",
+            flag_as_int(is_postgres, "request_body_truncated"),
+            flag_as_int(is_postgres, "response_body_truncated"),
+            created_at_text(is_postgres),
+            before_cutoff(is_postgres, 1),
+            ph(is_postgres, 2),
+            ph(is_postgres, 3)
+        );
+        let rows = sqlx::query_as::<_, RequestLogRow>(&sql)
+            .bind(cutoff)
+            .bind(after_id)
+            .bind(limit)
+            .fetch_all(conn.pool())
+            .await?;
+        Ok(rows.into_iter().map(RouterRequestLog::from).collect())
+    }
+
+    /// Number of router logs created before `cutoff`.
+    pub async fn count_expired_logs(db: &Database, cutoff: &str) -> Result<i64> {
+        Self::count_before(db, "router_logs", cutoff).await
+    }
+
+    /// Number of request/response bodies created before `cutoff`.
+    pub async fn count_expired_request_logs(db: &Database, cutoff: &str) -> Result<i64> {
+        Self::count_before(db, "router_request_logs", cutoff).await
+    }
+
+    async fn count_before(db: &Database, table: &str, cutoff: &str) -> Result<i64> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let sql = format!(
+            "SELECT COUNT(*) FROM {table} WHERE {}",
+            before_cutoff(is_postgres, 1)
+        );
+        let count: i64 = sqlx::query_scalar(&sql)
+            .bind(cutoff)
+            .fetch_one(conn.pool())
+            .await?;
+        Ok(count)
+    }
+
+    /// Delete router logs by id. Returns the number of rows removed.
+    pub async fn delete_logs(db: &Database, ids: &[i64]) -> Result<u64> {
+        Self::delete_ids(db, "router_logs", ids).await
+    }
+
+    /// Delete request/response bodies by id. Returns the number of rows removed.
+    pub async fn delete_request_logs(db: &Database, ids: &[i64]) -> Result<u64> {
+        Self::delete_ids(db, "router_request_logs", ids).await
+    }
+
+    async fn delete_ids(db: &Database, table: &str, ids: &[i64]) -> Result<u64> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let mut deleted = 0;
+        for chunk in ids.chunks(DELETE_CHUNK) {
+            let sql = format!(
+                "DELETE FROM {table} WHERE id IN ({})",
+                phs(is_postgres, chunk.len())
+            );
+            let mut query = sqlx::query(&sql);
+            for id in chunk {
+                query = query.bind(*id);
+            }
+            deleted += query.execute(conn.pool()).await?.rows_affected();
+        }
+        Ok(deleted)
+    }
+
+    /// Re-insert an archived router log, keeping its `created_at`.
+    ///
+    /// Returns `false` when a log with the same `request_id` already exists.
+    /// Unlike [`crate::RouterLogModel::insert`] this never touches token quota.
+    pub async fn restore_log(db: &Database, log: &RouterLog) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        if Self::exists(db, "router_logs", &log.request_id).await? {
+            return Ok(false);
+        }
+
+        let sql = format!(
+            r#"
+            INSERT INTO router_logs
+            (request_id, user_id, path, upstream_id, status_code, latency_ms,
+             prompt_tokens, completion_tokens, cost,
+             model, cache_read_tokens, reasoning_tokens, pricing_region, video_tokens,
+             cache_write_tokens, audio_input_tokens, audio_output_tokens, image_tokens, embedding_tokens,
+             input_cost, output_cost, cache_read_cost, cache_write_cost,
+             audio_cost, image_cost, video_cost, reasoning_cost, embedding_cost,
+             layer_decision, traffic_color, cost_status, error_type, token_hash, served_model, currency, created_at)
+            VALUES ({}, {})
+            "#,
+            phs(is_postgres, 35),
+            Self::created_at_value(is_postgres, 36)
+        );
+
+        sqlx::query(&sql)
+            .bind(&log.request_id)
+            .bind(&log.user_id)
+            .bind(&log.path)
+            .bind(&log.upstream_id)
+            .bind(log.status_code)
+            .bind(log.latency_ms)
+            .bind(log.prompt_tokens)
+            .bind(log.completion_tokens)
+            .bind(log.cost)
+            .bind(&log.model)
+            .bind(log.cache_read_tokens)
+            .bind(log.reasoning_tokens)
+            .bind(&log.pricing_region)
+            .bind(log.video_tokens)
+            .bind(log.cache_write_tokens)
+            .bind(log.audio_input_tokens)
+            .bind(log.audio_output_tokens)
+            .bind(log.image_tokens)
+            .bind(log.embedding_tokens)
+            .bind(log.input_cost)
+            .bind(log.output_cost)
+            .bind(log.cache_read_cost)
+            .bind(log.cache_write_cost)
+            .bind(log.audio_cost)
+            .bind(log.image_cost)
+            .bind(log.video_cost)
+            .bind(log.reasoning_cost)
+            .bind(log.embedding_cost)
+            .bind(&log.layer_decision)
+            .bind(&log.traffic_color)
+            .bind(&log.cost_status)
+            .bind(&log.error_type)
+            .bind(&log.token_hash)
+            .bind(&log.served_model)
+            .bind(&log.currency)
+            .bind(&log.created_at)
+            .execute(conn.pool())
+            .await?;
+        Ok(true)
+    }
+
+    /// Re-insert archived request/response bodies, keeping `created_at`.
+    ///
+    /// Returns `false` when bodies for the same `request_id` already exist.
+    pub async fn restore_request_log(db: &Database, log: &RouterRequestLog) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        if Self::exists(db, "router_request_logs", &log.request_id).await? {
+            return Ok(false);
+        }
+
+        let sql = format!(
+            r#"
+            INSERT INTO router_request_logs
+            (request_id, request_body, request_body_truncated, request_headers,
+             response_body, response_body_truncated, response_status,
+             stream_chunk_count, stream_first_chunk_latency_ms, stream_last_chunk_latency_ms,
+             candidates, candidates_count, affinity_key, affinity_hit_channel_id,
+             failover_history, storage_policy, created_at)
+            VALUES ({}, {})
+            "#,
+            phs(is_postgres, 16),
+            Self::created_at_value(is_postgres, 17)
+        );
+
+        sqlx::query(&sql)
+            .bind(&log.request_id)
+            .bind(&log.request_body)
+            .bind(log.request_body_truncated)
+            .bind(&log.request_headers)
+            .bind(&log.response_body)
+            .bind(log.response_body_truncated)
+            .bind(log.response_status)
+            .bind(log.stream_chunk_count)
+            .bind(log.stream_first_chunk_latency_ms)
+            .bind(log.stream_last_chunk_latency_ms)
+            .bind(&log.candidates)
+            .bind(log.candidates_count)
+            .bind(&log.affinity_key)
+            .bind(log.affinity_hit_channel_id)
+            .bind(&log.failover_history)
+            .bind(&log.storage_policy)
+            .bind(&log.created_at)
+            .execute(conn.pool())
+            .await?;
+        Ok(true)
+    }
+
+    /// Archived `created_at`; a missing value falls back to the current time.
+    fn created_at_value(is_postgres: bool, index: usize) -> String {
+        if is_postgres {
+            format!(
+                "COALESCE(CAST({} AS TIMESTAMP), CURRENT_TIMESTAMP)",
+                ph(is_postgres, index)
+            )
+        } else {
+            format!("COALESCE({}, CURRENT_TIMESTAMP)", ph(is_postgres, index))
+        }
+    }
+
+    async fn exists(db: &Database, table: &str, request_id: &str) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let sql = format!(
+            "SELECT COUNT(*) FROM {table} WHERE request_id = {}",
+            ph(is_postgres, 1)
+        );
+        let count: i64 = sqlx::query_scalar(&sql)
+            .bind(request_id)
+            .fetch_one(conn.pool())
+            .await?;
+        Ok(count > 0)
+    }
+}
@@ -0,0 +1,731 @@
+//! Time-series usage rollups: minute, hour and day aggregates of
+//! `router_logs` per model, channel, user, group and currency.
+//!
+//! Counters are additive. The logging task upserts one delta per logged
+//! request ([`UsageRollupModel::record_log`]) and
+//! [`UsageRollupModel::rebuild`] re-aggregates a range from `router_logs`.
+//! Bucket starts are UTC unix seconds; empty strings stand for a missing
+//! model, channel, user or group.
+
+use std::collections::HashMap;
+
+use burncloud_database::{ph, phs, Database, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Row};
+
+use crate::log::{created_at_compare, created_at_text, RouterLog, LOG_COLUMNS};
+
+/// Rows per batch when rebuilding from `router_logs`.
+const REBUILD_BATCH: i64 = 1000;
+
+/// Currency of `router_logs.cost` when the row records none.
+pub const LOG_CURRENCY: &str = "USD";
+
+/// Upper bounds (ms) of the latency histogram buckets. A final bucket
+/// counts everything slower.
+pub const LATENCY_BOUNDS_MS: [i64; 9] =
+    [100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000, 60_000];
+
+const LATENCY_BUCKETS: usize = LATENCY_BOUNDS_MS.len() + 1;
+
+const KEY_COLUMNS: [&str; 7] = [
+    "granularity",
+    "bucket_start",
+    "model",
+    "upstream_id",
+    "user_id",
+    "group_name",
+    "currency",
+];
+
+/// Counter columns, in [`RollupCounters::to_array`] order.
+const COUNTER_COLUMNS: [&str; 30] = [
+    "requests",
+    "failed_requests",
+    "prompt_tokens",
+    "completion_tokens",
+    "cache_read_tokens",
+    "cache_write_tokens",
+    "reasoning_tokens",
+    "audio_input_tokens",
+    "audio_output_tokens",
+    "image_tokens",
+    "video_tokens",
+    "embedding_tokens",
+    "cost_nano",
+    "errors_upstream",
+    "errors_timeout",
+    "errors_auth",
+    "errors_rate_limit",
+    "errors_router_reject",
+    "errors_other",
+    "latency_sum_ms",
+    "latency_le_100",
+    "latency_le_250",
+    "latency_le_500",
+    "latency_le_1000",
+    "latency_le_2500",
+    "latency_le_5000",
+    "latency_le_10000",
+    "latency_le_30000",
+    "latency_le_60000",
+    "latency_inf",
+];
+
+/// Bucket width of a rollup row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RollupGranularity {
+    Minute,
+    Hour,
+    Day,
+}
+
+impl RollupGranularity {
+    pub const ALL: [Self; 3] = [Self::Minute, Self::Hour, Self::Day];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Minute => "minute",
+            Self::Hour => "hour",
+            Self::Day => "day",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "minute" => Some(Self::Minute),
+            "hour" => Some(Self::Hour),
+            "day" => Some(Self::Day),
+            _ => None,
+        }
+    }
+
+    /// Bucket width in seconds.
+    pub fn seconds(&self) -> i64 {
+        match self {
+            Self::Minute => 60,
+            Self::Hour => 3_600,
+            Self::Day => 86_400,
+        }
+    }
+
+    /// Start of the bucket containing unix time `ts`.
+    pub fn bucket_start(&self, ts: i64) -> i64 {
+        ts - ts.rem_euclid(self.seconds())
+    }
+}
+
+/// Identity of one rollup row.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RollupKey {
+    pub granularity: RollupGranularity,
+    pub bucket_start: i64,
+    pub model: String,
+    pub upstream_id: String,
+    pub user_id: String,
+    pub group_name: String,
+    pub currency: String,
+}
+
+/// Additive counters of a rollup row.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RollupCounters {
+    pub requests: i64,
+    /// Requests answered with status >= 400
+    pub failed_requests: i64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cache_write_tokens: i64,
+    pub reasoning_tokens: i64,
+    pub audio_input_tokens: i64,
+    pub audio_output_tokens: i64,
+    pub image_tokens: i64,
+    pub video_tokens: i64,
+    pub embedding_tokens: i64,
+    /// Cost in nanounits of the row's currency
+    pub cost_nano: i64,
+    // Error classes, from router_logs.error_type
+    pub errors_upstream: i64,
+    pub errors_timeout: i64,
+    pub errors_auth: i64,
+    pub errors_rate_limit: i64,
+    pub errors_router_reject: i64,
+    pub errors_other: i64,
+    pub latency_sum_ms: i64,
+    /// Requests per [`LATENCY_BOUNDS_MS`] bucket, then the overflow bucket
+    pub latency_histogram: [i64; LATENCY_BUCKETS],
+}
+
+impl RollupCounters {
+    /// Add one logged request.
+    pub fn record(&mut self, log: &RouterLog) {
+        self.requests += 1;
+        if log.status_code >= 400 {
+            self.failed_requests += 1;
+        }
+        self.prompt_tokens += i64::from(log.prompt_tokens);
+        self.completion_tokens += i64::from(log.completion_tokens);
+        self.cache_read_tokens += i64::from(log.cache_read_tokens);
+        self.cache_write_tokens += i64::from(log.cache_write_tokens);
+        self.reasoning_tokens += i64::from(log.reasoning_tokens);
+        self.audio_input_tokens += i64::from(log.audio_input_tokens);
+        self.audio_output_tokens += i64::from(log.audio_output_tokens);
+        self.image_tokens += i64::from(log.image_tokens);
+        self.video_tokens += i64::from(log.video_tokens);
+        self.embedding_tokens += i64::from(log.embedding_tokens);
+        self.cost_nano = self.cost_nano.saturating_add(log.cost);
+        match log.error_type.as_deref() {
+            None => {}
+            Some("upstream_error") => self.errors_upstream += 1,
+            Some("timeout") => self.errors_timeout += 1,
+            Some("auth_failed") => self.errors_auth += 1,
+            Some("rate_limit") => self.errors_rate_limit += 1,
+            Some("router_reject") => self.errors_router_reject += 1,
+            Some(_) => self.errors_other += 1,
+        }
+        self.latency_sum_ms += log.latency_ms;
+        let bucket = LATENCY_BOUNDS_MS
+            .iter()
+            .position(|bound| log.latency_ms <= *bound)
+            .unwrap_or(LATENCY_BOUNDS_MS.len());
+        self.latency_histogram[bucket] += 1;
+    }
+
+    /// Add another row's counters.
+    pub fn merge(&mut self, other: &Self) {
+        let mut sum = self.to_array();
+        for (total, value) in sum.iter_mut().zip(other.to_array()) {
+            *total = total.saturating_add(value);
+        }
+        *self = Self::from_array(sum);
+    }
+
+    pub fn avg_latency_ms(&self) -> Option<i64> {
+        (self.requests > 0).then(|| self.latency_sum_ms / self.requests)
+    }
+
+    /// Latency quantile `q` (0..=1) estimated from the histogram by linear
+    /// interpolation inside the bucket holding the rank. Requests over the
+    /// last bound report that bound.
+    pub fn latency_quantile_ms(&self, q: f64) -> Option<i64> {
+        let total: i64 = self.latency_histogram.iter().sum();
+        if total == 0 {
+            return None;
+        }
+        let rank = q.clamp(0.0, 1.0) * total as f64;
+        let mut seen = 0i64;
+        for (i, count) in self.latency_histogram.iter().enumerate() {
+            if *count == 0 || ((seen + count) as f64) < rank {
+                seen += count;
+                continue;
+            }
+            let Some(upper) = LATENCY_BOUNDS_MS.get(i) else {
+                break;
+            };
+            let lower = if i == 0 { 0 } else { LATENCY_BOUNDS_MS[i - 1] };
+            let fraction = (rank - seen as f64) / *count as f64;
+            return Some(lower + ((upper - lower) as f64 * fraction).round() as i64);
+        }
+        LATENCY_BOUNDS_MS.last().copied()
+    }
+
+    fn to_array(&self) -> [i64; 30] {
+        let h = &self.latency_histogram;
+        [
+            self.requests,
+            self.failed_requests,
+            self.prompt_tokens,
+            self.completion_tokens,
+            self.cache_read_tokens,
+            self.cache_write_tokens,
+            self.reasoning_tokens,
+            self.audio_input_tokens,
+            self.audio_output_tokens,
+            self.image_tokens,
+            self.video_tokens,
+            self.embedding_tokens,
+            self.cost_nano,
+            self.errors_upstream,
+            self.errors_timeout,
+            self.errors_auth,
+            self.errors_rate_limit,
+            self.errors_router_reject,
+            self.errors_other,
+            self.latency_sum_ms,
+            h[0],
+            h[1],
+            h[2],
+            h[3],
+            h[4],
+            h[5],
+            h[6],
+            h[7],
+            h[8],
+            h[9],
+        ]
+    }
+
+    fn from_array(v: [i64; 30]) -> Self {
+        let mut latency_histogram = [0; LATENCY_BUCKETS];
+        latency_histogram.copy_from_slice(&v[20..]);
+        Self {
+            requests: v[0],
+            failed_requests: v[1],
+            prompt_tokens: v[2],
+            completion_tokens: v[3],
+            cache_read_tokens: v[4],
+            cache_write_tokens: v[5],
+            reasoning_tokens: v[6],
+            audio_input_tokens: v[7],
+            audio_output_tokens: v[8],
+            image_tokens: v[9],
+            video_tokens: v[10],
+            embedding_tokens: v[11],
+            cost_nano: v[12],
+            errors_upstream: v[13],
+            errors_timeout: v[14],
+            errors_auth: v[15],
+            errors_rate_limit: v[16],
+            errors_router_reject: v[17],
+            errors_other: v[18],
+            latency_sum_ms: v[19],
+            latency_histogram,
+        }
+    }
+}
+
+/// Pending rollup deltas, keyed by row.
+pub type RollupDeltas = HashMap<RollupKey, RollupCounters>;
+
+/// Add `log`, created at unix time `at`, to the minute, hour and day rows
+/// of `deltas`.
+pub fn accumulate(deltas: &mut RollupDeltas, log: &RouterLog, group_name: &str, at: i64) {
+    for granularity in RollupGranularity::ALL {
+        let key = RollupKey {
+            granularity,
+            bucket_start: granularity.bucket_start(at),
+            model: log.model.clone().unwrap_or_default(),
+            upstream_id: log.upstream_id.clone().unwrap_or_default(),
+            user_id: log.user_id.clone().unwrap_or_default(),
+            group_name: group_name.to_string(),
+            currency: log
+                .currency
+                .clone()
+                .filter(|c| !c.is_empty())
+                .unwrap_or_else(|| LOG_CURRENCY.to_string()),
+        };
+        deltas.entry(key).or_default().record(log);
+    }
+}
+
+/// Dimension a [`RollupQuery`] can group by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RollupDimension {
+    Model,
+    Channel,
+    User,
+    Group,
+    Currency,
+}
+
+impl RollupDimension {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "model" => Some(Self::Model),
+            "channel" | "upstream_id" => Some(Self::Channel),
+            "user" | "user_id" => Some(Self::User),
+            "group" => Some(Self::Group),
+            "currency" => Some(Self::Currency),
+            _ => None,
+        }
+    }
+
+    fn column(&self) -> &'static str {
+        match self {
+            Self::Model => "model",
+            Self::Channel => "upstream_id",
+            Self::User => "user_id",
+            Self::Group => "group_name",
+            Self::Currency => "currency",
+        }
+    }
+}
+
+/// Aggregate read over rollup rows.
+#[derive(Debug, Clone)]
+pub struct RollupQuery {
+    pub granularity: RollupGranularity,
+    /// Inclusive lower bound on `bucket_start`
+    pub since: i64,
+    /// Exclusive upper bound on `bucket_start`
+    pub until: Option<i64>,
+    pub model: Option<String>,
+    pub upstream_id: Option<String>,
+    pub user_id: Option<String>,
+    pub group_name: Option<String>,
+    /// One series per dimension value; empty sums over all of them
+    pub group_by: Vec<RollupDimension>,
+    /// Keep buckets apart instead of summing the whole range
+    pub by_bucket: bool,
+}
+
+impl RollupQuery {
+    pub fn new(granularity: RollupGranularity, since: i64) -> Self {
+        Self {
+            granularity,
+            since,
+            until: None,
+            model: None,
+            upstream_id: None,
+            user_id: None,
+            group_name: None,
+            group_by: Vec::new(),
+            by_bucket: false,
+        }
+    }
+}
+
+/// One aggregated point. Dimensions not grouped by are `None`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RollupRow {
+    pub bucket_start: Option<i64>,
+    pub model: Option<String>,
+    pub upstream_id: Option<String>,
+    pub user_id: Option<String>,
+    pub group_name: Option<String>,
+    pub currency: Option<String>,
+    #[serde(flatten)]
+    pub counters: RollupCounters,
+}
+
+/// `router_logs` row with its creation time as unix seconds.
+#[derive(FromRow)]
+struct TimedLog {
+    #[sqlx(flatten)]
+    log: RouterLog,
+    created_ts: i64,
+}
+
+/// Outcome of [`UsageRollupModel::rebuild`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RollupRebuildReport {
+    /// Rollup rows removed before re-aggregating
+    pub rows_deleted: u64,
+    /// `router_logs` rows aggregated
+    pub logs_aggregated: u64,
+}
+
+/// `expr`, a `created_at` timestamp, as unix seconds.
+fn created_epoch(is_postgres: bool, expr: &str) -> String {
+    if is_postgres {
+        format!("CAST(EXTRACT(EPOCH FROM {expr}) AS BIGINT)")
+    } else {
+        format!("CAST(strftime('%s', {expr}) AS BIGINT)")
+    }
+}
+
+fn epoch_text(ts: i64) -> String {
+    chrono::DateTime::from_timestamp(ts, 0)
+        .unwrap_or_default()
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+pub struct UsageRollupModel;
+
+impl UsageRollupModel {
+    /// Add `deltas` to their rows, creating missing rows, in one transaction.
+    pub async fn upsert(db: &Database, deltas: &RollupDeltas) -> Result<()> {
+        if deltas.is_empty() {
+            return Ok(());
+        }
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let updates: Vec<String> = COUNTER_COLUMNS
+            .iter()
+            .map(|c| format!("{c} = usage_rollups.{c} + excluded.{c}"))
+            .collect();
+        let sql = format!(
+            "INSERT INTO usage_rollups ({}, {}) VALUES ({}) ON CONFLICT ({}) DO UPDATE SET {}",
+            KEY_COLUMNS.join(", "),
+            COUNTER_COLUMNS.join(", "),
+            phs(is_postgres, KEY_COLUMNS.len() + COUNTER_COLUMNS.len()),
+            KEY_COLUMNS.join(", "),
+            updates.join(", ")
+        );
+
+        let mut tx = conn.pool().begin().await?;
+        for (key, counters) in deltas {
+            let mut query = sqlx::query(&sql)
+                .bind(key.granularity.as_str())
+                .bind(key.bucket_start)
+                .bind(&key.model)
+                .bind(&key.upstream_id)
+                .bind(&key.user_id)
+                .bind(&key.group_name)
+                .bind(&key.currency);
+            for value in counters.to_array() {
+                query = query.bind(value);
+            }
+            query.execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Group of `user_id`, or an empty string for unknown users.
+    pub async fn user_group(db: &Database, user_id: &str) -> Result<String> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let group_col = if is_postgres { "\"group\"" } else { "`group`" };
+        let sql = format!(
+            "SELECT {group_col} FROM user_accounts WHERE id = {}",
+            ph(is_postgres, 1)
+        );
+        let group: Option<Option<String>> = sqlx::query_scalar(&sql)
+            .bind(user_id)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(group.flatten().unwrap_or_default())
+    }
+
+    /// Roll up a request just written to `router_logs`, attributed to the
+    /// current minute. The user's group is looked up at this point.
+    pub async fn record_log(db: &Database, log: &RouterLog) -> Result<()> {
+        let group = match log.user_id.as_deref() {
+            Some(user_id) => Self::user_group(db, user_id).await?,
+            None => String::new(),
+        };
+        let mut deltas = RollupDeltas::new();
+        accumulate(&mut deltas, log, &group, chrono::Utc::now().timestamp());
+        Self::upsert(db, &deltas).await
+    }
+
+    /// Replace rollups for `[since, until)` (unix seconds, day-aligned) with
+    /// a fresh aggregation of `router_logs`.
+    ///
+    /// Requests logged into the range while this runs may be counted twice
+    /// or missed, so rebuild periods that no longer receive traffic. Rollups
+    /// outlive pruned logs, so rebuilding a pruned period loses its history.
+    pub async fn rebuild(db: &Database, since: i64, until: i64) -> Result<RollupRebuildReport> {
+        let since = RollupGranularity::Day.bucket_start(since);
+        let mut report = RollupRebuildReport {
+            rows_deleted: Self::delete_range(db, since, until).await?,
+            ..Default::default()
+        };
+
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let sql = format!(
+            "SELECT {LOG_COLUMNS}, {}, {} AS created_ts FROM router_logs WHERE {} AND {} AND id > {} ORDER BY id LIMIT {}",
+            created_at_text(is_postgres),
+            created_epoch(is_postgres, "created_at"),
+            created_at_compare(is_postgres, ">=", 1),
+            created_at_compare(is_postgres, "<", 2),
+            ph(is_postgres, 3),
+            ph(is_postgres, 4)
+        );
+        let (since_text, until_text) = (epoch_text(since), epoch_text(until));
+
+        let mut groups: HashMap<String, String> = HashMap::new();
+        let mut after_id = 0i64;
+        loop {
+            let batch = sqlx::query_as::<_, TimedLog>(&sql)
+                .bind(&since_text)
+                .bind(&until_text)
+                .bind(after_id)
+                .bind(REBUILD_BATCH)
+                .fetch_all(conn.pool())
+                .await?;
+            let Some(last) = batch.last() else {
+                break;
+            };
+            after_id = last.log.id;
+
+            let mut deltas = RollupDeltas::new();
+            for TimedLog { log, created_ts } in &batch {
+                let user_id = log.user_id.clone().unwrap_or_default();
+                if !groups.contains_key(&user_id) {
+                    let group = Self::user_group(db, &user_id).await?;
+                    groups.insert(user_id.clone(), group);
+                }
+                let group = groups.get(&user_id).map(String::as_str).unwrap_or("");
+                accumulate(&mut deltas, log, group, *created_ts);
+            }
+            Self::upsert(db, &deltas).await?;
+            report.logs_aggregated += batch.len() as u64;
+            if (batch.len() as i64) < REBUILD_BATCH {
+                break;
+            }
+        }
+        Ok(report)
+    }
+
+    /// Aggregate every `router_logs` row when `usage_rollups` is empty, as on
+    /// the first start after the rollup migration. Call before the router
+    /// serves traffic; see [`Self::rebuild`]. Returns `None` when there was
+    /// nothing to do.
+    pub async fn backfill_if_empty(db: &Database) -> Result<Option<RollupRebuildReport>> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let has_rollups: Option<i32> = sqlx::query_scalar("SELECT 1 FROM usage_rollups LIMIT 1")
+            .fetch_optional(conn.pool())
+            .await?;
+        if has_rollups.is_some() {
+            return Ok(None);
+        }
+        let sql = format!(
+            "SELECT {} FROM router_logs",
+            created_epoch(is_postgres, "MIN(created_at)")
+        );
+        let earliest: Option<i64> = sqlx::query_scalar(&sql).fetch_one(conn.pool()).await?;
+        let Some(earliest) = earliest else {
+            return Ok(None);
+        };
+        let until = RollupGranularity::Day.bucket_start(chrono::Utc::now().timestamp())
+            + RollupGranularity::Day.seconds();
+        Self::rebuild(db, earliest, until).await.map(Some)
+    }
+
+    /// Delete rows of every granularity with `bucket_start` in `[since, until)`.
+    pub async fn delete_range(db: &Database, since: i64, until: i64) -> Result<u64> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let sql = format!(
+            "DELETE FROM usage_rollups WHERE bucket_start >= {} AND bucket_start < {}",
+            ph(is_postgres, 1),
+            ph(is_postgres, 2)
+        );
+        let result = sqlx::query(&sql)
+            .bind(since)
+            .bind(until)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Delete `granularity` rows whose bucket started before `before`.
+    pub async fn delete_before(
+        db: &Database,
+        granularity: RollupGranularity,
+        before: i64,
+    ) -> Result<u64> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let sql = format!(
+            "DELETE FROM usage_rollups WHERE granularity = {} AND bucket_start < {}",
+            ph(is_postgres, 1),
+            ph(is_postgres, 2)
+        );
+        let result = sqlx::query(&sql)
+            .bind(granularity.as_str())
+            .bind(before)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Sum rollup rows matching `query`, ordered by bucket and then by cost.
+    pub async fn query(db: &Database, query: &RollupQuery) -> Result<Vec<RollupRow>> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+
+        let mut conditions = vec![
+            format!("granularity = {}", ph(is_postgres, 1)),
+            format!("bucket_start >= {}", ph(is_postgres, 2)),
+        ];
+        let mut texts: Vec<&str> = Vec::new();
+        if let Some(until) = query.until {
+            conditions.push(format!("bucket_start < {until}"));
+        }
+        let filters = [
+            ("model", &query.model),
+            ("upstream_id", &query.upstream_id),
+            ("user_id", &query.user_id),
+            ("group_name", &query.group_name),
+        ];
+        for (column, value) in filters {
+            if let Some(v) = value {
+                texts.push(v);
+                conditions.push(format!("{column} = {}", ph(is_postgres, texts.len() + 2)));
+            }
+        }
+
+        let mut dimensions: Vec<&str> = Vec::new();
+        if query.by_bucket {
+            dimensions.push("bucket_start");
+        }
+        for dimension in &query.group_by {
+            if !dimensions.contains(&dimension.column()) {
+                dimensions.push(dimension.column());
+            }
+        }
+        let sums: Vec<String> = COUNTER_COLUMNS
+            .iter()
+            .map(|c| format!("CAST(COALESCE(SUM({c}), 0) AS BIGINT) AS {c}"))
+            .collect();
+        let select = dimensions
+            .iter()
+            .map(|d| d.to_string())
+            .chain(sums)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let (group_by, order_by) = if dimensions.is_empty() {
+            (String::new(), String::new())
+        } else {
+            let order = if query.by_bucket {
+                "ORDER BY bucket_start, cost_nano DESC"
+            } else {
+                "ORDER BY cost_nano DESC"
+            };
+            (
+                format!("GROUP BY {}", dimensions.join(", ")),
+                order.to_string(),
+            )
+        };
+        let sql = format!(
+            "SELECT {select} FROM usage_rollups WHERE {} {group_by} {order_by}",
+            conditions.join(" AND ")
+        );
+
+        let mut q = sqlx::query(&sql)
+            .bind(query.granularity.as_str())
+            .bind(query.since);
+        for text in texts {
+            q = q.bind(text);
+        }
+        let rows = q.fetch_all(conn.pool()).await?;
+
+        let mut points = Vec::with_capacity(rows.len());
+        for row in rows {
+            let text = |column: &str| -> Result<Option<String>> {
+                if dimensions.contains(&column) {
+                    Ok(Some(row.try_get::<String, _>(column)?))
+                } else {
+                    Ok(None)
+                }
+            };
+            let mut values = [0i64; 30];
+            for (value, column) in values.iter_mut().zip(COUNTER_COLUMNS) {
+                *value = row.try_get(column)?;
+            }
+            points.push(RollupRow {
+                bucket_start: if query.by_bucket {
+                    Some(row.try_get("bucket_start")?)
+                } else {
+                    None
+                },
+                model: text("model")?,
+                upstream_id: text("upstream_id")?,
+                user_id: text("user_id")?,
+                group_name: text("group_name")?,
+                currency: text("currency")?,
+                counters: RollupCounters::from_array(values),
+            });
+        }
+        Ok(points)
+    }
+}
@@ -3,7 +3,7 @@
 //! This crate handles all database operations related to API tokens,
 //! including validation, spend-quota tracking, CRUD operations, and key rotation.
 
-use burncloud_common::CrudRepository;
+use burncloud_common::{CrudRepository, RateLimits};
 use burncloud_database::{adapt_sql, phs, Database, DatabaseError, Result};
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
@@ -63,6 +63,60 @@ pub struct RouterToken {
     pub created_at: i64,
     #[sqlx(default)]
     pub last_rotated_at: i64,
+    /// Owning organization; when set, spend is charged to the organization's wallet.
+    #[sqlx(default)]
+    #[serde(default)]
+    pub org_id: Option<String>,
+    /// JSON `ModelPolicy` (see `burncloud_common::model_policy`): model allow/deny globs and per-model spend caps.
+    #[sqlx(default)]
+    #[serde(default)]
+    pub model_policy: Option<String>,
+    /// Requests per minute; `None` is unlimited.
+    #[sqlx(default)]
+    #[serde(default)]
+    pub rpm_limit: Option<i32>,
+    /// Tokens per minute; `None` is unlimited.
+    #[sqlx(default)]
+    #[serde(default)]
+    pub tpm_limit: Option<i64>,
+    /// Concurrent in-flight requests; `None` is unlimited.
+    #[sqlx(default)]
+    #[serde(default)]
+    pub max_concurrency: Option<i32>,
+}
+
+impl RouterToken {
+    /// The token's own rate limits.
+    pub fn rate_limits(&self) -> RateLimits {
+        RateLimits {
+            rpm_limit: self.rpm_limit,
+            tpm_limit: self.tpm_limit,
+            max_concurrency: self.max_concurrency,
+        }
+    }
+}
+
+/// Stable non-secret identifier of a bearer token: the md5 hex digest also used
+/// for `old_key_hash` and `router_logs.token_hash`.
+pub fn token_hash(token: &str) -> String {
+    format!("{:x}", md5::compute(token.as_bytes()))
+}
+
+/// Spend quota of a credential, in nanodollars
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenQuota {
+    /// Canonical current token
+    pub token: String,
+    /// Negative for unlimited
+    pub limit: i64,
+    pub used: i64,
+}
+
+impl TokenQuota {
+    /// Quota left, `None` when unlimited
+    pub fn remaining(&self) -> Option<i64> {
+        (self.limit >= 0).then(|| self.limit.saturating_sub(self.used))
+    }
 }
 
 pub struct RouterTokenModel;
@@ -92,7 +146,8 @@ impl RouterTokenModel {
         let conn = db.get_connection()?;
         let tokens = sqlx::query_as::<_, RouterToken>(
             "SELECT token, user_id, status, quota_limit, used_quota, expired_time, accessed_time, \
-             key_version, old_key_hash, old_key_expires_at, ip_whitelist, key_prefix, created_at, last_rotated_at \
+             key_version, old_key_hash, old_key_expires_at, ip_whitelist, key_prefix, created_at, last_rotated_at, org_id, model_policy, \
+             rpm_limit, tpm_limit, max_concurrency \
              FROM router_tokens",
         )
         .fetch_all(conn.pool())
@@ -106,9 +161,10 @@ impl RouterTokenModel {
         let is_postgres = db.kind() == "postgres";
         let sql = format!(
             "INSERT INTO router_tokens (token, user_id, status, quota_limit, used_quota, expired_time, accessed_time, \
-             key_version, old_key_hash, old_key_expires_at, ip_whitelist, key_prefix, created_at, last_rotated_at) \
+             key_version, old_key_hash, old_key_expires_at, ip_whitelist, key_prefix, created_at, last_rotated_at, org_id, model_policy, \
+             rpm_limit, tpm_limit, max_concurrency) \
              VALUES ({})",
-            phs(is_postgres, 14)
+            phs(is_postgres, 19)
         );
         sqlx::query(&sql)
             .bind(&t.token)
@@ -125,6 +181,11 @@ impl RouterTokenModel {
             .bind(&t.key_prefix)
             .bind(t.created_at)
             .bind(t.last_rotated_at)
+            .bind(&t.org_id)
+            .bind(&t.model_policy)
+            .bind(t.rpm_limit)
+            .bind(t.tpm_limit)
+            .bind(t.max_concurrency)
             .execute(conn.pool())
             .await?;
         Ok(())
@@ -162,7 +223,8 @@ impl RouterTokenModel {
         let sql = adapt_sql(
             db.kind() == "postgres",
             "SELECT token, user_id, status, quota_limit, used_quota, expired_time, accessed_time, \
-             key_version, old_key_hash, old_key_expires_at, ip_whitelist, key_prefix, created_at, last_rotated_at \
+             key_version, old_key_hash, old_key_expires_at, ip_whitelist, key_prefix, created_at, last_rotated_at, org_id, model_policy, \
+             rpm_limit, tpm_limit, max_concurrency \
              FROM router_tokens WHERE token = ?",
         );
         let result = sqlx::query_as::<_, RouterToken>(&sql)
@@ -267,7 +329,8 @@ impl RouterTokenModel {
         let sql = adapt_sql(
             db.kind() == "postgres",
             "SELECT token, user_id, status, quota_limit, used_quota, expired_time, accessed_time, \
-             key_version, old_key_hash, old_key_expires_at, ip_whitelist, key_prefix, created_at, last_rotated_at \
+             key_version, old_key_hash, old_key_expires_at, ip_whitelist, key_prefix, created_at, last_rotated_at, org_id, model_policy, \
+             rpm_limit, tpm_limit, max_concurrency \
              FROM router_tokens WHERE token = ? AND status = 'active'",
         );
 
@@ -288,7 +351,8 @@ impl RouterTokenModel {
         let old_key_sql = adapt_sql(
             db.kind() == "postgres",
             "SELECT token, user_id, status, quota_limit, used_quota, expired_time, accessed_time, \
-             key_version, old_key_hash, old_key_expires_at, ip_whitelist, key_prefix, created_at, last_rotated_at \
+             key_version, old_key_hash, old_key_expires_at, ip_whitelist, key_prefix, created_at, last_rotated_at, org_id, model_policy, \
+             rpm_limit, tpm_limit, max_concurrency \
              FROM router_tokens WHERE old_key_hash = ? AND old_key_expires_at > ? AND status = 'active'",
         );
 
@@ -318,7 +382,8 @@ impl RouterTokenModel {
         let sql = adapt_sql(
             db.kind() == "postgres",
             "SELECT token, user_id, status, quota_limit, used_quota, expired_time, accessed_time, \
-             key_version, old_key_hash, old_key_expires_at, ip_whitelist, key_prefix, created_at, last_rotated_at \
+             key_version, old_key_hash, old_key_expires_at, ip_whitelist, key_prefix, created_at, last_rotated_at, org_id, model_policy, \
+             rpm_limit, tpm_limit, max_concurrency \
              FROM router_tokens WHERE token = ? AND status = 'active'",
         );
 
@@ -337,7 +402,8 @@ impl RouterTokenModel {
         let old_key_sql = adapt_sql(
             db.kind() == "postgres",
             "SELECT token, user_id, status, quota_limit, used_quota, expired_time, accessed_time, \
-             key_version, old_key_hash, old_key_expires_at, ip_whitelist, key_prefix, created_at, last_rotated_at \
+             key_version, old_key_hash, old_key_expires_at, ip_whitelist, key_prefix, created_at, last_rotated_at, org_id, model_policy, \
+             rpm_limit, tpm_limit, max_concurrency \
              FROM router_tokens WHERE old_key_hash = ? AND old_key_expires_at > ? AND status = 'active'",
         );
 
@@ -523,6 +589,46 @@ impl RouterTokenModel {
         Ok(within_limit)
     }
 
+    /// Quota of an active credential after settlement, resolving rotation
+    /// aliases to the canonical current token like [`Self::deduct_quota`].
+    pub async fn quota_state(db: &Database, token: &str) -> Result<Option<TokenQuota>> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let direct_sql = adapt_sql(
+            is_postgres,
+            "SELECT token, quota_limit, used_quota FROM router_tokens WHERE token = ? AND status = 'active'",
+        );
+        let mut quota = sqlx::query_as::<_, (String, i64, i64)>(&direct_sql)
+            .bind(token)
+            .fetch_optional(conn.pool())
+            .await?;
+
+        if quota.is_none() {
+            let old_key_sql = adapt_sql(
+                is_postgres,
+                "SELECT token, quota_limit, used_quota FROM router_tokens WHERE old_key_hash = ? AND old_key_expires_at > ? AND status = 'active'",
+            );
+            quota = sqlx::query_as::<_, (String, i64, i64)>(&old_key_sql)
+                .bind(token_hash(token))
+                .bind(Self::current_timestamp())
+                .fetch_optional(conn.pool())
+                .await?;
+        }
+
+        if quota.is_none() {
+            let legacy_sql = adapt_sql(
+                is_postgres,
+                "SELECT key, remain_quota, used_quota FROM user_api_keys WHERE key = ? AND status = 1",
+            );
+            quota = sqlx::query_as::<_, (String, i64, i64)>(&legacy_sql)
+                .bind(token)
+                .fetch_optional(conn.pool())
+                .await?;
+        }
+
+        Ok(quota.map(|(token, limit, used)| TokenQuota { token, limit, used }))
+    }
+
     /// Revoke old key version immediately
     pub async fn revoke_old_key(db: &Database, token: &str) -> Result<bool> {
         let conn = db.get_connection()?;
@@ -549,6 +655,42 @@ impl RouterTokenModel {
         Ok(result.rows_affected() > 0)
     }
 
+    /// Replace (or clear, with `None`) the model policy JSON of a token
+    pub async fn set_model_policy(
+        db: &Database,
+        token: &str,
+        model_policy: Option<&str>,
+    ) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE router_tokens SET model_policy = ? WHERE token = ?",
+        );
+        let result = sqlx::query(&sql)
+            .bind(model_policy)
+            .bind(token)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Replace the RPM / TPM / concurrency limits of a token
+    pub async fn set_rate_limits(db: &Database, token: &str, limits: &RateLimits) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE router_tokens SET rpm_limit = ?, tpm_limit = ?, max_concurrency = ? WHERE token = ?",
+        );
+        let result = sqlx::query(&sql)
+            .bind(limits.rpm_limit)
+            .bind(limits.tpm_limit)
+            .bind(limits.max_concurrency)
+            .bind(token)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
     /// Check if IP is allowed for token
     pub async fn is_ip_allowed(db: &Database, token: &str, client_ip: &str) -> Result<bool> {
         let conn = db.get_connection()?;
@@ -0,0 +1,157 @@
+//! Virtual model definitions (`router_virtual_models`).
+//!
+//! A virtual model is a customer-facing name such as `company-smart` that
+//! resolves to an ordered chain of concrete models. The router tries every
+//! channel of the first hop, then moves to the next hop once those are
+//! exhausted or tripped. Each hop may carry its own request parameter
+//! overrides, e.g. a lower `max_tokens` for a cheaper fallback.
+
+use burncloud_database::{adapt_sql, Database, Result};
+use serde::{Deserialize, Serialize};
+
+/// Top-level request fields a hop replaces. Values are arbitrary request JSON.
+#[allow(clippy::disallowed_types)]
+pub type ParamOverride = serde_json::Map<String, serde_json::Value>;
+
+/// One step of a virtual model's fallback chain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VirtualModelHop {
+    /// Concrete model name, matched against `channel_abilities.model`
+    pub model: String,
+    /// Top-level request fields replaced when this hop serves the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub param_override: Option<ParamOverride>,
+}
+
+/// A named fallback chain of concrete models
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VirtualModel {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub hops: Vec<VirtualModelHop>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub created_at: i64,
+    #[serde(default)]
+    pub updated_at: i64,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl VirtualModel {
+    /// Check the definition is routable: a name, at least one hop, and no
+    /// hop that is blank, repeated or points back at the virtual name.
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name is required".to_string());
+        }
+        if self.hops.is_empty() {
+            return Err("at least one hop is required".to_string());
+        }
+        let mut seen = std::collections::HashSet::new();
+        for hop in &self.hops {
+            if hop.model.trim().is_empty() {
+                return Err("hop model must not be empty".to_string());
+            }
+            if hop.model == self.name {
+                return Err(format!(
+                    "hop '{}' refers to the virtual model itself",
+                    hop.model
+                ));
+            }
+            if !seen.insert(hop.model.as_str()) {
+                return Err(format!("hop '{}' appears more than once", hop.model));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct VirtualModelRow {
+    name: String,
+    description: Option<String>,
+    hops: String,
+    enabled: i32,
+    created_at: i64,
+    updated_at: i64,
+}
+
+impl TryFrom<VirtualModelRow> for VirtualModel {
+    type Error = burncloud_database::DatabaseError;
+
+    fn try_from(row: VirtualModelRow) -> Result<Self> {
+        Ok(Self {
+            name: row.name,
+            description: row.description,
+            hops: serde_json::from_str(&row.hops)?,
+            enabled: row.enabled != 0,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        })
+    }
+}
+
+const VIRTUAL_MODEL_COLUMNS: &str = "name, description, hops, enabled, created_at, updated_at";
+
+pub struct VirtualModelModel;
+
+impl VirtualModelModel {
+    pub async fn get(db: &Database, name: &str) -> Result<Option<VirtualModel>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!("SELECT {VIRTUAL_MODEL_COLUMNS} FROM router_virtual_models WHERE name = ?"),
+        );
+        let row: Option<VirtualModelRow> = sqlx::query_as(&sql)
+            .bind(name)
+            .fetch_optional(conn.pool())
+            .await?;
+        row.map(VirtualModel::try_from).transpose()
+    }
+
+    pub async fn list(db: &Database) -> Result<Vec<VirtualModel>> {
+        let conn = db.get_connection()?;
+        let sql =
+            format!("SELECT {VIRTUAL_MODEL_COLUMNS} FROM router_virtual_models ORDER BY name");
+        let rows: Vec<VirtualModelRow> = sqlx::query_as(&sql).fetch_all(conn.pool()).await?;
+        rows.into_iter().map(VirtualModel::try_from).collect()
+    }
+
+    /// Create or replace a definition. `created_at` is kept on replace.
+    pub async fn upsert(db: &Database, model: &VirtualModel) -> Result<()> {
+        let conn = db.get_connection()?;
+        let now = chrono::Utc::now().timestamp();
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "INSERT INTO router_virtual_models (name, description, hops, enabled, created_at, updated_at) \
+             VALUES (?, ?, ?, ?, ?, ?) \
+             ON CONFLICT(name) DO UPDATE SET description = excluded.description, hops = excluded.hops, \
+             enabled = excluded.enabled, updated_at = excluded.updated_at",
+        );
+        sqlx::query(&sql)
+            .bind(&model.name)
+            .bind(&model.description)
+            .bind(serde_json::to_string(&model.hops)?)
+            .bind(model.enabled as i32)
+            .bind(now)
+            .bind(now)
+            .execute(conn.pool())
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete(db: &Database, name: &str) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "DELETE FROM router_virtual_models WHERE name = ?",
+        );
+        let result = sqlx::query(&sql).bind(name).execute(conn.pool()).await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -90,6 +90,9 @@ fn make_log(request_id: &str) -> RouterLog {
         traffic_color: None,
         cost_status: None,
         error_type: None,
+        token_hash: None,
+        served_model: None,
+        currency: None,
         created_at: None,
     }
 }
@@ -238,6 +241,9 @@ async fn test_zero_values_read_as_zero_not_null() {
         traffic_color: None,
         cost_status: None,
         error_type: None,
+        token_hash: None,
+        served_model: None,
+        currency: None,
         created_at: None,
     };
 
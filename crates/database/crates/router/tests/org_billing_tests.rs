@@ -0,0 +1,156 @@
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+//! Organization wallet admission and settlement (`OrgBillingModel`).
+use burncloud_database::create_database_with_url;
+use burncloud_database_router::{OrgAdmission, RouterDatabase};
+use tempfile::NamedTempFile;
+
+/// 7 CNY per USD, scaled by 10^9.
+const RATE: i64 = 7_000_000_000;
+
+async fn create_test_db() -> (burncloud_database::Database, NamedTempFile) {
+    let tmp = NamedTempFile::new().unwrap_or_else(|e| panic!("failed to create temp file: {e}"));
+    let url = format!("sqlite://{}?mode=rwc", tmp.path().display());
+    let db = create_database_with_url(&url)
+        .await
+        .unwrap_or_else(|e| panic!("failed to initialize test database: {e}"));
+    RouterDatabase::init(&db)
+        .await
+        .unwrap_or_else(|e| panic!("failed to initialize router tables: {e}"));
+    (db, tmp)
+}
+
+async fn insert_org(
+    db: &burncloud_database::Database,
+    id: &str,
+    balance_usd: i64,
+    balance_cny: i64,
+    spend_limit: i64,
+) {
+    let conn = db.get_connection().unwrap();
+    sqlx::query(
+        "INSERT INTO user_organizations (id, name, owner_id, balance_usd, balance_cny, spend_limit, spent, status, created_at) \
+         VALUES (?, ?, 'owner', ?, ?, ?, 0, 1, 0)",
+    )
+    .bind(id)
+    .bind(id)
+    .bind(balance_usd)
+    .bind(balance_cny)
+    .bind(spend_limit)
+    .execute(conn.pool())
+    .await
+    .unwrap_or_else(|e| panic!("insert_org failed: {e}"));
+}
+
+async fn wallet(db: &burncloud_database::Database, id: &str) -> (i64, i64, i64) {
+    let conn = db.get_connection().unwrap();
+    sqlx::query_as("SELECT balance_usd, balance_cny, spent FROM user_organizations WHERE id = ?")
+        .bind(id)
+        .fetch_one(conn.pool())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_org_admission_states() {
+    let (db, _tmp) = create_test_db().await;
+    insert_org(&db, "funded", 100, 0, -1).await;
+    insert_org(&db, "empty", 0, 0, -1).await;
+    insert_org(&db, "capped", 100, 0, 50).await;
+
+    assert_eq!(
+        RouterDatabase::org_admission(&db, "funded").await.unwrap(),
+        OrgAdmission::Allowed
+    );
+    assert_eq!(
+        RouterDatabase::org_admission(&db, "empty").await.unwrap(),
+        OrgAdmission::InsufficientBalance
+    );
+    assert_eq!(
+        RouterDatabase::org_admission(&db, "missing").await.unwrap(),
+        OrgAdmission::NotFound
+    );
+
+    RouterDatabase::charge_org(&db, "capped", 50, Some(RATE))
+        .await
+        .unwrap();
+    assert_eq!(
+        RouterDatabase::org_admission(&db, "capped").await.unwrap(),
+        OrgAdmission::SpendLimitReached
+    );
+
+    let conn = db.get_connection().unwrap();
+    sqlx::query("UPDATE user_organizations SET status = 0 WHERE id = 'funded'")
+        .execute(conn.pool())
+        .await
+        .unwrap();
+    assert_eq!(
+        RouterDatabase::org_admission(&db, "funded").await.unwrap(),
+        OrgAdmission::Disabled
+    );
+}
+
+#[tokio::test]
+async fn test_org_charge_spends_usd_first() {
+    let (db, _tmp) = create_test_db().await;
+    insert_org(&db, "org", 500, 1_000, -1).await;
+
+    assert!(RouterDatabase::charge_org(&db, "org", 100, Some(RATE))
+        .await
+        .unwrap());
+    assert_eq!(wallet(&db, "org").await, (400, 1_000, 100));
+}
+
+#[tokio::test]
+async fn test_org_charge_falls_back_to_cny() {
+    let (db, _tmp) = create_test_db().await;
+    insert_org(&db, "org", 40, 1_000, -1).await;
+
+    // 40 USD from the USD balance, the remaining 60 USD as 420 CNY
+    assert!(RouterDatabase::charge_org(&db, "org", 100, Some(RATE))
+        .await
+        .unwrap());
+    assert_eq!(wallet(&db, "org").await, (0, 580, 100));
+}
+
+#[tokio::test]
+async fn test_org_charge_records_overdraft() {
+    let (db, _tmp) = create_test_db().await;
+    insert_org(&db, "org", 30, 70, -1).await;
+
+    // 70 CNY covers 10 USD; 30 USD from the USD balance; 60 USD becomes debt
+    assert!(!RouterDatabase::charge_org(&db, "org", 100, Some(RATE))
+        .await
+        .unwrap());
+    assert_eq!(wallet(&db, "org").await, (-60, 0, 100));
+    assert_eq!(
+        RouterDatabase::org_admission(&db, "org").await.unwrap(),
+        OrgAdmission::InsufficientBalance
+    );
+
+    // Without an exchange rate, CNY is left untouched
+    insert_org(&db, "no-rate", 30, 70, -1).await;
+    assert!(!RouterDatabase::charge_org(&db, "no-rate", 100, None)
+        .await
+        .unwrap());
+    assert_eq!(wallet(&db, "no-rate").await, (-70, 70, 100));
+}
+
+#[tokio::test]
+async fn test_concurrent_org_charges_all_land() {
+    let (db, _tmp) = create_test_db().await;
+    insert_org(&db, "usd", 1_000, 0, -1).await;
+    insert_org(&db, "mixed", 50, 1_000, -1).await;
+
+    let (a, b, c, d) = tokio::join!(
+        RouterDatabase::charge_org(&db, "usd", 100, Some(RATE)),
+        RouterDatabase::charge_org(&db, "usd", 300, Some(RATE)),
+        RouterDatabase::charge_org(&db, "mixed", 40, Some(RATE)),
+        RouterDatabase::charge_org(&db, "mixed", 40, Some(RATE)),
+    );
+    assert!(a.unwrap() && b.unwrap() && c.unwrap() && d.unwrap());
+
+    assert_eq!(wallet(&db, "usd").await, (600, 0, 400));
+    // 50 USD from the USD balance, the remaining 30 USD as 210 CNY
+    assert_eq!(wallet(&db, "mixed").await, (0, 790, 80));
+}
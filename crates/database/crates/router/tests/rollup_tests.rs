@@ -0,0 +1,329 @@
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+//! Minute/hour/day usage rollups (`UsageRollupModel`) and the summaries
+//! read from them.
+use burncloud_database::create_database_with_url;
+use burncloud_database_router::{
+    get_billing_summary, get_billing_summary_for_user, RollupCounters, RollupDimension,
+    RollupGranularity, RollupQuery, RouterDatabase, RouterLog, UsageRollupModel,
+};
+use tempfile::NamedTempFile;
+
+/// 2026-05-10 00:00:00 UTC
+const DAY_START: i64 = 1_778_371_200;
+/// 2026-05-12 00:00:00 UTC
+const RANGE_END: i64 = DAY_START + 2 * 86_400;
+
+async fn create_test_db() -> (burncloud_database::Database, NamedTempFile) {
+    let tmp = NamedTempFile::new().unwrap_or_else(|e| panic!("failed to create temp file: {e}"));
+    let url = format!("sqlite://{}?mode=rwc", tmp.path().display());
+    let db = create_database_with_url(&url)
+        .await
+        .unwrap_or_else(|e| panic!("failed to initialize test database: {e}"));
+    RouterDatabase::init(&db)
+        .await
+        .unwrap_or_else(|e| panic!("failed to initialize router tables: {e}"));
+    (db, tmp)
+}
+
+async fn insert_user(db: &burncloud_database::Database, user_id: &str, group: &str) {
+    let conn = db.get_connection().unwrap();
+    sqlx::query(
+        "INSERT INTO user_accounts (id, username, password_hash, status, `group`) \
+         VALUES (?, ?, 'no-login', 1, ?)",
+    )
+    .bind(user_id)
+    .bind(user_id)
+    .bind(group)
+    .execute(conn.pool())
+    .await
+    .unwrap_or_else(|e| panic!("insert_user failed: {e}"));
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn insert_log(
+    db: &burncloud_database::Database,
+    user_id: &str,
+    model: Option<&str>,
+    status_code: i32,
+    latency_ms: i64,
+    cost: i64,
+    error_type: Option<&str>,
+    created_at: &str,
+) {
+    let conn = db.get_connection().unwrap();
+    let sql = r#"
+        INSERT INTO router_logs
+        (request_id, user_id, path, upstream_id, status_code, latency_ms,
+         prompt_tokens, completion_tokens, cost, model, error_type, created_at)
+        VALUES (?, ?, '/v1/chat/completions', 'up-1', ?, ?, 10, 5, ?, ?, ?, ?)
+    "#;
+    sqlx::query(sql)
+        .bind(format!("req-{}", uuid::Uuid::new_v4()))
+        .bind(user_id)
+        .bind(status_code)
+        .bind(latency_ms)
+        .bind(cost)
+        .bind(model)
+        .bind(error_type)
+        .bind(created_at)
+        .execute(conn.pool())
+        .await
+        .unwrap_or_else(|e| panic!("insert_log failed: {e}"));
+}
+
+async fn seed(db: &burncloud_database::Database) {
+    insert_user(db, "user-1", "vip").await;
+    insert_user(db, "user-2", "default").await;
+    let logs = [
+        (
+            "user-1",
+            Some("gpt-4o"),
+            200,
+            80,
+            100,
+            None,
+            "2026-05-10 08:00:10",
+        ),
+        (
+            "user-1",
+            Some("gpt-4o"),
+            200,
+            300,
+            100,
+            None,
+            "2026-05-10 08:00:50",
+        ),
+        (
+            "user-1",
+            Some("o1"),
+            502,
+            1_200,
+            0,
+            Some("upstream_error"),
+            "2026-05-10 08:30:00",
+        ),
+        (
+            "user-2",
+            Some("gpt-4o"),
+            429,
+            20,
+            0,
+            Some("rate_limit"),
+            "2026-05-11 01:00:00",
+        ),
+        ("user-2", None, 200, 90, 7, None, "2026-05-11 02:00:00"),
+        // Outside the rebuilt range
+        (
+            "user-1",
+            Some("gpt-4o"),
+            200,
+            80,
+            1_000,
+            None,
+            "2026-05-12 00:00:00",
+        ),
+    ];
+    for (user, model, status, latency, cost, error, at) in logs {
+        insert_log(db, user, model, status, latency, cost, error, at).await;
+    }
+}
+
+#[tokio::test]
+async fn test_rebuild_aggregates_each_granularity() {
+    let (db, _tmp) = create_test_db().await;
+    seed(&db).await;
+
+    let report = UsageRollupModel::rebuild(&db, DAY_START, RANGE_END)
+        .await
+        .unwrap();
+    assert_eq!(report.logs_aggregated, 5);
+
+    // Minute buckets keep 08:00 and 08:30 apart
+    let mut minute = RollupQuery::new(RollupGranularity::Minute, DAY_START);
+    minute.user_id = Some("user-1".to_string());
+    minute.by_bucket = true;
+    let rows = UsageRollupModel::query(&db, &minute).await.unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].bucket_start, Some(DAY_START + 8 * 3_600));
+    assert_eq!(rows[0].counters.requests, 2);
+    assert_eq!(rows[0].counters.cost_nano, 200);
+    assert_eq!(rows[1].counters.errors_upstream, 1);
+    assert_eq!(rows[1].counters.failed_requests, 1);
+
+    // Day totals per group
+    let mut day = RollupQuery::new(RollupGranularity::Day, DAY_START);
+    day.group_by = vec![RollupDimension::Group];
+    let mut rows = UsageRollupModel::query(&db, &day).await.unwrap();
+    rows.sort_by(|a, b| a.group_name.cmp(&b.group_name));
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].group_name.as_deref(), Some("default"));
+    assert_eq!(rows[0].counters.requests, 2);
+    assert_eq!(rows[0].counters.errors_rate_limit, 1);
+    assert_eq!(rows[1].group_name.as_deref(), Some("vip"));
+    assert_eq!(rows[1].counters.requests, 3);
+    assert_eq!(rows[1].counters.prompt_tokens, 30);
+    assert!(rows[1].model.is_none());
+
+    // Rebuilding again replaces rather than adds
+    let again = UsageRollupModel::rebuild(&db, DAY_START, RANGE_END)
+        .await
+        .unwrap();
+    assert!(again.rows_deleted > 0);
+    let mut hour = RollupQuery::new(RollupGranularity::Hour, DAY_START);
+    hour.until = Some(RANGE_END);
+    let total = UsageRollupModel::query(&db, &hour).await.unwrap();
+    assert_eq!(total.len(), 1);
+    assert_eq!(total[0].counters.requests, 5);
+}
+
+#[tokio::test]
+async fn test_record_log_updates_every_granularity() {
+    let (db, _tmp) = create_test_db().await;
+    insert_user(&db, "user-1", "vip").await;
+    let log = RouterLog {
+        id: 0,
+        request_id: "req-live".to_string(),
+        user_id: Some("user-1".to_string()),
+        path: "/v1/chat/completions".to_string(),
+        upstream_id: Some("up-1".to_string()),
+        status_code: 200,
+        latency_ms: 400,
+        prompt_tokens: 3,
+        completion_tokens: 4,
+        cost: 900,
+        model: Some("gpt-4o".to_string()),
+        cache_read_tokens: 0,
+        reasoning_tokens: 0,
+        pricing_region: None,
+        video_tokens: 0,
+        cache_write_tokens: 0,
+        audio_input_tokens: 0,
+        audio_output_tokens: 0,
+        image_tokens: 0,
+        embedding_tokens: 0,
+        input_cost: 0,
+        output_cost: 0,
+        cache_read_cost: 0,
+        cache_write_cost: 0,
+        audio_cost: 0,
+        image_cost: 0,
+        video_cost: 0,
+        reasoning_cost: 0,
+        embedding_cost: 0,
+        layer_decision: None,
+        traffic_color: None,
+        cost_status: None,
+        error_type: None,
+        token_hash: None,
+        served_model: None,
+        currency: None,
+        created_at: None,
+    };
+    UsageRollupModel::record_log(&db, &log).await.unwrap();
+    UsageRollupModel::record_log(&db, &log).await.unwrap();
+    let priced_in_cny = RouterLog {
+        currency: Some("CNY".to_string()),
+        cost: 5_000,
+        ..log
+    };
+    UsageRollupModel::record_log(&db, &priced_in_cny)
+        .await
+        .unwrap();
+
+    let since = chrono::Utc::now().timestamp() - 2 * 86_400;
+    for granularity in RollupGranularity::ALL {
+        let mut query = RollupQuery::new(granularity, granularity.bucket_start(since));
+        query.group_by = vec![
+            RollupDimension::Model,
+            RollupDimension::Group,
+            RollupDimension::Currency,
+        ];
+        let rows = UsageRollupModel::query(&db, &query).await.unwrap();
+        assert_eq!(rows.len(), 2, "{granularity:?}");
+        assert_eq!(rows[0].currency.as_deref(), Some("CNY"));
+        assert_eq!(rows[0].counters.cost_nano, 5_000);
+        assert_eq!(rows[1].currency.as_deref(), Some("USD"));
+        assert_eq!(rows[1].model.as_deref(), Some("gpt-4o"));
+        assert_eq!(rows[1].group_name.as_deref(), Some("vip"));
+        assert_eq!(rows[1].counters.requests, 2);
+        assert_eq!(rows[1].counters.cost_nano, 1_800);
+        assert_eq!(rows[1].counters.latency_histogram[2], 2);
+    }
+}
+
+#[tokio::test]
+async fn test_backfill_if_empty_seeds_rollups_once() {
+    let (db, _tmp) = create_test_db().await;
+    assert!(UsageRollupModel::backfill_if_empty(&db)
+        .await
+        .unwrap()
+        .is_none());
+
+    seed(&db).await;
+    let report = UsageRollupModel::backfill_if_empty(&db)
+        .await
+        .unwrap()
+        .expect("logs without rollups are backfilled");
+    assert_eq!(report.logs_aggregated, 6);
+    let total = UsageRollupModel::query(&db, &RollupQuery::new(RollupGranularity::Day, 0))
+        .await
+        .unwrap();
+    assert_eq!(total[0].counters.requests, 6);
+
+    // Existing rollups are left alone
+    assert!(UsageRollupModel::backfill_if_empty(&db)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn test_latency_quantiles_interpolate_within_buckets() {
+    let mut counters = RollupCounters::default();
+    assert_eq!(counters.latency_quantile_ms(0.5), None);
+
+    // Ten requests in (100, 250], ten in (1000, 2500]
+    counters.latency_histogram[1] = 10;
+    counters.latency_histogram[4] = 10;
+    counters.requests = 20;
+    counters.latency_sum_ms = 20_000;
+    assert_eq!(counters.latency_quantile_ms(0.25), Some(175));
+    assert_eq!(counters.latency_quantile_ms(0.5), Some(250));
+    assert_eq!(counters.latency_quantile_ms(0.95), Some(2_350));
+    assert_eq!(counters.avg_latency_ms(), Some(1_000));
+
+    // Requests slower than the last bound report that bound
+    counters.latency_histogram[9] = 100;
+    assert_eq!(counters.latency_quantile_ms(0.99), Some(60_000));
+}
+
+#[tokio::test]
+async fn test_billing_summary_reads_day_rollups() {
+    let (db, _tmp) = create_test_db().await;
+    seed(&db).await;
+    UsageRollupModel::rebuild(&db, DAY_START, RANGE_END + 86_400)
+        .await
+        .unwrap();
+
+    // End date is inclusive
+    let summary = get_billing_summary(&db, Some("2026-05-10"), Some("2026-05-11"))
+        .await
+        .unwrap();
+    assert_eq!(summary.pre_migration_requests, 1);
+    assert_eq!(summary.models.len(), 2);
+    assert_eq!(summary.models[0].model, "gpt-4o");
+    assert_eq!(summary.models[0].requests, 3);
+    assert!((summary.total_cost_usd - 200e-9).abs() < 1e-12);
+
+    let user = get_billing_summary_for_user(&db, "user-1", Some("2026-05-12"), None)
+        .await
+        .unwrap();
+    assert_eq!(user.models.len(), 1);
+    assert_eq!(user.models[0].requests, 1);
+    assert_eq!(user.pre_migration_requests, 0);
+
+    assert!(get_billing_summary(&db, Some("May 10"), None)
+        .await
+        .is_err());
+}
@@ -0,0 +1,100 @@
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+//! Per-token, per-model spend derived from `router_logs.token_hash`
+//! (`RouterLogModel::token_model_spend`).
+use burncloud_database::create_database_with_url;
+use burncloud_database_router::{token_hash, RouterDatabase, RouterLogModel};
+use tempfile::NamedTempFile;
+
+/// 2026-05-01 00:00:00 UTC
+const MONTH_START: i64 = 1_777_593_600;
+/// 2026-05-10 00:00:00 UTC
+const DAY_START: i64 = 1_778_371_200;
+
+async fn create_test_db() -> (burncloud_database::Database, NamedTempFile) {
+    let tmp = NamedTempFile::new().unwrap_or_else(|e| panic!("failed to create temp file: {e}"));
+    let url = format!("sqlite://{}?mode=rwc", tmp.path().display());
+    let db = create_database_with_url(&url)
+        .await
+        .unwrap_or_else(|e| panic!("failed to initialize test database: {e}"));
+    RouterDatabase::init(&db)
+        .await
+        .unwrap_or_else(|e| panic!("failed to initialize router tables: {e}"));
+    (db, tmp)
+}
+
+async fn insert_log(
+    db: &burncloud_database::Database,
+    token_hash: &str,
+    model: &str,
+    cost: i64,
+    created_at: &str,
+) {
+    let conn = db.get_connection().unwrap();
+    let sql = r#"
+        INSERT INTO router_logs
+        (request_id, user_id, path, upstream_id, status_code, latency_ms,
+         prompt_tokens, completion_tokens, cost, model, token_hash, created_at)
+        VALUES (?, 'user-1', '/v1/chat/completions', 'up-1', 200, 100,
+                10, 10, ?, ?, ?, ?)
+    "#;
+    sqlx::query(sql)
+        .bind(format!("req-{}", uuid::Uuid::new_v4()))
+        .bind(cost)
+        .bind(model)
+        .bind(token_hash)
+        .bind(created_at)
+        .execute(conn.pool())
+        .await
+        .unwrap_or_else(|e| panic!("insert_log failed: {e}"));
+}
+
+#[tokio::test]
+async fn test_token_model_spend_groups_by_model_and_period() {
+    let (db, _tmp) = create_test_db().await;
+    let current = token_hash("bc_live_current");
+    let previous = token_hash("bc_live_previous");
+    let other = token_hash("bc_live_other");
+
+    // Last month: outside both windows
+    insert_log(&db, &current, "gpt-4o", 1_000, "2026-04-30 23:00:00").await;
+    // Earlier this month: monthly only
+    insert_log(&db, &current, "gpt-4o", 200, "2026-05-03 12:00:00").await;
+    // Today, logged under the pre-rotation key: monthly and daily
+    insert_log(&db, &previous, "gpt-4o", 30, "2026-05-10 08:00:00").await;
+    insert_log(&db, &current, "o1", 7, "2026-05-10 09:00:00").await;
+    // Another token's spend never counts
+    insert_log(&db, &other, "gpt-4o", 5_000, "2026-05-10 09:00:00").await;
+
+    let mut spend = RouterLogModel::token_model_spend(
+        &db,
+        &[current.clone(), previous.clone()],
+        MONTH_START,
+        DAY_START,
+    )
+    .await
+    .unwrap();
+    spend.sort_by(|a, b| a.model.cmp(&b.model));
+
+    assert_eq!(spend.len(), 2);
+    assert_eq!(spend[0].model, "gpt-4o");
+    assert_eq!(spend[0].monthly_nano, 230);
+    assert_eq!(spend[0].daily_nano, 30);
+    assert_eq!(spend[1].model, "o1");
+    assert_eq!(spend[1].monthly_nano, 7);
+    assert_eq!(spend[1].daily_nano, 7);
+
+    let current_only = RouterLogModel::token_model_spend(&db, &[current], MONTH_START, DAY_START)
+        .await
+        .unwrap();
+    let gpt = current_only.iter().find(|s| s.model == "gpt-4o").unwrap();
+    assert_eq!(gpt.monthly_nano, 200);
+    assert_eq!(gpt.daily_nano, 0);
+
+    assert!(
+        RouterLogModel::token_model_spend(&db, &[], MONTH_START, DAY_START)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
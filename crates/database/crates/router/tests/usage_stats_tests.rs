@@ -6,7 +6,7 @@
 /// - B5: get_usage_stats_by_model period parameter (no longer ignored)
 use burncloud_database::create_database_with_url;
 use burncloud_database_router::{
-    get_usage_stats, get_usage_stats_by_model, BalanceModel, RouterDatabase,
+    get_usage_stats, get_usage_stats_by_model, BalanceModel, RouterDatabase, UsageRollupModel,
 };
 use tempfile::NamedTempFile;
 
@@ -23,7 +23,8 @@ async fn create_test_db() -> (burncloud_database::Database, NamedTempFile) {
     (db, tmp)
 }
 
-/// Insert a router_logs row with a specific created_at timestamp.
+/// Insert a router_logs row with a specific created_at timestamp, then
+/// rebuild the usage rollups the stats are read from.
 /// `created_at` must be an ISO8601 string like "2026-04-28 12:00:00".
 async fn insert_log_with_timestamp(
     db: &burncloud_database::Database,
@@ -45,6 +46,11 @@ async fn insert_log_with_timestamp(
         .execute(conn.pool())
         .await
         .unwrap_or_else(|e| panic!("insert_log_with_timestamp failed: {e}"));
+
+    let until = chrono::Utc::now().timestamp() + 86_400;
+    UsageRollupModel::rebuild(db, 0, until)
+        .await
+        .unwrap_or_else(|e| panic!("rollup rebuild failed: {e}"));
 }
 
 /// Insert a user_accounts row with given USD and CNY balances.
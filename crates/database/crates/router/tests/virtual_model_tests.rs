@@ -0,0 +1,165 @@
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+//! Virtual model persistence (`VirtualModelModel`) and `router_logs.served_model`.
+use burncloud_database::create_database_with_url;
+use burncloud_database_router::{
+    RouterDatabase, RouterLog, RouterLogModel, VirtualModel, VirtualModelHop, VirtualModelModel,
+};
+use tempfile::NamedTempFile;
+
+async fn create_test_db() -> (burncloud_database::Database, NamedTempFile) {
+    let tmp = NamedTempFile::new().unwrap_or_else(|e| panic!("failed to create temp file: {e}"));
+    let url = format!("sqlite://{}?mode=rwc", tmp.path().display());
+    let db = create_database_with_url(&url)
+        .await
+        .unwrap_or_else(|e| panic!("failed to initialize test database: {e}"));
+    RouterDatabase::init(&db)
+        .await
+        .unwrap_or_else(|e| panic!("failed to initialize router tables: {e}"));
+    (db, tmp)
+}
+
+fn hop(model: &str) -> VirtualModelHop {
+    VirtualModelHop {
+        model: model.to_string(),
+        param_override: None,
+    }
+}
+
+fn company_smart() -> VirtualModel {
+    let mut cheap = hop("gemini-pro");
+    cheap.param_override = serde_json::json!({ "max_tokens": 1024 })
+        .as_object()
+        .cloned();
+    VirtualModel {
+        name: "company-smart".to_string(),
+        description: Some("Best available model".to_string()),
+        hops: vec![hop("gpt-4.1"), hop("claude-sonnet"), cheap],
+        enabled: true,
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+#[tokio::test]
+async fn test_virtual_model_upsert_get_delete() {
+    let (db, _tmp) = create_test_db().await;
+
+    VirtualModelModel::upsert(&db, &company_smart())
+        .await
+        .unwrap();
+    let stored = VirtualModelModel::get(&db, "company-smart")
+        .await
+        .unwrap()
+        .expect("virtual model stored");
+    let models: Vec<&str> = stored.hops.iter().map(|h| h.model.as_str()).collect();
+    assert_eq!(models, ["gpt-4.1", "claude-sonnet", "gemini-pro"]);
+    assert_eq!(
+        stored.hops[2]
+            .param_override
+            .as_ref()
+            .and_then(|o| o.get("max_tokens")),
+        Some(&serde_json::json!(1024))
+    );
+    assert!(stored.enabled);
+    assert!(stored.created_at > 0);
+
+    // Replace keeps created_at and swaps the chain
+    let mut updated = company_smart();
+    updated.hops = vec![hop("claude-sonnet")];
+    updated.enabled = false;
+    VirtualModelModel::upsert(&db, &updated).await.unwrap();
+    let replaced = VirtualModelModel::get(&db, "company-smart")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(replaced.hops, vec![hop("claude-sonnet")]);
+    assert!(!replaced.enabled);
+    assert_eq!(replaced.created_at, stored.created_at);
+    assert_eq!(VirtualModelModel::list(&db).await.unwrap().len(), 1);
+
+    assert!(VirtualModelModel::delete(&db, "company-smart")
+        .await
+        .unwrap());
+    assert!(!VirtualModelModel::delete(&db, "company-smart")
+        .await
+        .unwrap());
+    assert!(VirtualModelModel::get(&db, "company-smart")
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn test_virtual_model_validation() {
+    assert!(company_smart().validate().is_ok());
+
+    let mut empty = company_smart();
+    empty.hops.clear();
+    assert!(empty.validate().is_err());
+
+    let mut self_ref = company_smart();
+    self_ref.hops.push(hop("company-smart"));
+    assert!(self_ref.validate().is_err());
+
+    let mut duplicate = company_smart();
+    duplicate.hops.push(hop("gpt-4.1"));
+    assert!(duplicate.validate().is_err());
+
+    let mut blank = company_smart();
+    blank.hops.push(hop(" "));
+    assert!(blank.validate().is_err());
+}
+
+#[tokio::test]
+async fn test_router_log_records_served_model() {
+    let (db, _tmp) = create_test_db().await;
+    let log = RouterLog {
+        id: 0,
+        request_id: "vm-req-1".to_string(),
+        user_id: Some("user-1".to_string()),
+        path: "/v1/chat/completions".to_string(),
+        upstream_id: Some("7".to_string()),
+        status_code: 200,
+        latency_ms: 120,
+        prompt_tokens: 10,
+        completion_tokens: 20,
+        cost: 0,
+        model: Some("company-smart".to_string()),
+        cache_read_tokens: 0,
+        reasoning_tokens: 0,
+        pricing_region: None,
+        video_tokens: 0,
+        cache_write_tokens: 0,
+        audio_input_tokens: 0,
+        audio_output_tokens: 0,
+        image_tokens: 0,
+        embedding_tokens: 0,
+        input_cost: 0,
+        output_cost: 0,
+        cache_read_cost: 0,
+        cache_write_cost: 0,
+        audio_cost: 0,
+        image_cost: 0,
+        video_cost: 0,
+        reasoning_cost: 0,
+        embedding_cost: 0,
+        layer_decision: Some("failover_1".to_string()),
+        traffic_color: None,
+        cost_status: None,
+        error_type: None,
+        token_hash: None,
+        served_model: Some("claude-sonnet".to_string()),
+        currency: Some("CNY".to_string()),
+        created_at: None,
+    };
+    RouterDatabase::insert_log(&db, &log).await.unwrap();
+
+    let stored = RouterLogModel::get_by_request_id(&db, "vm-req-1")
+        .await
+        .unwrap()
+        .expect("log stored");
+    assert_eq!(stored.model.as_deref(), Some("company-smart"));
+    assert_eq!(stored.served_model.as_deref(), Some("claude-sonnet"));
+    assert_eq!(stored.currency.as_deref(), Some("CNY"));
+}
@@ -0,0 +1,202 @@
+//! `sys_audit_log`: append-only trail of administrative changes.
+//!
+//! Rows are only ever inserted; this model deliberately has no update or
+//! delete operations.
+
+use burncloud_database::{adapt_sql, Database, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// One recorded change
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct SysAuditLog {
+    pub id: i64,
+    pub created_at: i64,
+    pub actor_id: Option<String>,
+    pub actor_name: Option<String>,
+    /// `console` or `cli`
+    pub source: String,
+    pub ip: Option<String>,
+    /// Dotted verb such as `channel.update`
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<String>,
+    /// JSON snapshot before the change
+    pub before_state: Option<String>,
+    /// JSON snapshot after the change
+    pub after_state: Option<String>,
+    /// JSON object of changed fields, `{"field": {"before": .., "after": ..}}`
+    pub changes: Option<String>,
+}
+
+/// Entry to append
+#[derive(Debug, Clone, Default)]
+pub struct NewAuditLog {
+    pub actor_id: Option<String>,
+    pub actor_name: Option<String>,
+    pub source: String,
+    pub ip: Option<String>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<String>,
+    pub before_state: Option<String>,
+    pub after_state: Option<String>,
+    pub changes: Option<String>,
+}
+
+/// Query filters, all optional and combined with AND
+#[derive(Debug, Clone, Default)]
+pub struct AuditLogFilter {
+    /// Actor id or name
+    pub actor: Option<String>,
+    /// Exact action, or a prefix ending in `.` / `*` such as `token.`
+    pub action: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub source: Option<String>,
+    /// Unix seconds, inclusive
+    pub since: Option<i64>,
+    /// Unix seconds, exclusive
+    pub until: Option<i64>,
+    /// Only entries older than this id, for paging through exports
+    pub before_id: Option<i64>,
+}
+
+const COLUMNS: &str = "id, created_at, actor_id, actor_name, source, ip, action, target_type, \
+                       target_id, before_state, after_state, changes";
+
+enum Param {
+    Text(String),
+    Int(i64),
+}
+
+impl AuditLogFilter {
+    fn where_clause(&self) -> (String, Vec<Param>) {
+        let mut clauses = Vec::new();
+        let mut params = Vec::new();
+        if let Some(actor) = self.actor.as_deref().filter(|v| !v.is_empty()) {
+            clauses.push("(actor_id = ? OR actor_name = ?)");
+            params.push(Param::Text(actor.to_string()));
+            params.push(Param::Text(actor.to_string()));
+        }
+        if let Some(action) = self.action.as_deref().filter(|v| !v.is_empty()) {
+            if let Some(prefix) = action.strip_suffix('*') {
+                clauses.push("action LIKE ?");
+                params.push(Param::Text(format!("{prefix}%")));
+            } else if action.ends_with('.') {
+                clauses.push("action LIKE ?");
+                params.push(Param::Text(format!("{action}%")));
+            } else {
+                clauses.push("action = ?");
+                params.push(Param::Text(action.to_string()));
+            }
+        }
+        for (column, value) in [
+            ("target_type = ?", &self.target_type),
+            ("target_id = ?", &self.target_id),
+            ("source = ?", &self.source),
+        ] {
+            if let Some(value) = value.as_deref().filter(|v| !v.is_empty()) {
+                clauses.push(column);
+                params.push(Param::Text(value.to_string()));
+            }
+        }
+        if let Some(since) = self.since {
+            clauses.push("created_at >= ?");
+            params.push(Param::Int(since));
+        }
+        if let Some(until) = self.until {
+            clauses.push("created_at < ?");
+            params.push(Param::Int(until));
+        }
+        if let Some(before_id) = self.before_id {
+            clauses.push("id < ?");
+            params.push(Param::Int(before_id));
+        }
+        let clause = if clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", clauses.join(" AND "))
+        };
+        (clause, params)
+    }
+}
+
+pub struct AuditLogModel;
+
+impl AuditLogModel {
+    /// Append an entry and return its id
+    pub async fn append(db: &Database, entry: &NewAuditLog) -> Result<i64> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "INSERT INTO sys_audit_log \
+             (created_at, actor_id, actor_name, source, ip, action, target_type, target_id, \
+              before_state, after_state, changes) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING id",
+        );
+        let id = sqlx::query_scalar(&sql)
+            .bind(chrono::Utc::now().timestamp())
+            .bind(&entry.actor_id)
+            .bind(&entry.actor_name)
+            .bind(&entry.source)
+            .bind(&entry.ip)
+            .bind(&entry.action)
+            .bind(&entry.target_type)
+            .bind(&entry.target_id)
+            .bind(&entry.before_state)
+            .bind(&entry.after_state)
+            .bind(&entry.changes)
+            .fetch_one(conn.pool())
+            .await?;
+        Ok(id)
+    }
+
+    /// Newest first
+    pub async fn list(
+        db: &Database,
+        filter: &AuditLogFilter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<SysAuditLog>> {
+        let conn = db.get_connection()?;
+        let (clause, params) = filter.where_clause();
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!(
+                "SELECT {COLUMNS} FROM sys_audit_log {clause} ORDER BY id DESC LIMIT ? OFFSET ?"
+            ),
+        );
+        let mut query = sqlx::query_as(&sql);
+        for param in params {
+            query = match param {
+                Param::Text(value) => query.bind(value),
+                Param::Int(value) => query.bind(value),
+            };
+        }
+        let entries = query
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(conn.pool())
+            .await?;
+        Ok(entries)
+    }
+
+    pub async fn count(db: &Database, filter: &AuditLogFilter) -> Result<i64> {
+        let conn = db.get_connection()?;
+        let (clause, params) = filter.where_clause();
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!("SELECT COUNT(*) FROM sys_audit_log {clause}"),
+        );
+        let mut query = sqlx::query_scalar(&sql);
+        for param in params {
+            query = match param {
+                Param::Text(value) => query.bind(value),
+                Param::Int(value) => query.bind(value),
+            };
+        }
+        let count = query.fetch_one(conn.pool()).await?;
+        Ok(count)
+    }
+}
@@ -4,14 +4,20 @@
 //! - [`setting`] - System settings key/value store (SysSetting, SettingDatabase)
 //! - [`installer`] - Software installation records (InstallerDB)
 //! - [`download`] - Download tasks (SysDownload, DownloadDB)
+//! - [`mail_outbox`] - Outbound mail queue (SysMailOutbox, MailOutboxModel)
+//! - [`audit_log`] - Administrative audit trail (SysAuditLog, AuditLogModel)
 
+pub mod audit_log;
 pub mod download;
 pub mod installer;
+pub mod mail_outbox;
 pub mod setting;
 
 // Re-export primary types for convenience
+pub use audit_log::{AuditLogFilter, AuditLogModel, NewAuditLog, SysAuditLog};
 pub use download::{DownloadDB, SysDownload};
 pub use installer::InstallerDB;
+pub use mail_outbox::{MailOutboxModel, NewMail, SysMailOutbox};
 pub use setting::{SettingDatabase, SysSetting};
 
 // Re-export shared DatabaseError for consumers
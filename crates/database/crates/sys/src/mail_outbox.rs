@@ -0,0 +1,217 @@
+//! `sys_mail_outbox`: outbound mail waiting for (or done with) delivery.
+
+use burncloud_database::{adapt_sql, Database, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+pub const MAIL_PENDING: &str = "pending";
+pub const MAIL_SENT: &str = "sent";
+pub const MAIL_FAILED: &str = "failed";
+
+/// One queued message
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct SysMailOutbox {
+    pub id: i64,
+    pub recipient: String,
+    pub subject: String,
+    pub body_text: String,
+    pub body_html: Option<String>,
+    /// Template that rendered the message, e.g. `password_reset`
+    pub template: String,
+    /// `pending`, `sent` or `failed`
+    pub status: String,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: i64,
+    pub created_at: i64,
+    pub sent_at: Option<i64>,
+}
+
+/// Message to enqueue
+#[derive(Debug, Clone)]
+pub struct NewMail {
+    pub recipient: String,
+    pub subject: String,
+    pub body_text: String,
+    pub body_html: Option<String>,
+    pub template: String,
+}
+
+const COLUMNS: &str = "id, recipient, subject, body_text, body_html, template, status, attempts, \
+                       last_error, next_attempt_at, created_at, sent_at";
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+pub struct MailOutboxModel;
+
+impl MailOutboxModel {
+    /// Queue a message for immediate delivery and return its id
+    pub async fn enqueue(db: &Database, mail: &NewMail) -> Result<i64> {
+        let conn = db.get_connection()?;
+        let now = now();
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "INSERT INTO sys_mail_outbox \
+             (recipient, subject, body_text, body_html, template, status, attempts, next_attempt_at, created_at) \
+             VALUES (?, ?, ?, ?, ?, 'pending', 0, ?, ?) RETURNING id",
+        );
+        let id = sqlx::query_scalar(&sql)
+            .bind(&mail.recipient)
+            .bind(&mail.subject)
+            .bind(&mail.body_text)
+            .bind(&mail.body_html)
+            .bind(&mail.template)
+            .bind(now)
+            .bind(now)
+            .fetch_one(conn.pool())
+            .await?;
+        Ok(id)
+    }
+
+    pub async fn get(db: &Database, id: i64) -> Result<Option<SysMailOutbox>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!("SELECT {COLUMNS} FROM sys_mail_outbox WHERE id = ?"),
+        );
+        let mail = sqlx::query_as(&sql)
+            .bind(id)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(mail)
+    }
+
+    /// Newest first, optionally filtered by status
+    pub async fn list(
+        db: &Database,
+        status: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<SysMailOutbox>> {
+        let conn = db.get_connection()?;
+        let filter = if status.is_some() {
+            "WHERE status = ?"
+        } else {
+            ""
+        };
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!("SELECT {COLUMNS} FROM sys_mail_outbox {filter} ORDER BY id DESC LIMIT ?"),
+        );
+        let mut query = sqlx::query_as(&sql);
+        if let Some(status) = status {
+            query = query.bind(status);
+        }
+        let mails = query.bind(limit).fetch_all(conn.pool()).await?;
+        Ok(mails)
+    }
+
+    /// Claim up to `limit` due messages by pushing their `next_attempt_at`
+    /// `lease_secs` into the future. A row is only returned to the caller
+    /// whose update won, so concurrent workers never send the same message.
+    pub async fn claim_due(
+        db: &Database,
+        limit: i64,
+        lease_secs: i64,
+    ) -> Result<Vec<SysMailOutbox>> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let now = now();
+        let select = adapt_sql(
+            is_postgres,
+            &format!(
+                "SELECT {COLUMNS} FROM sys_mail_outbox \
+                 WHERE status = 'pending' AND next_attempt_at <= ? \
+                 ORDER BY next_attempt_at, id LIMIT ?"
+            ),
+        );
+        let due: Vec<SysMailOutbox> = sqlx::query_as(&select)
+            .bind(now)
+            .bind(limit)
+            .fetch_all(conn.pool())
+            .await?;
+
+        let lease = adapt_sql(
+            is_postgres,
+            "UPDATE sys_mail_outbox SET next_attempt_at = ? \
+             WHERE id = ? AND status = 'pending' AND next_attempt_at = ?",
+        );
+        let mut claimed = Vec::with_capacity(due.len());
+        for mut mail in due {
+            let result = sqlx::query(&lease)
+                .bind(now + lease_secs)
+                .bind(mail.id)
+                .bind(mail.next_attempt_at)
+                .execute(conn.pool())
+                .await?;
+            if result.rows_affected() == 1 {
+                mail.next_attempt_at = now + lease_secs;
+                claimed.push(mail);
+            }
+        }
+        Ok(claimed)
+    }
+
+    /// Mark a message delivered and drop its body, which can hold one-time
+    /// links such as password resets
+    pub async fn mark_sent(db: &Database, id: i64) -> Result<()> {
+        let conn = db.get_connection()?;
+        let now = now();
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE sys_mail_outbox SET status = 'sent', attempts = attempts + 1, \
+             last_error = NULL, sent_at = ?, body_text = '', body_html = NULL WHERE id = ?",
+        );
+        sqlx::query(&sql)
+            .bind(now)
+            .bind(id)
+            .execute(conn.pool())
+            .await?;
+        Ok(())
+    }
+
+    /// Record a failed attempt. `retry_at` schedules the next one, `None`
+    /// gives up and marks the message `failed`.
+    pub async fn mark_attempt_failed(
+        db: &Database,
+        id: i64,
+        error: &str,
+        retry_at: Option<i64>,
+    ) -> Result<()> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE sys_mail_outbox SET status = ?, attempts = attempts + 1, last_error = ?, \
+             next_attempt_at = ? WHERE id = ?",
+        );
+        sqlx::query(&sql)
+            .bind(if retry_at.is_some() {
+                MAIL_PENDING
+            } else {
+                MAIL_FAILED
+            })
+            .bind(error)
+            .bind(retry_at.unwrap_or_else(now))
+            .bind(id)
+            .execute(conn.pool())
+            .await?;
+        Ok(())
+    }
+
+    /// Put a failed message back in the queue for another round of attempts
+    pub async fn retry(db: &Database, id: i64) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE sys_mail_outbox SET status = 'pending', attempts = 0, next_attempt_at = ? \
+             WHERE id = ? AND status = 'failed'",
+        );
+        let result = sqlx::query(&sql)
+            .bind(now())
+            .bind(id)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -1,9 +1,21 @@
-//! Database operations for user_ domain (accounts, roles, bindings, recharges, API keys).
+//! Database operations for user_ domain (accounts, roles, bindings, recharges, API keys,
+//! organizations, OAuth identities, console sessions, TOTP two-factor authentication,
+//! management API keys, online payments, balance notifications).
 //!
 //! The spec-aligned entity layout is split across per-entity files:
 //! - `user_account.rs`: `UserAccount`, `UserAccountInput`
 //! - `user_recharge.rs`: `UserRecharge`
 //! - `user_api_key.rs`: `UserApiKey`, `UserApiKeyModel`, `UserApiKeyInput`, `UserApiKeyUpdateInput`
+//! - `user_organization.rs`: `UserOrganization`, `UserOrganizationMember`, `UserOrganizationInvitation`,
+//!   `UserOrganizationModel`
+//! - `user_oauth.rs`: `UserOAuthIdentity`, `UserOAuthState`, `UserOAuthModel`
+//! - `user_session.rs`: `UserSession`, `UserSessionModel`
+//! - `user_totp.rs`: `UserTotp`, `UserMfaChallenge`, `UserTotpModel`
+//! - `user_role.rs`: `UserRole`, `UserRoleModel` (custom roles and role permissions)
+//! - `user_management_key.rs`: `UserManagementKey`, `UserManagementKeyModel` (management API keys)
+//! - `user_payment.rs`: `UserPayment`, `UserPaymentModel` (top-ups through payment providers)
+//! - `user_notification.rs`: `UserNotificationSettings`, `UserNotificationThreshold`,
+//!   `UserNotificationModel` (low-balance and quota notifications)
 //!
 //! `UserDatabase` is the crate-level controller (initialises sub-tables, seeds default roles,
 //! and contains operation-style helpers). `UserAccountModel` is exposed as a spec-aligned alias
@@ -13,14 +25,38 @@ mod common;
 mod password_reset;
 mod user_account;
 mod user_api_key;
+mod user_management_key;
+mod user_notification;
+mod user_oauth;
+mod user_organization;
+mod user_payment;
 mod user_recharge;
+mod user_role;
+mod user_session;
+mod user_totp;
 
 pub use password_reset::{PasswordResetDatabase, PasswordResetToken};
 pub use user_account::{UserAccount, UserAccountInput};
 pub use user_api_key::{UserApiKey, UserApiKeyInput, UserApiKeyModel, UserApiKeyUpdateInput};
+pub use user_management_key::{UserManagementKey, UserManagementKeyModel};
+pub use user_notification::{
+    UserNotificationModel, UserNotificationSettings, UserNotificationThreshold, THRESHOLD_ABSOLUTE,
+    THRESHOLD_PERCENT,
+};
+pub use user_oauth::{UserOAuthIdentity, UserOAuthModel, UserOAuthState};
+pub use user_organization::{
+    UserOrganization, UserOrganizationInvitation, UserOrganizationMember, UserOrganizationModel,
+};
+pub use user_payment::{
+    UserPayment, UserPaymentModel, PAYMENT_CLOSED, PAYMENT_PAID, PAYMENT_PENDING, PAYMENT_REFUNDED,
+};
 pub use user_recharge::UserRecharge;
+pub use user_role::{UserRole, UserRoleModel};
+pub use user_session::{UserSession, UserSessionModel};
+pub use user_totp::{UserMfaChallenge, UserTotp, UserTotpModel};
 
-use burncloud_database::{Database, Result};
+use burncloud_common::RateLimits;
+use burncloud_database::{adapt_sql, Database, Result};
 use sqlx::Row;
 
 pub struct UserDatabase;
@@ -336,6 +372,48 @@ impl UserDatabase {
         Ok(())
     }
 
+    /// Replace the RPM / TPM / concurrency limits of a user. Returns `false`
+    /// when the user does not exist.
+    pub async fn set_rate_limits(
+        db: &Database,
+        user_id: &str,
+        limits: &RateLimits,
+    ) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE user_accounts SET rpm_limit = ?, tpm_limit = ?, max_concurrency = ? WHERE id = ?",
+        );
+        let result = sqlx::query(&sql)
+            .bind(limits.rpm_limit)
+            .bind(limits.tpm_limit)
+            .bind(limits.max_concurrency)
+            .bind(user_id)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Rate limits of a user; unlimited when none are configured.
+    pub async fn get_rate_limits(db: &Database, user_id: &str) -> Result<RateLimits> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "SELECT rpm_limit, tpm_limit, max_concurrency FROM user_accounts WHERE id = ?",
+        );
+        let row = sqlx::query(&sql)
+            .bind(user_id)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(row
+            .map(|r| RateLimits {
+                rpm_limit: r.get(0),
+                tpm_limit: r.get(1),
+                max_concurrency: r.get(2),
+            })
+            .unwrap_or_default())
+    }
+
     pub async fn get_user_by_id(db: &Database, user_id: &str) -> Result<Option<UserAccount>> {
         let conn = db.get_connection()?;
         let sql = if db.kind() == "postgres" {
@@ -448,7 +526,8 @@ impl UserDatabase {
     }
 
     /// Update balance by delta in nanodollars (generic currency-aware method)
-    /// Defaults to USD if currency is not specified
+    /// Defaults to USD if currency is not specified. Only USD and CNY have
+    /// wallets; other currencies must be converted by the caller.
     pub async fn update_balance(
         db: &Database,
         user_id: &str,
@@ -457,13 +536,17 @@ impl UserDatabase {
     ) -> Result<i64> {
         match currency {
             Some("CNY") => Self::update_balance_cny(db, user_id, delta_nano).await,
-            _ => Self::update_balance_usd(db, user_id, delta_nano).await,
+            None | Some("USD") => Self::update_balance_usd(db, user_id, delta_nano).await,
+            Some(other) => Err(unsupported_wallet(other)),
         }
     }
 
     pub async fn create_recharge(db: &Database, recharge: &UserRecharge) -> Result<i32> {
         let conn = db.get_connection()?;
         let currency = recharge.currency.as_deref().unwrap_or("USD");
+        if currency != "USD" && currency != "CNY" {
+            return Err(unsupported_wallet(currency));
+        }
         let id: i32 = match db.kind().as_str() {
             "sqlite" => {
                 sqlx::query("INSERT INTO user_recharges (user_id, amount, currency, description) VALUES (?, ?, ?, ?)")
@@ -515,3 +598,9 @@ impl UserDatabase {
         Ok(recharges)
     }
 }
+
+fn unsupported_wallet(currency: &str) -> burncloud_database::DatabaseError {
+    burncloud_database::DatabaseError::Query(format!(
+        "no {currency} wallet, convert to USD or CNY first"
+    ))
+}
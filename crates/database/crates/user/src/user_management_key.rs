@@ -0,0 +1,144 @@
+use crate::common::current_timestamp;
+use burncloud_database::{adapt_sql, Database, Result};
+use serde::{Deserialize, Serialize};
+
+/// Long-lived credential for automation against the management API
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserManagementKey {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    /// First characters of the key, shown so it can be recognised
+    pub prefix: String,
+    /// SHA-256 (hex) of the key
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    /// Comma-separated permission names
+    pub scopes: String,
+    /// Comma-separated addresses or CIDR ranges; `None` allows any address
+    pub allowed_ips: Option<String>,
+    pub created_at: i64,
+    pub expires_at: Option<i64>,
+    pub last_used_at: Option<i64>,
+    pub last_used_ip: Option<String>,
+    pub revoked_at: Option<i64>,
+}
+
+impl UserManagementKey {
+    pub fn is_active(&self, now: i64) -> bool {
+        self.revoked_at.is_none() && self.expires_at.is_none_or(|at| at > now)
+    }
+}
+
+const KEY_COLUMNS: &str = "id, user_id, name, prefix, key_hash, scopes, allowed_ips, \
+                           created_at, expires_at, last_used_at, last_used_ip, revoked_at";
+
+pub struct UserManagementKeyModel;
+
+impl UserManagementKeyModel {
+    pub async fn create(db: &Database, key: &UserManagementKey) -> Result<()> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!(
+                "INSERT INTO user_management_keys ({KEY_COLUMNS}) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+            ),
+        );
+        sqlx::query(&sql)
+            .bind(&key.id)
+            .bind(&key.user_id)
+            .bind(&key.name)
+            .bind(&key.prefix)
+            .bind(&key.key_hash)
+            .bind(&key.scopes)
+            .bind(&key.allowed_ips)
+            .bind(key.created_at)
+            .bind(key.expires_at)
+            .bind(key.last_used_at)
+            .bind(&key.last_used_ip)
+            .bind(key.revoked_at)
+            .execute(conn.pool())
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get(db: &Database, id: &str) -> Result<Option<UserManagementKey>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!("SELECT {KEY_COLUMNS} FROM user_management_keys WHERE id = ?"),
+        );
+        let key = sqlx::query_as(&sql)
+            .bind(id)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(key)
+    }
+
+    pub async fn find_by_hash(db: &Database, hash: &str) -> Result<Option<UserManagementKey>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!("SELECT {KEY_COLUMNS} FROM user_management_keys WHERE key_hash = ?"),
+        );
+        let key = sqlx::query_as(&sql)
+            .bind(hash)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(key)
+    }
+
+    /// Keys of one user, or of every user when `user_id` is `None`, newest first
+    pub async fn list(db: &Database, user_id: Option<&str>) -> Result<Vec<UserManagementKey>> {
+        let conn = db.get_connection()?;
+        let filter = if user_id.is_some() {
+            "WHERE user_id = ? "
+        } else {
+            ""
+        };
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!(
+                "SELECT {KEY_COLUMNS} FROM user_management_keys {filter}ORDER BY created_at DESC"
+            ),
+        );
+        let mut query = sqlx::query_as::<_, UserManagementKey>(&sql);
+        if let Some(user_id) = user_id {
+            query = query.bind(user_id);
+        }
+        Ok(query.fetch_all(conn.pool()).await?)
+    }
+
+    /// Record a successful authentication
+    pub async fn touch(db: &Database, id: &str, ip: Option<&str>) -> Result<()> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE user_management_keys SET last_used_at = ?, last_used_ip = ? WHERE id = ?",
+        );
+        sqlx::query(&sql)
+            .bind(current_timestamp())
+            .bind(ip)
+            .bind(id)
+            .execute(conn.pool())
+            .await?;
+        Ok(())
+    }
+
+    /// Revoke a key. Returns `false` when it does not exist or is already
+    /// revoked.
+    pub async fn revoke(db: &Database, id: &str) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE user_management_keys SET revoked_at = ? WHERE id = ? AND revoked_at IS NULL",
+        );
+        let result = sqlx::query(&sql)
+            .bind(current_timestamp())
+            .bind(id)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,241 @@
+use crate::common::current_timestamp;
+use burncloud_database::{adapt_sql, Database, Result};
+use serde::{Deserialize, Serialize};
+
+/// `value` is a percentage of the API key quota left
+pub const THRESHOLD_PERCENT: &str = "percent";
+/// `value` is nanodollars left
+pub const THRESHOLD_ABSOLUTE: &str = "absolute";
+
+/// How a customer is notified, and the optional auto top-up
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserNotificationSettings {
+    pub user_id: String,
+    pub email_enabled: i32,
+    /// Receives a signed JSON POST on every crossing
+    pub webhook_url: Option<String>,
+    /// HMAC key for the webhook signature
+    #[serde(skip_serializing)]
+    pub webhook_secret: Option<String>,
+    /// Provider that charges the saved payment method
+    pub auto_topup_provider: Option<String>,
+    /// Saved payment method at the provider
+    #[serde(skip_serializing)]
+    pub auto_topup_method: Option<String>,
+    /// Minor units of `auto_topup_currency` charged when a wallet threshold
+    /// in that currency is crossed
+    pub auto_topup_amount: Option<i64>,
+    pub auto_topup_currency: Option<String>,
+    pub updated_at: i64,
+}
+
+impl UserNotificationSettings {
+    /// Defaults for users who never saved settings: email only
+    pub fn new(user_id: &str) -> Self {
+        Self {
+            user_id: user_id.to_string(),
+            email_enabled: 1,
+            webhook_url: None,
+            webhook_secret: None,
+            auto_topup_provider: None,
+            auto_topup_method: None,
+            auto_topup_amount: None,
+            auto_topup_currency: None,
+            updated_at: 0,
+        }
+    }
+}
+
+/// A balance or quota level the customer wants to hear about
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserNotificationThreshold {
+    pub id: String,
+    pub user_id: String,
+    /// `token_hash` (md5 hex) of the watched API key; `None` watches the wallet
+    pub token_hash: Option<String>,
+    /// Wallet currency (USD, CNY) of a wallet threshold
+    pub currency: Option<String>,
+    /// [`THRESHOLD_PERCENT`] or [`THRESHOLD_ABSOLUTE`]
+    pub kind: String,
+    pub value: i64,
+    /// Set while the balance is below the threshold
+    pub triggered_at: Option<i64>,
+    pub created_at: i64,
+}
+
+const SETTINGS_COLUMNS: &str = "user_id, email_enabled, webhook_url, webhook_secret, \
+                                auto_topup_provider, auto_topup_method, auto_topup_amount, \
+                                auto_topup_currency, updated_at";
+
+const THRESHOLD_COLUMNS: &str =
+    "id, user_id, token_hash, currency, kind, value, triggered_at, created_at";
+
+pub struct UserNotificationModel;
+
+impl UserNotificationModel {
+    pub async fn get_settings(
+        db: &Database,
+        user_id: &str,
+    ) -> Result<Option<UserNotificationSettings>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!("SELECT {SETTINGS_COLUMNS} FROM user_notification_settings WHERE user_id = ?"),
+        );
+        let settings = sqlx::query_as(&sql)
+            .bind(user_id)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(settings)
+    }
+
+    pub async fn save_settings(db: &Database, settings: &UserNotificationSettings) -> Result<()> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!(
+                "INSERT INTO user_notification_settings ({SETTINGS_COLUMNS}) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?) \
+                 ON CONFLICT (user_id) DO UPDATE SET \
+                 email_enabled = excluded.email_enabled, webhook_url = excluded.webhook_url, \
+                 webhook_secret = excluded.webhook_secret, \
+                 auto_topup_provider = excluded.auto_topup_provider, \
+                 auto_topup_method = excluded.auto_topup_method, \
+                 auto_topup_amount = excluded.auto_topup_amount, \
+                 auto_topup_currency = excluded.auto_topup_currency, \
+                 updated_at = excluded.updated_at"
+            ),
+        );
+        sqlx::query(&sql)
+            .bind(&settings.user_id)
+            .bind(settings.email_enabled)
+            .bind(&settings.webhook_url)
+            .bind(&settings.webhook_secret)
+            .bind(&settings.auto_topup_provider)
+            .bind(&settings.auto_topup_method)
+            .bind(settings.auto_topup_amount)
+            .bind(&settings.auto_topup_currency)
+            .bind(current_timestamp())
+            .execute(conn.pool())
+            .await?;
+        Ok(())
+    }
+
+    pub async fn create_threshold(
+        db: &Database,
+        threshold: &UserNotificationThreshold,
+    ) -> Result<()> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!(
+                "INSERT INTO user_notification_thresholds ({THRESHOLD_COLUMNS}) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+            ),
+        );
+        sqlx::query(&sql)
+            .bind(&threshold.id)
+            .bind(&threshold.user_id)
+            .bind(&threshold.token_hash)
+            .bind(&threshold.currency)
+            .bind(&threshold.kind)
+            .bind(threshold.value)
+            .bind(threshold.triggered_at)
+            .bind(threshold.created_at)
+            .execute(conn.pool())
+            .await?;
+        Ok(())
+    }
+
+    /// Thresholds of a user, oldest first
+    pub async fn list_thresholds(
+        db: &Database,
+        user_id: &str,
+    ) -> Result<Vec<UserNotificationThreshold>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!(
+                "SELECT {THRESHOLD_COLUMNS} FROM user_notification_thresholds \
+                 WHERE user_id = ? ORDER BY created_at, id"
+            ),
+        );
+        let thresholds = sqlx::query_as(&sql)
+            .bind(user_id)
+            .fetch_all(conn.pool())
+            .await?;
+        Ok(thresholds)
+    }
+
+    /// Returns `false` when the user has no such threshold
+    pub async fn delete_threshold(db: &Database, user_id: &str, id: &str) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "DELETE FROM user_notification_thresholds WHERE id = ? AND user_id = ?",
+        );
+        let result = sqlx::query(&sql)
+            .bind(id)
+            .bind(user_id)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Mark a threshold as crossed. Only the first caller gets `true`, so
+    /// concurrent settlements notify once.
+    pub async fn mark_triggered(db: &Database, id: &str) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE user_notification_thresholds SET triggered_at = ? \
+             WHERE id = ? AND triggered_at IS NULL",
+        );
+        let result = sqlx::query(&sql)
+            .bind(current_timestamp())
+            .bind(id)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Re-arm a threshold once the balance is back above it
+    pub async fn clear_triggered(db: &Database, id: &str) -> Result<()> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE user_notification_thresholds SET triggered_at = NULL WHERE id = ?",
+        );
+        sqlx::query(&sql).bind(id).execute(conn.pool()).await?;
+        Ok(())
+    }
+
+    /// Move the thresholds of an API key to its rotated replacement
+    pub async fn rename_token(db: &Database, old_hash: &str, new_hash: &str) -> Result<()> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE user_notification_thresholds SET token_hash = ? WHERE token_hash = ?",
+        );
+        sqlx::query(&sql)
+            .bind(new_hash)
+            .bind(old_hash)
+            .execute(conn.pool())
+            .await?;
+        Ok(())
+    }
+
+    /// Drop the thresholds of a deleted API key
+    pub async fn delete_token(db: &Database, token_hash: &str) -> Result<()> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "DELETE FROM user_notification_thresholds WHERE token_hash = ?",
+        );
+        sqlx::query(&sql)
+            .bind(token_hash)
+            .execute(conn.pool())
+            .await?;
+        Ok(())
+    }
+}
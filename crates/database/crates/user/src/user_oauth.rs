@@ -0,0 +1,160 @@
+use crate::common::current_timestamp;
+use burncloud_database::{adapt_sql, Database, Result};
+use serde::{Deserialize, Serialize};
+
+/// External identity (Google, GitHub, OIDC issuer) linked to a user account
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserOAuthIdentity {
+    pub provider: String,
+    /// Stable subject id issued by the provider
+    pub subject: String,
+    pub user_id: String,
+    pub email: Option<String>,
+    pub created_at: i64,
+}
+
+/// Authorization request waiting for its callback
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserOAuthState {
+    pub state: String,
+    pub provider: String,
+    /// PKCE verifier sent with the token exchange
+    pub code_verifier: String,
+    /// Set when a signed-in user is linking a provider rather than logging in
+    pub link_user_id: Option<String>,
+    pub expires_at: i64,
+}
+
+const IDENTITY_COLUMNS: &str = "provider, subject, user_id, email, created_at";
+
+pub struct UserOAuthModel;
+
+impl UserOAuthModel {
+    pub async fn find_identity(
+        db: &Database,
+        provider: &str,
+        subject: &str,
+    ) -> Result<Option<UserOAuthIdentity>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!(
+                "SELECT {IDENTITY_COLUMNS} FROM user_oauth_identities WHERE provider = ? AND subject = ?"
+            ),
+        );
+        let identity = sqlx::query_as(&sql)
+            .bind(provider)
+            .bind(subject)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(identity)
+    }
+
+    pub async fn list_identities(db: &Database, user_id: &str) -> Result<Vec<UserOAuthIdentity>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!(
+                "SELECT {IDENTITY_COLUMNS} FROM user_oauth_identities WHERE user_id = ? ORDER BY provider"
+            ),
+        );
+        let identities = sqlx::query_as(&sql)
+            .bind(user_id)
+            .fetch_all(conn.pool())
+            .await?;
+        Ok(identities)
+    }
+
+    /// Link an identity. Fails on the primary key or `(user_id, provider)`
+    /// unique constraint when either side is already linked.
+    pub async fn link_identity(
+        db: &Database,
+        provider: &str,
+        subject: &str,
+        user_id: &str,
+        email: Option<&str>,
+    ) -> Result<()> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "INSERT INTO user_oauth_identities (provider, subject, user_id, email, created_at) \
+             VALUES (?, ?, ?, ?, ?)",
+        );
+        sqlx::query(&sql)
+            .bind(provider)
+            .bind(subject)
+            .bind(user_id)
+            .bind(email)
+            .bind(current_timestamp())
+            .execute(conn.pool())
+            .await?;
+        Ok(())
+    }
+
+    pub async fn unlink_identity(db: &Database, user_id: &str, provider: &str) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "DELETE FROM user_oauth_identities WHERE user_id = ? AND provider = ?",
+        );
+        let result = sqlx::query(&sql)
+            .bind(user_id)
+            .bind(provider)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn create_state(db: &Database, state: &UserOAuthState) -> Result<()> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        // Opportunistic cleanup of abandoned authorization requests
+        let cleanup = adapt_sql(
+            is_postgres,
+            "DELETE FROM user_oauth_states WHERE expires_at < ?",
+        );
+        sqlx::query(&cleanup)
+            .bind(current_timestamp())
+            .execute(conn.pool())
+            .await?;
+        let sql = adapt_sql(
+            is_postgres,
+            "INSERT INTO user_oauth_states (state, provider, code_verifier, link_user_id, expires_at) \
+             VALUES (?, ?, ?, ?, ?)",
+        );
+        sqlx::query(&sql)
+            .bind(&state.state)
+            .bind(&state.provider)
+            .bind(&state.code_verifier)
+            .bind(&state.link_user_id)
+            .bind(state.expires_at)
+            .execute(conn.pool())
+            .await?;
+        Ok(())
+    }
+
+    /// Remove and return a pending state. Returns `None` when it does not
+    /// exist or another callback already consumed it.
+    pub async fn take_state(db: &Database, state: &str) -> Result<Option<UserOAuthState>> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let select = adapt_sql(
+            is_postgres,
+            "SELECT state, provider, code_verifier, link_user_id, expires_at \
+             FROM user_oauth_states WHERE state = ?",
+        );
+        let found: Option<UserOAuthState> = sqlx::query_as(&select)
+            .bind(state)
+            .fetch_optional(conn.pool())
+            .await?;
+        let Some(found) = found else {
+            return Ok(None);
+        };
+        let delete = adapt_sql(is_postgres, "DELETE FROM user_oauth_states WHERE state = ?");
+        let result = sqlx::query(&delete)
+            .bind(state)
+            .execute(conn.pool())
+            .await?;
+        Ok((result.rows_affected() == 1).then_some(found))
+    }
+}
@@ -0,0 +1,439 @@
+use crate::common::current_timestamp;
+use burncloud_database::{adapt_sql, Database, DatabaseError, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+
+/// Organization owning a shared wallet and API tokens
+///
+/// Balances, `spend_limit` and `spent` are nanodollars. `spend_limit = -1` means unlimited.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserOrganization {
+    pub id: String,
+    pub name: String,
+    pub owner_id: String,
+    pub balance_usd: i64,
+    pub balance_cny: i64,
+    pub spend_limit: i64,
+    pub spent: i64,
+    /// 1 = active, 0 = disabled
+    pub status: i32,
+    pub created_at: i64,
+}
+
+/// Organization membership; `role` is one of owner / admin / developer / billing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserOrganizationMember {
+    pub org_id: String,
+    pub user_id: String,
+    pub username: Option<String>,
+    pub email: Option<String>,
+    pub role: String,
+    pub joined_at: i64,
+}
+
+/// Pending invitation; `code` is the secret presented when accepting
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserOrganizationInvitation {
+    pub code: String,
+    pub org_id: String,
+    pub email: String,
+    pub role: String,
+    pub invited_by: String,
+    /// pending / accepted / revoked
+    pub status: String,
+    pub expires_at: i64,
+    pub created_at: i64,
+}
+
+const ORG_COLUMNS: &str =
+    "id, name, owner_id, balance_usd, balance_cny, spend_limit, spent, status, created_at";
+
+const INVITATION_COLUMNS: &str =
+    "code, org_id, email, role, invited_by, status, expires_at, created_at";
+
+pub struct UserOrganizationModel;
+
+impl UserOrganizationModel {
+    /// Create an organization and register its owner as a member with the `owner` role
+    pub async fn create(db: &Database, org: &UserOrganization) -> Result<()> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let now = current_timestamp();
+
+        let mut tx = conn.pool().begin().await?;
+        sqlx::query(&adapt_sql(
+            is_postgres,
+            "INSERT INTO user_organizations (id, name, owner_id, balance_usd, balance_cny, spend_limit, spent, status, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        ))
+        .bind(&org.id)
+        .bind(&org.name)
+        .bind(&org.owner_id)
+        .bind(org.balance_usd)
+        .bind(org.balance_cny)
+        .bind(org.spend_limit)
+        .bind(org.spent)
+        .bind(org.status)
+        .bind(org.created_at)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(&adapt_sql(
+            is_postgres,
+            "INSERT INTO user_organization_members (org_id, user_id, role, joined_at) VALUES (?, ?, 'owner', ?)",
+        ))
+        .bind(&org.id)
+        .bind(&org.owner_id)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn get(db: &Database, id: &str) -> Result<Option<UserOrganization>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!(
+                "SELECT {} FROM user_organizations WHERE id = ?",
+                ORG_COLUMNS
+            ),
+        );
+        let org = sqlx::query_as(&sql)
+            .bind(id)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(org)
+    }
+
+    /// List all organizations (admin view)
+    pub async fn list(db: &Database) -> Result<Vec<UserOrganization>> {
+        let conn = db.get_connection()?;
+        let sql = format!(
+            "SELECT {} FROM user_organizations ORDER BY created_at DESC",
+            ORG_COLUMNS
+        );
+        let orgs = sqlx::query_as(&sql).fetch_all(conn.pool()).await?;
+        Ok(orgs)
+    }
+
+    /// List the organizations a user belongs to, paired with the user's role in each
+    pub async fn list_for_user(
+        db: &Database,
+        user_id: &str,
+    ) -> Result<Vec<(UserOrganization, String)>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "SELECT o.id, o.name, o.owner_id, o.balance_usd, o.balance_cny, o.spend_limit, o.spent, o.status, o.created_at, m.role \
+             FROM user_organizations o JOIN user_organization_members m ON m.org_id = o.id \
+             WHERE m.user_id = ? ORDER BY o.created_at DESC",
+        );
+        let rows = sqlx::query(&sql)
+            .bind(user_id)
+            .fetch_all(conn.pool())
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|r| {
+                (
+                    UserOrganization {
+                        id: r.get(0),
+                        name: r.get(1),
+                        owner_id: r.get(2),
+                        balance_usd: r.get(3),
+                        balance_cny: r.get(4),
+                        spend_limit: r.get(5),
+                        spent: r.get(6),
+                        status: r.get(7),
+                        created_at: r.get(8),
+                    },
+                    r.get(9),
+                )
+            })
+            .collect())
+    }
+
+    pub async fn update_name(db: &Database, id: &str, name: &str) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE user_organizations SET name = ? WHERE id = ?",
+        );
+        let result = sqlx::query(&sql)
+            .bind(name)
+            .bind(id)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Set the spend limit in nanodollars (`-1` = unlimited)
+    pub async fn update_spend_limit(db: &Database, id: &str, spend_limit: i64) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE user_organizations SET spend_limit = ? WHERE id = ?",
+        );
+        let result = sqlx::query(&sql)
+            .bind(spend_limit)
+            .bind(id)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn update_status(db: &Database, id: &str, status: i32) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE user_organizations SET status = ? WHERE id = ?",
+        );
+        let result = sqlx::query(&sql)
+            .bind(status)
+            .bind(id)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Add funds to the organization's wallet. `currency` is "USD" or "CNY".
+    pub async fn topup(db: &Database, id: &str, amount_nano: i64, currency: &str) -> Result<bool> {
+        let column = match currency {
+            "USD" => "balance_usd",
+            "CNY" => "balance_cny",
+            other => {
+                return Err(DatabaseError::Query(format!(
+                    "unsupported currency: {}",
+                    other
+                )))
+            }
+        };
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!(
+                "UPDATE user_organizations SET {col} = {col} + ? WHERE id = ?",
+                col = column
+            ),
+        );
+        let result = sqlx::query(&sql)
+            .bind(amount_nano)
+            .bind(id)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    // ============== Members ==============
+
+    pub async fn list_members(db: &Database, org_id: &str) -> Result<Vec<UserOrganizationMember>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "SELECT m.org_id, m.user_id, u.username, u.email, m.role, m.joined_at \
+             FROM user_organization_members m LEFT JOIN user_accounts u ON u.id = m.user_id \
+             WHERE m.org_id = ? ORDER BY m.joined_at",
+        );
+        let rows = sqlx::query(&sql)
+            .bind(org_id)
+            .fetch_all(conn.pool())
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|r| UserOrganizationMember {
+                org_id: r.get(0),
+                user_id: r.get(1),
+                username: r.get(2),
+                email: r.get(3),
+                role: r.get(4),
+                joined_at: r.get(5),
+            })
+            .collect())
+    }
+
+    /// Role of `user_id` in the organization, or `None` if not a member
+    pub async fn get_member_role(
+        db: &Database,
+        org_id: &str,
+        user_id: &str,
+    ) -> Result<Option<String>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "SELECT role FROM user_organization_members WHERE org_id = ? AND user_id = ?",
+        );
+        let role = sqlx::query_scalar(&sql)
+            .bind(org_id)
+            .bind(user_id)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(role)
+    }
+
+    pub async fn add_member(db: &Database, org_id: &str, user_id: &str, role: &str) -> Result<()> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "INSERT INTO user_organization_members (org_id, user_id, role, joined_at) VALUES (?, ?, ?, ?)",
+        );
+        sqlx::query(&sql)
+            .bind(org_id)
+            .bind(user_id)
+            .bind(role)
+            .bind(current_timestamp())
+            .execute(conn.pool())
+            .await?;
+        Ok(())
+    }
+
+    pub async fn update_member_role(
+        db: &Database,
+        org_id: &str,
+        user_id: &str,
+        role: &str,
+    ) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE user_organization_members SET role = ? WHERE org_id = ? AND user_id = ?",
+        );
+        let result = sqlx::query(&sql)
+            .bind(role)
+            .bind(org_id)
+            .bind(user_id)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn remove_member(db: &Database, org_id: &str, user_id: &str) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "DELETE FROM user_organization_members WHERE org_id = ? AND user_id = ?",
+        );
+        let result = sqlx::query(&sql)
+            .bind(org_id)
+            .bind(user_id)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    // ============== Invitations ==============
+
+    pub async fn create_invitation(
+        db: &Database,
+        invitation: &UserOrganizationInvitation,
+    ) -> Result<()> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!(
+                "INSERT INTO user_organization_invitations ({}) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                INVITATION_COLUMNS
+            ),
+        );
+        sqlx::query(&sql)
+            .bind(&invitation.code)
+            .bind(&invitation.org_id)
+            .bind(&invitation.email)
+            .bind(&invitation.role)
+            .bind(&invitation.invited_by)
+            .bind(&invitation.status)
+            .bind(invitation.expires_at)
+            .bind(invitation.created_at)
+            .execute(conn.pool())
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_invitation(
+        db: &Database,
+        code: &str,
+    ) -> Result<Option<UserOrganizationInvitation>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!(
+                "SELECT {} FROM user_organization_invitations WHERE code = ?",
+                INVITATION_COLUMNS
+            ),
+        );
+        let invitation = sqlx::query_as(&sql)
+            .bind(code)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(invitation)
+    }
+
+    pub async fn list_invitations(
+        db: &Database,
+        org_id: &str,
+    ) -> Result<Vec<UserOrganizationInvitation>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!(
+                "SELECT {} FROM user_organization_invitations WHERE org_id = ? ORDER BY created_at DESC",
+                INVITATION_COLUMNS
+            ),
+        );
+        let invitations = sqlx::query_as(&sql)
+            .bind(org_id)
+            .fetch_all(conn.pool())
+            .await?;
+        Ok(invitations)
+    }
+
+    /// Revoke a pending invitation. Returns false if it was not pending.
+    pub async fn revoke_invitation(db: &Database, org_id: &str, code: &str) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE user_organization_invitations SET status = 'revoked' WHERE code = ? AND org_id = ? AND status = 'pending'",
+        );
+        let result = sqlx::query(&sql)
+            .bind(code)
+            .bind(org_id)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Atomically mark a pending invitation accepted and add the user as a member.
+    /// Returns false if the invitation was no longer pending.
+    pub async fn accept_invitation(
+        db: &Database,
+        invitation: &UserOrganizationInvitation,
+        user_id: &str,
+    ) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+
+        let mut tx = conn.pool().begin().await?;
+        let claimed = sqlx::query(&adapt_sql(
+            is_postgres,
+            "UPDATE user_organization_invitations SET status = 'accepted' WHERE code = ? AND status = 'pending'",
+        ))
+        .bind(&invitation.code)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if claimed == 0 {
+            return Ok(false);
+        }
+        sqlx::query(&adapt_sql(
+            is_postgres,
+            "INSERT INTO user_organization_members (org_id, user_id, role, joined_at) VALUES (?, ?, ?, ?)",
+        ))
+        .bind(&invitation.org_id)
+        .bind(user_id)
+        .bind(&invitation.role)
+        .bind(current_timestamp())
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+}
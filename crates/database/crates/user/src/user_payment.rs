@@ -0,0 +1,316 @@
+use crate::common::current_timestamp;
+use burncloud_database::{adapt_sql, Database, DatabaseError, Result};
+use serde::{Deserialize, Serialize};
+
+pub const PAYMENT_PENDING: &str = "pending";
+pub const PAYMENT_PAID: &str = "paid";
+pub const PAYMENT_REFUNDED: &str = "refunded";
+pub const PAYMENT_CLOSED: &str = "closed";
+
+/// Online top-up through a payment provider
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserPayment {
+    pub id: String,
+    pub user_id: String,
+    /// Provider name (`stripe`, `alipay`, `wechat`)
+    pub provider: String,
+    /// Charged amount in the minor unit of `currency`
+    pub amount: i64,
+    /// Wallet currency (USD, CNY)
+    pub currency: String,
+    /// Nanodollars credited to the wallet, 0 until paid
+    pub credited: i64,
+    /// Refunded so far, in the minor unit of `currency`
+    pub refunded_amount: i64,
+    /// `pending`, `paid`, `refunded` (in full) or `closed`
+    pub status: String,
+    /// Checkout session at the provider
+    pub provider_session_id: Option<String>,
+    /// Charge or trade at the provider, known once paid
+    pub provider_payment_id: Option<String>,
+    pub created_at: i64,
+    pub paid_at: Option<i64>,
+    pub updated_at: i64,
+}
+
+const PAYMENT_COLUMNS: &str = "id, user_id, provider, amount, currency, credited, \
+                               refunded_amount, status, provider_session_id, \
+                               provider_payment_id, created_at, paid_at, updated_at";
+
+pub struct UserPaymentModel;
+
+impl UserPaymentModel {
+    pub async fn create(db: &Database, payment: &UserPayment) -> Result<()> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!(
+                "INSERT INTO user_payments ({PAYMENT_COLUMNS}) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+            ),
+        );
+        sqlx::query(&sql)
+            .bind(&payment.id)
+            .bind(&payment.user_id)
+            .bind(&payment.provider)
+            .bind(payment.amount)
+            .bind(&payment.currency)
+            .bind(payment.credited)
+            .bind(payment.refunded_amount)
+            .bind(&payment.status)
+            .bind(&payment.provider_session_id)
+            .bind(&payment.provider_payment_id)
+            .bind(payment.created_at)
+            .bind(payment.paid_at)
+            .bind(payment.updated_at)
+            .execute(conn.pool())
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get(db: &Database, id: &str) -> Result<Option<UserPayment>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!("SELECT {PAYMENT_COLUMNS} FROM user_payments WHERE id = ?"),
+        );
+        let payment = sqlx::query_as(&sql)
+            .bind(id)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(payment)
+    }
+
+    pub async fn find_by_provider_ref(
+        db: &Database,
+        provider: &str,
+        provider_payment_id: &str,
+    ) -> Result<Option<UserPayment>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!(
+                "SELECT {PAYMENT_COLUMNS} FROM user_payments \
+                 WHERE provider = ? AND provider_payment_id = ?"
+            ),
+        );
+        let payment = sqlx::query_as(&sql)
+            .bind(provider)
+            .bind(provider_payment_id)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(payment)
+    }
+
+    /// Payments of one user, or of every user when `user_id` is `None`,
+    /// newest first
+    pub async fn list(
+        db: &Database,
+        user_id: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<UserPayment>> {
+        let conn = db.get_connection()?;
+        let filter = if user_id.is_some() {
+            "WHERE user_id = ? "
+        } else {
+            ""
+        };
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!(
+                "SELECT {PAYMENT_COLUMNS} FROM user_payments {filter}\
+                 ORDER BY created_at DESC, id DESC LIMIT ?"
+            ),
+        );
+        let mut query = sqlx::query_as::<_, UserPayment>(&sql);
+        if let Some(user_id) = user_id {
+            query = query.bind(user_id);
+        }
+        Ok(query.bind(limit).fetch_all(conn.pool()).await?)
+    }
+
+    /// Remember the provider checkout session of a pending payment
+    pub async fn set_session(db: &Database, id: &str, session_id: &str) -> Result<()> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE user_payments SET provider_session_id = ?, updated_at = ? WHERE id = ?",
+        );
+        sqlx::query(&sql)
+            .bind(session_id)
+            .bind(current_timestamp())
+            .bind(id)
+            .execute(conn.pool())
+            .await?;
+        Ok(())
+    }
+
+    /// Move a pending payment to `paid` and credit `credited` nanodollars
+    /// to the wallet in one transaction. Returns `false` when it was not
+    /// pending, so a redelivered webhook credits the wallet only once.
+    pub async fn mark_paid(
+        db: &Database,
+        payment: &UserPayment,
+        provider_payment_id: &str,
+        credited: i64,
+        description: &str,
+    ) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let now = current_timestamp();
+
+        let mut tx = conn.pool().begin().await?;
+        let claimed = sqlx::query(&adapt_sql(
+            is_postgres,
+            "UPDATE user_payments SET status = 'paid', provider_payment_id = ?, credited = ?, \
+             paid_at = ?, updated_at = ? WHERE id = ? AND status = 'pending'",
+        ))
+        .bind(provider_payment_id)
+        .bind(credited)
+        .bind(now)
+        .bind(now)
+        .bind(&payment.id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if claimed == 0 {
+            return Ok(false);
+        }
+        change_wallet(&mut tx, is_postgres, payment, credited, description).await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Close a pending payment the customer abandoned or that expired
+    pub async fn mark_closed(db: &Database, id: &str) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE user_payments SET status = 'closed', updated_at = ? \
+             WHERE id = ? AND status = 'pending'",
+        );
+        let result = sqlx::query(&sql)
+            .bind(current_timestamp())
+            .bind(id)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Raise the refunded total from what `payment` recorded to `total` and
+    /// debit `debited` nanodollars from the wallet in one transaction.
+    /// Returns `false` when another refund changed the total in between.
+    pub async fn record_refund(
+        db: &Database,
+        payment: &UserPayment,
+        total: i64,
+        status: &str,
+        debited: i64,
+        description: &str,
+    ) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+
+        let mut tx = conn.pool().begin().await?;
+        let claimed = sqlx::query(&adapt_sql(
+            is_postgres,
+            "UPDATE user_payments SET refunded_amount = ?, status = ?, updated_at = ? \
+             WHERE id = ? AND refunded_amount = ? AND status IN ('paid', 'refunded')",
+        ))
+        .bind(total)
+        .bind(status)
+        .bind(current_timestamp())
+        .bind(&payment.id)
+        .bind(payment.refunded_amount)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if claimed == 0 {
+            return Ok(false);
+        }
+        change_wallet(&mut tx, is_postgres, payment, -debited, description).await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Customer that holds the saved payment methods of a user at a provider
+    pub async fn customer(db: &Database, user_id: &str, provider: &str) -> Result<Option<String>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "SELECT customer_id FROM user_payment_customers WHERE user_id = ? AND provider = ?",
+        );
+        let customer = sqlx::query_scalar(&sql)
+            .bind(user_id)
+            .bind(provider)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(customer)
+    }
+
+    /// Store the customer of a user at a provider unless one is stored
+    /// already, and return the stored one. Two checkouts racing to create a
+    /// customer thus settle on the same one.
+    pub async fn set_customer(
+        db: &Database,
+        user_id: &str,
+        provider: &str,
+        customer_id: &str,
+    ) -> Result<String> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "INSERT INTO user_payment_customers (user_id, provider, customer_id, created_at) \
+             VALUES (?, ?, ?, ?) ON CONFLICT (user_id, provider) DO NOTHING",
+        );
+        sqlx::query(&sql)
+            .bind(user_id)
+            .bind(provider)
+            .bind(customer_id)
+            .bind(current_timestamp())
+            .execute(conn.pool())
+            .await?;
+        Self::customer(db, user_id, provider)
+            .await?
+            .ok_or_else(|| DatabaseError::Query(format!("no {provider} customer stored")))
+    }
+}
+
+/// Record a wallet change of `amount` nanos for `payment` in
+/// `user_recharges` and apply it to the balance of its currency
+async fn change_wallet(
+    tx: &mut sqlx::Transaction<'_, sqlx::Any>,
+    is_postgres: bool,
+    payment: &UserPayment,
+    amount: i64,
+    description: &str,
+) -> Result<()> {
+    let balance = match payment.currency.as_str() {
+        "USD" => "balance_usd",
+        "CNY" => "balance_cny",
+        other => {
+            return Err(DatabaseError::Query(format!(
+                "no {other} wallet, convert to USD or CNY first"
+            )))
+        }
+    };
+    sqlx::query(&adapt_sql(
+        is_postgres,
+        "INSERT INTO user_recharges (user_id, amount, currency, description) VALUES (?, ?, ?, ?)",
+    ))
+    .bind(&payment.user_id)
+    .bind(amount)
+    .bind(&payment.currency)
+    .bind(description)
+    .execute(&mut **tx)
+    .await?;
+    sqlx::query(&adapt_sql(
+        is_postgres,
+        &format!("UPDATE user_accounts SET {balance} = {balance} + ? WHERE id = ?"),
+    ))
+    .bind(amount)
+    .bind(&payment.user_id)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,209 @@
+use burncloud_database::{adapt_sql, Database, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+
+/// Role a user can be bound to; its permissions live in `user_role_permissions`
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserRole {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    /// 1 = preset seeded by migrations, which cannot be edited or deleted
+    pub built_in: i32,
+}
+
+pub struct UserRoleModel;
+
+impl UserRoleModel {
+    pub async fn list(db: &Database) -> Result<Vec<UserRole>> {
+        let conn = db.get_connection()?;
+        let roles = sqlx::query_as(
+            "SELECT id, name, description, built_in FROM user_roles ORDER BY built_in DESC, name",
+        )
+        .fetch_all(conn.pool())
+        .await?;
+        Ok(roles)
+    }
+
+    pub async fn get_by_name(db: &Database, name: &str) -> Result<Option<UserRole>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "SELECT id, name, description, built_in FROM user_roles WHERE name = ?",
+        );
+        let role = sqlx::query_as(&sql)
+            .bind(name)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(role)
+    }
+
+    /// Every `(role_id, permission)` grant
+    pub async fn grants(db: &Database) -> Result<Vec<(String, String)>> {
+        let conn = db.get_connection()?;
+        let rows = sqlx::query(
+            "SELECT role_id, permission FROM user_role_permissions ORDER BY role_id, permission",
+        )
+        .fetch_all(conn.pool())
+        .await?;
+        Ok(rows.iter().map(|r| (r.get(0), r.get(1))).collect())
+    }
+
+    /// Union of the permissions granted by the user's roles
+    pub async fn user_permissions(db: &Database, user_id: &str) -> Result<Vec<String>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "SELECT DISTINCT p.permission FROM user_role_permissions p \
+             JOIN user_role_bindings b ON b.role_id = p.role_id WHERE b.user_id = ?",
+        );
+        let rows = sqlx::query(&sql)
+            .bind(user_id)
+            .fetch_all(conn.pool())
+            .await?;
+        Ok(rows.iter().map(|r| r.get(0)).collect())
+    }
+
+    /// Create a custom role with its permissions
+    pub async fn create(db: &Database, role: &UserRole, permissions: &[String]) -> Result<()> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let mut tx = conn.pool().begin().await?;
+        sqlx::query(&adapt_sql(
+            is_postgres,
+            "INSERT INTO user_roles (id, name, description, built_in) VALUES (?, ?, ?, 0)",
+        ))
+        .bind(&role.id)
+        .bind(&role.name)
+        .bind(&role.description)
+        .execute(&mut *tx)
+        .await?;
+        let insert = adapt_sql(
+            is_postgres,
+            "INSERT INTO user_role_permissions (role_id, permission) VALUES (?, ?)",
+        );
+        for permission in permissions {
+            sqlx::query(&insert)
+                .bind(&role.id)
+                .bind(permission)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Replace the description and permissions of a custom role.
+    /// Returns `false` when no custom role has this id.
+    pub async fn update(
+        db: &Database,
+        id: &str,
+        description: Option<&str>,
+        permissions: &[String],
+    ) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let mut tx = conn.pool().begin().await?;
+        let updated = sqlx::query(&adapt_sql(
+            is_postgres,
+            "UPDATE user_roles SET description = ? WHERE id = ? AND built_in = 0",
+        ))
+        .bind(description)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        if updated.rows_affected() == 0 {
+            return Ok(false);
+        }
+        sqlx::query(&adapt_sql(
+            is_postgres,
+            "DELETE FROM user_role_permissions WHERE role_id = ?",
+        ))
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        let insert = adapt_sql(
+            is_postgres,
+            "INSERT INTO user_role_permissions (role_id, permission) VALUES (?, ?)",
+        );
+        for permission in permissions {
+            sqlx::query(&insert)
+                .bind(id)
+                .bind(permission)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Delete a custom role and unbind it from every user.
+    /// Returns `false` when no custom role has this id.
+    pub async fn delete(db: &Database, id: &str) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let mut tx = conn.pool().begin().await?;
+        let deleted = sqlx::query(&adapt_sql(
+            is_postgres,
+            "DELETE FROM user_roles WHERE id = ? AND built_in = 0",
+        ))
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        if deleted.rows_affected() == 0 {
+            return Ok(false);
+        }
+        for table in ["user_role_permissions", "user_role_bindings"] {
+            sqlx::query(&adapt_sql(
+                is_postgres,
+                &format!("DELETE FROM {table} WHERE role_id = ?"),
+            ))
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Replace every role binding of a user
+    pub async fn set_user_roles(db: &Database, user_id: &str, role_ids: &[String]) -> Result<()> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let mut tx = conn.pool().begin().await?;
+        sqlx::query(&adapt_sql(
+            is_postgres,
+            "DELETE FROM user_role_bindings WHERE user_id = ?",
+        ))
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        let insert = adapt_sql(
+            is_postgres,
+            "INSERT INTO user_role_bindings (user_id, role_id) VALUES (?, ?)",
+        );
+        for role_id in role_ids {
+            sqlx::query(&insert)
+                .bind(user_id)
+                .bind(role_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Number of users bound to a role
+    pub async fn member_count(db: &Database, role_id: &str) -> Result<i64> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "SELECT COUNT(*) FROM user_role_bindings WHERE role_id = ?",
+        );
+        let count = sqlx::query_scalar(&sql)
+            .bind(role_id)
+            .fetch_one(conn.pool())
+            .await?;
+        Ok(count)
+    }
+}
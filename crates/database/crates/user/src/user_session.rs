@@ -0,0 +1,217 @@
+use crate::common::current_timestamp;
+use burncloud_database::{adapt_sql, Database, Result};
+use serde::{Deserialize, Serialize};
+
+/// A signed-in console device, identified by its rotating refresh token
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserSession {
+    pub id: String,
+    pub user_id: String,
+    /// SHA-256 (hex) of the current refresh token
+    #[serde(skip_serializing)]
+    pub refresh_hash: String,
+    /// Hash of the token replaced by the last rotation
+    #[serde(skip_serializing)]
+    pub previous_hash: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: i64,
+    pub last_used_at: i64,
+    pub expires_at: i64,
+    pub revoked_at: Option<i64>,
+}
+
+impl UserSession {
+    pub fn is_active(&self, now: i64) -> bool {
+        self.revoked_at.is_none() && self.expires_at > now
+    }
+}
+
+const SESSION_COLUMNS: &str = "id, user_id, refresh_hash, previous_hash, user_agent, ip, \
+                               created_at, last_used_at, expires_at, revoked_at";
+
+pub struct UserSessionModel;
+
+impl UserSessionModel {
+    pub async fn create(db: &Database, session: &UserSession) -> Result<()> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        // Opportunistic cleanup of sessions that can no longer be refreshed
+        let cleanup = adapt_sql(
+            is_postgres,
+            "DELETE FROM user_sessions WHERE expires_at < ?",
+        );
+        sqlx::query(&cleanup)
+            .bind(current_timestamp())
+            .execute(conn.pool())
+            .await?;
+        let sql = adapt_sql(
+            is_postgres,
+            &format!(
+                "INSERT INTO user_sessions ({SESSION_COLUMNS}) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+            ),
+        );
+        sqlx::query(&sql)
+            .bind(&session.id)
+            .bind(&session.user_id)
+            .bind(&session.refresh_hash)
+            .bind(&session.previous_hash)
+            .bind(&session.user_agent)
+            .bind(&session.ip)
+            .bind(session.created_at)
+            .bind(session.last_used_at)
+            .bind(session.expires_at)
+            .bind(session.revoked_at)
+            .execute(conn.pool())
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get(db: &Database, id: &str) -> Result<Option<UserSession>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!("SELECT {SESSION_COLUMNS} FROM user_sessions WHERE id = ?"),
+        );
+        let session = sqlx::query_as(&sql)
+            .bind(id)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(session)
+    }
+
+    pub async fn find_by_refresh_hash(db: &Database, hash: &str) -> Result<Option<UserSession>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!("SELECT {SESSION_COLUMNS} FROM user_sessions WHERE refresh_hash = ?"),
+        );
+        let session = sqlx::query_as(&sql)
+            .bind(hash)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(session)
+    }
+
+    /// Session whose previous (already rotated) refresh token has this hash
+    pub async fn find_by_previous_hash(db: &Database, hash: &str) -> Result<Option<UserSession>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!("SELECT {SESSION_COLUMNS} FROM user_sessions WHERE previous_hash = ?"),
+        );
+        let session = sqlx::query_as(&sql)
+            .bind(hash)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(session)
+    }
+
+    /// Replace the refresh token of an active session. Only succeeds while
+    /// `old_hash` is still current, so two concurrent refreshes with the
+    /// same token cannot both win.
+    pub async fn rotate(
+        db: &Database,
+        id: &str,
+        old_hash: &str,
+        new_hash: &str,
+        expires_at: i64,
+    ) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE user_sessions SET refresh_hash = ?, previous_hash = ?, last_used_at = ?, expires_at = ? \
+             WHERE id = ? AND refresh_hash = ? AND revoked_at IS NULL",
+        );
+        let result = sqlx::query(&sql)
+            .bind(new_hash)
+            .bind(old_hash)
+            .bind(current_timestamp())
+            .bind(expires_at)
+            .bind(id)
+            .bind(old_hash)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Revoke one session of a user. Returns `false` when it does not exist,
+    /// belongs to someone else or is already revoked.
+    pub async fn revoke(db: &Database, user_id: &str, id: &str) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE user_sessions SET revoked_at = ? WHERE id = ? AND user_id = ? AND revoked_at IS NULL",
+        );
+        let result = sqlx::query(&sql)
+            .bind(current_timestamp())
+            .bind(id)
+            .bind(user_id)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Revoke every session of a user, returns how many were still active
+    pub async fn revoke_all(db: &Database, user_id: &str) -> Result<u64> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE user_sessions SET revoked_at = ? WHERE user_id = ? AND revoked_at IS NULL",
+        );
+        let result = sqlx::query(&sql)
+            .bind(current_timestamp())
+            .bind(user_id)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Sessions that are neither revoked nor expired, most recently used first
+    pub async fn list_active(db: &Database, user_id: &str) -> Result<Vec<UserSession>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            &format!(
+                "SELECT {SESSION_COLUMNS} FROM user_sessions \
+                 WHERE user_id = ? AND revoked_at IS NULL AND expires_at > ? \
+                 ORDER BY last_used_at DESC"
+            ),
+        );
+        let sessions = sqlx::query_as(&sql)
+            .bind(user_id)
+            .bind(current_timestamp())
+            .fetch_all(conn.pool())
+            .await?;
+        Ok(sessions)
+    }
+
+    /// Access tokens issued before this instant are rejected. `None` when
+    /// the user does not exist.
+    pub async fn tokens_valid_after(db: &Database, user_id: &str) -> Result<Option<i64>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "SELECT tokens_valid_after FROM user_accounts WHERE id = ?",
+        );
+        let value = sqlx::query_scalar(&sql)
+            .bind(user_id)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(value)
+    }
+
+    pub async fn set_tokens_valid_after(db: &Database, user_id: &str, at: i64) -> Result<()> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE user_accounts SET tokens_valid_after = ? WHERE id = ?",
+        );
+        sqlx::query(&sql)
+            .bind(at)
+            .bind(user_id)
+            .execute(conn.pool())
+            .await?;
+        Ok(())
+    }
+}
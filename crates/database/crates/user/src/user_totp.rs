@@ -0,0 +1,267 @@
+use crate::common::current_timestamp;
+use burncloud_database::{adapt_sql, Database, Result};
+use serde::{Deserialize, Serialize};
+
+/// TOTP enrollment of a user
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserTotp {
+    pub user_id: String,
+    /// Base32 shared secret
+    #[serde(skip_serializing)]
+    pub secret: String,
+    /// `None` while the enrollment waits for its first code
+    pub enabled_at: Option<i64>,
+    /// Time step of the last accepted code
+    pub last_used_step: i64,
+    pub created_at: i64,
+}
+
+/// Password-verified login waiting for its second factor
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserMfaChallenge {
+    /// SHA-256 (hex) of the challenge token
+    pub id: String,
+    pub user_id: String,
+    pub expires_at: i64,
+    pub attempts: i32,
+}
+
+pub struct UserTotpModel;
+
+impl UserTotpModel {
+    pub async fn get(db: &Database, user_id: &str) -> Result<Option<UserTotp>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "SELECT user_id, secret, enabled_at, last_used_step, created_at FROM user_totp WHERE user_id = ?",
+        );
+        let totp = sqlx::query_as(&sql)
+            .bind(user_id)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(totp)
+    }
+
+    /// Start (or restart) an enrollment. An enabled enrollment is left
+    /// untouched; returns `false` in that case.
+    pub async fn begin(db: &Database, user_id: &str, secret: &str) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let delete = adapt_sql(
+            is_postgres,
+            "DELETE FROM user_totp WHERE user_id = ? AND enabled_at IS NULL",
+        );
+        sqlx::query(&delete)
+            .bind(user_id)
+            .execute(conn.pool())
+            .await?;
+        let sql = adapt_sql(
+            is_postgres,
+            "INSERT INTO user_totp (user_id, secret, enabled_at, last_used_step, created_at) \
+             VALUES (?, ?, NULL, 0, ?) ON CONFLICT (user_id) DO NOTHING",
+        );
+        let result = sqlx::query(&sql)
+            .bind(user_id)
+            .bind(secret)
+            .bind(current_timestamp())
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn enable(db: &Database, user_id: &str) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE user_totp SET enabled_at = ? WHERE user_id = ? AND enabled_at IS NULL",
+        );
+        let result = sqlx::query(&sql)
+            .bind(current_timestamp())
+            .bind(user_id)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Record `step` as used. Fails when the same or a later step was
+    /// already accepted, so every code works once.
+    pub async fn consume_step(db: &Database, user_id: &str, step: i64) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE user_totp SET last_used_step = ? WHERE user_id = ? AND last_used_step < ?",
+        );
+        let result = sqlx::query(&sql)
+            .bind(step)
+            .bind(user_id)
+            .bind(step)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Remove the enrollment and its recovery codes
+    pub async fn delete(db: &Database, user_id: &str) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let codes = adapt_sql(
+            is_postgres,
+            "DELETE FROM user_recovery_codes WHERE user_id = ?",
+        );
+        sqlx::query(&codes)
+            .bind(user_id)
+            .execute(conn.pool())
+            .await?;
+        let sql = adapt_sql(is_postgres, "DELETE FROM user_totp WHERE user_id = ?");
+        let result = sqlx::query(&sql).bind(user_id).execute(conn.pool()).await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Replace every recovery code of a user
+    pub async fn replace_recovery_codes(
+        db: &Database,
+        user_id: &str,
+        code_hashes: &[String],
+    ) -> Result<()> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        let mut tx = conn.pool().begin().await?;
+        let delete = adapt_sql(
+            is_postgres,
+            "DELETE FROM user_recovery_codes WHERE user_id = ?",
+        );
+        sqlx::query(&delete).bind(user_id).execute(&mut *tx).await?;
+        let insert = adapt_sql(
+            is_postgres,
+            "INSERT INTO user_recovery_codes (user_id, code_hash, used_at) VALUES (?, ?, NULL)",
+        );
+        for hash in code_hashes {
+            sqlx::query(&insert)
+                .bind(user_id)
+                .bind(hash)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Mark an unused recovery code as used
+    pub async fn use_recovery_code(db: &Database, user_id: &str, code_hash: &str) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE user_recovery_codes SET used_at = ? \
+             WHERE user_id = ? AND code_hash = ? AND used_at IS NULL",
+        );
+        let result = sqlx::query(&sql)
+            .bind(current_timestamp())
+            .bind(user_id)
+            .bind(code_hash)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn remaining_recovery_codes(db: &Database, user_id: &str) -> Result<i64> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "SELECT COUNT(*) FROM user_recovery_codes WHERE user_id = ? AND used_at IS NULL",
+        );
+        let count: i64 = sqlx::query_scalar(&sql)
+            .bind(user_id)
+            .fetch_one(conn.pool())
+            .await?;
+        Ok(count)
+    }
+
+    pub async fn is_required(db: &Database, user_id: &str) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "SELECT totp_required FROM user_accounts WHERE id = ?",
+        );
+        let required: Option<i32> = sqlx::query_scalar(&sql)
+            .bind(user_id)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(required.unwrap_or(0) != 0)
+    }
+
+    /// Returns `false` when the user does not exist
+    pub async fn set_required(db: &Database, user_id: &str, required: bool) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE user_accounts SET totp_required = ? WHERE id = ?",
+        );
+        let result = sqlx::query(&sql)
+            .bind(i32::from(required))
+            .bind(user_id)
+            .execute(conn.pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn create_challenge(db: &Database, challenge: &UserMfaChallenge) -> Result<()> {
+        let conn = db.get_connection()?;
+        let is_postgres = db.kind() == "postgres";
+        // Opportunistic cleanup of abandoned logins
+        let cleanup = adapt_sql(
+            is_postgres,
+            "DELETE FROM user_mfa_challenges WHERE expires_at < ?",
+        );
+        sqlx::query(&cleanup)
+            .bind(current_timestamp())
+            .execute(conn.pool())
+            .await?;
+        let sql = adapt_sql(
+            is_postgres,
+            "INSERT INTO user_mfa_challenges (id, user_id, expires_at, attempts) VALUES (?, ?, ?, ?)",
+        );
+        sqlx::query(&sql)
+            .bind(&challenge.id)
+            .bind(&challenge.user_id)
+            .bind(challenge.expires_at)
+            .bind(challenge.attempts)
+            .execute(conn.pool())
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_challenge(db: &Database, id: &str) -> Result<Option<UserMfaChallenge>> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "SELECT id, user_id, expires_at, attempts FROM user_mfa_challenges WHERE id = ?",
+        );
+        let challenge = sqlx::query_as(&sql)
+            .bind(id)
+            .fetch_optional(conn.pool())
+            .await?;
+        Ok(challenge)
+    }
+
+    /// Count a wrong code against a challenge
+    pub async fn fail_challenge(db: &Database, id: &str) -> Result<()> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "UPDATE user_mfa_challenges SET attempts = attempts + 1 WHERE id = ?",
+        );
+        sqlx::query(&sql).bind(id).execute(conn.pool()).await?;
+        Ok(())
+    }
+
+    /// Consume a challenge. Returns `false` when another request already did.
+    pub async fn delete_challenge(db: &Database, id: &str) -> Result<bool> {
+        let conn = db.get_connection()?;
+        let sql = adapt_sql(
+            db.kind() == "postgres",
+            "DELETE FROM user_mfa_challenges WHERE id = ?",
+        );
+        let result = sqlx::query(&sql).bind(id).execute(conn.pool()).await?;
+        Ok(result.rows_affected() == 1)
+    }
+}
@@ -0,0 +1,9 @@
+-- Migration 0022: Per-token and per-user RPM / TPM / concurrency limits (PostgreSQL)
+-- NULL means unlimited. Enforced by the router for every request.
+
+ALTER TABLE router_tokens ADD COLUMN IF NOT EXISTS rpm_limit INTEGER;
+ALTER TABLE router_tokens ADD COLUMN IF NOT EXISTS tpm_limit BIGINT;
+ALTER TABLE router_tokens ADD COLUMN IF NOT EXISTS max_concurrency INTEGER;
+ALTER TABLE user_accounts ADD COLUMN IF NOT EXISTS rpm_limit INTEGER;
+ALTER TABLE user_accounts ADD COLUMN IF NOT EXISTS tpm_limit BIGINT;
+ALTER TABLE user_accounts ADD COLUMN IF NOT EXISTS max_concurrency INTEGER;
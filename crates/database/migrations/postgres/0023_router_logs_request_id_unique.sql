@@ -0,0 +1,5 @@
+-- Migration 0023: Unique router_logs.request_id (PostgreSQL)
+-- router_request_logs.request_id references router_logs(request_id), which needs
+-- a unique parent column. Request ids are generated UUIDs.
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_router_logs_request_id ON router_logs(request_id);
@@ -0,0 +1,49 @@
+-- Migration 0024: Time-series usage rollups (PostgreSQL)
+-- One row per granularity (minute / hour / day), UTC bucket start (unix seconds)
+-- and model, channel, user, group and currency. Empty strings stand for NULL
+-- so the key can be a primary key. Costs are BIGINT nanodollars.
+-- latency_le_N counts requests with latency in (previous bound, N] ms and
+-- latency_inf those above 60000 ms, which gives mergeable p50 / p95 estimates.
+
+CREATE TABLE IF NOT EXISTS usage_rollups (
+    granularity TEXT NOT NULL,
+    bucket_start BIGINT NOT NULL,
+    model TEXT NOT NULL DEFAULT '',
+    upstream_id TEXT NOT NULL DEFAULT '',
+    user_id TEXT NOT NULL DEFAULT '',
+    group_name TEXT NOT NULL DEFAULT '',
+    currency TEXT NOT NULL DEFAULT 'USD',
+    requests BIGINT NOT NULL DEFAULT 0,
+    failed_requests BIGINT NOT NULL DEFAULT 0,
+    prompt_tokens BIGINT NOT NULL DEFAULT 0,
+    completion_tokens BIGINT NOT NULL DEFAULT 0,
+    cache_read_tokens BIGINT NOT NULL DEFAULT 0,
+    cache_write_tokens BIGINT NOT NULL DEFAULT 0,
+    reasoning_tokens BIGINT NOT NULL DEFAULT 0,
+    audio_input_tokens BIGINT NOT NULL DEFAULT 0,
+    audio_output_tokens BIGINT NOT NULL DEFAULT 0,
+    image_tokens BIGINT NOT NULL DEFAULT 0,
+    video_tokens BIGINT NOT NULL DEFAULT 0,
+    embedding_tokens BIGINT NOT NULL DEFAULT 0,
+    cost_nano BIGINT NOT NULL DEFAULT 0,
+    errors_upstream BIGINT NOT NULL DEFAULT 0,
+    errors_timeout BIGINT NOT NULL DEFAULT 0,
+    errors_auth BIGINT NOT NULL DEFAULT 0,
+    errors_rate_limit BIGINT NOT NULL DEFAULT 0,
+    errors_router_reject BIGINT NOT NULL DEFAULT 0,
+    errors_other BIGINT NOT NULL DEFAULT 0,
+    latency_sum_ms BIGINT NOT NULL DEFAULT 0,
+    latency_le_100 BIGINT NOT NULL DEFAULT 0,
+    latency_le_250 BIGINT NOT NULL DEFAULT 0,
+    latency_le_500 BIGINT NOT NULL DEFAULT 0,
+    latency_le_1000 BIGINT NOT NULL DEFAULT 0,
+    latency_le_2500 BIGINT NOT NULL DEFAULT 0,
+    latency_le_5000 BIGINT NOT NULL DEFAULT 0,
+    latency_le_10000 BIGINT NOT NULL DEFAULT 0,
+    latency_le_30000 BIGINT NOT NULL DEFAULT 0,
+    latency_le_60000 BIGINT NOT NULL DEFAULT 0,
+    latency_inf BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (granularity, bucket_start, model, upstream_id, user_id, group_name, currency)
+);
+CREATE INDEX IF NOT EXISTS idx_usage_rollups_user ON usage_rollups(granularity, user_id, bucket_start);
+CREATE INDEX IF NOT EXISTS idx_usage_rollups_model ON usage_rollups(granularity, model, bucket_start);
@@ -0,0 +1,17 @@
+-- Migration 0026: Exchange rate history (PostgreSQL)
+-- One row per rate a refresh or operator proposed. applied = 0 marks a rate the
+-- sanity bound refused, note carries the reason. Billed conversions are audited
+-- by looking up the last applied rate at or before the log timestamp.
+
+CREATE TABLE IF NOT EXISTS billing_exchange_rate_history (
+    id SERIAL PRIMARY KEY,
+    from_currency VARCHAR(10) NOT NULL,
+    to_currency VARCHAR(10) NOT NULL,
+    rate BIGINT NOT NULL,
+    source TEXT NOT NULL,
+    applied INTEGER NOT NULL DEFAULT 1,
+    note TEXT,
+    recorded_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_billing_exchange_rate_history_pair
+    ON billing_exchange_rate_history(from_currency, to_currency, recorded_at);
@@ -0,0 +1,33 @@
+-- Migration 0027: OAuth / OIDC identities and pending login states (PostgreSQL)
+-- One row per (provider, subject) linked to a user. Supersedes the single-provider
+-- user_accounts.google_id / github_id columns, which are copied over once.
+-- user_oauth_states holds the state and PKCE verifier of an authorization request
+-- until its callback consumes it. link_user_id is set when an existing user links.
+
+CREATE TABLE IF NOT EXISTS user_oauth_identities (
+    provider TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    user_id TEXT NOT NULL REFERENCES user_accounts(id) ON DELETE CASCADE,
+    email TEXT,
+    created_at BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (provider, subject),
+    UNIQUE (user_id, provider)
+);
+
+CREATE TABLE IF NOT EXISTS user_oauth_states (
+    state TEXT PRIMARY KEY,
+    provider TEXT NOT NULL,
+    code_verifier TEXT NOT NULL,
+    link_user_id TEXT,
+    expires_at BIGINT NOT NULL
+);
+
+INSERT INTO user_oauth_identities (provider, subject, user_id, email, created_at)
+    SELECT 'google', google_id, id, email, 0 FROM user_accounts
+    WHERE google_id IS NOT NULL AND google_id <> ''
+    ON CONFLICT DO NOTHING;
+
+INSERT INTO user_oauth_identities (provider, subject, user_id, email, created_at)
+    SELECT 'github', github_id, id, email, 0 FROM user_accounts
+    WHERE github_id IS NOT NULL AND github_id <> ''
+    ON CONFLICT DO NOTHING;
@@ -0,0 +1,23 @@
+-- Migration 0029: Console sessions (PostgreSQL)
+-- One row per signed-in device. Only the SHA-256 of the current refresh token
+-- is stored, previous_hash keeps the one it replaced so a replayed refresh
+-- token can be detected and the session revoked.
+-- tokens_valid_after rejects every access token issued before it, which is how
+-- "log out all sessions" and password changes reach tokens without a session.
+
+CREATE TABLE IF NOT EXISTS user_sessions (
+    id VARCHAR(64) PRIMARY KEY,
+    user_id VARCHAR(64) NOT NULL,
+    refresh_hash VARCHAR(64) NOT NULL UNIQUE,
+    previous_hash VARCHAR(64),
+    user_agent TEXT,
+    ip TEXT,
+    created_at BIGINT NOT NULL,
+    last_used_at BIGINT NOT NULL,
+    expires_at BIGINT NOT NULL,
+    revoked_at BIGINT
+);
+CREATE INDEX IF NOT EXISTS idx_user_sessions_user ON user_sessions(user_id);
+CREATE INDEX IF NOT EXISTS idx_user_sessions_previous ON user_sessions(previous_hash);
+
+ALTER TABLE user_accounts ADD COLUMN tokens_valid_after BIGINT NOT NULL DEFAULT 0;
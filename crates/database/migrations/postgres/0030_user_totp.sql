@@ -0,0 +1,29 @@
+-- Migration 0030: TOTP two-factor authentication (PostgreSQL)
+-- user_totp holds the shared secret (base32). enabled_at stays NULL until the
+-- first code is confirmed. last_used_step rejects a code seen before.
+-- Recovery codes and login challenges are stored as SHA-256 hashes.
+-- totp_required lets an administrator make 2FA mandatory for an account.
+
+CREATE TABLE IF NOT EXISTS user_totp (
+    user_id VARCHAR(64) PRIMARY KEY,
+    secret TEXT NOT NULL,
+    enabled_at BIGINT,
+    last_used_step BIGINT NOT NULL DEFAULT 0,
+    created_at BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS user_recovery_codes (
+    user_id VARCHAR(64) NOT NULL,
+    code_hash VARCHAR(64) NOT NULL,
+    used_at BIGINT,
+    PRIMARY KEY (user_id, code_hash)
+);
+
+CREATE TABLE IF NOT EXISTS user_mfa_challenges (
+    id VARCHAR(64) PRIMARY KEY,
+    user_id VARCHAR(64) NOT NULL,
+    expires_at BIGINT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0
+);
+
+ALTER TABLE user_accounts ADD COLUMN totp_required INTEGER NOT NULL DEFAULT 0;
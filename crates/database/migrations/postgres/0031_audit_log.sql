@@ -0,0 +1,25 @@
+-- Migration 0031: Administrative audit log (PostgreSQL)
+-- Append-only record of who changed what through the console API or the CLI.
+-- before_state and after_state hold JSON snapshots with secrets redacted,
+-- changes holds the per-field difference between them.
+
+CREATE TABLE IF NOT EXISTS sys_audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    created_at BIGINT NOT NULL,
+    actor_id TEXT,
+    actor_name TEXT,
+    source VARCHAR(16) NOT NULL,
+    ip TEXT,
+    action VARCHAR(64) NOT NULL,
+    target_type VARCHAR(64) NOT NULL,
+    target_id TEXT,
+    before_state TEXT,
+    after_state TEXT,
+    changes TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_sys_audit_log_created
+    ON sys_audit_log(created_at);
+CREATE INDEX IF NOT EXISTS idx_sys_audit_log_target
+    ON sys_audit_log(target_type, target_id);
+CREATE INDEX IF NOT EXISTS idx_sys_audit_log_actor
+    ON sys_audit_log(actor_id);
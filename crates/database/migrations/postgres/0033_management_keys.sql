@@ -0,0 +1,22 @@
+-- Migration 0033: Management API keys (PostgreSQL)
+-- Long-lived credentials for automation against /console/api. Only the
+-- SHA-256 of a key is stored, prefix keeps its first characters for display.
+-- scopes is a comma-separated list of permission names, allowed_ips a
+-- comma-separated list of addresses or CIDR ranges (NULL allows any address).
+-- expires_at NULL means the key never expires.
+
+CREATE TABLE IF NOT EXISTS user_management_keys (
+    id VARCHAR(64) PRIMARY KEY,
+    user_id VARCHAR(64) NOT NULL,
+    name VARCHAR(64) NOT NULL,
+    prefix VARCHAR(32) NOT NULL,
+    key_hash VARCHAR(64) NOT NULL UNIQUE,
+    scopes TEXT NOT NULL,
+    allowed_ips TEXT,
+    created_at BIGINT NOT NULL,
+    expires_at BIGINT,
+    last_used_at BIGINT,
+    last_used_ip TEXT,
+    revoked_at BIGINT
+);
+CREATE INDEX IF NOT EXISTS idx_user_management_keys_user ON user_management_keys(user_id);
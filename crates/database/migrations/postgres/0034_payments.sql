@@ -0,0 +1,26 @@
+-- Migration 0034: Online payments (PostgreSQL)
+-- One row per checkout started through a payment provider. amount and
+-- refunded_amount are in the minor unit of currency (cents, fen), credited is
+-- the nanodollar amount added to the wallet once the provider confirms the
+-- payment. Webhooks only move a payment forward with guarded updates
+-- (pending to paid, refunded_amount upwards) so redelivered events are no-ops.
+-- status is one of pending, paid, refunded, closed.
+
+CREATE TABLE IF NOT EXISTS user_payments (
+    id VARCHAR(64) PRIMARY KEY,
+    user_id VARCHAR(64) NOT NULL,
+    provider VARCHAR(32) NOT NULL,
+    amount BIGINT NOT NULL,
+    currency VARCHAR(10) NOT NULL,
+    credited BIGINT NOT NULL DEFAULT 0,
+    refunded_amount BIGINT NOT NULL DEFAULT 0,
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',
+    provider_session_id TEXT,
+    provider_payment_id TEXT,
+    created_at BIGINT NOT NULL,
+    paid_at BIGINT,
+    updated_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_user_payments_user ON user_payments(user_id, created_at);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_user_payments_provider_ref
+    ON user_payments(provider, provider_payment_id);
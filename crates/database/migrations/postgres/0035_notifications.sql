@@ -0,0 +1,34 @@
+-- Migration 0035: Low-balance and quota notifications (PostgreSQL)
+-- user_notification_settings holds how a customer is told (email, a signed
+-- webhook) and the optional auto top-up through a saved payment method.
+-- user_notification_thresholds holds when. A row with token_hash NULL watches
+-- the wallet balance in currency, otherwise the API key whose md5 hex is
+-- token_hash (the identifier router_logs uses). kind is percent (value is a
+-- percentage of the key quota left) or absolute (value is nanodollars left).
+-- triggered_at is set when the threshold is crossed and cleared once the
+-- balance recovers, so every crossing notifies once.
+
+CREATE TABLE IF NOT EXISTS user_notification_settings (
+    user_id VARCHAR(64) PRIMARY KEY,
+    email_enabled INTEGER NOT NULL DEFAULT 1,
+    webhook_url TEXT,
+    webhook_secret TEXT,
+    auto_topup_provider VARCHAR(32),
+    auto_topup_method TEXT,
+    auto_topup_amount BIGINT,
+    auto_topup_currency VARCHAR(10),
+    updated_at BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS user_notification_thresholds (
+    id VARCHAR(64) PRIMARY KEY,
+    user_id VARCHAR(64) NOT NULL,
+    token_hash VARCHAR(32),
+    currency VARCHAR(10),
+    kind VARCHAR(16) NOT NULL,
+    value BIGINT NOT NULL,
+    triggered_at BIGINT,
+    created_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_user_notification_thresholds_user
+    ON user_notification_thresholds(user_id);
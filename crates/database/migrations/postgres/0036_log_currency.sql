@@ -0,0 +1,5 @@
+-- Migration 0036: Currency of router_logs.cost (PostgreSQL)
+-- cost is in the currency of the price that billed the request. NULL means USD,
+-- which is what every row written before this migration used.
+
+ALTER TABLE router_logs ADD COLUMN IF NOT EXISTS currency TEXT;
@@ -0,0 +1,12 @@
+-- Migration 0037: Payment provider customers (PostgreSQL)
+-- The customer a provider keeps saved payment methods under, one per user and
+-- provider. The server creates it during a checkout that saves the method, so
+-- auto top-up only ever charges methods attached to the user's own customer.
+
+CREATE TABLE IF NOT EXISTS user_payment_customers (
+    user_id VARCHAR(64) NOT NULL,
+    provider VARCHAR(32) NOT NULL,
+    customer_id TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    PRIMARY KEY (user_id, provider)
+);
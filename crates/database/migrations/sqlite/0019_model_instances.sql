@@ -0,0 +1,18 @@
+-- Migration 0019: Local inference instances (SQLite)
+-- One row per locally served model. Tracks the llama-server process, the port it
+-- listens on and the channel registered for it so that `burncloud model ps|stop`
+-- can see instances started by another process (CLI or server).
+
+CREATE TABLE IF NOT EXISTS model_instances (
+    model_id TEXT PRIMARY KEY,
+    file_path TEXT NOT NULL,
+    port INTEGER NOT NULL,
+    pid INTEGER,
+    channel_id INTEGER,
+    status TEXT NOT NULL DEFAULT 'stopped',
+    last_error TEXT,
+    started_at INTEGER,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_model_instances_status ON model_instances(status);
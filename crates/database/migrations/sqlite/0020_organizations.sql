@@ -0,0 +1,41 @@
+-- Migration 0020: Organizations with shared balances and member roles (SQLite)
+-- Balances and spend are BIGINT nanodollars. spend_limit = -1 means unlimited.
+
+CREATE TABLE IF NOT EXISTS user_organizations (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    owner_id TEXT NOT NULL,
+    balance_usd BIGINT NOT NULL DEFAULT 0,
+    balance_cny BIGINT NOT NULL DEFAULT 0,
+    spend_limit BIGINT NOT NULL DEFAULT -1,
+    spent BIGINT NOT NULL DEFAULT 0,
+    status INTEGER NOT NULL DEFAULT 1,
+    created_at INTEGER NOT NULL DEFAULT 0
+);
+
+-- role: owner / admin / developer / billing
+CREATE TABLE IF NOT EXISTS user_organization_members (
+    org_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    role TEXT NOT NULL,
+    joined_at INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (org_id, user_id)
+);
+CREATE INDEX IF NOT EXISTS idx_user_organization_members_user_id ON user_organization_members(user_id);
+
+-- status: pending / accepted / revoked
+CREATE TABLE IF NOT EXISTS user_organization_invitations (
+    code TEXT PRIMARY KEY,
+    org_id TEXT NOT NULL,
+    email TEXT NOT NULL,
+    role TEXT NOT NULL,
+    invited_by TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    expires_at INTEGER NOT NULL,
+    created_at INTEGER NOT NULL DEFAULT 0
+);
+CREATE INDEX IF NOT EXISTS idx_user_organization_invitations_org_id ON user_organization_invitations(org_id);
+
+-- Tokens owned by an organization are charged against its balance
+ALTER TABLE router_tokens ADD COLUMN org_id TEXT;
+CREATE INDEX IF NOT EXISTS idx_router_tokens_org_id ON router_tokens(org_id);
@@ -0,0 +1,8 @@
+-- Migration 0021: Per-token model allow/deny lists and per-model spend caps (SQLite)
+-- model_policy holds the ModelPolicy JSON (see burncloud_common::model_policy).
+-- router_logs.token_hash is md5(token), the same scheme as router_tokens.old_key_hash,
+-- so spend counters can be rebuilt from logs without storing bearer secrets.
+
+ALTER TABLE router_tokens ADD COLUMN model_policy TEXT;
+ALTER TABLE router_logs ADD COLUMN token_hash VARCHAR(32);
+CREATE INDEX IF NOT EXISTS idx_router_logs_token_hash ON router_logs(token_hash, created_at);
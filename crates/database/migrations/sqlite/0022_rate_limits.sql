@@ -0,0 +1,9 @@
+-- Migration 0022: Per-token and per-user RPM / TPM / concurrency limits (SQLite)
+-- NULL means unlimited. Enforced by the router for every request.
+
+ALTER TABLE router_tokens ADD COLUMN rpm_limit INTEGER;
+ALTER TABLE router_tokens ADD COLUMN tpm_limit BIGINT;
+ALTER TABLE router_tokens ADD COLUMN max_concurrency INTEGER;
+ALTER TABLE user_accounts ADD COLUMN rpm_limit INTEGER;
+ALTER TABLE user_accounts ADD COLUMN tpm_limit BIGINT;
+ALTER TABLE user_accounts ADD COLUMN max_concurrency INTEGER;
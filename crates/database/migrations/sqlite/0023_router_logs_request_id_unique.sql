@@ -0,0 +1,7 @@
+-- Migration 0023: Unique router_logs.request_id (SQLite)
+-- router_request_logs.request_id references router_logs(request_id). SQLite only
+-- accepts a foreign key whose parent column is unique, otherwise inserting bodies
+-- and deleting metadata rows both fail with "foreign key mismatch". Log retention
+-- needs both. Request ids are generated UUIDs.
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_router_logs_request_id ON router_logs(request_id);
@@ -0,0 +1,14 @@
+-- Migration 0025: Virtual models with ordered fallback chains (SQLite)
+-- hops holds a JSON array of {"model": "...", "param_override": {...}}, tried in order.
+-- router_logs.served_model is the concrete model that answered, model keeps the requested name.
+
+CREATE TABLE IF NOT EXISTS router_virtual_models (
+    name TEXT PRIMARY KEY,
+    description TEXT,
+    hops TEXT NOT NULL,
+    enabled INTEGER NOT NULL DEFAULT 1,
+    created_at INTEGER NOT NULL DEFAULT 0,
+    updated_at INTEGER NOT NULL DEFAULT 0
+);
+
+ALTER TABLE router_logs ADD COLUMN served_model TEXT;
@@ -0,0 +1,17 @@
+-- Migration 0026: Exchange rate history (SQLite)
+-- One row per rate a refresh or operator proposed. applied = 0 marks a rate the
+-- sanity bound refused, note carries the reason. Billed conversions are audited
+-- by looking up the last applied rate at or before the log timestamp.
+
+CREATE TABLE IF NOT EXISTS billing_exchange_rate_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    from_currency TEXT NOT NULL,
+    to_currency TEXT NOT NULL,
+    rate BIGINT NOT NULL,
+    source TEXT NOT NULL,
+    applied INTEGER NOT NULL DEFAULT 1,
+    note TEXT,
+    recorded_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_billing_exchange_rate_history_pair
+    ON billing_exchange_rate_history(from_currency, to_currency, recorded_at);
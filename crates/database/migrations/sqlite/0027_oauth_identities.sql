@@ -0,0 +1,32 @@
+-- Migration 0027: OAuth / OIDC identities and pending login states (SQLite)
+-- One row per (provider, subject) linked to a user. Supersedes the single-provider
+-- user_accounts.google_id / github_id columns, which are copied over once.
+-- user_oauth_states holds the state and PKCE verifier of an authorization request
+-- until its callback consumes it. link_user_id is set when an existing user links.
+
+CREATE TABLE IF NOT EXISTS user_oauth_identities (
+    provider TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    email TEXT,
+    created_at INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (provider, subject),
+    UNIQUE (user_id, provider),
+    FOREIGN KEY(user_id) REFERENCES user_accounts(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS user_oauth_states (
+    state TEXT PRIMARY KEY,
+    provider TEXT NOT NULL,
+    code_verifier TEXT NOT NULL,
+    link_user_id TEXT,
+    expires_at INTEGER NOT NULL
+);
+
+INSERT OR IGNORE INTO user_oauth_identities (provider, subject, user_id, email, created_at)
+    SELECT 'google', google_id, id, email, 0 FROM user_accounts
+    WHERE google_id IS NOT NULL AND google_id <> '';
+
+INSERT OR IGNORE INTO user_oauth_identities (provider, subject, user_id, email, created_at)
+    SELECT 'github', github_id, id, email, 0 FROM user_accounts
+    WHERE github_id IS NOT NULL AND github_id <> '';
@@ -0,0 +1,21 @@
+-- Migration 0028: Outbound mail outbox (SQLite)
+-- Every message is written here before delivery so it survives restarts.
+-- status moves pending -> sent, or pending -> failed once attempts run out.
+-- next_attempt_at doubles as a short lease while a worker is sending.
+
+CREATE TABLE IF NOT EXISTS sys_mail_outbox (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    recipient TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    body_text TEXT NOT NULL,
+    body_html TEXT,
+    template TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    sent_at INTEGER
+);
+CREATE INDEX IF NOT EXISTS idx_sys_mail_outbox_due
+    ON sys_mail_outbox(status, next_attempt_at);
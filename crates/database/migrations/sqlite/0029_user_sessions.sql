@@ -0,0 +1,23 @@
+-- Migration 0029: Console sessions (SQLite)
+-- One row per signed-in device. Only the SHA-256 of the current refresh token
+-- is stored, previous_hash keeps the one it replaced so a replayed refresh
+-- token can be detected and the session revoked.
+-- tokens_valid_after rejects every access token issued before it, which is how
+-- "log out all sessions" and password changes reach tokens without a session.
+
+CREATE TABLE IF NOT EXISTS user_sessions (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    refresh_hash TEXT NOT NULL UNIQUE,
+    previous_hash TEXT,
+    user_agent TEXT,
+    ip TEXT,
+    created_at INTEGER NOT NULL,
+    last_used_at INTEGER NOT NULL,
+    expires_at INTEGER NOT NULL,
+    revoked_at INTEGER
+);
+CREATE INDEX IF NOT EXISTS idx_user_sessions_user ON user_sessions(user_id);
+CREATE INDEX IF NOT EXISTS idx_user_sessions_previous ON user_sessions(previous_hash);
+
+ALTER TABLE user_accounts ADD COLUMN tokens_valid_after INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,46 @@
+-- Migration 0032: role permissions (SQLite)
+-- A role grants permissions such as channels:write or logs:read, and a user
+-- holds the union of the permissions of their roles. * grants everything.
+-- Built-in presets (built_in = 1) are seeded here and cannot be edited or
+-- deleted. Custom roles are created from the console.
+
+ALTER TABLE user_roles ADD COLUMN built_in INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS user_role_permissions (
+    role_id TEXT NOT NULL,
+    permission TEXT NOT NULL,
+    PRIMARY KEY (role_id, permission),
+    FOREIGN KEY(role_id) REFERENCES user_roles(id) ON DELETE CASCADE
+);
+
+INSERT OR IGNORE INTO user_roles (id, name, description) VALUES
+    ('role-admin', 'admin', 'Administrator'),
+    ('role-user', 'user', 'Standard User'),
+    ('role-support', 'support', 'Read-only access to users, tokens, channels and logs'),
+    ('role-finance', 'finance', 'Balance top-ups and billing review'),
+    ('role-operator', 'operator', 'Channels, models, security and cache operations');
+
+UPDATE user_roles SET built_in = 1
+WHERE id IN ('role-admin', 'role-user', 'role-support', 'role-finance', 'role-operator');
+
+INSERT OR IGNORE INTO user_role_permissions (role_id, permission) VALUES
+    ('role-admin', '*'),
+    ('role-support', 'users:read'),
+    ('role-support', 'tokens:read'),
+    ('role-support', 'channels:read'),
+    ('role-support', 'logs:read'),
+    ('role-support', 'monitor:read'),
+    ('role-finance', 'users:read'),
+    ('role-finance', 'orgs:read'),
+    ('role-finance', 'logs:read'),
+    ('role-finance', 'billing:topup'),
+    ('role-operator', 'channels:read'),
+    ('role-operator', 'channels:write'),
+    ('role-operator', 'models:read'),
+    ('role-operator', 'models:write'),
+    ('role-operator', 'logs:read'),
+    ('role-operator', 'monitor:read'),
+    ('role-operator', 'security:read'),
+    ('role-operator', 'security:write'),
+    ('role-operator', 'security:break'),
+    ('role-operator', 'cache:write');
@@ -0,0 +1,26 @@
+-- Migration 0034: Online payments (SQLite)
+-- One row per checkout started through a payment provider. amount and
+-- refunded_amount are in the minor unit of currency (cents, fen), credited is
+-- the nanodollar amount added to the wallet once the provider confirms the
+-- payment. Webhooks only move a payment forward with guarded updates
+-- (pending to paid, refunded_amount upwards) so redelivered events are no-ops.
+-- status is one of pending, paid, refunded, closed.
+
+CREATE TABLE IF NOT EXISTS user_payments (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    provider TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    currency TEXT NOT NULL,
+    credited INTEGER NOT NULL DEFAULT 0,
+    refunded_amount INTEGER NOT NULL DEFAULT 0,
+    status TEXT NOT NULL DEFAULT 'pending',
+    provider_session_id TEXT,
+    provider_payment_id TEXT,
+    created_at INTEGER NOT NULL,
+    paid_at INTEGER,
+    updated_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_user_payments_user ON user_payments(user_id, created_at);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_user_payments_provider_ref
+    ON user_payments(provider, provider_payment_id);
@@ -0,0 +1,34 @@
+-- Migration 0035: Low-balance and quota notifications (SQLite)
+-- user_notification_settings holds how a customer is told (email, a signed
+-- webhook) and the optional auto top-up through a saved payment method.
+-- user_notification_thresholds holds when. A row with token_hash NULL watches
+-- the wallet balance in currency, otherwise the API key whose md5 hex is
+-- token_hash (the identifier router_logs uses). kind is percent (value is a
+-- percentage of the key quota left) or absolute (value is nanodollars left).
+-- triggered_at is set when the threshold is crossed and cleared once the
+-- balance recovers, so every crossing notifies once.
+
+CREATE TABLE IF NOT EXISTS user_notification_settings (
+    user_id TEXT PRIMARY KEY,
+    email_enabled INTEGER NOT NULL DEFAULT 1,
+    webhook_url TEXT,
+    webhook_secret TEXT,
+    auto_topup_provider TEXT,
+    auto_topup_method TEXT,
+    auto_topup_amount INTEGER,
+    auto_topup_currency TEXT,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS user_notification_thresholds (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    token_hash TEXT,
+    currency TEXT,
+    kind TEXT NOT NULL,
+    value INTEGER NOT NULL,
+    triggered_at INTEGER,
+    created_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_user_notification_thresholds_user
+    ON user_notification_thresholds(user_id);
@@ -103,6 +103,82 @@ const MIGRATIONS_SQLITE: &[Migration] = &[
         version: "0018_router_request_logs",
         sql: include_str!("../../migrations/sqlite/0018_router_request_logs.sql"),
     },
+    Migration {
+        version: "0019_model_instances",
+        sql: include_str!("../../migrations/sqlite/0019_model_instances.sql"),
+    },
+    Migration {
+        version: "0020_organizations",
+        sql: include_str!("../../migrations/sqlite/0020_organizations.sql"),
+    },
+    Migration {
+        version: "0021_token_model_policy",
+        sql: include_str!("../../migrations/sqlite/0021_token_model_policy.sql"),
+    },
+    Migration {
+        version: "0022_rate_limits",
+        sql: include_str!("../../migrations/sqlite/0022_rate_limits.sql"),
+    },
+    Migration {
+        version: "0023_router_logs_request_id_unique",
+        sql: include_str!("../../migrations/sqlite/0023_router_logs_request_id_unique.sql"),
+    },
+    Migration {
+        version: "0024_usage_rollups",
+        sql: include_str!("../../migrations/sqlite/0024_usage_rollups.sql"),
+    },
+    Migration {
+        version: "0025_virtual_models",
+        sql: include_str!("../../migrations/sqlite/0025_virtual_models.sql"),
+    },
+    Migration {
+        version: "0026_exchange_rate_history",
+        sql: include_str!("../../migrations/sqlite/0026_exchange_rate_history.sql"),
+    },
+    Migration {
+        version: "0027_oauth_identities",
+        sql: include_str!("../../migrations/sqlite/0027_oauth_identities.sql"),
+    },
+    Migration {
+        version: "0028_mail_outbox",
+        sql: include_str!("../../migrations/sqlite/0028_mail_outbox.sql"),
+    },
+    Migration {
+        version: "0029_user_sessions",
+        sql: include_str!("../../migrations/sqlite/0029_user_sessions.sql"),
+    },
+    Migration {
+        version: "0030_user_totp",
+        sql: include_str!("../../migrations/sqlite/0030_user_totp.sql"),
+    },
+    Migration {
+        version: "0031_audit_log",
+        sql: include_str!("../../migrations/sqlite/0031_audit_log.sql"),
+    },
+    Migration {
+        version: "0032_role_permissions",
+        sql: include_str!("../../migrations/sqlite/0032_role_permissions.sql"),
+    },
+    Migration {
+        version: "0033_management_keys",
+        sql: include_str!("../../migrations/sqlite/0033_management_keys.sql"),
+    },
+    Migration {
+        version: "0034_payments",
+        sql: include_str!("../../migrations/sqlite/0034_payments.sql"),
+    },
+    Migration {
+        version: "0035_notifications",
+        sql: include_str!("../../migrations/sqlite/0035_notifications.sql"),
+    },
+    Migration {
+        version: "0036_log_currency",
+        sql: include_str!("../../migrations/sqlite/0036_log_currency.sql"),
+    },
+    Migration {
+        version: "0037_payment_customers",
+        sql: include_str!("../../migrations/sqlite/0037_payment_customers.sql"),
+    },
 ];
 
 // ---------------------------------------------------------------------------
@@ -182,6 +258,82 @@ const MIGRATIONS_POSTGRES: &[Migration] = &[
         version: "0018_router_request_logs",
         sql: include_str!("../../migrations/postgres/0018_router_request_logs.sql"),
     },
+    Migration {
+        version: "0019_model_instances",
+        sql: include_str!("../../migrations/postgres/0019_model_instances.sql"),
+    },
+    Migration {
+        version: "0020_organizations",
+        sql: include_str!("../../migrations/postgres/0020_organizations.sql"),
+    },
+    Migration {
+        version: "0021_token_model_policy",
+        sql: include_str!("../../migrations/postgres/0021_token_model_policy.sql"),
+    },
+    Migration {
+        version: "0022_rate_limits",
+        sql: include_str!("../../migrations/postgres/0022_rate_limits.sql"),
+    },
+    Migration {
+        version: "0023_router_logs_request_id_unique",
+        sql: include_str!("../../migrations/postgres/0023_router_logs_request_id_unique.sql"),
+    },
+    Migration {
+        version: "0024_usage_rollups",
+        sql: include_str!("../../migrations/postgres/0024_usage_rollups.sql"),
+    },
+    Migration {
+        version: "0025_virtual_models",
+        sql: include_str!("../../migrations/postgres/0025_virtual_models.sql"),
+    },
+    Migration {
+        version: "0026_exchange_rate_history",
+        sql: include_str!("../../migrations/postgres/0026_exchange_rate_history.sql"),
+    },
+    Migration {
+        version: "0027_oauth_identities",
+        sql: include_str!("../../migrations/postgres/0027_oauth_identities.sql"),
+    },
+    Migration {
+        version: "0028_mail_outbox",
+        sql: include_str!("../../migrations/postgres/0028_mail_outbox.sql"),
+    },
+    Migration {
+        version: "0029_user_sessions",
+        sql: include_str!("../../migrations/postgres/0029_user_sessions.sql"),
+    },
+    Migration {
+        version: "0030_user_totp",
+        sql: include_str!("../../migrations/postgres/0030_user_totp.sql"),
+    },
+    Migration {
+        version: "0031_audit_log",
+        sql: include_str!("../../migrations/postgres/0031_audit_log.sql"),
+    },
+    Migration {
+        version: "0032_role_permissions",
+        sql: include_str!("../../migrations/postgres/0032_role_permissions.sql"),
+    },
+    Migration {
+        version: "0033_management_keys",
+        sql: include_str!("../../migrations/postgres/0033_management_keys.sql"),
+    },
+    Migration {
+        version: "0034_payments",
+        sql: include_str!("../../migrations/postgres/0034_payments.sql"),
+    },
+    Migration {
+        version: "0035_notifications",
+        sql: include_str!("../../migrations/postgres/0035_notifications.sql"),
+    },
+    Migration {
+        version: "0036_log_currency",
+        sql: include_str!("../../migrations/postgres/0036_log_currency.sql"),
+    },
+    Migration {
+        version: "0037_payment_customers",
+        sql: include_str!("../../migrations/postgres/0037_payment_customers.sql"),
+    },
 ];
 
 // ---------------------------------------------------------------------------
@@ -23,10 +23,13 @@
 
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
+use std::sync::OnceLock;
 use std::time::{Duration, Instant};
 
 use dashmap::DashMap;
 
+use crate::state_sync::{SyncEvent, SyncPublisher};
+
 /// Default sticky TTL — within this window the cached channel is always returned.
 pub const DEFAULT_STICKY_TTL: Duration = Duration::from_secs(5 * 60);
 /// Default hard TTL — entry is re-evaluated past this point.
@@ -50,6 +53,8 @@ pub struct AffinityCache {
     entries: DashMap<CacheKey, CacheEntry>,
     sticky_ttl: Duration,
     hard_ttl: Duration,
+    /// Shares entries with other router instances once attached.
+    sync: OnceLock<SyncPublisher>,
 }
 
 impl Default for AffinityCache {
@@ -71,6 +76,7 @@ impl AffinityCache {
             entries: DashMap::new(),
             sticky_ttl,
             hard_ttl,
+            sync: OnceLock::new(),
         }
     }
 
@@ -85,36 +91,122 @@ impl AffinityCache {
     ///   the affinity hint is "soft".)
     pub fn lookup(&self, key: &str, model: &str) -> Option<i32> {
         let compound = (key.to_string(), model.to_string());
-        let entry = self.entries.get(&compound)?;
+        let Some(entry) = self.entries.get(&compound) else {
+            crate::metrics::record_affinity_lookup("miss");
+            return None;
+        };
         let age = entry.created_at.elapsed();
         if age > self.hard_ttl {
             drop(entry);
             self.entries.remove(&compound);
+            crate::metrics::record_affinity_lookup("expired");
+            crate::metrics::record_affinity_eviction("expired", self.entries.len());
             return None;
         }
         if age > self.sticky_ttl {
+            crate::metrics::record_affinity_lookup("stale");
             return None;
         }
+        crate::metrics::record_affinity_lookup("hit");
         Some(entry.channel_id)
     }
 
     /// Insert or refresh an affinity entry for `(key, model) → channel_id`.
+    ///
+    /// With state sync attached, new or re-pointed entries are shared right
+    /// away; refreshes only once the previous share is half a sticky TTL old,
+    /// so remote copies never lag by more than that.
     pub fn insert(&self, key: &str, model: &str, channel_id: i32) {
         let compound = (key.to_string(), model.to_string());
-        self.entries.insert(
+        let previous = self.entries.insert(
             compound,
             CacheEntry {
                 channel_id,
                 created_at: Instant::now(),
             },
         );
+        crate::metrics::set_affinity_entries(self.entries.len());
+        let share = previous.is_none_or(|p| {
+            p.channel_id != channel_id || p.created_at.elapsed() > self.sticky_ttl / 2
+        });
+        if share {
+            self.publish(SyncEvent::AffinitySet {
+                key: key.to_string(),
+                model: model.to_string(),
+                channel_id,
+                age_ms: 0,
+            });
+        }
     }
 
     /// Evict the entry for `(key, model)`. Used by failover so a sick channel
     /// isn't re-affined on the next request.
     pub fn evict(&self, key: &str, model: &str) {
         let compound = (key.to_string(), model.to_string());
-        self.entries.remove(&compound);
+        if self.entries.remove(&compound).is_some() {
+            crate::metrics::record_affinity_eviction("failover", self.entries.len());
+        }
+        self.publish(SyncEvent::AffinityEvicted {
+            key: key.to_string(),
+            model: model.to_string(),
+        });
+    }
+
+    /// Publish local changes to other router instances from now on.
+    pub fn attach_sync(&self, publisher: SyncPublisher) {
+        let _ = self.sync.set(publisher);
+    }
+
+    fn publish(&self, event: SyncEvent) {
+        if let Some(sync) = self.sync.get() {
+            sync.publish(event);
+        }
+    }
+
+    /// Take over an entry another instance created `age` ago, unless the
+    /// local entry is newer.
+    pub fn apply_remote_set(&self, key: &str, model: &str, channel_id: i32, age: Duration) {
+        if age > self.sticky_ttl {
+            return;
+        }
+        let Some(created_at) = Instant::now().checked_sub(age) else {
+            return;
+        };
+        let compound = (key.to_string(), model.to_string());
+        let mut entry = self.entries.entry(compound).or_insert(CacheEntry {
+            channel_id,
+            created_at,
+        });
+        if entry.created_at < created_at {
+            *entry = CacheEntry {
+                channel_id,
+                created_at,
+            };
+        }
+        drop(entry);
+        crate::metrics::set_affinity_entries(self.entries.len());
+    }
+
+    /// Drop an entry another instance evicted after a failure.
+    pub fn apply_remote_evict(&self, key: &str, model: &str) {
+        let compound = (key.to_string(), model.to_string());
+        if self.entries.remove(&compound).is_some() {
+            crate::metrics::record_affinity_eviction("remote", self.entries.len());
+        }
+    }
+
+    /// Sticky-fresh entries, for the periodic state sync snapshot.
+    pub fn sync_snapshot(&self) -> Vec<SyncEvent> {
+        self.entries
+            .iter()
+            .filter(|e| e.created_at.elapsed() <= self.sticky_ttl)
+            .map(|e| SyncEvent::AffinitySet {
+                key: e.key().0.clone(),
+                model: e.key().1.clone(),
+                channel_id: e.channel_id,
+                age_ms: u64::try_from(e.created_at.elapsed().as_millis()).unwrap_or(u64::MAX),
+            })
+            .collect()
     }
 
     /// Approximate live entry count (DashMap len is approximate under concurrency).
@@ -288,6 +288,38 @@ impl AimdController {
         }
     }
 
+    /// Time left in the current cooldown, if cooling down.
+    pub fn cooldown_remaining(&self) -> Option<Duration> {
+        if self.state != RateLimitState::Cooldown {
+            return None;
+        }
+        self.cooldown_until
+            .map(|until| until.saturating_duration_since(Instant::now()))
+            .filter(|left| !left.is_zero())
+    }
+
+    /// Adopt limits learned by another router instance.
+    ///
+    /// Streaks and the learning counter stay local; only the limits and an
+    /// active cooldown are taken over.
+    pub fn adopt(
+        &mut self,
+        current_limit: u32,
+        learned_limit: Option<u32>,
+        cooldown: Option<Duration>,
+    ) {
+        let now = Instant::now();
+        self.current_limit = current_limit.clamp(1, self.config.max_limit);
+        if learned_limit.is_some() {
+            self.learned_limit = learned_limit;
+        }
+        if let Some(cooldown) = cooldown {
+            self.state = RateLimitState::Cooldown;
+            self.cooldown_until = Some(now + cooldown);
+        }
+        self.last_adjusted_at = Some(now);
+    }
+
     /// Enter cooldown state.
     fn enter_cooldown(&mut self, now: Instant) {
         self.state = RateLimitState::Cooldown;
@@ -4,7 +4,8 @@
 //! with the existing channel state tracking system.
 
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
 
 use dashmap::DashMap;
 
@@ -12,6 +13,7 @@ use dashmap::DashMap;
 use crate::channel_state::ChannelStateTracker;
 use crate::response_quality::{ResponseQuality, ResponseQualityDetector};
 use crate::smart_circuit_breaker::{MultiLevelCircuitBreaker, SmartCircuitBreakerConfig, TripLevel};
+use crate::state_sync::{SyncEvent, SyncPublisher};
 
 /// Integrated channel health manager combining:
 /// - Response quality detection
@@ -24,6 +26,8 @@ pub struct ChannelHealthManager {
     config: SmartCircuitBreakerConfig,
     /// Response quality detector
     detector: ResponseQualityDetector,
+    /// Shares trips and resets with other router instances once attached
+    sync: OnceLock<SyncPublisher>,
 }
 
 impl ChannelHealthManager {
@@ -33,6 +37,7 @@ impl ChannelHealthManager {
             breakers: DashMap::new(),
             config: SmartCircuitBreakerConfig::default(),
             detector: ResponseQualityDetector::new(),
+            sync: OnceLock::new(),
         }
     }
 
@@ -42,6 +47,7 @@ impl ChannelHealthManager {
             breakers: DashMap::new(),
             config,
             detector: ResponseQualityDetector::new(),
+            sync: OnceLock::new(),
         }
     }
 
@@ -68,10 +74,20 @@ impl ChannelHealthManager {
         );
 
         // 2. Record to circuit breaker
-        self.breakers
+        let tripped = self
+            .breakers
             .entry(channel_id)
             .or_insert_with(|| MultiLevelCircuitBreaker::new(self.config.clone()))
             .record(model, &quality, latency_ms);
+        match tripped {
+            Some(TripLevel::Model { until, reason }) => {
+                self.publish_trip(channel_id, Some(model), until, reason)
+            }
+            Some(TripLevel::Channel { until, reason }) => {
+                self.publish_trip(channel_id, None, until, reason)
+            }
+            _ => {}
+        }
 
         // 3. Log significant events
         match &quality {
@@ -129,18 +145,96 @@ impl ChannelHealthManager {
 
     /// Manual reset for a channel
     pub fn reset_channel(&self, channel_id: i32) {
-        if let Some(mut breaker) = self.breakers.get_mut(&channel_id) {
-            breaker.reset();
-        }
+        self.reset_breakers(Some(channel_id));
+        self.publish(SyncEvent::HealthReset {
+            channel_id: Some(channel_id),
+        });
     }
 
     /// Manual reset for all channels
     pub fn reset_all(&self) {
-        for mut breaker in self.breakers.iter_mut() {
-            breaker.reset();
+        self.reset_breakers(None);
+        self.publish(SyncEvent::HealthReset { channel_id: None });
+    }
+
+    /// Publish trips and resets to other router instances from now on
+    pub fn attach_sync(&self, publisher: SyncPublisher) {
+        let _ = self.sync.set(publisher);
+    }
+
+    fn publish(&self, event: SyncEvent) {
+        if let Some(sync) = self.sync.get() {
+            sync.publish(event);
         }
     }
 
+    fn publish_trip(&self, channel_id: i32, model: Option<&str>, until: Instant, reason: String) {
+        let left = until.saturating_duration_since(Instant::now());
+        self.publish(SyncEvent::HealthTripped {
+            channel_id,
+            model: model.map(str::to_string),
+            open_ms: u64::try_from(left.as_millis()).unwrap_or(u64::MAX),
+            reason,
+        });
+    }
+
+    /// Apply a trip reported by another router instance
+    pub fn apply_remote_trip(
+        &self,
+        channel_id: i32,
+        model: Option<&str>,
+        reason: &str,
+        duration: Duration,
+    ) {
+        if duration.is_zero() {
+            return;
+        }
+        self.breakers
+            .entry(channel_id)
+            .or_insert_with(|| MultiLevelCircuitBreaker::new(self.config.clone()))
+            .trip(model, reason, duration);
+    }
+
+    /// Apply a reset made on another router instance
+    pub fn apply_remote_reset(&self, channel_id: Option<i32>) {
+        self.reset_breakers(channel_id);
+    }
+
+    fn reset_breakers(&self, channel_id: Option<i32>) {
+        match channel_id {
+            Some(channel_id) => {
+                if let Some(mut breaker) = self.breakers.get_mut(&channel_id) {
+                    breaker.reset();
+                }
+            }
+            None => {
+                for mut breaker in self.breakers.iter_mut() {
+                    breaker.reset();
+                }
+            }
+        }
+    }
+
+    /// Open breakers of every channel, for the periodic state sync snapshot
+    pub fn sync_snapshot(&self) -> Vec<SyncEvent> {
+        self.breakers
+            .iter()
+            .flat_map(|entry| {
+                let channel_id = *entry.key();
+                entry
+                    .open_breakers()
+                    .into_iter()
+                    .map(move |(model, left, reason)| SyncEvent::HealthTripped {
+                        channel_id,
+                        model,
+                        open_ms: u64::try_from(left.as_millis()).unwrap_or(u64::MAX),
+                        reason,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
     /// Get circuit breaker status for monitoring
     pub fn get_status(&self, channel_id: i32) -> Option<ChannelHealthStatus> {
         self.breakers.get(&channel_id).map(|breaker| {
@@ -4,6 +4,7 @@
 //! of upstream channels and their models.
 
 use std::collections::HashMap;
+use std::sync::OnceLock;
 use std::time::{Duration, Instant};
 
 use dashmap::DashMap;
@@ -11,6 +12,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::aimd_limiter::{AimdConfig, AimdController, AimdSnapshot};
 use crate::circuit_breaker::{FailureType, RateLimitScope};
+use crate::state_sync::{SyncEvent, SyncPublisher};
 
 // Health score penalty factors
 const PENALTY_AUTH_FAILED: f64 = 0.1;
@@ -182,6 +184,8 @@ impl ChannelState {
 pub struct ChannelStateTracker {
     /// Map of channel_id to ChannelState
     channel_states: DashMap<i32, ChannelState>,
+    /// Shares AIMD limit changes with other router instances once attached.
+    sync: OnceLock<SyncPublisher>,
 }
 
 impl ChannelStateTracker {
@@ -189,6 +193,7 @@ impl ChannelStateTracker {
     pub fn new() -> Self {
         Self {
             channel_states: DashMap::new(),
+            sync: OnceLock::new(),
         }
     }
 }
@@ -375,6 +380,13 @@ impl ChannelStateTracker {
                             model_state.failure_count += 1;
                             // Update adaptive rate limiter
                             model_state.adaptive_limit.on_rate_limited(*retry_after);
+                            crate::metrics::set_aimd_limits(
+                                channel_id,
+                                model_name,
+                                model_state.adaptive_limit.current_limit,
+                                model_state.adaptive_limit.get_learned_limit(),
+                            );
+                            self.publish_aimd(channel_id, model_name, &model_state.adaptive_limit);
                         }
                     }
                     RateLimitScope::Unknown => {
@@ -385,6 +397,13 @@ impl ChannelStateTracker {
                             let model_state =
                                 channel_state.get_or_create_model(model_name, channel_id);
                             model_state.adaptive_limit.on_rate_limited(*retry_after);
+                            crate::metrics::set_aimd_limits(
+                                channel_id,
+                                model_name,
+                                model_state.adaptive_limit.current_limit,
+                                model_state.adaptive_limit.get_learned_limit(),
+                            );
+                            self.publish_aimd(channel_id, model_name, &model_state.adaptive_limit);
                         }
                     }
                 }
@@ -502,8 +521,25 @@ impl ChannelStateTracker {
 
         // Update adaptive rate limiter with learned upstream limit
         let prev_learned = model_state.adaptive_limit.get_learned_limit();
+        let prev_limits = (
+            model_state.adaptive_limit.current_limit,
+            model_state.adaptive_limit.state,
+        );
         model_state.adaptive_limit.on_success(upstream_limit);
         let new_learned = model_state.adaptive_limit.get_learned_limit();
+        crate::metrics::set_aimd_limits(
+            channel_id,
+            model_name,
+            model_state.adaptive_limit.current_limit,
+            new_learned,
+        );
+        let new_limits = (
+            model_state.adaptive_limit.current_limit,
+            model_state.adaptive_limit.state,
+        );
+        if new_learned != prev_learned || new_limits != prev_limits {
+            self.publish_aimd(channel_id, model_name, &model_state.adaptive_limit);
+        }
         // Return learned limit if it changed (for BudgetUpdate feedback)
         if new_learned != prev_learned {
             new_learned
@@ -512,6 +548,77 @@ impl ChannelStateTracker {
         }
     }
 
+    /// Publish AIMD limit changes to other router instances from now on.
+    pub fn attach_sync(&self, publisher: SyncPublisher) {
+        let _ = self.sync.set(publisher);
+    }
+
+    fn aimd_event(channel_id: i32, model: &str, limiter: &AimdController) -> SyncEvent {
+        SyncEvent::AimdLimits {
+            channel_id,
+            model: model.to_string(),
+            current_limit: limiter.current_limit,
+            learned_limit: limiter.get_learned_limit(),
+            cooldown_ms: limiter
+                .cooldown_remaining()
+                .map(|left| u64::try_from(left.as_millis()).unwrap_or(u64::MAX)),
+        }
+    }
+
+    fn publish_aimd(&self, channel_id: i32, model: &str, limiter: &AimdController) {
+        if let Some(sync) = self.sync.get() {
+            sync.publish(Self::aimd_event(channel_id, model, limiter));
+        }
+    }
+
+    /// Adopt AIMD limits learned by another router instance.
+    ///
+    /// Returns the learned limit if it changed (for BudgetUpdate feedback,
+    /// like [`Self::record_success`]).
+    pub fn apply_remote_aimd(
+        &self,
+        channel_id: i32,
+        model: &str,
+        current_limit: u32,
+        learned_limit: Option<u32>,
+        cooldown: Option<Duration>,
+    ) -> Option<u32> {
+        let mut channel_state = self
+            .channel_states
+            .entry(channel_id)
+            .or_insert_with(|| ChannelState::new(channel_id));
+        let model_state = channel_state.get_or_create_model(model, channel_id);
+        let prev_learned = model_state.adaptive_limit.get_learned_limit();
+        model_state
+            .adaptive_limit
+            .adopt(current_limit, learned_limit, cooldown);
+        let new_learned = model_state.adaptive_limit.get_learned_limit();
+        crate::metrics::set_aimd_limits(
+            channel_id,
+            model,
+            model_state.adaptive_limit.current_limit,
+            new_learned,
+        );
+        new_learned.filter(|_| new_learned != prev_learned)
+    }
+
+    /// AIMD limits of every channel/model pair that has left its initial
+    /// state, for the periodic state sync snapshot.
+    pub fn sync_snapshot(&self) -> Vec<SyncEvent> {
+        self.channel_states
+            .iter()
+            .flat_map(|channel| {
+                let channel_id = *channel.key();
+                channel
+                    .models
+                    .iter()
+                    .filter(|(_, m)| m.adaptive_limit.last_adjusted_at.is_some())
+                    .map(|(model, m)| Self::aimd_event(channel_id, model, &m.adaptive_limit))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
     /// Filter a list of candidate channels to return only available ones.
     ///
     /// This method takes a list of candidate channel IDs and filters out any
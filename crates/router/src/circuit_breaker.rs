@@ -1,6 +1,9 @@
+use crate::smart_circuit_breaker::TripLevel;
+use crate::state_sync::{SyncEvent, SyncPublisher};
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::OnceLock;
 use std::time::{Duration, Instant};
 
 /// Represents the scope of a rate limit.
@@ -70,10 +73,15 @@ impl Default for UpstreamState {
 /// Default retry duration when no retry_after header is provided.
 const DEFAULT_RATE_LIMIT_RETRY_SECS: u64 = 60;
 
+/// How long auth/payment failures keep an upstream's circuit open.
+const AUTH_FAILURE_OPEN_SECS: u64 = 1800;
+
 pub struct CircuitBreaker {
     states: DashMap<String, UpstreamState>,
     failure_threshold: u32,
     cooldown_duration: Duration,
+    /// Shares trips with other router instances once attached.
+    sync: OnceLock<SyncPublisher>,
 }
 
 impl CircuitBreaker {
@@ -82,6 +90,7 @@ impl CircuitBreaker {
             states: DashMap::new(),
             failure_threshold,
             cooldown_duration: Duration::from_secs(cooldown_seconds),
+            sync: OnceLock::new(),
         }
     }
 
@@ -119,15 +128,28 @@ impl CircuitBreaker {
 
     /// Records a successful request.
     pub fn record_success(&self, upstream_id: &str) {
-        if let Some(mut entry) = self.states.get_mut(upstream_id) {
-            // Reset failure count on success
-            entry.failure_count.store(0, Ordering::Relaxed);
-            entry.last_failure_time = None;
-            entry.failure_type = None;
-            entry.rate_limit_until = None;
+        if self.close(upstream_id) {
+            self.publish(SyncEvent::BreakerClosed {
+                upstream_id: upstream_id.to_string(),
+            });
         }
     }
 
+    /// Reset an upstream's state; returns whether it was open or rate limited.
+    fn close(&self, upstream_id: &str) -> bool {
+        let Some(mut entry) = self.states.get_mut(upstream_id) else {
+            return false;
+        };
+        let was_open = entry.failure_count.load(Ordering::Relaxed) >= self.failure_threshold
+            || entry.rate_limit_until.is_some();
+        // Reset failure count on success
+        entry.failure_count.store(0, Ordering::Relaxed);
+        entry.last_failure_time = None;
+        entry.failure_type = None;
+        entry.rate_limit_until = None;
+        was_open
+    }
+
     /// Records a failed request with a specific failure type.
     ///
     /// Different failure types may have different impacts:
@@ -147,11 +169,17 @@ impl CircuitBreaker {
                 // Set high failure count + long cooldown (30 min) to avoid retrying.
                 entry.failure_count.store(self.failure_threshold * 10, Ordering::Relaxed);
                 entry.last_failure_time = Some(Instant::now());
-                entry.rate_limit_until = Some(Instant::now() + Duration::from_secs(1800)); // 30 minutes
+                entry.rate_limit_until =
+                    Some(Instant::now() + Duration::from_secs(AUTH_FAILURE_OPEN_SECS)); // 30 minutes
                 tracing::warn!(
                     "Circuit Breaker: Upstream {} auth/payment failure ({:?}) — circuit tripped for 30 min",
                     upstream_id, failure_type
                 );
+                crate::metrics::record_circuit_trip(TripLevel::CHANNEL_LABEL, "auth");
+                self.publish(SyncEvent::BreakerOpened {
+                    upstream_id: upstream_id.to_string(),
+                    open_ms: AUTH_FAILURE_OPEN_SECS * 1000,
+                });
             }
             FailureType::RateLimited {
                 scope: _,
@@ -162,6 +190,10 @@ impl CircuitBreaker {
                     .map(Duration::from_secs)
                     .unwrap_or(Duration::from_secs(DEFAULT_RATE_LIMIT_RETRY_SECS));
                 entry.rate_limit_until = Some(Instant::now() + duration);
+                self.publish(SyncEvent::BreakerRateLimited {
+                    upstream_id: upstream_id.to_string(),
+                    retry_ms: u64::try_from(duration.as_millis()).unwrap_or(u64::MAX),
+                });
 
                 // Also increment failure count for rate limits
                 let new_count = entry.failure_count.fetch_add(1, Ordering::Relaxed) + 1;
@@ -172,6 +204,10 @@ impl CircuitBreaker {
                         new_count
                     );
                 }
+                if new_count == self.failure_threshold {
+                    crate::metrics::record_circuit_trip(TripLevel::CHANNEL_LABEL, "rate_limit");
+                    self.publish_opened(upstream_id);
+                }
             }
             _ => {
                 // Other failures just increment the count
@@ -183,6 +219,10 @@ impl CircuitBreaker {
                         new_count
                     );
                 }
+                if new_count == self.failure_threshold {
+                    crate::metrics::record_circuit_trip(TripLevel::CHANNEL_LABEL, "failures");
+                    self.publish_opened(upstream_id);
+                }
             }
         }
     }
@@ -204,6 +244,10 @@ impl CircuitBreaker {
             entry.rate_limit_until = None;
             tripped.push(entry.key().clone());
         }
+        for upstream_id in &tripped {
+            crate::metrics::record_circuit_trip(TripLevel::CHANNEL_LABEL, "manual");
+            self.publish_opened(upstream_id);
+        }
         tracing::warn!(
             "Circuit Breaker: Emergency trip-all triggered — {} upstream(s) forced to Open",
             tripped.len()
@@ -211,6 +255,95 @@ impl CircuitBreaker {
         tripped
     }
 
+    /// Publish local trips to other router instances from now on.
+    pub fn attach_sync(&self, publisher: SyncPublisher) {
+        let _ = self.sync.set(publisher);
+    }
+
+    fn publish(&self, event: SyncEvent) {
+        if let Some(sync) = self.sync.get() {
+            sync.publish(event);
+        }
+    }
+
+    fn publish_opened(&self, upstream_id: &str) {
+        self.publish(SyncEvent::BreakerOpened {
+            upstream_id: upstream_id.to_string(),
+            open_ms: u64::try_from(self.cooldown_duration.as_millis()).unwrap_or(u64::MAX),
+        });
+    }
+
+    /// Open an upstream's circuit for `open_for`, as tripped by another
+    /// instance. Longer-than-cooldown trips (auth failures) are held by the
+    /// rate-limit timer.
+    pub fn apply_remote_open(&self, upstream_id: &str, open_for: Duration) {
+        if open_for.is_zero() {
+            return;
+        }
+        let now = Instant::now();
+        let mut entry = self.states.entry(upstream_id.to_string()).or_default();
+        entry
+            .failure_count
+            .fetch_max(self.failure_threshold, Ordering::Relaxed);
+        let elapsed = self.cooldown_duration.saturating_sub(open_for);
+        entry.last_failure_time = Some(now.checked_sub(elapsed).unwrap_or(now));
+        if entry.failure_type.is_none() {
+            entry.failure_type = Some(FailureType::ServerError);
+        }
+        if open_for > self.cooldown_duration {
+            let until = now + open_for;
+            entry.rate_limit_until = Some(entry.rate_limit_until.map_or(until, |u| u.max(until)));
+        }
+    }
+
+    /// Hold back an upstream rate limited on another instance.
+    pub fn apply_remote_rate_limit(&self, upstream_id: &str, retry_after: Duration) {
+        if retry_after.is_zero() {
+            return;
+        }
+        let until = Instant::now() + retry_after;
+        let mut entry = self.states.entry(upstream_id.to_string()).or_default();
+        entry.rate_limit_until = Some(entry.rate_limit_until.map_or(until, |u| u.max(until)));
+    }
+
+    /// Close an upstream's circuit after a success on another instance.
+    pub fn apply_remote_close(&self, upstream_id: &str) {
+        self.close(upstream_id);
+    }
+
+    /// Open and rate-limited upstreams, for the periodic state sync snapshot.
+    pub fn sync_snapshot(&self) -> Vec<SyncEvent> {
+        let now = Instant::now();
+        let millis = |d: Duration| u64::try_from(d.as_millis()).unwrap_or(u64::MAX);
+        let mut events = Vec::new();
+        for r in self.states.iter() {
+            let rate_limited = r
+                .rate_limit_until
+                .map(|until| until.saturating_duration_since(now))
+                .unwrap_or_default();
+            let open = if r.failure_count.load(Ordering::Relaxed) >= self.failure_threshold {
+                r.last_failure_time
+                    .map(|last| self.cooldown_duration.saturating_sub(last.elapsed()))
+                    .unwrap_or_default()
+                    .max(rate_limited)
+            } else {
+                Duration::ZERO
+            };
+            if !open.is_zero() {
+                events.push(SyncEvent::BreakerOpened {
+                    upstream_id: r.key().clone(),
+                    open_ms: millis(open),
+                });
+            } else if !rate_limited.is_zero() {
+                events.push(SyncEvent::BreakerRateLimited {
+                    upstream_id: r.key().clone(),
+                    retry_ms: millis(rate_limited),
+                });
+            }
+        }
+        events
+    }
+
     /// Get current health status map for monitoring
     pub fn get_status_map(&self) -> std::collections::HashMap<String, String> {
         let mut map = std::collections::HashMap::new();
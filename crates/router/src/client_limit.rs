@@ -0,0 +1,690 @@
+//! Per-token and per-user RPM / TPM / concurrency limits.
+//!
+//! Unlike the L2 Shaper ([`crate::rate_budget`]), which protects upstream
+//! channels, these limits protect the fleet from a single customer. Every
+//! request is admitted against all of its scopes (the API key and its owning
+//! user) or against none of them. Counters use fixed one-minute windows:
+//!
+//! - RPM: one unit per admitted request.
+//! - TPM: the request's estimated tokens on admission, reconciled with the
+//!   actual usage by a [`TokenReconcile`] once the response body (including a
+//!   stream) has been fully sent or dropped.
+//! - Concurrency: held by a [`ConcurrencyPermit`] until the response body
+//!   (including a stream) has been fully sent or dropped.
+//!
+//! [`InMemoryClientLimiter`] serves a single instance; [`RedisClientLimiter`]
+//! shares counters across instances. Backend errors fail open.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use burncloud_common::RateLimits;
+
+/// Length of an RPM / TPM window in seconds.
+pub const WINDOW_SECS: u64 = 60;
+
+/// Entries kept by [`InMemoryClientLimiter`] before idle ones are pruned.
+const MAX_IDLE_ENTRIES: usize = 10_000;
+
+/// One set of limits and the counter key it is tracked under,
+/// e.g. `token:<md5>` or `user:<id>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitScope {
+    pub key: String,
+    pub limits: RateLimits,
+}
+
+impl LimitScope {
+    pub fn new(key: impl Into<String>, limits: RateLimits) -> Self {
+        Self {
+            key: key.into(),
+            limits,
+        }
+    }
+}
+
+/// Which limit rejected a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitKind {
+    Requests,
+    Tokens,
+    Concurrency,
+}
+
+impl LimitKind {
+    pub fn message(&self) -> &'static str {
+        match self {
+            LimitKind::Requests => "Rate limit reached for requests per minute",
+            LimitKind::Tokens => "Rate limit reached for tokens per minute",
+            LimitKind::Concurrency => "Too many concurrent requests",
+        }
+    }
+}
+
+/// Values for the `x-ratelimit-*` response headers, taken from the most
+/// constrained scope of each dimension.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    pub limit_requests: Option<i64>,
+    pub remaining_requests: Option<i64>,
+    pub limit_tokens: Option<i64>,
+    pub remaining_tokens: Option<i64>,
+    /// Seconds until the current window resets.
+    pub reset_secs: u64,
+}
+
+/// Outcome of [`ClientLimitBackend::acquire`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Admission {
+    Admitted(RateLimitStatus),
+    Rejected {
+        kind: LimitKind,
+        retry_after_secs: u64,
+        status: RateLimitStatus,
+    },
+}
+
+/// Usage of one scope in the current window.
+#[derive(Debug, Clone, Copy, Default)]
+struct ScopeUsage {
+    requests: i64,
+    tokens: i64,
+    in_flight: i64,
+}
+
+/// Pluggable counter store for client limits.
+#[async_trait::async_trait]
+pub trait ClientLimitBackend: Send + Sync {
+    /// Admit one request estimated at `est_tokens` against every scope, or
+    /// reject it without touching any counter.
+    async fn acquire(&self, scopes: &[LimitScope], est_tokens: i64) -> Admission;
+
+    /// Correct the TPM counters once the actual usage is known.
+    async fn adjust_tokens(&self, scopes: &[LimitScope], delta: i64);
+
+    /// Release the concurrency slots taken by `acquire`.
+    async fn release(&self, scopes: &[LimitScope]);
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Start of the window containing `now` and the seconds left in it.
+fn window(now: u64) -> (u64, u64) {
+    let start = now - now % WINDOW_SECS;
+    (start, start + WINDOW_SECS - now)
+}
+
+/// Check every scope's current usage; `None` when the request fits.
+fn first_violation(
+    scopes: &[LimitScope],
+    usage: &[ScopeUsage],
+    est_tokens: i64,
+) -> Option<LimitKind> {
+    for (scope, used) in scopes.iter().zip(usage) {
+        if let Some(limit) = scope.limits.max_concurrency {
+            if used.in_flight >= i64::from(limit) {
+                return Some(LimitKind::Concurrency);
+            }
+        }
+        if let Some(limit) = scope.limits.rpm_limit {
+            if used.requests >= i64::from(limit) {
+                return Some(LimitKind::Requests);
+            }
+        }
+        if let Some(limit) = scope.limits.tpm_limit {
+            // A request larger than the whole budget still runs in an empty window
+            if used.tokens > 0 && used.tokens.saturating_add(est_tokens) > limit {
+                return Some(LimitKind::Tokens);
+            }
+        }
+    }
+    None
+}
+
+/// Header values after (or, on rejection, without) counting this request.
+fn summarize(scopes: &[LimitScope], usage: &[ScopeUsage], reset_secs: u64) -> RateLimitStatus {
+    let mut status = RateLimitStatus {
+        reset_secs,
+        ..Default::default()
+    };
+    for (scope, used) in scopes.iter().zip(usage) {
+        if let Some(limit) = scope.limits.rpm_limit.map(i64::from) {
+            let remaining = (limit - used.requests).max(0);
+            if status.remaining_requests.is_none_or(|r| remaining < r) {
+                status.limit_requests = Some(limit);
+                status.remaining_requests = Some(remaining);
+            }
+        }
+        if let Some(limit) = scope.limits.tpm_limit {
+            let remaining = (limit - used.tokens).max(0);
+            if status.remaining_tokens.is_none_or(|r| remaining < r) {
+                status.limit_tokens = Some(limit);
+                status.remaining_tokens = Some(remaining);
+            }
+        }
+    }
+    status
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Counter {
+    window_start: u64,
+    usage: ScopeUsage,
+}
+
+impl Counter {
+    /// Reset the per-window counters when the window has moved on.
+    fn roll(&mut self, window_start: u64) {
+        if self.window_start != window_start {
+            self.window_start = window_start;
+            self.usage.requests = 0;
+            self.usage.tokens = 0;
+        }
+    }
+}
+
+/// Single-instance backend. One lock covers all scopes so multi-scope
+/// admission is atomic.
+#[derive(Default)]
+pub struct InMemoryClientLimiter {
+    counters: Mutex<HashMap<String, Counter>>,
+}
+
+impl InMemoryClientLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn acquire_at(&self, scopes: &[LimitScope], est_tokens: i64, now: u64) -> Admission {
+        let (window_start, reset_secs) = window(now);
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        if counters.len() > MAX_IDLE_ENTRIES {
+            counters.retain(|_, c| c.usage.in_flight > 0 || c.window_start == window_start);
+        }
+
+        let usage: Vec<ScopeUsage> = scopes
+            .iter()
+            .map(|scope| {
+                let counter = counters.entry(scope.key.clone()).or_default();
+                counter.roll(window_start);
+                counter.usage
+            })
+            .collect();
+
+        if let Some(kind) = first_violation(scopes, &usage, est_tokens) {
+            let retry_after_secs = if kind == LimitKind::Concurrency {
+                1
+            } else {
+                reset_secs
+            };
+            return Admission::Rejected {
+                kind,
+                retry_after_secs,
+                status: summarize(scopes, &usage, reset_secs),
+            };
+        }
+
+        let mut after = Vec::with_capacity(scopes.len());
+        for scope in scopes {
+            let counter = counters.entry(scope.key.clone()).or_default();
+            counter.usage.requests += 1;
+            counter.usage.tokens = counter.usage.tokens.saturating_add(est_tokens);
+            if scope.limits.max_concurrency.is_some() {
+                counter.usage.in_flight += 1;
+            }
+            after.push(counter.usage);
+        }
+        Admission::Admitted(summarize(scopes, &after, reset_secs))
+    }
+}
+
+#[async_trait::async_trait]
+impl ClientLimitBackend for InMemoryClientLimiter {
+    async fn acquire(&self, scopes: &[LimitScope], est_tokens: i64) -> Admission {
+        self.acquire_at(scopes, est_tokens, now_secs())
+    }
+
+    async fn adjust_tokens(&self, scopes: &[LimitScope], delta: i64) {
+        let (window_start, _) = window(now_secs());
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        for scope in scopes.iter().filter(|s| s.limits.tpm_limit.is_some()) {
+            if let Some(counter) = counters.get_mut(&scope.key) {
+                // Usage settled after the window rolled over is not carried forward
+                if counter.window_start == window_start {
+                    counter.usage.tokens = counter.usage.tokens.saturating_add(delta).max(0);
+                }
+            }
+        }
+    }
+
+    async fn release(&self, scopes: &[LimitScope]) {
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        for scope in scopes.iter().filter(|s| s.limits.max_concurrency.is_some()) {
+            if let Some(counter) = counters.get_mut(&scope.key) {
+                counter.usage.in_flight = (counter.usage.in_flight - 1).max(0);
+            }
+        }
+    }
+}
+
+/// Atomically checks every scope and, if all fit, counts the request.
+///
+/// KEYS: per scope `requests`, `tokens`, `in_flight` keys.
+/// ARGV: est_tokens, window ttl, in-flight ttl, then per scope rpm, tpm,
+/// concurrency limits (`-1` = unlimited).
+/// Returns `{admitted, kind, requests_1, tokens_1, in_flight_1, ...}` where
+/// kind is 0 on admission, 1 requests, 2 tokens, 3 concurrency.
+const ACQUIRE_SCRIPT: &str = r#"
+local est = tonumber(ARGV[1])
+local n = #KEYS / 3
+local usage = {}
+local kind = 0
+for i = 1, n do
+  local r = tonumber(redis.call('GET', KEYS[3*i-2]) or '0')
+  local t = tonumber(redis.call('GET', KEYS[3*i-1]) or '0')
+  local c = tonumber(redis.call('GET', KEYS[3*i]) or '0')
+  usage[i] = {r, t, c}
+  local rpm = tonumber(ARGV[3*i+1])
+  local tpm = tonumber(ARGV[3*i+2])
+  local conc = tonumber(ARGV[3*i+3])
+  if kind == 0 then
+    if conc >= 0 and c >= conc then kind = 3
+    elseif rpm >= 0 and r >= rpm then kind = 1
+    elseif tpm >= 0 and (t >= tpm or (t > 0 and t + est > tpm)) then kind = 2
+    end
+  end
+end
+local out = {kind == 0 and 1 or 0, kind}
+for i = 1, n do
+  if kind == 0 then
+    usage[i][1] = redis.call('INCR', KEYS[3*i-2])
+    redis.call('EXPIRE', KEYS[3*i-2], ARGV[2])
+    usage[i][2] = redis.call('INCRBY', KEYS[3*i-1], est)
+    redis.call('EXPIRE', KEYS[3*i-1], ARGV[2])
+    if tonumber(ARGV[3*i+3]) >= 0 then
+      usage[i][3] = redis.call('INCR', KEYS[3*i])
+      redis.call('EXPIRE', KEYS[3*i], ARGV[3])
+    end
+  end
+  table.insert(out, usage[i][1])
+  table.insert(out, usage[i][2])
+  table.insert(out, usage[i][3])
+end
+return out
+"#;
+
+/// Decrement without going below zero (slots may have expired meanwhile).
+const RELEASE_SCRIPT: &str = r#"
+for i = 1, #KEYS do
+  local v = redis.call('DECR', KEYS[i])
+  if v < 0 then redis.call('SET', KEYS[i], 0) end
+end
+return 1
+"#;
+
+/// Multi-instance backend sharing counters through Redis.
+pub struct RedisClientLimiter {
+    conn: redis::aio::ConnectionManager,
+}
+
+impl RedisClientLimiter {
+    /// In-flight slots expire after this long in case an instance dies
+    /// without releasing them.
+    const IN_FLIGHT_TTL_SECS: u64 = 3600;
+
+    pub async fn connect(url: &str) -> Result<Self, String> {
+        let client = redis::Client::open(url).map_err(|e| e.to_string())?;
+        let conn = client
+            .get_connection_manager()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(Self { conn })
+    }
+
+    fn keys(scope: &LimitScope, window_start: u64) -> [String; 3] {
+        [
+            format!("bc:rl:{}:r:{}", scope.key, window_start),
+            format!("bc:rl:{}:t:{}", scope.key, window_start),
+            format!("bc:rl:{}:c", scope.key),
+        ]
+    }
+}
+
+#[async_trait::async_trait]
+impl ClientLimitBackend for RedisClientLimiter {
+    async fn acquire(&self, scopes: &[LimitScope], est_tokens: i64) -> Admission {
+        let (window_start, reset_secs) = window(now_secs());
+        let script = redis::Script::new(ACQUIRE_SCRIPT);
+        let mut invocation = script.prepare_invoke();
+        invocation
+            .arg(est_tokens)
+            .arg(WINDOW_SECS * 2)
+            .arg(Self::IN_FLIGHT_TTL_SECS);
+        for scope in scopes {
+            for key in Self::keys(scope, window_start) {
+                invocation.key(key);
+            }
+            invocation
+                .arg(scope.limits.rpm_limit.map_or(-1, i64::from))
+                .arg(scope.limits.tpm_limit.unwrap_or(-1))
+                .arg(scope.limits.max_concurrency.map_or(-1, i64::from));
+        }
+
+        let mut conn = self.conn.clone();
+        let reply: Vec<i64> = match invocation.invoke_async(&mut conn).await {
+            Ok(reply) => reply,
+            Err(e) => {
+                tracing::warn!(error = %e, "Redis client limiter unavailable, admitting request");
+                return Admission::Admitted(RateLimitStatus::default());
+            }
+        };
+
+        let usage: Vec<ScopeUsage> = reply
+            .get(2..)
+            .unwrap_or_default()
+            .chunks(3)
+            .map(|c| ScopeUsage {
+                requests: c.first().copied().unwrap_or(0),
+                tokens: c.get(1).copied().unwrap_or(0),
+                in_flight: c.get(2).copied().unwrap_or(0),
+            })
+            .collect();
+        let status = summarize(scopes, &usage, reset_secs);
+        let kind = match reply.get(1).copied().unwrap_or(0) {
+            1 => LimitKind::Requests,
+            2 => LimitKind::Tokens,
+            3 => LimitKind::Concurrency,
+            _ => return Admission::Admitted(status),
+        };
+        let retry_after_secs = if kind == LimitKind::Concurrency {
+            1
+        } else {
+            reset_secs
+        };
+        Admission::Rejected {
+            kind,
+            retry_after_secs,
+            status,
+        }
+    }
+
+    async fn adjust_tokens(&self, scopes: &[LimitScope], delta: i64) {
+        let (window_start, _) = window(now_secs());
+        let mut pipe = redis::pipe();
+        for scope in scopes.iter().filter(|s| s.limits.tpm_limit.is_some()) {
+            let [_, tokens, _] = Self::keys(scope, window_start);
+            pipe.cmd("INCRBY").arg(tokens).arg(delta).ignore();
+        }
+        let mut conn = self.conn.clone();
+        if let Err(e) = pipe.query_async::<()>(&mut conn).await {
+            tracing::warn!(error = %e, "Failed to reconcile client token usage in Redis");
+        }
+    }
+
+    async fn release(&self, scopes: &[LimitScope]) {
+        let script = redis::Script::new(RELEASE_SCRIPT);
+        let mut invocation = script.prepare_invoke();
+        for scope in scopes.iter().filter(|s| s.limits.max_concurrency.is_some()) {
+            let [_, _, in_flight] = Self::keys(scope, 0);
+            invocation.key(in_flight);
+        }
+        let mut conn = self.conn.clone();
+        if let Err(e) = invocation.invoke_async::<i64>(&mut conn).await {
+            tracing::warn!(error = %e, "Failed to release client concurrency slot in Redis");
+        }
+    }
+}
+
+/// Build the backend selected by `BURNCLOUD_RATE_LIMIT_BACKEND`
+/// (`memory`, the default, or `redis` using `REDIS_URL`).
+pub async fn backend_from_env() -> Arc<dyn ClientLimitBackend> {
+    let choice = std::env::var("BURNCLOUD_RATE_LIMIT_BACKEND").unwrap_or_default();
+    if choice.eq_ignore_ascii_case("redis") {
+        match std::env::var("REDIS_URL") {
+            Ok(url) => match RedisClientLimiter::connect(&url).await {
+                Ok(limiter) => {
+                    tracing::info!("Client rate limits backed by Redis");
+                    return Arc::new(limiter);
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, "Redis client limiter unavailable, using in-memory limits")
+                }
+            },
+            Err(_) => tracing::warn!(
+                "BURNCLOUD_RATE_LIMIT_BACKEND=redis but REDIS_URL is not set, using in-memory limits"
+            ),
+        }
+    }
+    Arc::new(InMemoryClientLimiter::new())
+}
+
+/// Holds concurrency slots until dropped. Attach it to the response body so
+/// streamed responses keep their slot until the stream ends.
+pub struct ConcurrencyPermit {
+    backend: Arc<dyn ClientLimitBackend>,
+    scopes: Vec<LimitScope>,
+}
+
+impl ConcurrencyPermit {
+    pub fn new(backend: Arc<dyn ClientLimitBackend>, scopes: Vec<LimitScope>) -> Self {
+        Self { backend, scopes }
+    }
+}
+
+impl Drop for ConcurrencyPermit {
+    fn drop(&mut self) {
+        if !self
+            .scopes
+            .iter()
+            .any(|s| s.limits.max_concurrency.is_some())
+        {
+            return;
+        }
+        let backend = self.backend.clone();
+        let scopes = std::mem::take(&mut self.scopes);
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            handle.spawn(async move { backend.release(&scopes).await });
+        }
+    }
+}
+
+/// Corrects the TPM estimate taken on admission when dropped.
+///
+/// Held by the response body like a [`ConcurrencyPermit`]: a stream's usage
+/// is only counted as its chunks pass through, so reading it when the
+/// headers are sent would refund the whole estimate.
+pub struct TokenReconcile {
+    backend: Arc<dyn ClientLimitBackend>,
+    scopes: Vec<LimitScope>,
+    estimated: i64,
+    actual: Box<dyn Fn() -> i64 + Send + Sync>,
+}
+
+impl TokenReconcile {
+    /// `actual` reports the tokens used so far and is read once, on drop.
+    pub fn new(
+        backend: Arc<dyn ClientLimitBackend>,
+        scopes: Vec<LimitScope>,
+        estimated: i64,
+        actual: impl Fn() -> i64 + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            backend,
+            scopes,
+            estimated,
+            actual: Box::new(actual),
+        }
+    }
+}
+
+impl Drop for TokenReconcile {
+    fn drop(&mut self) {
+        let delta = (self.actual)().saturating_sub(self.estimated);
+        if delta == 0 || !self.scopes.iter().any(|s| s.limits.tpm_limit.is_some()) {
+            return;
+        }
+        let backend = self.backend.clone();
+        let scopes = std::mem::take(&mut self.scopes);
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            handle.spawn(async move { backend.adjust_tokens(&scopes, delta).await });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(key: &str, rpm: Option<i32>, tpm: Option<i64>, conc: Option<i32>) -> LimitScope {
+        LimitScope::new(
+            key,
+            RateLimits {
+                rpm_limit: rpm,
+                tpm_limit: tpm,
+                max_concurrency: conc,
+            },
+        )
+    }
+
+    #[test]
+    fn test_rpm_window_and_headers() {
+        let limiter = InMemoryClientLimiter::new();
+        let scopes = [scope("token:a", Some(2), None, None)];
+        let now = 1_000 * WINDOW_SECS + 15;
+
+        match limiter.acquire_at(&scopes, 0, now) {
+            Admission::Admitted(status) => {
+                assert_eq!(status.limit_requests, Some(2));
+                assert_eq!(status.remaining_requests, Some(1));
+                assert_eq!(status.reset_secs, 45);
+            }
+            other => panic!("expected admission, got {other:?}"),
+        }
+        assert!(matches!(
+            limiter.acquire_at(&scopes, 0, now),
+            Admission::Admitted(_)
+        ));
+        match limiter.acquire_at(&scopes, 0, now + 5) {
+            Admission::Rejected {
+                kind,
+                retry_after_secs,
+                status,
+            } => {
+                assert_eq!(kind, LimitKind::Requests);
+                assert_eq!(retry_after_secs, 40);
+                assert_eq!(status.remaining_requests, Some(0));
+            }
+            other => panic!("expected rejection, got {other:?}"),
+        }
+        // Next window starts fresh
+        assert!(matches!(
+            limiter.acquire_at(&scopes, 0, now + WINDOW_SECS),
+            Admission::Admitted(_)
+        ));
+    }
+
+    #[test]
+    fn test_rejection_does_not_count_against_other_scopes() {
+        let limiter = InMemoryClientLimiter::new();
+        let user = scope("user:u", Some(10), None, None);
+        let token = scope("token:t", Some(1), None, None);
+        let now = 500 * WINDOW_SECS;
+
+        assert!(matches!(
+            limiter.acquire_at(&[token.clone(), user.clone()], 0, now),
+            Admission::Admitted(_)
+        ));
+        assert!(matches!(
+            limiter.acquire_at(&[token, user.clone()], 0, now),
+            Admission::Rejected { .. }
+        ));
+        match limiter.acquire_at(&[user], 0, now) {
+            Admission::Admitted(status) => assert_eq!(status.remaining_requests, Some(8)),
+            other => panic!("expected admission, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tpm_reconciliation() {
+        let limiter = InMemoryClientLimiter::new();
+        let scopes = [scope("token:tpm", None, Some(1_000), None)];
+
+        assert!(matches!(
+            limiter.acquire(&scopes, 900).await,
+            Admission::Admitted(_)
+        ));
+        assert!(matches!(
+            limiter.acquire(&scopes, 200).await,
+            Admission::Rejected {
+                kind: LimitKind::Tokens,
+                ..
+            }
+        ));
+        // The first request actually used far fewer tokens than estimated
+        limiter.adjust_tokens(&scopes, -800).await;
+        match limiter.acquire(&scopes, 200).await {
+            Admission::Admitted(status) => assert_eq!(status.remaining_tokens, Some(700)),
+            other => panic!("expected admission, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_permit_releases_slot() {
+        let backend: Arc<dyn ClientLimitBackend> = Arc::new(InMemoryClientLimiter::new());
+        let scopes = vec![scope("token:c", None, None, Some(1))];
+
+        assert!(matches!(
+            backend.acquire(&scopes, 0).await,
+            Admission::Admitted(_)
+        ));
+        let permit = ConcurrencyPermit::new(backend.clone(), scopes.clone());
+        assert!(matches!(
+            backend.acquire(&scopes, 0).await,
+            Admission::Rejected {
+                kind: LimitKind::Concurrency,
+                retry_after_secs: 1,
+                ..
+            }
+        ));
+
+        drop(permit);
+        tokio::task::yield_now().await;
+        assert!(matches!(
+            backend.acquire(&scopes, 0).await,
+            Admission::Admitted(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_token_reconcile_reads_usage_when_dropped() {
+        use std::sync::atomic::{AtomicI64, Ordering};
+
+        let backend: Arc<dyn ClientLimitBackend> = Arc::new(InMemoryClientLimiter::new());
+        let scopes = vec![scope("token:stream", None, Some(1_000), None)];
+        assert!(matches!(
+            backend.acquire(&scopes, 500).await,
+            Admission::Admitted(_)
+        ));
+
+        // A stream is counted after the reconcile guard is created
+        let used = Arc::new(AtomicI64::new(0));
+        let reconcile = TokenReconcile::new(backend.clone(), scopes.clone(), 500, {
+            let used = used.clone();
+            move || used.load(Ordering::Relaxed)
+        });
+        used.store(800, Ordering::Relaxed);
+        drop(reconcile);
+        tokio::task::yield_now().await;
+
+        match backend.acquire(&scopes, 100).await {
+            Admission::Admitted(status) => assert_eq!(status.remaining_tokens, Some(100)),
+            other => panic!("expected admission, got {other:?}"),
+        }
+    }
+}
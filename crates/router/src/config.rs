@@ -61,4 +61,10 @@ pub struct Upstream {
     pub api_version: Option<String>,
     #[serde(default)]
     pub pricing_region: Option<String>,
+    /// Concrete model this candidate serves when the request named a virtual model
+    #[serde(default)]
+    pub served_model: Option<String>,
+    /// Virtual-model hop overrides, merged into the body before protocol conversion
+    #[serde(default)]
+    pub hop_param_override: Option<burncloud_database_router::ParamOverride>,
 }
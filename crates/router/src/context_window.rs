@@ -0,0 +1,624 @@
+#![allow(clippy::disallowed_types)]
+//! Context-window preflight.
+//!
+//! With `CONTEXT_PREFLIGHT=true`, `proxy_handler` estimates the prompt and
+//! output tokens of a request and compares them with the model's
+//! `context_window` / `max_output_tokens` from the price table before any
+//! channel is selected. A request that cannot fit is rejected with
+//! `context_length_exceeded` instead of being sent upstream, where the 400
+//! would count against the channel.
+//!
+//! The prompt estimate is text bytes / 4, which can overshoot the real token
+//! count, so the check is off by default: enable it where a false rejection
+//! is cheaper than the upstream 400.
+//!
+//! Two further opt-in escapes exist before rejecting:
+//!
+//! - `CONTEXT_CLAMP_MAX_TOKENS=true` lowers the requested output budget to
+//!   what is left of the window (or the model's output limit).
+//! - `LONG_CONTEXT_MODELS` maps a model to a larger-context sibling, e.g.
+//!   `{"gpt-4o-mini": "gpt-4.1-mini"}`. Requests that do not fit the model
+//!   are rerouted to the sibling when they fit there.
+//!
+//! Models without known limits are never checked.
+
+use crate::response_parser::ErrorInfo;
+use axum::http::StatusCode;
+use burncloud_common::types::Price;
+use burncloud_service_billing::PriceCache;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Request fields holding the output budget, in the order they are read.
+const OUTPUT_FIELDS: &[&str] = &["max_tokens", "max_completion_tokens", "max_output_tokens"];
+
+/// Keys whose string values are binary payloads (base64 images, audio,
+/// files) rather than prompt text.
+const BINARY_KEYS: &[&str] = &["data", "b64_json", "file_data", "image_url", "url"];
+
+/// Context preflight settings, read once at startup.
+#[derive(Debug, Clone, Default)]
+pub struct ContextWindowConfig {
+    /// Check requests against the model's limits (`CONTEXT_PREFLIGHT`, default off).
+    pub enabled: bool,
+    /// Lower an oversized output budget instead of rejecting
+    /// (`CONTEXT_CLAMP_MAX_TOKENS`, default off).
+    pub clamp_max_tokens: bool,
+    /// Lowercase model name → larger-context sibling (`LONG_CONTEXT_MODELS`).
+    pub long_context_models: HashMap<String, String>,
+}
+
+impl ContextWindowConfig {
+    /// Read `CONTEXT_PREFLIGHT`, `CONTEXT_CLAMP_MAX_TOKENS` and
+    /// `LONG_CONTEXT_MODELS` (a JSON object of model → sibling).
+    pub fn from_env() -> Self {
+        let flag = |name: &str, default: bool| {
+            std::env::var(name)
+                .map(|v| !matches!(v.trim().to_lowercase().as_str(), "false" | "0" | ""))
+                .unwrap_or(default)
+        };
+        let long_context_models = match std::env::var("LONG_CONTEXT_MODELS") {
+            Ok(raw) => match serde_json::from_str::<HashMap<String, String>>(&raw) {
+                Ok(map) => map
+                    .into_iter()
+                    .map(|(model, sibling)| (model.to_lowercase(), sibling))
+                    .collect(),
+                Err(e) => {
+                    tracing::warn!("Failed to parse LONG_CONTEXT_MODELS: {e}");
+                    HashMap::new()
+                }
+            },
+            Err(_) => HashMap::new(),
+        };
+        Self {
+            enabled: flag("CONTEXT_PREFLIGHT", false),
+            clamp_max_tokens: flag("CONTEXT_CLAMP_MAX_TOKENS", false),
+            long_context_models,
+        }
+    }
+
+    /// Configured larger-context sibling of `model` (case-insensitive).
+    pub fn sibling(&self, model: &str) -> Option<&str> {
+        self.long_context_models
+            .get(&model.to_lowercase())
+            .map(String::as_str)
+    }
+}
+
+/// Token limits of a model. `None` means unknown and unchecked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ModelLimits {
+    pub context_window: Option<i64>,
+    pub max_output_tokens: Option<i64>,
+}
+
+impl ModelLimits {
+    pub fn is_known(&self) -> bool {
+        self.context_window.is_some() || self.max_output_tokens.is_some()
+    }
+
+    /// Limits recorded for `model` in the price table.
+    pub async fn lookup(price_cache: &PriceCache, model: &str) -> Self {
+        price_cache
+            .get(model, None)
+            .await
+            .map(|p| Self::from(&p))
+            .unwrap_or_default()
+    }
+}
+
+impl From<&Price> for ModelLimits {
+    fn from(price: &Price) -> Self {
+        // Non-positive values are placeholders in synced price data
+        Self {
+            context_window: price.context_window.filter(|&v| v > 0),
+            max_output_tokens: price.max_output_tokens.filter(|&v| v > 0),
+        }
+    }
+}
+
+/// Estimated size of a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenEstimate {
+    /// Prompt text bytes / 4, the same approximation as client TPM limits.
+    pub prompt_tokens: i64,
+    /// Requested output budget, if the request sets one.
+    pub max_output_tokens: Option<i64>,
+}
+
+/// Estimate the prompt and requested output tokens of a request body.
+/// Binary payloads such as base64 images are not counted as text.
+pub fn estimate(body: &Value) -> TokenEstimate {
+    fn text_bytes(value: &Value, key: Option<&str>) -> usize {
+        match value {
+            Value::String(s) => {
+                let binary =
+                    key.is_some_and(|k| BINARY_KEYS.contains(&k)) || s.starts_with("data:");
+                if binary {
+                    0
+                } else {
+                    s.len()
+                }
+            }
+            Value::Array(items) => items.iter().map(|v| text_bytes(v, key)).sum(),
+            Value::Object(map) => map
+                .iter()
+                .filter(|(k, _)| k.as_str() != "model")
+                .map(|(k, v)| text_bytes(v, Some(k)))
+                .sum(),
+            _ => 0,
+        }
+    }
+
+    TokenEstimate {
+        prompt_tokens: text_bytes(body, None).div_ceil(4) as i64,
+        max_output_tokens: requested_output(body),
+    }
+}
+
+/// Output budget requested by an OpenAI, Anthropic or Gemini native body.
+fn requested_output(body: &Value) -> Option<i64> {
+    OUTPUT_FIELDS
+        .iter()
+        .find_map(|f| body.get(*f))
+        .or_else(|| body.get("generationConfig")?.get("maxOutputTokens"))
+        .and_then(Value::as_i64)
+        .filter(|&n| n > 0)
+}
+
+/// Replace the output budget in whichever field the request used.
+pub fn set_max_output(body: &mut Value, max_tokens: i64) {
+    if let Some(field) = OUTPUT_FIELDS.iter().find(|f| body.get(**f).is_some()) {
+        body[*field] = max_tokens.into();
+    } else if let Some(config) = body.get_mut("generationConfig") {
+        config["maxOutputTokens"] = max_tokens.into();
+    }
+}
+
+/// Why a request cannot be served by a model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextOverflow {
+    /// The prompt alone exceeds the context window.
+    PromptTooLong {
+        prompt_tokens: i64,
+        context_window: i64,
+    },
+    /// The requested output exceeds the model's output limit.
+    OutputTooLarge { requested: i64, limit: i64 },
+    /// Prompt plus requested output exceed the context window.
+    TotalTooLarge {
+        prompt_tokens: i64,
+        requested: i64,
+        context_window: i64,
+    },
+}
+
+impl ContextOverflow {
+    /// Client-facing error message.
+    pub fn message(&self, model: &str) -> String {
+        match *self {
+            Self::PromptTooLong {
+                prompt_tokens,
+                context_window,
+            } => format!(
+                "This model's maximum context length is {context_window} tokens, but the prompt is about {prompt_tokens} tokens ({model})"
+            ),
+            Self::OutputTooLarge { requested, limit } => format!(
+                "max_tokens is too large: {requested}. This model supports at most {limit} output tokens ({model})"
+            ),
+            Self::TotalTooLarge {
+                prompt_tokens,
+                requested,
+                context_window,
+            } => format!(
+                "This model's maximum context length is {context_window} tokens, but about {} tokens were requested ({prompt_tokens} in the prompt, {requested} for the output) ({model})",
+                prompt_tokens.saturating_add(requested)
+            ),
+        }
+    }
+}
+
+/// Outcome of checking an estimate against a model's limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextVerdict {
+    Fits,
+    /// Fits once the output budget is lowered to `max_tokens`.
+    Clamp {
+        max_tokens: i64,
+    },
+    Overflow(ContextOverflow),
+}
+
+/// Check `estimate` against `limits`. With `clamp` an oversized output
+/// budget becomes [`ContextVerdict::Clamp`] as long as some room is left.
+pub fn check(estimate: &TokenEstimate, limits: &ModelLimits, clamp: bool) -> ContextVerdict {
+    let prompt_tokens = estimate.prompt_tokens;
+    if let Some(context_window) = limits.context_window {
+        if prompt_tokens >= context_window {
+            return ContextVerdict::Overflow(ContextOverflow::PromptTooLong {
+                prompt_tokens,
+                context_window,
+            });
+        }
+    }
+    let Some(requested) = estimate.max_output_tokens else {
+        return ContextVerdict::Fits;
+    };
+
+    let mut allowed = requested;
+    if let Some(limit) = limits.max_output_tokens {
+        if requested > limit {
+            if !clamp {
+                return ContextVerdict::Overflow(ContextOverflow::OutputTooLarge {
+                    requested,
+                    limit,
+                });
+            }
+            allowed = limit;
+        }
+    }
+    if let Some(context_window) = limits.context_window {
+        if prompt_tokens.saturating_add(allowed) > context_window {
+            if !clamp {
+                return ContextVerdict::Overflow(ContextOverflow::TotalTooLarge {
+                    prompt_tokens,
+                    requested,
+                    context_window,
+                });
+            }
+            allowed = context_window - prompt_tokens;
+        }
+    }
+    if allowed < requested {
+        ContextVerdict::Clamp {
+            max_tokens: allowed,
+        }
+    } else {
+        ContextVerdict::Fits
+    }
+}
+
+/// What the preflight changed in the request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Preflight {
+    Unchanged,
+    /// The output budget was lowered to `max_tokens`.
+    Clamped {
+        max_tokens: i64,
+    },
+    /// The request moved to the larger-context sibling `model`.
+    Rerouted {
+        model: String,
+    },
+}
+
+/// Check `body` for `model`, applying a clamp or a sibling reroute in place.
+/// Returns the overflow for the requested model when neither helps.
+pub async fn preflight(
+    config: &ContextWindowConfig,
+    price_cache: &PriceCache,
+    model: &str,
+    body: &mut Value,
+) -> Result<Preflight, ContextOverflow> {
+    let limits = ModelLimits::lookup(price_cache, model).await;
+    if !limits.is_known() {
+        return Ok(Preflight::Unchanged);
+    }
+    let estimate = estimate(body);
+    let overflow = match check(&estimate, &limits, config.clamp_max_tokens) {
+        ContextVerdict::Fits => return Ok(Preflight::Unchanged),
+        ContextVerdict::Clamp { max_tokens } => {
+            set_max_output(body, max_tokens);
+            return Ok(Preflight::Clamped { max_tokens });
+        }
+        ContextVerdict::Overflow(overflow) => overflow,
+    };
+
+    let Some(sibling) = config.sibling(model) else {
+        return Err(overflow);
+    };
+    // A sibling without recorded limits is trusted to be larger, as configured
+    let sibling_limits = ModelLimits::lookup(price_cache, sibling).await;
+    match check(&estimate, &sibling_limits, config.clamp_max_tokens) {
+        ContextVerdict::Fits => {}
+        ContextVerdict::Clamp { max_tokens } => set_max_output(body, max_tokens),
+        ContextVerdict::Overflow(_) => return Err(overflow),
+    }
+    if body.get("model").is_some() {
+        body["model"] = sibling.into();
+    }
+    Ok(Preflight::Rerouted {
+        model: sibling.to_string(),
+    })
+}
+
+/// Whether an upstream error is the provider rejecting an oversized request.
+/// Such errors are the client's fault and do not count against the channel.
+pub fn is_context_overflow(status: StatusCode, error_info: &ErrorInfo) -> bool {
+    if status != StatusCode::BAD_REQUEST && status != StatusCode::PAYLOAD_TOO_LARGE {
+        return false;
+    }
+    if error_info.code.as_deref() == Some("context_length_exceeded") {
+        return true;
+    }
+    let Some(message) = error_info.message.as_deref() else {
+        return false;
+    };
+    let message = message.to_lowercase();
+    [
+        "maximum context length",
+        "context length exceeded",
+        "context window",
+        "prompt is too long",
+        "input token count",
+    ]
+    .iter()
+    .any(|needle| message.contains(needle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn limits(context_window: i64, max_output_tokens: i64) -> ModelLimits {
+        ModelLimits {
+            context_window: Some(context_window),
+            max_output_tokens: Some(max_output_tokens),
+        }
+    }
+
+    fn estimate_of(prompt_tokens: i64, max_output_tokens: Option<i64>) -> TokenEstimate {
+        TokenEstimate {
+            prompt_tokens,
+            max_output_tokens,
+        }
+    }
+
+    #[test]
+    fn test_estimate_counts_text_and_skips_binary() {
+        let body = json!({
+            "model": "gpt-4o",
+            "max_tokens": 256,
+            "messages": [
+                {"role": "user", "content": [
+                    {"type": "text", "text": "a".repeat(400)},
+                    {"type": "image_url", "image_url": {"url": format!("data:image/png;base64,{}", "A".repeat(100_000))}}
+                ]}
+            ]
+        });
+        let est = estimate(&body);
+        // 400 text bytes plus the role/type strings, not the image
+        assert!(
+            est.prompt_tokens >= 100 && est.prompt_tokens < 110,
+            "{est:?}"
+        );
+        assert_eq!(est.max_output_tokens, Some(256));
+
+        let gemini = json!({
+            "contents": [{"parts": [{"text": "hello"}]}],
+            "generationConfig": {"maxOutputTokens": 64}
+        });
+        assert_eq!(estimate(&gemini).max_output_tokens, Some(64));
+    }
+
+    #[test]
+    fn test_check_rejects_or_clamps() {
+        let l = limits(1000, 300);
+        assert_eq!(
+            check(&estimate_of(500, Some(200)), &l, false),
+            ContextVerdict::Fits
+        );
+        assert_eq!(
+            check(&estimate_of(500, None), &l, false),
+            ContextVerdict::Fits
+        );
+        assert_eq!(
+            check(&estimate_of(1200, None), &l, true),
+            ContextVerdict::Overflow(ContextOverflow::PromptTooLong {
+                prompt_tokens: 1200,
+                context_window: 1000
+            })
+        );
+        assert_eq!(
+            check(&estimate_of(100, Some(500)), &l, false),
+            ContextVerdict::Overflow(ContextOverflow::OutputTooLarge {
+                requested: 500,
+                limit: 300
+            })
+        );
+        assert_eq!(
+            check(&estimate_of(800, Some(300)), &l, false),
+            ContextVerdict::Overflow(ContextOverflow::TotalTooLarge {
+                prompt_tokens: 800,
+                requested: 300,
+                context_window: 1000
+            })
+        );
+        // Clamp: first to the output limit, then to what is left of the window
+        assert_eq!(
+            check(&estimate_of(100, Some(500)), &l, true),
+            ContextVerdict::Clamp { max_tokens: 300 }
+        );
+        assert_eq!(
+            check(&estimate_of(800, Some(500)), &l, true),
+            ContextVerdict::Clamp { max_tokens: 200 }
+        );
+        // Unknown limits never reject
+        assert_eq!(
+            check(
+                &estimate_of(1_000_000, Some(1_000_000)),
+                &ModelLimits::default(),
+                false
+            ),
+            ContextVerdict::Fits
+        );
+    }
+
+    #[test]
+    fn test_set_max_output_keeps_field_name() {
+        let mut openai = json!({"max_completion_tokens": 4000});
+        set_max_output(&mut openai, 100);
+        assert_eq!(openai, json!({"max_completion_tokens": 100}));
+
+        let mut gemini = json!({"generationConfig": {"maxOutputTokens": 4000}});
+        set_max_output(&mut gemini, 100);
+        assert_eq!(gemini["generationConfig"]["maxOutputTokens"], 100);
+    }
+
+    #[test]
+    fn test_overflow_message_is_precise() {
+        let msg = ContextOverflow::TotalTooLarge {
+            prompt_tokens: 900,
+            requested: 300,
+            context_window: 1000,
+        }
+        .message("gpt-4o");
+        assert!(msg.contains("1000 tokens"), "{msg}");
+        assert!(msg.contains("1200 tokens"), "{msg}");
+        assert!(msg.contains("gpt-4o"), "{msg}");
+    }
+
+    #[test]
+    fn test_is_context_overflow() {
+        let info = |code: Option<&str>, message: &str| ErrorInfo {
+            error_type: None,
+            message: Some(message.to_string()),
+            code: code.map(str::to_string),
+            scope: None,
+        };
+        assert!(is_context_overflow(
+            StatusCode::BAD_REQUEST,
+            &info(Some("context_length_exceeded"), "too long")
+        ));
+        assert!(is_context_overflow(
+            StatusCode::BAD_REQUEST,
+            &info(None, "prompt is too long: 210000 tokens > 200000 maximum")
+        ));
+        assert!(!is_context_overflow(
+            StatusCode::BAD_REQUEST,
+            &info(None, "invalid temperature")
+        ));
+        assert!(!is_context_overflow(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &info(Some("context_length_exceeded"), "too long")
+        ));
+    }
+
+    /// Price cache over a fresh database holding `(model, context_window,
+    /// max_output_tokens)` limits.
+    async fn cache_with(limits: &[(&str, i64, i64)]) -> (PriceCache, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap_or_else(|e| panic!("tempdir: {e}"));
+        let db = burncloud_database::create_database_with_url(&format!(
+            "sqlite://{}?mode=rwc",
+            dir.path().join("prices.db").display()
+        ))
+        .await
+        .unwrap_or_else(|e| panic!("database: {e}"));
+        for (model, context_window, max_output_tokens) in limits {
+            db.execute_query(&format!(
+                "INSERT INTO billing_prices (model, currency, input_price, output_price, region, \
+                 context_window, max_output_tokens, created_at) \
+                 VALUES ('{model}', 'USD', 0, 0, '', {context_window}, {max_output_tokens}, 1700000000)"
+            ))
+            .await
+            .unwrap_or_else(|e| panic!("insert price: {e}"));
+        }
+        let cache = PriceCache::load(&db)
+            .await
+            .unwrap_or_else(|e| panic!("load prices: {e}"));
+        (cache, dir)
+    }
+
+    fn chat(model: &str, prompt_bytes: usize, max_tokens: i64) -> Value {
+        json!({
+            "model": model,
+            "max_tokens": max_tokens,
+            "messages": [{"content": "a".repeat(prompt_bytes)}]
+        })
+    }
+
+    #[tokio::test]
+    async fn test_preflight_clamps_only_when_enabled() {
+        let (cache, _dir) = cache_with(&[("small", 1000, 300)]).await;
+        let mut config = ContextWindowConfig {
+            enabled: true,
+            ..Default::default()
+        };
+
+        let mut body = chat("small", 3200, 500);
+        assert_eq!(
+            preflight(&config, &cache, "small", &mut body).await,
+            Err(ContextOverflow::OutputTooLarge {
+                requested: 500,
+                limit: 300
+            })
+        );
+        assert_eq!(body, chat("small", 3200, 500));
+
+        config.clamp_max_tokens = true;
+        assert_eq!(
+            preflight(&config, &cache, "small", &mut body).await,
+            Ok(Preflight::Clamped { max_tokens: 200 })
+        );
+        assert_eq!(body, chat("small", 3200, 200));
+
+        let mut fits = chat("small", 400, 100);
+        assert_eq!(
+            preflight(&config, &cache, "small", &mut fits).await,
+            Ok(Preflight::Unchanged)
+        );
+        assert_eq!(
+            preflight(
+                &config,
+                &cache,
+                "unpriced",
+                &mut chat("unpriced", 1_000_000, 1)
+            )
+            .await,
+            Ok(Preflight::Unchanged)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_preflight_reroutes_to_a_sibling_that_fits() {
+        let (cache, _dir) = cache_with(&[("small", 1000, 300), ("large", 8000, 1000)]).await;
+        let mut config = ContextWindowConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        config
+            .long_context_models
+            .insert("small".to_string(), "large".to_string());
+
+        let mut body = chat("small", 8000, 500);
+        assert_eq!(
+            preflight(&config, &cache, "small", &mut body).await,
+            Ok(Preflight::Rerouted {
+                model: "large".to_string()
+            })
+        );
+        assert_eq!(body, chat("large", 8000, 500));
+
+        // Too long for the sibling too: the overflow names the requested model's limits
+        let mut body = chat("small", 40_000, 500);
+        assert_eq!(
+            preflight(&config, &cache, "small", &mut body).await,
+            Err(ContextOverflow::PromptTooLong {
+                prompt_tokens: 10_000,
+                context_window: 1000
+            })
+        );
+        assert_eq!(body, chat("small", 40_000, 500));
+    }
+
+    #[test]
+    fn test_sibling_lookup_is_case_insensitive() {
+        let mut config = ContextWindowConfig::default();
+        config
+            .long_context_models
+            .insert("gpt-4o-mini".to_string(), "gpt-4.1-mini".to_string());
+        assert_eq!(config.sibling("GPT-4o-mini"), Some("gpt-4.1-mini"));
+        assert_eq!(config.sibling("gpt-4o"), None);
+    }
+}
@@ -1,10 +1,14 @@
 //! Exchange Rate Service Module
 //!
 //! This module provides currency conversion functionality with caching support.
-//! Supports USD, CNY, and EUR currencies.
+//! Supports every [`Currency`] variant (USD, CNY, EUR, JPY, GBP, ...).
 //!
 //! Exchange rates are stored as i64 scaled values (rate * 10^9) for precision.
 //! For example, a rate of 7.24 CNY per USD is stored as 7240000000.
+//!
+//! Rates come from `billing_exchange_rates` (set with `burncloud currency
+//! set-rate`) and, when `EXCHANGE_RATE_PROVIDER` is configured, from a
+//! scheduled provider refresh. See [`crate::exchange_rate_provider`].
 
 use std::str::FromStr;
 use std::sync::Arc;
@@ -13,15 +17,13 @@ use std::sync::Arc;
 use burncloud_common::rate_to_scaled;
 use burncloud_common::{scaled_to_rate, Currency};
 use burncloud_database::{sqlx, Database};
+use burncloud_database_billing::BillingExchangeRateModel;
 use chrono::{DateTime, Utc};
 
-/// Background sync check interval (1 hour).
-const SYNC_CHECK_INTERVAL_SECS: u64 = 3600;
+use crate::exchange_rate_provider::{change_pct, ExchangeRateConfig};
+
 /// Hours after which exchange rates are considered stale and should be refreshed.
 const STALE_THRESHOLD_HOURS: i64 = 24;
-/// Timeout for exchange rate API calls.
-#[cfg(feature = "exchange-api")]
-const API_TIMEOUT_SECS: u64 = 5;
 use dashmap::DashMap;
 
 /// Exchange rate entry with timestamp
@@ -54,6 +56,16 @@ pub struct ExchangeRateService {
     db: Arc<Database>,
     /// In-memory cache for exchange rates: (from_currency, to_currency) -> rate
     rates: DashMap<(Currency, Currency), CachedRate>,
+    config: ExchangeRateConfig,
+}
+
+/// Outcome of one provider refresh
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RefreshReport {
+    /// Pairs whose rate was written
+    pub applied: usize,
+    /// Pairs refused by the sanity bound, old rate kept
+    pub rejected: usize,
 }
 
 impl ExchangeRateService {
@@ -62,9 +74,16 @@ impl ExchangeRateService {
         Self {
             db,
             rates: DashMap::new(),
+            config: ExchangeRateConfig::default(),
         }
     }
 
+    /// Set the refresh provider, interval and sanity bound
+    pub fn with_config(mut self, config: ExchangeRateConfig) -> Self {
+        self.config = config;
+        self
+    }
+
     /// Convert an amount from one currency to another
     pub fn convert(&self, amount: f64, from: Currency, to: Currency) -> anyhow::Result<f64> {
         if from == to {
@@ -166,22 +185,100 @@ impl ExchangeRateService {
         self.rates.get(&(from, to)).map(|r| r.updated_at)
     }
 
+    /// Fetch a snapshot from the configured provider and apply it.
+    ///
+    /// Each quote is written in both directions (`base -> quote` and its
+    /// inverse). A pair whose rate moved more than `max_change_pct` from the
+    /// cached rate is refused: the old rate stays and the proposal is kept in
+    /// `billing_exchange_rate_history` with `applied = 0`.
+    pub async fn refresh_once(&self) -> anyhow::Result<RefreshReport> {
+        let provider = self
+            .config
+            .provider
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("no exchange rate provider configured"))?;
+        let snapshot = provider.fetch().await?;
+        let mut report = RefreshReport::default();
+        for (quote, rate) in snapshot.rates {
+            for (from, to, rate) in [
+                (snapshot.base, quote, rate),
+                (quote, snapshot.base, 1.0 / rate),
+            ] {
+                if self.apply_rate(from, to, rate, provider.name()).await? {
+                    report.applied += 1;
+                } else {
+                    report.rejected += 1;
+                }
+            }
+        }
+        tracing::info!(
+            provider = provider.name(),
+            applied = report.applied,
+            rejected = report.rejected,
+            "Exchange rates refreshed"
+        );
+        Ok(report)
+    }
+
+    /// Write one rate if it is within the sanity bound. Returns false when
+    /// the rate was refused.
+    async fn apply_rate(
+        &self,
+        from: Currency,
+        to: Currency,
+        rate: f64,
+        source: &str,
+    ) -> anyhow::Result<bool> {
+        let rate_nano = burncloud_common::rate_to_scaled(rate);
+        if let Some(current) = self.get_rate(from, to) {
+            let moved = change_pct(current, rate);
+            if moved > self.config.max_change_pct {
+                let note = format!(
+                    "moved {moved:.2}% from {current:.6}, bound is {:.2}%",
+                    self.config.max_change_pct
+                );
+                tracing::warn!(%from, %to, rate, "Refusing exchange rate: {note}");
+                BillingExchangeRateModel::record(
+                    &self.db,
+                    from.code(),
+                    to.code(),
+                    rate_nano,
+                    source,
+                    false,
+                    Some(&note),
+                )
+                .await?;
+                return Ok(false);
+            }
+        }
+        BillingExchangeRateModel::upsert(&self.db, from.code(), to.code(), rate_nano, source)
+            .await?;
+        self.rates.insert(
+            (from, to),
+            CachedRate {
+                rate_nano,
+                updated_at: Utc::now(),
+            },
+        );
+        Ok(true)
+    }
+
     /// Start a background task to periodically refresh exchange rates
     ///
-    /// This spawns a tokio task that:
-    /// - Checks every hour if rates need to be refreshed
-    /// - Refreshes rates that are older than 24 hours
+    /// This spawns a tokio task that, every `EXCHANGE_RATE_REFRESH_SECS`:
+    /// - Reloads rates from the database, picking up manual edits
+    /// - Refreshes from the configured provider, if any
+    /// - Warns about rates older than 24 hours when no provider is set
     /// - Logs warnings on failure but doesn't panic
     ///
     /// # Example
     /// ```ignore
-    /// let service = Arc::new(ExchangeRateService::new(db));
+    /// let service = Arc::new(ExchangeRateService::new(db).with_config(ExchangeRateConfig::from_env()));
     /// service.start_sync_task();
     /// ```
     pub fn start_sync_task(self: Arc<Self>) {
         tokio::spawn(async move {
-            let mut interval =
-                tokio::time::interval(tokio::time::Duration::from_secs(SYNC_CHECK_INTERVAL_SECS));
+            let mut interval = tokio::time::interval(self.config.refresh_interval);
 
             loop {
                 interval.tick().await;
@@ -196,6 +293,13 @@ impl ExchangeRateService {
                     }
                 }
 
+                if self.config.provider.is_some() {
+                    if let Err(e) = self.refresh_once().await {
+                        tracing::warn!("Exchange rate refresh failed: {}", e);
+                    }
+                    continue;
+                }
+
                 // Check if we need to refresh rates (older than 24 hours)
                 let now = Utc::now();
                 let needs_refresh = self.rates.iter().any(|entry| {
@@ -204,55 +308,14 @@ impl ExchangeRateService {
                 });
 
                 if needs_refresh {
-                    tracing::info!("Exchange rates are stale, attempting refresh");
-                    // Note: External API refresh would go here
-                    // For now, we just log that refresh is needed
                     tracing::info!(
-                        "Exchange rate auto-refresh not configured. \
-                         Use 'burncloud currency set-rate' to update manually."
+                        "Exchange rates are stale and auto-refresh is not configured. \
+                         Set EXCHANGE_RATE_PROVIDER or use 'burncloud currency set-rate'."
                     );
                 }
             }
         });
     }
-
-    /// Start exchange rate sync task with external API support
-    ///
-    /// This is an extended version that can fetch rates from an external API.
-    /// The API URL should return JSON like: {"USD_CNY": 7.2, "EUR_USD": 1.08}
-    #[cfg(feature = "exchange-api")]
-    pub async fn fetch_from_api(&self, api_url: &str) -> anyhow::Result<()> {
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(API_TIMEOUT_SECS))
-            .build()?;
-
-        let response = client.get(api_url).send().await?;
-        let json: serde_json::Value = response.json().await?;
-
-        // Parse rates from API response
-        // Expected format: {"USD_CNY": 7.2, "EUR_USD": 1.08}
-        if let Some(obj) = json.as_object() {
-            for (key, value) in obj {
-                if let (Some(from), Some(to)) = (key.split('_').next(), key.split('_').nth(1)) {
-                    if let (Ok(from_currency), Ok(to_currency), Some(rate)) = (
-                        Currency::from_str(from),
-                        Currency::from_str(to),
-                        value.as_f64(),
-                    ) {
-                        self.set_rate(from_currency, to_currency, rate);
-                        tracing::info!(
-                            "Updated rate: {} -> {} = {}",
-                            from_currency,
-                            to_currency,
-                            rate
-                        );
-                    }
-                }
-            }
-        }
-
-        Ok(())
-    }
 }
 
 #[cfg(test)]
@@ -502,4 +565,113 @@ mod tests {
         let reverse = service.convert(720.0, Currency::CNY, Currency::USD);
         assert!(reverse.is_err());
     }
+
+    async fn service_with_file(rates: &str) -> (ExchangeRateService, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let db = burncloud_database::create_database_with_url(&format!(
+            "sqlite://{}?mode=rwc",
+            dir.path().join("rates.db").display()
+        ))
+        .await
+        .unwrap();
+        let file = dir.path().join("rates.json");
+        std::fs::write(&file, rates).unwrap();
+        let config = ExchangeRateConfig {
+            provider: Some(Arc::new(
+                crate::exchange_rate_provider::StaticFileProvider::new(file),
+            )),
+            max_change_pct: 10.0,
+            ..Default::default()
+        };
+        (
+            ExchangeRateService::new(Arc::new(db)).with_config(config),
+            dir,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_refresh_applies_both_directions_and_records_history() {
+        let (service, _dir) =
+            service_with_file(r#"{"base":"USD","rates":{"JPY":150,"GBP":0.8}}"#).await;
+
+        let report = service.refresh_once().await.unwrap();
+        assert_eq!(
+            report,
+            RefreshReport {
+                applied: 4,
+                rejected: 0
+            }
+        );
+        assert_eq!(service.get_rate(Currency::USD, Currency::JPY), Some(150.0));
+        assert_eq!(service.get_rate(Currency::GBP, Currency::USD), Some(1.25));
+
+        // Persisted, so a fresh load sees the same rates
+        service.clear_cache();
+        assert_eq!(service.load_rates_from_db().await.unwrap(), 4);
+        let history = BillingExchangeRateModel::history(&service.db, "USD", "JPY", 10)
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].source, "file");
+        assert_eq!(history[0].applied, 1);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_refuses_rates_beyond_bound() {
+        let (service, dir) = service_with_file(r#"{"base":"USD","rates":{"CNY":7.2}}"#).await;
+        service.refresh_once().await.unwrap();
+
+        // 7.2 -> 9.0 is a 25% move
+        std::fs::write(
+            dir.path().join("rates.json"),
+            r#"{"base":"USD","rates":{"CNY":9.0}}"#,
+        )
+        .unwrap();
+        let report = service.refresh_once().await.unwrap();
+        assert_eq!(
+            report,
+            RefreshReport {
+                applied: 0,
+                rejected: 2
+            }
+        );
+        assert_eq!(service.get_rate(Currency::USD, Currency::CNY), Some(7.2));
+
+        let history = BillingExchangeRateModel::history(&service.db, "USD", "CNY", 10)
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].applied, 0);
+        assert!(history[0].note.as_deref().unwrap().contains("25.00%"));
+
+        // The audit lookup still answers with the applied rate
+        let at = chrono::Utc::now().timestamp();
+        let rate = BillingExchangeRateModel::rate_at(&service.db, "USD", "CNY", at)
+            .await
+            .unwrap();
+        assert_eq!(rate, Some(rate_to_scaled(7.2)));
+
+        // A move within the bound goes through
+        std::fs::write(
+            dir.path().join("rates.json"),
+            r#"{"base":"USD","rates":{"CNY":7.5}}"#,
+        )
+        .unwrap();
+        let report = service.refresh_once().await.unwrap();
+        assert_eq!(report.applied, 2);
+        assert_eq!(service.get_rate(Currency::USD, Currency::CNY), Some(7.5));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_without_provider_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = burncloud_database::create_database_with_url(&format!(
+            "sqlite://{}?mode=rwc",
+            dir.path().join("rates.db").display()
+        ))
+        .await
+        .unwrap();
+        let service = ExchangeRateService::new(Arc::new(db));
+        assert!(service.refresh_once().await.is_err());
+    }
 }
@@ -0,0 +1,367 @@
+//! Exchange rate providers
+//!
+//! A provider returns a snapshot of rates quoted against one base currency.
+//! [`ExchangeRateService`](crate::exchange_rate::ExchangeRateService) applies
+//! the snapshot on a schedule, refusing rates that moved more than the
+//! configured bound.
+//!
+//! Built-in providers, selected with `EXCHANGE_RATE_PROVIDER`:
+//! - `open-er-api`: open.er-api.com, no key required
+//! - `frankfurter`: api.frankfurter.app, ECB reference rates
+//! - `exchangerate-api`: v6.exchangerate-api.com, needs `EXCHANGE_RATE_API_KEY`
+//! - `file`: a local JSON file at `EXCHANGE_RATE_FILE`
+//!
+//! All of them accept the same body shape, `{"base": "USD", "rates": {"CNY": 7.2}}`,
+//! with `base_code` / `conversion_rates` accepted as aliases.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use burncloud_common::Currency;
+use serde::Deserialize;
+
+/// Timeout for exchange rate API calls.
+const API_TIMEOUT_SECS: u64 = 10;
+/// Default interval between refreshes (1 hour).
+const DEFAULT_REFRESH_SECS: u64 = 3600;
+/// Default largest accepted move between two consecutive rates, in percent.
+const DEFAULT_MAX_CHANGE_PCT: f64 = 10.0;
+
+/// Rates quoted against `base`: one unit of `base` buys `rate` units of the quote
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateSnapshot {
+    pub base: Currency,
+    pub rates: Vec<(Currency, f64)>,
+}
+
+/// Source of exchange rates
+#[async_trait]
+pub trait ExchangeRateProvider: Send + Sync {
+    /// Name recorded as the `source` of every history row
+    fn name(&self) -> &str;
+
+    async fn fetch(&self) -> anyhow::Result<RateSnapshot>;
+}
+
+#[derive(Debug, Deserialize)]
+struct RatesBody {
+    #[serde(default, alias = "base_code")]
+    base: Option<String>,
+    #[serde(alias = "conversion_rates")]
+    rates: HashMap<String, f64>,
+    /// `"error"` on exchangerate-api style failures
+    #[serde(default)]
+    result: Option<String>,
+    #[serde(default, rename = "error-type")]
+    error_type: Option<String>,
+}
+
+/// Turn a rates body into a snapshot, keeping only supported currencies with
+/// a positive, finite rate. `expected` is the base the provider asked for.
+fn parse_snapshot(body: &str, expected: Option<Currency>) -> anyhow::Result<RateSnapshot> {
+    let body: RatesBody = serde_json::from_str(body)?;
+    if body.result.as_deref() == Some("error") {
+        return Err(anyhow::anyhow!(
+            "provider returned an error: {}",
+            body.error_type.as_deref().unwrap_or("unknown")
+        ));
+    }
+    let base = match body.base.as_deref().map(Currency::from_str) {
+        Some(Ok(base)) => base,
+        Some(Err(e)) => return Err(anyhow::anyhow!("unsupported base currency: {e}")),
+        None => expected.unwrap_or_default(),
+    };
+    if let Some(expected) = expected.filter(|e| *e != base) {
+        return Err(anyhow::anyhow!(
+            "provider quoted against {base}, expected {expected}"
+        ));
+    }
+    let mut rates: Vec<(Currency, f64)> = body
+        .rates
+        .iter()
+        .filter_map(|(code, rate)| {
+            let currency = Currency::from_str(code).ok()?;
+            (currency != base && rate.is_finite() && *rate > 0.0).then_some((currency, *rate))
+        })
+        .collect();
+    rates.sort_by_key(|(c, _)| c.code());
+    if rates.is_empty() {
+        return Err(anyhow::anyhow!("provider returned no supported rates"));
+    }
+    Ok(RateSnapshot { base, rates })
+}
+
+/// Public JSON rate API
+pub struct HttpRateProvider {
+    name: &'static str,
+    url: String,
+    base: Currency,
+    client: reqwest::Client,
+}
+
+impl HttpRateProvider {
+    /// `url` is the full request URL, already containing the base currency
+    pub fn new(name: &'static str, url: String, base: Currency) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(API_TIMEOUT_SECS))
+            .build()
+            .unwrap_or_default();
+        Self {
+            name,
+            url,
+            base,
+            client,
+        }
+    }
+
+    /// open.er-api.com, free and keyless, refreshed daily
+    pub fn open_er_api(endpoint: Option<&str>, base: Currency) -> Self {
+        let endpoint = endpoint.unwrap_or("https://open.er-api.com/v6/latest");
+        Self::new(
+            "open-er-api",
+            format!("{}/{}", endpoint.trim_end_matches('/'), base.code()),
+            base,
+        )
+    }
+
+    /// api.frankfurter.app, European Central Bank reference rates
+    pub fn frankfurter(endpoint: Option<&str>, base: Currency) -> Self {
+        let endpoint = endpoint.unwrap_or("https://api.frankfurter.app/latest");
+        Self::new(
+            "frankfurter",
+            format!("{}?from={}", endpoint.trim_end_matches('/'), base.code()),
+            base,
+        )
+    }
+
+    /// v6.exchangerate-api.com, keyed
+    pub fn exchangerate_api(endpoint: Option<&str>, api_key: &str, base: Currency) -> Self {
+        let endpoint = endpoint.unwrap_or("https://v6.exchangerate-api.com/v6");
+        Self::new(
+            "exchangerate-api",
+            format!(
+                "{}/{}/latest/{}",
+                endpoint.trim_end_matches('/'),
+                api_key,
+                base.code()
+            ),
+            base,
+        )
+    }
+}
+
+#[async_trait]
+impl ExchangeRateProvider for HttpRateProvider {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    async fn fetch(&self) -> anyhow::Result<RateSnapshot> {
+        let response = self.client.get(&self.url).send().await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(anyhow::anyhow!("{} returned HTTP {}", self.name, status));
+        }
+        parse_snapshot(&body, Some(self.base))
+    }
+}
+
+/// Rates read from a local JSON file, re-read on every refresh so operators
+/// can edit it in place
+pub struct StaticFileProvider {
+    path: PathBuf,
+}
+
+impl StaticFileProvider {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl ExchangeRateProvider for StaticFileProvider {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    async fn fetch(&self) -> anyhow::Result<RateSnapshot> {
+        let body = tokio::fs::read_to_string(&self.path)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to read {}: {e}", self.path.display()))?;
+        parse_snapshot(&body, None)
+    }
+}
+
+/// Refresh schedule and provider selection
+#[derive(Clone)]
+pub struct ExchangeRateConfig {
+    pub provider: Option<std::sync::Arc<dyn ExchangeRateProvider>>,
+    pub refresh_interval: std::time::Duration,
+    /// Largest accepted move from the current rate, in percent. A rate that
+    /// moved further is recorded as refused and the old rate is kept.
+    pub max_change_pct: f64,
+}
+
+impl Default for ExchangeRateConfig {
+    fn default() -> Self {
+        Self {
+            provider: None,
+            refresh_interval: std::time::Duration::from_secs(DEFAULT_REFRESH_SECS),
+            max_change_pct: DEFAULT_MAX_CHANGE_PCT,
+        }
+    }
+}
+
+impl std::fmt::Debug for ExchangeRateConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExchangeRateConfig")
+            .field("provider", &self.provider.as_ref().map(|p| p.name()))
+            .field("refresh_interval", &self.refresh_interval)
+            .field("max_change_pct", &self.max_change_pct)
+            .finish()
+    }
+}
+
+impl ExchangeRateConfig {
+    /// Read `EXCHANGE_RATE_PROVIDER`, `EXCHANGE_RATE_BASE`, `EXCHANGE_RATE_URL`,
+    /// `EXCHANGE_RATE_API_KEY`, `EXCHANGE_RATE_FILE`,
+    /// `EXCHANGE_RATE_REFRESH_SECS` and `EXCHANGE_RATE_MAX_CHANGE_PCT`.
+    /// A misconfigured provider is logged and disabled.
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let base = var("EXCHANGE_RATE_BASE")
+            .and_then(|b| match Currency::from_str(&b) {
+                Ok(c) => Some(c),
+                Err(e) => {
+                    tracing::warn!("EXCHANGE_RATE_BASE: {e}, using USD");
+                    None
+                }
+            })
+            .unwrap_or_default();
+        let endpoint = var("EXCHANGE_RATE_URL");
+        let endpoint = endpoint.as_deref();
+
+        let provider: Option<std::sync::Arc<dyn ExchangeRateProvider>> = match var(
+            "EXCHANGE_RATE_PROVIDER",
+        )
+        .as_deref()
+        {
+            None | Some("none") => None,
+            Some("open-er-api") => Some(std::sync::Arc::new(HttpRateProvider::open_er_api(
+                endpoint, base,
+            ))),
+            Some("frankfurter") => Some(std::sync::Arc::new(HttpRateProvider::frankfurter(
+                endpoint, base,
+            ))),
+            Some("exchangerate-api") => match var("EXCHANGE_RATE_API_KEY") {
+                Some(key) => Some(std::sync::Arc::new(HttpRateProvider::exchangerate_api(
+                    endpoint, &key, base,
+                ))),
+                None => {
+                    tracing::warn!(
+                            "EXCHANGE_RATE_PROVIDER=exchangerate-api needs EXCHANGE_RATE_API_KEY, refresh disabled"
+                        );
+                    None
+                }
+            },
+            Some("file") => match var("EXCHANGE_RATE_FILE") {
+                Some(path) => Some(std::sync::Arc::new(StaticFileProvider::new(path))),
+                None => {
+                    tracing::warn!(
+                        "EXCHANGE_RATE_PROVIDER=file needs EXCHANGE_RATE_FILE, refresh disabled"
+                    );
+                    None
+                }
+            },
+            Some(other) => {
+                tracing::warn!("Unknown EXCHANGE_RATE_PROVIDER '{other}', refresh disabled");
+                None
+            }
+        };
+
+        let defaults = Self::default();
+        Self {
+            provider,
+            refresh_interval: var("EXCHANGE_RATE_REFRESH_SECS")
+                .and_then(|v| v.parse::<u64>().ok())
+                .filter(|secs| *secs > 0)
+                .map(std::time::Duration::from_secs)
+                .unwrap_or(defaults.refresh_interval),
+            max_change_pct: var("EXCHANGE_RATE_MAX_CHANGE_PCT")
+                .and_then(|v| v.parse::<f64>().ok())
+                .filter(|pct| pct.is_finite() && *pct > 0.0)
+                .unwrap_or(defaults.max_change_pct),
+        }
+    }
+}
+
+/// Percentage move from `current` to `proposed`
+pub fn change_pct(current: f64, proposed: f64) -> f64 {
+    if current <= 0.0 {
+        return f64::INFINITY;
+    }
+    ((proposed - current) / current).abs() * 100.0
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::expect_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_common_body_shapes() {
+        let open_er = r#"{"result":"success","base_code":"USD","rates":{"USD":1,"CNY":7.1,"JPY":149.5,"XAU":0.0004}}"#;
+        let snapshot = parse_snapshot(open_er, Some(Currency::USD)).unwrap();
+        assert_eq!(snapshot.base, Currency::USD);
+        assert_eq!(
+            snapshot.rates,
+            vec![(Currency::CNY, 7.1), (Currency::JPY, 149.5)]
+        );
+
+        let keyed = r#"{"result":"success","base_code":"EUR","conversion_rates":{"GBP":0.85}}"#;
+        let snapshot = parse_snapshot(keyed, Some(Currency::EUR)).unwrap();
+        assert_eq!(snapshot.rates, vec![(Currency::GBP, 0.85)]);
+
+        let file = r#"{"rates":{"HKD":7.8,"KRW":-1}}"#;
+        let snapshot = parse_snapshot(file, None).unwrap();
+        assert_eq!(snapshot.base, Currency::USD);
+        assert_eq!(snapshot.rates, vec![(Currency::HKD, 7.8)]);
+    }
+
+    #[test]
+    fn rejects_errors_and_mismatched_base() {
+        let error = r#"{"result":"error","error-type":"invalid-key","rates":{}}"#;
+        assert!(parse_snapshot(error, Some(Currency::USD)).is_err());
+        let wrong_base = r#"{"base":"EUR","rates":{"USD":1.08}}"#;
+        assert!(parse_snapshot(wrong_base, Some(Currency::USD)).is_err());
+        assert!(parse_snapshot(r#"{"rates":{"XAU":1}}"#, None).is_err());
+    }
+
+    #[tokio::test]
+    async fn http_provider_fetches_from_endpoint() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/latest?from=USD")
+            .with_body(r#"{"amount":1.0,"base":"USD","rates":{"EUR":0.92,"CHF":0.88}}"#)
+            .create_async()
+            .await;
+        let provider =
+            HttpRateProvider::frankfurter(Some(&format!("{}/latest", server.url())), Currency::USD);
+        let snapshot = provider.fetch().await.unwrap();
+        mock.assert_async().await;
+        assert_eq!(
+            snapshot.rates,
+            vec![(Currency::CHF, 0.88), (Currency::EUR, 0.92)]
+        );
+    }
+
+    #[test]
+    fn change_is_relative_to_current_rate() {
+        assert!((change_pct(7.0, 7.7) - 10.0).abs() < 1e-9);
+        assert!((change_pct(7.0, 6.3) - 10.0).abs() < 1e-9);
+        assert!(change_pct(0.0, 1.0).is_infinite());
+    }
+}
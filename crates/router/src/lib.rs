@@ -7,10 +7,16 @@ mod aimd_limiter;
 mod balancer;
 pub mod channel_state;
 mod circuit_breaker;
+pub mod client_limit;
 mod config;
+pub mod context_window;
 pub mod exchange_rate;
+pub mod exchange_rate_provider;
 mod limiter;
+pub mod local_instance;
 pub mod metrics;
+pub mod model_catalog;
+pub mod model_policy;
 pub mod model_router;
 pub mod order_type;
 pub mod passthrough;
@@ -21,7 +27,9 @@ pub mod response_quality;
 mod scheduler;
 mod stream_peek;
 mod state;
+pub mod state_sync;
 pub mod stream_parser;
+mod telemetry;
 pub mod token_counter;
 
 /// Peek first chunk timeout (seconds). Used to detect immediate errors (auth, rate limit).
@@ -142,23 +150,27 @@ use axum::{
 };
 use balancer::RoundRobinBalancer;
 use burncloud_common::types::OpenAIChatRequest;
-use burncloud_common::TrafficColor;
+use burncloud_common::{RateLimits, TrafficColor};
 use burncloud_database::Database;
+use burncloud_database_channel::key_cipher::decrypt_key;
 use burncloud_database_channel::ChannelProviderModel;
 use burncloud_database_router::{
-    CandidateInfo, FailoverAttempt, RouterDatabase, RouterLog, RouterRequestLog,
-    RouterTokenValidationResult, RouterVideoTask, RouterVideoTaskModel, StoragePolicy,
+    token_hash, CandidateInfo, FailoverAttempt, OrgAdmission, RouterDatabase, RouterLog,
+    RouterRequestLog, RouterTokenValidationResult, RouterVideoTask, RouterVideoTaskModel,
+    StoragePolicy, UsageRollupModel, VirtualModelHop,
 };
 use burncloud_service_billing::{
     get_parser, parse_chunk_or_default, parse_response_or_default, UnifiedTokenCounter,
 };
-use burncloud_service_user::UserService;
+use burncloud_service_user::{UserService, UserServiceError};
 use channel_state::ChannelStateTracker;
 use circuit_breaker::CircuitBreaker;
+use client_limit::{Admission, ConcurrencyPermit, LimitScope, RateLimitStatus, TokenReconcile};
 use config::{AuthType, Upstream};
 use futures::stream::StreamExt;
 use http_body_util::BodyExt;
 use limiter::RateLimiter;
+use model_policy::ModelPolicyRejection;
 use model_router::ModelRouter;
 use order_type::OrderType;
 use reqwest::Client;
@@ -167,6 +179,7 @@ use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::{mpsc, RwLock};
 use tower_http::cors::CorsLayer;
+use tracing::Instrument;
 use uuid::Uuid;
 
 /// Video billing: resolution multiplier for 720p Seedance videos.
@@ -266,6 +279,9 @@ fn record_failover_attempt(
 struct ProxyResult {
     response: Response,
     upstream_id: Option<String>,
+    /// Concrete model of the last candidate tried. Differs from the requested
+    /// name when a virtual model resolved to one of its hops.
+    served_model: Option<String>,
     final_status: StatusCode,
     pricing_region: Option<String>,
     video_task_id: Option<String>,
@@ -292,11 +308,55 @@ struct JwtClaims {
     sub: String,
     #[allow(dead_code)]
     exp: usize,
-    #[allow(dead_code)]
     iat: usize,
+    /// Console session the token belongs to
+    #[serde(default)]
+    sid: Option<String>,
+}
+
+/// User id of a console JWT whose session is still live. Logged-out,
+/// revoked or expired sessions are rejected like a bad signature, and so is
+/// a failed session lookup.
+async fn console_jwt_user(db: &Database, token: &str) -> Option<String> {
+    let secret = burncloud_common::constants::jwt_secret();
+    let claims = jsonwebtoken::decode::<JwtClaims>(
+        token,
+        &jsonwebtoken::DecodingKey::from_secret(secret.as_bytes()),
+        &jsonwebtoken::Validation::default(),
+    )
+    .ok()?
+    .claims;
+    let issued_at = i64::try_from(claims.iat).unwrap_or(i64::MAX);
+    match UserService::check_access(db, &claims.sub, claims.sid.as_deref(), issued_at).await {
+        Ok(()) => Some(claims.sub),
+        Err(UserServiceError::SessionRevoked | UserServiceError::UserNotFound) => None,
+        Err(e) => {
+            tracing::error!(user_id = %claims.sub, "Session check failed: {e}");
+            None
+        }
+    }
 }
 
 /// Build a JSON error response body: `{"error": "<message>"}`.
+/// Record the upstream status (or transport error) on a `proxy_attempt` span.
+fn record_attempt_outcome(
+    span: &tracing::Span,
+    result: &Result<reqwest::Response, reqwest::Error>,
+) {
+    match result {
+        Ok(resp) => {
+            span.record("http.status_code", i64::from(resp.status().as_u16()));
+            if !resp.status().is_success() {
+                span.record("otel.status_code", "ERROR");
+            }
+        }
+        Err(e) => {
+            span.record("error", tracing::field::display(e));
+            span.record("otel.status_code", "ERROR");
+        }
+    }
+}
+
 fn json_error_body(message: impl std::fmt::Display) -> Body {
     Body::from(serde_json::json!({"error": message.to_string()}).to_string())
 }
@@ -633,6 +693,23 @@ fn apply_header_override(
     }
 }
 
+/// Rewrite a request body for a virtual-model hop: the `model` field names the
+/// hop's concrete model and the hop's parameter overrides replace top-level
+/// fields. Bodies without a `model` field (Gemini native) keep it in the path.
+fn apply_virtual_hop(body: &mut serde_json::Value, upstream: &Upstream) {
+    let serde_json::Value::Object(map) = body else {
+        return;
+    };
+    if let (Some(model), Some(field)) = (upstream.served_model.as_ref(), map.get_mut("model")) {
+        *field = serde_json::Value::String(model.clone());
+    }
+    if let Some(ref overrides) = upstream.hop_param_override {
+        for (k, v) in overrides {
+            map.insert(k.clone(), v.clone());
+        }
+    }
+}
+
 /// Helper function to build a response with a header safely.
 fn build_response_with_header(
     status: StatusCode,
@@ -652,6 +729,98 @@ fn build_response_with_header(
         })
 }
 
+/// Estimated tokens of a request for client TPM limits: prompt bytes / 4
+/// plus the requested completion budget.
+fn estimate_client_tokens(body_bytes: &[u8]) -> i64 {
+    let max_tokens = serde_json::from_slice::<serde_json::Value>(body_bytes)
+        .ok()
+        .and_then(|v| {
+            v.get("max_tokens")
+                .or_else(|| v.get("max_completion_tokens"))
+                .and_then(|m| m.as_i64())
+        })
+        .unwrap_or(0);
+    (body_bytes.len() as i64 / 4).saturating_add(max_tokens.max(0))
+}
+
+/// Context-window preflight for `handle_proxy_request`. Returns the body,
+/// path and model to continue with (rewritten on a clamp or long-context
+/// reroute), or the 400 to send when the request cannot fit.
+async fn context_preflight(
+    state: &AppState,
+    body_bytes: axum::body::Bytes,
+    path: String,
+    model_name: Option<String>,
+) -> Result<(axum::body::Bytes, String, Option<String>), Response> {
+    let Some(model) = model_name.as_deref().filter(|_| state.context_window.enabled) else {
+        return Ok((body_bytes, path, model_name));
+    };
+    let Ok(mut body_json) = serde_json::from_slice::<serde_json::Value>(&body_bytes) else {
+        return Ok((body_bytes, path, model_name));
+    };
+    let outcome = match context_window::preflight(
+        &state.context_window,
+        &state.price_cache,
+        model,
+        &mut body_json,
+    )
+    .await
+    {
+        Ok(outcome) => outcome,
+        Err(overflow) => {
+            tracing::info!(model = %model, ?overflow, "Context preflight rejected request");
+            let body = serde_json::json!({
+                "error": {
+                    "message": overflow.message(model),
+                    "type": "invalid_request_error",
+                    "code": "context_length_exceeded",
+                }
+            });
+            return Err(build_response_with_header(
+                StatusCode::BAD_REQUEST,
+                "content-type",
+                "application/json",
+                Body::from(body.to_string()),
+            ));
+        }
+    };
+    if outcome == context_window::Preflight::Unchanged {
+        return Ok((body_bytes, path, model_name));
+    }
+    let body_bytes = match serde_json::to_vec(&body_json) {
+        Ok(bytes) => bytes.into(),
+        Err(_) => body_bytes,
+    };
+    match outcome {
+        context_window::Preflight::Rerouted { model: sibling } => {
+            tracing::info!(model = %model, sibling = %sibling, "Rerouting to long-context model");
+            let path = crate::passthrough::with_gemini_path_model(&path, &sibling).unwrap_or(path);
+            Ok((body_bytes, path, Some(sibling)))
+        }
+        context_window::Preflight::Clamped { max_tokens } => {
+            tracing::info!(model = %model, max_tokens, "Clamped max_tokens to the model limit");
+            Ok((body_bytes, path, model_name))
+        }
+        context_window::Preflight::Unchanged => Ok((body_bytes, path, model_name)),
+    }
+}
+
+/// OpenAI-style `x-ratelimit-*` headers for client limits.
+fn insert_rate_limit_headers(headers: &mut HeaderMap, status: &RateLimitStatus) {
+    let reset = HeaderValue::from_str(&format!("{}s", status.reset_secs))
+        .unwrap_or_else(|_| HeaderValue::from_static("60s"));
+    if let (Some(limit), Some(remaining)) = (status.limit_requests, status.remaining_requests) {
+        headers.insert("x-ratelimit-limit-requests", HeaderValue::from(limit));
+        headers.insert("x-ratelimit-remaining-requests", HeaderValue::from(remaining));
+        headers.insert("x-ratelimit-reset-requests", reset.clone());
+    }
+    if let (Some(limit), Some(remaining)) = (status.limit_tokens, status.remaining_tokens) {
+        headers.insert("x-ratelimit-limit-tokens", HeaderValue::from(limit));
+        headers.insert("x-ratelimit-remaining-tokens", HeaderValue::from(remaining));
+        headers.insert("x-ratelimit-reset-tokens", reset);
+    }
+}
+
 /// Startup helper: load every channel's L2 Shaper config (rpm_cap / tpm_cap /
 /// reservation triple) from `channel_providers` and feed it into
 /// [`rate_budget::InMemoryBudget`]. Channels with `rpm_cap = NULL` (or zero)
@@ -747,6 +916,7 @@ pub async fn create_router_app(
     Router,
     mpsc::Sender<tokio::sync::oneshot::Sender<price_sync::SyncResult>>,
 )> {
+    metrics::init_from_env();
     let client = Client::builder()
         .connect_timeout(std::time::Duration::from_secs(HTTP_CONNECT_TIMEOUT_SECS))
         .timeout(std::time::Duration::from_secs(HTTP_REQUEST_TIMEOUT_SECS))
@@ -754,7 +924,8 @@ pub async fn create_router_app(
         .tcp_keepalive(std::time::Duration::from_secs(HTTP_TCP_KEEPALIVE_SECS))
         .build()?;
     let balancer = Arc::new(RoundRobinBalancer::new());
-    // Rate limiter: 100 burst, 10 requests/second
+    // Default per-user rate limit for clients without RPM/TPM/concurrency
+    // limits: 100 burst, 10 requests/second
     let limiter = Arc::new(RateLimiter::new(100.0, 10.0));
     // Circuit breaker: 5 failure threshold, 30s cooldown
     let circuit_breaker = Arc::new(CircuitBreaker::new(
@@ -778,7 +949,10 @@ pub async fn create_router_app(
     let cost_calculator = burncloud_service_billing::CostCalculator::new(price_cache.clone());
 
     // Exchange Rate Service for multi-currency cost calculations
-    let exchange_rate_service = Arc::new(exchange_rate::ExchangeRateService::new(db.clone()));
+    let exchange_rate_service = Arc::new(
+        exchange_rate::ExchangeRateService::new(db.clone())
+            .with_config(exchange_rate_provider::ExchangeRateConfig::from_env()),
+    );
     if let Err(e) = exchange_rate_service.load_rates_from_db().await {
         tracing::warn!("Failed to load exchange rates at startup: {e}");
     }
@@ -875,36 +1049,67 @@ pub async fn create_router_app(
     );
 
     // Setup Async Logging Channel
-    let (log_tx, mut log_rx) = mpsc::channel::<RouterLog>(LOG_CHANNEL_BUFFER);
+    let (log_tx, mut log_rx) = mpsc::channel::<(RouterLog, tracing::Span)>(LOG_CHANNEL_BUFFER);
     let db_for_logger = db.clone(); // Clone Arc
 
     // Spawn Logging Task
     tokio::spawn(async move {
         tracing::info!("Logging task started");
-        while let Some(log) = log_rx.recv().await {
+        while let Some((log, span)) = log_rx.recv().await {
             // Need to create a new default database or use the shared one?
             // Since Database struct isn't thread-safe or Clone by default, we rely on Arc<Database>.
             // But RouterDatabase::insert_log takes &Database.
-            if let Err(e) = RouterDatabase::insert_log(&db_for_logger, &log).await {
+            if let Err(e) = RouterDatabase::insert_log(&db_for_logger, &log)
+                .instrument(span)
+                .await
+            {
                 tracing::error!("Failed to insert log: {}", e);
+                continue;
+            }
+            if let Err(e) = UsageRollupModel::record_log(&db_for_logger, &log).await {
+                tracing::error!("Failed to update usage rollups: {}", e);
             }
         }
     });
 
     // Setup Async Request Log Channel (detailed request/response logging)
-    let (request_log_tx, mut request_log_rx) = mpsc::channel::<RouterRequestLog>(LOG_CHANNEL_BUFFER);
+    let (request_log_tx, mut request_log_rx) =
+        mpsc::channel::<(RouterRequestLog, tracing::Span)>(LOG_CHANNEL_BUFFER);
     let db_for_request_logger = db.clone();
 
     // Spawn Request Logging Task
     tokio::spawn(async move {
         tracing::info!("Request logging task started");
-        while let Some(log) = request_log_rx.recv().await {
-            if let Err(e) = RouterDatabase::insert_request_log(&db_for_request_logger, &log).await {
+        while let Some((log, span)) = request_log_rx.recv().await {
+            if let Err(e) = RouterDatabase::insert_request_log(&db_for_request_logger, &log)
+                .instrument(span)
+                .await
+            {
                 tracing::error!("Failed to insert request log: {}", e);
             }
         }
     });
 
+    let channel_health_manager =
+        Arc::new(crate::channel_health_manager::ChannelHealthManager::new());
+
+    // Cross-instance state sync (BURNCLOUD_STATE_SYNC=redis|gossip). Off by
+    // default: a single instance has nothing to share.
+    if let Some(transport) = state_sync::transport_from_env().await {
+        let instance_id = state_sync::start(
+            transport,
+            state_sync::SyncTargets {
+                circuit_breaker: circuit_breaker.clone(),
+                channel_state_tracker: channel_state_tracker.clone(),
+                channel_health_manager: channel_health_manager.clone(),
+                affinity_cache: affinity_cache.clone(),
+                budget_update_tx: Some(budget_update_tx.clone()),
+            },
+            state_sync::SyncConfig::from_env(),
+        );
+        tracing::info!(%instance_id, "Router state sync started");
+    }
+
     let state = AppState {
         client,
         db, // Arc<Database>
@@ -931,7 +1136,11 @@ pub async fn create_router_app(
         budget_update_tx,
         request_log_storage_policy,
         empty_response_counter: Arc::new(EmptyResponseCounter::new()),
-        channel_health_manager: Arc::new(crate::channel_health_manager::ChannelHealthManager::new())
+        channel_health_manager,
+        model_spend: Arc::new(crate::model_policy::ModelSpendTracker::new()),
+        client_limits: client_limit::backend_from_env().await,
+        context_window: Arc::new(context_window::ContextWindowConfig::from_env()),
+        notifications: Arc::new(burncloud_service_notification::NotificationService::from_env()),
     };
 
     use burncloud_common::constants::INTERNAL_PREFIX;
@@ -952,7 +1161,24 @@ pub async fn create_router_app(
         .with_state(state.clone());
 
     let app = Router::new()
-        .route("/v1/models", axum::routing::get(models_handler))
+        .route(
+            "/v1/models",
+            axum::routing::get(model_catalog::list_models_handler),
+        )
+        // Non-GET requests under these paths (e.g. Gemini `:generateContent`)
+        // still go to the proxy.
+        .route(
+            "/v1/models/{*id}",
+            axum::routing::get(model_catalog::get_model_handler).fallback(proxy_handler),
+        )
+        .route(
+            "/v1beta/models",
+            axum::routing::get(model_catalog::gemini_list_models_handler),
+        )
+        .route(
+            "/v1beta/models/{*id}",
+            axum::routing::get(model_catalog::gemini_get_model_handler).fallback(proxy_handler),
+        )
         .route("/api/v1/usage", axum::routing::get(usage_handler))
         .route(
             "/api/v1/usage/models",
@@ -1038,47 +1264,6 @@ async fn circuit_breaker_trip_all_handler(State(state): State<AppState>) -> Resp
     )
 }
 
-async fn models_handler(State(state): State<AppState>) -> Response {
-    // Fetch all distinct models from channel_abilities
-    // This shows models that have at least one enabled channel
-    use burncloud_database_channel::ChannelAbilityModel;
-
-    let mut model_entries = Vec::new();
-    let current_time = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs();
-
-    if let Ok(models) = ChannelAbilityModel::list_distinct_models(&state.db).await {
-        for model in models {
-            model_entries.push(serde_json::json!({
-                "id": model,
-                "object": "model",
-                "created": current_time,
-                "owned_by": "burncloud",
-                "permission": [],
-                "root": model,
-                "parent": null,
-            }));
-        }
-    }
-
-    let response_json = serde_json::json!({
-        "object": "list",
-        "data": model_entries
-    });
-
-    build_response_with_header(
-        StatusCode::OK,
-        "content-type",
-        "application/json",
-        Body::from(
-            serde_json::to_string(&response_json)
-                .unwrap_or_else(|_| r#"{"object":"list","data":[]}"#.to_string()),
-        ),
-    )
-}
-
 /// Helper: extract and validate the Bearer token from an Authorization header.
 /// Returns (user_id, user_group) on success or an error Response.
 async fn extract_token_user(
@@ -1104,18 +1289,10 @@ async fn extract_token_user(
             match RouterDatabase::validate_token_detailed(&state.db, &token).await {
                 Ok(RouterTokenValidationResult::Valid(t)) => Ok(t.user_id),
                 _ => {
-                    // Fall back to JWT: decode and extract sub (user_id)
-                    let secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| {
-                        "burncloud-default-secret-change-in-production".to_string()
-                    });
-                    let decoded = jsonwebtoken::decode::<JwtClaims>(
-                        &token,
-                        &jsonwebtoken::DecodingKey::from_secret(secret.as_bytes()),
-                        &jsonwebtoken::Validation::default(),
-                    );
-                    match decoded {
-                        Ok(data) => Ok(data.claims.sub),
-                        _ => Err(build_response(
+                    // Fall back to a console JWT with a live session
+                    match console_jwt_user(&state.db, &token).await {
+                        Some(user_id) => Ok(user_id),
+                        None => Err(build_response(
                             StatusCode::UNAUTHORIZED,
                             Body::from(
                                 r#"{"error":{"message":"Invalid Token","type":"invalid_request_error","code":"invalid_token"}}"#,
@@ -1135,13 +1312,23 @@ async fn extract_token_user(
     }
 }
 
-/// GET /api/v1/usage — overall usage for the authenticated token holder.
+/// GET /api/v1/usage — overall usage and notification settings for the authenticated token holder.
 async fn usage_handler(State(state): State<AppState>, headers: axum::http::HeaderMap) -> Response {
     let user_id = match extract_token_user(&state, &headers).await {
         Ok(id) => id,
         Err(resp) => return resp,
     };
 
+    let notifications =
+        match burncloud_service_notification::NotificationService::view(&state.db, &user_id).await
+        {
+            Ok(view) => Some(view),
+            Err(e) => {
+                tracing::warn!(user_id, error = %e, "Failed to load notification settings");
+                None
+            }
+        };
+
     match burncloud_database_router::get_usage_stats(&state.db, &user_id, "month").await {
         Ok(stats) => build_response_with_header(
             StatusCode::OK,
@@ -1156,6 +1343,7 @@ async fn usage_handler(State(state): State<AppState>, headers: axum::http::Heade
                     "completion_tokens": stats.total_completion_tokens,
                     "total_cost_nano": stats.total_cost_nano,
                     "total_cost_usd": stats.total_cost_nano as f64 / 1_000_000_000.0,
+                    "notifications": notifications,
                 }))
                 .unwrap_or_else(|_| "{}".to_string()),
             ),
@@ -1362,9 +1550,87 @@ async fn proxy_handler(
     uri: Uri,
     headers: HeaderMap,
     body: Body,
+) -> Response {
+    // Fields are filled in as the request progresses; the span is built
+    // manually so an incoming `traceparent` can become its parent.
+    let span = tracing::info_span!(
+        "proxy_handler",
+        otel.kind = "server",
+        otel.status_code = tracing::field::Empty,
+        request_id = tracing::field::Empty,
+        http.method = %method,
+        http.route = %uri.path(),
+        http.status_code = tracing::field::Empty,
+        model = tracing::field::Empty,
+        channel_id = tracing::field::Empty,
+        routing_decision = tracing::field::Empty,
+        prompt_tokens = tracing::field::Empty,
+        completion_tokens = tracing::field::Empty,
+        cost_nano = tracing::field::Empty,
+    );
+    telemetry::set_remote_parent(&span, &headers);
+
+    let response = handle_proxy_request(state, method, uri, headers, body)
+        .instrument(span.clone())
+        .await;
+    span.record("http.status_code", i64::from(response.status().as_u16()));
+    if response.status().is_server_error() {
+        span.record("otel.status_code", "ERROR");
+    }
+    response
+}
+
+/// The account a data-plane request is made for, resolved from its credential
+struct Caller {
+    user_id: String,
+    group: String,
+    /// Key quota in nanodollars, -1 when unlimited
+    quota_limit: i64,
+    used_quota: i64,
+    order_type: Option<String>,
+    price_cap: Option<i64>,
+    org_id: Option<String>,
+    model_policy: Option<String>,
+    /// Hashes the key's spend is recorded under, current key first
+    token_hashes: Vec<String>,
+    token_limits: RateLimits,
+    /// Owner limits when the credential lookup already loaded them
+    user_limits: Option<RateLimits>,
+}
+
+/// Check `model` against the token's raw model policy. A policy that does
+/// not parse refuses every model.
+async fn model_policy_verdict(
+    state: &AppState,
+    raw_policy: &str,
+    token_hashes: &[String],
+    user_id: &str,
+    model: Option<&str>,
+) -> Result<Result<(), ModelPolicyRejection>, String> {
+    match burncloud_common::ModelPolicy::parse(raw_policy) {
+        Ok(policy) => state
+            .model_spend
+            .check(&state.db, token_hashes, &policy, model)
+            .await
+            .map_err(|e| e.to_string()),
+        Err(e) => {
+            // Policies are validated on write; fail closed on a corrupt one
+            tracing::error!(user_id = %user_id, "Unreadable token model policy: {e}");
+            Ok(Err(ModelPolicyRejection::NotAllowed))
+        }
+    }
+}
+
+async fn handle_proxy_request(
+    state: AppState,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Body,
 ) -> Response {
     let start_time = Instant::now();
     let request_id = Uuid::new_v4().to_string();
+    tracing::Span::current().record("request_id", request_id.as_str());
     let raw_path = uri.path().to_string();
 
     // Normalize doubled path prefixes caused by client SDKs that include
@@ -1393,109 +1659,133 @@ async fn proxy_handler(
     };
 
     // Check against DB
-    let (user_id, user_group, quota_limit, used_quota, order_type_str, price_cap) =
-        match RouterDatabase::validate_token_and_get_info(&state.db, &user_token).await {
-            Ok(Some(info)) => {
-                // Update accessed_time non-blocking
-                let db = state.db.clone();
-                let token = user_token.clone();
-                tokio::spawn(async move {
-                    let _ = RouterDatabase::update_token_accessed_time(&db, &token).await;
-                });
-                (
-                    info.user_id,
-                    info.group,
-                    info.remain_quota,
-                    info.used_quota,
-                    info.order_type,
-                    info.price_cap,
-                )
+    let caller = match RouterDatabase::validate_token_and_get_info(&state.db, &user_token).await {
+        Ok(Some(info)) => {
+            // Update accessed_time non-blocking
+            let db = state.db.clone();
+            let token = user_token.clone();
+            tokio::spawn(async move {
+                let _ = RouterDatabase::update_token_accessed_time(&db, &token).await;
+            });
+            Caller {
+                user_id: info.user_id,
+                group: info.group,
+                quota_limit: info.remain_quota,
+                used_quota: info.used_quota,
+                order_type: info.order_type,
+                price_cap: info.price_cap,
+                org_id: info.org_id,
+                model_policy: info.model_policy,
+                token_hashes: vec![token_hash(&user_token)],
+                token_limits: info.token_limits,
+                user_limits: Some(info.user_limits),
             }
-            Ok(None) => {
-                // Fallback to old token table logic with detailed validation
-                match RouterDatabase::validate_token_detailed(&state.db, &user_token).await {
-                    Ok(RouterTokenValidationResult::Valid(t)) => {
-                        // Update accessed_time non-blocking
-                        let db = state.db.clone();
-                        let token = user_token.clone();
-                        tokio::spawn(async move {
-                            let _ = RouterDatabase::update_token_accessed_time(&db, &token).await;
-                        });
-                        (
-                            t.user_id,
-                            "default".to_string(),
-                            t.quota_limit,
-                            t.used_quota,
-                            None,
-                            None,
-                        )
-                    }
-                    Ok(RouterTokenValidationResult::Expired) => {
-                        return build_response_with_header(
-                            StatusCode::UNAUTHORIZED,
-                            "content-type",
-                            "application/json",
-                            Body::from(
-                                r#"{"error":{"message":"Token has expired","type":"invalid_request_error","code":"token_expired"}}"#,
-                            ),
-                        )
+        }
+        Ok(None) => {
+            // Fallback to old token table logic with detailed validation
+            match RouterDatabase::validate_token_detailed(&state.db, &user_token).await {
+                Ok(RouterTokenValidationResult::Valid(t)) => {
+                    // Update accessed_time non-blocking
+                    let db = state.db.clone();
+                    let token = user_token.clone();
+                    tokio::spawn(async move {
+                        let _ = RouterDatabase::update_token_accessed_time(&db, &token).await;
+                    });
+                    // Spend logged under the pre-rotation key still counts
+                    let limits = t.rate_limits();
+                    let mut hashes = vec![token_hash(&t.token)];
+                    hashes.extend(t.old_key_hash);
+                    Caller {
+                        user_id: t.user_id,
+                        group: "default".to_string(),
+                        quota_limit: t.quota_limit,
+                        used_quota: t.used_quota,
+                        order_type: None,
+                        price_cap: None,
+                        org_id: t.org_id,
+                        model_policy: t.model_policy,
+                        token_hashes: hashes,
+                        token_limits: limits,
+                        user_limits: None,
                     }
-                    Ok(RouterTokenValidationResult::Invalid) => {
-                        // Fall back to JWT: decode and extract sub (user_id)
-                        let secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| {
-                            "burncloud-default-secret-change-in-production".to_string()
-                        });
-                        let decoded = jsonwebtoken::decode::<JwtClaims>(
-                            &user_token,
-                            &jsonwebtoken::DecodingKey::from_secret(secret.as_bytes()),
-                            &jsonwebtoken::Validation::default(),
-                        );
-                        match decoded {
-                            Ok(data) => (
-                                data.claims.sub,
-                                "default".to_string(),
-                                -1_i64,
-                                0_i64,
-                                None,
-                                None,
-                            ),
-                            _ => {
-                                return build_response_with_header(
-                                    StatusCode::UNAUTHORIZED,
-                                    "content-type",
-                                    "application/json",
-                                    Body::from(
-                                        r#"{"error":{"message":"Invalid Token","type":"invalid_request_error","code":"invalid_token"}}"#,
-                                    ),
-                                )
-                            }
+                }
+                Ok(RouterTokenValidationResult::Expired) => {
+                    return build_response_with_header(
+                        StatusCode::UNAUTHORIZED,
+                        "content-type",
+                        "application/json",
+                        Body::from(
+                            r#"{"error":{"message":"Token has expired","type":"invalid_request_error","code":"token_expired"}}"#,
+                        ),
+                    )
+                }
+                Ok(RouterTokenValidationResult::Invalid) => {
+                    // Fall back to a console JWT with a live session
+                    match console_jwt_user(&state.db, &user_token).await {
+                        Some(user_id) => Caller {
+                            user_id,
+                            group: "default".to_string(),
+                            quota_limit: -1,
+                            used_quota: 0,
+                            order_type: None,
+                            price_cap: None,
+                            org_id: None,
+                            model_policy: None,
+                            token_hashes: Vec::new(),
+                            token_limits: RateLimits::default(),
+                            user_limits: None,
+                        },
+                        None => {
+                            return build_response_with_header(
+                                StatusCode::UNAUTHORIZED,
+                                "content-type",
+                                "application/json",
+                                Body::from(
+                                    r#"{"error":{"message":"Invalid Token","type":"invalid_request_error","code":"invalid_token"}}"#,
+                                ),
+                            )
                         }
                     }
-                    Err(e) => {
-                        return build_response_with_header(
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            "content-type",
-                            "application/json",
-                            Body::from(format!(
-                                r#"{{"error":{{"message":"Internal Auth Error: {}","type":"server_error"}}}}"#,
-                                e
-                            )),
-                        )
-                    }
+                }
+                Err(e) => {
+                    return build_response_with_header(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "content-type",
+                        "application/json",
+                        Body::from(format!(
+                            r#"{{"error":{{"message":"Internal Auth Error: {}","type":"server_error"}}}}"#,
+                            e
+                        )),
+                    )
                 }
             }
-            Err(e) => {
-                return build_response_with_header(
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "content-type",
-                    "application/json",
-                    Body::from(format!(
-                        r#"{{"error":{{"message":"Internal Auth Error: {}","type":"server_error"}}}}"#,
-                        e
-                    )),
-                )
-            }
-        };
+        }
+        Err(e) => {
+            return build_response_with_header(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "content-type",
+                "application/json",
+                Body::from(format!(
+                    r#"{{"error":{{"message":"Internal Auth Error: {}","type":"server_error"}}}}"#,
+                    e
+                )),
+            )
+        }
+    };
+
+    let Caller {
+        user_id,
+        group: user_group,
+        quota_limit,
+        used_quota,
+        order_type: order_type_str,
+        price_cap,
+        org_id,
+        model_policy,
+        token_hashes,
+        token_limits,
+        user_limits,
+    } = caller;
 
     if quota_limit >= 0 && used_quota >= quota_limit {
         return build_response_with_header(
@@ -1508,14 +1798,42 @@ async fn proxy_handler(
         );
     }
 
-    // Rate Limiting Check
-    if !state.limiter.check(&user_id, 1.0) {
-        return build_response_with_header(
-            StatusCode::TOO_MANY_REQUESTS,
-            "content-type",
-            "application/json",
-            Body::from(r#"{"error":{"message":"Too Many Requests","type":"rate_limit_error","code":"rate_limit_exceeded"}}"#),
-        );
+    // Org-owned tokens spend from the organization's shared wallet
+    if let Some(ref org) = org_id {
+        let rejection = match RouterDatabase::org_admission(&state.db, org).await {
+            Ok(OrgAdmission::Allowed) => None,
+            Ok(OrgAdmission::NotFound) | Ok(OrgAdmission::Disabled) => Some((
+                StatusCode::FORBIDDEN,
+                r#"{"error":{"message":"Organization is disabled","type":"permission_error","code":"organization_disabled"}}"#,
+            )),
+            Ok(OrgAdmission::InsufficientBalance) => Some((
+                StatusCode::PAYMENT_REQUIRED,
+                r#"{"error":{"message":"Insufficient organization balance","type":"insufficient_quota_error","code":"insufficient_balance"}}"#,
+            )),
+            Ok(OrgAdmission::SpendLimitReached) => Some((
+                StatusCode::PAYMENT_REQUIRED,
+                r#"{"error":{"message":"Organization spend limit reached","type":"insufficient_quota_error","code":"spend_limit_reached"}}"#,
+            )),
+            Err(e) => {
+                return build_response_with_header(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "content-type",
+                    "application/json",
+                    Body::from(format!(
+                        r#"{{"error":{{"message":"Internal Auth Error: {}","type":"server_error"}}}}"#,
+                        e
+                    )),
+                )
+            }
+        };
+        if let Some((status, body)) = rejection {
+            return build_response_with_header(
+                status,
+                "content-type",
+                "application/json",
+                Body::from(body),
+            );
+        }
     }
 
     // Buffer body for token counting and retries
@@ -1557,6 +1875,171 @@ async fn proxy_handler(
                 .map(|(base, _)| base.to_string())
                 .unwrap_or(s)
         });
+    // Context-window preflight runs before the model policy so a long-context
+    // reroute is checked against the token's allow list like any other model.
+    let (body_bytes, path, model_name) =
+        match context_preflight(&state, body_bytes, path, model_name).await {
+            Ok(checked) => checked,
+            Err(response) => return response,
+        };
+    if let Some(ref model) = model_name {
+        tracing::Span::current().record("model", model.as_str());
+    }
+
+    // Token model policy: allow/deny lists and per-model spend caps, enforced
+    // before any channel is selected against the models that may serve. A
+    // virtual model is checked hop by hop; refused hops are skipped and the
+    // request is refused only when no hop is left.
+    let mut allowed_hops: Option<Vec<String>> = None;
+    if let Some(raw_policy) = model_policy.as_deref() {
+        let chain = match model_name.as_deref() {
+            Some(model) => state.model_router.virtual_chain(model).await,
+            None => None,
+        };
+        let verdict = match chain {
+            Some(chain) => {
+                let mut allowed = Vec::new();
+                let mut first_refusal = None;
+                for hop in chain {
+                    match model_policy_verdict(
+                        &state,
+                        raw_policy,
+                        &token_hashes,
+                        &user_id,
+                        Some(&hop.model),
+                    )
+                    .await
+                    {
+                        Ok(Ok(())) => allowed.push(hop.model),
+                        refusal => {
+                            first_refusal.get_or_insert(refusal);
+                        }
+                    }
+                }
+                if allowed.is_empty() {
+                    first_refusal.unwrap_or(Ok(Err(ModelPolicyRejection::NotAllowed)))
+                } else {
+                    allowed_hops = Some(allowed);
+                    Ok(Ok(()))
+                }
+            }
+            None => {
+                model_policy_verdict(
+                    &state,
+                    raw_policy,
+                    &token_hashes,
+                    &user_id,
+                    model_name.as_deref(),
+                )
+                .await
+            }
+        };
+        let rejection = match verdict {
+            Ok(Ok(())) => None,
+            Ok(Err(ModelPolicyRejection::NotAllowed)) => Some((
+                StatusCode::FORBIDDEN,
+                format!(
+                    r#"{{"error":{{"message":"Model '{}' is not allowed for this token","type":"permission_error","code":"model_not_allowed"}}}}"#,
+                    model_name.as_deref().unwrap_or("")
+                ),
+            )),
+            Ok(Err(ModelPolicyRejection::DailyCapReached { pattern })) => Some((
+                StatusCode::PAYMENT_REQUIRED,
+                format!(
+                    r#"{{"error":{{"message":"Daily spend cap for '{}' reached","type":"insufficient_quota_error","code":"model_spend_cap_exceeded"}}}}"#,
+                    pattern
+                ),
+            )),
+            Ok(Err(ModelPolicyRejection::MonthlyCapReached { pattern })) => Some((
+                StatusCode::PAYMENT_REQUIRED,
+                format!(
+                    r#"{{"error":{{"message":"Monthly spend cap for '{}' reached","type":"insufficient_quota_error","code":"model_spend_cap_exceeded"}}}}"#,
+                    pattern
+                ),
+            )),
+            Err(e) => Some((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!(
+                    r#"{{"error":{{"message":"Internal Auth Error: {}","type":"server_error"}}}}"#,
+                    e
+                ),
+            )),
+        };
+        if let Some((status, body)) = rejection {
+            return build_response_with_header(
+                status,
+                "content-type",
+                "application/json",
+                Body::from(body),
+            );
+        }
+    }
+
+    // Per-token / per-user RPM, TPM and concurrency limits. Tokens are
+    // estimated here and reconciled with the actual usage after settlement.
+    let user_limits = match user_limits {
+        Some(limits) => limits,
+        None => UserService::rate_limits(&state.db, &user_id)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!(user_id = %user_id, "Failed to load user rate limits: {e}");
+                RateLimits::default()
+            }),
+    };
+    let mut limit_scopes = Vec::new();
+    if let (false, Some(hash)) = (token_limits.is_unlimited(), token_hashes.first()) {
+        limit_scopes.push(LimitScope::new(format!("token:{hash}"), token_limits));
+    }
+    if !user_limits.is_unlimited() {
+        limit_scopes.push(LimitScope::new(format!("user:{user_id}"), user_limits));
+    }
+    let est_client_tokens = estimate_client_tokens(&body_bytes);
+    let (limit_status, concurrency_permit) = if limit_scopes.is_empty() {
+        // Clients without configured limits keep the default per-user bucket
+        if !state.limiter.check(&user_id, 1.0) {
+            return build_response_with_header(
+                StatusCode::TOO_MANY_REQUESTS,
+                "content-type",
+                "application/json",
+                Body::from(r#"{"error":{"message":"Too Many Requests","type":"rate_limit_error","code":"rate_limit_exceeded"}}"#),
+            );
+        }
+        (None, None)
+    } else {
+        match state
+            .client_limits
+            .acquire(&limit_scopes, est_client_tokens)
+            .await
+        {
+            Admission::Admitted(status) => (
+                Some(status),
+                Some(ConcurrencyPermit::new(
+                    state.client_limits.clone(),
+                    limit_scopes.clone(),
+                )),
+            ),
+            Admission::Rejected {
+                kind,
+                retry_after_secs,
+                status,
+            } => {
+                let mut response = build_response_with_header(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "content-type",
+                    "application/json",
+                    Body::from(format!(
+                        r#"{{"error":{{"message":"{}","type":"rate_limit_error","code":"rate_limit_exceeded"}}}}"#,
+                        kind.message()
+                    )),
+                );
+                insert_rate_limit_headers(response.headers_mut(), &status);
+                response
+                    .headers_mut()
+                    .insert("retry-after", HeaderValue::from(retry_after_secs));
+                return response;
+            }
+        }
+    };
 
     // Extract Veo-specific fields for request-side billing.
     // Veo's predictLongRunning response has no usageMetadata; duration is in the request body.
@@ -1667,13 +2150,26 @@ async fn proxy_handler(
             }
         };
 
+        let Ok(api_key) = decrypt_key(&channel.key) else {
+            tracing::error!(channel_id = channel.id, "Video task channel API key is unreadable");
+            return build_response_with_header(
+                StatusCode::BAD_GATEWAY,
+                "content-type",
+                "application/json",
+                Body::from(
+                    r#"{"error":{"message":"Upstream channel not available","code":"channel_unavailable"}}"#,
+                ),
+            );
+        };
         let base_url = channel.base_url.unwrap_or_default();
         let upstream_url = format!("{}/v1/videos/{task_id}", base_url.trim_end_matches('/'));
 
-        let upstream_resp = state
-            .client
-            .get(&upstream_url)
-            .header("Authorization", format!("Bearer {}", channel.key))
+        let upstream_req = telemetry::inject_trace_context(
+            &tracing::Span::current(),
+            state.client.get(&upstream_url),
+        );
+        let upstream_resp = upstream_req
+            .header("Authorization", format!("Bearer {api_key}"))
             .timeout(std::time::Duration::from_secs(VIDEO_TASK_TIMEOUT_SECS))
             .send()
             .await;
@@ -1710,6 +2206,7 @@ async fn proxy_handler(
     let token_counter = Arc::new(UnifiedTokenCounter::new());
 
     // Perform Proxy Logic
+    let mut local_lease = None;
     let result = proxy_logic(
         &state,
         method,
@@ -1723,10 +2220,16 @@ async fn proxy_handler(
         price_cap,
         token_counter.clone(),
         model_name.as_deref(),
+        allowed_hops.as_deref(),
         start_time,
+        &mut local_lease,
     )
     .await;
 
+    // Billing, video task mapping and the log use the model that actually
+    // served: for a virtual model that is the hop, not the requested name.
+    let served_model = result.served_model.clone().or_else(|| model_name.clone());
+
     // Save video task mapping asynchronously (fire-and-forget)
     if let Some(task_id) = result.video_task_id {
         if let Some(ch_id) = result
@@ -1739,7 +2242,7 @@ async fn proxy_handler(
                 task_id,
                 channel_id: ch_id,
                 user_id: Some(user_id.clone()),
-                model: model_name.clone(),
+                model: served_model.clone(),
                 duration: seedance_duration_secs,
                 resolution: seedance_resolution.clone(),
             };
@@ -1755,7 +2258,7 @@ async fn proxy_handler(
     let usage = token_counter.get_usage();
 
     // Veo request-side billing: inject video_tokens when response has no usageMetadata
-    let veo_tokens = if model_name
+    let veo_tokens = if served_model
         .as_deref()
         .is_some_and(|m| m.to_lowercase().contains("veo"))
     {
@@ -1775,9 +2278,19 @@ async fn proxy_handler(
     let usage =
         inject_video_tokens_if_empty(result.final_status, usage, seedance_tokens, "seedance");
 
-    // Calculate cost using CostCalculator (nanodollars)
+    // Billing settle span: covers cost calculation here and the spawned
+    // quota deduction below, so it closes once the charge is written.
+    let billing_span = tracing::info_span!(
+        "billing_settle",
+        cost_nano = tracing::field::Empty,
+        cost_status = tracing::field::Empty,
+    );
+
+    // Calculate cost using CostCalculator (nanodollars). Costs are in the
+    // currency of the matched price.
+    let mut cost_currency = None;
     let (cost, cost_breakdown, cost_status) = if !usage.is_empty() {
-        if let Some(model) = &model_name {
+        if let Some(model) = &served_model {
             match state
                 .cost_calculator
                 .calculate(
@@ -1788,10 +2301,12 @@ async fn proxy_handler(
                     is_priority_request,
                     result.pricing_region.as_deref(),
                 )
+                .instrument(billing_span.clone())
                 .await
             {
                 Ok(result) => {
                     let total = result.usd_amount_nano;
+                    cost_currency = Some(result.local_currency);
                     (total, result.breakdown, Some("ok".to_string()))
                 }
                 Err(burncloud_service_billing::BillingError::PriceNotFound(m)) => {
@@ -1815,6 +2330,11 @@ async fn proxy_handler(
         (0, Default::default(), None)
     };
 
+    billing_span.record("cost_nano", cost);
+    if let Some(ref status) = cost_status {
+        billing_span.record("cost_status", status.as_str());
+    }
+
     // Warn if cost is non-zero but model is unknown — reconciliation data will be degraded
     if model_name.is_none() && cost > 0 {
         tracing::warn!(cost, %request_id, "cost > 0 but model unknown — reconciliation data degraded");
@@ -1852,6 +2372,7 @@ async fn proxy_handler(
         request_id = %request_id,
         path = %path,
         model = ?model_name,
+        served_model = ?served_model,
         channel = ?upstream_id_for_header,
         status = result.final_status.as_u16(),
         latency_ms = start_time.elapsed().as_millis() as i64,
@@ -1862,6 +2383,42 @@ async fn proxy_handler(
         color = ?traffic_color,
         "request completed"
     );
+    {
+        let span = tracing::Span::current();
+        if let Some(ref ch_id) = upstream_id_for_header {
+            span.record("channel_id", ch_id.as_str());
+        }
+        if let Some(ref layer) = layer_decision {
+            span.record("routing_decision", layer.as_str());
+        }
+        span.record("prompt_tokens", usage.input_tokens);
+        span.record("completion_tokens", usage.output_tokens);
+        span.record("cost_nano", cost);
+    }
+
+    if metrics::is_enabled() {
+        let status_label = if result.final_status.is_success() {
+            "success"
+        } else {
+            "error"
+        };
+        let model_label = model_name.as_deref().unwrap_or("unknown");
+        metrics::record_request(status_label);
+        metrics::record_request_by_model(model_label);
+        metrics::record_request_duration(&path, model_label, start_time.elapsed().as_secs_f64());
+        metrics::record_prompt_tokens(usage.input_tokens.max(0) as u64);
+        metrics::record_completion_tokens(usage.output_tokens.max(0) as u64);
+        metrics::record_cost_nano(cost.max(0) as u64);
+        // Candidates tried: a failover decision carries the 0-based index of
+        // the candidate that served (or last failed) the request.
+        if result.upstream_id.is_some() {
+            let attempts = match result.routing_decision {
+                Some(model_router::RoutingDecision::Failover { attempt }) => attempt + 1,
+                _ => 1,
+            };
+            metrics::record_failover_attempts(attempts, result.final_status.is_success());
+        }
+    }
 
     let log = RouterLog {
         id: 0, // Auto-generated by database
@@ -1899,10 +2456,14 @@ async fn proxy_handler(
         traffic_color,
         cost_status,
         error_type: result.error_type,
+        token_hash: token_hashes.first().cloned(),
+        served_model: served_model.clone(),
+        currency: cost_currency,
         created_at: None, // Auto-generated by database
     };
 
-    if state.log_tx.send(log).await.is_err() {
+    let log_span = tracing::info_span!("log_write", table = "router_logs");
+    if state.log_tx.send((log, log_span)).await.is_err() {
         tracing::error!(
             cost,
             "billing log channel full or closed — request cost NOT recorded"
@@ -1942,20 +2503,61 @@ async fn proxy_handler(
         };
 
         // Use try_send to avoid blocking if channel is full
-        let _ = state.request_log_tx.try_send(request_log);
+        let log_span = tracing::info_span!("log_write", table = "router_request_logs");
+        let _ = state.request_log_tx.try_send((request_log, log_span));
     }
 
+    // Reconcile the TPM estimate taken at admission with the actual usage
+    // once the body is done: a stream is counted as its chunks pass through
+    let token_reconcile = limit_status.is_some().then(|| {
+        let counter = token_counter.clone();
+        TokenReconcile::new(
+            state.client_limits.clone(),
+            limit_scopes.clone(),
+            est_client_tokens,
+            move || {
+                let usage = counter.get_usage();
+                usage.input_tokens.saturating_add(usage.output_tokens)
+            },
+        )
+    });
+
     // Deduct quota (non-blocking)
     // Use cost > 0 (not total_tokens > 0) so video/audio/music requests are also deducted.
     // total_tokens only counts text tokens; multi-modal costs flow through cost (nanodollars).
     if cost > 0 {
+        if let (Some(hash), Some(model)) = (token_hashes.first(), served_model.as_deref()) {
+            state.model_spend.record(hash, model, cost);
+        }
         let db = state.db.clone();
         let token_for_quota = user_token.to_string();
         let user_id_for_quota = user_id.clone();
-        tokio::spawn(async move {
+        let org_rate = org_id.as_ref().map(|_| {
+            state
+                .exchange_rate_service
+                .get_rate(burncloud_common::Currency::USD, burncloud_common::Currency::CNY)
+                .map(burncloud_common::rate_to_scaled)
+        });
+        let notifications = state.notifications.clone();
+        let settle = async move {
             let _ =
                 RouterDatabase::deduct_quota(&db, &user_id_for_quota, &token_for_quota, cost).await;
-        });
+            notifications
+                .evaluate(&db, &user_id_for_quota, &token_for_quota)
+                .await;
+            if let (Some(org), Some(rate)) = (org_id, org_rate) {
+                match RouterDatabase::charge_org(&db, &org, cost, rate).await {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        tracing::warn!(org_id = %org, cost, "Organization wallet overdrawn")
+                    }
+                    Err(e) => {
+                        tracing::error!(org_id = %org, cost, "Failed to charge organization: {}", e)
+                    }
+                }
+            }
+        };
+        tokio::spawn(settle.instrument(billing_span));
     }
 
     // Inject route-tracing headers for client-side observability.
@@ -1974,11 +2576,34 @@ async fn proxy_handler(
                     .unwrap_or_else(|_| HeaderValue::from_static("unknown")),
             );
         }
+        if let Some(ref m) = served_model {
+            r.headers_mut().insert(
+                "X-Served-Model",
+                m.parse()
+                    .unwrap_or_else(|_| HeaderValue::from_static("unknown")),
+            );
+        }
         r
     } else {
         result.response
     };
 
+    let mut response = response;
+    if let Some(ref status) = limit_status {
+        insert_rate_limit_headers(response.headers_mut(), status);
+    }
+    // Streams keep their concurrency slot and local instance, and settle
+    // their TPM usage, when the body is finished or dropped
+    if concurrency_permit.is_some() || local_lease.is_some() || token_reconcile.is_some() {
+        let held = (concurrency_permit, local_lease, token_reconcile);
+        response = response.map(|body| {
+            Body::from_stream(body.into_data_stream().map(move |chunk| {
+                let _ = &held;
+                chunk
+            }))
+        });
+    }
+
     response
 }
 
@@ -2021,7 +2646,9 @@ async fn proxy_logic(
     price_cap: Option<i64>,
     token_counter: Arc<UnifiedTokenCounter>,
     model_name: Option<&str>,
+    allowed_hops: Option<&[String]>,
     request_start_time: Instant,
+    local_lease: &mut Option<local_instance::LocalInstanceLease>,
 ) -> ProxyResult {
     // Initialize request log data collection (Issue #334)
     // Only collect detailed data when storage policy is not 'none'
@@ -2125,142 +2752,180 @@ async fn proxy_logic(
                 sched_request.order_type.as_label()
             );
 
-            match state
-                .model_router
-                .route_with_scheduler(model_router::RouteInputs {
-                    group: user_group,
-                    model,
-                    state_tracker: &state.channel_state_tracker,
-                    price_cache: &state.price_cache,
-                    exchange_rate: &state.exchange_rate_service,
-                    scheduler_kind: scheduler_kind.as_ref(),
-                    request: &sched_request,
-                    affinity_cache: Some(state.affinity_cache.as_ref()),
-                })
-                .await
-            {
-                Ok((channels, routing_decision)) if !channels.is_empty() => {
-                    tracing::debug!(
-                        "ModelRouter: Got {} candidates for {}",
-                        channels.len(),
-                        model
-                    );
+            // A virtual model expands into its hops in order; each hop's
+            // ranked channels are appended, so the failover loop reaches the
+            // next model once every channel of the current one has failed.
+            let virtual_hops = state.model_router.virtual_chain(model).await;
+            let hops: Vec<Option<&VirtualModelHop>> = match virtual_hops.as_ref() {
+                Some(chain) => chain.iter().map(Some).collect(),
+                None => vec![None],
+            };
+            let mut routing_error: Option<model_router::NoAvailableChannelsError> = None;
+            for hop in hops {
+                let hop_model = hop.map_or(model, |h| h.model.as_str());
+                // Hops the token's model policy refused
+                if hop.is_some()
+                    && allowed_hops.is_some_and(|allowed| !allowed.iter().any(|m| m == hop_model))
+                {
+                    continue;
+                }
+                match state
+                    .model_router
+                    .route_with_scheduler(model_router::RouteInputs {
+                        group: user_group,
+                        model: hop_model,
+                        state_tracker: &state.channel_state_tracker,
+                        price_cache: &state.price_cache,
+                        exchange_rate: &state.exchange_rate_service,
+                        scheduler_kind: scheduler_kind.as_ref(),
+                        request: &sched_request,
+                        affinity_cache: Some(state.affinity_cache.as_ref()),
+                    })
+                    .await
+                {
+                    Ok((channels, routing_decision)) if !channels.is_empty() => {
+                        tracing::debug!(
+                            "ModelRouter: Got {} candidates for {}",
+                            channels.len(),
+                            hop_model
+                        );
 
-                    // Store routing_decision for L6 Observability priority chain.
-                    // Will be overridden by Failover{attempt} if failover loop
-                    // advances past attempt 0.
-                    sched_routing_decision = routing_decision;
-
-                    for channel in channels {
-                        let channel_type = ChannelType::from(channel.type_);
-                        // Path-based channel filtering (Issue #263)
-                        // OpenAI format requests should only go to OpenAI-type channels
-                        // Anthropic format requests should only go to Anthropic-type channels
-                        let is_openai_path = path.starts_with("/v1/chat/completions")
-                            || path.starts_with("/v1/completions")
-                            || path.starts_with("/v1/embeddings");
-                        let is_anthropic_path = path.starts_with("/v1/messages");
-
-                        // Skip channel if path format does not match channel type
-                        if is_openai_path
-                            && !matches!(channel_type, ChannelType::OpenAI | ChannelType::Zai)
-                        {
-                            tracing::debug!(
-                                "Skipping {:?} channel for OpenAI format path: {}",
-                                channel_type,
-                                path
-                            );
-                            continue;
-                        }
-                        if is_anthropic_path && !matches!(channel_type, ChannelType::Anthropic) {
-                            tracing::debug!(
-                                "Skipping {:?} channel for Anthropic format path: {}",
-                                channel_type,
-                                path
-                            );
-                            continue;
+                        // Store routing_decision for L6 Observability priority chain.
+                        // Will be overridden by Failover{attempt} if failover loop
+                        // advances past attempt 0. Later hops only append fallbacks.
+                        if sched_routing_decision.is_none() {
+                            sched_routing_decision = routing_decision;
                         }
 
-                        let (auth_type, protocol) = match channel_type {
-                            ChannelType::OpenAI => (AuthType::Bearer, PROTOCOL_OPENAI.to_string()),
-                            ChannelType::Anthropic => {
-                                (AuthType::Claude, PROTOCOL_CLAUDE.to_string())
+                        for channel in channels {
+                            let channel_type = ChannelType::from(channel.type_);
+                            // Path-based channel filtering (Issue #263)
+                            // OpenAI format requests should only go to OpenAI-type channels
+                            // Anthropic format requests should only go to Anthropic-type channels
+                            let is_openai_path = path.starts_with("/v1/chat/completions")
+                                || path.starts_with("/v1/completions")
+                                || path.starts_with("/v1/embeddings");
+                            let is_anthropic_path = path.starts_with("/v1/messages");
+
+                            // Skip channel if path format does not match channel type
+                            if is_openai_path
+                                && !matches!(channel_type, ChannelType::OpenAI | ChannelType::Zai)
+                            {
+                                tracing::debug!(
+                                    "Skipping {:?} channel for OpenAI format path: {}",
+                                    channel_type,
+                                    path
+                                );
+                                continue;
                             }
-                            ChannelType::Gemini | ChannelType::VertexAi => {
-                                (AuthType::GoogleAI, PROTOCOL_GEMINI.to_string())
+                            if is_anthropic_path && !matches!(channel_type, ChannelType::Anthropic) {
+                                tracing::debug!(
+                                    "Skipping {:?} channel for Anthropic format path: {}",
+                                    channel_type,
+                                    path
+                                );
+                                continue;
                             }
-                            ChannelType::Zai => (AuthType::Bearer, PROTOCOL_ZAI.to_string()),
-                            _ => (AuthType::Bearer, PROTOCOL_OPENAI.to_string()),
-                        };
-                        let ch_id = channel.id.to_string();
-                        candidates.push(Upstream {
-                            id: ch_id,
-                            name: channel.name,
-                            base_url: channel.base_url.unwrap_or_default(),
-                            api_key: channel.key,
-                            match_path: String::new(),
-                            auth_type,
-                            priority: channel.priority as i32,
-                            protocol,
-                            param_override: channel.param_override.clone(),
-                            header_override: channel.header_override.clone(),
-                            api_version: channel.api_version.clone(),
-                            pricing_region: channel.pricing_region.clone(),
-                        });
+
+                            let (auth_type, protocol) = match channel_type {
+                                ChannelType::OpenAI => (AuthType::Bearer, PROTOCOL_OPENAI.to_string()),
+                                ChannelType::Anthropic => {
+                                    (AuthType::Claude, PROTOCOL_CLAUDE.to_string())
+                                }
+                                ChannelType::Gemini | ChannelType::VertexAi => {
+                                    (AuthType::GoogleAI, PROTOCOL_GEMINI.to_string())
+                                }
+                                ChannelType::Zai => (AuthType::Bearer, PROTOCOL_ZAI.to_string()),
+                                _ => (AuthType::Bearer, PROTOCOL_OPENAI.to_string()),
+                            };
+                            let api_key = match decrypt_key(&channel.key) {
+                                Ok(key) => key,
+                                Err(e) => {
+                                    tracing::error!(
+                                        channel_id = channel.id,
+                                        "Skipping channel with unreadable API key: {}",
+                                        e
+                                    );
+                                    continue;
+                                }
+                            };
+                            let ch_id = channel.id.to_string();
+                            candidates.push(Upstream {
+                                id: ch_id,
+                                name: channel.name,
+                                base_url: channel.base_url.unwrap_or_default(),
+                                api_key,
+                                match_path: String::new(),
+                                auth_type,
+                                priority: channel.priority as i32,
+                                protocol,
+                                param_override: channel.param_override.clone(),
+                                header_override: channel.header_override.clone(),
+                                api_version: channel.api_version.clone(),
+                                pricing_region: channel.pricing_region.clone(),
+                                served_model: hop.map(|h| h.model.clone()),
+                                hop_param_override: hop.and_then(|h| h.param_override.clone()),
+                            });
+                        }
+                    }
+                    Ok(_) => {
+                        tracing::debug!(
+                            "ModelRouter: No candidates for {} (Group: {})",
+                            hop_model,
+                            user_group
+                        );
+                    }
+                    Err(e) => {
+                        // NoAvailableChannelsError - all channels are unavailable.
+                        // A virtual model falls through to its next hop.
+                        tracing::warn!("ModelRouter: No available channels for {}: {}", hop_model, e);
+                        routing_error.get_or_insert(e);
                     }
                 }
-                Ok(_) => {
-                    tracing::debug!(
-                        "ModelRouter: No candidates for {} (Group: {})",
-                        model,
-                        user_group
-                    );
-                }
-                Err(e) => {
-                    // NoAvailableChannelsError - all channels are unavailable
-                    tracing::warn!("ModelRouter: No available channels for {}: {}", model, e);
-                    // 503 response contract (audit decision D12): clients must
-                    // be able to distinguish a local Shaper / OrderType reject
-                    // from an upstream 5xx. We attach two headers:
-                    //   - X-Rejected-By: which router layer dropped the request
-                    //   - Retry-After: seconds the client should back off
-                    // Future Shaper rejections add `X-Rejected-By: shaper`
-                    // from inside the Shaper path; here we emit `order_type`
-                    // or `scheduler`.
-                    let rejected_by = if e.reason.contains("OrderType") {
-                        "order_type"
-                    } else {
-                        "scheduler"
-                    };
-                    let body = Body::from(format!(
-                        r#"{{"error":{{"message":"{}","type":"service_unavailable","code":"no_available_channels","rejected_by":"{}"}}}}"#,
-                        e, rejected_by
-                    ));
-                    let response = Response::builder()
-                        .status(StatusCode::SERVICE_UNAVAILABLE)
-                        .header("content-type", "application/json")
-                        .header("X-Rejected-By", rejected_by)
-                        .header("Retry-After", "60")
-                        .body(body)
-                        .unwrap_or_else(|_| {
-                            Response::builder()
-                                .status(StatusCode::SERVICE_UNAVAILABLE)
-                                .body(Body::empty())
-                                .unwrap_or_else(|_| Response::new(Body::empty()))
-                        });
-                    return ProxyResult {
-                        response,
-                        upstream_id: None,
-                        final_status: StatusCode::SERVICE_UNAVAILABLE,
-                        pricing_region: None,
-                        video_task_id: None,
-                        shaper_outcome: None,
-                        routing_decision: None,
-                        sched_request_color: shaper_color,
-                        error_type: Some("router_reject".to_string()),
-                        request_log_data: None,
-                    };
-                }
+            }
+            if let Some(e) = routing_error.filter(|_| candidates.is_empty()) {
+                // 503 response contract (audit decision D12): clients must
+                // be able to distinguish a local Shaper / OrderType reject
+                // from an upstream 5xx. We attach two headers:
+                //   - X-Rejected-By: which router layer dropped the request
+                //   - Retry-After: seconds the client should back off
+                // Future Shaper rejections add `X-Rejected-By: shaper`
+                // from inside the Shaper path; here we emit `order_type`
+                // or `scheduler`.
+                let rejected_by = if e.reason.contains("OrderType") {
+                    "order_type"
+                } else {
+                    "scheduler"
+                };
+                let body = Body::from(format!(
+                    r#"{{"error":{{"message":"{}","type":"service_unavailable","code":"no_available_channels","rejected_by":"{}"}}}}"#,
+                    e, rejected_by
+                ));
+                let response = Response::builder()
+                    .status(StatusCode::SERVICE_UNAVAILABLE)
+                    .header("content-type", "application/json")
+                    .header("X-Rejected-By", rejected_by)
+                    .header("Retry-After", "60")
+                    .body(body)
+                    .unwrap_or_else(|_| {
+                        Response::builder()
+                            .status(StatusCode::SERVICE_UNAVAILABLE)
+                            .body(Body::empty())
+                            .unwrap_or_else(|_| Response::new(Body::empty()))
+                    });
+                return ProxyResult {
+                    response,
+                    upstream_id: None,
+                    served_model: None,
+                    final_status: StatusCode::SERVICE_UNAVAILABLE,
+                    pricing_region: None,
+                    video_task_id: None,
+                    shaper_outcome: None,
+                    routing_decision: None,
+                    sched_request_color: shaper_color,
+                    error_type: Some("router_reject".to_string()),
+                    request_log_data: None,
+                };
             }
         } else {
             tracing::debug!("ProxyLogic: No 'model' field in JSON body");
@@ -2286,6 +2951,7 @@ async fn proxy_logic(
                 Body::from(error_body.to_string()),
             ),
             upstream_id: None,
+            served_model: None,
             final_status: StatusCode::NOT_FOUND,
             pricing_region: None,
             video_task_id: None,
@@ -2300,13 +2966,38 @@ async fn proxy_logic(
     // Preflight billing check: reject requests for models with no price configured.
     // In strict mode (default), returns 400 to prevent unbilled usage.
     // In non-strict mode, only warns and allows the request through.
+    // Virtual-model hops bill at their own price, so every served model is
+    // checked; strict mode drops unpriced hops and rejects once none remain.
     if let Some(model) = model_name {
-        if let Err(e) = state.cost_calculator.preflight(model, None).await {
-            if state.billing_strict {
-                tracing::warn!(model = %model, "Preflight billing check failed — rejecting request: {e}");
+        let mut served_models: Vec<&str> = Vec::new();
+        for upstream in &candidates {
+            let served = upstream.served_model.as_deref().unwrap_or(model);
+            if !served_models.contains(&served) {
+                served_models.push(served);
+            }
+        }
+        let mut unpriced: Vec<String> = Vec::new();
+        for served in served_models {
+            if let Err(e) = state.cost_calculator.preflight(served, None).await {
+                if state.billing_strict {
+                    tracing::warn!(model = %model, served_model = %served, "Preflight billing check failed — skipping model: {e}");
+                } else {
+                    tracing::warn!(model = %model, served_model = %served, "Preflight billing check failed — non-strict mode, allowing request: {e}");
+                }
+                unpriced.push(served.to_string());
+            }
+        }
+        if state.billing_strict && !unpriced.is_empty() {
+            candidates.retain(|u| {
+                let served = u.served_model.as_deref().unwrap_or(model);
+                !unpriced.iter().any(|m| m == served)
+            });
+            if candidates.is_empty() {
+                tracing::warn!(model = %model, "Preflight billing check failed — rejecting request");
                 state
                     .billing_preflight_rejected_count
                     .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                metrics::record_billing_preflight_rejected(model);
                 return ProxyResult {
                     response: build_response_with_header(
                         StatusCode::BAD_REQUEST,
@@ -2318,6 +3009,7 @@ async fn proxy_logic(
                         )),
                     ),
                     upstream_id: None,
+                    served_model: None,
                     final_status: StatusCode::BAD_REQUEST,
                     pricing_region: None,
                     video_task_id: None,
@@ -2325,10 +3017,8 @@ async fn proxy_logic(
                     routing_decision: None,
                     sched_request_color: shaper_color,
                     error_type: Some("router_reject".to_string()),
-                        request_log_data: None,
+                    request_log_data: None,
                 };
-            } else {
-                tracing::warn!(model = %model, "Preflight billing check failed — non-strict mode, allowing request: {e}");
             }
         }
     }
@@ -2353,6 +3043,7 @@ async fn proxy_logic(
             name: u.name.clone(),
             protocol: u.protocol.clone(),
             priority: u.priority,
+            model: u.served_model.clone(),
         }).collect();
         log_data.candidates_count = candidates.len() as i32;
 
@@ -2375,6 +3066,33 @@ async fn proxy_logic(
         }
         last_upstream_id = Some(upstream.id.clone());
 
+        // A virtual-model hop is served under its concrete model name: breaker,
+        // affinity and span state below all key on it, as does the upstream path.
+        let model_name = upstream.served_model.as_deref().or(model_name);
+        let hop_path = upstream
+            .served_model
+            .as_deref()
+            .and_then(|m| passthrough::with_gemini_path_model(path, m));
+        let path = hop_path.as_deref().unwrap_or(path);
+
+        // One span per candidate; it closes when the iteration ends, so a
+        // `continue` to the next candidate shows up as a separate attempt.
+        let attempt_span = tracing::info_span!(
+            "proxy_attempt",
+            otel.kind = "client",
+            otel.status_code = tracing::field::Empty,
+            attempt = attempt as i64 + 1,
+            channel_id = %upstream.id,
+            channel_name = %upstream.name,
+            model = model_name.unwrap_or_default(),
+            routing_decision = tracing::field::Empty,
+            http.status_code = tracing::field::Empty,
+            error = tracing::field::Empty,
+        );
+        if let Some(ref decision) = sched_routing_decision {
+            attempt_span.record("routing_decision", decision.to_label());
+        }
+
         // Update pricing_region for billing to match the actual upstream serving
         selected_pricing_region = upstream.pricing_region.clone();
 
@@ -2391,12 +3109,24 @@ async fn proxy_logic(
             state
                 .fail_open_count
                 .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            metrics::record_shaper_decision(
+                "unconfigured",
+                &shaper_ctx.color.as_char().to_string(),
+            );
             "shaper_unconfigured"
         } else {
             let outcome =
                 state
                     .rate_budget
                     .try_consume(channel_id_i32, shaper_ctx.color, shaper_ctx.est_tpm);
+            metrics::record_shaper_decision(
+                match outcome {
+                    ConsumeOutcome::OwnBucket => "own",
+                    ConsumeOutcome::Borrowed { .. } => "borrow",
+                    ConsumeOutcome::Rejected => "reject",
+                },
+                &shaper_ctx.color.as_char().to_string(),
+            );
             if outcome == ConsumeOutcome::Rejected {
                 shaper_ctx.rejected_count += 1;
                 tracing::debug!(
@@ -2442,6 +3172,29 @@ async fn proxy_logic(
             continue;
         }
 
+        // Local inference channels may have been unloaded while idle: wait for
+        // the cold start before sending, or fail over if it cannot be served.
+        if let Some(hook) = local_instance::local_instance_hook() {
+            match hook.ensure_ready(channel_id_i32).await {
+                Ok(lease) => *local_lease = lease,
+                Err(reason) => {
+                    tracing::warn!("Skipping local upstream {}: {}", upstream.name, reason);
+                    last_error = format!(
+                        "Local instance unavailable for {}: {}",
+                        upstream.name, reason
+                    );
+                    record_failover_attempt(
+                        &mut request_log_data,
+                        attempt as u32,
+                        upstream,
+                        Some(&last_error),
+                        0,
+                    );
+                    continue;
+                }
+            }
+        }
+
         // 2. Construct Target URL
         // Note: Some adaptors might override URL, but we set base here.
         let query = uri.query().map(|q| format!("?{}", q)).unwrap_or_default();
@@ -2475,6 +3228,9 @@ async fn proxy_logic(
                 continue;
             }
         };
+        if upstream.served_model.is_some() {
+            apply_virtual_hop(&mut body_json, upstream);
+        }
 
         // 4. Check if we should use passthrough mode (Gemini native format)
         let passthrough_decision = should_passthrough(path, &body_json, channel_type);
@@ -2557,9 +3313,12 @@ async fn proxy_logic(
                 apply_header_override(req_builder, upstream.header_override.as_deref());
 
             let req_builder = req_builder.json(&passthrough_body);
+            let req_builder = telemetry::inject_trace_context(&attempt_span, req_builder);
 
             // Execute passthrough request
-            match req_builder.send().await {
+            let send_result = req_builder.send().instrument(attempt_span.clone()).await;
+            record_attempt_outcome(&attempt_span, &send_result);
+            match send_result {
                 Ok(resp) => {
                     let status = resp.status();
                     let resp_headers = resp.headers().clone();
@@ -2781,6 +3540,7 @@ async fn proxy_logic(
                                         )
                                     }),
                                 upstream_id: last_upstream_id,
+                                served_model: model_name.map(str::to_string),
                                 final_status: status,
                                 pricing_region: selected_pricing_region.clone(),
                                 video_task_id: None,
@@ -2920,6 +3680,7 @@ async fn proxy_logic(
                                     Body::from(resp_bytes),
                                 ),
                                 upstream_id: last_upstream_id,
+                                served_model: model_name.map(str::to_string),
                                 final_status: status,
                                 pricing_region: selected_pricing_region.clone(),
                                 video_task_id: None,
@@ -2954,6 +3715,7 @@ async fn proxy_logic(
                                         )),
                                     ),
                                     upstream_id: last_upstream_id,
+                                    served_model: model_name.map(str::to_string),
                                     final_status: status,
                                     pricing_region: selected_pricing_region.clone(),
                                     video_task_id: None,
@@ -2982,14 +3744,17 @@ async fn proxy_logic(
                             StatusCode::UNAUTHORIZED | StatusCode::PAYMENT_REQUIRED => None,
                             _ => model_name,
                         };
-                        record_upstream_failure(
-                            state,
-                            upstream,
-                            error_model,
-                            failure_type.clone(),
-                            error_message,
-                            &session_id,
-                        );
+                        // Oversized requests are the client's fault, not the channel's
+                        if !context_window::is_context_overflow(status, &error_info) {
+                            record_upstream_failure(
+                                state,
+                                upstream,
+                                error_model,
+                                failure_type.clone(),
+                                error_message,
+                                &session_id,
+                            );
+                        }
                         // 429 (rate limit), 401 (auth failed), 402 (payment required):
                         // try next ranked candidate — other channels may have valid credentials.
                         if status == StatusCode::TOO_MANY_REQUESTS
@@ -3025,6 +3790,7 @@ async fn proxy_logic(
                                 Body::from(body_bytes),
                             ),
                             upstream_id: last_upstream_id,
+                            served_model: model_name.map(str::to_string),
                             final_status: status,
                             pricing_region: selected_pricing_region.clone(),
                             video_task_id: None,
@@ -3084,10 +3850,11 @@ async fn proxy_logic(
             .map(|s| s.to_string());
 
         let request_body_json: Option<serde_json::Value> =
-            if let Ok(req) = serde_json::from_slice::<OpenAIChatRequest>(&body_bytes) {
-                let mut converted = adaptor
-                    .convert_request(&req)
-                    .or_else(|| Some(serde_json::json!(req))); // Use converted or original
+            if let Ok(req) = serde_json::from_value::<OpenAIChatRequest>(body_json.clone()) {
+                let mut converted =
+                    tracing::info_span!(parent: &attempt_span, "adaptor_convert_request")
+                        .in_scope(|| adaptor.convert_request(&req))
+                        .or_else(|| Some(serde_json::json!(req))); // Use converted or original
 
                 // Preserve stream flag and model in converted body for adaptor's build_request
                 #[allow(clippy::collapsible_match)]
@@ -3154,9 +3921,12 @@ async fn proxy_logic(
                 &request_body_json,
             )
             .await;
+        let req_builder = telemetry::inject_trace_context(&attempt_span, req_builder);
 
         // 5. Execute
-        match req_builder.send().await {
+        let send_result = req_builder.send().instrument(attempt_span.clone()).await;
+        record_attempt_outcome(&attempt_span, &send_result);
+        match send_result {
             Ok(resp) => {
                 let status = resp.status();
                 let resp_headers = resp.headers().clone();
@@ -3271,6 +4041,7 @@ async fn proxy_logic(
                                     Body::from(resp_bytes),
                                 ),
                                 upstream_id: last_upstream_id,
+                                served_model: model_name.map(str::to_string),
                                 final_status: status,
                                 pricing_region: selected_pricing_region.clone(),
                                 video_task_id: task_id,
@@ -3527,6 +4298,7 @@ async fn proxy_logic(
                                     )
                                 }),
                             upstream_id: last_upstream_id,
+                            served_model: model_name.map(str::to_string),
                             final_status: status,
                             pricing_region: selected_pricing_region.clone(),
                             video_task_id: None,
@@ -3826,6 +4598,7 @@ async fn proxy_logic(
                                     )
                                 }),
                             upstream_id: last_upstream_id,
+                            served_model: model_name.map(str::to_string),
                             final_status: status,
                             pricing_region: selected_pricing_region.clone(),
                             video_task_id: None,
@@ -3864,9 +4637,13 @@ async fn proxy_logic(
                         token_counter.set_from_usage(&resp_usage);
                     }
 
-                    let response_body = if let Some(converted) =
+                    let converted_response = {
+                        let _span =
+                            tracing::info_span!(parent: &attempt_span, "adaptor_convert_response")
+                                .entered();
                         adaptor.convert_response(resp_json.clone(), &upstream.name)
-                    {
+                    };
+                    let response_body = if let Some(converted) = converted_response {
                         // Also extract usage from converted response if not yet captured
                         if token_counter.get_usage().is_empty() {
                             let conv_usage = parse_response_or_default(
@@ -3923,6 +4700,7 @@ async fn proxy_logic(
                             Body::from(response_body),
                         ),
                         upstream_id: last_upstream_id,
+                        served_model: model_name.map(str::to_string),
                         final_status: status,
                         pricing_region: selected_pricing_region.clone(),
                         video_task_id: None,
@@ -3956,6 +4734,7 @@ async fn proxy_logic(
                                         )),
                                     ),
                                 upstream_id: last_upstream_id,
+                                served_model: model_name.map(str::to_string),
                                 final_status: status,
                                 pricing_region: selected_pricing_region.clone(),
                                 video_task_id: None,
@@ -3985,14 +4764,17 @@ async fn proxy_logic(
                         _ => model_name,
                     };
 
-                    record_upstream_failure(
-                        state,
-                        upstream,
-                        error_model,
-                        failure_type.clone(),
-                        error_message,
-                        &session_id,
-                    );
+                    // Oversized requests are the client's fault, not the channel's
+                    if !context_window::is_context_overflow(status, &error_info) {
+                        record_upstream_failure(
+                            state,
+                            upstream,
+                            error_model,
+                            failure_type.clone(),
+                            error_message,
+                            &session_id,
+                        );
+                    }
 
                     // 429: try next ranked candidate (scheduler provides alternatives)
                     if status == StatusCode::TOO_MANY_REQUESTS {
@@ -4064,6 +4846,7 @@ async fn proxy_logic(
                             Body::from(body_bytes),
                         ),
                         upstream_id: last_upstream_id,
+                        served_model: model_name.map(str::to_string),
                         final_status: status,
                         pricing_region: selected_pricing_region.clone(),
                         video_task_id: None,
@@ -4126,6 +4909,7 @@ async fn proxy_logic(
         return ProxyResult {
             response,
             upstream_id: None,
+            served_model: None,
             final_status: StatusCode::SERVICE_UNAVAILABLE,
             pricing_region: None,
             video_task_id: None,
@@ -4151,6 +4935,7 @@ async fn proxy_logic(
             )),
         ),
         upstream_id: None,
+        served_model: None,
         final_status: StatusCode::BAD_GATEWAY,
         pricing_region: None,
         video_task_id: None,
@@ -4214,6 +4999,40 @@ mod tests {
         assert_eq!(usage.video_tokens, 0);
     }
 
+    #[tokio::test]
+    async fn test_console_jwt_needs_a_live_session() {
+        use super::console_jwt_user;
+        use burncloud_service_user::{SessionClient, UserService};
+
+        let dir = tempfile::tempdir().unwrap();
+        let db = burncloud_database::create_database_with_url(&format!(
+            "sqlite://{}?mode=rwc",
+            dir.path().join("sessions.db").display()
+        ))
+        .await
+        .unwrap();
+        let service = UserService::new();
+        let user_id = service
+            .register_user(&db, "jwt-user", "jwt-password", None)
+            .await
+            .unwrap();
+        let session = service
+            .start_session(&db, &user_id, "jwt-user", &SessionClient::default())
+            .await
+            .unwrap();
+
+        assert_eq!(console_jwt_user(&db, &session.access_token).await, Some(user_id.clone()));
+        assert_eq!(console_jwt_user(&db, "not-a-jwt").await, None);
+        service.logout(&db, &user_id, &session.session_id).await.unwrap();
+        assert_eq!(console_jwt_user(&db, &session.access_token).await, None);
+
+        // Sessionless tokens stop working after "log out all sessions"
+        let legacy = service.generate_token(&user_id, "jwt-user").unwrap().token;
+        assert_eq!(console_jwt_user(&db, &legacy).await, Some(user_id.clone()));
+        service.logout_all(&db, &user_id).await.unwrap();
+        assert_eq!(console_jwt_user(&db, &legacy).await, None);
+    }
+
     #[test]
     fn test_veo_billing_no_tokens_when_zero() {
         // Non-Veo model: caller computes 0 tokens, inject should skip
@@ -4314,11 +5133,53 @@ mod tests {
             "5s 720p fast @ $0.07/s should cost $0.35 = 350_000_000 nanodollars"
         );
     }
+
+    fn hop_upstream(model: &str, overrides: serde_json::Value) -> super::Upstream {
+        super::Upstream {
+            id: "1".to_string(),
+            name: "hop".to_string(),
+            base_url: String::new(),
+            api_key: String::new(),
+            match_path: String::new(),
+            auth_type: super::AuthType::Bearer,
+            priority: 0,
+            protocol: "openai".to_string(),
+            param_override: None,
+            header_override: None,
+            api_version: None,
+            pricing_region: None,
+            served_model: Some(model.to_string()),
+            hop_param_override: overrides.as_object().cloned(),
+        }
+    }
+
+    #[test]
+    fn test_virtual_hop_rewrites_model_and_overrides() {
+        let mut body = serde_json::json!({
+            "model": "company-smart",
+            "max_tokens": 4096,
+            "messages": [],
+        });
+        let upstream = hop_upstream("gemini-pro", serde_json::json!({ "max_tokens": 1024 }));
+        super::apply_virtual_hop(&mut body, &upstream);
+        assert_eq!(body["model"], "gemini-pro");
+        assert_eq!(body["max_tokens"], 1024);
+        assert_eq!(body["messages"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_virtual_hop_keeps_gemini_native_body_without_model() {
+        let mut body = serde_json::json!({ "contents": [] });
+        let upstream = hop_upstream("gemini-pro", serde_json::Value::Null);
+        super::apply_virtual_hop(&mut body, &upstream);
+        assert_eq!(body, serde_json::json!({ "contents": [] }));
+    }
 }
 
 /// Handler for /internal/metrics endpoint - Prometheus metrics
 #[allow(clippy::expect_used)]
 async fn metrics_handler() -> Response {
+    crate::metrics::update_system_metrics();
     let metrics_output = crate::metrics::export();
     Response::builder()
         .status(StatusCode::OK)
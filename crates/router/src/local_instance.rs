@@ -0,0 +1,40 @@
+//! Readiness hook for locally served models.
+//!
+//! Channels registered by the inference service point at a `llama-server`
+//! process that may have been unloaded after sitting idle. Before a request is
+//! forwarded, the proxy asks the installed hook to make the channel ready; the
+//! hook cold-starts the instance if needed and returns once it passes its
+//! health check. Channels the hook does not manage are passed through.
+//!
+//! A ready channel comes with a lease that the proxy holds until the response
+//! body is finished, so the instance is not unloaded under an in-flight request.
+//!
+//! The router does not depend on the inference service directly — the server
+//! installs an implementation at startup via [`set_local_instance_hook`].
+
+use once_cell::sync::Lazy;
+use std::sync::{Arc, RwLock};
+
+/// Keeps a local instance loaded until dropped.
+pub type LocalInstanceLease = Box<dyn Send + Sync>;
+
+#[async_trait::async_trait]
+pub trait LocalInstanceHook: Send + Sync {
+    /// Make sure the channel can serve a request, waiting for a cold start if
+    /// necessary. Returns a lease for channels backed by a local instance,
+    /// `None` for channels the hook does not manage, and `Err` with a reason
+    /// when the channel should be skipped for this request.
+    async fn ensure_ready(&self, channel_id: i32) -> Result<Option<LocalInstanceLease>, String>;
+}
+
+static HOOK: Lazy<RwLock<Option<Arc<dyn LocalInstanceHook>>>> = Lazy::new(|| RwLock::new(None));
+
+/// Install the hook, replacing any previous one.
+pub fn set_local_instance_hook(hook: Arc<dyn LocalInstanceHook>) {
+    *HOOK.write().unwrap_or_else(|e| e.into_inner()) = Some(hook);
+}
+
+/// Currently installed hook, if any.
+pub(crate) fn local_instance_hook() -> Option<Arc<dyn LocalInstanceHook>> {
+    HOOK.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
@@ -1,16 +1,81 @@
 //! Prometheus metrics for observability.
 //!
 //! This module provides Prometheus-compatible metrics for monitoring
-//! request rates, latencies, token usage, channel health, and system resources.
+//! request rates, latencies, token usage, channel health, the L2 shaper,
+//! L3 affinity, billing preflight, AIMD limits, circuit breakers, failover,
+//! cross-instance state sync and system resources.
+//!
+//! Every series is described once in a [`MetricDef`]; registration and the
+//! bundled Grafana dashboard ([`grafana_dashboard`]) are both generated from
+//! [`DEFINITIONS`].
+//!
+//! # Cardinality
+//!
+//! Labels fed from request data (model, endpoint) are capped per label by
+//! `METRICS_MAX_LABEL_VALUES` (default 200, `0` = unlimited): once a label
+//! has seen that many distinct values, new values are reported as
+//! [`OVERFLOW_LABEL`].
 
 use once_cell::sync::Lazy;
-use prometheus::{HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Registry};
-use std::sync::atomic::{AtomicBool, Ordering};
+use prometheus::{
+    HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
 use std::time::Instant;
 
 /// Global flag indicating whether metrics collection is enabled.
 static METRICS_ENABLED: AtomicBool = AtomicBool::new(true);
 
+/// Default for `METRICS_MAX_LABEL_VALUES`.
+pub const DEFAULT_MAX_LABEL_VALUES: usize = 200;
+
+/// Label value reported once a label reaches its distinct-value cap.
+pub const OVERFLOW_LABEL: &str = "other";
+
+/// Distinct values seen per `(metric, label)`.
+type SeenLabelValues = HashMap<(&'static str, &'static str), HashSet<String>>;
+
+/// Distinct-value cap for bounded labels and the values seen so far.
+struct LabelBounds {
+    /// Distinct values allowed per label; `0` disables the cap
+    max: AtomicUsize,
+    seen: Mutex<SeenLabelValues>,
+}
+
+impl LabelBounds {
+    fn new(max: usize) -> Self {
+        Self {
+            max: AtomicUsize::new(max),
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn bound(&self, metric: &'static str, label: &'static str, value: &str) -> String {
+        let max = self.max.load(Ordering::Relaxed);
+        if max == 0 {
+            return value.to_string();
+        }
+        let mut seen = self
+            .seen
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let values = seen.entry((metric, label)).or_default();
+        if values.contains(value) {
+            return value.to_string();
+        }
+        if values.len() >= max {
+            return OVERFLOW_LABEL.to_string();
+        }
+        values.insert(value.to_string());
+        value.to_string()
+    }
+}
+
+static LABEL_BOUNDS: Lazy<LabelBounds> = Lazy::new(|| LabelBounds::new(DEFAULT_MAX_LABEL_VALUES));
+
 /// Check if metrics collection is enabled.
 pub fn is_enabled() -> bool {
     METRICS_ENABLED.load(Ordering::Relaxed)
@@ -21,246 +86,525 @@ pub fn set_enabled(enabled: bool) {
     METRICS_ENABLED.store(enabled, Ordering::Relaxed);
 }
 
-/// Initialize metrics from environment variable.
+/// Set the distinct-value cap for bounded labels (`0` = unlimited).
+pub fn set_max_label_values(max: usize) {
+    LABEL_BOUNDS.max.store(max, Ordering::Relaxed);
+}
+
+/// Initialize metrics from environment variables.
+///
+/// - `METRICS_ENABLED`: `false` or `0` disables collection (default enabled)
+/// - `METRICS_MAX_LABEL_VALUES`: distinct values per bounded label (default 200, `0` = unlimited)
 pub fn init_from_env() {
     let enabled = std::env::var("METRICS_ENABLED")
         .map(|v| v != "false" && v != "0")
         .unwrap_or(true);
     set_enabled(enabled);
+    if let Some(max) = std::env::var("METRICS_MAX_LABEL_VALUES")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+    {
+        set_max_label_values(max);
+    }
     if enabled {
+        register_all();
         log::info!("Prometheus metrics enabled");
     } else {
         log::info!("Prometheus metrics disabled via METRICS_ENABLED=false");
     }
 }
 
+/// Map `value` to itself while `label` of `metric` is under its cap, or to
+/// [`OVERFLOW_LABEL`] once the cap of distinct values is reached.
+pub fn bounded_label(metric: &'static str, label: &'static str, value: &str) -> String {
+    LABEL_BOUNDS.bound(metric, label, value)
+}
+
 /// Custom Prometheus registry for burncloud metrics.
-pub static REGISTRY: Lazy<Registry> = Lazy::new(|| Registry::new());
+pub static REGISTRY: Lazy<Registry> = Lazy::new(Registry::new);
+
+// ============================================================================
+// Metric Definitions
+// ============================================================================
+
+/// Prometheus metric type of a [`MetricDef`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    Counter,
+    Gauge,
+    Histogram,
+}
+
+/// Name, help text, labels and dashboard placement of one metric.
+#[derive(Debug)]
+pub struct MetricDef {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub kind: MetricKind,
+    pub labels: &'static [&'static str],
+    /// Histogram bucket bounds; empty for counters and gauges
+    pub buckets: &'static [f64],
+    /// Dashboard row the panel goes in
+    pub section: &'static str,
+    /// Grafana unit of the panel
+    pub unit: &'static str,
+}
+
+macro_rules! metric_def {
+    ($name:expr, $help:expr, $kind:ident, [$($label:expr),*], $section:expr, $unit:expr) => {
+        MetricDef {
+            name: $name,
+            help: $help,
+            kind: MetricKind::$kind,
+            labels: &[$($label),*],
+            buckets: &[],
+            section: $section,
+            unit: $unit,
+        }
+    };
+    ($name:expr, $help:expr, Histogram($buckets:expr), [$($label:expr),*], $section:expr, $unit:expr) => {
+        MetricDef {
+            name: $name,
+            help: $help,
+            kind: MetricKind::Histogram,
+            labels: &[$($label),*],
+            buckets: $buckets,
+            section: $section,
+            unit: $unit,
+        }
+    };
+}
+
+pub const REQUESTS_TOTAL_DEF: MetricDef = metric_def!(
+    "burncloud_requests_total",
+    "Total number of requests processed",
+    Counter,
+    ["status"],
+    "Requests",
+    "reqps"
+);
+pub const REQUESTS_DURATION_SECONDS_DEF: MetricDef = metric_def!(
+    "burncloud_requests_duration_seconds",
+    "Request latency in seconds",
+    Histogram(&[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0]),
+    ["endpoint", "model"],
+    "Requests",
+    "s"
+);
+pub const REQUESTS_IN_FLIGHT_DEF: MetricDef = metric_def!(
+    "burncloud_requests_in_flight",
+    "Number of requests currently being processed",
+    Gauge,
+    ["endpoint"],
+    "Requests",
+    "short"
+);
+pub const REQUESTS_BY_MODEL_DEF: MetricDef = metric_def!(
+    "burncloud_requests_by_model",
+    "Number of requests per model",
+    Counter,
+    ["model"],
+    "Requests",
+    "reqps"
+);
+pub const REQUESTS_BY_CHANNEL_DEF: MetricDef = metric_def!(
+    "burncloud_requests_by_channel",
+    "Number of requests per channel",
+    Counter,
+    ["channel_id", "channel_name"],
+    "Requests",
+    "reqps"
+);
+pub const TOKENS_PROMPT_TOTAL_DEF: MetricDef = metric_def!(
+    "burncloud_tokens_prompt_total",
+    "Total number of prompt tokens processed",
+    Counter,
+    [],
+    "Tokens and cost",
+    "short"
+);
+pub const TOKENS_COMPLETION_TOTAL_DEF: MetricDef = metric_def!(
+    "burncloud_tokens_completion_total",
+    "Total number of completion tokens generated",
+    Counter,
+    [],
+    "Tokens and cost",
+    "short"
+);
+pub const COST_TOTAL_NANO_DEF: MetricDef = metric_def!(
+    "burncloud_cost_total_nano",
+    "Total cost in nanodollars",
+    Counter,
+    [],
+    "Tokens and cost",
+    "short"
+);
+pub const CHANNEL_STATUS_DEF: MetricDef = metric_def!(
+    "burncloud_channel_status",
+    "Channel status (1=healthy, 0=unhealthy)",
+    Gauge,
+    ["channel_id", "channel_name"],
+    "Channels",
+    "short"
+);
+pub const CHANNEL_ERRORS_TOTAL_DEF: MetricDef = metric_def!(
+    "burncloud_channel_errors_total",
+    "Total number of channel errors",
+    Counter,
+    ["channel_id", "channel_name", "error_type"],
+    "Channels",
+    "short"
+);
+pub const CHANNEL_LATENCY_SECONDS_DEF: MetricDef = metric_def!(
+    "burncloud_channel_latency_seconds",
+    "Channel request latency in seconds",
+    Histogram(&[0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0]),
+    ["channel_id", "channel_name"],
+    "Channels",
+    "s"
+);
+pub const SHAPER_DECISIONS_TOTAL_DEF: MetricDef = metric_def!(
+    "burncloud_shaper_decisions_total",
+    "L2 shaper decisions per candidate (own, borrow, reject, unconfigured)",
+    Counter,
+    ["outcome", "color"],
+    "Shaper",
+    "short"
+);
+pub const SHAPER_FAIL_OPEN_TOTAL_DEF: MetricDef = metric_def!(
+    "burncloud_shaper_fail_open_total",
+    "Candidates admitted without a shaper bucket (channel has no rpm cap)",
+    Counter,
+    [],
+    "Shaper",
+    "short"
+);
+pub const AFFINITY_LOOKUPS_TOTAL_DEF: MetricDef = metric_def!(
+    "burncloud_affinity_lookups_total",
+    "L3 affinity cache lookups (hit, miss, stale, expired)",
+    Counter,
+    ["result"],
+    "Affinity",
+    "short"
+);
+pub const AFFINITY_EVICTIONS_TOTAL_DEF: MetricDef = metric_def!(
+    "burncloud_affinity_evictions_total",
+    "L3 affinity cache evictions (failover, expired, remote)",
+    Counter,
+    ["reason"],
+    "Affinity",
+    "short"
+);
+pub const AFFINITY_ENTRIES_DEF: MetricDef = metric_def!(
+    "burncloud_affinity_entries",
+    "Entries in the L3 affinity cache",
+    Gauge,
+    [],
+    "Affinity",
+    "short"
+);
+pub const BILLING_PREFLIGHT_REJECTED_TOTAL_DEF: MetricDef = metric_def!(
+    "burncloud_billing_preflight_rejected_total",
+    "Requests rejected by the billing preflight (no price configured)",
+    Counter,
+    ["model"],
+    "Billing",
+    "short"
+);
+pub const AIMD_CURRENT_LIMIT_DEF: MetricDef = metric_def!(
+    "burncloud_aimd_current_limit",
+    "Current AIMD request limit per channel and model",
+    Gauge,
+    ["channel_id", "model"],
+    "Adaptive limits",
+    "short"
+);
+pub const AIMD_LEARNED_LIMIT_DEF: MetricDef = metric_def!(
+    "burncloud_aimd_learned_limit",
+    "Upstream request limit learned by AIMD per channel and model",
+    Gauge,
+    ["channel_id", "model"],
+    "Adaptive limits",
+    "short"
+);
+pub const CIRCUIT_BREAKER_TRIPS_TOTAL_DEF: MetricDef = metric_def!(
+    "burncloud_circuit_breaker_trips_total",
+    "Circuit breaker trips per trip level and reason",
+    Counter,
+    ["level", "reason"],
+    "Circuit breaker",
+    "short"
+);
+pub const FAILOVER_ATTEMPTS_DEF: MetricDef = metric_def!(
+    "burncloud_failover_attempts",
+    "Candidates tried per request (1 = first pick served it)",
+    Histogram(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 8.0, 10.0]),
+    ["outcome"],
+    "Failover",
+    "short"
+);
+pub const STATE_SYNC_EVENTS_TOTAL_DEF: MetricDef = metric_def!(
+    "burncloud_state_sync_events_total",
+    "Cross-instance state sync events (sent, applied, stale, dropped)",
+    Counter,
+    ["direction"],
+    "State sync",
+    "short"
+);
+pub const UPTIME_SECONDS_DEF: MetricDef = metric_def!(
+    "burncloud_uptime_seconds",
+    "Service uptime in seconds",
+    Gauge,
+    [],
+    "System",
+    "s"
+);
+pub const CONNECTIONS_ACTIVE_DEF: MetricDef = metric_def!(
+    "burncloud_connections_active",
+    "Number of active connections",
+    Gauge,
+    [],
+    "System",
+    "short"
+);
+pub const MEMORY_BYTES_DEF: MetricDef = metric_def!(
+    "burncloud_memory_bytes",
+    "Memory usage in bytes",
+    Gauge,
+    [],
+    "System",
+    "bytes"
+);
+
+/// Every metric, in dashboard order.
+pub const DEFINITIONS: &[&MetricDef] = &[
+    &REQUESTS_TOTAL_DEF,
+    &REQUESTS_DURATION_SECONDS_DEF,
+    &REQUESTS_IN_FLIGHT_DEF,
+    &REQUESTS_BY_MODEL_DEF,
+    &REQUESTS_BY_CHANNEL_DEF,
+    &TOKENS_PROMPT_TOTAL_DEF,
+    &TOKENS_COMPLETION_TOTAL_DEF,
+    &COST_TOTAL_NANO_DEF,
+    &CHANNEL_STATUS_DEF,
+    &CHANNEL_ERRORS_TOTAL_DEF,
+    &CHANNEL_LATENCY_SECONDS_DEF,
+    &SHAPER_DECISIONS_TOTAL_DEF,
+    &SHAPER_FAIL_OPEN_TOTAL_DEF,
+    &AFFINITY_LOOKUPS_TOTAL_DEF,
+    &AFFINITY_EVICTIONS_TOTAL_DEF,
+    &AFFINITY_ENTRIES_DEF,
+    &BILLING_PREFLIGHT_REJECTED_TOTAL_DEF,
+    &AIMD_CURRENT_LIMIT_DEF,
+    &AIMD_LEARNED_LIMIT_DEF,
+    &CIRCUIT_BREAKER_TRIPS_TOTAL_DEF,
+    &FAILOVER_ATTEMPTS_DEF,
+    &STATE_SYNC_EVENTS_TOTAL_DEF,
+    &UPTIME_SECONDS_DEF,
+    &CONNECTIONS_ACTIVE_DEF,
+    &MEMORY_BYTES_DEF,
+];
+
+fn register<C>(def: &MetricDef, collector: C) -> C
+where
+    C: prometheus::core::Collector + Clone + 'static,
+{
+    if let Err(e) = REGISTRY.register(Box::new(collector.clone())) {
+        log::error!("Failed to register metric {}: {}", def.name, e);
+    }
+    collector
+}
+
+// Definitions are static and checked by the tests below, so construction
+// can only fail on a programming error.
+#[allow(clippy::expect_used)]
+fn int_counter(def: &MetricDef) -> IntCounter {
+    register(
+        def,
+        IntCounter::new(def.name, def.help).expect("invalid counter definition"),
+    )
+}
+
+#[allow(clippy::expect_used)]
+fn int_counter_vec(def: &MetricDef) -> IntCounterVec {
+    register(
+        def,
+        IntCounterVec::new(Opts::new(def.name, def.help), def.labels)
+            .expect("invalid counter definition"),
+    )
+}
+
+#[allow(clippy::expect_used)]
+fn int_gauge(def: &MetricDef) -> IntGauge {
+    register(
+        def,
+        IntGauge::new(def.name, def.help).expect("invalid gauge definition"),
+    )
+}
+
+#[allow(clippy::expect_used)]
+fn int_gauge_vec(def: &MetricDef) -> IntGaugeVec {
+    register(
+        def,
+        IntGaugeVec::new(Opts::new(def.name, def.help), def.labels)
+            .expect("invalid gauge definition"),
+    )
+}
+
+#[allow(clippy::expect_used)]
+fn histogram_vec(def: &MetricDef) -> HistogramVec {
+    let opts = HistogramOpts::new(def.name, def.help).buckets(def.buckets.to_vec());
+    register(
+        def,
+        HistogramVec::new(opts, def.labels).expect("invalid histogram definition"),
+    )
+}
+
+/// Register every metric so all series are exported from startup.
+pub fn register_all() {
+    Lazy::force(&REQUESTS_TOTAL);
+    Lazy::force(&REQUESTS_DURATION_SECONDS);
+    Lazy::force(&REQUESTS_IN_FLIGHT);
+    Lazy::force(&REQUESTS_BY_MODEL);
+    Lazy::force(&REQUESTS_BY_CHANNEL);
+    Lazy::force(&TOKENS_PROMPT_TOTAL);
+    Lazy::force(&TOKENS_COMPLETION_TOTAL);
+    Lazy::force(&COST_TOTAL_NANO);
+    Lazy::force(&CHANNEL_STATUS);
+    Lazy::force(&CHANNEL_ERRORS_TOTAL);
+    Lazy::force(&CHANNEL_LATENCY_SECONDS);
+    Lazy::force(&SHAPER_DECISIONS_TOTAL);
+    Lazy::force(&SHAPER_FAIL_OPEN_TOTAL);
+    Lazy::force(&AFFINITY_LOOKUPS_TOTAL);
+    Lazy::force(&AFFINITY_EVICTIONS_TOTAL);
+    Lazy::force(&AFFINITY_ENTRIES);
+    Lazy::force(&BILLING_PREFLIGHT_REJECTED_TOTAL);
+    Lazy::force(&AIMD_CURRENT_LIMIT);
+    Lazy::force(&AIMD_LEARNED_LIMIT);
+    Lazy::force(&CIRCUIT_BREAKER_TRIPS_TOTAL);
+    Lazy::force(&FAILOVER_ATTEMPTS);
+    Lazy::force(&STATE_SYNC_EVENTS_TOTAL);
+    Lazy::force(&UPTIME_SECONDS);
+    Lazy::force(&CONNECTIONS_ACTIVE);
+    Lazy::force(&MEMORY_BYTES);
+}
 
 // ============================================================================
 // Request Metrics
 // ============================================================================
 
 /// Total number of requests processed.
-pub static REQUESTS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
-    let counter = IntCounterVec::new(
-        prometheus::Opts::new(
-            "burncloud_requests_total",
-            "Total number of requests processed",
-        )
-        .namespace("burncloud"),
-        &["status"],
-    )
-    .expect("Failed to create REQUESTS_TOTAL counter");
-    REGISTRY
-        .register(Box::new(counter.clone()))
-        .expect("Failed to register REQUESTS_TOTAL");
-    counter
-});
+pub static REQUESTS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| int_counter_vec(&REQUESTS_TOTAL_DEF));
 
 /// Request latency histogram in seconds.
-pub static REQUESTS_DURATION_SECONDS: Lazy<HistogramVec> = Lazy::new(|| {
-    let histogram = HistogramVec::new(
-        prometheus::HistogramOpts::new(
-            "burncloud_requests_duration_seconds",
-            "Request latency in seconds",
-        )
-        .namespace("burncloud")
-        .buckets(vec![
-            0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
-        ]),
-        &["endpoint", "model"],
-    )
-    .expect("Failed to create REQUESTS_DURATION_SECONDS histogram");
-    REGISTRY
-        .register(Box::new(histogram.clone()))
-        .expect("Failed to register REQUESTS_DURATION_SECONDS");
-    histogram
-});
+pub static REQUESTS_DURATION_SECONDS: Lazy<HistogramVec> =
+    Lazy::new(|| histogram_vec(&REQUESTS_DURATION_SECONDS_DEF));
 
 /// Number of requests currently being processed.
-pub static REQUESTS_IN_FLIGHT: Lazy<IntGaugeVec> = Lazy::new(|| {
-    let gauge = IntGaugeVec::new(
-        prometheus::Opts::new(
-            "burncloud_requests_in_flight",
-            "Number of requests currently being processed",
-        )
-        .namespace("burncloud"),
-        &["endpoint"],
-    )
-    .expect("Failed to create REQUESTS_IN_FLIGHT gauge");
-    REGISTRY
-        .register(Box::new(gauge.clone()))
-        .expect("Failed to register REQUESTS_IN_FLIGHT");
-    gauge
-});
+pub static REQUESTS_IN_FLIGHT: Lazy<IntGaugeVec> =
+    Lazy::new(|| int_gauge_vec(&REQUESTS_IN_FLIGHT_DEF));
 
 /// Requests by model.
-pub static REQUESTS_BY_MODEL: Lazy<IntCounterVec> = Lazy::new(|| {
-    let counter = IntCounterVec::new(
-        prometheus::Opts::new(
-            "burncloud_requests_by_model",
-            "Number of requests per model",
-        )
-        .namespace("burncloud"),
-        &["model"],
-    )
-    .expect("Failed to create REQUESTS_BY_MODEL counter");
-    REGISTRY
-        .register(Box::new(counter.clone()))
-        .expect("Failed to register REQUESTS_BY_MODEL");
-    counter
-});
+pub static REQUESTS_BY_MODEL: Lazy<IntCounterVec> =
+    Lazy::new(|| int_counter_vec(&REQUESTS_BY_MODEL_DEF));
 
 /// Requests by channel.
-pub static REQUESTS_BY_CHANNEL: Lazy<IntCounterVec> = Lazy::new(|| {
-    let counter = IntCounterVec::new(
-        prometheus::Opts::new(
-            "burncloud_requests_by_channel",
-            "Number of requests per channel",
-        )
-        .namespace("burncloud"),
-        &["channel_id", "channel_name"],
-    )
-    .expect("Failed to create REQUESTS_BY_CHANNEL counter");
-    REGISTRY
-        .register(Box::new(counter.clone()))
-        .expect("Failed to register REQUESTS_BY_CHANNEL");
-    counter
-});
+pub static REQUESTS_BY_CHANNEL: Lazy<IntCounterVec> =
+    Lazy::new(|| int_counter_vec(&REQUESTS_BY_CHANNEL_DEF));
 
 // ============================================================================
 // Token Metrics
 // ============================================================================
 
 /// Total prompt tokens processed.
-pub static TOKENS_PROMPT_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
-    let counter = IntCounter::new(
-        "burncloud_tokens_prompt_total",
-        "Total number of prompt tokens processed",
-    )
-    .expect("Failed to create TOKENS_PROMPT_TOTAL counter");
-    REGISTRY
-        .register(Box::new(counter.clone()))
-        .expect("Failed to register TOKENS_PROMPT_TOTAL");
-    counter
-});
+pub static TOKENS_PROMPT_TOTAL: Lazy<IntCounter> =
+    Lazy::new(|| int_counter(&TOKENS_PROMPT_TOTAL_DEF));
 
 /// Total completion tokens generated.
-pub static TOKENS_COMPLETION_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
-    let counter = IntCounter::new(
-        "burncloud_tokens_completion_total",
-        "Total number of completion tokens generated",
-    )
-    .expect("Failed to create TOKENS_COMPLETION_TOTAL counter");
-    REGISTRY
-        .register(Box::new(counter.clone()))
-        .expect("Failed to register TOKENS_COMPLETION_TOTAL");
-    counter
-});
+pub static TOKENS_COMPLETION_TOTAL: Lazy<IntCounter> =
+    Lazy::new(|| int_counter(&TOKENS_COMPLETION_TOTAL_DEF));
 
 /// Total cost in nanodollars.
-pub static COST_TOTAL_NANO: Lazy<IntCounter> = Lazy::new(|| {
-    let counter = IntCounter::new("burncloud_cost_total_nano", "Total cost in nanodollars")
-        .expect("Failed to create COST_TOTAL_NANO counter");
-    REGISTRY
-        .register(Box::new(counter.clone()))
-        .expect("Failed to register COST_TOTAL_NANO");
-    counter
-});
+pub static COST_TOTAL_NANO: Lazy<IntCounter> = Lazy::new(|| int_counter(&COST_TOTAL_NANO_DEF));
 
 // ============================================================================
 // Channel Health Metrics
 // ============================================================================
 
 /// Channel status (1=healthy, 0=unhealthy).
-pub static CHANNEL_STATUS: Lazy<IntGaugeVec> = Lazy::new(|| {
-    let gauge = IntGaugeVec::new(
-        prometheus::Opts::new(
-            "burncloud_channel_status",
-            "Channel status (1=healthy, 0=unhealthy)",
-        )
-        .namespace("burncloud"),
-        &["channel_id", "channel_name"],
-    )
-    .expect("Failed to create CHANNEL_STATUS gauge");
-    REGISTRY
-        .register(Box::new(gauge.clone()))
-        .expect("Failed to register CHANNEL_STATUS");
-    gauge
-});
+pub static CHANNEL_STATUS: Lazy<IntGaugeVec> = Lazy::new(|| int_gauge_vec(&CHANNEL_STATUS_DEF));
 
 /// Channel error count.
-pub static CHANNEL_ERRORS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
-    let counter = IntCounterVec::new(
-        prometheus::Opts::new(
-            "burncloud_channel_errors_total",
-            "Total number of channel errors",
-        )
-        .namespace("burncloud"),
-        &["channel_id", "channel_name", "error_type"],
-    )
-    .expect("Failed to create CHANNEL_ERRORS_TOTAL counter");
-    REGISTRY
-        .register(Box::new(counter.clone()))
-        .expect("Failed to register CHANNEL_ERRORS_TOTAL");
-    counter
-});
+pub static CHANNEL_ERRORS_TOTAL: Lazy<IntCounterVec> =
+    Lazy::new(|| int_counter_vec(&CHANNEL_ERRORS_TOTAL_DEF));
 
 /// Channel latency in seconds.
-pub static CHANNEL_LATENCY_SECONDS: Lazy<HistogramVec> = Lazy::new(|| {
-    let histogram = HistogramVec::new(
-        prometheus::HistogramOpts::new(
-            "burncloud_channel_latency_seconds",
-            "Channel request latency in seconds",
-        )
-        .namespace("burncloud")
-        .buckets(vec![0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0]),
-        &["channel_id", "channel_name"],
-    )
-    .expect("Failed to create CHANNEL_LATENCY_SECONDS histogram");
-    REGISTRY
-        .register(Box::new(histogram.clone()))
-        .expect("Failed to register CHANNEL_LATENCY_SECONDS");
-    histogram
-});
+pub static CHANNEL_LATENCY_SECONDS: Lazy<HistogramVec> =
+    Lazy::new(|| histogram_vec(&CHANNEL_LATENCY_SECONDS_DEF));
+
+// ============================================================================
+// Routing Metrics (L2 shaper, L3 affinity, L5 failover)
+// ============================================================================
+
+/// L2 shaper decisions by outcome and traffic color.
+pub static SHAPER_DECISIONS_TOTAL: Lazy<IntCounterVec> =
+    Lazy::new(|| int_counter_vec(&SHAPER_DECISIONS_TOTAL_DEF));
+
+/// Candidates admitted without a shaper bucket.
+pub static SHAPER_FAIL_OPEN_TOTAL: Lazy<IntCounter> =
+    Lazy::new(|| int_counter(&SHAPER_FAIL_OPEN_TOTAL_DEF));
+
+/// L3 affinity cache lookups by result.
+pub static AFFINITY_LOOKUPS_TOTAL: Lazy<IntCounterVec> =
+    Lazy::new(|| int_counter_vec(&AFFINITY_LOOKUPS_TOTAL_DEF));
+
+/// L3 affinity cache evictions by reason.
+pub static AFFINITY_EVICTIONS_TOTAL: Lazy<IntCounterVec> =
+    Lazy::new(|| int_counter_vec(&AFFINITY_EVICTIONS_TOTAL_DEF));
+
+/// L3 affinity cache size.
+pub static AFFINITY_ENTRIES: Lazy<IntGauge> = Lazy::new(|| int_gauge(&AFFINITY_ENTRIES_DEF));
+
+/// Candidates tried per request.
+pub static FAILOVER_ATTEMPTS: Lazy<HistogramVec> =
+    Lazy::new(|| histogram_vec(&FAILOVER_ATTEMPTS_DEF));
+
+// ============================================================================
+// Billing, Adaptive Limit and Circuit Breaker Metrics
+// ============================================================================
+
+/// Requests rejected by the billing preflight.
+pub static BILLING_PREFLIGHT_REJECTED_TOTAL: Lazy<IntCounterVec> =
+    Lazy::new(|| int_counter_vec(&BILLING_PREFLIGHT_REJECTED_TOTAL_DEF));
+
+/// Current AIMD limit per channel and model.
+pub static AIMD_CURRENT_LIMIT: Lazy<IntGaugeVec> =
+    Lazy::new(|| int_gauge_vec(&AIMD_CURRENT_LIMIT_DEF));
+
+/// Learned upstream limit per channel and model.
+pub static AIMD_LEARNED_LIMIT: Lazy<IntGaugeVec> =
+    Lazy::new(|| int_gauge_vec(&AIMD_LEARNED_LIMIT_DEF));
+
+/// Circuit breaker trips by level and reason.
+pub static CIRCUIT_BREAKER_TRIPS_TOTAL: Lazy<IntCounterVec> =
+    Lazy::new(|| int_counter_vec(&CIRCUIT_BREAKER_TRIPS_TOTAL_DEF));
+
+/// Cross-instance state sync events by direction.
+pub static STATE_SYNC_EVENTS_TOTAL: Lazy<IntCounterVec> =
+    Lazy::new(|| int_counter_vec(&STATE_SYNC_EVENTS_TOTAL_DEF));
 
 // ============================================================================
 // System Resource Metrics
 // ============================================================================
 
 /// Service uptime in seconds.
-pub static UPTIME_SECONDS: Lazy<IntGauge> = Lazy::new(|| {
-    let gauge = IntGauge::new("burncloud_uptime_seconds", "Service uptime in seconds")
-        .expect("Failed to create UPTIME_SECONDS gauge");
-    REGISTRY
-        .register(Box::new(gauge.clone()))
-        .expect("Failed to register UPTIME_SECONDS");
-    gauge
-});
+pub static UPTIME_SECONDS: Lazy<IntGauge> = Lazy::new(|| int_gauge(&UPTIME_SECONDS_DEF));
 
 /// Active connections count.
-pub static CONNECTIONS_ACTIVE: Lazy<IntGauge> = Lazy::new(|| {
-    let gauge = IntGauge::new(
-        "burncloud_connections_active",
-        "Number of active connections",
-    )
-    .expect("Failed to create CONNECTIONS_ACTIVE gauge");
-    REGISTRY
-        .register(Box::new(gauge.clone()))
-        .expect("Failed to register CONNECTIONS_ACTIVE");
-    gauge
-});
+pub static CONNECTIONS_ACTIVE: Lazy<IntGauge> = Lazy::new(|| int_gauge(&CONNECTIONS_ACTIVE_DEF));
 
 /// Memory usage in bytes.
-pub static MEMORY_BYTES: Lazy<IntGauge> = Lazy::new(|| {
-    let gauge = IntGauge::new("burncloud_memory_bytes", "Memory usage in bytes")
-        .expect("Failed to create MEMORY_BYTES gauge");
-    REGISTRY
-        .register(Box::new(gauge.clone()))
-        .expect("Failed to register MEMORY_BYTES");
-    gauge
-});
+pub static MEMORY_BYTES: Lazy<IntGauge> = Lazy::new(|| int_gauge(&MEMORY_BYTES_DEF));
 
 /// Service start time for uptime calculation.
 static START_TIME: Lazy<Instant> = Lazy::new(Instant::now);
@@ -279,8 +623,12 @@ pub fn record_request(status: &str) {
 /// Record request duration.
 pub fn record_request_duration(endpoint: &str, model: &str, duration_secs: f64) {
     if is_enabled() {
+        let def = &REQUESTS_DURATION_SECONDS_DEF;
         REQUESTS_DURATION_SECONDS
-            .with_label_values(&[endpoint, model])
+            .with_label_values(&[
+                &bounded_label(def.name, "endpoint", endpoint),
+                &bounded_label(def.name, "model", model),
+            ])
             .observe(duration_secs);
     }
 }
@@ -302,7 +650,8 @@ pub fn dec_in_flight(endpoint: &str) {
 /// Record a request by model.
 pub fn record_request_by_model(model: &str) {
     if is_enabled() {
-        REQUESTS_BY_MODEL.with_label_values(&[model]).inc();
+        let model = bounded_label(REQUESTS_BY_MODEL_DEF.name, "model", model);
+        REQUESTS_BY_MODEL.with_label_values(&[&model]).inc();
     }
 }
 
@@ -363,6 +712,97 @@ pub fn record_channel_latency(channel_id: i32, channel_name: &str, latency_secs:
     }
 }
 
+/// Record one L2 shaper decision. `outcome` is `own`, `borrow`, `reject`
+/// or `unconfigured` (which also counts as a fail-open admission).
+pub fn record_shaper_decision(outcome: &str, color: &str) {
+    if is_enabled() {
+        SHAPER_DECISIONS_TOTAL
+            .with_label_values(&[outcome, color])
+            .inc();
+        if outcome == "unconfigured" {
+            SHAPER_FAIL_OPEN_TOTAL.inc();
+        }
+    }
+}
+
+/// Record an affinity cache lookup (`hit`, `miss`, `stale` or `expired`).
+pub fn record_affinity_lookup(result: &str) {
+    if is_enabled() {
+        AFFINITY_LOOKUPS_TOTAL.with_label_values(&[result]).inc();
+    }
+}
+
+/// Record an affinity cache eviction and the resulting cache size.
+pub fn record_affinity_eviction(reason: &str, entries: usize) {
+    if is_enabled() {
+        AFFINITY_EVICTIONS_TOTAL.with_label_values(&[reason]).inc();
+        AFFINITY_ENTRIES.set(entries as i64);
+    }
+}
+
+/// Set the affinity cache size.
+pub fn set_affinity_entries(entries: usize) {
+    if is_enabled() {
+        AFFINITY_ENTRIES.set(entries as i64);
+    }
+}
+
+/// Record a request rejected by the billing preflight.
+pub fn record_billing_preflight_rejected(model: &str) {
+    if is_enabled() {
+        let model = bounded_label(BILLING_PREFLIGHT_REJECTED_TOTAL_DEF.name, "model", model);
+        BILLING_PREFLIGHT_REJECTED_TOTAL
+            .with_label_values(&[&model])
+            .inc();
+    }
+}
+
+/// Set the AIMD limits of a channel/model pair.
+pub fn set_aimd_limits(channel_id: i32, model: &str, current: u32, learned: Option<u32>) {
+    if is_enabled() {
+        let channel_id = channel_id.to_string();
+        let model = bounded_label(AIMD_CURRENT_LIMIT_DEF.name, "model", model);
+        AIMD_CURRENT_LIMIT
+            .with_label_values(&[&channel_id, &model])
+            .set(i64::from(current));
+        if let Some(learned) = learned {
+            AIMD_LEARNED_LIMIT
+                .with_label_values(&[&channel_id, &model])
+                .set(i64::from(learned));
+        }
+    }
+}
+
+/// Record a circuit breaker trip. `level` is a trip level label (`model`,
+/// `channel`); `reason` is a short static cause.
+pub fn record_circuit_trip(level: &str, reason: &str) {
+    if is_enabled() {
+        CIRCUIT_BREAKER_TRIPS_TOTAL
+            .with_label_values(&[level, reason])
+            .inc();
+    }
+}
+
+/// Record how many candidates a request went through.
+pub fn record_failover_attempts(attempts: u32, success: bool) {
+    if is_enabled() {
+        let outcome = if success { "success" } else { "failure" };
+        FAILOVER_ATTEMPTS
+            .with_label_values(&[outcome])
+            .observe(f64::from(attempts));
+    }
+}
+
+/// Record `count` state sync events; `direction` is `sent`, `applied`,
+/// `stale` or `dropped`.
+pub fn record_state_sync_events(direction: &str, count: usize) {
+    if is_enabled() {
+        STATE_SYNC_EVENTS_TOTAL
+            .with_label_values(&[direction])
+            .inc_by(count as u64);
+    }
+}
+
 /// Update system metrics (uptime, memory).
 pub fn update_system_metrics() {
     if is_enabled() {
@@ -434,6 +874,241 @@ pub fn export() -> String {
     String::from_utf8(buffer).unwrap_or_default()
 }
 
+// ============================================================================
+// Grafana Dashboard
+// ============================================================================
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Dashboard {
+    uid: &'static str,
+    title: &'static str,
+    tags: [&'static str; 1],
+    timezone: &'static str,
+    schema_version: u32,
+    refresh: &'static str,
+    time: TimeRange,
+    templating: Templating,
+    panels: Vec<Panel>,
+}
+
+#[derive(Serialize)]
+struct TimeRange {
+    from: &'static str,
+    to: &'static str,
+}
+
+#[derive(Serialize)]
+struct Templating {
+    list: [DatasourceVariable; 1],
+}
+
+#[derive(Serialize)]
+struct DatasourceVariable {
+    name: &'static str,
+    label: &'static str,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    query: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Panel {
+    id: u32,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    title: String,
+    grid_pos: GridPos,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    datasource: Option<Datasource>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    targets: Vec<Target>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field_config: Option<FieldConfig>,
+}
+
+#[derive(Serialize)]
+struct GridPos {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+#[derive(Serialize)]
+struct Datasource {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    uid: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Target {
+    expr: String,
+    legend_format: String,
+    ref_id: &'static str,
+}
+
+#[derive(Serialize)]
+struct FieldConfig {
+    defaults: FieldDefaults,
+}
+
+#[derive(Serialize)]
+struct FieldDefaults {
+    unit: &'static str,
+}
+
+const PANEL_WIDTH: u32 = 12;
+const PANEL_HEIGHT: u32 = 8;
+
+/// PromQL query and legend for a metric's panel.
+fn panel_query(def: &MetricDef) -> (String, String) {
+    let legend = def
+        .labels
+        .iter()
+        .map(|l| format!("{{{{{l}}}}}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let by = |labels: &[&str]| {
+        if labels.is_empty() {
+            String::new()
+        } else {
+            format!(" by ({})", labels.join(", "))
+        }
+    };
+    match def.kind {
+        MetricKind::Counter => (
+            format!(
+                "sum{} (rate({}[$__rate_interval]))",
+                by(def.labels),
+                def.name
+            ),
+            legend,
+        ),
+        MetricKind::Gauge => (format!("sum{} ({})", by(def.labels), def.name), legend),
+        MetricKind::Histogram => {
+            // One series per value of the first label keeps quantiles readable
+            let first: Vec<&str> = def.labels.iter().take(1).copied().collect();
+            let mut grouping = vec!["le"];
+            grouping.extend(&first);
+            let legend = first
+                .iter()
+                .map(|l| format!("p95 {{{{{l}}}}}"))
+                .next()
+                .unwrap_or_else(|| "p95".to_string());
+            (
+                format!(
+                    "histogram_quantile(0.95, sum{} (rate({}_bucket[$__rate_interval])))",
+                    by(&grouping),
+                    def.name
+                ),
+                legend,
+            )
+        }
+    }
+}
+
+/// Grafana dashboard JSON with one panel per metric in [`DEFINITIONS`],
+/// grouped into a row per section.
+///
+/// The bundled copy lives at `deploy/grafana/burncloud-router.json`; the
+/// metrics tests fail when it drifts from this output.
+pub fn grafana_dashboard() -> String {
+    let datasource = || Datasource {
+        kind: "prometheus",
+        uid: "${datasource}",
+    };
+    let mut panels = Vec::new();
+    let mut id = 0;
+    let mut y = 0;
+    let mut x = 0;
+    let mut section = "";
+    for def in DEFINITIONS {
+        if def.section != section {
+            if x > 0 {
+                y += PANEL_HEIGHT;
+                x = 0;
+            }
+            section = def.section;
+            id += 1;
+            panels.push(Panel {
+                id,
+                kind: "row",
+                title: section.to_string(),
+                grid_pos: GridPos {
+                    x: 0,
+                    y,
+                    w: 2 * PANEL_WIDTH,
+                    h: 1,
+                },
+                description: None,
+                datasource: None,
+                targets: Vec::new(),
+                field_config: None,
+            });
+            y += 1;
+        }
+
+        let (expr, legend_format) = panel_query(def);
+        id += 1;
+        panels.push(Panel {
+            id,
+            kind: "timeseries",
+            title: def.name.trim_start_matches("burncloud_").replace('_', " "),
+            grid_pos: GridPos {
+                x,
+                y,
+                w: PANEL_WIDTH,
+                h: PANEL_HEIGHT,
+            },
+            description: Some(def.help),
+            datasource: Some(datasource()),
+            targets: vec![Target {
+                expr,
+                legend_format,
+                ref_id: "A",
+            }],
+            field_config: Some(FieldConfig {
+                defaults: FieldDefaults { unit: def.unit },
+            }),
+        });
+        if x == 0 {
+            x = PANEL_WIDTH;
+        } else {
+            x = 0;
+            y += PANEL_HEIGHT;
+        }
+    }
+
+    let dashboard = Dashboard {
+        uid: "burncloud-router",
+        title: "BurnCloud Router",
+        tags: ["burncloud"],
+        timezone: "browser",
+        schema_version: 39,
+        refresh: "30s",
+        time: TimeRange {
+            from: "now-6h",
+            to: "now",
+        },
+        templating: Templating {
+            list: [DatasourceVariable {
+                name: "datasource",
+                label: "Prometheus",
+                kind: "datasource",
+                query: "prometheus",
+            }],
+        },
+        panels,
+    };
+    serde_json::to_string_pretty(&dashboard).unwrap_or_default() + "\n"
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -472,10 +1147,118 @@ mod tests {
 
     #[test]
     fn test_export() {
-        // Initialize the metrics by accessing them
-        let _ = &*REQUESTS_TOTAL;
+        // Labeled series are only exported once they have a sample
+        REQUESTS_TOTAL.with_label_values(&["success"]).inc();
         set_enabled(true);
         let output = export();
         assert!(output.contains("burncloud_requests_total"));
     }
+
+    #[test]
+    fn test_every_definition_is_exported() {
+        register_all();
+        // Touch the statics directly: other tests toggle `set_enabled`
+        let labels = |def: &MetricDef| vec!["x"; def.labels.len()];
+        REQUESTS_TOTAL
+            .with_label_values(&labels(&REQUESTS_TOTAL_DEF))
+            .inc();
+        REQUESTS_DURATION_SECONDS
+            .with_label_values(&labels(&REQUESTS_DURATION_SECONDS_DEF))
+            .observe(0.1);
+        REQUESTS_IN_FLIGHT
+            .with_label_values(&labels(&REQUESTS_IN_FLIGHT_DEF))
+            .set(0);
+        REQUESTS_BY_MODEL
+            .with_label_values(&labels(&REQUESTS_BY_MODEL_DEF))
+            .inc();
+        REQUESTS_BY_CHANNEL
+            .with_label_values(&labels(&REQUESTS_BY_CHANNEL_DEF))
+            .inc();
+        CHANNEL_STATUS
+            .with_label_values(&labels(&CHANNEL_STATUS_DEF))
+            .set(1);
+        CHANNEL_ERRORS_TOTAL
+            .with_label_values(&labels(&CHANNEL_ERRORS_TOTAL_DEF))
+            .inc();
+        CHANNEL_LATENCY_SECONDS
+            .with_label_values(&labels(&CHANNEL_LATENCY_SECONDS_DEF))
+            .observe(0.1);
+        SHAPER_DECISIONS_TOTAL
+            .with_label_values(&labels(&SHAPER_DECISIONS_TOTAL_DEF))
+            .inc();
+        AFFINITY_LOOKUPS_TOTAL
+            .with_label_values(&labels(&AFFINITY_LOOKUPS_TOTAL_DEF))
+            .inc();
+        AFFINITY_EVICTIONS_TOTAL
+            .with_label_values(&labels(&AFFINITY_EVICTIONS_TOTAL_DEF))
+            .inc();
+        BILLING_PREFLIGHT_REJECTED_TOTAL
+            .with_label_values(&labels(&BILLING_PREFLIGHT_REJECTED_TOTAL_DEF))
+            .inc();
+        AIMD_CURRENT_LIMIT
+            .with_label_values(&labels(&AIMD_CURRENT_LIMIT_DEF))
+            .set(10);
+        AIMD_LEARNED_LIMIT
+            .with_label_values(&labels(&AIMD_LEARNED_LIMIT_DEF))
+            .set(10);
+        CIRCUIT_BREAKER_TRIPS_TOTAL
+            .with_label_values(&labels(&CIRCUIT_BREAKER_TRIPS_TOTAL_DEF))
+            .inc();
+        FAILOVER_ATTEMPTS
+            .with_label_values(&labels(&FAILOVER_ATTEMPTS_DEF))
+            .observe(2.0);
+        STATE_SYNC_EVENTS_TOTAL
+            .with_label_values(&labels(&STATE_SYNC_EVENTS_TOTAL_DEF))
+            .inc();
+
+        let output = export();
+        for def in DEFINITIONS {
+            assert!(
+                output.contains(&format!("# TYPE {} ", def.name)),
+                "{} not exported",
+                def.name
+            );
+        }
+        assert!(!output.contains("burncloud_burncloud_"));
+    }
+
+    #[test]
+    fn test_bounded_label_caps_distinct_values() {
+        let bounds = LabelBounds::new(2);
+        assert_eq!(bounds.bound("test_metric", "model", "a"), "a");
+        assert_eq!(bounds.bound("test_metric", "model", "b"), "b");
+        assert_eq!(bounds.bound("test_metric", "model", "c"), OVERFLOW_LABEL);
+        // Known values keep their label; other labels have their own cap
+        assert_eq!(bounds.bound("test_metric", "model", "a"), "a");
+        assert_eq!(bounds.bound("test_metric", "endpoint", "c"), "c");
+
+        let unlimited = LabelBounds::new(0);
+        for value in ["a", "b", "c"] {
+            assert_eq!(unlimited.bound("test_metric", "model", value), value);
+        }
+    }
+
+    /// Regenerate with `UPDATE_GRAFANA_DASHBOARD=1 cargo test -p burncloud-router metrics`.
+    #[test]
+    fn test_bundled_dashboard_matches_definitions() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../deploy/grafana/burncloud-router.json"
+        );
+        let generated = grafana_dashboard();
+        if std::env::var_os("UPDATE_GRAFANA_DASHBOARD").is_some() {
+            if let Err(e) = std::fs::write(path, &generated) {
+                panic!("failed to write {path}: {e}");
+            }
+        }
+        let bundled = std::fs::read_to_string(path).unwrap_or_default();
+        assert!(
+            bundled == generated,
+            "deploy/grafana/burncloud-router.json is out of date; \
+             rerun with UPDATE_GRAFANA_DASHBOARD=1"
+        );
+        for def in DEFINITIONS {
+            assert!(generated.contains(def.name), "{} has no panel", def.name);
+        }
+    }
 }
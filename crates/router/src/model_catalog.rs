@@ -0,0 +1,495 @@
+#![allow(clippy::disallowed_types)]
+//! Model listing: `/v1/models`, `/v1/models/{id}` and the Gemini native
+//! `/v1beta/models[/{id}]`.
+//!
+//! The caller must present an API key (or console JWT). Only models with an
+//! enabled channel in the caller's group are listed, further narrowed by the
+//! token's model policy, so a listed model is one the caller can route to.
+//! Enabled virtual models are listed when any of their hops is.
+//!
+//! Each entry carries the model's context window, capabilities and selling
+//! price from the price table. `/v1/models` answers in Anthropic's format
+//! when the request has an `anthropic-version` header.
+
+use crate::context_window::ModelLimits;
+use crate::state::AppState;
+use crate::{build_response_with_header, console_jwt_user};
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Response,
+};
+use burncloud_common::types::Price;
+use burncloud_common::ModelPolicy;
+use burncloud_database_channel::ChannelAbilityModel;
+use burncloud_database_router::{RouterDatabase, RouterTokenValidationResult, VirtualModelModel};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+
+const NANO_PER_UNIT: f64 = 1_000_000_000.0;
+
+/// Response format of a listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CatalogFormat {
+    OpenAI,
+    Anthropic,
+    Gemini,
+}
+
+impl CatalogFormat {
+    /// Anthropic SDKs always send `anthropic-version`; everything else on
+    /// `/v1/models` gets the OpenAI shape.
+    fn for_v1(headers: &HeaderMap) -> Self {
+        if headers.contains_key("anthropic-version") {
+            Self::Anthropic
+        } else {
+            Self::OpenAI
+        }
+    }
+}
+
+/// A model the caller can use, with its price row when one exists.
+#[derive(Debug, Clone)]
+pub struct CatalogEntry {
+    pub id: String,
+    pub price: Option<Price>,
+    /// Concrete models behind a virtual model, in fallback order.
+    pub hops: Option<Vec<String>>,
+}
+
+impl CatalogEntry {
+    fn limits(&self) -> ModelLimits {
+        self.price
+            .as_ref()
+            .map(ModelLimits::from)
+            .unwrap_or_default()
+    }
+
+    fn created(&self) -> i64 {
+        self.price
+            .as_ref()
+            .and_then(|p| p.created_at)
+            .unwrap_or_default()
+    }
+
+    /// OpenAI-style object, extended with limits, capabilities and pricing.
+    pub fn to_openai(&self) -> Value {
+        let limits = self.limits();
+        let mut entry = json!({
+            "id": self.id,
+            "object": "model",
+            "created": self.created(),
+            "owned_by": "burncloud",
+            "permission": [],
+            "root": self.id,
+            "parent": null,
+            "context_window": limits.context_window,
+            "max_output_tokens": limits.max_output_tokens,
+        });
+        if let Some(price) = &self.price {
+            entry["capabilities"] = json!({
+                "vision": price.supports_vision.is_some_and(|v| v != 0),
+                "function_calling": price.supports_function_calling.is_some_and(|v| v != 0),
+            });
+            entry["pricing"] = pricing(price);
+        }
+        if let Some(hops) = &self.hops {
+            entry["fallbacks"] = json!(hops);
+        }
+        entry
+    }
+
+    /// Anthropic `ModelInfo` object.
+    pub fn to_anthropic(&self) -> Value {
+        let created_at = chrono::DateTime::from_timestamp(self.created(), 0)
+            .unwrap_or_default()
+            .to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        json!({
+            "type": "model",
+            "id": self.id,
+            "display_name": self.id,
+            "created_at": created_at,
+        })
+    }
+
+    /// Gemini `Model` resource.
+    pub fn to_gemini(&self) -> Value {
+        let limits = self.limits();
+        let mut entry = json!({
+            "name": format!("models/{}", self.id),
+            "baseModelId": self.id,
+            "version": "001",
+            "displayName": self.id,
+            "supportedGenerationMethods": ["generateContent", "streamGenerateContent", "countTokens"],
+        });
+        if let Some(input) = limits.context_window {
+            entry["inputTokenLimit"] = json!(input);
+        }
+        if let Some(output) = limits.max_output_tokens {
+            entry["outputTokenLimit"] = json!(output);
+        }
+        entry
+    }
+}
+
+/// Selling price per 1M tokens in the price row's currency.
+fn pricing(price: &Price) -> Value {
+    let per_million = |nano: i64| nano as f64 / NANO_PER_UNIT;
+    let mut pricing = json!({
+        "currency": price.currency,
+        "unit": "1M tokens",
+        "input": per_million(price.input_price),
+        "output": per_million(price.output_price),
+    });
+    if let Some(cache_read) = price.cache_read_input_price {
+        pricing["cache_read"] = json!(per_million(cache_read));
+    }
+    if let Some(cache_write) = price.cache_creation_input_price {
+        pricing["cache_write"] = json!(per_million(cache_write));
+    }
+    pricing
+}
+
+/// Full listing body in `format`.
+pub fn render_list(entries: &[CatalogEntry], format: CatalogFormat) -> Value {
+    match format {
+        CatalogFormat::OpenAI => json!({
+            "object": "list",
+            "data": entries.iter().map(CatalogEntry::to_openai).collect::<Vec<_>>(),
+        }),
+        CatalogFormat::Anthropic => json!({
+            "data": entries.iter().map(CatalogEntry::to_anthropic).collect::<Vec<_>>(),
+            "has_more": false,
+            "first_id": entries.first().map(|e| e.id.as_str()),
+            "last_id": entries.last().map(|e| e.id.as_str()),
+        }),
+        CatalogFormat::Gemini => json!({
+            "models": entries.iter().map(CatalogEntry::to_gemini).collect::<Vec<_>>(),
+        }),
+    }
+}
+
+/// "Model not found" body in `format`.
+fn not_found(id: &str, format: CatalogFormat) -> Value {
+    let message = format!("The model '{id}' does not exist or you do not have access to it");
+    match format {
+        CatalogFormat::OpenAI => json!({
+            "error": {"message": message, "type": "invalid_request_error", "code": "model_not_found"}
+        }),
+        CatalogFormat::Anthropic => json!({
+            "type": "error",
+            "error": {"type": "not_found_error", "message": message}
+        }),
+        CatalogFormat::Gemini => json!({
+            "error": {"code": 404, "message": message, "status": "NOT_FOUND"}
+        }),
+    }
+}
+
+fn json_response(status: StatusCode, body: &Value) -> Response {
+    build_response_with_header(
+        status,
+        "content-type",
+        "application/json",
+        Body::from(body.to_string()),
+    )
+}
+
+fn unauthorized(message: &str, code: &str) -> Response {
+    json_response(
+        StatusCode::UNAUTHORIZED,
+        &json!({
+            "error": {"message": message, "type": "invalid_request_error", "code": code}
+        }),
+    )
+}
+
+/// Group and model policy of the caller, accepting the same credentials as
+/// `proxy_handler`: API keys in `Authorization`, `x-api-key` or
+/// `x-goog-api-key`, then console JWTs with a live session (default group,
+/// unrestricted).
+async fn caller_scope(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> Result<(String, Option<ModelPolicy>), Response> {
+    let token = headers
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer "))
+        .or_else(|| headers.get("x-api-key").and_then(|h| h.to_str().ok()))
+        .or_else(|| headers.get("x-goog-api-key").and_then(|h| h.to_str().ok()))
+        .ok_or_else(|| unauthorized("Unauthorized: Missing Bearer Token", "missing_token"))?;
+
+    let (group, raw_policy) = match RouterDatabase::validate_token_and_get_info(&state.db, token)
+        .await
+    {
+        Ok(Some(info)) => (info.group, info.model_policy),
+        Ok(None) => match RouterDatabase::validate_token_detailed(&state.db, token).await {
+            Ok(RouterTokenValidationResult::Valid(t)) => ("default".to_string(), t.model_policy),
+            Ok(RouterTokenValidationResult::Expired) => {
+                return Err(unauthorized("Token has expired", "token_expired"))
+            }
+            Ok(RouterTokenValidationResult::Invalid) => {
+                console_jwt_user(&state.db, token)
+                    .await
+                    .ok_or_else(|| unauthorized("Invalid Token", "invalid_token"))?;
+                ("default".to_string(), None)
+            }
+            Err(e) => return Err(internal_error(&e.to_string())),
+        },
+        Err(e) => return Err(internal_error(&e.to_string())),
+    };
+
+    let policy = raw_policy.map(|raw| {
+        ModelPolicy::parse(&raw).unwrap_or_else(|_| {
+            // Mirror proxy_handler, which fails closed on an unreadable policy
+            ModelPolicy {
+                deny: vec!["*".to_string()],
+                ..Default::default()
+            }
+        })
+    });
+    Ok((group, policy))
+}
+
+fn internal_error(message: &str) -> Response {
+    json_response(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        &json!({
+            "error": {"message": format!("Internal Auth Error: {message}"), "type": "server_error"}
+        }),
+    )
+}
+
+fn catalog_error() -> Response {
+    json_response(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        &json!({
+            "error": {"message": "Failed to list models", "type": "server_error"}
+        }),
+    )
+}
+
+/// Models the caller can route to, sorted by id.
+async fn catalog(
+    state: &AppState,
+    group: &str,
+    policy: Option<&ModelPolicy>,
+) -> burncloud_database::Result<Vec<CatalogEntry>> {
+    let routable: HashSet<String> =
+        ChannelAbilityModel::list_distinct_models_by_group(&state.db, group)
+            .await?
+            .into_iter()
+            .collect();
+    let allowed = |model: &str| policy.is_none_or(|p| p.allows(model));
+
+    let mut entries = Vec::new();
+    for model in &routable {
+        if allowed(model) {
+            entries.push(CatalogEntry {
+                id: model.clone(),
+                price: state.price_cache.get(model, None).await,
+                hops: None,
+            });
+        }
+    }
+    for virtual_model in VirtualModelModel::list(&state.db).await? {
+        let hops: Vec<String> = virtual_model
+            .hops
+            .into_iter()
+            .map(|h| h.model)
+            .filter(|m| routable.contains(m))
+            .collect();
+        if virtual_model.enabled && !hops.is_empty() && allowed(&virtual_model.name) {
+            entries.push(CatalogEntry {
+                id: virtual_model.name,
+                price: None,
+                hops: Some(hops),
+            });
+        }
+    }
+    entries.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(entries)
+}
+
+async fn list(state: &AppState, headers: &HeaderMap, format: CatalogFormat) -> Response {
+    let (group, policy) = match caller_scope(state, headers).await {
+        Ok(scope) => scope,
+        Err(response) => return response,
+    };
+    match catalog(state, &group, policy.as_ref()).await {
+        Ok(entries) => json_response(StatusCode::OK, &render_list(&entries, format)),
+        Err(e) => {
+            tracing::error!(group = %group, "Failed to list models: {e}");
+            catalog_error()
+        }
+    }
+}
+
+async fn get_one(
+    state: &AppState,
+    headers: &HeaderMap,
+    id: &str,
+    format: CatalogFormat,
+) -> Response {
+    let (group, policy) = match caller_scope(state, headers).await {
+        Ok(scope) => scope,
+        Err(response) => return response,
+    };
+    let entries = match catalog(state, &group, policy.as_ref()).await {
+        Ok(entries) => entries,
+        Err(e) => {
+            tracing::error!(group = %group, "Failed to list models: {e}");
+            return catalog_error();
+        }
+    };
+    match entries.iter().find(|e| e.id == id) {
+        Some(entry) => json_response(
+            StatusCode::OK,
+            &match format {
+                CatalogFormat::OpenAI => entry.to_openai(),
+                CatalogFormat::Anthropic => entry.to_anthropic(),
+                CatalogFormat::Gemini => entry.to_gemini(),
+            },
+        ),
+        None => json_response(StatusCode::NOT_FOUND, &not_found(id, format)),
+    }
+}
+
+/// GET /v1/models (OpenAI, or Anthropic with `anthropic-version`)
+pub(crate) async fn list_models_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Response {
+    list(&state, &headers, CatalogFormat::for_v1(&headers)).await
+}
+
+/// GET /v1/models/{id}
+pub(crate) async fn get_model_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    get_one(&state, &headers, &id, CatalogFormat::for_v1(&headers)).await
+}
+
+/// GET /v1beta/models
+pub(crate) async fn gemini_list_models_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Response {
+    list(&state, &headers, CatalogFormat::Gemini).await
+}
+
+/// GET /v1beta/models/{id}
+pub(crate) async fn gemini_get_model_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    get_one(&state, &headers, &id, CatalogFormat::Gemini).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn priced(model: &str) -> Price {
+        Price {
+            id: 1,
+            model: model.to_string(),
+            currency: "USD".to_string(),
+            input_price: 2_500_000_000,
+            output_price: 10_000_000_000,
+            cache_read_input_price: Some(1_250_000_000),
+            cache_creation_input_price: None,
+            batch_input_price: None,
+            batch_output_price: None,
+            priority_input_price: None,
+            priority_output_price: None,
+            audio_input_price: None,
+            audio_output_price: None,
+            reasoning_price: None,
+            embedding_price: None,
+            image_price: None,
+            video_price: None,
+            music_price: None,
+            source: None,
+            region: None,
+            context_window: Some(128_000),
+            max_output_tokens: Some(16_384),
+            supports_vision: Some(1),
+            supports_function_calling: Some(1),
+            synced_at: None,
+            created_at: Some(1_700_000_000),
+            updated_at: None,
+            voices_pricing: None,
+            video_pricing: None,
+            asr_pricing: None,
+            realtime_pricing: None,
+            model_type: None,
+        }
+    }
+
+    fn entries() -> Vec<CatalogEntry> {
+        vec![
+            CatalogEntry {
+                id: "gpt-4o".to_string(),
+                price: Some(priced("gpt-4o")),
+                hops: None,
+            },
+            CatalogEntry {
+                id: "unpriced".to_string(),
+                price: None,
+                hops: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_openai_listing_includes_metadata_and_price() {
+        let list = render_list(&entries(), CatalogFormat::OpenAI);
+        assert_eq!(list["object"], "list");
+        let gpt = &list["data"][0];
+        assert_eq!(gpt["id"], "gpt-4o");
+        assert_eq!(gpt["created"], 1_700_000_000);
+        assert_eq!(gpt["context_window"], 128_000);
+        assert_eq!(gpt["max_output_tokens"], 16_384);
+        assert_eq!(gpt["capabilities"]["vision"], true);
+        assert_eq!(gpt["pricing"]["input"], 2.5);
+        assert_eq!(gpt["pricing"]["output"], 10.0);
+        assert_eq!(gpt["pricing"]["cache_read"], 1.25);
+
+        let unpriced = &list["data"][1];
+        assert!(unpriced["context_window"].is_null());
+        assert!(unpriced.get("pricing").is_none());
+    }
+
+    #[test]
+    fn test_anthropic_and_gemini_listings() {
+        let anthropic = render_list(&entries(), CatalogFormat::Anthropic);
+        assert_eq!(anthropic["data"][0]["type"], "model");
+        assert_eq!(anthropic["data"][0]["created_at"], "2023-11-14T22:13:20Z");
+        assert_eq!(anthropic["first_id"], "gpt-4o");
+        assert_eq!(anthropic["last_id"], "unpriced");
+        assert_eq!(anthropic["has_more"], false);
+
+        let gemini = render_list(&entries(), CatalogFormat::Gemini);
+        assert_eq!(gemini["models"][0]["name"], "models/gpt-4o");
+        assert_eq!(gemini["models"][0]["inputTokenLimit"], 128_000);
+        assert_eq!(gemini["models"][0]["outputTokenLimit"], 16_384);
+        assert!(gemini["models"][1].get("inputTokenLimit").is_none());
+    }
+
+    #[test]
+    fn test_format_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(CatalogFormat::for_v1(&headers), CatalogFormat::OpenAI);
+        headers.insert(
+            "anthropic-version",
+            axum::http::HeaderValue::from_static("2023-06-01"),
+        );
+        assert_eq!(CatalogFormat::for_v1(&headers), CatalogFormat::Anthropic);
+    }
+}
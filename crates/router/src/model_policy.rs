@@ -0,0 +1,172 @@
+//! Per-token model restrictions and per-model spend caps.
+//!
+//! The token's [`ModelPolicy`] is checked in `proxy_handler` after the model
+//! name is known and before any channel is selected; a virtual model is
+//! checked per hop, and spend is counted under the model that served. Spend
+//! counters are kept in memory per token and seeded from `router_logs` (keyed
+//! by `router_logs.token_hash`) the first time a token is seen in a period,
+//! so they survive restarts without a separate counter table.
+
+use burncloud_common::ModelPolicy;
+use burncloud_database::Database;
+use burncloud_database_router::RouterLogModel;
+use chrono::{Datelike, NaiveTime, TimeZone, Utc};
+use dashmap::DashMap;
+use std::collections::HashMap;
+
+/// Why a request was refused by the token's model policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModelPolicyRejection {
+    /// The model is denied, or not on the token's allow list.
+    NotAllowed,
+    /// A matching daily cap is exhausted.
+    DailyCapReached { pattern: String },
+    /// A matching monthly cap is exhausted.
+    MonthlyCapReached { pattern: String },
+}
+
+/// Spend of one token in the current UTC day and month.
+#[derive(Debug, Clone, Default)]
+struct TokenSpend {
+    day_start: i64,
+    month_start: i64,
+    /// model -> (monthly, daily) nanodollars
+    by_model: HashMap<String, (i64, i64)>,
+}
+
+/// In-memory per-token, per-model spend counters.
+#[derive(Default)]
+pub struct ModelSpendTracker {
+    tokens: DashMap<String, TokenSpend>,
+}
+
+/// Unix seconds of the start of the current UTC (month, day).
+fn period_starts() -> (i64, i64) {
+    let today = Utc::now().date_naive();
+    let day_start = Utc
+        .from_utc_datetime(&today.and_time(NaiveTime::MIN))
+        .timestamp();
+    let month_start = today
+        .with_day(1)
+        .map(|d| {
+            Utc.from_utc_datetime(&d.and_time(NaiveTime::MIN))
+                .timestamp()
+        })
+        .unwrap_or(day_start);
+    (month_start, day_start)
+}
+
+impl ModelSpendTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check `model` against `policy` for the token identified by
+    /// `token_hashes[0]` (any further hashes are earlier keys of the same
+    /// token whose logged spend still counts).
+    pub async fn check(
+        &self,
+        db: &Database,
+        token_hashes: &[String],
+        policy: &ModelPolicy,
+        model: Option<&str>,
+    ) -> burncloud_database::Result<Result<(), ModelPolicyRejection>> {
+        let restricted = !policy.allow.is_empty() || !policy.deny.is_empty();
+        let Some(model) = model else {
+            // A request without a model can't be matched against an allow list
+            return Ok(if restricted {
+                Err(ModelPolicyRejection::NotAllowed)
+            } else {
+                Ok(())
+            });
+        };
+        if !policy.allows(model) {
+            return Ok(Err(ModelPolicyRejection::NotAllowed));
+        }
+        let caps: Vec<_> = policy.caps_for(model).collect();
+        let Some(key) = token_hashes.first() else {
+            return Ok(Ok(()));
+        };
+        if caps.is_empty() {
+            return Ok(Ok(()));
+        }
+
+        self.ensure_seeded(db, token_hashes).await?;
+        let Some(spend) = self.tokens.get(key) else {
+            return Ok(Ok(()));
+        };
+        for cap in caps {
+            let (monthly, daily) = spend
+                .by_model
+                .iter()
+                .filter(|(m, _)| burncloud_common::model_policy::glob_match(&cap.model, m))
+                .fold((0_i64, 0_i64), |(mo, da), (_, (m, d))| {
+                    (mo.saturating_add(*m), da.saturating_add(*d))
+                });
+            if cap.daily.is_some_and(|limit| daily >= limit) {
+                return Ok(Err(ModelPolicyRejection::DailyCapReached {
+                    pattern: cap.model.clone(),
+                }));
+            }
+            if cap.monthly.is_some_and(|limit| monthly >= limit) {
+                return Ok(Err(ModelPolicyRejection::MonthlyCapReached {
+                    pattern: cap.model.clone(),
+                }));
+            }
+        }
+        Ok(Ok(()))
+    }
+
+    /// Add settled spend. Tokens that were never checked (no caps) are not tracked.
+    pub fn record(&self, token_hash: &str, model: &str, cost_nano: i64) {
+        if cost_nano <= 0 {
+            return;
+        }
+        let (month_start, day_start) = period_starts();
+        let stale = match self.tokens.get_mut(token_hash) {
+            Some(mut spend) if spend.month_start == month_start && spend.day_start == day_start => {
+                let entry = spend.by_model.entry(model.to_string()).or_default();
+                entry.0 = entry.0.saturating_add(cost_nano);
+                entry.1 = entry.1.saturating_add(cost_nano);
+                false
+            }
+            Some(_) => true,
+            None => false,
+        };
+        if stale {
+            // Re-seed from router_logs on the next check
+            self.tokens.remove(token_hash);
+        }
+    }
+
+    async fn ensure_seeded(
+        &self,
+        db: &Database,
+        token_hashes: &[String],
+    ) -> burncloud_database::Result<()> {
+        let Some(key) = token_hashes.first() else {
+            return Ok(());
+        };
+        let (month_start, day_start) = period_starts();
+        if self
+            .tokens
+            .get(key)
+            .is_some_and(|s| s.month_start == month_start && s.day_start == day_start)
+        {
+            return Ok(());
+        }
+
+        let rows =
+            RouterLogModel::token_model_spend(db, token_hashes, month_start, day_start).await?;
+        let spend = TokenSpend {
+            day_start,
+            month_start,
+            by_model: rows
+                .into_iter()
+                .map(|r| (r.model, (r.monthly_nano, r.daily_nano)))
+                .collect(),
+        };
+        self.tokens.insert(key.clone(), spend);
+        Ok(())
+    }
+}
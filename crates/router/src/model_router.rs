@@ -5,6 +5,7 @@ use burncloud_common::types::Channel;
 use burncloud_database::placeholder::{ph, phs};
 use burncloud_database::sqlx;
 use burncloud_database::Database;
+use burncloud_database_router::{VirtualModelHop, VirtualModelModel};
 
 use crate::affinity::{self, AffinityCache};
 use crate::channel_state::ChannelStateTracker;
@@ -199,6 +200,21 @@ impl ModelRouter {
         Ok(result)
     }
 
+    /// Fallback chain for `model` when it names an enabled virtual model.
+    ///
+    /// Returns `None` for concrete model names. Lookup errors are logged and
+    /// treated as "not virtual" so routing still works without the table.
+    pub async fn virtual_chain(&self, model: &str) -> Option<Vec<VirtualModelHop>> {
+        match VirtualModelModel::get(&self.db, model).await {
+            Ok(Some(vm)) if vm.enabled && !vm.hops.is_empty() => Some(vm.hops),
+            Ok(_) => None,
+            Err(e) => {
+                tracing::warn!(model, "Virtual model lookup failed: {e}");
+                None
+            }
+        }
+    }
+
     /// Route with the multi-factor scheduler, returning ranked candidates (top-5)
     /// and the routing-layer decision that determined the first candidate.
     ///
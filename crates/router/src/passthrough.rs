@@ -186,6 +186,20 @@ pub fn extract_model_from_gemini_path(path: &str) -> Option<String> {
     None
 }
 
+/// Replaces the model segment of a Gemini native path, keeping the method.
+///
+/// Returns `None` when `path` is not a Gemini native path, so callers can
+/// keep the original path unchanged.
+pub fn with_gemini_path_model(path: &str, model: &str) -> Option<String> {
+    let current = extract_model_from_gemini_path(path)?;
+    let start = path.find("/models/")? + "/models/".len();
+    Some(format!(
+        "{}{model}{}",
+        &path[..start],
+        &path[start + current.len()..]
+    ))
+}
+
 /// Parses token usage from Gemini response's `usageMetadata` field.
 ///
 /// # Arguments
@@ -415,6 +429,19 @@ mod tests {
         assert_eq!(extract_model_from_gemini_path("/v1/chat/completions"), None);
     }
 
+    #[test]
+    fn test_with_gemini_path_model() {
+        assert_eq!(
+            with_gemini_path_model("/v1beta/models/company-smart:generateContent", "gemini-pro"),
+            Some("/v1beta/models/gemini-pro:generateContent".to_string())
+        );
+        assert_eq!(
+            with_gemini_path_model("/v1/models/smart", "gemini-2.0-flash"),
+            Some("/v1/models/gemini-2.0-flash".to_string())
+        );
+        assert_eq!(with_gemini_path_model("/v1/chat/completions", "gpt-4.1"), None);
+    }
+
     #[test]
     fn test_parse_gemini_usage() {
         let response = json!({
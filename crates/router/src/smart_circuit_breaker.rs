@@ -66,6 +66,23 @@ pub enum TripLevel {
     },
 }
 
+impl TripLevel {
+    /// Metrics label of [`TripLevel::Model`]
+    pub const MODEL_LABEL: &'static str = "model";
+    /// Metrics label of [`TripLevel::Channel`]
+    pub const CHANNEL_LABEL: &'static str = "channel";
+
+    /// Label used for the `level` of circuit breaker metrics
+    pub fn as_label(&self) -> &'static str {
+        match self {
+            TripLevel::None => "none",
+            TripLevel::Degraded { .. } => "degraded",
+            TripLevel::Model { .. } => Self::MODEL_LABEL,
+            TripLevel::Channel { .. } => Self::CHANNEL_LABEL,
+        }
+    }
+}
+
 /// Statistics for a time window
 #[derive(Debug, Clone, Default)]
 pub struct WindowStats {
@@ -164,8 +181,11 @@ impl SmartCircuitBreaker {
         Self::new(SmartCircuitBreakerConfig::default())
     }
 
-    /// Record a response and update state
-    pub fn record(&mut self, quality: &ResponseQuality, latency_ms: u64) {
+    /// Record a response and update state.
+    ///
+    /// Returns the trip cause (`error_rate`, `health`, `probe_failed`) when
+    /// this response opened the circuit.
+    pub fn record(&mut self, quality: &ResponseQuality, latency_ms: u64) -> Option<&'static str> {
         let now = Instant::now();
         let health_score = ResponseQualityDetector::quality_to_health_score(quality);
         let is_success = health_score > 0.5;
@@ -185,7 +205,7 @@ impl SmartCircuitBreaker {
         self.update_health_score(health_score);
 
         // Check for state transitions
-        self.check_state_transition();
+        self.check_state_transition()
     }
 
     /// Get current circuit state
@@ -284,6 +304,22 @@ impl SmartCircuitBreaker {
 
     // --- Private methods ---
 
+    /// Time left open and the trip reason, while the circuit is open.
+    fn open_remaining(&self) -> Option<(Duration, String)> {
+        if self.state != CircuitState::Open {
+            return None;
+        }
+        let left = self.reset_at?.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return None;
+        }
+        let reason = self
+            .trip_reason
+            .clone()
+            .unwrap_or_else(|| "Circuit open".to_string());
+        Some((left, reason))
+    }
+
     fn prune_old_records(&mut self, now: Instant) {
         let cutoff = now - self.config.window_size;
         while let Some(front) = self.records.front() {
@@ -304,12 +340,12 @@ impl SmartCircuitBreaker {
         self.health_score.store(raw, Ordering::Relaxed);
     }
 
-    fn check_state_transition(&mut self) {
+    fn check_state_transition(&mut self) -> Option<&'static str> {
         let stats = self.get_stats();
 
         // Need minimum requests to make decision
         if stats.total_requests < self.config.min_requests as u64 {
-            return;
+            return None;
         }
 
         let error_rate = stats.error_rate();
@@ -324,11 +360,13 @@ impl SmartCircuitBreaker {
                         error_rate * 100.0,
                         self.config.error_rate_threshold * 100.0
                     ));
+                    return Some("error_rate");
                 } else if health < self.config.health_break_threshold {
                     self.trip_circuit(&format!(
                         "Health score {:.2} below threshold {:.2}",
                         health, self.config.health_break_threshold
                     ));
+                    return Some("health");
                 }
             }
             CircuitState::Open => {
@@ -345,9 +383,11 @@ impl SmartCircuitBreaker {
                 } else if health < self.config.health_break_threshold {
                     // Recovery failed, re-open
                     self.trip_circuit("Recovery probe failed");
+                    return Some("probe_failed");
                 }
             }
         }
+        None
     }
 
     fn trip_circuit(&mut self, reason: &str) {
@@ -380,16 +420,74 @@ impl MultiLevelCircuitBreaker {
         }
     }
 
-    /// Record response for a model
-    pub fn record(&mut self, model: &str, quality: &ResponseQuality, latency_ms: u64) {
+    /// Record response for a model; trips are counted per level in metrics.
+    ///
+    /// Returns the trip this response caused, if any (a channel-level trip
+    /// takes precedence over a model-level one).
+    pub fn record(
+        &mut self,
+        model: &str,
+        quality: &ResponseQuality,
+        latency_ms: u64,
+    ) -> Option<TripLevel> {
+        let mut tripped = None;
+
         // Record at model level
-        self.model_breakers
+        let model_breaker = self
+            .model_breakers
             .entry(model.to_string())
-            .or_insert_with(SmartCircuitBreaker::with_defaults)
-            .record(quality, latency_ms);
+            .or_insert_with(SmartCircuitBreaker::with_defaults);
+        if let Some(cause) = model_breaker.record(quality, latency_ms) {
+            crate::metrics::record_circuit_trip(TripLevel::MODEL_LABEL, cause);
+            tripped = model_breaker
+                .open_remaining()
+                .map(|(left, reason)| TripLevel::Model {
+                    until: Instant::now() + left,
+                    reason,
+                });
+        }
 
         // Also record at channel level (for detecting channel-wide issues)
-        self.channel_breaker.record(quality, latency_ms);
+        if let Some(cause) = self.channel_breaker.record(quality, latency_ms) {
+            crate::metrics::record_circuit_trip(TripLevel::CHANNEL_LABEL, cause);
+            tripped = self
+                .channel_breaker
+                .open_remaining()
+                .map(|(left, reason)| TripLevel::Channel {
+                    until: Instant::now() + left,
+                    reason,
+                })
+                .or(tripped);
+        }
+        tripped
+    }
+
+    /// Open the channel breaker (`model` = `None`) or a model breaker for
+    /// `duration`, e.g. for a trip reported by another router instance.
+    pub fn trip(&mut self, model: Option<&str>, reason: &str, duration: Duration) {
+        match model {
+            Some(model) => self
+                .model_breakers
+                .entry(model.to_string())
+                .or_insert_with(SmartCircuitBreaker::with_defaults)
+                .trip(reason, duration),
+            None => self.channel_breaker.trip(reason, duration),
+        }
+    }
+
+    /// Currently open breakers as `(model, time left, reason)`; `model` is
+    /// `None` for the channel breaker.
+    pub fn open_breakers(&self) -> Vec<(Option<String>, Duration, String)> {
+        let channel = self
+            .channel_breaker
+            .open_remaining()
+            .map(|(left, reason)| (None, left, reason));
+        let models = self.model_breakers.iter().filter_map(|(model, breaker)| {
+            breaker
+                .open_remaining()
+                .map(|(left, reason)| (Some(model.clone()), left, reason))
+        });
+        channel.into_iter().chain(models).collect()
     }
 
     /// Check if request is allowed for a model
@@ -5,8 +5,11 @@ use crate::affinity::AffinityCache;
 use crate::balancer::RoundRobinBalancer;
 use crate::channel_state::ChannelStateTracker;
 use crate::circuit_breaker::CircuitBreaker;
+use crate::client_limit::ClientLimitBackend;
+use crate::context_window::ContextWindowConfig;
 use crate::exchange_rate::ExchangeRateService;
 use crate::limiter::RateLimiter;
+use crate::model_policy::ModelSpendTracker;
 use crate::model_router::ModelRouter;
 use crate::price_sync::SyncResult;
 use crate::rate_budget::InMemoryBudget;
@@ -16,6 +19,7 @@ use crate::channel_health_manager::ChannelHealthManager;
 use burncloud_database::Database;
 use burncloud_database_router::{RouterLog, RouterRequestLog, StoragePolicy};
 use burncloud_service_billing::{CostCalculator, PriceCache};
+use burncloud_service_notification::NotificationService;
 use reqwest::Client;
 use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
@@ -37,10 +41,11 @@ pub struct AppState {
     pub balancer: Arc<RoundRobinBalancer>,
     pub limiter: Arc<RateLimiter>,
     pub circuit_breaker: Arc<CircuitBreaker>,
-    pub log_tx: mpsc::Sender<RouterLog>,
+    /// Async billing log writes; the span is the request's `log_write` span.
+    pub log_tx: mpsc::Sender<(RouterLog, tracing::Span)>,
     /// Channel for async request log writes (router_request_logs table).
     /// Capacity matches log_tx for consistent throughput.
-    pub request_log_tx: mpsc::Sender<RouterRequestLog>,
+    pub request_log_tx: mpsc::Sender<(RouterRequestLog, tracing::Span)>,
     pub model_router: Arc<ModelRouter>,
     pub channel_state_tracker: Arc<ChannelStateTracker>,
     pub adaptor_factory: Arc<adaptor::factory::DynamicAdaptorFactory>,
@@ -80,4 +85,13 @@ pub struct AppState {
     /// NEW: Smart Channel Health Manager
     /// Integrates response quality detection with circuit breaker decisions
     pub channel_health_manager: Arc<ChannelHealthManager>,
+    /// Per-token, per-model spend counters for token model policies.
+    pub model_spend: Arc<ModelSpendTracker>,
+    /// Per-token / per-user RPM, TPM and concurrency counters.
+    pub client_limits: Arc<dyn ClientLimitBackend>,
+    /// Context-window preflight: limits check, opt-in clamp and
+    /// long-context sibling models.
+    pub context_window: Arc<ContextWindowConfig>,
+    /// Low-balance and quota thresholds, checked after each settlement.
+    pub notifications: Arc<NotificationService>,
 }
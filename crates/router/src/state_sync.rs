@@ -0,0 +1,1138 @@
+//! Cross-instance sync of the router's in-memory health state.
+//!
+//! The L0 [`CircuitBreaker`], the smart breakers of the
+//! [`ChannelHealthManager`], the AIMD limits kept by the
+//! [`ChannelStateTracker`] and the L3 [`AffinityCache`] all live in process
+//! memory. Behind a load balancer every replica would otherwise learn channel
+//! failures on its own, and sticky sessions would break whenever a request
+//! lands on another node.
+//!
+//! Each component publishes its own changes as [`SyncEvent`]s through an
+//! attached [`SyncPublisher`]; a background task batches them into
+//! [`SyncEnvelope`]s and hands them to a [`SyncTransport`]:
+//!
+//! - [`RedisSyncTransport`]: Redis pub/sub on [`REDIS_CHANNEL`].
+//! - [`GossipSyncTransport`]: UDP datagrams to a static peer list, signed
+//!   with a shared secret, for deployments without Redis.
+//! - [`LocalSyncBus`]: in-process bus connecting several router instances
+//!   in one process (tests, embedded setups).
+//!
+//! Remote events are applied through the components' `apply_remote_*`
+//! methods, which never publish, so events do not echo between replicas.
+//!
+//! # Staleness bound
+//!
+//! Changes are published as they happen. On top of that every replica
+//! re-publishes a full snapshot of its shareable state every
+//! [`SyncConfig::snapshot_interval`], so a replica that missed a message
+//! (Redis reconnect, dropped datagram, full outbox) converges within one
+//! interval plus delivery latency. Envelopes older than
+//! [`SyncConfig::max_staleness`] are discarded instead of applied, and
+//! remaining durations are shortened by the envelope's age on arrival.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tokio::net::UdpSocket;
+use tokio::sync::{broadcast, mpsc};
+
+use crate::affinity::AffinityCache;
+use crate::channel_health_manager::ChannelHealthManager;
+use crate::channel_state::ChannelStateTracker;
+use crate::circuit_breaker::CircuitBreaker;
+use crate::state::BudgetUpdate;
+
+/// Redis pub/sub channel used by [`RedisSyncTransport`].
+pub const REDIS_CHANNEL: &str = "bc:state-sync";
+
+/// Default UDP bind address of [`GossipSyncTransport`]. Replicas on other
+/// hosts need `BURNCLOUD_STATE_SYNC_BIND` set to a private interface.
+pub const DEFAULT_GOSSIP_BIND: &str = "127.0.0.1:7946";
+
+/// Default for `BURNCLOUD_STATE_SYNC_INTERVAL_SECS`.
+pub const DEFAULT_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Default for `BURNCLOUD_STATE_SYNC_MAX_STALENESS_SECS`.
+pub const DEFAULT_MAX_STALENESS: Duration = Duration::from_secs(30);
+
+/// Events per envelope; keeps a gossip datagram well under 64 KiB.
+const MAX_EVENTS_PER_ENVELOPE: usize = 200;
+
+/// Local events buffered before new ones are dropped (the next snapshot
+/// repairs them).
+const OUTBOX_CAPACITY: usize = 4096;
+
+/// Largest datagram [`GossipSyncTransport`] sends or accepts.
+const MAX_DATAGRAM_BYTES: usize = 65_000;
+
+/// How often [`GossipSyncTransport`] re-resolves its peer names.
+const PEER_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Largest difference between a gossip packet's timestamp and the
+/// receiver's clock; nonces are remembered for as long.
+const GOSSIP_MAX_SKEW: Duration = Duration::from_secs(30);
+
+/// One change to shareable router state.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SyncEvent {
+    /// The L0 breaker of an upstream opened for `open_ms`.
+    BreakerOpened { upstream_id: String, open_ms: u64 },
+    /// An upstream answered 429 and must not be used for `retry_ms`.
+    BreakerRateLimited { upstream_id: String, retry_ms: u64 },
+    /// The L0 breaker of an upstream closed after a success.
+    BreakerClosed { upstream_id: String },
+    /// A smart breaker tripped; `model` is `None` for a channel-level trip.
+    HealthTripped {
+        channel_id: i32,
+        model: Option<String>,
+        open_ms: u64,
+        reason: String,
+    },
+    /// Smart breakers were reset; `channel_id` is `None` for all channels.
+    HealthReset { channel_id: Option<i32> },
+    /// The AIMD limits of a channel/model pair changed.
+    AimdLimits {
+        channel_id: i32,
+        model: String,
+        current_limit: u32,
+        learned_limit: Option<u32>,
+        cooldown_ms: Option<u64>,
+    },
+    /// An affinity entry was created or refreshed `age_ms` ago.
+    AffinitySet {
+        key: String,
+        model: String,
+        channel_id: i32,
+        age_ms: u64,
+    },
+    /// An affinity entry was evicted after a failure.
+    AffinityEvicted { key: String, model: String },
+}
+
+impl SyncEvent {
+    /// Shorten remaining durations and age entries by `lag`, the time the
+    /// event spent in transit.
+    fn aged(self, lag: Duration) -> Self {
+        let lag_ms = millis(lag);
+        match self {
+            SyncEvent::BreakerOpened {
+                upstream_id,
+                open_ms,
+            } => SyncEvent::BreakerOpened {
+                upstream_id,
+                open_ms: open_ms.saturating_sub(lag_ms),
+            },
+            SyncEvent::BreakerRateLimited {
+                upstream_id,
+                retry_ms,
+            } => SyncEvent::BreakerRateLimited {
+                upstream_id,
+                retry_ms: retry_ms.saturating_sub(lag_ms),
+            },
+            SyncEvent::HealthTripped {
+                channel_id,
+                model,
+                open_ms,
+                reason,
+            } => SyncEvent::HealthTripped {
+                channel_id,
+                model,
+                open_ms: open_ms.saturating_sub(lag_ms),
+                reason,
+            },
+            SyncEvent::AimdLimits {
+                channel_id,
+                model,
+                current_limit,
+                learned_limit,
+                cooldown_ms,
+            } => SyncEvent::AimdLimits {
+                channel_id,
+                model,
+                current_limit,
+                learned_limit,
+                cooldown_ms: cooldown_ms
+                    .map(|ms| ms.saturating_sub(lag_ms))
+                    .filter(|&ms| ms > 0),
+            },
+            SyncEvent::AffinitySet {
+                key,
+                model,
+                channel_id,
+                age_ms,
+            } => SyncEvent::AffinitySet {
+                key,
+                model,
+                channel_id,
+                age_ms: age_ms.saturating_add(lag_ms),
+            },
+            other => other,
+        }
+    }
+}
+
+/// A batch of events from one instance.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncEnvelope {
+    /// Instance id of the sender; receivers skip their own envelopes.
+    pub origin: String,
+    /// Wall-clock send time in Unix milliseconds.
+    pub sent_at_ms: u64,
+    pub events: Vec<SyncEvent>,
+}
+
+/// Handle components use to publish local changes. Never blocks: when the
+/// outbox is full the event is dropped and left to the next snapshot.
+#[derive(Clone)]
+pub struct SyncPublisher {
+    tx: mpsc::Sender<SyncEvent>,
+}
+
+impl SyncPublisher {
+    pub fn publish(&self, event: SyncEvent) {
+        if self.tx.try_send(event).is_err() {
+            crate::metrics::record_state_sync_events("dropped", 1);
+        }
+    }
+}
+
+/// Delivery of envelopes between instances (best effort).
+#[async_trait::async_trait]
+pub trait SyncTransport: Send + Sync {
+    /// Send an envelope to the other instances.
+    async fn publish(&self, envelope: &SyncEnvelope) -> Result<(), String>;
+
+    /// Start receiving envelopes. May include this instance's own envelopes.
+    async fn subscribe(&self) -> Result<mpsc::Receiver<SyncEnvelope>, String>;
+}
+
+/// Timing of the sync loop.
+#[derive(Debug, Clone)]
+pub struct SyncConfig {
+    /// How often the full shareable state is re-published.
+    pub snapshot_interval: Duration,
+    /// Envelopes older than this on arrival are discarded.
+    pub max_staleness: Duration,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            snapshot_interval: DEFAULT_SNAPSHOT_INTERVAL,
+            max_staleness: DEFAULT_MAX_STALENESS,
+        }
+    }
+}
+
+impl SyncConfig {
+    /// Read `BURNCLOUD_STATE_SYNC_INTERVAL_SECS` and
+    /// `BURNCLOUD_STATE_SYNC_MAX_STALENESS_SECS`.
+    pub fn from_env() -> Self {
+        let secs = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .filter(|&s| s > 0)
+                .map(Duration::from_secs)
+        };
+        let defaults = Self::default();
+        Self {
+            snapshot_interval: secs("BURNCLOUD_STATE_SYNC_INTERVAL_SECS")
+                .unwrap_or(defaults.snapshot_interval),
+            max_staleness: secs("BURNCLOUD_STATE_SYNC_MAX_STALENESS_SECS")
+                .unwrap_or(defaults.max_staleness),
+        }
+    }
+}
+
+/// The components kept in sync.
+#[derive(Clone)]
+pub struct SyncTargets {
+    pub circuit_breaker: Arc<CircuitBreaker>,
+    pub channel_state_tracker: Arc<ChannelStateTracker>,
+    pub channel_health_manager: Arc<ChannelHealthManager>,
+    pub affinity_cache: Arc<AffinityCache>,
+    /// Receives learned limits adopted from other instances, like local
+    /// AIMD feedback does.
+    pub budget_update_tx: Option<mpsc::Sender<BudgetUpdate>>,
+}
+
+impl SyncTargets {
+    /// Every event needed to rebuild this instance's shareable state.
+    pub fn snapshot(&self) -> Vec<SyncEvent> {
+        let mut events = self.circuit_breaker.sync_snapshot();
+        events.extend(self.channel_health_manager.sync_snapshot());
+        events.extend(self.channel_state_tracker.sync_snapshot());
+        events.extend(self.affinity_cache.sync_snapshot());
+        events
+    }
+
+    /// Apply one remote event without publishing it again.
+    pub fn apply(&self, event: SyncEvent) {
+        match event {
+            SyncEvent::BreakerOpened {
+                upstream_id,
+                open_ms,
+            } => self
+                .circuit_breaker
+                .apply_remote_open(&upstream_id, Duration::from_millis(open_ms)),
+            SyncEvent::BreakerRateLimited {
+                upstream_id,
+                retry_ms,
+            } => self
+                .circuit_breaker
+                .apply_remote_rate_limit(&upstream_id, Duration::from_millis(retry_ms)),
+            SyncEvent::BreakerClosed { upstream_id } => {
+                self.circuit_breaker.apply_remote_close(&upstream_id)
+            }
+            SyncEvent::HealthTripped {
+                channel_id,
+                model,
+                open_ms,
+                reason,
+            } => self.channel_health_manager.apply_remote_trip(
+                channel_id,
+                model.as_deref(),
+                &reason,
+                Duration::from_millis(open_ms),
+            ),
+            SyncEvent::HealthReset { channel_id } => {
+                self.channel_health_manager.apply_remote_reset(channel_id)
+            }
+            SyncEvent::AimdLimits {
+                channel_id,
+                model,
+                current_limit,
+                learned_limit,
+                cooldown_ms,
+            } => {
+                let changed = self.channel_state_tracker.apply_remote_aimd(
+                    channel_id,
+                    &model,
+                    current_limit,
+                    learned_limit,
+                    cooldown_ms.map(Duration::from_millis),
+                );
+                if let (Some(learned_limit), Some(tx)) = (changed, &self.budget_update_tx) {
+                    let _ = tx.try_send(BudgetUpdate {
+                        channel_id,
+                        learned_limit,
+                    });
+                }
+            }
+            SyncEvent::AffinitySet {
+                key,
+                model,
+                channel_id,
+                age_ms,
+            } => self.affinity_cache.apply_remote_set(
+                &key,
+                &model,
+                channel_id,
+                Duration::from_millis(age_ms),
+            ),
+            SyncEvent::AffinityEvicted { key, model } => {
+                self.affinity_cache.apply_remote_evict(&key, &model)
+            }
+        }
+    }
+}
+
+fn millis(d: Duration) -> u64 {
+    u64::try_from(d.as_millis()).unwrap_or(u64::MAX)
+}
+
+fn now_ms() -> u64 {
+    millis(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default(),
+    )
+}
+
+/// Attach a publisher to every target and start the publish and apply
+/// tasks. Returns this instance's id.
+pub fn start(
+    transport: Arc<dyn SyncTransport>,
+    targets: SyncTargets,
+    config: SyncConfig,
+) -> String {
+    let origin = uuid::Uuid::new_v4().to_string();
+    let (tx, outbox) = mpsc::channel(OUTBOX_CAPACITY);
+    let publisher = SyncPublisher { tx };
+    targets.circuit_breaker.attach_sync(publisher.clone());
+    targets
+        .channel_health_manager
+        .attach_sync(publisher.clone());
+    targets.channel_state_tracker.attach_sync(publisher.clone());
+    targets.affinity_cache.attach_sync(publisher);
+
+    tokio::spawn(publish_loop(
+        transport.clone(),
+        targets.clone(),
+        outbox,
+        origin.clone(),
+        config.snapshot_interval,
+    ));
+    tokio::spawn(apply_loop(
+        transport,
+        targets,
+        origin.clone(),
+        config.max_staleness,
+    ));
+    origin
+}
+
+async fn send_events(transport: &dyn SyncTransport, origin: &str, events: Vec<SyncEvent>) {
+    for chunk in events.chunks(MAX_EVENTS_PER_ENVELOPE) {
+        let envelope = SyncEnvelope {
+            origin: origin.to_string(),
+            sent_at_ms: now_ms(),
+            events: chunk.to_vec(),
+        };
+        match transport.publish(&envelope).await {
+            Ok(()) => crate::metrics::record_state_sync_events("sent", chunk.len()),
+            Err(e) => tracing::warn!(error = %e, "State sync publish failed"),
+        }
+    }
+}
+
+async fn publish_loop(
+    transport: Arc<dyn SyncTransport>,
+    targets: SyncTargets,
+    mut outbox: mpsc::Receiver<SyncEvent>,
+    origin: String,
+    snapshot_interval: Duration,
+) {
+    let mut ticker = tokio::time::interval(snapshot_interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            event = outbox.recv() => {
+                let Some(event) = event else { break };
+                let mut events = vec![event];
+                while events.len() < MAX_EVENTS_PER_ENVELOPE {
+                    match outbox.try_recv() {
+                        Ok(event) => events.push(event),
+                        Err(_) => break,
+                    }
+                }
+                send_events(transport.as_ref(), &origin, events).await;
+            }
+            _ = ticker.tick() => {
+                let events = targets.snapshot();
+                if !events.is_empty() {
+                    send_events(transport.as_ref(), &origin, events).await;
+                }
+            }
+        }
+    }
+}
+
+async fn apply_loop(
+    transport: Arc<dyn SyncTransport>,
+    targets: SyncTargets,
+    origin: String,
+    max_staleness: Duration,
+) {
+    let mut inbox = match transport.subscribe().await {
+        Ok(inbox) => inbox,
+        Err(e) => {
+            tracing::error!(error = %e, "State sync subscribe failed, remote state will not be applied");
+            return;
+        }
+    };
+    while let Some(envelope) = inbox.recv().await {
+        if envelope.origin == origin {
+            continue;
+        }
+        let lag = Duration::from_millis(now_ms().saturating_sub(envelope.sent_at_ms));
+        if lag > max_staleness {
+            crate::metrics::record_state_sync_events("stale", envelope.events.len());
+            tracing::debug!(
+                origin = %envelope.origin,
+                lag_ms = millis(lag),
+                "Discarding stale state sync envelope"
+            );
+            continue;
+        }
+        crate::metrics::record_state_sync_events("applied", envelope.events.len());
+        for event in envelope.events {
+            targets.apply(event.aged(lag));
+        }
+    }
+}
+
+// ============================================================================
+// Transports
+// ============================================================================
+
+/// In-process transport: every subscriber of a bus receives every envelope.
+#[derive(Clone)]
+pub struct LocalSyncBus {
+    tx: broadcast::Sender<SyncEnvelope>,
+}
+
+impl LocalSyncBus {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(OUTBOX_CAPACITY);
+        Self { tx }
+    }
+}
+
+impl Default for LocalSyncBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl SyncTransport for LocalSyncBus {
+    async fn publish(&self, envelope: &SyncEnvelope) -> Result<(), String> {
+        // No subscribers yet is not an error
+        let _ = self.tx.send(envelope.clone());
+        Ok(())
+    }
+
+    async fn subscribe(&self) -> Result<mpsc::Receiver<SyncEnvelope>, String> {
+        let mut rx = self.tx.subscribe();
+        let (tx, inbox) = mpsc::channel(OUTBOX_CAPACITY);
+        tokio::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(envelope) => {
+                        if tx.send(envelope).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        Ok(inbox)
+    }
+}
+
+/// Redis pub/sub transport. The subscriber reconnects on its own; envelopes
+/// missed meanwhile are repaired by the next snapshot.
+pub struct RedisSyncTransport {
+    client: redis::Client,
+    conn: redis::aio::ConnectionManager,
+}
+
+impl RedisSyncTransport {
+    pub async fn connect(url: &str) -> Result<Self, String> {
+        let client = redis::Client::open(url).map_err(|e| e.to_string())?;
+        let conn = client
+            .get_connection_manager()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(Self { client, conn })
+    }
+}
+
+#[async_trait::async_trait]
+impl SyncTransport for RedisSyncTransport {
+    async fn publish(&self, envelope: &SyncEnvelope) -> Result<(), String> {
+        let payload = serde_json::to_string(envelope).map_err(|e| e.to_string())?;
+        let mut conn = self.conn.clone();
+        redis::cmd("PUBLISH")
+            .arg(REDIS_CHANNEL)
+            .arg(payload)
+            .query_async::<i64>(&mut conn)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn subscribe(&self) -> Result<mpsc::Receiver<SyncEnvelope>, String> {
+        use futures::StreamExt;
+
+        let client = self.client.clone();
+        let (tx, inbox) = mpsc::channel(OUTBOX_CAPACITY);
+        tokio::spawn(async move {
+            while !tx.is_closed() {
+                let mut pubsub = match client.get_async_pubsub().await {
+                    Ok(pubsub) => pubsub,
+                    Err(e) => {
+                        tracing::warn!(error = %e, "State sync Redis subscriber unavailable, retrying");
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        continue;
+                    }
+                };
+                if let Err(e) = pubsub.subscribe(REDIS_CHANNEL).await {
+                    tracing::warn!(error = %e, "State sync Redis SUBSCRIBE failed, retrying");
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+                let mut messages = pubsub.into_on_message();
+                while let Some(msg) = messages.next().await {
+                    let Ok(payload) = msg.get_payload::<String>() else {
+                        continue;
+                    };
+                    match serde_json::from_str::<SyncEnvelope>(&payload) {
+                        Ok(envelope) => {
+                            if tx.send(envelope).await.is_err() {
+                                return;
+                            }
+                        }
+                        Err(e) => {
+                            tracing::debug!(error = %e, "Ignoring malformed state sync message")
+                        }
+                    }
+                }
+                tracing::warn!("State sync Redis subscription closed, reconnecting");
+            }
+        });
+        Ok(inbox)
+    }
+}
+
+/// Datagram of [`GossipSyncTransport`].
+#[derive(Serialize, Deserialize)]
+struct GossipPacket {
+    /// Unix ms the packet was sealed
+    sent_at_ms: u64,
+    /// Random per packet; a repeat within [`GOSSIP_MAX_SKEW`] is a replay
+    nonce: String,
+    /// The [`SyncEnvelope`] as JSON
+    envelope: String,
+    /// Hex HMAC-SHA256 of the fields above under the shared secret
+    mac: String,
+}
+
+/// Signs outgoing [`GossipPacket`]s and verifies incoming ones.
+struct GossipAuth {
+    secret: String,
+    /// Nonces accepted recently, with the unix ms they can be forgotten at
+    seen: Mutex<HashMap<String, u64>>,
+}
+
+impl GossipAuth {
+    fn new(secret: String) -> Self {
+        Self {
+            secret,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn mac(&self, sent_at_ms: u64, nonce: &str, envelope: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.as_bytes())
+            .unwrap_or_else(|_| unreachable!("HMAC accepts keys of any length"));
+        mac.update(sent_at_ms.to_string().as_bytes());
+        mac.update(b".");
+        mac.update(nonce.as_bytes());
+        mac.update(b".");
+        mac.update(envelope.as_bytes());
+        mac
+    }
+
+    fn seal(&self, envelope: &SyncEnvelope, now_ms: u64) -> Result<Vec<u8>, String> {
+        let envelope = serde_json::to_string(envelope).map_err(|e| e.to_string())?;
+        let nonce = uuid::Uuid::new_v4().simple().to_string();
+        let mac = hex::encode(self.mac(now_ms, &nonce, &envelope).finalize().into_bytes());
+        let packet = GossipPacket {
+            sent_at_ms: now_ms,
+            nonce,
+            envelope,
+            mac,
+        };
+        serde_json::to_vec(&packet).map_err(|e| e.to_string())
+    }
+
+    /// The envelope of `datagram` when its MAC matches, it was sealed within
+    /// [`GOSSIP_MAX_SKEW`] of `now_ms` and its nonce has not been seen.
+    fn open(&self, datagram: &[u8], now_ms: u64) -> Option<SyncEnvelope> {
+        let packet: GossipPacket = serde_json::from_slice(datagram).ok()?;
+        let skew_ms = millis(GOSSIP_MAX_SKEW);
+        if packet.sent_at_ms.abs_diff(now_ms) > skew_ms {
+            return None;
+        }
+        let mac = hex::decode(&packet.mac).ok()?;
+        self.mac(packet.sent_at_ms, &packet.nonce, &packet.envelope)
+            .verify_slice(&mac)
+            .ok()?;
+        {
+            let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+            seen.retain(|_, forget_at| *forget_at > now_ms);
+            let forget_at = packet.sent_at_ms + skew_ms;
+            if seen.insert(packet.nonce, forget_at).is_some() {
+                return None;
+            }
+        }
+        serde_json::from_str(&packet.envelope).ok()
+    }
+}
+
+/// Configured gossip peers, resolved at most every
+/// [`PEER_REFRESH_INTERVAL`].
+struct GossipPeers {
+    names: Vec<String>,
+    resolved: Mutex<(Option<Instant>, Vec<SocketAddr>)>,
+}
+
+impl GossipPeers {
+    /// Peer addresses other than `own`.
+    async fn current(&self, own: Option<SocketAddr>) -> Vec<SocketAddr> {
+        {
+            let resolved = self.resolved.lock().unwrap_or_else(|e| e.into_inner());
+            if resolved
+                .0
+                .is_some_and(|at| at.elapsed() < PEER_REFRESH_INTERVAL)
+            {
+                return resolved.1.clone();
+            }
+        }
+        let mut addrs = Vec::new();
+        for name in &self.names {
+            match tokio::net::lookup_host(name.as_str()).await {
+                Ok(found) => addrs.extend(found),
+                Err(e) => {
+                    tracing::warn!(peer = %name, error = %e, "State sync peer did not resolve")
+                }
+            }
+        }
+        if let Some(own) = own {
+            addrs.retain(|addr| *addr != own);
+        }
+        let mut resolved = self.resolved.lock().unwrap_or_else(|e| e.into_inner());
+        *resolved = (Some(Instant::now()), addrs.clone());
+        addrs
+    }
+}
+
+/// Redis-less transport: every envelope is sent as one UDP datagram to each
+/// peer. Peers are `host:port` names re-resolved every 30 s, so a DNS name
+/// listing all replicas (e.g. a headless service) can be used.
+///
+/// Datagrams carry an HMAC-SHA256 under the shared secret, a timestamp and a
+/// nonce; forged, stale and replayed ones are dropped, as is anything not
+/// sent from a configured peer address. Datagrams are not encrypted, so
+/// keep the port on a private network.
+pub struct GossipSyncTransport {
+    socket: Arc<UdpSocket>,
+    peers: Arc<GossipPeers>,
+    auth: Arc<GossipAuth>,
+}
+
+impl GossipSyncTransport {
+    /// Bind `addr`, sending to and accepting from `peer_names`. Fails when
+    /// `secret` is empty.
+    pub async fn bind(addr: &str, peer_names: Vec<String>, secret: String) -> Result<Self, String> {
+        if secret.is_empty() {
+            return Err("state sync gossip needs a shared secret".to_string());
+        }
+        let socket = UdpSocket::bind(addr).await.map_err(|e| e.to_string())?;
+        Ok(Self {
+            socket: Arc::new(socket),
+            peers: Arc::new(GossipPeers {
+                names: peer_names,
+                resolved: Mutex::new((None, Vec::new())),
+            }),
+            auth: Arc::new(GossipAuth::new(secret)),
+        })
+    }
+
+    /// Address the socket is bound to.
+    pub fn local_addr(&self) -> Result<SocketAddr, String> {
+        self.socket.local_addr().map_err(|e| e.to_string())
+    }
+}
+
+#[async_trait::async_trait]
+impl SyncTransport for GossipSyncTransport {
+    async fn publish(&self, envelope: &SyncEnvelope) -> Result<(), String> {
+        let payload = self.auth.seal(envelope, now_ms())?;
+        if payload.len() > MAX_DATAGRAM_BYTES {
+            return Err(format!(
+                "state sync envelope of {} bytes exceeds the datagram limit",
+                payload.len()
+            ));
+        }
+        for peer in self.peers.current(self.socket.local_addr().ok()).await {
+            if let Err(e) = self.socket.send_to(&payload, peer).await {
+                tracing::debug!(%peer, error = %e, "State sync datagram not sent");
+            }
+        }
+        Ok(())
+    }
+
+    async fn subscribe(&self) -> Result<mpsc::Receiver<SyncEnvelope>, String> {
+        let socket = self.socket.clone();
+        let peers = self.peers.clone();
+        let auth = self.auth.clone();
+        let (tx, inbox) = mpsc::channel(OUTBOX_CAPACITY);
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; MAX_DATAGRAM_BYTES];
+            loop {
+                let (len, from) = match socket.recv_from(&mut buf).await {
+                    Ok(received) => received,
+                    Err(e) => {
+                        tracing::debug!(error = %e, "State sync datagram not received");
+                        continue;
+                    }
+                };
+                if !peers
+                    .current(socket.local_addr().ok())
+                    .await
+                    .contains(&from)
+                {
+                    tracing::debug!(%from, "State sync datagram from unknown address dropped");
+                    continue;
+                }
+                let Some(envelope) = auth.open(&buf[..len], now_ms()) else {
+                    tracing::debug!(%from, "State sync datagram failed authentication");
+                    continue;
+                };
+                if tx.send(envelope).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(inbox)
+    }
+}
+
+/// Build the transport selected by `BURNCLOUD_STATE_SYNC`: `redis` (using
+/// `REDIS_URL`) or `gossip` (binding `BURNCLOUD_STATE_SYNC_BIND`, sending to
+/// the comma-separated `BURNCLOUD_STATE_SYNC_PEERS`, authenticated by
+/// `BURNCLOUD_STATE_SYNC_SECRET`, which gossip requires). `None` when unset,
+/// `off`, or the transport could not be set up.
+pub async fn transport_from_env() -> Option<Arc<dyn SyncTransport>> {
+    let choice = std::env::var("BURNCLOUD_STATE_SYNC").unwrap_or_default();
+    if choice.eq_ignore_ascii_case("redis") {
+        let Ok(url) = std::env::var("REDIS_URL") else {
+            tracing::warn!(
+                "BURNCLOUD_STATE_SYNC=redis but REDIS_URL is not set, state sync disabled"
+            );
+            return None;
+        };
+        return match RedisSyncTransport::connect(&url).await {
+            Ok(transport) => {
+                tracing::info!("Router state synced through Redis pub/sub");
+                Some(Arc::new(transport))
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "State sync Redis unavailable, state sync disabled");
+                None
+            }
+        };
+    }
+    if choice.eq_ignore_ascii_case("gossip") {
+        let bind = std::env::var("BURNCLOUD_STATE_SYNC_BIND")
+            .unwrap_or_else(|_| DEFAULT_GOSSIP_BIND.to_string());
+        let peers: Vec<String> = std::env::var("BURNCLOUD_STATE_SYNC_PEERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .map(str::to_string)
+            .collect();
+        if peers.is_empty() {
+            tracing::warn!("BURNCLOUD_STATE_SYNC=gossip but BURNCLOUD_STATE_SYNC_PEERS is empty");
+        }
+        let Some(secret) = std::env::var("BURNCLOUD_STATE_SYNC_SECRET")
+            .ok()
+            .filter(|s| !s.is_empty())
+        else {
+            tracing::warn!(
+                "BURNCLOUD_STATE_SYNC=gossip but BURNCLOUD_STATE_SYNC_SECRET is not set, state sync disabled"
+            );
+            return None;
+        };
+        return match GossipSyncTransport::bind(&bind, peers, secret).await {
+            Ok(transport) => {
+                tracing::info!(%bind, "Router state synced through UDP gossip");
+                Some(Arc::new(transport))
+            }
+            Err(e) => {
+                tracing::warn!(%bind, error = %e, "State sync gossip bind failed, state sync disabled");
+                None
+            }
+        };
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit_breaker::{FailureType, RateLimitScope};
+    use crate::smart_circuit_breaker::TripLevel;
+
+    /// One router instance's shareable state, synced over `bus`.
+    fn instance(bus: &LocalSyncBus) -> SyncTargets {
+        let targets = SyncTargets {
+            circuit_breaker: Arc::new(CircuitBreaker::new(3, 30)),
+            channel_state_tracker: Arc::new(ChannelStateTracker::new()),
+            channel_health_manager: Arc::new(ChannelHealthManager::new()),
+            affinity_cache: Arc::new(AffinityCache::default()),
+            budget_update_tx: None,
+        };
+        start(
+            Arc::new(bus.clone()),
+            targets.clone(),
+            SyncConfig {
+                snapshot_interval: Duration::from_millis(100),
+                max_staleness: Duration::from_secs(5),
+            },
+        );
+        targets
+    }
+
+    async fn eventually(check: impl Fn() -> bool) -> bool {
+        for _ in 0..100 {
+            if check() {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        false
+    }
+
+    #[tokio::test]
+    async fn test_breaker_trip_and_recovery_reach_every_instance() {
+        let bus = LocalSyncBus::new();
+        let nodes: Vec<SyncTargets> = (0..3).map(|_| instance(&bus)).collect();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        for _ in 0..3 {
+            nodes[0]
+                .circuit_breaker
+                .record_failure_with_type("up-1", FailureType::ServerError);
+        }
+        assert!(!nodes[0].circuit_breaker.allow_request("up-1"));
+        assert!(
+            eventually(|| nodes
+                .iter()
+                .all(|n| !n.circuit_breaker.allow_request("up-1")))
+            .await
+        );
+
+        nodes[1].circuit_breaker.record_success("up-1");
+        assert!(
+            eventually(|| nodes
+                .iter()
+                .all(|n| n.circuit_breaker.allow_request("up-1")))
+            .await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_aimd_limits_follow_rate_limits_elsewhere() {
+        let bus = LocalSyncBus::new();
+        let a = instance(&bus);
+        let (budget_tx, mut budget_rx) = mpsc::channel(1);
+        let b = SyncTargets {
+            budget_update_tx: Some(budget_tx),
+            ..instance(&bus)
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        a.channel_state_tracker.record_error(
+            7,
+            Some("gpt-4o"),
+            &FailureType::RateLimited {
+                scope: RateLimitScope::Model,
+                retry_after: Some(30),
+            },
+            "429",
+        );
+        let limit = |t: &SyncTargets| {
+            t.channel_state_tracker
+                .get_health_and_adaptive(7, "gpt-4o")
+                .1
+                .current_limit
+        };
+        assert_eq!(limit(&a), 8);
+        assert!(eventually(|| limit(&b) == 8).await);
+
+        // A learned upstream limit also reconfigures the remote shaper budget
+        b.apply(SyncEvent::AimdLimits {
+            channel_id: 7,
+            model: "gpt-4o".into(),
+            current_limit: 8,
+            learned_limit: Some(120),
+            cooldown_ms: None,
+        });
+        let update = budget_rx
+            .try_recv()
+            .unwrap_or_else(|e| panic!("no budget update: {e}"));
+        assert_eq!((update.channel_id, update.learned_limit), (7, 120));
+    }
+
+    #[tokio::test]
+    async fn test_affinity_entries_follow_requests_across_instances() {
+        let bus = LocalSyncBus::new();
+        let a = instance(&bus);
+        let b = instance(&bus);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        a.affinity_cache.insert("session-1", "gpt-4o", 42);
+        assert!(eventually(|| b.affinity_cache.lookup("session-1", "gpt-4o") == Some(42)).await);
+
+        b.affinity_cache.evict("session-1", "gpt-4o");
+        assert!(eventually(|| a.affinity_cache.lookup("session-1", "gpt-4o").is_none()).await);
+    }
+
+    #[tokio::test]
+    async fn test_late_instance_converges_from_snapshot() {
+        let bus = LocalSyncBus::new();
+        let a = instance(&bus);
+        a.channel_health_manager.apply_remote_trip(
+            3,
+            Some("claude-sonnet"),
+            "error rate",
+            Duration::from_secs(60),
+        );
+        a.affinity_cache
+            .apply_remote_set("session-2", "claude-sonnet", 9, Duration::ZERO);
+
+        // Joins after the changes were made; only snapshots can catch it up
+        let late = instance(&bus);
+        assert!(
+            eventually(|| matches!(
+                late.channel_health_manager
+                    .check_availability(3, "claude-sonnet"),
+                TripLevel::Model { .. }
+            ))
+            .await
+        );
+        assert!(
+            eventually(|| late.affinity_cache.lookup("session-2", "claude-sonnet") == Some(9))
+                .await
+        );
+
+        late.channel_health_manager.reset_all();
+        assert!(
+            eventually(|| a
+                .channel_health_manager
+                .check_availability(3, "claude-sonnet")
+                == TripLevel::None)
+            .await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stale_and_own_envelopes_are_ignored() {
+        let bus = LocalSyncBus::new();
+        let a = instance(&bus);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let trip = |upstream_id: &str| SyncEvent::BreakerOpened {
+            upstream_id: upstream_id.into(),
+            open_ms: 60_000,
+        };
+        let stale = SyncEnvelope {
+            origin: "other".into(),
+            sent_at_ms: now_ms() - 60_000,
+            events: vec![trip("stale")],
+        };
+        let fresh = SyncEnvelope {
+            origin: "other".into(),
+            sent_at_ms: now_ms(),
+            events: vec![trip("fresh")],
+        };
+        bus.publish(&stale).await.unwrap_or_default();
+        bus.publish(&fresh).await.unwrap_or_default();
+
+        assert!(eventually(|| !a.circuit_breaker.allow_request("fresh")).await);
+        assert!(a.circuit_breaker.allow_request("stale"));
+    }
+
+    #[test]
+    fn test_gossip_auth_rejects_forged_stale_and_replayed_packets() {
+        let auth = GossipAuth::new("s3cret".into());
+        let envelope = SyncEnvelope {
+            origin: "peer".into(),
+            sent_at_ms: 1_000_000,
+            events: vec![SyncEvent::HealthReset { channel_id: None }],
+        };
+        let now = 1_000_000;
+        let sealed = auth
+            .seal(&envelope, now)
+            .unwrap_or_else(|e| panic!("seal failed: {e}"));
+
+        let forger = GossipAuth::new("wrong".into());
+        let forged = forger
+            .seal(&envelope, now)
+            .unwrap_or_else(|e| panic!("seal failed: {e}"));
+        assert!(auth.open(&forged, now).is_none());
+
+        let mut tampered: GossipPacket =
+            serde_json::from_slice(&sealed).unwrap_or_else(|e| panic!("bad packet: {e}"));
+        tampered.envelope = tampered.envelope.replace("peer", "evil");
+        let tampered = serde_json::to_vec(&tampered).unwrap_or_default();
+        assert!(auth.open(&tampered, now).is_none());
+
+        let late = now + millis(GOSSIP_MAX_SKEW) + 1;
+        assert!(auth.open(&sealed, late).is_none());
+
+        let opened = auth.open(&sealed, now + 10);
+        assert_eq!(opened.map(|e| e.origin), Some("peer".to_string()));
+        // The same datagram again is a replay
+        assert!(auth.open(&sealed, now + 20).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_gossip_transport_accepts_only_signed_packets_from_peers() {
+        // Reserve ports so receiver and senders can list each other
+        let free_addr = || {
+            std::net::UdpSocket::bind("127.0.0.1:0")
+                .and_then(|socket| socket.local_addr())
+                .unwrap_or_else(|e| panic!("no free port: {e}"))
+        };
+        let (receiver_addr, peer_addr, forger_addr) = (free_addr(), free_addr(), free_addr());
+        let listed = vec![peer_addr.to_string(), forger_addr.to_string()];
+        let receiver =
+            GossipSyncTransport::bind(&receiver_addr.to_string(), listed, "s3cret".into())
+                .await
+                .unwrap_or_else(|e| panic!("bind failed: {e}"));
+        let mut inbox = receiver
+            .subscribe()
+            .await
+            .unwrap_or_else(|e| panic!("subscribe failed: {e}"));
+
+        let envelope = |origin: &str| SyncEnvelope {
+            origin: origin.into(),
+            sent_at_ms: now_ms(),
+            events: vec![SyncEvent::HealthReset { channel_id: None }],
+        };
+        let senders = [
+            (forger_addr.to_string(), "wrong", "forger"),
+            ("127.0.0.1:0".to_string(), "s3cret", "outsider"),
+            (peer_addr.to_string(), "s3cret", "peer"),
+        ];
+        for (addr, secret, origin) in senders {
+            let sender =
+                GossipSyncTransport::bind(&addr, vec![receiver_addr.to_string()], secret.into())
+                    .await
+                    .unwrap_or_else(|e| panic!("bind failed: {e}"));
+            sender
+                .publish(&envelope(origin))
+                .await
+                .unwrap_or_else(|e| panic!("publish failed: {e}"));
+        }
+
+        // The forger's and the outsider's datagrams were sent first but dropped
+        let received = tokio::time::timeout(Duration::from_secs(2), inbox.recv())
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| panic!("no envelope received"));
+        assert_eq!(received.origin, "peer");
+        assert_eq!(received.events, envelope("peer").events);
+        assert!(
+            GossipSyncTransport::bind("127.0.0.1:0", vec![], String::new())
+                .await
+                .is_err()
+        );
+    }
+}
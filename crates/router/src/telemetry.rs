@@ -0,0 +1,65 @@
+//! W3C trace-context propagation for proxied requests.
+//!
+//! Spans are plain `tracing` spans; when the server installs the
+//! OpenTelemetry layer they are exported, and the helpers here continue an
+//! incoming `traceparent` and forward the active context to upstreams. With
+//! no layer installed both helpers are no-ops.
+
+use axum::http::HeaderMap;
+use opentelemetry::propagation::{Extractor, Injector};
+use opentelemetry::trace::TraceContextExt;
+use reqwest::header::{HeaderName, HeaderValue};
+use reqwest::RequestBuilder;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
+    }
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+/// Make `span` a child of the caller's trace when the request carries a
+/// valid `traceparent`. Must run before `span` is first entered.
+pub(crate) fn set_remote_parent(span: &Span, headers: &HeaderMap) {
+    let cx = opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(headers))
+    });
+    if cx.span().span_context().is_valid() {
+        if let Err(e) = span.set_parent(cx) {
+            tracing::debug!("Ignoring incoming trace context: {}", e);
+        }
+    }
+}
+
+/// Add `traceparent` (and `tracestate`) for `span` to an upstream request.
+pub(crate) fn inject_trace_context(span: &Span, req: RequestBuilder) -> RequestBuilder {
+    let mut headers = HeaderMap::new();
+    opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&span.context(), &mut HeaderInjector(&mut headers))
+    });
+    if headers.is_empty() {
+        req
+    } else {
+        req.headers(headers)
+    }
+}
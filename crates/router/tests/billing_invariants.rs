@@ -45,6 +45,9 @@ fn test_log(user_id: &str) -> RouterLog {
         traffic_color: None,
         cost_status: Some("ok".to_string()),
         error_type: None,
+        token_hash: None,
+        served_model: None,
+        currency: None,
         created_at: None,
     }
 }
@@ -52,6 +52,9 @@ fn make_log(cost_status: Option<&str>) -> RouterLog {
         traffic_color: None,
         cost_status: cost_status.map(|s| s.to_string()),
         error_type: None,
+        token_hash: None,
+        served_model: None,
+        currency: None,
         created_at: None,
     }
 }
@@ -55,6 +55,9 @@ fn make_log(layer_decision: Option<&str>, traffic_color: Option<&str>) -> Router
         traffic_color: traffic_color.map(|s| s.to_string()),
         cost_status: None,
         error_type: None,
+        token_hash: None,
+        served_model: None,
+        currency: None,
         created_at: None,
     }
 }
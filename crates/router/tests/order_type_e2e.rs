@@ -305,6 +305,9 @@ async fn t5b_layer_decision_and_traffic_color_db_roundtrip() -> anyhow::Result<(
         traffic_color: Some("Y".to_string()),
         cost_status: None,
         error_type: None,
+        token_hash: None,
+        served_model: None,
+        currency: None,
         created_at: None,
     };
     RouterDatabase::insert_log(&db, &log).await?;
@@ -512,6 +515,9 @@ async fn t5c_affinity_hit_e2e_observability() -> anyhow::Result<()> {
         traffic_color,
         cost_status: None,
         error_type: None,
+        token_hash: None,
+        served_model: None,
+        currency: None,
         created_at: None,
     };
     RouterDatabase::insert_log(&db_arc, &log).await?;
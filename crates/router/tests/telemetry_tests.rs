@@ -0,0 +1,168 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::disallowed_types)]
+
+//! OpenTelemetry spans for proxied requests, checked with the SDK's
+//! in-memory exporter. Installs a process-global subscriber, so this file
+//! holds a single test.
+
+mod common;
+
+use burncloud_database::sqlx;
+use common::{insert_router_token, insert_test_channel, setup_db, start_test_server};
+use opentelemetry::trace::{SpanId, TraceId, TracerProvider as _};
+use opentelemetry::Value;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider, SpanData};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing_subscriber::layer::SubscriberExt;
+
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+const PARENT_SPAN_ID: &str = "00f067aa0ba902b7";
+
+/// OpenAI-compatible upstream that records the `traceparent` it receives.
+async fn start_upstream(seen: Arc<Mutex<Vec<String>>>) -> anyhow::Result<String> {
+    let handler = move |headers: axum::http::HeaderMap| {
+        let seen = seen.clone();
+        async move {
+            if let Some(tp) = headers.get("traceparent").and_then(|v| v.to_str().ok()) {
+                seen.lock().unwrap().push(tp.to_string());
+            }
+            axum::Json(serde_json::json!({
+                "id": "chatcmpl-otel",
+                "object": "chat.completion",
+                "model": "otel-model",
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "hi"},
+                    "finish_reason": "stop"
+                }],
+                "usage": {"prompt_tokens": 7, "completion_tokens": 3, "total_tokens": 10}
+            }))
+        }
+    };
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(async move {
+        axum::serve(listener, axum::Router::new().fallback(handler))
+            .await
+            .unwrap_or_else(|e| panic!("mock upstream failed: {e}"));
+    });
+    Ok(format!("http://{addr}"))
+}
+
+fn attr(span: &SpanData, key: &str) -> Option<Value> {
+    span.attributes
+        .iter()
+        .find(|kv| kv.key.as_str() == key)
+        .map(|kv| kv.value.clone())
+}
+
+fn find<'a>(spans: &'a [SpanData], name: &str) -> Option<&'a SpanData> {
+    spans.iter().find(|s| s.name == name)
+}
+
+#[tokio::test]
+async fn proxy_spans_are_exported_and_traceparent_is_propagated() -> anyhow::Result<()> {
+    let exporter = InMemorySpanExporter::default();
+    let provider = SdkTracerProvider::builder()
+        .with_simple_exporter(exporter.clone())
+        .build();
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("telemetry-test")));
+    tracing::subscriber::set_global_default(subscriber)?;
+
+    let (db, pool, db_url) = setup_db().await?;
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let upstream = start_upstream(seen.clone()).await?;
+    insert_test_channel(
+        &pool,
+        9_301,
+        "otel-channel",
+        &upstream,
+        "k",
+        "otel-model",
+        "default",
+    )
+    .await?;
+    sqlx::query("UPDATE channel_providers SET type = 1 WHERE id = 9301")
+        .execute(&pool)
+        .await?;
+    sqlx::query(
+        "INSERT OR REPLACE INTO billing_prices (model, currency, input_price, output_price, region) \
+         VALUES ('otel-model', 'USD', 1, 1, '')",
+    )
+    .execute(&pool)
+    .await?;
+    insert_router_token(&db, "sk-otel-test", "u-otel", "default", None, None).await?;
+    sqlx::query("UPDATE user_api_keys SET remain_quota = -1 WHERE key = 'sk-otel-test'")
+        .execute(&pool)
+        .await?;
+
+    let port = 14_761_u16;
+    start_test_server(port, &db_url).await;
+
+    let resp = reqwest::Client::new()
+        .post(format!("http://127.0.0.1:{port}/v1/chat/completions"))
+        .header("Authorization", "Bearer sk-otel-test")
+        .header("traceparent", format!("00-{TRACE_ID}-{PARENT_SPAN_ID}-01"))
+        .json(&serde_json::json!({
+            "model": "otel-model",
+            "messages": [{"role": "user", "content": "hello"}]
+        }))
+        .send()
+        .await?;
+    assert_eq!(resp.status(), 200);
+    resp.text().await?;
+
+    // Billing and log writes finish on background tasks
+    let mut spans = Vec::new();
+    for _ in 0..50 {
+        spans = exporter.get_finished_spans()?;
+        let done = ["proxy_handler", "billing_settle"]
+            .iter()
+            .all(|name| find(&spans, name).is_some())
+            && spans.iter().filter(|s| s.name == "log_write").count() >= 1;
+        if done {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    let trace_id = TraceId::from_hex(TRACE_ID)?;
+    let handler = find(&spans, "proxy_handler").expect("proxy_handler span");
+    assert_eq!(handler.span_context.trace_id(), trace_id);
+    assert_eq!(handler.parent_span_id, SpanId::from_hex(PARENT_SPAN_ID)?);
+    assert_eq!(attr(handler, "model"), Some(Value::from("otel-model")));
+    assert_eq!(attr(handler, "channel_id"), Some(Value::from("9301")));
+    assert_eq!(attr(handler, "prompt_tokens"), Some(Value::I64(7)));
+    assert_eq!(attr(handler, "completion_tokens"), Some(Value::I64(3)));
+
+    let attempt = find(&spans, "proxy_attempt").expect("proxy_attempt span");
+    assert_eq!(attempt.parent_span_id, handler.span_context.span_id());
+    assert_eq!(attr(attempt, "channel_id"), Some(Value::from("9301")));
+    assert_eq!(attr(attempt, "attempt"), Some(Value::I64(1)));
+    assert_eq!(attr(attempt, "http.status_code"), Some(Value::I64(200)));
+    assert!(attr(attempt, "routing_decision").is_some());
+    assert_eq!(
+        attr(handler, "routing_decision"),
+        attr(attempt, "routing_decision")
+    );
+
+    for name in ["billing_settle", "log_write"] {
+        let span = find(&spans, name).unwrap_or_else(|| panic!("{name} span"));
+        assert_eq!(span.span_context.trace_id(), trace_id, "{name}");
+    }
+
+    // The upstream sees the attempt span as its parent
+    let forwarded = seen.lock().unwrap().clone();
+    assert_eq!(
+        forwarded,
+        vec![format!(
+            "00-{TRACE_ID}-{}-01",
+            attempt.span_context.span_id()
+        )]
+    );
+
+    Ok(())
+}
@@ -0,0 +1,165 @@
+use crate::api::auth::{session_client, Claims};
+use crate::api::response::{err_status, ok};
+use crate::AppState;
+use axum::{
+    body::{Body, Bytes},
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use burncloud_service_audit::{Actor, AuditEvent, AuditLogFilter, AuditService, ExportFormat};
+use burncloud_service_router_log::parse_unix_time;
+use futures::StreamExt;
+use serde::Deserialize;
+
+/// Largest page `GET /console/api/audit` returns.
+const MAX_PAGE_SIZE: i64 = 500;
+/// Entries fetched per query while streaming an export.
+const EXPORT_BATCH: i64 = 500;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/console/api/audit", get(list_audit))
+        .route("/console/api/audit/export", get(export_audit))
+}
+
+/// Who made a request: the signed-in console user, or the owner of the
+/// management key it was made with
+pub(crate) fn actor(claims: &Claims, headers: &HeaderMap) -> Actor {
+    let ip = session_client(headers).ip;
+    match &claims.management_key {
+        Some(key) => Actor::management_key(
+            &claims.sub,
+            &format!("{} (key {})", claims.username, key.name),
+            ip,
+        ),
+        None => Actor::console(&claims.sub, &claims.username, ip),
+    }
+}
+
+/// Record an administrative change made by the signed-in console user.
+/// The change has already been applied, so a failed write is only logged.
+pub(crate) async fn record(
+    state: &AppState,
+    claims: &Claims,
+    headers: &HeaderMap,
+    event: AuditEvent,
+) {
+    AuditService::record_or_log(&state.db, &actor(claims, headers), event).await;
+}
+
+#[derive(Deserialize)]
+struct AuditFilterParams {
+    actor: Option<String>,
+    /// Exact action, or a prefix such as `token.`
+    action: Option<String>,
+    target_type: Option<String>,
+    target_id: Option<String>,
+    source: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+}
+
+impl AuditFilterParams {
+    fn into_filter(self) -> Result<AuditLogFilter, String> {
+        let time = |name: &str, value: Option<String>| match value.filter(|v| !v.is_empty()) {
+            Some(v) => parse_unix_time(&v)
+                .map(Some)
+                .ok_or_else(|| format!("invalid {name}: {v}")),
+            None => Ok(None),
+        };
+        Ok(AuditLogFilter {
+            since: time("since", self.since)?,
+            until: time("until", self.until)?,
+            actor: self.actor,
+            action: self.action,
+            target_type: self.target_type,
+            target_id: self.target_id,
+            source: self.source,
+            before_id: None,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct PageParams {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct ExportParams {
+    /// `csv` (default) or `jsonl`
+    format: Option<String>,
+}
+
+/// Entries matching the filters, newest first, with the total match count.
+async fn list_audit(
+    State(state): State<AppState>,
+    Query(page): Query<PageParams>,
+    Query(filters): Query<AuditFilterParams>,
+) -> Response {
+    let filter = match filters.into_filter() {
+        Ok(filter) => filter,
+        Err(e) => return err_status(StatusCode::BAD_REQUEST, e).into_response(),
+    };
+    let limit = page.limit.unwrap_or(50).clamp(1, MAX_PAGE_SIZE);
+    let offset = page.offset.unwrap_or(0).max(0);
+    match AuditService::list(&state.db, &filter, limit, offset).await {
+        Ok(page) => ok(page).into_response(),
+        Err(e) => err_status(StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+/// Stream every entry matching the filters as CSV or JSON Lines, newest first.
+async fn export_audit(
+    State(state): State<AppState>,
+    Query(params): Query<ExportParams>,
+    Query(filters): Query<AuditFilterParams>,
+) -> Response {
+    let format_name = params.format.unwrap_or_else(|| "csv".to_string());
+    let Some(format) = ExportFormat::from_name(&format_name) else {
+        let message = format!("unsupported format: {format_name} (expected csv or jsonl)");
+        return err_status(StatusCode::BAD_REQUEST, message).into_response();
+    };
+    let filter = match filters.into_filter() {
+        Ok(filter) => filter,
+        Err(e) => return err_status(StatusCode::BAD_REQUEST, e).into_response(),
+    };
+
+    let header_chunk = format
+        .header()
+        .map(|h| Ok::<_, std::io::Error>(Bytes::from(h)));
+    let rows = futures::stream::try_unfold(Some(None), move |cursor: Option<Option<i64>>| {
+        let db = state.db.clone();
+        let mut filter = filter.clone();
+        async move {
+            let Some(before_id) = cursor else {
+                return Ok(None);
+            };
+            filter.before_id = before_id;
+            let entries = AuditService::entries(&db, &filter, EXPORT_BATCH)
+                .await
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+            if entries.is_empty() {
+                return Ok(None);
+            }
+            let next = (entries.len() as i64 == EXPORT_BATCH).then(|| entries.last().map(|e| e.id));
+            let mut chunk = String::new();
+            for entry in &entries {
+                chunk.push_str(&format.row(entry).map_err(std::io::Error::other)?);
+            }
+            Ok(Some((Bytes::from(chunk), next)))
+        }
+    });
+    let body = futures::stream::iter(header_chunk).chain(rows);
+
+    let disposition = format!("attachment; filename=\"audit.{}\"", format.extension());
+    Response::builder()
+        .header(header::CONTENT_TYPE, format.content_type())
+        .header(header::CONTENT_DISPOSITION, disposition)
+        .body(Body::from_stream(body))
+        .unwrap_or_else(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response())
+}
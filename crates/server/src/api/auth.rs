@@ -1,17 +1,28 @@
-use crate::api::response::{err, ok};
+use crate::api::response::{err, err_status, ok};
 use crate::AppState;
 use axum::{
     body::Body,
-    extract::{Json, State},
-    http::{Method, Request, StatusCode},
+    extract::{ConnectInfo, Extension, Json, MatchedPath, Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, Method, Request, StatusCode},
     middleware::Next,
-    response::{IntoResponse, Response},
-    routing::{get, post},
+    response::{IntoResponse, Redirect, Response},
+    routing::{delete, get, post},
     Router,
 };
-use burncloud_service_user::UserServiceError;
+use burncloud_service_audit::{AuditEvent, AuditService};
+use burncloud_service_user::{
+    management_key::is_management_key, IpRange, LoginChallenge, ManagementKeyService,
+    OAuthAuthorization, OAuthService, Permission, PermissionSet, RoleService, SessionClient,
+    SessionTokens, UserService, UserServiceError, UserSession,
+};
 use jsonwebtoken::{decode, DecodingKey, Validation};
 use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::OnceLock;
+
+/// Request header carrying the client address resolved by
+/// [`client_address_middleware`]; whatever the client sent in it is dropped
+const CLIENT_ADDRESS_HEADER: &str = "x-real-ip";
 
 #[derive(Deserialize)]
 pub struct RegisterDto {
@@ -37,12 +48,69 @@ pub struct ResetPasswordDto {
     pub new_password: String,
 }
 
+#[derive(Deserialize)]
+pub struct RefreshDto {
+    pub refresh_token: String,
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordDto {
+    /// May be empty for accounts created through OAuth that have no password yet
+    #[serde(default)]
+    pub current_password: String,
+    pub new_password: String,
+}
+
+#[derive(Deserialize)]
+pub struct TwoFactorLoginDto {
+    pub challenge: String,
+    /// TOTP or recovery code
+    pub code: String,
+}
+
+#[derive(Deserialize)]
+pub struct TwoFactorCodeDto {
+    pub code: String,
+}
+
+#[derive(Deserialize)]
+pub struct OAuthCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    /// Set by the provider when the user denied access
+    pub error: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub sub: String,
     pub username: String,
     pub exp: usize,
     pub iat: usize,
+    /// Console session of the token, absent on sessionless tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    /// Set by `auth_middleware` when the request carries a management key
+    /// instead of a JWT
+    #[serde(skip)]
+    pub management_key: Option<KeyScope>,
+}
+
+/// Management key a request was made with
+#[derive(Debug, Clone)]
+pub struct KeyScope {
+    pub id: String,
+    pub name: String,
+    /// Permissions the key may use, on top of what its owner holds
+    pub scopes: PermissionSet,
+}
+
+/// Method, path and outcome of a request made with a management key
+#[derive(Serialize)]
+struct KeyUse {
+    method: String,
+    path: String,
+    status: u16,
 }
 
 #[derive(Serialize)]
@@ -51,7 +119,197 @@ struct AuthData {
     username: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     roles: Option<Vec<String>>,
+    /// Permission names granted by `roles`; `*` grants everything
+    #[serde(skip_serializing_if = "Option::is_none")]
+    permissions: Option<Vec<String>>,
     token: String,
+    refresh_token: String,
+    /// Access token expiry (Unix seconds); refresh before it passes
+    expires_at: i64,
+}
+
+/// Sign-in payload when the password was right but a second factor is due
+#[derive(Serialize)]
+struct TwoFactorPending {
+    two_factor_required: bool,
+    #[serde(flatten)]
+    challenge: LoginChallenge,
+}
+
+#[derive(Serialize)]
+struct SessionView {
+    #[serde(flatten)]
+    session: UserSession,
+    /// The session the request was made with
+    current: bool,
+}
+
+/// User agent and client address recorded with a new session. The address
+/// is the one [`client_address_middleware`] resolved.
+pub(crate) fn session_client(headers: &HeaderMap) -> SessionClient {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+    };
+    SessionClient {
+        user_agent: header("user-agent").map(str::to_string),
+        ip: header(CLIENT_ADDRESS_HEADER).map(str::to_string),
+    }
+}
+
+/// Proxies allowed to report the client address, from `TRUSTED_PROXIES`
+/// (comma separated addresses or CIDR ranges)
+fn trusted_proxies() -> &'static [IpRange] {
+    static PROXIES: OnceLock<Vec<IpRange>> = OnceLock::new();
+    PROXIES.get_or_init(|| {
+        std::env::var("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|range| !range.is_empty())
+            .filter_map(|range| match IpRange::parse(range) {
+                Ok(range) => Some(range),
+                Err(e) => {
+                    tracing::warn!("Ignoring TRUSTED_PROXIES entry: {}", e);
+                    None
+                }
+            })
+            .collect()
+    })
+}
+
+/// The client a request came from: the socket peer, or, when the peer is a
+/// trusted proxy, the nearest untrusted hop in `X-Forwarded-For` (falling
+/// back to `X-Real-IP`). Forwarding headers from anyone else are ignored.
+fn client_address(
+    peer: Option<IpAddr>,
+    headers: &HeaderMap,
+    trusted: &[IpRange],
+) -> Option<IpAddr> {
+    let peer = peer?;
+    let is_trusted = |ip: &IpAddr| trusted.iter().any(|range| range.contains(*ip));
+    if !is_trusted(&peer) {
+        return Some(peer);
+    }
+    let forwarded: Vec<IpAddr> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|hop| hop.trim().parse().ok())
+        .collect();
+    let real_ip = || {
+        headers
+            .get(CLIENT_ADDRESS_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse().ok())
+    };
+    Some(
+        forwarded
+            .into_iter()
+            .rev()
+            .find(|hop| !is_trusted(hop))
+            .or_else(real_ip)
+            .unwrap_or(peer),
+    )
+}
+
+/// Resolve the client address from the socket peer and pass it on in
+/// `X-Real-IP`, replacing what the client sent, so address allowlists,
+/// sessions and audit entries never trust a spoofed header.
+pub async fn client_address_middleware(mut req: Request<Body>, next: Next) -> Response {
+    let peer = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_canonical());
+    let client = client_address(peer, req.headers(), trusted_proxies());
+    let headers = req.headers_mut();
+    headers.remove(CLIENT_ADDRESS_HEADER);
+    if let Some(value) = client.and_then(|ip| HeaderValue::from_str(&ip.to_string()).ok()) {
+        headers.insert(CLIENT_ADDRESS_HEADER, value);
+    }
+    next.run(req).await
+}
+
+/// Role names and permission names of a user, for the sign-in payload
+async fn role_grants(state: &AppState, user_id: &str) -> (Vec<String>, Vec<String>) {
+    let roles = state
+        .user_service
+        .get_user_roles(&state.db, user_id)
+        .await
+        .unwrap_or_default();
+    let permissions = RoleService::permissions(&state.db, user_id)
+        .await
+        .map(|granted| granted.names())
+        .unwrap_or_default();
+    (roles, permissions)
+}
+
+/// Open a session and build the sign-in payload
+async fn issue_session(
+    state: &AppState,
+    user_id: &str,
+    username: &str,
+    headers: &HeaderMap,
+) -> Result<AuthData, Response> {
+    let tokens: SessionTokens = state
+        .user_service
+        .start_session(&state.db, user_id, username, &session_client(headers))
+        .await
+        .map_err(|e| {
+            tracing::error!(user_id, "Failed to start session: {}", e);
+            err("Failed to generate authentication token").into_response()
+        })?;
+    let (roles, permissions) = role_grants(state, user_id).await;
+    Ok(AuthData {
+        id: tokens.user_id,
+        username: tokens.username,
+        roles: Some(roles),
+        permissions: Some(permissions),
+        token: tokens.access_token,
+        refresh_token: tokens.refresh_token,
+        expires_at: tokens.expires_at,
+    })
+}
+
+/// Open a session, or hand out a login challenge when the account has
+/// two-factor authentication enabled
+async fn sign_in(state: &AppState, user_id: &str, username: &str, headers: &HeaderMap) -> Response {
+    match state
+        .user_service
+        .two_factor_enabled(&state.db, user_id)
+        .await
+    {
+        Ok(false) => {}
+        Ok(true) => {
+            return match state
+                .user_service
+                .create_login_challenge(&state.db, user_id)
+                .await
+            {
+                Ok(challenge) => ok(TwoFactorPending {
+                    two_factor_required: true,
+                    challenge,
+                })
+                .into_response(),
+                Err(e) => {
+                    tracing::error!(user_id, "Failed to create login challenge: {}", e);
+                    err("Login failed").into_response()
+                }
+            };
+        }
+        Err(e) => {
+            tracing::error!(user_id, "Failed to read two-factor status: {}", e);
+            return err("Login failed").into_response();
+        }
+    }
+    match issue_session(state, user_id, username, headers).await {
+        Ok(data) => ok(data).into_response(),
+        Err(response) => response,
+    }
 }
 
 fn get_jwt_secret() -> String {
@@ -68,39 +326,98 @@ pub fn verify_jwt(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
     Ok(token_data.claims)
 }
 
-/// Resolve whether the authenticated principal currently has the admin role.
-/// Roles are read from the database instead of trusting client-provided claims.
-pub async fn is_admin(state: &AppState, claims: &Claims) -> Result<bool, StatusCode> {
-    state
-        .user_service
-        .get_user_roles(&state.db, &claims.sub)
+/// Permissions the authenticated principal currently holds. Roles are read
+/// from the database instead of trusting client-provided claims; a
+/// management key narrows them to its scopes.
+pub async fn permissions(state: &AppState, claims: &Claims) -> Result<PermissionSet, StatusCode> {
+    let granted = RoleService::permissions(&state.db, &claims.sub)
         .await
-        .map(|roles| roles.iter().any(|role| role == "admin"))
         .map_err(|e| {
-            tracing::error!(user_id = %claims.sub, error = %e, "Failed to resolve admin role");
+            tracing::error!(user_id = %claims.sub, error = %e, "Failed to resolve permissions");
             StatusCode::INTERNAL_SERVER_ERROR
-        })
+        })?;
+    Ok(match &claims.management_key {
+        Some(key) => granted.restrict(&key.scopes),
+        None => granted,
+    })
+}
+
+/// Whether the authenticated principal currently holds `permission`
+pub async fn has_permission(
+    state: &AppState,
+    claims: &Claims,
+    permission: Permission,
+) -> Result<bool, StatusCode> {
+    permissions(state, claims)
+        .await
+        .map(|granted| granted.contains(permission))
+}
+
+/// Route template the request matched, or its path outside a router
+fn matched_route(req: &Request<Body>) -> &str {
+    req.extensions()
+        .get::<MatchedPath>()
+        .map(MatchedPath::as_str)
+        .unwrap_or_else(|| req.uri().path())
+}
+
+/// Permission the matched route declares in
+/// [`crate::api::required_permission`], and the caller. Must run in a
+/// `route_layer` so the route template is known; authentication must run
+/// outside it so `Claims` are already present.
+fn route_requirement(req: &Request<Body>) -> (Option<Permission>, Option<Claims>) {
+    let required = crate::api::required_permission(req.method(), matched_route(req));
+    (required, req.extensions().get::<Claims>().cloned())
+}
+
+/// Check `required`, or `*` when the route declares nothing and `strict`
+async fn authorize(
+    state: &AppState,
+    required: Option<Permission>,
+    claims: Option<Claims>,
+    strict: bool,
+) -> Result<(), StatusCode> {
+    if required.is_none() && !strict {
+        return Ok(());
+    }
+    let claims = claims.ok_or(StatusCode::UNAUTHORIZED)?;
+    let granted = permissions(state, &claims).await?;
+    let allowed = match required {
+        Some(permission) => granted.contains(permission),
+        None => granted.is_unrestricted(),
+    };
+    if allowed {
+        Ok(())
+    } else {
+        tracing::debug!(user_id = %claims.sub, ?required, "Permission denied");
+        Err(StatusCode::FORBIDDEN)
+    }
 }
 
-/// Authorization middleware for administrator-only Console routes.
-/// Authentication must run outside this layer so `Claims` are already present.
+/// Authorization middleware for the administrative Console routes: every
+/// route needs its declared permission, or `*` when it declares none.
 #[tracing::instrument(skip_all)]
 pub async fn admin_middleware(
     State(state): State<AppState>,
     req: Request<Body>,
     next: Next,
 ) -> Result<Response, StatusCode> {
-    let claims = req
-        .extensions()
-        .get::<Claims>()
-        .cloned()
-        .ok_or(StatusCode::UNAUTHORIZED)?;
+    let (required, claims) = route_requirement(&req);
+    authorize(&state, required, claims, true).await?;
+    Ok(next.run(req).await)
+}
 
-    if is_admin(&state, &claims).await? {
-        Ok(next.run(req).await)
-    } else {
-        Err(StatusCode::FORBIDDEN)
-    }
+/// Authorization middleware for self-service Console routes: only routes
+/// with a declared permission are checked.
+#[tracing::instrument(skip_all)]
+pub async fn permission_middleware(
+    State(state): State<AppState>,
+    req: Request<Body>,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let (required, claims) = route_requirement(&req);
+    authorize(&state, required, claims, false).await?;
+    Ok(next.run(req).await)
 }
 
 fn is_data_plane_path(path: &str) -> bool {
@@ -118,6 +435,42 @@ fn is_sensitive_internal_mutation(method: &Method, path: &str) -> bool {
         )
 }
 
+/// Internal endpoint a Console route forwards to, if any
+fn proxied_internal_path(path: &str) -> Option<&'static str> {
+    match path {
+        "/console/api/monitor/security/emergency-circuit-break" => {
+            Some("/console/internal/circuit-breaker/trip-all")
+        }
+        _ => None,
+    }
+}
+
+/// Administrative changes to upstream keys, access control and traffic
+/// guardrails that need the same step-up as the internal mutations
+const STEP_UP_ROUTES: &[(Method, &str)] = &[
+    (Method::POST, "/console/api/channel"),
+    (Method::PUT, "/console/api/channel"),
+    (Method::DELETE, "/console/api/channel/{id}"),
+    (Method::PUT, "/console/api/monitor/security/filters"),
+    (Method::POST, "/console/api/roles"),
+    (Method::PUT, "/console/api/roles/{name}"),
+    (Method::DELETE, "/console/api/roles/{name}"),
+    (Method::POST, "/console/api/user/roles"),
+    (Method::POST, "/console/api/user/2fa-policy"),
+    (Method::POST, "/console/api/user/2fa-reset"),
+];
+
+/// Console mutations that need a fresh TOTP code (`X-TOTP-Code`) from users
+/// with two-factor authentication enabled. Management keys can't present
+/// one and are refused on them. `route` is the matched route template.
+fn requires_step_up(method: &Method, route: &str) -> bool {
+    proxied_internal_path(route)
+        .is_some_and(|internal| is_sensitive_internal_mutation(method, internal))
+        || STEP_UP_ROUTES
+            .iter()
+            .any(|(m, r)| m == method && *r == route)
+}
+
 /// Enforce the trust boundary between the management plane, data plane, and
 /// sensitive internal control-plane mutations.
 ///
@@ -171,8 +524,8 @@ pub async fn security_boundary_middleware(
                     .and_then(|header| header.to_str().ok())
             });
 
-        if credential.is_some_and(|token| verify_jwt(token).is_ok()) {
-            tracing::warn!(path, "Rejected Console JWT on data-plane route");
+        if credential.is_some_and(|token| is_management_key(token) || verify_jwt(token).is_ok()) {
+            tracing::warn!(path, "Rejected Console credential on data-plane route");
             return Err(StatusCode::UNAUTHORIZED);
         }
     }
@@ -185,31 +538,57 @@ pub async fn security_boundary_middleware(
 /// - /api/auth/login - Login
 /// - /api/auth/forgot-password - Forgot password
 /// - /api/auth/reset-password - Reset password
-/// - /api/auth/google - Google OAuth
-/// - /api/auth/github - GitHub OAuth
+/// - /api/auth/{provider} - Start OAuth sign-in (google, github, oidc)
+/// - /console/api/auth/{provider}/callback - OAuth redirect target
+/// - /api/auth/refresh - Rotate a refresh token into a new token pair
+/// - /api/auth/login/2fa - Answer a login challenge with a TOTP or recovery code
 pub fn public_routes() -> Router<AppState> {
     Router::new()
         .route("/api/auth/register", post(create_user))
         .route("/api/auth/login", post(login))
+        .route("/api/auth/login/2fa", post(login_two_factor))
+        .route("/api/auth/refresh", post(refresh))
         .route("/api/auth/forgot-password", post(forgot_password))
         .route("/api/auth/reset-password", post(reset_password))
-        .route("/api/auth/google", get(oauth_google))
-        .route("/api/auth/github", get(oauth_github))
+        .route("/api/auth/{provider}", get(oauth_start))
+        .route("/console/api/auth/{provider}/callback", get(oauth_callback))
 }
 
 /// Protected routes - authentication required
-/// Currently empty, but available for future protected auth endpoints
-/// (e.g., logout, change-password)
+/// - /console/api/auth/identities - Providers linked to the caller
+/// - /console/api/auth/{provider}/link - Link (POST) or unlink (DELETE) a provider
+/// - /console/api/auth/logout - Revoke the current session
+/// - /console/api/auth/logout-all - Revoke every session of the caller
+/// - /console/api/auth/sessions - Active sessions, DELETE `/{id}` revokes one
+/// - /console/api/auth/change-password - Change password and sign out everywhere else
+/// - /console/api/auth/2fa - Two-factor status; `/setup`, `/enable`, `/disable`
+///   and `/recovery-codes` manage the enrollment
 pub fn protected_routes() -> Router<AppState> {
     Router::new()
-    // Add protected auth routes here when needed:
-    // .route("/console/api/auth/logout", post(logout))
-    // .route("/console/api/auth/change-password", post(change_password))
+        .route("/console/api/auth/logout", post(logout))
+        .route("/console/api/auth/logout-all", post(logout_all))
+        .route("/console/api/auth/sessions", get(list_sessions))
+        .route("/console/api/auth/sessions/{id}", delete(revoke_session))
+        .route("/console/api/auth/change-password", post(change_password))
+        .route("/console/api/auth/2fa", get(two_factor_status))
+        .route("/console/api/auth/2fa/setup", post(two_factor_setup))
+        .route("/console/api/auth/2fa/enable", post(two_factor_enable))
+        .route("/console/api/auth/2fa/disable", post(two_factor_disable))
+        .route(
+            "/console/api/auth/2fa/recovery-codes",
+            post(two_factor_recovery_codes),
+        )
+        .route("/console/api/auth/identities", get(oauth_identities))
+        .route(
+            "/console/api/auth/{provider}/link",
+            post(oauth_link).delete(oauth_unlink),
+        )
 }
 
 #[tracing::instrument(skip(state, payload), fields(username = %payload.username))]
 async fn create_user(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<RegisterDto>,
 ) -> impl IntoResponse {
     match state
@@ -222,29 +601,10 @@ async fn create_user(
         )
         .await
     {
-        Ok(user_id) => {
-            let roles = state
-                .user_service
-                .get_user_roles(&state.db, &user_id)
-                .await
-                .unwrap_or_default();
-            match state
-                .user_service
-                .generate_token(&user_id, &payload.username)
-            {
-                Ok(auth_token) => ok(AuthData {
-                    id: user_id,
-                    username: payload.username,
-                    roles: Some(roles),
-                    token: auth_token.token,
-                })
-                .into_response(),
-                Err(e) => {
-                    tracing::error!("JWT generation failed: {}", e);
-                    err("Failed to generate authentication token").into_response()
-                }
-            }
-        }
+        Ok(user_id) => match issue_session(&state, &user_id, &payload.username, &headers).await {
+            Ok(data) => ok(data).into_response(),
+            Err(response) => response,
+        },
         Err(UserServiceError::UserAlreadyExists) => err("Username already exists").into_response(),
         Err(e) => {
             tracing::error!("Registration error: {}", e);
@@ -254,36 +614,300 @@ async fn create_user(
 }
 
 #[tracing::instrument(skip(state, payload), fields(username = %payload.username))]
-async fn login(State(state): State<AppState>, Json(payload): Json<LoginDto>) -> impl IntoResponse {
+async fn login(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<LoginDto>,
+) -> impl IntoResponse {
     match state
         .user_service
-        .login_user(&state.db, &payload.username, &payload.password)
+        .authenticate(&state.db, &payload.username, &payload.password)
         .await
     {
-        Ok(auth_token) => {
-            let roles = state
-                .user_service
-                .get_user_roles(&state.db, &auth_token.user_id)
-                .await
-                .unwrap_or_default();
+        Ok(user) => sign_in(&state, &user.id, &user.username, &headers).await,
+        Err(UserServiceError::UserNotFound) => err("User not found").into_response(),
+        Err(UserServiceError::InvalidCredentials) => err("Invalid credentials").into_response(),
+        Err(e) => {
+            tracing::error!("Login error: {}", e);
+            err("Login failed").into_response()
+        }
+    }
+}
+
+/// Second sign-in step: a wrong or expired challenge or code is a 401
+async fn login_two_factor(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<TwoFactorLoginDto>,
+) -> impl IntoResponse {
+    match state
+        .user_service
+        .complete_login_challenge(&state.db, &payload.challenge, &payload.code)
+        .await
+    {
+        Ok(user) => match issue_session(&state, &user.id, &user.username, &headers).await {
+            Ok(data) => ok(data).into_response(),
+            Err(response) => response,
+        },
+        Err(UserServiceError::InvalidCredentials | UserServiceError::UserNotFound) => {
+            err_status(StatusCode::UNAUTHORIZED, "Invalid or expired code").into_response()
+        }
+        Err(e) => {
+            tracing::error!("Two-factor login error: {}", e);
+            err_status(StatusCode::INTERNAL_SERVER_ERROR, "Login failed").into_response()
+        }
+    }
+}
 
+async fn refresh(
+    State(state): State<AppState>,
+    Json(payload): Json<RefreshDto>,
+) -> impl IntoResponse {
+    match state
+        .user_service
+        .refresh_session(&state.db, &payload.refresh_token)
+        .await
+    {
+        Ok(tokens) => {
+            let (roles, permissions) = role_grants(&state, &tokens.user_id).await;
             ok(AuthData {
-                id: auth_token.user_id,
-                username: auth_token.username,
+                id: tokens.user_id,
+                username: tokens.username,
                 roles: Some(roles),
-                token: auth_token.token,
+                permissions: Some(permissions),
+                token: tokens.access_token,
+                refresh_token: tokens.refresh_token,
+                expires_at: tokens.expires_at,
             })
             .into_response()
         }
-        Err(UserServiceError::UserNotFound) => err("User not found").into_response(),
-        Err(UserServiceError::InvalidCredentials) => err("Invalid credentials").into_response(),
+        Err(UserServiceError::SessionRevoked) => {
+            err_status(StatusCode::UNAUTHORIZED, "Session expired or revoked").into_response()
+        }
         Err(e) => {
-            tracing::error!("Login error: {}", e);
-            err("Login failed").into_response()
+            tracing::error!("Token refresh error: {}", e);
+            err_status(StatusCode::INTERNAL_SERVER_ERROR, "Token refresh failed").into_response()
+        }
+    }
+}
+
+async fn logout(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> impl IntoResponse {
+    let Some(sid) = claims.sid else {
+        return err_status(
+            StatusCode::BAD_REQUEST,
+            "Token has no session, use logout-all to revoke it",
+        )
+        .into_response();
+    };
+    match state
+        .user_service
+        .logout(&state.db, &claims.sub, &sid)
+        .await
+    {
+        Ok(_) => ok(serde_json::json!({ "revoked": 1 })).into_response(),
+        Err(e) => {
+            tracing::error!("Logout error: {}", e);
+            err_status(StatusCode::INTERNAL_SERVER_ERROR, "Logout failed").into_response()
+        }
+    }
+}
+
+async fn logout_all(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> impl IntoResponse {
+    match state.user_service.logout_all(&state.db, &claims.sub).await {
+        Ok(revoked) => ok(serde_json::json!({ "revoked": revoked })).into_response(),
+        Err(e) => {
+            tracing::error!("Logout-all error: {}", e);
+            err_status(StatusCode::INTERNAL_SERVER_ERROR, "Logout failed").into_response()
+        }
+    }
+}
+
+async fn list_sessions(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> impl IntoResponse {
+    match state
+        .user_service
+        .list_sessions(&state.db, &claims.sub)
+        .await
+    {
+        Ok(sessions) => {
+            let views: Vec<SessionView> = sessions
+                .into_iter()
+                .map(|session| SessionView {
+                    current: claims.sid.as_deref() == Some(session.id.as_str()),
+                    session,
+                })
+                .collect();
+            ok(views).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to list sessions: {}", e);
+            err("Failed to list sessions").into_response()
+        }
+    }
+}
+
+async fn revoke_session(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match state.user_service.logout(&state.db, &claims.sub, &id).await {
+        Ok(true) => ok(serde_json::json!({ "id": id, "revoked": true })).into_response(),
+        Ok(false) => err_status(StatusCode::NOT_FOUND, "Session not found").into_response(),
+        Err(e) => {
+            tracing::error!("Failed to revoke session: {}", e);
+            err_status(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to revoke session",
+            )
+            .into_response()
+        }
+    }
+}
+
+/// Every existing session is revoked; the caller gets a fresh one back.
+async fn change_password(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Json(payload): Json<ChangePasswordDto>,
+) -> impl IntoResponse {
+    match state
+        .user_service
+        .change_password(
+            &state.db,
+            &claims.sub,
+            &payload.current_password,
+            &payload.new_password,
+        )
+        .await
+    {
+        Ok(()) => match issue_session(&state, &claims.sub, &claims.username, &headers).await {
+            Ok(data) => ok(data).into_response(),
+            Err(response) => response,
+        },
+        Err(UserServiceError::InvalidCredentials) => {
+            err_status(StatusCode::FORBIDDEN, "Current password is incorrect").into_response()
+        }
+        Err(UserServiceError::InvalidInput(msg)) => {
+            err_status(StatusCode::BAD_REQUEST, msg).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Change password error: {}", e);
+            err_status(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to change password",
+            )
+            .into_response()
         }
     }
 }
 
+fn two_factor_error(e: UserServiceError) -> Response {
+    match e {
+        UserServiceError::InvalidCredentials => {
+            err_status(StatusCode::FORBIDDEN, "Invalid two-factor code").into_response()
+        }
+        UserServiceError::InvalidInput(msg) => {
+            err_status(StatusCode::BAD_REQUEST, msg).into_response()
+        }
+        UserServiceError::PermissionDenied(msg) => {
+            err_status(StatusCode::FORBIDDEN, msg).into_response()
+        }
+        e => {
+            tracing::error!("Two-factor error: {}", e);
+            err_status(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Two-factor request failed",
+            )
+            .into_response()
+        }
+    }
+}
+
+async fn two_factor_status(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> impl IntoResponse {
+    match state
+        .user_service
+        .two_factor_status(&state.db, &claims.sub)
+        .await
+    {
+        Ok(status) => ok(status).into_response(),
+        Err(e) => two_factor_error(e),
+    }
+}
+
+/// Start (or restart) enrollment; the secret is shown once
+async fn two_factor_setup(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> impl IntoResponse {
+    match state
+        .user_service
+        .begin_two_factor_setup(&state.db, &claims.sub, &claims.username)
+        .await
+    {
+        Ok(setup) => ok(setup).into_response(),
+        Err(e) => two_factor_error(e),
+    }
+}
+
+/// Confirm enrollment with a first code, returns the recovery codes
+async fn two_factor_enable(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<TwoFactorCodeDto>,
+) -> impl IntoResponse {
+    match state
+        .user_service
+        .enable_two_factor(&state.db, &claims.sub, &payload.code)
+        .await
+    {
+        Ok(codes) => ok(serde_json::json!({ "recovery_codes": codes })).into_response(),
+        Err(e) => two_factor_error(e),
+    }
+}
+
+async fn two_factor_disable(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<TwoFactorCodeDto>,
+) -> impl IntoResponse {
+    match state
+        .user_service
+        .disable_two_factor(&state.db, &claims.sub, &payload.code)
+        .await
+    {
+        Ok(()) => ok(serde_json::json!({ "enabled": false })).into_response(),
+        Err(e) => two_factor_error(e),
+    }
+}
+
+async fn two_factor_recovery_codes(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<TwoFactorCodeDto>,
+) -> impl IntoResponse {
+    match state
+        .user_service
+        .regenerate_recovery_codes(&state.db, &claims.sub, &payload.code)
+        .await
+    {
+        Ok(codes) => ok(serde_json::json!({ "recovery_codes": codes })).into_response(),
+        Err(e) => two_factor_error(e),
+    }
+}
+
 async fn forgot_password(
     State(state): State<AppState>,
     Json(payload): Json<ForgotPasswordDto>,
@@ -294,12 +918,12 @@ async fn forgot_password(
         .await
     {
         Ok(_reset_token) => {
-            tracing::info!("Password reset token generated for {}", payload.email);
-            ok(serde_json::json!({ "message": "If the email exists, a reset token has been generated" })).into_response()
+            tracing::info!("Password reset email queued for {}", payload.email);
+            ok(serde_json::json!({ "message": "If the email exists, a reset link has been sent" })).into_response()
         }
         Err(UserServiceError::UserNotFound) => {
             // Return success even if user not found to prevent email enumeration
-            ok(serde_json::json!({ "message": "If the email exists, a reset token has been generated" })).into_response()
+            ok(serde_json::json!({ "message": "If the email exists, a reset link has been sent" })).into_response()
         }
         Err(e) => {
             tracing::error!("Forgot password error: {}", e);
@@ -328,30 +952,250 @@ async fn reset_password(
     }
 }
 
-async fn oauth_google(State(_state): State<AppState>) -> impl IntoResponse {
-    match burncloud_service_user::UserService::oauth_url("google") {
-        Ok(url) => ok(serde_json::json!({ "url": url })).into_response(),
-        Err(e) => {
-            tracing::error!("Google OAuth URL error: {}", e);
-            err("Failed to generate Google OAuth URL").into_response()
+fn oauth_error(provider: &str, e: UserServiceError) -> Response {
+    match e {
+        UserServiceError::InvalidInput(msg) => {
+            err_status(StatusCode::BAD_REQUEST, msg).into_response()
+        }
+        UserServiceError::InvalidCredentials => {
+            err_status(StatusCode::UNAUTHORIZED, "OAuth code exchange failed").into_response()
+        }
+        UserServiceError::PermissionDenied(msg) => {
+            err_status(StatusCode::FORBIDDEN, msg).into_response()
+        }
+        UserServiceError::UserNotFound => {
+            err_status(StatusCode::NOT_FOUND, "User not found").into_response()
+        }
+        UserServiceError::ConfigError(msg) => {
+            tracing::error!(provider, "OAuth provider unavailable: {}", msg);
+            err_status(
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!("{provider} sign-in is not available"),
+            )
+            .into_response()
+        }
+        e => {
+            tracing::error!(provider, "OAuth error: {}", e);
+            err_status(StatusCode::INTERNAL_SERVER_ERROR, "OAuth sign-in failed").into_response()
         }
     }
 }
 
-async fn oauth_github(State(_state): State<AppState>) -> impl IntoResponse {
-    match burncloud_service_user::UserService::oauth_url("github") {
-        Ok(url) => ok(serde_json::json!({ "url": url })).into_response(),
+/// Cookie binding an OAuth flow to the browser that started it, so a
+/// callback URL handed to someone else cannot complete in their browser
+const OAUTH_STATE_COOKIE: &str = "burncloud_oauth_state";
+
+/// `Set-Cookie` for the state cookie, sent only to the callback. An empty
+/// `value` with `max_age` 0 clears it.
+fn oauth_state_cookie(value: &str, max_age: i64, secure: bool) -> HeaderValue {
+    let secure = if secure { "; Secure" } else { "" };
+    HeaderValue::from_str(&format!(
+        "{OAUTH_STATE_COOKIE}={value}; Path=/console/api/auth; Max-Age={max_age}; HttpOnly; SameSite=Lax{secure}"
+    ))
+    .unwrap_or_else(|_| HeaderValue::from_static("burncloud_oauth_state=; Max-Age=0"))
+}
+
+/// The authorize URL, with the flow's state bound to this browser
+fn oauth_authorization(authorization: OAuthAuthorization) -> Response {
+    let mut response = ok(serde_json::json!({ "url": authorization.url })).into_response();
+    response.headers_mut().insert(
+        header::SET_COOKIE,
+        oauth_state_cookie(
+            &authorization.state,
+            burncloud_service_user::oauth::STATE_TTL_SECS,
+            authorization.redirect_uri.starts_with("https://"),
+        ),
+    );
+    response
+}
+
+/// State cookie sent with the callback, if any
+fn oauth_state_from_cookie(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .find_map(|pair| {
+            pair.trim()
+                .strip_prefix(OAUTH_STATE_COOKIE)?
+                .strip_prefix('=')
+        })
+}
+
+async fn oauth_start(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+) -> impl IntoResponse {
+    match OAuthService::authorize_url(&state.db, &provider, None).await {
+        Ok(authorization) => oauth_authorization(authorization),
+        Err(e) => oauth_error(&provider, e),
+    }
+}
+
+/// Provider redirect target. Returns the usual auth payload, or when
+/// `OAUTH_SUCCESS_REDIRECT` is set, sends the browser there with the token in
+/// the URL fragment so it never reaches server logs.
+///
+/// Only the browser holding the flow's state cookie can complete it; the
+/// cookie is cleared either way.
+#[tracing::instrument(skip(state, headers, query))]
+async fn oauth_callback(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    headers: HeaderMap,
+    Query(query): Query<OAuthCallbackQuery>,
+) -> impl IntoResponse {
+    let mut response = complete_oauth(&state, &provider, &headers, query).await;
+    response
+        .headers_mut()
+        .append(header::SET_COOKIE, oauth_state_cookie("", 0, false));
+    response
+}
+
+async fn complete_oauth(
+    state: &AppState,
+    provider: &str,
+    headers: &HeaderMap,
+    query: OAuthCallbackQuery,
+) -> Response {
+    if let Some(error) = query.error {
+        return err_status(
+            StatusCode::BAD_REQUEST,
+            format!("{provider} sign-in was not completed: {error}"),
+        )
+        .into_response();
+    }
+    let (Some(code), Some(oauth_state)) = (query.code, query.state) else {
+        return err_status(StatusCode::BAD_REQUEST, "Missing code or state").into_response();
+    };
+    if oauth_state_from_cookie(headers) != Some(oauth_state.as_str()) {
+        return err_status(
+            StatusCode::BAD_REQUEST,
+            "OAuth state was not started in this browser",
+        )
+        .into_response();
+    }
+    let login = match OAuthService::complete(&state.db, provider, &code, &oauth_state).await {
+        Ok(login) => login,
+        Err(e) => return oauth_error(provider, e),
+    };
+    match state
+        .user_service
+        .two_factor_enabled(&state.db, &login.user_id)
+        .await
+    {
+        Ok(false) => {}
+        // The second factor is still due, the client continues at
+        // /api/auth/login/2fa
+        Ok(true) => return sign_in(state, &login.user_id, &login.username, headers).await,
+        Err(e) => return oauth_error(provider, e),
+    }
+    let data = match issue_session(state, &login.user_id, &login.username, headers).await {
+        Ok(data) => data,
+        Err(response) => return response,
+    };
+    if let Some(target) = std::env::var("OAUTH_SUCCESS_REDIRECT")
+        .ok()
+        .filter(|v| !v.is_empty())
+    {
+        return Redirect::to(&format!(
+            "{target}#token={}&refresh_token={}",
+            data.token, data.refresh_token
+        ))
+        .into_response();
+    }
+    ok(data).into_response()
+}
+
+async fn oauth_identities(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> impl IntoResponse {
+    match OAuthService::identities(&state.db, &claims.sub).await {
+        Ok(identities) => ok(identities).into_response(),
         Err(e) => {
-            tracing::error!("GitHub OAuth URL error: {}", e);
-            err("Failed to generate GitHub OAuth URL").into_response()
+            tracing::error!("Failed to list OAuth identities: {}", e);
+            err("Failed to list linked accounts").into_response()
         }
     }
 }
 
+/// Start a flow whose callback links the provider to the caller
+async fn oauth_link(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(provider): Path<String>,
+) -> impl IntoResponse {
+    match OAuthService::authorize_url(&state.db, &provider, Some(&claims.sub)).await {
+        Ok(authorization) => oauth_authorization(authorization),
+        Err(e) => oauth_error(&provider, e),
+    }
+}
+
+async fn oauth_unlink(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(provider): Path<String>,
+) -> impl IntoResponse {
+    match OAuthService::unlink(&state.db, &claims.sub, &provider).await {
+        Ok(()) => ok(serde_json::json!({ "provider": provider, "linked": false })).into_response(),
+        Err(e) => oauth_error(&provider, e),
+    }
+}
+
+/// Console paths a management key cannot reach: sessions, passwords, 2FA
+/// and linked identities, and the keys themselves
+fn is_interactive_only_path(path: &str) -> bool {
+    path.starts_with("/console/api/auth/") || path.starts_with("/console/api/management-keys")
+}
+
+/// Authenticate a management key presented as a bearer token
+async fn management_key_claims(
+    state: &AppState,
+    secret: &str,
+    headers: &HeaderMap,
+    path: &str,
+) -> Result<Claims, Response> {
+    let ip = session_client(headers).ip;
+    let principal = match ManagementKeyService::authenticate(&state.db, secret, ip.as_deref()).await {
+        Ok(principal) => principal,
+        Err(UserServiceError::InvalidCredentials) => {
+            return Err(StatusCode::UNAUTHORIZED.into_response())
+        }
+        Err(UserServiceError::PermissionDenied(reason)) => {
+            tracing::warn!(path, "{}", reason);
+            return Err(err_status(StatusCode::FORBIDDEN, reason).into_response());
+        }
+        Err(e) => {
+            tracing::error!("Management key check failed: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
+        }
+    };
+    Ok(Claims {
+        sub: principal.user_id,
+        username: principal.username,
+        exp: 0,
+        iat: 0,
+        sid: None,
+        management_key: Some(KeyScope {
+            id: principal.key_id,
+            name: principal.key_name,
+            scopes: principal.scopes,
+        }),
+    })
+}
+
 /// Authentication middleware for protected routes.
-/// Validates JWT token from Authorization header and injects Claims into request extensions.
+/// Validates the JWT or management key from the Authorization header, rejects
+/// tokens whose session was revoked and injects Claims into request
+/// extensions. Every request made with a management key is audited.
 #[tracing::instrument(skip_all)]
-pub async fn auth_middleware(mut req: Request<Body>, next: Next) -> Result<Response, StatusCode> {
+pub async fn auth_middleware(
+    State(state): State<AppState>,
+    mut req: Request<Body>,
+    next: Next,
+) -> Result<Response, StatusCode> {
     let auth_header = req
         .headers()
         .get(axum::http::header::AUTHORIZATION)
@@ -367,19 +1211,120 @@ pub async fn auth_middleware(mut req: Request<Body>, next: Next) -> Result<Respo
         return Err(StatusCode::UNAUTHORIZED);
     };
 
-    match verify_jwt(token) {
-        Ok(claims) => {
-            req.extensions_mut().insert(claims);
-            Ok(next.run(req).await)
+    let claims = if is_management_key(token) {
+        match management_key_claims(&state, token, req.headers(), req.uri().path()).await {
+            Ok(claims) => claims,
+            Err(response) => return Ok(response),
+        }
+    } else {
+        let claims = verify_jwt(token).map_err(|_| StatusCode::UNAUTHORIZED)?;
+        let issued_at = i64::try_from(claims.iat).unwrap_or(i64::MAX);
+        match UserService::check_access(&state.db, &claims.sub, claims.sid.as_deref(), issued_at)
+            .await
+        {
+            Ok(()) => {}
+            Err(UserServiceError::SessionRevoked | UserServiceError::UserNotFound) => {
+                return Err(StatusCode::UNAUTHORIZED)
+            }
+            Err(e) => {
+                tracing::error!(user_id = %claims.sub, "Session check failed: {}", e);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        }
+        claims
+    };
+
+    // Accounts that must use two-factor authentication can only reach the
+    // auth endpoints (to enroll) until they have
+    let path = req.uri().path();
+    if !path.starts_with("/console/api/auth/") {
+        let pending = state
+            .user_service
+            .two_factor_setup_pending(&state.db, &claims.sub)
+            .await
+            .map_err(|e| {
+                tracing::error!(user_id = %claims.sub, "Two-factor check failed: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        if pending {
+            return Ok(err_status(
+                StatusCode::FORBIDDEN,
+                "Two-factor authentication must be set up first",
+            )
+            .into_response());
+        }
+    }
+
+    let step_up = requires_step_up(req.method(), matched_route(&req));
+    if step_up && claims.management_key.is_none() {
+        let enabled = state
+            .user_service
+            .two_factor_enabled(&state.db, &claims.sub)
+            .await
+            .map_err(|e| {
+                tracing::error!(user_id = %claims.sub, "Two-factor check failed: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        if enabled {
+            let code = req
+                .headers()
+                .get("x-totp-code")
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("");
+            if state
+                .user_service
+                .verify_totp(&state.db, &claims.sub, code)
+                .await
+                .is_err()
+            {
+                tracing::warn!(user_id = %claims.sub, path, "Step-up verification failed");
+                return Ok(err_status(
+                    StatusCode::FORBIDDEN,
+                    "A valid X-TOTP-Code header is required for this action",
+                )
+                .into_response());
+            }
         }
-        Err(_) => Err(StatusCode::UNAUTHORIZED),
     }
+
+    let key_use = claims.management_key.as_ref().map(|key| {
+        let actor = crate::api::audit::actor(&claims, req.headers());
+        (actor, key.id.clone(), req.method().to_string(), path.to_string())
+    });
+    let response = if claims.management_key.is_some() && is_interactive_only_path(path) {
+        err_status(
+            StatusCode::FORBIDDEN,
+            "Management keys cannot manage sessions, credentials or keys",
+        )
+        .into_response()
+    } else if claims.management_key.is_some() && step_up {
+        err_status(
+            StatusCode::FORBIDDEN,
+            "Management keys cannot make changes that require two-factor step-up",
+        )
+        .into_response()
+    } else {
+        req.extensions_mut().insert(claims);
+        next.run(req).await
+    };
+    if let Some((actor, key_id, method, path)) = key_use {
+        let event = AuditEvent::new("management_key.use")
+            .target(key_id)
+            .after(&KeyUse {
+                method,
+                path,
+                status: response.status().as_u16(),
+            });
+        AuditService::record_or_log(&state.db, &actor, event).await;
+    }
+    Ok(response)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::verify_jwt;
-    use burncloud_service_user::UserService;
+    use super::{client_address, verify_jwt};
+    use axum::http::{HeaderMap, HeaderValue};
+    use burncloud_service_user::{IpRange, UserService};
 
     #[test]
     fn verify_jwt_accepts_tokens_signed_by_user_service() {
@@ -392,4 +1337,31 @@ mod tests {
         assert_eq!(claims.sub, "user-1");
         assert_eq!(claims.username, "alice");
     }
+
+    #[test]
+    fn forwarded_addresses_count_only_from_trusted_proxies() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("10.9.9.9, 203.0.113.7, 192.0.2.10"),
+        );
+        let proxies: Vec<IpRange> = IpRange::parse("192.0.2.0/24").into_iter().collect();
+        let ip = |addr: &str| addr.parse().ok();
+
+        // A direct client cannot claim another address
+        assert_eq!(
+            client_address(ip("198.51.100.4"), &headers, &proxies),
+            ip("198.51.100.4")
+        );
+        assert_eq!(
+            client_address(ip("198.51.100.4"), &headers, &[]),
+            ip("198.51.100.4")
+        );
+        // Behind a trusted proxy the nearest untrusted hop is the client
+        assert_eq!(
+            client_address(ip("192.0.2.1"), &headers, &proxies),
+            ip("203.0.113.7")
+        );
+        assert_eq!(client_address(None, &headers, &proxies), None);
+    }
 }
@@ -2,11 +2,15 @@
 //!
 //! All endpoints are protected by authentication middleware at the API layer.
 
+use crate::api::audit;
+use crate::api::auth::Claims;
 use crate::api::response::{err, ok};
 use crate::AppState;
-use axum::extract::State;
+use axum::extract::{Extension, State};
+use axum::http::HeaderMap;
 use axum::response::IntoResponse;
 use axum::Router;
+use burncloud_service_audit::AuditEvent;
 
 /// Get cache statistics.
 #[tracing::instrument(skip(state))]
@@ -18,10 +22,17 @@ pub async fn stats(State(state): State<AppState>) -> impl IntoResponse {
 }
 
 /// Clear all cache.
-#[tracing::instrument(skip(state))]
-pub async fn clear(State(state): State<AppState>) -> impl IntoResponse {
+#[tracing::instrument(skip_all)]
+pub async fn clear(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
     match state.cache.clear_all().await {
-        Ok(()) => ok(serde_json::json!({"message": "Cache cleared"})).into_response(),
+        Ok(()) => {
+            audit::record(&state, &claims, &headers, AuditEvent::new("cache.clear")).await;
+            ok(serde_json::json!({"message": "Cache cleared"})).into_response()
+        }
         Err(e) => err(format!("Failed to clear cache: {}", e)).into_response(),
     }
 }
@@ -1,14 +1,16 @@
+use crate::api::audit;
 use crate::api::auth::Claims;
-use crate::api::response::{err, ok};
+use crate::api::response::{err, err_status, ok};
 use crate::AppState;
 use axum::{
     extract::{Path, Query, State},
-    response::IntoResponse,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     routing::{get, post},
     Router,
 };
-use burncloud_database_user::UserDatabase;
-use burncloud_service_channel::{Channel, ChannelService};
+use burncloud_service_audit::AuditEvent;
+use burncloud_service_channel::{encryption_config_error, mask_key, Channel, ChannelService};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize)]
@@ -72,6 +74,25 @@ struct ChannelCreated {
     id: i32,
 }
 
+/// Channels leave the API with their key masked; the stored key is only ever
+/// decrypted by the router when it calls the upstream.
+fn masked(mut channel: Channel) -> Channel {
+    channel.key = mask_key(&channel.key);
+    channel
+}
+
+/// Refuse to store keys while the configured master key is unusable, rather
+/// than failing inside the write
+fn key_encryption_unavailable() -> Option<Response> {
+    encryption_config_error().map(|e| {
+        err_status(
+            StatusCode::BAD_REQUEST,
+            format!("Channel key encryption is misconfigured: {e}"),
+        )
+        .into_response()
+    })
+}
+
 impl ChannelDto {
     fn into_channel(self) -> Channel {
         Channel {
@@ -120,37 +141,16 @@ pub fn routes() -> Router<AppState> {
         )
 }
 
-async fn check_admin(state: &AppState, claims: &Claims) -> Result<(), impl IntoResponse> {
-    match UserDatabase::get_user_roles(&state.db, &claims.sub).await {
-        Ok(roles) => {
-            if roles.iter().any(|r| r == "admin") {
-                Ok(())
-            } else {
-                Err(err("Admin access required"))
-            }
-        }
-        Err(e) => {
-            tracing::error!("Failed to get user roles: {}", e);
-            Err(err("Database error"))
-        }
-    }
-}
-
 async fn list_channels(
     State(state): State<AppState>,
-    axum::Extension(claims): axum::Extension<Claims>,
     Query(params): Query<PaginationParams>,
 ) -> impl IntoResponse {
-    if let Err(resp) = check_admin(&state, &claims).await {
-        return resp.into_response();
-    }
-
     let limit = params.limit.clamp(1, 100);
     let offset = params.offset.max(0);
 
     match ChannelService::list(&state.db, limit, offset).await {
         Ok(channels) => ok(ChannelListData {
-            channels,
+            channels: channels.into_iter().map(masked).collect(),
             pagination: PaginationInfo { limit, offset },
         })
         .into_response(),
@@ -158,19 +158,24 @@ async fn list_channels(
     }
 }
 
-#[tracing::instrument(skip(state), fields(name = %payload.name))]
+#[tracing::instrument(skip(state, payload), fields(name = %payload.name))]
 async fn create_channel(
     State(state): State<AppState>,
     axum::Extension(claims): axum::Extension<Claims>,
+    headers: HeaderMap,
     axum::extract::Json(payload): axum::extract::Json<ChannelDto>,
 ) -> impl IntoResponse {
-    if let Err(resp) = check_admin(&state, &claims).await {
-        return resp.into_response();
+    if let Some(response) = key_encryption_unavailable() {
+        return response;
     }
-
     let mut channel = payload.into_channel();
     match ChannelService::create(&state.db, &mut channel).await {
-        Ok(id) => ok(ChannelCreated { id }).into_response(),
+        Ok(id) => {
+            channel.id = id;
+            let event = AuditEvent::new("channel.create").target(id).after(&channel);
+            audit::record(&state, &claims, &headers, event).await;
+            ok(ChannelCreated { id }).into_response()
+        }
         Err(e) => err(e).into_response(),
     }
 }
@@ -179,18 +184,33 @@ async fn create_channel(
 async fn update_channel(
     State(state): State<AppState>,
     axum::Extension(claims): axum::Extension<Claims>,
+    headers: HeaderMap,
     axum::extract::Json(payload): axum::extract::Json<ChannelDto>,
 ) -> impl IntoResponse {
-    if let Err(resp) = check_admin(&state, &claims).await {
-        return resp.into_response();
-    }
-
     let channel = payload.into_channel();
     if channel.id == 0 {
         return err("id is required").into_response();
     }
+    if let Some(response) = key_encryption_unavailable() {
+        return response;
+    }
+    let before = ChannelService::get_by_id(&state.db, channel.id)
+        .await
+        .ok()
+        .flatten();
     match ChannelService::update(&state.db, &channel).await {
-        Ok(_) => ok(channel).into_response(),
+        Ok(_) => {
+            let after = ChannelService::get_by_id(&state.db, channel.id)
+                .await
+                .ok()
+                .flatten();
+            let event = AuditEvent::new("channel.update")
+                .target(channel.id)
+                .before(&before)
+                .after(&after);
+            audit::record(&state, &claims, &headers, event).await;
+            ok(masked(channel)).into_response()
+        }
         Err(e) => err(e).into_response(),
     }
 }
@@ -199,29 +219,26 @@ async fn update_channel(
 async fn delete_channel(
     State(state): State<AppState>,
     axum::Extension(claims): axum::Extension<Claims>,
+    headers: HeaderMap,
     Path(id): Path<i32>,
 ) -> impl IntoResponse {
-    if let Err(resp) = check_admin(&state, &claims).await {
-        return resp.into_response();
-    }
-
+    let before = ChannelService::get_by_id(&state.db, id)
+        .await
+        .ok()
+        .flatten();
     match ChannelService::delete(&state.db, id).await {
-        Ok(_) => ok(()).into_response(),
+        Ok(_) => {
+            let event = AuditEvent::new("channel.delete").target(id).before(&before);
+            audit::record(&state, &claims, &headers, event).await;
+            ok(()).into_response()
+        }
         Err(e) => err(e).into_response(),
     }
 }
 
-async fn get_channel(
-    State(state): State<AppState>,
-    axum::Extension(claims): axum::Extension<Claims>,
-    Path(id): Path<i32>,
-) -> impl IntoResponse {
-    if let Err(resp) = check_admin(&state, &claims).await {
-        return resp.into_response();
-    }
-
+async fn get_channel(State(state): State<AppState>, Path(id): Path<i32>) -> impl IntoResponse {
     match ChannelService::get_by_id(&state.db, id).await {
-        Ok(Some(c)) => ok(c).into_response(),
+        Ok(Some(c)) => ok(masked(c)).into_response(),
         Ok(None) => err("channel not found").into_response(),
         Err(e) => err(e).into_response(),
     }
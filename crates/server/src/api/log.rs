@@ -1,18 +1,174 @@
+use crate::api::token::token_management_id;
 use crate::AppState;
 use axum::{
+    body::{Body, Bytes},
     extract::{Path, Query, State},
-    http::{HeaderMap, StatusCode},
+    http::{header, HeaderMap, StatusCode},
     response::{IntoResponse, Json, Response},
     routing::get,
     Router,
 };
-use burncloud_service_router_log::{BillingService, RouterLogService};
+use burncloud_database_router::token_hash;
+use burncloud_service_router_log::{
+    parse_log_time, parse_unix_time, BillingService, ExportFormat, LogFilter, RollupDimension,
+    RollupGranularity, RollupQuery, RollupRow, RouterLogService, UsageRollupService,
+};
+use burncloud_service_token::TokenService;
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
 
+/// Largest page `GET /console/api/logs` returns.
+const MAX_PAGE_SIZE: i32 = 1000;
+/// Rows fetched per query while streaming an export.
+const EXPORT_BATCH: i64 = 500;
+
 #[derive(Deserialize)]
 pub struct Pagination {
     pub page: Option<i32>,
     pub page_size: Option<i32>,
+    /// `next_cursor` of the previous page; takes precedence over `page`
+    pub cursor: Option<i64>,
+}
+
+/// Query-string filters shared by the log list and export endpoints.
+#[derive(Deserialize)]
+pub struct LogFilterParams {
+    user_id: Option<String>,
+    /// Management id of an API key (`tok_…`, as listed by the token API)
+    token: Option<String>,
+    /// md5 of an API key, or a prefix of it
+    token_hash: Option<String>,
+    model: Option<String>,
+    upstream_id: Option<String>,
+    status_code: Option<i32>,
+    error_type: Option<String>,
+    layer_decision: Option<String>,
+    traffic_color: Option<String>,
+    cost_status: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+    min_latency_ms: Option<i64>,
+    max_latency_ms: Option<i64>,
+    /// Nanodollars, like `cost` in the response
+    min_cost: Option<i64>,
+    max_cost: Option<i64>,
+    q: Option<String>,
+}
+
+impl LogFilterParams {
+    /// Builds the filter, resolving `token` to the hash logged for that key.
+    async fn resolve(self, state: &AppState) -> Result<LogFilter, Response> {
+        let token = self.token.clone().filter(|t| !t.is_empty());
+        let mut filter = self
+            .into_filter()
+            .map_err(|error| (StatusCode::BAD_REQUEST, Json(ApiError { error })).into_response())?;
+        if let Some(id) = token {
+            let tokens = TokenService::list(&state.db).await.map_err(|e| {
+                tracing::error!(error = %e, "Failed to load API tokens for log filter");
+                let error = "Failed to load API tokens".to_string();
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiError { error })).into_response()
+            })?;
+            let Some(record) = tokens
+                .into_iter()
+                .find(|record| token_management_id(&record.token) == id)
+            else {
+                let error = format!("unknown token: {id}");
+                return Err((StatusCode::NOT_FOUND, Json(ApiError { error })).into_response());
+            };
+            filter.token_hash = Some(token_hash(&record.token));
+        }
+        Ok(filter)
+    }
+
+    fn into_filter(self) -> Result<LogFilter, String> {
+        let time = |name: &str, value: Option<String>| match value.filter(|v| !v.is_empty()) {
+            Some(v) => parse_log_time(&v)
+                .map(Some)
+                .ok_or_else(|| format!("invalid {name}: {v}")),
+            None => Ok(None),
+        };
+        let text = |value: Option<String>| value.filter(|v| !v.is_empty());
+        Ok(LogFilter {
+            user_id: text(self.user_id),
+            token_hash: text(self.token_hash),
+            model: text(self.model),
+            upstream_id: text(self.upstream_id),
+            status_code: self.status_code,
+            error_type: text(self.error_type),
+            layer_decision: text(self.layer_decision),
+            traffic_color: text(self.traffic_color),
+            cost_status: text(self.cost_status),
+            since: time("since", self.since)?,
+            until: time("until", self.until)?,
+            min_latency_ms: self.min_latency_ms,
+            max_latency_ms: self.max_latency_ms,
+            min_cost: self.min_cost,
+            max_cost: self.max_cost,
+            search: text(self.q),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct ExportParams {
+    format: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RollupParams {
+    /// `minute`, `hour` (default) or `day`
+    granularity: Option<String>,
+    /// Defaults to 24 hours ago
+    since: Option<String>,
+    until: Option<String>,
+    model: Option<String>,
+    #[serde(alias = "channel")]
+    upstream_id: Option<String>,
+    user_id: Option<String>,
+    group: Option<String>,
+    /// Comma-separated: model, channel, user, group, currency
+    group_by: Option<String>,
+    /// Sum the whole range instead of returning one point per bucket
+    total: Option<bool>,
+}
+
+impl RollupParams {
+    fn into_query(self) -> Result<RollupQuery, String> {
+        let granularity = match self.granularity.as_deref().filter(|g| !g.is_empty()) {
+            Some(name) => RollupGranularity::from_name(name)
+                .ok_or_else(|| format!("invalid granularity: {name}"))?,
+            None => RollupGranularity::Hour,
+        };
+        let time = |name: &str, value: Option<String>| match value.filter(|v| !v.is_empty()) {
+            Some(v) => parse_unix_time(&v)
+                .map(Some)
+                .ok_or_else(|| format!("invalid {name}: {v}")),
+            None => Ok(None),
+        };
+        let since = time("since", self.since)?
+            .unwrap_or_else(|| chrono::Utc::now().timestamp() - 24 * 3600);
+        let mut group_by = Vec::new();
+        for name in self.group_by.iter().flat_map(|g| g.split(',')) {
+            let name = name.trim();
+            if name.is_empty() {
+                continue;
+            }
+            group_by.push(
+                RollupDimension::from_name(name)
+                    .ok_or_else(|| format!("invalid group_by: {name}"))?,
+            );
+        }
+        let text = |value: Option<String>| value.filter(|v| !v.is_empty());
+        let mut query = RollupQuery::new(granularity, granularity.bucket_start(since));
+        query.until = time("until", self.until)?;
+        query.model = text(self.model);
+        query.upstream_id = text(self.upstream_id);
+        query.user_id = text(self.user_id);
+        query.group_name = text(self.group);
+        query.group_by = group_by;
+        query.by_bucket = !self.total.unwrap_or(false);
+        Ok(query)
+    }
 }
 
 #[derive(Deserialize)]
@@ -26,6 +182,30 @@ struct LogPage {
     data: Vec<burncloud_service_router_log::RouterLog>,
     page: i32,
     page_size: i32,
+    /// Pass as `cursor` to fetch the next page; `None` on the last page
+    next_cursor: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct LogDetail {
+    log: burncloud_service_router_log::RouterLog,
+    /// Stored bodies, candidates and failover history, when kept
+    detail: Option<burncloud_service_router_log::RouterRequestLog>,
+}
+
+#[derive(Serialize)]
+struct RollupPoint {
+    #[serde(flatten)]
+    row: RollupRow,
+    avg_latency_ms: Option<i64>,
+    p50_latency_ms: Option<i64>,
+    p95_latency_ms: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct RollupSeries {
+    granularity: RollupGranularity,
+    data: Vec<RollupPoint>,
 }
 
 #[derive(Serialize)]
@@ -45,6 +225,9 @@ pub fn routes() -> Router<AppState> {
     // Authenticated routes
     let authenticated = Router::new()
         .route("/console/api/logs", get(list_logs))
+        .route("/console/api/logs/export", get(export_logs))
+        .route("/console/api/logs/{request_id}", get(get_log))
+        .route("/console/api/usage/rollups", get(get_usage_rollups))
         .route("/console/api/usage/{user_id}", get(get_user_usage));
 
     // Internal routes (with their own authentication)
@@ -59,18 +242,42 @@ pub fn routes() -> Router<AppState> {
 async fn list_logs(
     State(state): State<AppState>,
     Query(params): Query<Pagination>,
+    Query(filters): Query<LogFilterParams>,
 ) -> impl IntoResponse {
-    let page = params.page.unwrap_or(1);
-    let page_size = params.page_size.unwrap_or(50);
-    let offset = (page - 1) * page_size;
-
-    match RouterLogService::get(&state.db, page_size, offset).await {
-        Ok(data) => Json(LogPage {
-            data,
-            page,
-            page_size,
-        })
-        .into_response(),
+    let filter = match filters.resolve(&state).await {
+        Ok(filter) => filter,
+        Err(response) => return response,
+    };
+    let page = params.page.unwrap_or(1).max(1);
+    let page_size = params.page_size.unwrap_or(50).clamp(1, MAX_PAGE_SIZE);
+    let offset = match params.cursor {
+        Some(_) => 0,
+        None => i64::from(page - 1) * i64::from(page_size),
+    };
+
+    match RouterLogService::search(
+        &state.db,
+        &filter,
+        params.cursor,
+        i64::from(page_size),
+        offset,
+    )
+    .await
+    {
+        Ok(data) => {
+            let next_cursor = if data.len() == page_size as usize {
+                data.last().map(|log| log.id)
+            } else {
+                None
+            };
+            Json(LogPage {
+                data,
+                page,
+                page_size,
+                next_cursor,
+            })
+            .into_response()
+        }
         Err(e) => Json(ApiError {
             error: e.to_string(),
         })
@@ -78,6 +285,82 @@ async fn list_logs(
     }
 }
 
+/// Stream every log matching the filters as CSV or JSON Lines, newest first.
+async fn export_logs(
+    State(state): State<AppState>,
+    Query(params): Query<ExportParams>,
+    Query(filters): Query<LogFilterParams>,
+) -> Response {
+    let format_name = params.format.unwrap_or_else(|| "csv".to_string());
+    let Some(format) = ExportFormat::from_name(&format_name) else {
+        let error = format!("unsupported format: {format_name} (expected csv or jsonl)");
+        return (StatusCode::BAD_REQUEST, Json(ApiError { error })).into_response();
+    };
+    let filter = match filters.resolve(&state).await {
+        Ok(filter) => filter,
+        Err(response) => return response,
+    };
+
+    // Pages are keyed on id, so rows inserted mid-export neither repeat nor
+    // shift later pages.
+    let header_chunk = format
+        .header()
+        .map(|h| Ok::<_, std::io::Error>(Bytes::from(h)));
+    let rows = futures::stream::try_unfold(Some(None), move |cursor: Option<Option<i64>>| {
+        let db = state.db.clone();
+        let filter = filter.clone();
+        async move {
+            let Some(before_id) = cursor else {
+                return Ok(None);
+            };
+            let logs = RouterLogService::search(&db, &filter, before_id, EXPORT_BATCH, 0)
+                .await
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+            if logs.is_empty() {
+                return Ok(None);
+            }
+            let next = (logs.len() as i64 == EXPORT_BATCH).then(|| logs.last().map(|l| l.id));
+            let mut chunk = String::new();
+            for log in &logs {
+                chunk.push_str(&format.row(log).map_err(std::io::Error::other)?);
+            }
+            Ok(Some((Bytes::from(chunk), next)))
+        }
+    });
+    let body = futures::stream::iter(header_chunk).chain(rows);
+
+    let disposition = format!("attachment; filename=\"logs.{}\"", format.extension());
+    Response::builder()
+        .header(header::CONTENT_TYPE, format.content_type())
+        .header(header::CONTENT_DISPOSITION, disposition)
+        .body(Body::from_stream(body))
+        .unwrap_or_else(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response())
+}
+
+/// A log by request_id, joined with its `router_request_logs` detail.
+async fn get_log(
+    State(state): State<AppState>,
+    Path(request_id): Path<String>,
+) -> impl IntoResponse {
+    match RouterLogService::get_with_detail(&state.db, &request_id).await {
+        Ok(Some((log, detail))) => Json(LogDetail { log, detail }).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(ApiError {
+                error: format!("log not found: {request_id}"),
+            }),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiError {
+                error: e.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
 async fn get_user_usage(
     State(state): State<AppState>,
     Path(user_id): Path<String>,
@@ -97,6 +380,39 @@ async fn get_user_usage(
     }
 }
 
+/// Usage time series from the minute/hour/day rollups.
+async fn get_usage_rollups(
+    State(state): State<AppState>,
+    Query(params): Query<RollupParams>,
+) -> impl IntoResponse {
+    let query = match params.into_query() {
+        Ok(query) => query,
+        Err(error) => return (StatusCode::BAD_REQUEST, Json(ApiError { error })).into_response(),
+    };
+    match UsageRollupService::query(&state.db, &query).await {
+        Ok(rows) => Json(RollupSeries {
+            granularity: query.granularity,
+            data: rows
+                .into_iter()
+                .map(|row| RollupPoint {
+                    avg_latency_ms: row.counters.avg_latency_ms(),
+                    p50_latency_ms: row.counters.latency_quantile_ms(0.5),
+                    p95_latency_ms: row.counters.latency_quantile_ms(0.95),
+                    row,
+                })
+                .collect(),
+        })
+        .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiError {
+                error: e.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
 async fn billing_summary_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
@@ -0,0 +1,134 @@
+//! Management API keys for automation.
+//!
+//! Users create and revoke their own keys; holders of `users:write` list
+//! (`?all=true`) and revoke the keys of everyone. A key is presented as
+//! `Authorization: Bearer bcm_...` and is accepted by `auth_middleware` on
+//! every console route except these and `/console/api/auth/*`.
+
+use crate::api::audit;
+use crate::api::auth::{has_permission, Claims};
+use crate::api::response::{err_status, ok};
+use crate::AppState;
+use axum::{
+    extract::{Extension, Json, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{delete, get},
+    Router,
+};
+use burncloud_service_audit::AuditEvent;
+use burncloud_service_user::{
+    ManagementKeyService, NewManagementKey, Permission, UserServiceError,
+};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct ListParams {
+    /// Keys of every user; needs `users:write`
+    #[serde(default)]
+    all: bool,
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/console/api/management-keys",
+            get(list_keys).post(create_key),
+        )
+        .route("/console/api/management-keys/{id}", delete(revoke_key))
+}
+
+fn key_error(e: UserServiceError) -> Response {
+    match e {
+        UserServiceError::ManagementKeyNotFound => {
+            err_status(StatusCode::NOT_FOUND, "Management key not found").into_response()
+        }
+        UserServiceError::PermissionDenied(_) => {
+            err_status(StatusCode::FORBIDDEN, e).into_response()
+        }
+        UserServiceError::InvalidInput(_) => err_status(StatusCode::BAD_REQUEST, e).into_response(),
+        e => {
+            tracing::error!("[API] management key error: {}", e);
+            err_status(StatusCode::INTERNAL_SERVER_ERROR, e).into_response()
+        }
+    }
+}
+
+/// Whether the caller may see and revoke the keys of other users
+async fn manages_all_keys(state: &AppState, claims: &Claims) -> Result<bool, Response> {
+    has_permission(state, claims, Permission::UsersWrite)
+        .await
+        .map_err(IntoResponse::into_response)
+}
+
+#[tracing::instrument(skip_all)]
+async fn list_keys(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(params): Query<ListParams>,
+) -> Response {
+    let owner = if params.all {
+        match manages_all_keys(&state, &claims).await {
+            Ok(true) => None,
+            Ok(false) => return StatusCode::FORBIDDEN.into_response(),
+            Err(response) => return response,
+        }
+    } else {
+        Some(claims.sub.as_str())
+    };
+    match ManagementKeyService::list(&state.db, owner).await {
+        Ok(keys) => ok(keys).into_response(),
+        Err(e) => key_error(e),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %claims.sub))]
+async fn create_key(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Json(payload): Json<NewManagementKey>,
+) -> Response {
+    match ManagementKeyService::create(&state.db, &claims.sub, &payload).await {
+        Ok(created) => {
+            let event = AuditEvent::new("management_key.create")
+                .target(&created.key.id)
+                .after(&created.key);
+            audit::record(&state, &claims, &headers, event).await;
+            ok(created).into_response()
+        }
+        Err(e) => key_error(e),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(id = %id))]
+async fn revoke_key(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Response {
+    let before = match ManagementKeyService::get(&state.db, &id).await {
+        Ok(key) => key,
+        Err(e) => return key_error(e),
+    };
+    if before.user_id != claims.sub {
+        match manages_all_keys(&state, &claims).await {
+            Ok(true) => {}
+            // Keys of other users are not disclosed
+            Ok(false) => return key_error(UserServiceError::ManagementKeyNotFound),
+            Err(response) => return response,
+        }
+    }
+    match ManagementKeyService::revoke(&state.db, &id).await {
+        Ok(after) => {
+            let event = AuditEvent::new("management_key.revoke")
+                .target(&id)
+                .before(&before)
+                .after(&after);
+            audit::record(&state, &claims, &headers, event).await;
+            ok(after).into_response()
+        }
+        Err(e) => key_error(e),
+    }
+}
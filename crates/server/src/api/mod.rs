@@ -1,24 +1,104 @@
 use crate::AppState;
+use burncloud_service_user::Permission;
 pub mod security;
 
 use axum::{
-    http::StatusCode,
+    http::{Method, StatusCode},
     middleware,
     response::IntoResponse,
     routing::get,
     Router,
 };
 
+pub mod audit;
 pub mod auth;
 pub mod billing;
 pub mod cache;
 pub mod channel;
 pub mod log;
+pub mod management_key;
+pub mod model;
 pub mod monitor;
+pub mod notification;
 pub mod openapi;
+pub mod org;
+pub mod payment;
 pub mod response;
+pub mod role;
 pub mod token;
 pub mod user;
+pub mod virtual_model;
+
+/// Permission each Console route requires, by method and route template.
+/// Administrative routes missing here require `*` (the admin role);
+/// self-service routes missing here only need a signed-in user, and their
+/// handlers check ownership.
+const ROUTE_PERMISSIONS: &[(Method, &str, Permission)] = &[
+    (Method::GET, "/console/api/audit", Permission::AuditRead),
+    (Method::GET, "/console/api/audit/export", Permission::AuditRead),
+    (Method::GET, "/console/api/channel", Permission::ChannelsRead),
+    (Method::GET, "/console/api/channel/{id}", Permission::ChannelsRead),
+    (Method::POST, "/console/api/channel", Permission::ChannelsWrite),
+    (Method::PUT, "/console/api/channel", Permission::ChannelsWrite),
+    (Method::DELETE, "/console/api/channel/{id}", Permission::ChannelsWrite),
+    (Method::GET, "/console/api/logs", Permission::LogsRead),
+    (Method::GET, "/console/api/logs/export", Permission::LogsRead),
+    (Method::GET, "/console/api/logs/{request_id}", Permission::LogsRead),
+    (Method::GET, "/console/api/usage/rollups", Permission::LogsRead),
+    (Method::GET, "/console/api/usage/{user_id}", Permission::LogsRead),
+    (Method::GET, "/console/api/models/instances", Permission::ModelsRead),
+    (Method::GET, "/console/api/models/logs", Permission::ModelsRead),
+    (Method::GET, "/console/api/models/pulls", Permission::ModelsRead),
+    (Method::GET, "/console/api/models/pulls/{id}", Permission::ModelsRead),
+    (Method::POST, "/console/api/models/pull", Permission::ModelsWrite),
+    (Method::POST, "/console/api/models/serve", Permission::ModelsWrite),
+    (Method::POST, "/console/api/models/stop", Permission::ModelsWrite),
+    (Method::GET, "/console/api/virtual-models", Permission::ModelsRead),
+    (Method::GET, "/console/api/virtual-models/{name}", Permission::ModelsRead),
+    (Method::POST, "/console/api/virtual-models", Permission::ModelsWrite),
+    (Method::DELETE, "/console/api/virtual-models/{name}", Permission::ModelsWrite),
+    (Method::GET, "/console/api/monitor", Permission::MonitorRead),
+    (Method::GET, "/console/api/monitor/log-retention", Permission::MonitorRead),
+    (Method::GET, "/console/api/cache/stats", Permission::MonitorRead),
+    (Method::POST, "/console/api/cache/clear", Permission::CacheWrite),
+    (Method::GET, "/console/api/monitor/security", Permission::SecurityRead),
+    (Method::GET, "/console/api/monitor/security/events", Permission::SecurityRead),
+    (Method::GET, "/console/api/monitor/security/filters", Permission::SecurityRead),
+    (
+        Method::GET,
+        "/console/api/monitor/security/circuit-breaker-status",
+        Permission::SecurityRead,
+    ),
+    (Method::PUT, "/console/api/monitor/security/filters", Permission::SecurityWrite),
+    (
+        Method::POST,
+        "/console/api/monitor/security/emergency-circuit-break",
+        Permission::SecurityBreak,
+    ),
+    (Method::GET, "/console/api/roles", Permission::RolesManage),
+    (Method::GET, "/console/api/roles/permissions", Permission::RolesManage),
+    (Method::POST, "/console/api/roles", Permission::RolesManage),
+    (Method::PUT, "/console/api/roles/{name}", Permission::RolesManage),
+    (Method::DELETE, "/console/api/roles/{name}", Permission::RolesManage),
+    // Self-service group
+    (Method::GET, "/console/api/list_users", Permission::UsersRead),
+    (Method::POST, "/console/api/user/register", Permission::UsersWrite),
+    (Method::POST, "/console/api/user/rate-limits", Permission::UsersWrite),
+    (Method::POST, "/console/api/user/2fa-policy", Permission::UsersWrite),
+    (Method::POST, "/console/api/user/2fa-reset", Permission::UsersWrite),
+    (Method::POST, "/console/api/user/roles", Permission::RolesManage),
+    (Method::POST, "/console/api/user/topup", Permission::BillingTopup),
+    (Method::POST, "/console/api/orgs/{org_id}/topup", Permission::BillingTopup),
+    (Method::POST, "/console/api/payments/{id}/refund", Permission::BillingTopup),
+];
+
+/// Permission the route declares in [`ROUTE_PERMISSIONS`], if any
+pub(crate) fn required_permission(method: &Method, route: &str) -> Option<Permission> {
+    ROUTE_PERMISSIONS
+        .iter()
+        .find(|(m, r, _)| m == method && *r == route)
+        .map(|(_, _, permission)| *permission)
+}
 
 /// Fallback handler for unmatched /console/api/* requests
 /// Returns 404 instead of being caught by LiveView's catch-all
@@ -30,35 +110,52 @@ pub fn routes(state: AppState) -> Router {
     // Public routes - no authentication required.
     let public_routes = Router::new()
         .merge(auth::public_routes())
+        .merge(payment::webhook_routes())
         .with_state(state.clone());
 
-    // Administrator-only management surfaces. Authentication runs on the
-    // outer protected router; this inner layer performs authorization.
+    // Administrative management surfaces. Authentication runs on the outer
+    // protected router; the route layer checks the permission each route
+    // declares in `ROUTE_PERMISSIONS`.
     let admin_routes = Router::new()
+        .merge(audit::routes())
         .merge(channel::routes())
         .merge(log::routes())
+        .merge(model::routes())
         .merge(monitor::routes())
         .merge(security::security_routes())
         .merge(cache::routes())
-        .layer(middleware::from_fn_with_state(
+        .merge(virtual_model::routes())
+        .merge(role::routes())
+        .route_layer(middleware::from_fn_with_state(
             state.clone(),
             auth::admin_middleware,
         ));
 
-    // Authenticated self-service routes. Token handlers perform resource-level
-    // owner/admin authorization because users are allowed to manage their own
-    // API credentials while administrators may manage all credentials.
+    // Authenticated self-service routes. Token and organization handlers
+    // perform resource-level owner/permission checks because users manage
+    // their own API credentials while `tokens:*` holders manage all of them.
     let protected_routes = Router::new()
         .merge(auth::protected_routes())
         .merge(billing::routes())
         .merge(token::routes())
         .merge(user::routes())
         .merge(openapi::routes())
+        .merge(org::routes())
+        .merge(management_key::routes())
+        .merge(payment::routes())
+        .merge(notification::routes())
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::permission_middleware,
+        ))
         .merge(admin_routes)
         // Catch-all for any unmatched /console/api/* paths. This prevents
         // LiveView from returning HTML for non-existent API endpoints.
         .route("/console/api/{*path}", get(api_not_found))
-        .layer(middleware::from_fn(crate::auth_middleware))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            crate::auth_middleware,
+        ))
         .with_state(state);
 
     public_routes.merge(protected_routes)
@@ -0,0 +1,153 @@
+//! Local model lifecycle endpoints: pull a GGUF quantization, serve it with
+//! `llama-server`, stop it and list running instances.
+//!
+//! Mirrors `burncloud model pull|serve|stop|ps`. Pulls run in the background;
+//! poll `/console/api/models/pulls/{id}` for progress. Served instances are
+//! supervised: crashes are restarted, idle ones unloaded and cold-started on
+//! the next routed request.
+
+use crate::api::audit;
+use crate::api::auth::Claims;
+use crate::api::response::{err, err_status, ok};
+use crate::AppState;
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use burncloud_service_audit::AuditEvent;
+use burncloud_service_inference::{InferenceError, PullRequest, ServeOptions};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub struct StopModelRequest {
+    pub model_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InstanceLogsQuery {
+    pub model_id: String,
+}
+
+#[derive(Serialize)]
+struct PullStarted {
+    id: String,
+}
+
+#[derive(Serialize)]
+struct ModelStopped {
+    model_id: String,
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/console/api/models/instances", get(list_instances))
+        .route("/console/api/models/logs", get(instance_logs))
+        .route("/console/api/models/pull", post(pull_model))
+        .route("/console/api/models/pulls", get(list_pulls))
+        .route("/console/api/models/pulls/{id}", get(get_pull))
+        .route("/console/api/models/serve", post(serve_model))
+        .route("/console/api/models/stop", post(stop_model))
+}
+
+fn inference_error(e: InferenceError) -> axum::response::Response {
+    match e {
+        InferenceError::NotFound(msg) => err_status(StatusCode::NOT_FOUND, msg).into_response(),
+        other => err(other).into_response(),
+    }
+}
+
+#[tracing::instrument(skip(state))]
+async fn list_instances(State(state): State<AppState>) -> impl IntoResponse {
+    match state.models.ps(&state.db).await {
+        Ok(instances) => ok(instances).into_response(),
+        Err(e) => inference_error(e),
+    }
+}
+
+/// Recent `llama-server` stderr output for an instance started by this server.
+#[tracing::instrument(skip(state))]
+async fn instance_logs(
+    State(state): State<AppState>,
+    Query(query): Query<InstanceLogsQuery>,
+) -> impl IntoResponse {
+    ok(state.models.inference().recent_logs(&query.model_id))
+}
+
+#[tracing::instrument(skip(state, claims, headers))]
+async fn pull_model(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Json(payload): Json<PullRequest>,
+) -> impl IntoResponse {
+    if payload.model_id.trim().is_empty() || payload.file.trim().is_empty() {
+        return err_status(StatusCode::BAD_REQUEST, "model_id and file are required")
+            .into_response();
+    }
+    let event = AuditEvent::new("model.pull")
+        .target(&payload.model_id)
+        .after(&serde_json::json!({
+            "model_id": payload.model_id,
+            "file": payload.file,
+            "sha256": payload.sha256,
+        }));
+    let id = state.models.start_pull(payload);
+    audit::record(&state, &claims, &headers, event).await;
+    ok(PullStarted { id }).into_response()
+}
+
+#[tracing::instrument(skip(state))]
+async fn list_pulls(State(state): State<AppState>) -> impl IntoResponse {
+    ok(state.models.list_pulls())
+}
+
+#[tracing::instrument(skip(state))]
+async fn get_pull(State(state): State<AppState>, Path(id): Path<String>) -> impl IntoResponse {
+    match state.models.pull_job(&id) {
+        Some(job) => ok(job).into_response(),
+        None => err_status(StatusCode::NOT_FOUND, "Pull job not found").into_response(),
+    }
+}
+
+#[tracing::instrument(skip(state, claims, headers))]
+async fn serve_model(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Json(payload): Json<ServeOptions>,
+) -> impl IntoResponse {
+    let model_id = payload.model_id.clone();
+    match state.models.serve(&state.db, payload).await {
+        Ok(instance) => {
+            let event = AuditEvent::new("model.serve")
+                .target(&model_id)
+                .after(&instance);
+            audit::record(&state, &claims, &headers, event).await;
+            ok(instance).into_response()
+        }
+        Err(e) => inference_error(e),
+    }
+}
+
+#[tracing::instrument(skip(state, claims, headers))]
+async fn stop_model(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Json(payload): Json<StopModelRequest>,
+) -> impl IntoResponse {
+    match state.models.stop(&state.db, &payload.model_id).await {
+        Ok(()) => {
+            let event = AuditEvent::new("model.stop").target(&payload.model_id);
+            audit::record(&state, &claims, &headers, event).await;
+            ok(ModelStopped {
+                model_id: payload.model_id,
+            })
+            .into_response()
+        }
+        Err(e) => inference_error(e),
+    }
+}
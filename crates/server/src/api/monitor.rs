@@ -5,6 +5,7 @@ use axum::{extract::State, response::IntoResponse, routing::get, Router};
 pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/console/api/monitor", get(get_system_metrics))
+        .route("/console/api/monitor/log-retention", get(get_log_retention))
 }
 
 async fn get_system_metrics(State(state): State<AppState>) -> impl IntoResponse {
@@ -13,3 +14,8 @@ async fn get_system_metrics(State(state): State<AppState>) -> impl IntoResponse
         Err(e) => err(e).into_response(),
     }
 }
+
+/// Retention policy, rows archived / purged and archive directory size.
+async fn get_log_retention(State(state): State<AppState>) -> impl IntoResponse {
+    ok(state.log_retention.stats()).into_response()
+}
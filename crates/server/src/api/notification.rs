@@ -0,0 +1,221 @@
+//! Low-balance and quota notification settings of the signed-in user.
+//!
+//! Thresholds watch the caller's wallet or one of the caller's own API keys,
+//! referenced by its management id. Only this API returns the webhook signing
+//! secret; the usage API shows the same settings without it.
+
+use crate::api::auth::Claims;
+use crate::api::response::{err_status, ok};
+use crate::api::token::{authorized_token, token_hint, token_management_id};
+use crate::AppState;
+use axum::{
+    extract::{Extension, Json, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Router,
+};
+use burncloud_database_router::token_hash;
+use burncloud_database_user::{UserNotificationSettings, UserNotificationThreshold};
+use burncloud_service_notification::{
+    NewThreshold, NotificationError, NotificationService, SettingsUpdate,
+};
+use burncloud_service_token::TokenService;
+use burncloud_service_user::Permission;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Deserialize)]
+struct ThresholdDto {
+    /// Management id of an API key; the wallet when omitted
+    #[serde(default)]
+    token: Option<String>,
+    /// Wallet currency, USD or CNY
+    #[serde(default)]
+    currency: Option<String>,
+    /// `percent` or `absolute`
+    kind: String,
+    /// Percent of the key quota, or nanodollars left
+    value: i64,
+}
+
+/// Settings as their owner sees them, secrets included
+#[derive(Serialize)]
+struct SettingsView {
+    #[serde(flatten)]
+    settings: UserNotificationSettings,
+    webhook_secret: Option<String>,
+    auto_topup_method: Option<String>,
+}
+
+impl From<UserNotificationSettings> for SettingsView {
+    fn from(settings: UserNotificationSettings) -> Self {
+        Self {
+            webhook_secret: settings.webhook_secret.clone(),
+            auto_topup_method: settings.auto_topup_method.clone(),
+            settings,
+        }
+    }
+}
+
+/// A threshold with its API key as listed under `/console/api/tokens`
+#[derive(Serialize)]
+struct ThresholdView {
+    #[serde(flatten)]
+    threshold: UserNotificationThreshold,
+    token: Option<String>,
+    token_hint: Option<String>,
+}
+
+#[derive(Serialize)]
+struct NotificationsView {
+    settings: SettingsView,
+    thresholds: Vec<ThresholdView>,
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/console/api/notifications",
+            get(get_notifications).put(update_settings),
+        )
+        .route(
+            "/console/api/notifications/thresholds",
+            post(create_threshold),
+        )
+        .route(
+            "/console/api/notifications/thresholds/{id}",
+            delete(delete_threshold),
+        )
+}
+
+fn notification_error(e: NotificationError) -> Response {
+    let status = match e {
+        NotificationError::InvalidInput(_) => StatusCode::BAD_REQUEST,
+        NotificationError::NotFound => StatusCode::NOT_FOUND,
+        NotificationError::Database(_) => {
+            tracing::error!("[API] notification error: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+    err_status(status, e).into_response()
+}
+
+/// Attach management ids and hints of the user's API keys
+async fn threshold_views(
+    state: &AppState,
+    user_id: &str,
+    thresholds: Vec<UserNotificationThreshold>,
+) -> Vec<ThresholdView> {
+    let keys: HashMap<String, (String, String)> = match TokenService::list(&state.db).await {
+        Ok(tokens) => tokens
+            .iter()
+            .filter(|t| t.user_id == user_id)
+            .map(|t| {
+                (
+                    token_hash(&t.token),
+                    (token_management_id(&t.token), token_hint(t)),
+                )
+            })
+            .collect(),
+        Err(e) => {
+            tracing::warn!("[API] failed to load API keys for thresholds: {}", e);
+            HashMap::new()
+        }
+    };
+    thresholds
+        .into_iter()
+        .map(|threshold| {
+            let key = threshold.token_hash.as_ref().and_then(|h| keys.get(h));
+            ThresholdView {
+                token: key.map(|(id, _)| id.clone()),
+                token_hint: key.map(|(_, hint)| hint.clone()),
+                threshold,
+            }
+        })
+        .collect()
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %claims.sub))]
+async fn get_notifications(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Response {
+    let view = match NotificationService::view(&state.db, &claims.sub).await {
+        Ok(view) => view,
+        Err(e) => return notification_error(e),
+    };
+    ok(NotificationsView {
+        settings: view.settings.into(),
+        thresholds: threshold_views(&state, &claims.sub, view.thresholds).await,
+    })
+    .into_response()
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %claims.sub))]
+async fn update_settings(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<SettingsUpdate>,
+) -> Response {
+    match state
+        .notifications
+        .save_settings(&state.db, &claims.sub, payload)
+        .await
+    {
+        Ok(settings) => ok(SettingsView::from(settings)).into_response(),
+        Err(e) => notification_error(e),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %claims.sub, kind = %payload.kind))]
+async fn create_threshold(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<ThresholdDto>,
+) -> Response {
+    let token_hash = match &payload.token {
+        Some(token_ref) => {
+            let record =
+                match authorized_token(&state, &claims, token_ref, Permission::TokensRead).await {
+                    Ok(record) => record,
+                    Err(response) => return response,
+                };
+            // Settlement checks the thresholds of the key's owner
+            if record.user_id != claims.sub {
+                return err_status(
+                    StatusCode::FORBIDDEN,
+                    "Thresholds can only watch your own API keys",
+                )
+                .into_response();
+            }
+            Some(token_hash(&record.token))
+        }
+        None => None,
+    };
+    let new = NewThreshold {
+        token_hash,
+        currency: payload.currency,
+        kind: payload.kind,
+        value: payload.value,
+    };
+    match NotificationService::create_threshold(&state.db, &claims.sub, new).await {
+        Ok(threshold) => {
+            let mut views = threshold_views(&state, &claims.sub, vec![threshold]).await;
+            ok(views.pop()).into_response()
+        }
+        Err(e) => notification_error(e),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(id = %id))]
+async fn delete_threshold(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Response {
+    match NotificationService::delete_threshold(&state.db, &claims.sub, &id).await {
+        Ok(()) => ok(serde_json::json!({ "status": "deleted" })).into_response(),
+        Err(e) => notification_error(e),
+    }
+}
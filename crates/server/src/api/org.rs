@@ -0,0 +1,522 @@
+//! Organization, membership and invitation management.
+//!
+//! Membership roles are enforced by `OrganizationService`; only wallet top-ups
+//! and enabling/disabling an organization require a platform administrator.
+
+use crate::api::audit;
+use crate::api::auth::{has_permission, Claims};
+use crate::api::response::{err_status, ok};
+use crate::api::token::TokenSummary;
+use crate::AppState;
+use axum::{
+    extract::{Extension, Json, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, put},
+    Router,
+};
+use burncloud_service_audit::AuditEvent;
+use burncloud_service_token::TokenService;
+use burncloud_service_user::{
+    OrgRole, OrganizationService, Permission, UserOrganization, UserOrganizationInvitation,
+    UserServiceError,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, Deserialize)]
+pub struct CreateOrgRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateOrgRequest {
+    pub name: Option<String>,
+    /// Spend limit in nanodollars. `-1` means unlimited.
+    pub spend_limit: Option<i64>,
+    /// Enable or disable the organization (platform admin only).
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OrgTopupRequest {
+    /// Amount in nanodollars ($1 = 1_000_000_000)
+    pub amount: i64,
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMemberRequest {
+    pub role: OrgRole,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateInvitationRequest {
+    pub email: String,
+    pub role: OrgRole,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListOrgsQuery {
+    /// Platform admins may list every organization.
+    #[serde(default)]
+    pub all: bool,
+}
+
+#[derive(Serialize)]
+struct OrgSummary {
+    #[serde(flatten)]
+    org: UserOrganization,
+    /// Caller's role; `None` for platform admins viewing an organization they are not in.
+    role: Option<OrgRole>,
+}
+
+/// Invitation as returned to organization managers. The accept code is only
+/// disclosed once, when the invitation is created.
+#[derive(Serialize)]
+struct InvitationSummary {
+    email: String,
+    role: String,
+    invited_by: String,
+    status: String,
+    expires_at: i64,
+    created_at: i64,
+    /// Reference for revocation; not accepted by the accept endpoint.
+    invitation_ref: String,
+}
+
+impl From<UserOrganizationInvitation> for InvitationSummary {
+    fn from(invitation: UserOrganizationInvitation) -> Self {
+        Self {
+            invitation_ref: invitation_ref(&invitation.code),
+            email: invitation.email,
+            role: invitation.role,
+            invited_by: invitation.invited_by,
+            status: invitation.status,
+            expires_at: invitation.expires_at,
+            created_at: invitation.created_at,
+        }
+    }
+}
+
+fn invitation_ref(code: &str) -> String {
+    format!("inv_{:x}", Sha256::digest(code.as_bytes()))
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/console/api/orgs", get(list_orgs).post(create_org))
+        .route("/console/api/orgs/{org_id}", get(get_org).put(update_org))
+        .route("/console/api/orgs/{org_id}/topup", post(topup_org))
+        .route("/console/api/orgs/{org_id}/members", get(list_members))
+        .route(
+            "/console/api/orgs/{org_id}/members/{user_id}",
+            put(update_member).delete(remove_member),
+        )
+        .route(
+            "/console/api/orgs/{org_id}/invitations",
+            get(list_invitations).post(create_invitation),
+        )
+        .route(
+            "/console/api/orgs/{org_id}/invitations/{invitation_ref}",
+            delete(revoke_invitation),
+        )
+        .route("/console/api/orgs/{org_id}/tokens", get(list_org_tokens))
+        .route(
+            "/console/api/org-invitations/{code}/accept",
+            post(accept_invitation),
+        )
+}
+
+/// Map organization service errors onto the HTTP contract.
+pub(crate) fn org_error(e: UserServiceError) -> Response {
+    match e {
+        UserServiceError::OrganizationNotFound => {
+            err_status(StatusCode::NOT_FOUND, "Organization not found").into_response()
+        }
+        UserServiceError::UserNotFound => {
+            err_status(StatusCode::NOT_FOUND, "Member not found").into_response()
+        }
+        UserServiceError::PermissionDenied(_) => {
+            err_status(StatusCode::FORBIDDEN, e).into_response()
+        }
+        UserServiceError::InvalidInvitation(_) | UserServiceError::InvalidInput(_) => {
+            err_status(StatusCode::BAD_REQUEST, e).into_response()
+        }
+        e => {
+            tracing::error!("[API] organization error: {}", e);
+            err_status(StatusCode::INTERNAL_SERVER_ERROR, e).into_response()
+        }
+    }
+}
+
+async fn principal_can(
+    state: &AppState,
+    claims: &Claims,
+    permission: Permission,
+) -> Result<bool, Response> {
+    has_permission(state, claims, permission)
+        .await
+        .map_err(|status| err_status(status, "Failed to authorize request").into_response())
+}
+
+async fn require_permission(
+    state: &AppState,
+    claims: &Claims,
+    permission: Permission,
+) -> Result<(), Response> {
+    if principal_can(state, claims, permission).await? {
+        Ok(())
+    } else {
+        let message = format!("Permission required: {permission}");
+        Err(err_status(StatusCode::FORBIDDEN, message).into_response())
+    }
+}
+
+/// Caller's role in the organization. Holders of `orgs:read` get `None`
+/// instead of 403.
+async fn member_or_admin(
+    state: &AppState,
+    claims: &Claims,
+    org_id: &str,
+) -> Result<Option<OrgRole>, Response> {
+    match OrganizationService::role(&state.db, org_id, &claims.sub).await {
+        Ok(role) => Ok(Some(role)),
+        Err(UserServiceError::PermissionDenied(msg)) => {
+            if principal_can(state, claims, Permission::OrgsRead).await? {
+                Ok(None)
+            } else {
+                Err(org_error(UserServiceError::PermissionDenied(msg)))
+            }
+        }
+        Err(e) => Err(org_error(e)),
+    }
+}
+
+#[tracing::instrument(skip_all)]
+async fn list_orgs(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<ListOrgsQuery>,
+) -> Response {
+    if query.all {
+        if let Err(response) = require_permission(&state, &claims, Permission::OrgsRead).await {
+            return response;
+        }
+        return match OrganizationService::list_all(&state.db).await {
+            Ok(orgs) => ok(orgs
+                .into_iter()
+                .map(|org| OrgSummary { org, role: None })
+                .collect::<Vec<_>>())
+            .into_response(),
+            Err(e) => org_error(e),
+        };
+    }
+
+    match OrganizationService::list_for_user(&state.db, &claims.sub).await {
+        Ok(orgs) => ok(orgs
+            .into_iter()
+            .map(|(org, role)| OrgSummary {
+                org,
+                role: Some(role),
+            })
+            .collect::<Vec<_>>())
+        .into_response(),
+        Err(e) => org_error(e),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(name = %payload.name))]
+async fn create_org(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateOrgRequest>,
+) -> Response {
+    match OrganizationService::create(&state.db, &claims.sub, &payload.name).await {
+        Ok(org) => {
+            tracing::info!(org_id = %org.id, "Organization created");
+            let event = AuditEvent::new("org.create").target(&org.id).after(&org);
+            audit::record(&state, &claims, &headers, event).await;
+            ok(OrgSummary {
+                org,
+                role: Some(OrgRole::Owner),
+            })
+            .into_response()
+        }
+        Err(e) => org_error(e),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(org_id = %org_id))]
+async fn get_org(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(org_id): Path<String>,
+) -> Response {
+    let role = match member_or_admin(&state, &claims, &org_id).await {
+        Ok(role) => role,
+        Err(response) => return response,
+    };
+    match OrganizationService::get(&state.db, &org_id).await {
+        Ok(org) => ok(OrgSummary { org, role }).into_response(),
+        Err(e) => org_error(e),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(org_id = %org_id))]
+async fn update_org(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Path(org_id): Path<String>,
+    Json(payload): Json<UpdateOrgRequest>,
+) -> Response {
+    let before = OrganizationService::get(&state.db, &org_id).await.ok();
+    if let Some(active) = payload.active {
+        if let Err(response) = require_permission(&state, &claims, Permission::OrgsWrite).await {
+            return response;
+        }
+        if let Err(e) = OrganizationService::set_active(&state.db, &org_id, active).await {
+            return org_error(e);
+        }
+    }
+    if let Some(name) = payload.name.as_deref() {
+        if let Err(e) = OrganizationService::rename(&state.db, &org_id, &claims.sub, name).await {
+            return org_error(e);
+        }
+    }
+    if let Some(limit) = payload.spend_limit {
+        if let Err(e) =
+            OrganizationService::set_spend_limit(&state.db, &org_id, &claims.sub, limit).await
+        {
+            return org_error(e);
+        }
+    }
+    match OrganizationService::get(&state.db, &org_id).await {
+        Ok(org) => {
+            let event = AuditEvent::new("org.update")
+                .target(&org_id)
+                .before(&before)
+                .after(&org);
+            audit::record(&state, &claims, &headers, event).await;
+            ok(org).into_response()
+        }
+        Err(e) => org_error(e),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(org_id = %org_id, amount = payload.amount))]
+async fn topup_org(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Path(org_id): Path<String>,
+    Json(payload): Json<OrgTopupRequest>,
+) -> Response {
+    let currency = payload.currency.unwrap_or_else(|| "USD".to_string());
+    match OrganizationService::topup(&state.db, &org_id, payload.amount, &currency).await {
+        Ok(org) => {
+            tracing::info!(currency, "Organization wallet topped up");
+            let event = AuditEvent::new("org.topup")
+                .target(&org_id)
+                .after(&serde_json::json!({
+                    "amount": payload.amount,
+                    "currency": currency,
+                }));
+            audit::record(&state, &claims, &headers, event).await;
+            ok(org).into_response()
+        }
+        Err(e) => org_error(e),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(org_id = %org_id))]
+async fn list_members(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(org_id): Path<String>,
+) -> Response {
+    match OrganizationService::list_members(&state.db, &org_id, &claims.sub).await {
+        Ok(members) => ok(members).into_response(),
+        Err(e) => org_error(e),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(org_id = %org_id, user_id = %user_id))]
+async fn update_member(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Path((org_id, user_id)): Path<(String, String)>,
+    Json(payload): Json<UpdateMemberRequest>,
+) -> Response {
+    match OrganizationService::update_member_role(
+        &state.db,
+        &org_id,
+        &claims.sub,
+        &user_id,
+        payload.role,
+    )
+    .await
+    {
+        Ok(()) => {
+            let event = AuditEvent::new("org.member_role")
+                .target(&org_id)
+                .after(&serde_json::json!({ "user_id": user_id, "role": payload.role }));
+            audit::record(&state, &claims, &headers, event).await;
+            ok(serde_json::json!({ "status": "updated" })).into_response()
+        }
+        Err(e) => org_error(e),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(org_id = %org_id, user_id = %user_id))]
+async fn remove_member(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Path((org_id, user_id)): Path<(String, String)>,
+) -> Response {
+    match OrganizationService::remove_member(&state.db, &org_id, &claims.sub, &user_id).await {
+        Ok(()) => {
+            let event = AuditEvent::new("org.member_remove")
+                .target(&org_id)
+                .before(&serde_json::json!({ "user_id": user_id }));
+            audit::record(&state, &claims, &headers, event).await;
+            ok(serde_json::json!({ "status": "removed" })).into_response()
+        }
+        Err(e) => org_error(e),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(org_id = %org_id))]
+async fn list_invitations(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(org_id): Path<String>,
+) -> Response {
+    match OrganizationService::list_invitations(&state.db, &org_id, &claims.sub).await {
+        Ok(invitations) => ok(invitations
+            .into_iter()
+            .map(InvitationSummary::from)
+            .collect::<Vec<_>>())
+        .into_response(),
+        Err(e) => org_error(e),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(org_id = %org_id, role = %payload.role))]
+async fn create_invitation(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Path(org_id): Path<String>,
+    Json(payload): Json<CreateInvitationRequest>,
+) -> Response {
+    match OrganizationService::invite(
+        &state.db,
+        &org_id,
+        &claims.sub,
+        &payload.email,
+        payload.role,
+    )
+    .await
+    {
+        Ok(invitation) => {
+            tracing::info!("Organization invitation created");
+            // Creation is the single disclosure point for the accept code.
+            let code = invitation.code.clone();
+            let summary = InvitationSummary::from(invitation);
+            let event = AuditEvent::new("org.invite")
+                .target(&org_id)
+                .after(&summary);
+            audit::record(&state, &claims, &headers, event).await;
+            ok(serde_json::json!({
+                "code": code,
+                "invitation": summary,
+            }))
+            .into_response()
+        }
+        Err(e) => org_error(e),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(org_id = %org_id))]
+async fn revoke_invitation(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Path((org_id, reference)): Path<(String, String)>,
+) -> Response {
+    let invitations =
+        match OrganizationService::list_invitations(&state.db, &org_id, &claims.sub).await {
+            Ok(invitations) => invitations,
+            Err(e) => return org_error(e),
+        };
+    let Some(invitation) = invitations
+        .into_iter()
+        .find(|invitation| invitation_ref(&invitation.code) == reference)
+    else {
+        return err_status(StatusCode::NOT_FOUND, "Invitation not found").into_response();
+    };
+    match OrganizationService::revoke_invitation(&state.db, &org_id, &claims.sub, &invitation.code)
+        .await
+    {
+        Ok(()) => {
+            let event = AuditEvent::new("org.invitation_revoke")
+                .target(&org_id)
+                .before(&InvitationSummary::from(invitation));
+            audit::record(&state, &claims, &headers, event).await;
+            ok(serde_json::json!({ "status": "revoked" })).into_response()
+        }
+        Err(e) => org_error(e),
+    }
+}
+
+#[tracing::instrument(skip_all)]
+async fn accept_invitation(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Path(code): Path<String>,
+) -> Response {
+    match OrganizationService::accept_invitation(&state.db, &code, &claims.sub).await {
+        Ok(org) => {
+            let role = OrganizationService::role(&state.db, &org.id, &claims.sub)
+                .await
+                .ok();
+            tracing::info!(org_id = %org.id, "Organization invitation accepted");
+            let event = AuditEvent::new("org.invitation_accept")
+                .target(&org.id)
+                .after(&serde_json::json!({ "user_id": claims.sub, "role": role }));
+            audit::record(&state, &claims, &headers, event).await;
+            ok(OrgSummary { org, role }).into_response()
+        }
+        Err(e) => org_error(e),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(org_id = %org_id))]
+async fn list_org_tokens(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(org_id): Path<String>,
+) -> Response {
+    if let Err(response) = member_or_admin(&state, &claims, &org_id).await {
+        return response;
+    }
+    match TokenService::list(&state.db).await {
+        Ok(tokens) => ok(tokens
+            .into_iter()
+            .filter(|token| token.org_id.as_deref() == Some(org_id.as_str()))
+            .map(TokenSummary::from)
+            .collect::<Vec<_>>())
+        .into_response(),
+        Err(e) => {
+            tracing::error!("[API] list_org_tokens error: {}", e);
+            err_status(StatusCode::INTERNAL_SERVER_ERROR, e).into_response()
+        }
+    }
+}
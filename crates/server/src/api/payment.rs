@@ -0,0 +1,217 @@
+//! Online top-up through payment providers.
+//!
+//! Customers start a checkout and follow its URL (or scan its QR code); the
+//! provider's webhook, posted to the public
+//! `/console/api/payments/webhook/{provider}` route and authenticated by its
+//! signature, credits the wallet. Holders of `billing:topup` list every
+//! payment (`?all=true`) and refund them. A checkout with `save_method` keeps
+//! the card under the caller's own provider customer; `/methods` lists those
+//! cards for auto top-up.
+
+use crate::api::audit;
+use crate::api::auth::{has_permission, Claims};
+use crate::api::response::{err_status, ok};
+use crate::AppState;
+use axum::{
+    body::Bytes,
+    extract::{Extension, Json, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Router,
+};
+use burncloud_service_audit::AuditEvent;
+use burncloud_service_payment::{PaymentError, PaymentService};
+use burncloud_service_user::Permission;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct ListParams {
+    /// Payments of every user; needs `billing:topup`
+    #[serde(default)]
+    all: bool,
+}
+
+#[derive(Deserialize)]
+struct CheckoutDto {
+    provider: String,
+    /// Amount in the minor unit of `currency` (cents, fen)
+    amount: i64,
+    /// Defaults to the first currency the provider accepts
+    #[serde(default)]
+    currency: Option<String>,
+    /// Save the payment method for auto top-up
+    #[serde(default)]
+    save_method: bool,
+}
+
+#[derive(Deserialize)]
+struct MethodsParams {
+    provider: String,
+}
+
+#[derive(Deserialize)]
+struct RefundDto {
+    /// Minor units to refund; the whole remainder when omitted
+    #[serde(default)]
+    amount: Option<i64>,
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+/// Authenticated routes
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/console/api/payments", get(list_payments))
+        .route("/console/api/payments/options", get(payment_options))
+        .route("/console/api/payments/checkout", post(create_checkout))
+        .route("/console/api/payments/methods", get(saved_methods))
+        .route("/console/api/payments/{id}/refund", post(refund_payment))
+}
+
+/// Public webhook endpoint, authenticated by the provider's signature
+pub fn webhook_routes() -> Router<AppState> {
+    Router::new().route(
+        "/console/api/payments/webhook/{provider}",
+        post(receive_webhook),
+    )
+}
+
+fn payment_error(e: PaymentError) -> Response {
+    let status = match e {
+        PaymentError::UnknownProvider(_) | PaymentError::NotFound => StatusCode::NOT_FOUND,
+        PaymentError::InvalidInput(_)
+        | PaymentError::InvalidSignature
+        | PaymentError::MalformedWebhook(_) => StatusCode::BAD_REQUEST,
+        PaymentError::Provider(_) => StatusCode::BAD_GATEWAY,
+        PaymentError::Config(_) | PaymentError::Database(_) => {
+            tracing::error!("[API] payment error: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+    err_status(status, e).into_response()
+}
+
+#[tracing::instrument(skip_all)]
+async fn payment_options(State(state): State<AppState>) -> Response {
+    ok(state.payments.options()).into_response()
+}
+
+#[tracing::instrument(skip_all)]
+async fn list_payments(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(params): Query<ListParams>,
+) -> Response {
+    let owner = if params.all {
+        match has_permission(&state, &claims, Permission::BillingTopup).await {
+            Ok(true) => None,
+            Ok(false) => return StatusCode::FORBIDDEN.into_response(),
+            Err(status) => return status.into_response(),
+        }
+    } else {
+        Some(claims.sub.as_str())
+    };
+    match PaymentService::list(&state.db, owner).await {
+        Ok(payments) => ok(payments).into_response(),
+        Err(e) => payment_error(e),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %claims.sub, provider = %payload.provider))]
+async fn create_checkout(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<CheckoutDto>,
+) -> Response {
+    match state
+        .payments
+        .create_checkout(
+            &state.db,
+            &claims.sub,
+            &payload.provider,
+            payload.amount,
+            payload.currency.as_deref(),
+            payload.save_method,
+        )
+        .await
+    {
+        Ok(checkout) => ok(checkout).into_response(),
+        Err(e) => payment_error(e),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(user_id = %claims.sub, provider = %params.provider))]
+async fn saved_methods(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(params): Query<MethodsParams>,
+) -> Response {
+    match state
+        .payments
+        .saved_methods(&state.db, &claims.sub, &params.provider)
+        .await
+    {
+        Ok(methods) => ok(methods).into_response(),
+        Err(e) => payment_error(e),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(id = %id))]
+async fn refund_payment(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(payload): Json<RefundDto>,
+) -> Response {
+    let before = match PaymentService::get(&state.db, &id).await {
+        Ok(payment) => payment,
+        Err(e) => return payment_error(e),
+    };
+    match state
+        .payments
+        .refund(&state.db, &id, payload.amount, payload.reason)
+        .await
+    {
+        Ok(after) => {
+            let event = AuditEvent::new("payment.refund")
+                .target(&id)
+                .before(&before)
+                .after(&after);
+            audit::record(&state, &claims, &headers, event).await;
+            ok(after).into_response()
+        }
+        Err(e) => payment_error(e),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(provider = %provider))]
+async fn receive_webhook(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    match state
+        .payments
+        .handle_webhook(&state.db, &provider, &headers, &body)
+        .await
+    {
+        Ok(changed) => {
+            if let Some(payment) = changed {
+                tracing::info!(
+                    payment_id = %payment.id,
+                    status = %payment.status,
+                    "Payment webhook applied"
+                );
+            }
+            let ack = state.payments.webhook_ack(&provider).unwrap_or_default();
+            (StatusCode::OK, ack).into_response()
+        }
+        Err(e) => {
+            tracing::warn!("Rejected payment webhook: {}", e);
+            payment_error(e)
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Role management: built-in presets and custom roles with their permissions.
+//!
+//! Presets are read-only. Users are bound to roles through
+//! `POST /console/api/user/roles`; permissions apply on their next request.
+
+use crate::api::audit;
+use crate::api::auth::{self, Claims};
+use crate::api::response::{err_status, ok};
+use crate::AppState;
+use axum::{
+    extract::{Extension, Json, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, put},
+    Router,
+};
+use burncloud_service_audit::AuditEvent;
+use burncloud_service_user::{Permission, RoleService, UserServiceError};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+struct CreateRoleRequest {
+    name: String,
+    description: Option<String>,
+    #[serde(default)]
+    permissions: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct UpdateRoleRequest {
+    description: Option<String>,
+    #[serde(default)]
+    permissions: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct PermissionInfo {
+    name: &'static str,
+    description: &'static str,
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/console/api/roles", get(list_roles).post(create_role))
+        .route("/console/api/roles/permissions", get(list_permissions))
+        .route(
+            "/console/api/roles/{name}",
+            put(update_role).delete(delete_role),
+        )
+}
+
+/// Map role service errors onto the HTTP contract.
+pub(crate) fn role_error(e: UserServiceError) -> Response {
+    match e {
+        UserServiceError::RoleNotFound => {
+            err_status(StatusCode::NOT_FOUND, "Role not found").into_response()
+        }
+        UserServiceError::UserNotFound => {
+            err_status(StatusCode::NOT_FOUND, "User not found").into_response()
+        }
+        UserServiceError::RoleExists => err_status(StatusCode::CONFLICT, e).into_response(),
+        UserServiceError::PermissionDenied(_) => {
+            err_status(StatusCode::FORBIDDEN, e).into_response()
+        }
+        UserServiceError::InvalidInput(_) => err_status(StatusCode::BAD_REQUEST, e).into_response(),
+        e => {
+            tracing::error!("[API] role error: {}", e);
+            err_status(StatusCode::INTERNAL_SERVER_ERROR, e).into_response()
+        }
+    }
+}
+
+#[tracing::instrument(skip_all)]
+async fn list_roles(State(state): State<AppState>) -> Response {
+    match RoleService::list(&state.db).await {
+        Ok(roles) => ok(roles).into_response(),
+        Err(e) => role_error(e),
+    }
+}
+
+/// Every permission a role can grant
+async fn list_permissions() -> Response {
+    let permissions: Vec<PermissionInfo> = Permission::ALL
+        .iter()
+        .map(|p| PermissionInfo {
+            name: p.as_str(),
+            description: p.description(),
+        })
+        .collect();
+    ok(permissions).into_response()
+}
+
+#[tracing::instrument(skip_all, fields(name = %payload.name))]
+async fn create_role(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateRoleRequest>,
+) -> Response {
+    let granted = match auth::permissions(&state, &claims).await {
+        Ok(granted) => granted,
+        Err(status) => return status.into_response(),
+    };
+    match RoleService::create(
+        &state.db,
+        &granted,
+        &payload.name,
+        payload.description.as_deref(),
+        &payload.permissions,
+    )
+    .await
+    {
+        Ok(role) => {
+            let event = AuditEvent::new("role.create")
+                .target(&role.name)
+                .after(&role);
+            audit::record(&state, &claims, &headers, event).await;
+            ok(role).into_response()
+        }
+        Err(e) => role_error(e),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(name = %name))]
+async fn update_role(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    Json(payload): Json<UpdateRoleRequest>,
+) -> Response {
+    let granted = match auth::permissions(&state, &claims).await {
+        Ok(granted) => granted,
+        Err(status) => return status.into_response(),
+    };
+    let before = RoleService::get(&state.db, &name).await.ok();
+    match RoleService::update(
+        &state.db,
+        &granted,
+        &name,
+        payload.description.as_deref(),
+        &payload.permissions,
+    )
+    .await
+    {
+        Ok(role) => {
+            let event = AuditEvent::new("role.update")
+                .target(&name)
+                .before(&before)
+                .after(&role);
+            audit::record(&state, &claims, &headers, event).await;
+            ok(role).into_response()
+        }
+        Err(e) => role_error(e),
+    }
+}
+
+#[tracing::instrument(skip_all, fields(name = %name))]
+async fn delete_role(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> Response {
+    let before = RoleService::get(&state.db, &name).await.ok();
+    match RoleService::delete(&state.db, &name).await {
+        Ok(()) => {
+            let event = AuditEvent::new("role.delete").target(&name).before(&before);
+            audit::record(&state, &claims, &headers, event).await;
+            ok(serde_json::json!({ "name": name })).into_response()
+        }
+        Err(e) => role_error(e),
+    }
+}
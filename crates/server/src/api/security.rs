@@ -1,15 +1,19 @@
 // HTTP service — API response parsing — Value required; no feasible typed alternative.
 #![allow(clippy::disallowed_types)]
 
+use crate::api::audit;
+use crate::api::auth::Claims;
 use crate::api::response::err;
 use crate::AppState;
 use axum::{
-    extract::{Query, State},
+    extract::{Extension, Query, State},
+    http::HeaderMap,
     response::IntoResponse,
     routing::{get, post},
     Json, Router,
 };
 use burncloud_database::sqlx::Row;
+use burncloud_service_audit::AuditEvent;
 use burncloud_service_router_log::{RouterLog, RouterLogService};
 use serde::{Deserialize, Serialize};
 
@@ -349,13 +353,30 @@ async fn security_filters_get(State(state): State<AppState>) -> impl IntoRespons
     }
 }
 
+/// The stored filter configuration, if one has been saved.
+async fn stored_filter_config(state: &AppState) -> Option<FilterConfig> {
+    let rows = state
+        .db
+        .query_with_params(
+            "SELECT * FROM sys_settings WHERE name = ?1",
+            vec!["security_filters".to_string()],
+        )
+        .await
+        .ok()?;
+    let val: String = rows.first()?.try_get("value").ok()?;
+    serde_json::from_str(&val).ok()
+}
+
 /// PUT /console/api/monitor/security/filters
 ///
 /// Update the security filter configuration in settings KV.
 async fn security_filters_put(
     State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
     Json(config): Json<FilterConfig>,
 ) -> impl IntoResponse {
+    let before = stored_filter_config(&state).await;
     let json = match serde_json::to_string(&config) {
         Ok(j) => j,
         Err(e) => {
@@ -376,15 +397,21 @@ async fn security_filters_put(
         return err("Failed to save filter config").into_response();
     }
 
+    let event = AuditEvent::new("security.filters")
+        .before(&before)
+        .after(&config);
+    audit::record(&state, &claims, &headers, event).await;
     Json(FilterConfigResponse::from(config)).into_response()
 }
 
 /// POST /console/api/monitor/security/emergency-circuit-break
 ///
 /// Proxy to router's internal trip-all endpoint. Requires a reason.
-#[tracing::instrument(skip(_state), fields(reason = %body.reason))]
+#[tracing::instrument(skip(state, claims, headers), fields(reason = %body.reason))]
 async fn security_emergency_circuit_break(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
     Json(body): Json<EmergencyBreakRequest>,
 ) -> impl IntoResponse {
     if body.reason.trim().is_empty() {
@@ -403,6 +430,9 @@ async fn security_emergency_circuit_break(
     .await
     {
         Ok(data) => {
+            let event = AuditEvent::new("security.emergency_circuit_break")
+                .after(&serde_json::json!({ "reason": body.reason }));
+            audit::record(&state, &claims, &headers, event).await;
             let mut resp = serde_json::Map::new();
             resp.insert("success".into(), serde_json::Value::Bool(true));
             resp.insert("data".into(), data);
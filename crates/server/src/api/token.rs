@@ -1,14 +1,19 @@
-use crate::api::auth::{is_admin, Claims};
+use crate::api::audit;
+use crate::api::auth::{has_permission, Claims};
 use crate::AppState;
 use axum::{
     body::Body,
     extract::{Extension, Json, Path, State},
-    http::{header, Request, StatusCode},
+    http::{header, HeaderMap, Request, StatusCode},
     response::{IntoResponse, Response},
     routing::{get, post},
     Router,
 };
+use burncloud_common::{ModelPolicy, RateLimits};
+use burncloud_service_audit::AuditEvent;
+use burncloud_service_notification::NotificationService;
 use burncloud_service_token::{RouterToken, TokenService};
+use burncloud_service_user::{OrgRole, OrganizationService, Permission};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use tower::ServiceExt;
@@ -21,6 +26,16 @@ pub struct CreateTokenRequest {
     pub user_id: String,
     /// Spend limit in nanodollars. `-1` means unlimited.
     pub quota_limit: Option<i64>,
+    /// Organization that owns the token and pays for its usage.
+    /// Requires the owner, admin or developer role in that organization.
+    #[serde(default)]
+    pub org_id: Option<String>,
+    /// Model allow/deny globs and per-model spend caps.
+    #[serde(default)]
+    pub model_policy: Option<ModelPolicy>,
+    /// RPM / TPM / concurrency limits; omitted fields are unlimited.
+    #[serde(default, flatten)]
+    pub rate_limits: RateLimits,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -43,6 +58,12 @@ pub struct SetIpWhitelistRequest {
     pub ip_whitelist: String,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SetModelPolicyRequest {
+    /// New policy; `null` removes every model restriction.
+    pub model_policy: Option<ModelPolicy>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 struct PlaygroundMessage {
     role: String,
@@ -64,7 +85,7 @@ struct PlaygroundChatRequest {
 /// compatibility with existing console DTOs; it is not accepted as a data-plane
 /// bearer credential. The human-readable `token_hint` is safe to display.
 #[derive(Debug, Serialize)]
-struct TokenSummary {
+pub(crate) struct TokenSummary {
     token: String,
     token_hint: String,
     user_id: String,
@@ -79,9 +100,13 @@ struct TokenSummary {
     key_prefix: String,
     created_at: i64,
     last_rotated_at: i64,
+    org_id: Option<String>,
+    model_policy: Option<ModelPolicy>,
+    #[serde(flatten)]
+    rate_limits: RateLimits,
 }
 
-fn token_hint(token: &RouterToken) -> String {
+pub(crate) fn token_hint(token: &RouterToken) -> String {
     let suffix: String = token
         .token
         .chars()
@@ -99,7 +124,7 @@ fn token_hint(token: &RouterToken) -> String {
 /// The source token is generated with high entropy. Returning its SHA-256 digest
 /// lets the authenticated management plane address the record without disclosing
 /// a credential that can be used against `/v1/*`.
-fn token_management_id(token: &str) -> String {
+pub(crate) fn token_management_id(token: &str) -> String {
     let digest = Sha256::digest(token.as_bytes());
     format!("tok_{digest:x}")
 }
@@ -108,6 +133,7 @@ impl From<RouterToken> for TokenSummary {
     fn from(token: RouterToken) -> Self {
         let management_id = token_management_id(&token.token);
         let hint = token_hint(&token);
+        let rate_limits = token.rate_limits();
         Self {
             token: management_id,
             token_hint: hint,
@@ -123,6 +149,12 @@ impl From<RouterToken> for TokenSummary {
             key_prefix: token.key_prefix,
             created_at: token.created_at,
             last_rotated_at: token.last_rotated_at,
+            org_id: token.org_id,
+            model_policy: token
+                .model_policy
+                .as_deref()
+                .and_then(|raw| ModelPolicy::parse(raw).ok()),
+            rate_limits,
         }
     }
 }
@@ -143,24 +175,38 @@ pub fn routes() -> Router<AppState> {
             "/console/api/tokens/{token_ref}/ip-whitelist",
             post(set_ip_whitelist),
         )
+        .route(
+            "/console/api/tokens/{token_ref}/model-policy",
+            post(set_model_policy),
+        )
+        .route(
+            "/console/api/tokens/{token_ref}/rate-limits",
+            post(set_rate_limits),
+        )
         .route("/console/api/playground/chat", post(playground_chat))
 }
 
-async fn principal_is_admin(state: &AppState, claims: &Claims) -> Result<bool, Response> {
-    is_admin(state, claims)
+/// Whether the principal may act on every user's tokens with `permission`
+async fn principal_can(
+    state: &AppState,
+    claims: &Claims,
+    permission: Permission,
+) -> Result<bool, Response> {
+    has_permission(state, claims, permission)
         .await
         .map_err(|status| err_status(status, "Failed to authorize request").into_response())
 }
 
 /// Resolve either the opaque management reference or, for backwards-compatible
 /// authenticated management calls, the exact bearer token. The latter is never
-/// returned by list/get responses.
+/// returned by list/get responses. Tokens of other users need `permission`.
 pub(crate) async fn authorized_token(
     state: &AppState,
     claims: &Claims,
     token_ref: &str,
+    permission: Permission,
 ) -> Result<RouterToken, Response> {
-    let admin = principal_is_admin(state, claims).await?;
+    let admin = principal_can(state, claims, permission).await?;
     let tokens = TokenService::list(&state.db).await.map_err(|e| {
         tracing::error!(error = %e, "Failed to load API token for authorization");
         err_status(
@@ -177,10 +223,41 @@ pub(crate) async fn authorized_token(
     };
 
     if admin || record.user_id == claims.sub {
-        Ok(record)
-    } else {
-        Err(err_status(StatusCode::FORBIDDEN, "Token access denied").into_response())
+        return Ok(record);
     }
+    // Organization owners and admins manage every token the organization owns
+    if let Some(org_id) = record.org_id.as_deref() {
+        if let Ok(role) = OrganizationService::role(&state.db, org_id, &claims.sub).await {
+            if role.can_manage_members() {
+                return Ok(record);
+            }
+        }
+    }
+    Err(err_status(StatusCode::FORBIDDEN, "Token access denied").into_response())
+}
+
+/// Record a change to a token. `current` is the bearer the record is stored
+/// under now, which differs from `before.token` only after a rotation.
+async fn audit_token(
+    state: &AppState,
+    claims: &Claims,
+    headers: &HeaderMap,
+    action: &str,
+    before: &RouterToken,
+    current: &str,
+) {
+    let after = match TokenService::list(&state.db).await {
+        Ok(tokens) => tokens
+            .into_iter()
+            .find(|t| t.token == current)
+            .map(TokenSummary::from),
+        Err(_) => None,
+    };
+    let event = AuditEvent::new(action)
+        .target(token_management_id(current))
+        .before(&TokenSummary::from(before.clone()))
+        .after(&after);
+    audit::record(state, claims, headers, event).await;
 }
 
 #[tracing::instrument(skip_all)]
@@ -188,7 +265,7 @@ async fn list_tokens(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
 ) -> impl IntoResponse {
-    let admin = match principal_is_admin(&state, &claims).await {
+    let admin = match principal_can(&state, &claims, Permission::TokensRead).await {
         Ok(value) => value,
         Err(response) => return response,
     };
@@ -213,9 +290,10 @@ async fn list_tokens(
 async fn create_token(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
     Json(payload): Json<CreateTokenRequest>,
 ) -> impl IntoResponse {
-    let admin = match principal_is_admin(&state, &claims).await {
+    let admin = match principal_can(&state, &claims, Permission::TokensWrite).await {
         Ok(value) => value,
         Err(response) => return response,
     };
@@ -226,6 +304,19 @@ async fn create_token(
         )
         .into_response();
     }
+    if let Some(org_id) = payload.org_id.as_deref() {
+        if let Err(response) = require_org_token_role(&state, org_id, &claims, admin).await {
+            return response;
+        }
+    }
+
+    let model_policy = match encode_model_policy(payload.model_policy.as_ref()) {
+        Ok(value) => value,
+        Err(message) => return err_status(StatusCode::BAD_REQUEST, message).into_response(),
+    };
+    if let Err(message) = payload.rate_limits.validate() {
+        return err_status(StatusCode::BAD_REQUEST, message).into_response();
+    }
 
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -250,11 +341,20 @@ async fn create_token(
         key_prefix: "bc_live_".to_string(),
         created_at: now,
         last_rotated_at: 0,
+        org_id: payload.org_id,
+        model_policy,
+        rpm_limit: payload.rate_limits.rpm_limit,
+        tpm_limit: payload.rate_limits.tpm_limit,
+        max_concurrency: payload.rate_limits.max_concurrency,
     };
 
     match TokenService::create(&state.db, &db_token).await {
         Ok(_) => {
             tracing::info!(user_id, "API token created");
+            let event = AuditEvent::new("token.create")
+                .target(token_management_id(&token_str))
+                .after(&TokenSummary::from(db_token));
+            audit::record(&state, &claims, &headers, event).await;
             // Creation is the single disclosure point for the bearer secret.
             ok(serde_json::json!({
                 "status": "created",
@@ -269,13 +369,44 @@ async fn create_token(
     }
 }
 
+/// Validate a policy and serialize it for storage; an empty policy is stored as `NULL`.
+fn encode_model_policy(policy: Option<&ModelPolicy>) -> Result<Option<String>, String> {
+    let Some(policy) = policy.filter(|p| !p.is_empty()) else {
+        return Ok(None);
+    };
+    policy.validate()?;
+    serde_json::to_string(policy)
+        .map(Some)
+        .map_err(|e| format!("invalid model policy: {e}"))
+}
+
+/// Platform admins may attach a token to any existing organization; everyone
+/// else needs a role that can create organization tokens.
+async fn require_org_token_role(
+    state: &AppState,
+    org_id: &str,
+    claims: &Claims,
+    admin: bool,
+) -> Result<(), Response> {
+    let result = if admin {
+        OrganizationService::get(&state.db, org_id)
+            .await
+            .map(|_| ())
+    } else {
+        OrganizationService::require(&state.db, org_id, &claims.sub, OrgRole::can_create_tokens)
+            .await
+            .map(|_| ())
+    };
+    result.map_err(crate::api::org::org_error)
+}
+
 #[tracing::instrument(skip_all)]
 async fn get_token(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
     Path(token_ref): Path<String>,
 ) -> impl IntoResponse {
-    match authorized_token(&state, &claims, &token_ref).await {
+    match authorized_token(&state, &claims, &token_ref, Permission::TokensRead).await {
         Ok(record) => ok(TokenSummary::from(record)).into_response(),
         Err(response) => response,
     }
@@ -285,16 +416,29 @@ async fn get_token(
 async fn update_token(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
     Path(token_ref): Path<String>,
     Json(payload): Json<UpdateTokenRequest>,
 ) -> impl IntoResponse {
-    let record = match authorized_token(&state, &claims, &token_ref).await {
+    let record = match authorized_token(&state, &claims, &token_ref, Permission::TokensWrite).await
+    {
         Ok(record) => record,
         Err(response) => return response,
     };
 
     match TokenService::update_status(&state.db, &record.token, &payload.status).await {
-        Ok(_) => ok(serde_json::json!({ "status": "updated" })).into_response(),
+        Ok(_) => {
+            audit_token(
+                &state,
+                &claims,
+                &headers,
+                "token.update",
+                &record,
+                &record.token,
+            )
+            .await;
+            ok(serde_json::json!({ "status": "updated" })).into_response()
+        }
         Err(e) => {
             tracing::error!("[API] update_token error: {}", e);
             err(e).into_response()
@@ -306,15 +450,26 @@ async fn update_token(
 async fn delete_token(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
     Path(token_ref): Path<String>,
 ) -> impl IntoResponse {
-    let record = match authorized_token(&state, &claims, &token_ref).await {
+    let record = match authorized_token(&state, &claims, &token_ref, Permission::TokensWrite).await
+    {
         Ok(record) => record,
         Err(response) => return response,
     };
 
     match TokenService::delete(&state.db, &record.token).await {
-        Ok(_) => ok(serde_json::json!({ "status": "deleted" })).into_response(),
+        Ok(_) => {
+            if let Err(e) = NotificationService::token_deleted(&state.db, &record.token).await {
+                tracing::warn!("[API] failed to drop notification thresholds: {}", e);
+            }
+            let event = AuditEvent::new("token.delete")
+                .target(token_management_id(&record.token))
+                .before(&TokenSummary::from(record));
+            audit::record(&state, &claims, &headers, event).await;
+            ok(serde_json::json!({ "status": "deleted" })).into_response()
+        }
         Err(e) => {
             tracing::error!("[API] delete_token error: {}", e);
             err(e).into_response()
@@ -326,10 +481,12 @@ async fn delete_token(
 async fn rotate_token(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
     Path(token_ref): Path<String>,
     Json(payload): Json<RotateTokenRequest>,
 ) -> impl IntoResponse {
-    let record = match authorized_token(&state, &claims, &token_ref).await {
+    let record = match authorized_token(&state, &claims, &token_ref, Permission::TokensWrite).await
+    {
         Ok(record) => record,
         Err(response) => return response,
     };
@@ -344,6 +501,21 @@ async fn rotate_token(
     {
         Ok(result) => {
             tracing::info!(new_version = result.key_version, "API token rotated");
+            if let Err(e) =
+                NotificationService::token_rotated(&state.db, &record.token, &result.new_token)
+                    .await
+            {
+                tracing::warn!("[API] failed to move notification thresholds: {}", e);
+            }
+            audit_token(
+                &state,
+                &claims,
+                &headers,
+                "token.rotate",
+                &record,
+                &result.new_token,
+            )
+            .await;
             // Rotation is the single disclosure point for the new bearer secret.
             ok(result).into_response()
         }
@@ -358,15 +530,28 @@ async fn rotate_token(
 async fn revoke_old_key(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
     Path(token_ref): Path<String>,
 ) -> impl IntoResponse {
-    let record = match authorized_token(&state, &claims, &token_ref).await {
+    let record = match authorized_token(&state, &claims, &token_ref, Permission::TokensWrite).await
+    {
         Ok(record) => record,
         Err(response) => return response,
     };
 
     match TokenService::revoke_old_key(&state.db, &record.token).await {
-        Ok(true) => ok(serde_json::json!({ "status": "revoked" })).into_response(),
+        Ok(true) => {
+            audit_token(
+                &state,
+                &claims,
+                &headers,
+                "token.revoke_old_key",
+                &record,
+                &record.token,
+            )
+            .await;
+            ok(serde_json::json!({ "status": "revoked" })).into_response()
+        }
         Ok(false) => err_status(StatusCode::NOT_FOUND, "Token not found").into_response(),
         Err(e) => {
             tracing::error!("[API] revoke_old_key error: {}", e);
@@ -379,16 +564,29 @@ async fn revoke_old_key(
 async fn set_ip_whitelist(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
     Path(token_ref): Path<String>,
     Json(payload): Json<SetIpWhitelistRequest>,
 ) -> impl IntoResponse {
-    let record = match authorized_token(&state, &claims, &token_ref).await {
+    let record = match authorized_token(&state, &claims, &token_ref, Permission::TokensWrite).await
+    {
         Ok(record) => record,
         Err(response) => return response,
     };
 
     match TokenService::set_ip_whitelist(&state.db, &record.token, &payload.ip_whitelist).await {
-        Ok(true) => ok(serde_json::json!({ "status": "updated" })).into_response(),
+        Ok(true) => {
+            audit_token(
+                &state,
+                &claims,
+                &headers,
+                "token.ip_whitelist",
+                &record,
+                &record.token,
+            )
+            .await;
+            ok(serde_json::json!({ "status": "updated" })).into_response()
+        }
         Ok(false) => err_status(StatusCode::NOT_FOUND, "Token not found").into_response(),
         Err(e) => {
             tracing::error!("[API] set_ip_whitelist error: {}", e);
@@ -397,6 +595,84 @@ async fn set_ip_whitelist(
     }
 }
 
+#[tracing::instrument(skip_all)]
+async fn set_model_policy(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Path(token_ref): Path<String>,
+    Json(payload): Json<SetModelPolicyRequest>,
+) -> impl IntoResponse {
+    let record = match authorized_token(&state, &claims, &token_ref, Permission::TokensWrite).await
+    {
+        Ok(record) => record,
+        Err(response) => return response,
+    };
+    let model_policy = match encode_model_policy(payload.model_policy.as_ref()) {
+        Ok(value) => value,
+        Err(message) => return err_status(StatusCode::BAD_REQUEST, message).into_response(),
+    };
+
+    match TokenService::set_model_policy(&state.db, &record.token, model_policy.as_deref()).await {
+        Ok(true) => {
+            audit_token(
+                &state,
+                &claims,
+                &headers,
+                "token.model_policy",
+                &record,
+                &record.token,
+            )
+            .await;
+            ok(serde_json::json!({ "status": "updated" })).into_response()
+        }
+        Ok(false) => err_status(StatusCode::NOT_FOUND, "Token not found").into_response(),
+        Err(e) => {
+            tracing::error!("[API] set_model_policy error: {}", e);
+            err(e).into_response()
+        }
+    }
+}
+
+/// Replace the token's RPM / TPM / concurrency limits; omitted fields become unlimited.
+#[tracing::instrument(skip_all)]
+async fn set_rate_limits(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Path(token_ref): Path<String>,
+    Json(payload): Json<RateLimits>,
+) -> impl IntoResponse {
+    let record = match authorized_token(&state, &claims, &token_ref, Permission::TokensWrite).await
+    {
+        Ok(record) => record,
+        Err(response) => return response,
+    };
+    if let Err(message) = payload.validate() {
+        return err_status(StatusCode::BAD_REQUEST, message).into_response();
+    }
+
+    match TokenService::set_rate_limits(&state.db, &record.token, &payload).await {
+        Ok(true) => {
+            audit_token(
+                &state,
+                &claims,
+                &headers,
+                "token.rate_limits",
+                &record,
+                &record.token,
+            )
+            .await;
+            ok(serde_json::json!({ "status": "updated" })).into_response()
+        }
+        Ok(false) => err_status(StatusCode::NOT_FOUND, "Token not found").into_response(),
+        Err(e) => {
+            tracing::error!("[API] set_rate_limits error: {}", e);
+            err(e).into_response()
+        }
+    }
+}
+
 /// Execute a console smoke-test request through the same data-plane router used
 /// by `/v1/*`, while keeping the selected bearer secret server-side.
 #[tracing::instrument(skip_all)]
@@ -405,7 +681,14 @@ async fn playground_chat(
     Extension(claims): Extension<Claims>,
     Json(payload): Json<PlaygroundChatRequest>,
 ) -> Response {
-    let record = match authorized_token(&state, &claims, &payload.token_ref).await {
+    let record = match authorized_token(
+        &state,
+        &claims,
+        &payload.token_ref,
+        Permission::TokensWrite,
+    )
+    .await
+    {
         Ok(record) => record,
         Err(response) => return response,
     };
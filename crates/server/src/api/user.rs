@@ -1,14 +1,18 @@
-use crate::api::auth::{is_admin, Claims};
+use crate::api::audit;
+use crate::api::role::role_error;
+use crate::api::auth::{self, session_client, Claims};
 use crate::api::response::{err, err_status, ok};
 use crate::AppState;
 use axum::{
     extract::{Extension, Json, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::IntoResponse,
     routing::{get, post},
     Router,
 };
-use burncloud_service_user::UserServiceError;
+use burncloud_common::RateLimits;
+use burncloud_service_audit::AuditEvent;
+use burncloud_service_user::{RoleService, UserServiceError};
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize)]
@@ -33,12 +37,43 @@ pub struct TopupDto {
     pub currency: Option<String>,
 }
 
+#[derive(Deserialize)]
+pub struct SetRateLimitsDto {
+    pub user_id: String,
+    /// RPM / TPM / concurrency limits; omitted fields are unlimited.
+    #[serde(flatten)]
+    pub limits: RateLimits,
+}
+
+#[derive(Deserialize)]
+pub struct TwoFactorPolicyDto {
+    pub user_id: String,
+    pub required: bool,
+}
+
+#[derive(Deserialize)]
+pub struct TwoFactorResetDto {
+    pub user_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct SetRolesDto {
+    pub user_id: String,
+    /// Role names; replaces every role the user holds
+    pub roles: Vec<String>,
+}
+
 #[derive(Serialize)]
 struct AuthData {
     id: String,
     username: String,
     roles: Vec<String>,
     token: String,
+    /// Set when the token belongs to a session, see `/api/auth/refresh`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    refresh_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<i64>,
 }
 
 #[derive(Serialize)]
@@ -62,13 +97,18 @@ struct UserSummary {
     balance_cny: i64,
     preferred_currency: Option<String>,
     role: String,
+    roles: Vec<String>,
     group: &'static str,
 }
 
 pub fn routes() -> Router<AppState> {
     let authenticated = Router::new()
         .route("/console/api/user/recharges", get(list_recharges))
-        .route("/console/api/list_users", get(list_users));
+        .route("/console/api/list_users", get(list_users))
+        .route("/console/api/user/rate-limits", post(set_rate_limits))
+        .route("/console/api/user/2fa-policy", post(set_two_factor_policy))
+        .route("/console/api/user/2fa-reset", post(reset_two_factor))
+        .route("/console/api/user/roles", post(set_user_roles));
 
     Router::new()
         .route("/console/api/user/register", post(register))
@@ -78,56 +118,183 @@ pub fn routes() -> Router<AppState> {
         .merge(authenticated)
 }
 
-async fn require_admin_or_response(state: &AppState, claims: &Claims) -> Option<axum::response::Response> {
-    match is_admin(state, claims).await {
-        Ok(true) => None,
-        Ok(false) => Some(err_status(StatusCode::FORBIDDEN, "Admin access required").into_response()),
-        Err(status) => Some(err_status(status, "Failed to authorize request").into_response()),
+#[tracing::instrument(skip(state, claims, payload), fields(user_id = %payload.user_id))]
+async fn topup(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Json(payload): Json<TopupDto>,
+) -> impl IntoResponse {
+    let currency = payload.currency.unwrap_or_else(|| "USD".to_string());
+    match state
+        .user_service
+        .topup(&state.db, &payload.user_id, payload.amount, &currency)
+        .await
+    {
+        Ok(receipt) => {
+            let event = AuditEvent::new("user.topup")
+                .target(&payload.user_id)
+                .after(&serde_json::json!({
+                    "amount": payload.amount,
+                    "currency": currency,
+                    "balance": receipt.balance,
+                }));
+            audit::record(&state, &claims, &headers, event).await;
+            ok(TopupData {
+                balance: receipt.balance,
+                currency: receipt.wallet.code().to_string(),
+            })
+            .into_response()
+        }
+        Err(UserServiceError::InvalidInput(message)) => {
+            err_status(StatusCode::BAD_REQUEST, message).into_response()
+        }
+        Err(e) => err(e).into_response(),
     }
 }
 
+/// Limits shared by every token of a user, on top of per-token limits.
 #[tracing::instrument(skip(state, claims, payload), fields(user_id = %payload.user_id))]
-async fn topup(
+async fn set_rate_limits(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
-    Json(payload): Json<TopupDto>,
+    headers: HeaderMap,
+    Json(payload): Json<SetRateLimitsDto>,
 ) -> impl IntoResponse {
-    if let Some(response) = require_admin_or_response(&state, &claims).await {
-        return response;
+    match state
+        .user_service
+        .set_rate_limits(&state.db, &payload.user_id, &payload.limits)
+        .await
+    {
+        Ok(()) => {
+            let event = AuditEvent::new("user.rate_limits")
+                .target(&payload.user_id)
+                .after(&payload.limits);
+            audit::record(&state, &claims, &headers, event).await;
+            ok(payload.limits).into_response()
+        }
+        Err(UserServiceError::UserNotFound) => {
+            err_status(StatusCode::NOT_FOUND, "User not found").into_response()
+        }
+        Err(UserServiceError::InvalidInput(message)) => {
+            err_status(StatusCode::BAD_REQUEST, message).into_response()
+        }
+        Err(e) => err(e).into_response(),
     }
+}
 
-    let currency = payload.currency.unwrap_or_else(|| "USD".to_string());
+/// Make two-factor authentication mandatory (or optional) for a user
+#[tracing::instrument(skip(state, claims, payload), fields(user_id = %payload.user_id))]
+async fn set_two_factor_policy(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Json(payload): Json<TwoFactorPolicyDto>,
+) -> impl IntoResponse {
     match state
         .user_service
-        .topup(&state.db, &payload.user_id, payload.amount, &currency)
+        .set_two_factor_required(&state.db, &payload.user_id, payload.required)
         .await
     {
-        Ok(balance) => ok(TopupData { balance, currency }).into_response(),
+        Ok(()) => {
+            let event = AuditEvent::new("user.2fa_policy")
+                .target(&payload.user_id)
+                .after(&serde_json::json!({ "required": payload.required }));
+            audit::record(&state, &claims, &headers, event).await;
+            ok(serde_json::json!({
+                "user_id": payload.user_id,
+                "required": payload.required,
+            }))
+            .into_response()
+        }
+        Err(UserServiceError::UserNotFound) => {
+            err_status(StatusCode::NOT_FOUND, "User not found").into_response()
+        }
         Err(e) => err(e).into_response(),
     }
 }
 
+/// Remove a user's two-factor enrollment, e.g. after a lost device
+#[tracing::instrument(skip(state, claims, payload), fields(user_id = %payload.user_id))]
+async fn reset_two_factor(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Json(payload): Json<TwoFactorResetDto>,
+) -> impl IntoResponse {
+    match state
+        .user_service
+        .reset_two_factor(&state.db, &payload.user_id)
+        .await
+    {
+        Ok(reset) => {
+            tracing::warn!(admin = %claims.sub, "Two-factor enrollment reset");
+            let event = AuditEvent::new("user.2fa_reset")
+                .target(&payload.user_id)
+                .after(&serde_json::json!({ "reset": reset }));
+            audit::record(&state, &claims, &headers, event).await;
+            ok(serde_json::json!({ "user_id": payload.user_id, "reset": reset })).into_response()
+        }
+        Err(e) => err(e).into_response(),
+    }
+}
+
+/// Replace the roles of a user
+#[tracing::instrument(skip(state, claims, payload), fields(user_id = %payload.user_id))]
+async fn set_user_roles(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Json(payload): Json<SetRolesDto>,
+) -> impl IntoResponse {
+    let granted = match auth::permissions(&state, &claims).await {
+        Ok(granted) => granted,
+        Err(status) => return status.into_response(),
+    };
+    let before = state
+        .user_service
+        .get_user_roles(&state.db, &payload.user_id)
+        .await
+        .ok();
+    match RoleService::set_user_roles(&state.db, &granted, &payload.user_id, &payload.roles).await {
+        Ok(roles) => {
+            tracing::info!(?roles, "User roles changed");
+            let event = AuditEvent::new("user.roles")
+                .target(&payload.user_id)
+                .before(&before.map(|roles| serde_json::json!({ "roles": roles })))
+                .after(&serde_json::json!({ "roles": roles }));
+            audit::record(&state, &claims, &headers, event).await;
+            ok(serde_json::json!({ "user_id": payload.user_id, "roles": roles })).into_response()
+        }
+        Err(e) => role_error(e),
+    }
+}
+
 #[tracing::instrument(skip(state, claims, payload), fields(username = %payload.username))]
 async fn register(
     State(state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
     Json(payload): Json<RegisterDto>,
 ) -> impl IntoResponse {
-    if let Some(response) = require_admin_or_response(&state, &claims).await {
-        return response;
-    }
-
     match state
         .user_service
         .register_user(
             &state.db,
             &payload.username,
             &payload.password,
-            payload.email,
+            payload.email.clone(),
         )
         .await
     {
         Ok(user_id) => {
+            let event = AuditEvent::new("user.register")
+                .target(&user_id)
+                .after(&serde_json::json!({
+                    "username": payload.username,
+                    "email": payload.email,
+                }));
+            audit::record(&state, &claims, &headers, event).await;
             let roles = state
                 .user_service
                 .get_user_roles(&state.db, &user_id)
@@ -136,17 +303,25 @@ async fn register(
 
             match state
                 .user_service
-                .generate_token(&user_id, &payload.username)
+                .start_session(
+                    &state.db,
+                    &user_id,
+                    &payload.username,
+                    &session_client(&headers),
+                )
+                .await
             {
-                Ok(auth_token) => ok(AuthData {
+                Ok(tokens) => ok(AuthData {
                     id: user_id,
                     username: payload.username,
                     roles,
-                    token: auth_token.token,
+                    token: tokens.access_token,
+                    refresh_token: Some(tokens.refresh_token),
+                    expires_at: Some(tokens.expires_at),
                 })
                 .into_response(),
                 Err(e) => {
-                    tracing::error!("Token generation error: {}", e);
+                    tracing::error!("Session creation error: {}", e);
                     err("Registration succeeded but token generation failed").into_response()
                 }
             }
@@ -175,33 +350,49 @@ async fn check_username(
     }
 }
 
-#[tracing::instrument(skip(state, payload), fields(username = %payload.username))]
-async fn login(State(state): State<AppState>, Json(payload): Json<LoginDto>) -> impl IntoResponse {
+#[tracing::instrument(skip(state, headers, payload), fields(username = %payload.username))]
+async fn login(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<LoginDto>,
+) -> impl IntoResponse {
     match state
         .user_service
-        .login_user(&state.db, &payload.username, &payload.password)
+        .login_user(
+            &state.db,
+            &payload.username,
+            &payload.password,
+            &session_client(&headers),
+        )
         .await
     {
-        Ok(auth_token) => {
+        Ok(tokens) => {
             let roles = state
                 .user_service
-                .get_user_roles(&state.db, &auth_token.user_id)
+                .get_user_roles(&state.db, &tokens.user_id)
                 .await
                 .unwrap_or_default();
 
+            persist_client_state(&tokens.username, &tokens.access_token);
+
             let data = AuthData {
-                id: auth_token.user_id.clone(),
-                username: auth_token.username.clone(),
-                roles: roles.clone(),
-                token: auth_token.token.clone(),
+                id: tokens.user_id,
+                username: tokens.username,
+                roles,
+                token: tokens.access_token,
+                refresh_token: Some(tokens.refresh_token),
+                expires_at: Some(tokens.expires_at),
             };
 
-            persist_client_state(&auth_token.username, &auth_token.token);
-
             ok(data).into_response()
         }
         Err(UserServiceError::UserNotFound) => err("User not found").into_response(),
         Err(UserServiceError::InvalidCredentials) => err("Invalid credentials").into_response(),
+        Err(UserServiceError::TwoFactorRequired) => err_status(
+            StatusCode::UNAUTHORIZED,
+            "Two-factor authentication required, sign in through /api/auth/login",
+        )
+        .into_response(),
         Err(e) => err(e).into_response(),
     }
 }
@@ -236,14 +427,7 @@ fn persist_client_state(username: &str, token: &str) {
 }
 
 #[tracing::instrument(skip_all)]
-async fn list_users(
-    State(state): State<AppState>,
-    Extension(claims): Extension<Claims>,
-) -> impl IntoResponse {
-    if let Some(response) = require_admin_or_response(&state, &claims).await {
-        return response;
-    }
-
+async fn list_users(State(state): State<AppState>) -> impl IntoResponse {
     match state.user_service.list_users(&state.db).await {
         Ok(users) => {
             let mut summaries = Vec::new();
@@ -254,8 +438,8 @@ async fn list_users(
                     .await
                     .unwrap_or_default();
                 let role = roles
-                    .into_iter()
-                    .next()
+                    .first()
+                    .cloned()
                     .unwrap_or_else(|| "user".to_string());
 
                 summaries.push(UserSummary {
@@ -267,6 +451,7 @@ async fn list_users(
                     balance_cny: u.balance_cny,
                     preferred_currency: u.preferred_currency,
                     role,
+                    roles,
                     group: "default",
                 });
             }
@@ -0,0 +1,123 @@
+//! Virtual model management: customer-facing names that resolve to an ordered
+//! fallback chain of concrete models.
+//!
+//! The router reads definitions per request, so changes apply to the next
+//! request without a reload.
+
+use crate::api::audit;
+use crate::api::auth::Claims;
+use crate::api::response::{err, err_status, ok};
+use crate::AppState;
+use axum::{
+    extract::{Extension, Json, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use burncloud_database_router::{VirtualModel, VirtualModelModel};
+use burncloud_service_audit::AuditEvent;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/console/api/virtual-models",
+            get(list_virtual_models).post(upsert_virtual_model),
+        )
+        .route(
+            "/console/api/virtual-models/{name}",
+            get(get_virtual_model).delete(delete_virtual_model),
+        )
+}
+
+#[tracing::instrument(skip(state))]
+async fn list_virtual_models(State(state): State<AppState>) -> impl IntoResponse {
+    match VirtualModelModel::list(&state.db).await {
+        Ok(models) => ok(models).into_response(),
+        Err(e) => err(format!("Failed to list virtual models: {e}")).into_response(),
+    }
+}
+
+#[tracing::instrument(skip(state))]
+async fn get_virtual_model(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    match VirtualModelModel::get(&state.db, &name).await {
+        Ok(Some(model)) => ok(model).into_response(),
+        Ok(None) => err_status(StatusCode::NOT_FOUND, "Virtual model not found").into_response(),
+        Err(e) => err(format!("Failed to load virtual model: {e}")).into_response(),
+    }
+}
+
+/// Create or replace a virtual model. Hops may not name another virtual
+/// model: chains are resolved one level deep.
+#[tracing::instrument(skip(state, claims, headers))]
+async fn upsert_virtual_model(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Json(payload): Json<VirtualModel>,
+) -> impl IntoResponse {
+    if let Err(msg) = payload.validate() {
+        return err_status(StatusCode::BAD_REQUEST, msg).into_response();
+    }
+    for hop in &payload.hops {
+        match VirtualModelModel::get(&state.db, &hop.model).await {
+            Ok(Some(_)) => {
+                return err_status(
+                    StatusCode::BAD_REQUEST,
+                    format!("hop '{}' is itself a virtual model", hop.model),
+                )
+                .into_response()
+            }
+            Ok(None) => {}
+            Err(e) => {
+                return err(format!("Failed to check hop '{}': {e}", hop.model)).into_response()
+            }
+        }
+    }
+    let before = VirtualModelModel::get(&state.db, &payload.name)
+        .await
+        .ok()
+        .flatten();
+    if let Err(e) = VirtualModelModel::upsert(&state.db, &payload).await {
+        return err(format!("Failed to save virtual model: {e}")).into_response();
+    }
+    match VirtualModelModel::get(&state.db, &payload.name).await {
+        Ok(Some(model)) => {
+            let event = AuditEvent::new("virtual_model.upsert")
+                .target(&model.name)
+                .before(&before)
+                .after(&model);
+            audit::record(&state, &claims, &headers, event).await;
+            ok(model).into_response()
+        }
+        Ok(None) => err("Virtual model vanished after save").into_response(),
+        Err(e) => err(format!("Failed to load virtual model: {e}")).into_response(),
+    }
+}
+
+#[tracing::instrument(skip(state, claims, headers))]
+async fn delete_virtual_model(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    let before = VirtualModelModel::get(&state.db, &name)
+        .await
+        .ok()
+        .flatten();
+    match VirtualModelModel::delete(&state.db, &name).await {
+        Ok(true) => {
+            let event = AuditEvent::new("virtual_model.delete")
+                .target(&name)
+                .before(&before);
+            audit::record(&state, &claims, &headers, event).await;
+            ok(serde_json::json!({ "name": name })).into_response()
+        }
+        Ok(false) => err_status(StatusCode::NOT_FOUND, "Virtual model not found").into_response(),
+        Err(e) => err(format!("Failed to delete virtual model: {e}")).into_response(),
+    }
+}
@@ -1,5 +1,6 @@
 pub mod api;
 pub mod logging;
+pub mod telemetry;
 pub use api::auth::{auth_middleware, Claims};
 
 use axum::http::HeaderName;
@@ -8,9 +9,20 @@ use burncloud_database::{create_default_database, Database};
 use burncloud_database_router::RouterDatabase;
 use burncloud_database_user::UserDatabase;
 use burncloud_router::create_router_app;
+use burncloud_router::local_instance::{
+    set_local_instance_hook, LocalInstanceHook, LocalInstanceLease,
+};
 use burncloud_router::price_sync::SyncResult;
 use burncloud_service_cache::CacheService;
+use burncloud_service_channel::ChannelService;
+use burncloud_service_inference::{InferenceService, ModelLifecycle, SupervisorConfig};
+use burncloud_service_mail::MailService;
 use burncloud_service_monitor::SystemMonitorService;
+use burncloud_service_notification::NotificationService;
+use burncloud_service_payment::PaymentService;
+use burncloud_service_router_log::{
+    LogRetentionService, RetentionPolicy, RollupPolicy, UsageRollupService,
+};
 use burncloud_service_user::UserService;
 use std::net::SocketAddr;
 use std::sync::Arc;
@@ -25,6 +37,14 @@ pub struct AppState {
     pub monitor: Arc<SystemMonitorService>,
     pub user_service: Arc<UserService>,
     pub cache: CacheService,
+    /// Local model lifecycle (pull / serve / stop) backing `/console/api/models/*`.
+    pub models: Arc<ModelLifecycle>,
+    /// Request-log retention scheduler, reported by `/console/api/monitor/log-retention`.
+    pub log_retention: Arc<LogRetentionService>,
+    /// Online top-up providers backing `/console/api/payments/*`.
+    pub payments: Arc<PaymentService>,
+    /// Threshold settings backing `/console/api/notifications`.
+    pub notifications: Arc<NotificationService>,
     pub force_sync_tx: mpsc::Sender<oneshot::Sender<SyncResult>>,
     /// Ready-to-serve data-plane router used by authenticated console smoke tests.
     /// Requests sent through this router still pass the router's bearer-token validation
@@ -32,8 +52,35 @@ pub struct AppState {
     pub data_plane: Router,
 }
 
+/// Lets the data-plane router cold-start local models that were unloaded while idle.
+struct LocalModelHook {
+    inference: Arc<InferenceService>,
+    db: Arc<Database>,
+}
+
+#[async_trait::async_trait]
+impl LocalInstanceHook for LocalModelHook {
+    async fn ensure_ready(&self, channel_id: i32) -> Result<Option<LocalInstanceLease>, String> {
+        match self.inference.ensure_ready(&self.db, channel_id).await {
+            Ok(lease) => Ok(lease.map(|lease| Box::new(lease) as LocalInstanceLease)),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
 #[tracing::instrument(skip(db))]
 pub async fn create_app(db: Arc<Database>, enable_liveview: bool) -> anyhow::Result<Router> {
+    // Channel keys written before encryption at rest are encrypted in place
+    match ChannelService::encrypt_plaintext_keys(&db).await {
+        Ok(report) if report.encrypted > 0 || report.failed > 0 => tracing::info!(
+            "Encrypted {} plaintext channel keys ({} unreadable)",
+            report.encrypted,
+            report.failed
+        ),
+        Ok(_) => {}
+        Err(e) => tracing::warn!("Channel keys were not encrypted: {}", e),
+    }
+
     let monitor = Arc::new(SystemMonitorService::new());
     // Start auto collection in background
     let _ = monitor.start_auto_update().await;
@@ -46,14 +93,46 @@ pub async fn create_app(db: Arc<Database>, enable_liveview: bool) -> anyhow::Res
         tracing::info!("Redis cache disabled");
     }
 
+    // Usage summaries read rollups, so seed them from existing logs before
+    // the router starts adding to them
+    match UsageRollupService::backfill_if_empty(&db).await {
+        Ok(Some(report)) => tracing::info!(
+            "Built usage rollups from {} existing request logs",
+            report.logs_aggregated
+        ),
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Usage rollups were not backfilled: {}", e),
+    }
+
     // 3. Data Plane Router (Fallback) — must be created first to get force_sync_tx
     let (router_app, internal_app, force_sync_tx) = create_router_app(db.clone()).await?;
 
+    // Local inference: supervise llama-server processes and cold-start idle
+    // models when the router picks their channel.
+    let inference = Arc::new(InferenceService::new().with_monitor(monitor.clone()));
+    inference.start_supervisor(db.clone(), SupervisorConfig::from_env());
+    set_local_instance_hook(Arc::new(LocalModelHook {
+        inference: inference.clone(),
+        db: db.clone(),
+    }));
+
+    // Archive and purge expired request logs in the background
+    let log_retention = Arc::new(LogRetentionService::new(RetentionPolicy::from_env()));
+    log_retention.clone().start(db.clone());
+    // Drop minute and hour usage rollups past their retention
+    Arc::new(UsageRollupService::new(RollupPolicy::from_env())).start(db.clone());
+    // Deliver queued mail (password resets, notices) and retry failures
+    Arc::new(MailService::from_env()).start(db.clone());
+
     let state = AppState {
         db: db.clone(),
         monitor,
         user_service: Arc::new(UserService::new()),
         cache,
+        models: Arc::new(ModelLifecycle::new(inference)),
+        log_retention,
+        payments: Arc::new(PaymentService::from_env()),
+        notifications: Arc::new(NotificationService::from_env()),
         force_sync_tx,
         data_plane: router_app.clone(),
     };
@@ -94,13 +173,21 @@ pub async fn create_app(db: Arc<Database>, enable_liveview: bool) -> anyhow::Res
         .layer(CorsLayer::permissive())
         // Security boundary is intentionally global so it protects both the
         // explicitly merged internal routes and the data-plane fallback.
-        .layer(middleware::from_fn(api::auth::security_boundary_middleware));
+        .layer(middleware::from_fn(api::auth::security_boundary_middleware))
+        // Outermost, so every layer and handler sees the resolved client address
+        .layer(middleware::from_fn(api::auth::client_address_middleware));
 
     Ok(app)
 }
 
 #[tracing::instrument(skip_all)]
 pub async fn start_server(host: &str, port: u16, enable_liveview: bool) -> anyhow::Result<()> {
+    match burncloud_common::constants::validate_jwt_secret() {
+        Ok(Some(warning)) => tracing::warn!("{warning}. Set it before going to production"),
+        Ok(None) => {}
+        Err(e) => anyhow::bail!(e),
+    }
+
     let db = create_default_database().await?;
     RouterDatabase::init(&db).await?;
     UserDatabase::init(&db).await?;
@@ -116,7 +203,11 @@ pub async fn start_server(host: &str, port: u16, enable_liveview: bool) -> anyho
     tracing::info!("- LLM API:   http://{}:{}/v1/...", host, port);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
 
     Ok(())
 }
@@ -1,3 +1,4 @@
+use crate::telemetry::{self, TelemetryGuard};
 use std::{env, fs};
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_subscriber::{
@@ -13,9 +14,10 @@ use tracing_subscriber::{
 /// - Daily log rotation with retention via `LOG_MAX_FILES` (default: 7)
 /// - Log directory via `LOG_DIR` env var (default: `./logs`)
 /// - `tracing-log` bridge so existing `log::*!` calls route to tracing
+/// - Optional OTLP span export (see [`telemetry::TelemetryConfig`])
 ///
-/// Returns `WorkerGuard`s that must be held for the program's lifetime.
-pub fn init_logging() -> Vec<WorkerGuard> {
+/// Returns guards that must be held for the program's lifetime.
+pub fn init_logging() -> LoggingGuards {
     let log_dir = env::var("LOG_DIR").unwrap_or_else(|_| "./logs".to_string());
     let max_files = env::var("LOG_MAX_FILES")
         .ok()
@@ -38,6 +40,11 @@ pub fn init_logging() -> Vec<WorkerGuard> {
     let (router_nb, g) = file_appender(&log_dir, "router", max_files);
     guards.push(g);
 
+    let telemetry = telemetry::init_from_env();
+    let otel_layer = telemetry
+        .as_ref()
+        .map(|(provider, _)| telemetry::layer(provider).with_filter(module_filter("burncloud")));
+
     let subscriber = tracing_subscriber::registry()
         .with(tracing_subscriber::fmt::layer().with_filter(env_filter))
         .with(
@@ -63,11 +70,21 @@ pub fn init_logging() -> Vec<WorkerGuard> {
                 .with_writer(router_nb)
                 .with_ansi(false)
                 .with_filter(module_filter("burncloud_router")),
-        );
+        )
+        .with(otel_layer);
 
     tracing::subscriber::set_global_default(subscriber).ok();
 
-    guards
+    LoggingGuards {
+        _workers: guards,
+        _telemetry: telemetry.map(|(_, guard)| guard),
+    }
+}
+
+/// File writers and the span exporter; dropping flushes both.
+pub struct LoggingGuards {
+    _workers: Vec<WorkerGuard>,
+    _telemetry: Option<TelemetryGuard>,
 }
 
 fn module_filter(target: &'static str) -> Targets {
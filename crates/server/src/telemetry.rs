@@ -0,0 +1,142 @@
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{ExporterBuildError, SpanExporter};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use std::env;
+use std::sync::OnceLock;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// OTLP transport, from `OTEL_EXPORTER_OTLP_TRACES_PROTOCOL` /
+/// `OTEL_EXPORTER_OTLP_PROTOCOL`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OtlpProtocol {
+    Grpc,
+    HttpProtobuf,
+}
+
+/// OpenTelemetry trace export settings.
+///
+/// Export is enabled when `OTEL_EXPORTER_OTLP_ENDPOINT` or
+/// `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT` is set and `OTEL_SDK_DISABLED` is not
+/// `true`. Endpoint, headers, timeout and sampler are read by the exporter and
+/// SDK from the standard `OTEL_*` variables.
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    pub protocol: OtlpProtocol,
+    /// `OTEL_SERVICE_NAME` (default: `burncloud`).
+    pub service_name: String,
+}
+
+impl TelemetryConfig {
+    pub fn from_env() -> Option<Self> {
+        if env::var("OTEL_SDK_DISABLED").is_ok_and(|v| v.eq_ignore_ascii_case("true")) {
+            return None;
+        }
+        let endpoint_set = [
+            "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+            "OTEL_EXPORTER_OTLP_ENDPOINT",
+        ]
+        .iter()
+        .any(|key| env::var(key).is_ok_and(|v| !v.trim().is_empty()));
+        if !endpoint_set {
+            return None;
+        }
+
+        let protocol = env::var("OTEL_EXPORTER_OTLP_TRACES_PROTOCOL")
+            .or_else(|_| env::var("OTEL_EXPORTER_OTLP_PROTOCOL"))
+            .map(|v| match v.trim() {
+                "grpc" => OtlpProtocol::Grpc,
+                _ => OtlpProtocol::HttpProtobuf,
+            })
+            .unwrap_or(OtlpProtocol::HttpProtobuf);
+        let service_name = env::var("OTEL_SERVICE_NAME")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .unwrap_or_else(|| "burncloud".to_string());
+
+        Some(Self {
+            protocol,
+            service_name,
+        })
+    }
+}
+
+/// Flushes and shuts down the tracer provider when dropped.
+pub struct TelemetryGuard {
+    provider: SdkTracerProvider,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.provider.shutdown() {
+            eprintln!("failed to flush OpenTelemetry spans: {e}");
+        }
+    }
+}
+
+/// Logging starts before the application runtime, but the gRPC exporter
+/// needs one for its transport, so it gets a small runtime of its own.
+fn exporter_runtime() -> std::io::Result<&'static tokio::runtime::Runtime> {
+    static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+    if let Some(rt) = RUNTIME.get() {
+        return Ok(rt);
+    }
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("otlp-exporter")
+        .enable_all()
+        .build()?;
+    Ok(RUNTIME.get_or_init(|| rt))
+}
+
+fn build_exporter(protocol: OtlpProtocol) -> Result<SpanExporter, ExporterBuildError> {
+    match protocol {
+        OtlpProtocol::Grpc => {
+            let rt = exporter_runtime()
+                .map_err(|e| ExporterBuildError::InternalFailure(e.to_string()))?;
+            let _enter = rt.enter();
+            SpanExporter::builder().with_tonic().build()
+        }
+        OtlpProtocol::HttpProtobuf => SpanExporter::builder().with_http().build(),
+    }
+}
+
+/// Build the OTLP tracer provider and register the W3C trace-context
+/// propagator used for `traceparent` on proxied requests.
+pub fn init_tracer_provider(
+    config: &TelemetryConfig,
+) -> Result<SdkTracerProvider, ExporterBuildError> {
+    let exporter = build_exporter(config.protocol)?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(config.service_name.clone())
+                .build(),
+        )
+        .build();
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+    Ok(provider)
+}
+
+/// `tracing` layer exporting spans through `provider`.
+pub fn layer<S>(provider: &SdkTracerProvider) -> impl Layer<S>
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+{
+    tracing_opentelemetry::layer().with_tracer(provider.tracer("burncloud"))
+}
+
+/// Set up OTLP export from the environment, or `None` when disabled.
+pub fn init_from_env() -> Option<(SdkTracerProvider, TelemetryGuard)> {
+    let config = TelemetryConfig::from_env()?;
+    match init_tracer_provider(&config) {
+        Ok(provider) => Some((provider.clone(), TelemetryGuard { provider })),
+        Err(e) => {
+            eprintln!("OpenTelemetry export disabled: {e}");
+            None
+        }
+    }
+}
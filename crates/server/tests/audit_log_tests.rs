@@ -0,0 +1,172 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::disallowed_types)]
+
+//! Administrative changes made through the console land in the audit log,
+//! which admins can query and export and which never holds secrets.
+
+mod test_utils;
+
+use burncloud_service_user::UserService;
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+
+const JWT_SECRET: &str = "burncloud-audit-log-test-jwt-secret-2026";
+
+#[tokio::test]
+async fn admin_changes_are_audited_queryable_and_exportable() -> anyhow::Result<()> {
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    std::env::set_var("SKIP_INITIAL_PRICE_SYNC", "1");
+
+    let db = test_utils::make_isolated_db().await;
+    let service = UserService::new();
+    let admin_id = service
+        .register_user(&db, "audit-admin", "test-password", None)
+        .await?;
+    let user_id = service
+        .register_user(&db, "audit-user", "test-password", None)
+        .await?;
+    let admin_jwt = service.generate_token(&admin_id, "audit-admin")?.token;
+    let user_jwt = service.generate_token(&user_id, "audit-user")?.token;
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let client = Client::new();
+
+    let created: Value = client
+        .post(format!("{base}/console/api/channel"))
+        .bearer_auth(&admin_jwt)
+        .header("x-forwarded-for", "203.0.113.7, 10.0.0.1")
+        .json(&json!({
+            "type": 1,
+            "key": "sk-audit-secret-0001",
+            "name": "audited",
+            "models": "gpt-4o",
+            "group": "default",
+            "weight": 1,
+            "priority": 0
+        }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let id = created["data"]["id"].as_i64().unwrap();
+    let response = client
+        .put(format!("{base}/console/api/channel"))
+        .bearer_auth(&admin_jwt)
+        .json(&json!({
+            "id": id,
+            "type": 1,
+            "key": "sk-audit-secret-0002",
+            "name": "audited-renamed",
+            "models": "gpt-4o",
+            "group": "default",
+            "weight": 3,
+            "priority": 0
+        }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let token: Value = client
+        .post(format!("{base}/console/api/tokens"))
+        .bearer_auth(&admin_jwt)
+        .json(&json!({ "user_id": user_id }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let bearer = token["data"]["token"].as_str().unwrap();
+
+    // Newest first, with actor, source address and the changed fields
+    let page: Value = client
+        .get(format!("{base}/console/api/audit?target_type=channel"))
+        .bearer_auth(&admin_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(page["data"]["total"], 2);
+    let entries = page["data"]["entries"].as_array().unwrap();
+    let (update, create) = (&entries[0], &entries[1]);
+    assert_eq!(create["action"], "channel.create");
+    assert_eq!(create["actor_id"], admin_id.as_str());
+    assert_eq!(create["actor_name"], "audit-admin");
+    assert_eq!(create["source"], "console");
+    // The socket peer, not the client-supplied forwarding header
+    assert_eq!(create["ip"], "127.0.0.1");
+    assert_eq!(create["target_id"], id.to_string());
+    assert_eq!(update["action"], "channel.update");
+    assert_eq!(
+        update["changes"]["name"],
+        json!({ "before": "audited", "after": "audited-renamed" })
+    );
+    assert_eq!(
+        update["changes"]["weight"],
+        json!({ "before": 1, "after": 3 })
+    );
+    assert_eq!(update["changes"]["key"]["after"], "[redacted]");
+    assert_eq!(update["before"]["key"], "[redacted]");
+
+    // Action prefixes and actor filters
+    let tokens: Value = client
+        .get(format!(
+            "{base}/console/api/audit?action=token.&actor=audit-admin"
+        ))
+        .bearer_auth(&admin_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(tokens["data"]["total"], 1);
+    assert_eq!(tokens["data"]["entries"][0]["action"], "token.create");
+    let none: Value = client
+        .get(format!(
+            "{base}/console/api/audit?since=2000-01-01&until=2000-01-02"
+        ))
+        .bearer_auth(&admin_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(none["data"]["total"], 0);
+
+    // Exports carry every matching entry and no secrets
+    let csv = client
+        .get(format!("{base}/console/api/audit/export?format=csv"))
+        .bearer_auth(&admin_jwt)
+        .send()
+        .await?;
+    assert_eq!(csv.status(), StatusCode::OK);
+    assert!(csv.headers()["content-disposition"]
+        .to_str()?
+        .contains("audit.csv"));
+    let csv = csv.text().await?;
+    let mut lines = csv.lines();
+    assert!(lines.next().unwrap().starts_with("id,created_at,actor_id"));
+    assert_eq!(lines.count(), 3);
+    let jsonl = client
+        .get(format!(
+            "{base}/console/api/audit/export?format=jsonl&action=channel."
+        ))
+        .bearer_auth(&admin_jwt)
+        .send()
+        .await?
+        .text()
+        .await?;
+    assert_eq!(jsonl.lines().count(), 2);
+    for body in [&csv, &jsonl] {
+        assert!(!body.contains("sk-audit-secret") && !body.contains(bearer));
+    }
+    let response = client
+        .get(format!("{base}/console/api/audit/export?format=xml"))
+        .bearer_auth(&admin_jwt)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Only administrators can read the trail
+    let response = client
+        .get(format!("{base}/console/api/audit"))
+        .bearer_auth(&user_jwt)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    Ok(())
+}
@@ -0,0 +1,167 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::disallowed_types)]
+
+//! Channel API keys are encrypted at rest, masked by the console API and
+//! decrypted only when the router calls the upstream.
+
+mod test_utils;
+
+use axum::{http::HeaderMap, routing::post, Json, Router};
+use burncloud_database::Database;
+use burncloud_service_channel::{decrypt_key, ChannelService};
+use burncloud_service_user::UserService;
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+const JWT_SECRET: &str = "burncloud-channel-key-test-jwt-secret-2026";
+
+/// Upstream that records the Authorization header it was called with.
+async fn spawn_upstream(seen: Arc<Mutex<Vec<String>>>) -> anyhow::Result<String> {
+    let app = Router::new().route(
+        "/chat/completions",
+        post(move |headers: HeaderMap| {
+            let seen = seen.clone();
+            async move {
+                if let Some(auth) = headers.get("authorization") {
+                    seen.lock()
+                        .unwrap()
+                        .push(auth.to_str().unwrap().to_string());
+                }
+                Json(json!({
+                    "id": "chatcmpl-key",
+                    "object": "chat.completion",
+                    "model": "key-model",
+                    "choices": [{
+                        "index": 0,
+                        "message": { "role": "assistant", "content": "ok" },
+                        "finish_reason": "stop"
+                    }],
+                    "usage": { "prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2 }
+                }))
+            }
+        }),
+    );
+    test_utils::spawn_app(app).await
+}
+
+async fn stored_key(db: &Database, id: i64) -> anyhow::Result<String> {
+    Ok(ChannelService::get_by_id(db, id as i32).await?.unwrap().key)
+}
+
+fn is_encrypted(stored: &str) -> bool {
+    stored.starts_with("enc:v1:")
+}
+
+#[tokio::test]
+async fn channel_keys_are_encrypted_masked_and_used_upstream() -> anyhow::Result<()> {
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    std::env::set_var("SKIP_INITIAL_PRICE_SYNC", "1");
+
+    let db = test_utils::make_isolated_db().await;
+    let service = UserService::new();
+    let admin_id = service
+        .register_user(&db, "key-admin", "test-password", None)
+        .await?;
+    let jwt = service.generate_token(&admin_id, "key-admin")?.token;
+
+    // A row written before encryption at rest is encrypted at startup
+    db.execute_query(
+        "INSERT INTO channel_providers (type, key, status, name, models, `group`, priority) \
+         VALUES (1, 'sk-legacy-plaintext-key', 2, 'legacy', 'legacy-model', 'default', 0)",
+    )
+    .await?;
+    db.execute_query(
+        "INSERT INTO billing_prices (model, currency, input_price, output_price, region, created_at) \
+         VALUES ('key-model', 'USD', 0, 0, '', 1700000000)",
+    )
+    .await?;
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let upstream = spawn_upstream(seen.clone()).await?;
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let client = Client::new();
+
+    let legacy = stored_key(&db, 1).await?;
+    assert!(is_encrypted(&legacy));
+    assert_eq!(decrypt_key(&legacy)?, "sk-legacy-plaintext-key");
+
+    // Keys written through the API never reach the database in plaintext
+    let created: Value = client
+        .post(format!("{base}/console/api/channel"))
+        .bearer_auth(&jwt)
+        .json(&json!({
+            "type": 1,
+            "key": "sk-upstream-secret-0001",
+            "name": "encrypted",
+            "base_url": upstream,
+            "models": "key-model",
+            "group": "default",
+            "weight": 1,
+            "priority": 0
+        }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let id = created["data"]["id"].as_i64().unwrap();
+    let stored = stored_key(&db, id).await?;
+    assert!(is_encrypted(&stored));
+    assert!(!stored.contains("sk-upstream-secret"));
+
+    // The console only ever sees a masked key
+    let channel: Value = client
+        .get(format!("{base}/console/api/channel/{id}"))
+        .bearer_auth(&jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    let masked = channel["data"]["key"].as_str().unwrap().to_string();
+    assert_eq!(masked, "sk-u****0001");
+    let list = client
+        .get(format!("{base}/console/api/channel"))
+        .bearer_auth(&jwt)
+        .send()
+        .await?
+        .text()
+        .await?;
+    assert!(!list.contains("sk-upstream-secret") && !list.contains("enc:v1:"));
+
+    // Saving the form with the masked value keeps the stored key
+    let mut update = channel["data"].clone();
+    update["name"] = json!("encrypted-renamed");
+    let response = client
+        .put(format!("{base}/console/api/channel"))
+        .bearer_auth(&jwt)
+        .json(&update)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(stored_key(&db, id).await?, stored);
+
+    // The router decrypts the key when it calls the upstream
+    let token: Value = client
+        .post(format!("{base}/console/api/tokens"))
+        .bearer_auth(&jwt)
+        .json(&json!({ "user_id": admin_id }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let api_key = token["data"]["token"].as_str().unwrap();
+    let response = client
+        .post(format!("{base}/v1/chat/completions"))
+        .bearer_auth(api_key)
+        .json(&json!({
+            "model": "key-model",
+            "messages": [{ "role": "user", "content": "hello" }]
+        }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        seen.lock().unwrap().as_slice(),
+        ["Bearer sk-upstream-secret-0001"]
+    );
+
+    Ok(())
+}
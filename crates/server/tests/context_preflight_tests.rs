@@ -0,0 +1,139 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::disallowed_types)]
+
+//! With `CONTEXT_PREFLIGHT` on, requests that cannot fit the model's context
+//! window are rejected before any channel is called, or rerouted to a
+//! configured larger-context sibling.
+
+mod test_utils;
+
+use axum::{routing::post, Json, Router};
+use burncloud_service_user::UserService;
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+const JWT_SECRET: &str = "burncloud-context-preflight-jwt-secret-2026";
+
+/// Upstream that records the model each request was sent for.
+async fn spawn_upstream(seen: Arc<Mutex<Vec<String>>>) -> anyhow::Result<String> {
+    let app = Router::new().route(
+        "/chat/completions",
+        post(move |Json(body): Json<Value>| {
+            let seen = seen.clone();
+            async move {
+                let model = body["model"].as_str().unwrap_or_default().to_string();
+                seen.lock().unwrap().push(model.clone());
+                Json(json!({
+                    "id": "chatcmpl-context",
+                    "object": "chat.completion",
+                    "model": model,
+                    "choices": [{
+                        "index": 0,
+                        "message": { "role": "assistant", "content": "ok" },
+                        "finish_reason": "stop"
+                    }],
+                    "usage": { "prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2 }
+                }))
+            }
+        }),
+    );
+    test_utils::spawn_app(app).await
+}
+
+/// Chat request whose prompt is estimated at `prompt_bytes` / 4 tokens.
+fn chat(model: &str, prompt_bytes: usize) -> Value {
+    json!({
+        "model": model,
+        "max_tokens": 100,
+        "messages": [{ "role": "user", "content": "a".repeat(prompt_bytes) }]
+    })
+}
+
+#[tokio::test]
+async fn context_preflight_rejects_or_reroutes_oversized_requests() -> anyhow::Result<()> {
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    std::env::set_var("SKIP_INITIAL_PRICE_SYNC", "1");
+    std::env::set_var("CONTEXT_PREFLIGHT", "true");
+    std::env::set_var("LONG_CONTEXT_MODELS", r#"{"ctx-small": "ctx-large"}"#);
+
+    let db = test_utils::make_isolated_db().await;
+    for (model, context_window) in [("ctx-small", 1_000), ("ctx-large", 8_000)] {
+        db.execute_query(&format!(
+            "INSERT INTO billing_prices (model, currency, input_price, output_price, region, \
+             context_window, max_output_tokens, created_at) \
+             VALUES ('{model}', 'USD', 0, 0, '', {context_window}, 1000, 1700000000)"
+        ))
+        .await?;
+    }
+    let service = UserService::new();
+    let admin_id = service
+        .register_user(&db, "context-admin", "test-password", None)
+        .await?;
+    let jwt = service.generate_token(&admin_id, "context-admin")?.token;
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let upstream = spawn_upstream(seen.clone()).await?;
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let client = Client::new();
+
+    let channel = client
+        .post(format!("{base}/console/api/channel"))
+        .bearer_auth(&jwt)
+        .json(&json!({
+            "type": 1,
+            "key": "sk-upstream",
+            "name": "context-channel",
+            "base_url": upstream,
+            "models": "ctx-small,ctx-large",
+            "group": "default",
+            "weight": 1,
+            "priority": 0
+        }))
+        .send()
+        .await?;
+    assert_eq!(channel.status(), StatusCode::OK);
+    let token: Value = client
+        .post(format!("{base}/console/api/tokens"))
+        .bearer_auth(&jwt)
+        .json(&json!({ "user_id": admin_id }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let api_key = token["data"]["token"].as_str().unwrap().to_string();
+
+    let fits = client
+        .post(format!("{base}/v1/chat/completions"))
+        .bearer_auth(&api_key)
+        .json(&chat("ctx-small", 400))
+        .send()
+        .await?;
+    assert_eq!(fits.status(), StatusCode::OK);
+
+    // About 2000 prompt tokens: too long for ctx-small, fits its sibling
+    let rerouted = client
+        .post(format!("{base}/v1/chat/completions"))
+        .bearer_auth(&api_key)
+        .json(&chat("ctx-small", 8_000))
+        .send()
+        .await?;
+    assert_eq!(rerouted.status(), StatusCode::OK);
+    assert_eq!(seen.lock().unwrap().as_slice(), ["ctx-small", "ctx-large"]);
+
+    // About 10000 prompt tokens fit neither: rejected without calling upstream
+    let rejected = client
+        .post(format!("{base}/v1/chat/completions"))
+        .bearer_auth(&api_key)
+        .json(&chat("ctx-small", 40_000))
+        .send()
+        .await?;
+    assert_eq!(rejected.status(), StatusCode::BAD_REQUEST);
+    let error: Value = rejected.json().await?;
+    assert_eq!(error["error"]["code"], "context_length_exceeded");
+    assert!(error["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("1000 tokens"));
+    assert_eq!(seen.lock().unwrap().len(), 2);
+
+    Ok(())
+}
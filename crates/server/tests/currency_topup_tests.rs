@@ -0,0 +1,93 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::disallowed_types)]
+
+//! Top-ups in currencies without a wallet are converted into the USD wallet
+//! at the current exchange rate instead of being credited as USD.
+
+mod test_utils;
+
+use burncloud_service_user::UserService;
+use reqwest::{Client, StatusCode};
+use serde_json::Value;
+
+const JWT_SECRET: &str = "burncloud-currency-topup-jwt-secret-2026";
+
+fn configure_env() {
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    std::env::set_var("SKIP_INITIAL_PRICE_SYNC", "1");
+}
+
+#[tokio::test]
+async fn foreign_currency_topup_is_converted_into_usd_wallet() -> anyhow::Result<()> {
+    configure_env();
+    let db = test_utils::make_isolated_db().await;
+    let service = UserService::new();
+    let admin_id = service
+        .register_user(&db, "topup-admin", "test-password", None)
+        .await?;
+    let admin_jwt = service.generate_token(&admin_id, "topup-admin")?.token;
+    let user_id = service
+        .register_user(&db, "topup-user", "test-password", None)
+        .await?;
+    // 1 USD = 150 JPY, stored only in that direction
+    db.execute_query(
+        "INSERT INTO billing_exchange_rates (from_currency, to_currency, rate, updated_at) \
+         VALUES ('USD', 'JPY', 150000000000, 1700000000)",
+    )
+    .await?;
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let client = Client::new();
+    let before =
+        burncloud_database_user::UserDatabase::update_balance(&db, &user_id, 0, None).await?;
+
+    let topup: Value = client
+        .post(format!("{base}/console/api/user/topup"))
+        .bearer_auth(&admin_jwt)
+        .json(&serde_json::json!({
+            "user_id": user_id,
+            "amount": 15_000_000_000_000_i64,
+            "currency": "JPY"
+        }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(topup["success"], true, "{topup}");
+    // 15000 JPY = 100 USD
+    assert_eq!(topup["data"]["currency"], "USD");
+    assert_eq!(
+        topup["data"]["balance"].as_i64().unwrap(),
+        before + 100_000_000_000
+    );
+
+    // No GBP rate configured: refused rather than credited as USD
+    let refused: Value = client
+        .post(format!("{base}/console/api/user/topup"))
+        .bearer_auth(&admin_jwt)
+        .json(&serde_json::json!({
+            "user_id": user_id,
+            "amount": 1_000_000_000_i64,
+            "currency": "GBP"
+        }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(refused["success"], false);
+
+    // A negative amount is refused, not clamped to a zero credit
+    let negative = client
+        .post(format!("{base}/console/api/user/topup"))
+        .bearer_auth(&admin_jwt)
+        .json(&serde_json::json!({
+            "user_id": user_id,
+            "amount": -15_000_000_000_000_i64,
+            "currency": "JPY"
+        }))
+        .send()
+        .await?;
+    assert_eq!(negative.status(), StatusCode::BAD_REQUEST);
+    let after =
+        burncloud_database_user::UserDatabase::update_balance(&db, &user_id, 0, None).await?;
+    assert_eq!(after, before + 100_000_000_000);
+    Ok(())
+}
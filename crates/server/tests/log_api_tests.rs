@@ -5,10 +5,16 @@
     clippy::disallowed_types
 )]
 
-use burncloud_database::create_database_with_url;
-use burncloud_database_router::{RouterDatabase, RouterLog};
+mod test_utils;
+
+use burncloud_database::{create_database_with_url, Database};
+use burncloud_database_router::{token_hash, RouterDatabase, RouterLog, RouterRequestLog};
 use burncloud_database_user::UserDatabase;
-use reqwest::Client;
+use burncloud_service_token::{RouterToken, TokenService};
+use burncloud_service_user::UserService;
+use reqwest::{Client, StatusCode};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::time::sleep;
@@ -66,6 +72,9 @@ async fn test_log_api_endpoints() -> anyhow::Result<()> {
         traffic_color: None,
         cost_status: None,
         error_type: None,
+        token_hash: None,
+        served_model: None,
+        currency: None,
         created_at: None,
     };
 
@@ -106,3 +115,236 @@ async fn test_log_api_endpoints() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+fn sample_log(
+    request_id: &str,
+    model: &str,
+    status_code: i32,
+    latency_ms: i64,
+    cost: i64,
+) -> RouterLog {
+    RouterLog {
+        id: 0,
+        request_id: request_id.to_string(),
+        user_id: Some("search-user".to_string()),
+        path: "/v1/chat/completions".to_string(),
+        upstream_id: Some("search-upstream".to_string()),
+        status_code,
+        latency_ms,
+        prompt_tokens: 10,
+        completion_tokens: 5,
+        cost,
+        model: Some(model.to_string()),
+        cache_read_tokens: 0,
+        reasoning_tokens: 0,
+        pricing_region: None,
+        video_tokens: 0,
+        cache_write_tokens: 0,
+        audio_input_tokens: 0,
+        audio_output_tokens: 0,
+        image_tokens: 0,
+        embedding_tokens: 0,
+        input_cost: 0,
+        output_cost: 0,
+        cache_read_cost: 0,
+        cache_write_cost: 0,
+        audio_cost: 0,
+        image_cost: 0,
+        video_cost: 0,
+        reasoning_cost: 0,
+        embedding_cost: 0,
+        layer_decision: Some("scorer_picked".to_string()),
+        traffic_color: None,
+        cost_status: Some("ok".to_string()),
+        error_type: (status_code >= 500).then(|| "upstream_error".to_string()),
+        token_hash: None,
+        served_model: None,
+        currency: None,
+        created_at: None,
+    }
+}
+
+async fn admin_jwt(db: &Database) -> anyhow::Result<String> {
+    std::env::set_var("JWT_SECRET", "burncloud-log-api-jwt-secret-2026");
+    std::env::set_var("SKIP_INITIAL_PRICE_SYNC", "1");
+    let service = UserService::new();
+    let user_id = service
+        .register_user(db, "log-admin", "test-password", None)
+        .await?;
+    Ok(service.generate_token(&user_id, "log-admin")?.token)
+}
+
+fn request_ids(page: &Value) -> Vec<String> {
+    page["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|log| log["request_id"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_log_search_export_and_detail() -> anyhow::Result<()> {
+    let db = test_utils::make_isolated_db().await;
+    let jwt = admin_jwt(&db).await?;
+
+    TokenService::create(
+        &db,
+        &RouterToken {
+            token: "sk-search-token".to_string(),
+            user_id: "user-1".to_string(),
+            status: "active".to_string(),
+            quota_limit: -1,
+            used_quota: 0,
+            expired_time: -1,
+            accessed_time: 0,
+            key_version: 1,
+            old_key_hash: None,
+            old_key_expires_at: 0,
+            ip_whitelist: None,
+            key_prefix: "sk-".to_string(),
+            created_at: 0,
+            last_rotated_at: 0,
+            org_id: None,
+            model_policy: None,
+            rpm_limit: None,
+            tpm_limit: None,
+            max_concurrency: None,
+        },
+    )
+    .await?;
+    let mut tokened = sample_log("req-a-1", "model-a", 200, 100, 1_000);
+    tokened.token_hash = Some(token_hash("sk-search-token"));
+    RouterDatabase::insert_log(&db, &tokened).await?;
+    RouterDatabase::insert_log(&db, &sample_log("req-b-1", "model-b", 502, 2_500, 0)).await?;
+    RouterDatabase::insert_log(&db, &sample_log("req-a-2", "model-a", 200, 900, 5_000)).await?;
+    RouterDatabase::insert_request_log(
+        &db,
+        &RouterRequestLog {
+            id: 0,
+            request_id: "req-a-1".to_string(),
+            request_body: Some(r#"{"model":"model-a"}"#.to_string()),
+            request_body_truncated: true,
+            request_headers: None,
+            response_body: Some("{}".to_string()),
+            response_body_truncated: false,
+            response_status: Some(200),
+            stream_chunk_count: 0,
+            stream_first_chunk_latency_ms: None,
+            stream_last_chunk_latency_ms: None,
+            candidates: Some("[]".to_string()),
+            candidates_count: 1,
+            affinity_key: None,
+            affinity_hit_channel_id: None,
+            failover_history: Some("[]".to_string()),
+            storage_policy: "full".to_string(),
+            created_at: None,
+        },
+    )
+    .await?;
+
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let client = Client::new();
+    let get = |query: &str| {
+        client
+            .get(format!("{base}/console/api/logs{query}"))
+            .bearer_auth(&jwt)
+            .send()
+    };
+
+    // Filters
+    let page: Value = get("?model=model-a").await?.json().await?;
+    assert_eq!(request_ids(&page), vec!["req-a-2", "req-a-1"]);
+    let page: Value = get("?status_code=502&error_type=upstream_error")
+        .await?
+        .json()
+        .await?;
+    assert_eq!(request_ids(&page), vec!["req-b-1"]);
+    let page: Value = get("?min_latency_ms=500&max_cost=4000")
+        .await?
+        .json()
+        .await?;
+    assert_eq!(request_ids(&page), vec!["req-b-1"]);
+    let management_id = format!("tok_{:x}", Sha256::digest(b"sk-search-token"));
+    let page: Value = get(&format!("?token={management_id}"))
+        .await?
+        .json()
+        .await?;
+    assert_eq!(request_ids(&page), vec!["req-a-1"]);
+    let hash_prefix = &token_hash("sk-search-token")[..8];
+    let page: Value = get(&format!("?token_hash={hash_prefix}"))
+        .await?
+        .json()
+        .await?;
+    assert_eq!(request_ids(&page), vec!["req-a-1"]);
+    // Raw keys never reach the query string
+    assert_eq!(
+        get("?token=sk-search-token").await?.status(),
+        StatusCode::NOT_FOUND
+    );
+    let page: Value = get("?q=b-1").await?.json().await?;
+    assert_eq!(request_ids(&page), vec!["req-b-1"]);
+    // LIKE wildcards match themselves
+    let page: Value = get("?q=_").await?.json().await?;
+    assert!(request_ids(&page).is_empty());
+    let page: Value = get("?q=%25").await?.json().await?;
+    assert!(request_ids(&page).is_empty());
+    let page: Value = get("?since=2999-01-01T00:00:00Z").await?.json().await?;
+    assert!(request_ids(&page).is_empty());
+    let page: Value = get("?until=2999-01-01").await?.json().await?;
+    assert_eq!(request_ids(&page).len(), 3);
+    assert_eq!(
+        get("?since=yesterday").await?.status(),
+        StatusCode::BAD_REQUEST
+    );
+
+    // Cursor pages are unaffected by logs inserted between requests
+    let first: Value = get("?page_size=2").await?.json().await?;
+    assert_eq!(request_ids(&first), vec!["req-a-2", "req-b-1"]);
+    let cursor = first["next_cursor"].as_i64().unwrap();
+    RouterDatabase::insert_log(&db, &sample_log("req-new", "model-a", 200, 10, 0)).await?;
+    let second: Value = get(&format!("?page_size=2&cursor={cursor}"))
+        .await?
+        .json()
+        .await?;
+    assert_eq!(request_ids(&second), vec!["req-a-1"]);
+    assert!(second["next_cursor"].is_null());
+
+    // Export
+    let csv = get("/export?format=csv&model=model-a").await?;
+    assert_eq!(csv.status(), StatusCode::OK);
+    assert!(csv.headers()["content-type"]
+        .to_str()?
+        .starts_with("text/csv"));
+    let csv = csv.text().await?;
+    let lines: Vec<&str> = csv.lines().collect();
+    assert!(lines[0].starts_with("id,request_id,created_at,"));
+    assert_eq!(lines.len(), 4);
+    assert!(lines[1].contains(",req-new,"));
+
+    let jsonl = get("/export?format=jsonl&status_code=200")
+        .await?
+        .text()
+        .await?;
+    let rows: Vec<Value> = jsonl
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    assert_eq!(rows.len(), 3);
+    assert!(rows.iter().all(|row| row["status_code"] == 200));
+    assert_eq!(
+        get("/export?format=xml").await?.status(),
+        StatusCode::BAD_REQUEST
+    );
+
+    // Detail joins the stored request log
+    let detail: Value = get("/req-a-1").await?.json().await?;
+    assert_eq!(detail["log"]["model"], "model-a");
+    assert_eq!(detail["detail"]["request_body_truncated"], true);
+    assert_eq!(detail["detail"]["candidates_count"], 1);
+    let detail: Value = get("/req-b-1").await?.json().await?;
+    assert!(detail["detail"].is_null());
+    assert_eq!(get("/missing").await?.status(), StatusCode::NOT_FOUND);
+
+    Ok(())
+}
@@ -0,0 +1,292 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::disallowed_types)]
+
+//! Management API keys authenticate console requests within their scopes,
+//! address ranges and lifetime, are refused on step-up routes, and every use
+//! is audited.
+
+mod test_utils;
+
+use burncloud_service_user::{totp::totp_code, PermissionSet, RoleService, UserService};
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+
+const JWT_SECRET: &str = "burncloud-management-key-test-jwt-secret-2026";
+
+#[tokio::test]
+async fn management_keys_are_scoped_restricted_and_audited() -> anyhow::Result<()> {
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    std::env::set_var("SKIP_INITIAL_PRICE_SYNC", "1");
+
+    let db = test_utils::make_isolated_db().await;
+    let service = UserService::new();
+    let admin_id = service
+        .register_user(&db, "key-admin", "test-password", None)
+        .await?;
+    let staff_id = service
+        .register_user(&db, "key-staff", "test-password", None)
+        .await?;
+    let admin_jwt = service.generate_token(&admin_id, "key-admin")?.token;
+    let staff_jwt = service.generate_token(&staff_id, "key-staff")?.token;
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let client = Client::new();
+
+    let create = |jwt: &str, body: Value| {
+        let request = client
+            .post(format!("{base}/console/api/management-keys"))
+            .bearer_auth(jwt)
+            .json(&body);
+        async move { request.send().await.unwrap() }
+    };
+    let get = |path: &str, key: &str| {
+        let request = client.get(format!("{base}{path}")).bearer_auth(key);
+        async move { request.send().await.unwrap().status() }
+    };
+
+    client
+        .post(format!("{base}/console/api/user/roles"))
+        .bearer_auth(&admin_jwt)
+        .json(&json!({ "user_id": staff_id, "roles": ["support"] }))
+        .send()
+        .await?;
+
+    // A key only carries scopes its owner holds
+    let response = create(
+        &staff_jwt,
+        json!({ "name": "ci", "scopes": ["billing:topup"] }),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    for body in [
+        json!({ "name": "ci", "scopes": [] }),
+        json!({ "name": "ci", "scopes": ["logs:read"], "allowed_ips": ["not-an-ip"] }),
+        json!({ "name": "ci", "scopes": ["logs:read"], "expires_at": 1 }),
+    ] {
+        assert_eq!(
+            create(&staff_jwt, body).await.status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    let created: Value = create(
+        &staff_jwt,
+        json!({ "name": "log-shipper", "scopes": ["logs:read"] }),
+    )
+    .await
+    .json()
+    .await?;
+    let key = created["data"]["secret"].as_str().unwrap().to_string();
+    let key_id = created["data"]["id"].as_str().unwrap().to_string();
+    assert!(key.starts_with("bcm_"));
+    assert!(key.starts_with(created["data"]["prefix"].as_str().unwrap()));
+
+    // Within scope it works, outside it is refused even though the owner
+    // holds users:read
+    assert_eq!(get("/console/api/logs", &key).await, StatusCode::OK);
+    assert_eq!(
+        get("/console/api/list_users", &key).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        get("/console/api/list_users", &staff_jwt).await,
+        StatusCode::OK
+    );
+
+    // Keys cannot manage sessions or keys, and are no inference credential
+    assert_eq!(
+        get("/console/api/auth/sessions", &key).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        get("/console/api/management-keys", &key).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(get("/v1/models", &key).await, StatusCode::UNAUTHORIZED);
+
+    // Address ranges are enforced
+    let created: Value = create(
+        &admin_jwt,
+        json!({ "name": "office", "scopes": ["*"], "allowed_ips": ["10.0.0.0/8"] }),
+    )
+    .await
+    .json()
+    .await?;
+    let office_key = created["data"]["secret"].as_str().unwrap().to_string();
+    assert_eq!(
+        get("/console/api/channel", &office_key).await,
+        StatusCode::FORBIDDEN
+    );
+    // A forwarded address from an untrusted peer does not get past them
+    let response = client
+        .get(format!("{base}/console/api/channel"))
+        .bearer_auth(&office_key)
+        .header("x-forwarded-for", "10.20.30.40")
+        .header("x-real-ip", "10.20.30.40")
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let created: Value = create(
+        &admin_jwt,
+        json!({ "name": "local", "scopes": ["*"], "allowed_ips": ["127.0.0.1"] }),
+    )
+    .await
+    .json()
+    .await?;
+    let local_key = created["data"]["secret"].as_str().unwrap().to_string();
+    assert_eq!(
+        get("/console/api/channel", &local_key).await,
+        StatusCode::OK
+    );
+
+    // Last use is tracked and admins see every key
+    let listed: Value = client
+        .get(format!("{base}/console/api/management-keys"))
+        .bearer_auth(&staff_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(listed["data"].as_array().unwrap().len(), 1);
+    assert!(listed["data"][0]["last_used_at"].is_i64());
+    assert!(listed["data"][0].get("secret").is_none());
+    let listed: Value = client
+        .get(format!("{base}/console/api/management-keys?all=true"))
+        .bearer_auth(&admin_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(listed["data"].as_array().unwrap().len(), 3);
+    assert_eq!(
+        get("/console/api/management-keys?all=true", &staff_jwt).await,
+        StatusCode::FORBIDDEN
+    );
+
+    // Losing the role narrows the key, revoking it shuts it out
+    client
+        .post(format!("{base}/console/api/user/roles"))
+        .bearer_auth(&admin_jwt)
+        .json(&json!({ "user_id": staff_id, "roles": ["user"] }))
+        .send()
+        .await?;
+    assert_eq!(get("/console/api/logs", &key).await, StatusCode::FORBIDDEN);
+    let response = client
+        .delete(format!("{base}/console/api/management-keys/{key_id}"))
+        .bearer_auth(&staff_jwt)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        get("/console/api/logs", &key).await,
+        StatusCode::UNAUTHORIZED
+    );
+
+    let audit: Value = client
+        .get(format!(
+            "{base}/console/api/audit?action=management_key.use&target_id={key_id}"
+        ))
+        .bearer_auth(&admin_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    let entries = audit["data"]["entries"].as_array().unwrap();
+    assert_eq!(audit["data"]["total"], 5);
+    assert!(entries.iter().all(|e| e["source"] == "management_key"));
+    assert!(entries.iter().all(|e| e["ip"] == "127.0.0.1"));
+    assert!(entries
+        .iter()
+        .any(|e| e["changes"]["status"]["after"] == 403));
+    let audit: Value = client
+        .get(format!(
+            "{base}/console/api/audit?action=management_key.revoke"
+        ))
+        .bearer_auth(&admin_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(audit["data"]["total"], 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn management_keys_are_refused_on_step_up_routes() -> anyhow::Result<()> {
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    std::env::set_var("SKIP_INITIAL_PRICE_SYNC", "1");
+
+    let db = test_utils::make_isolated_db().await;
+    let service = UserService::new();
+    let plain_id = service
+        .register_user(&db, "su-plain", "test-password", None)
+        .await?;
+    // Second administrator with two-factor authentication
+    let secure_id = service
+        .register_user(&db, "su-secure", "test-password", None)
+        .await?;
+    let admin: PermissionSet = ["*"].into_iter().collect();
+    RoleService::set_user_roles(&db, &admin, &secure_id, &["admin".to_string()]).await?;
+    let setup = service
+        .begin_two_factor_setup(&db, &secure_id, "su-secure")
+        .await?;
+    let now = chrono::Utc::now().timestamp();
+    let enable_code = totp_code(&setup.secret, now).unwrap();
+    service
+        .enable_two_factor(&db, &secure_id, &enable_code)
+        .await?;
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let client = Client::new();
+
+    let mut keys = Vec::new();
+    for (user_id, username) in [(&plain_id, "su-plain"), (&secure_id, "su-secure")] {
+        let jwt = service.generate_token(user_id, username)?.token;
+        let created: Value = client
+            .post(format!("{base}/console/api/management-keys"))
+            .bearer_auth(&jwt)
+            .json(&json!({ "name": "automation", "scopes": ["*"] }))
+            .send()
+            .await?
+            .json()
+            .await?;
+        assert_eq!(created["success"], true, "{created}");
+        keys.push(created["data"]["secret"].as_str().unwrap().to_string());
+    }
+
+    // The same rule applies whether or not the owner uses two-factor
+    // authentication, and a TOTP code does not let a key through
+    let totp = totp_code(&setup.secret, now + 30).unwrap();
+    for key in &keys {
+        let response = client
+            .get(format!("{base}/console/api/roles"))
+            .bearer_auth(key)
+            .send()
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = client
+            .post(format!("{base}/console/api/roles"))
+            .bearer_auth(key)
+            .header("x-totp-code", &totp)
+            .json(&json!({ "name": "scripted", "permissions": ["logs:read"] }))
+            .send()
+            .await?;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body: Value = response.json().await?;
+        assert!(body["message"]
+            .as_str()
+            .unwrap()
+            .contains("two-factor step-up"));
+    }
+
+    let plain_jwt = service.generate_token(&plain_id, "su-plain")?.token;
+    let roles: Value = client
+        .get(format!("{base}/console/api/roles"))
+        .bearer_auth(&plain_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert!(!roles.to_string().contains("scripted"));
+
+    Ok(())
+}
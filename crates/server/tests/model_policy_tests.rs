@@ -0,0 +1,338 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::disallowed_types)]
+
+mod test_utils;
+
+use axum::{routing::post, Json, Router};
+use burncloud_database::Database;
+use burncloud_database_router::{
+    token_hash, RouterDatabase, RouterLog, VirtualModel, VirtualModelHop, VirtualModelModel,
+};
+use burncloud_service_user::UserService;
+use reqwest::{Client, StatusCode};
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+
+const JWT_SECRET: &str = "burncloud-model-policy-jwt-secret-2026";
+
+fn configure_env() {
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    std::env::set_var("SKIP_INITIAL_PRICE_SYNC", "1");
+}
+
+/// Register a user and return (user_id, jwt).
+async fn principal(db: &Database, username: &str) -> anyhow::Result<(String, String)> {
+    let service = UserService::new();
+    let user_id = service
+        .register_user(db, username, "test-password", None)
+        .await?;
+    let jwt = service.generate_token(&user_id, username)?.token;
+    Ok((user_id, jwt))
+}
+
+/// A settled log row for `token` that spent `cost` nanodollars on `model` just now.
+fn spend_log(token: &str, user_id: &str, model: &str, cost: i64) -> RouterLog {
+    RouterLog {
+        id: 0,
+        request_id: uuid::Uuid::new_v4().to_string(),
+        user_id: Some(user_id.to_string()),
+        path: "/v1/chat/completions".to_string(),
+        upstream_id: Some("1".to_string()),
+        status_code: 200,
+        latency_ms: 10,
+        prompt_tokens: 10,
+        completion_tokens: 10,
+        cost,
+        model: Some(model.to_string()),
+        cache_read_tokens: 0,
+        reasoning_tokens: 0,
+        pricing_region: None,
+        video_tokens: 0,
+        cache_write_tokens: 0,
+        audio_input_tokens: 0,
+        audio_output_tokens: 0,
+        image_tokens: 0,
+        embedding_tokens: 0,
+        input_cost: 0,
+        output_cost: 0,
+        cache_read_cost: 0,
+        cache_write_cost: 0,
+        audio_cost: 0,
+        image_cost: 0,
+        video_cost: 0,
+        reasoning_cost: 0,
+        embedding_cost: 0,
+        layer_decision: None,
+        traffic_color: None,
+        cost_status: Some("ok".to_string()),
+        error_type: None,
+        token_hash: Some(token_hash(token)),
+        served_model: None,
+        currency: None,
+        created_at: None,
+    }
+}
+
+/// Upstream that records the model each request was sent for.
+async fn spawn_upstream(seen: Arc<Mutex<Vec<String>>>) -> anyhow::Result<String> {
+    let app = Router::new().route(
+        "/chat/completions",
+        post(move |Json(body): Json<Value>| {
+            let seen = seen.clone();
+            async move {
+                let model = body["model"].as_str().unwrap_or_default().to_string();
+                seen.lock().unwrap().push(model.clone());
+                Json(serde_json::json!({
+                    "id": "chatcmpl-policy",
+                    "object": "chat.completion",
+                    "model": model,
+                    "choices": [{
+                        "index": 0,
+                        "message": { "role": "assistant", "content": "ok" },
+                        "finish_reason": "stop"
+                    }],
+                    "usage": { "prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2 }
+                }))
+            }
+        }),
+    );
+    test_utils::spawn_app(app).await
+}
+
+fn virtual_model(name: &str, hops: &[&str]) -> VirtualModel {
+    VirtualModel {
+        name: name.to_string(),
+        description: None,
+        hops: hops
+            .iter()
+            .map(|model| VirtualModelHop {
+                model: model.to_string(),
+                param_override: None,
+            })
+            .collect(),
+        enabled: true,
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+fn chat(model: &str) -> Value {
+    serde_json::json!({
+        "model": model,
+        "messages": [{"role": "user", "content": "hello"}]
+    })
+}
+
+#[tokio::test]
+async fn token_model_policy_restricts_models_and_caps_spend() -> anyhow::Result<()> {
+    configure_env();
+    let db = test_utils::make_isolated_db().await;
+    let (_admin_id, admin_jwt) = principal(&db, "policy-admin").await?;
+    let (user_id, user_jwt) = principal(&db, "policy-user").await?;
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let client = Client::new();
+
+    let channel = client
+        .post(format!("{base}/console/api/channel"))
+        .bearer_auth(&admin_jwt)
+        .json(&serde_json::json!({
+            "type": 1,
+            "key": "sk-upstream",
+            "name": "policy-channel",
+            "base_url": "http://127.0.0.1:9",
+            "models": "cheap-mini,cheap-capped,premium-large",
+            "group": "default",
+            "weight": 1,
+            "priority": 0
+        }))
+        .send()
+        .await?;
+    assert_eq!(channel.status(), StatusCode::OK);
+
+    let invalid = client
+        .post(format!("{base}/console/api/tokens"))
+        .bearer_auth(&user_jwt)
+        .json(&serde_json::json!({
+            "user_id": user_id,
+            "model_policy": { "caps": [{ "model": "cheap-*" }] }
+        }))
+        .send()
+        .await?;
+    assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+
+    let created: Value = client
+        .post(format!("{base}/console/api/tokens"))
+        .bearer_auth(&user_jwt)
+        .json(&serde_json::json!({
+            "user_id": user_id,
+            "model_policy": {
+                "allow": ["cheap-*"],
+                "caps": [{ "model": "cheap-capped", "daily": 1_000 }]
+            }
+        }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let api_key = created["data"]["token"].as_str().unwrap().to_string();
+
+    let denied = client
+        .post(format!("{base}/v1/chat/completions"))
+        .bearer_auth(&api_key)
+        .json(&chat("premium-large"))
+        .send()
+        .await?;
+    assert_eq!(denied.status(), StatusCode::FORBIDDEN);
+    assert!(denied.text().await?.contains("model_not_allowed"));
+
+    let allowed = client
+        .post(format!("{base}/v1/chat/completions"))
+        .bearer_auth(&api_key)
+        .json(&chat("cheap-mini"))
+        .send()
+        .await?;
+    assert_ne!(allowed.status(), StatusCode::FORBIDDEN);
+
+    // Spend already logged today counts against the cap, as it would after a restart
+    let logged = spend_log(&api_key, &user_id, "cheap-capped", 1_000);
+    RouterDatabase::insert_log(&db, &logged).await?;
+    let capped = client
+        .post(format!("{base}/v1/chat/completions"))
+        .bearer_auth(&api_key)
+        .json(&chat("cheap-capped"))
+        .send()
+        .await?;
+    assert_eq!(capped.status(), StatusCode::PAYMENT_REQUIRED);
+    assert!(capped.text().await?.contains("model_spend_cap_exceeded"));
+
+    let models: Value = client
+        .get(format!("{base}/v1/models"))
+        .bearer_auth(&api_key)
+        .send()
+        .await?
+        .json()
+        .await?;
+    let ids: Vec<&str> = models["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["id"].as_str().unwrap())
+        .collect();
+    assert!(ids.contains(&"cheap-mini"));
+    assert!(!ids.contains(&"premium-large"));
+
+    // Clearing the policy lifts the restriction
+    let summary: Value = client
+        .get(format!("{base}/console/api/tokens"))
+        .bearer_auth(&user_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    let token_ref = summary["data"][0]["token"].as_str().unwrap().to_string();
+    assert_eq!(summary["data"][0]["model_policy"]["allow"][0], "cheap-*");
+    let cleared = client
+        .post(format!(
+            "{base}/console/api/tokens/{token_ref}/model-policy"
+        ))
+        .bearer_auth(&user_jwt)
+        .json(&serde_json::json!({ "model_policy": null }))
+        .send()
+        .await?;
+    assert_eq!(cleared.status(), StatusCode::OK);
+
+    let unrestricted = client
+        .post(format!("{base}/v1/chat/completions"))
+        .bearer_auth(&api_key)
+        .json(&chat("premium-large"))
+        .send()
+        .await?;
+    assert_ne!(unrestricted.status(), StatusCode::FORBIDDEN);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn virtual_model_policy_skips_refused_hops_and_caps_the_served_model() -> anyhow::Result<()> {
+    configure_env();
+    let db = test_utils::make_isolated_db().await;
+    let (_admin_id, admin_jwt) = principal(&db, "hop-admin").await?;
+    let (user_id, user_jwt) = principal(&db, "hop-user").await?;
+    for model in ["hop-denied", "hop-capped", "hop-open"] {
+        db.execute_query(&format!(
+            "INSERT INTO billing_prices (model, currency, input_price, output_price, region, created_at) \
+             VALUES ('{model}', 'USD', 0, 0, '', 1700000000)"
+        ))
+        .await?;
+    }
+    VirtualModelModel::upsert(
+        &db,
+        &virtual_model("smart", &["hop-denied", "hop-capped", "hop-open"]),
+    )
+    .await?;
+    VirtualModelModel::upsert(&db, &virtual_model("locked", &["hop-denied"])).await?;
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let upstream = spawn_upstream(seen.clone()).await?;
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let client = Client::new();
+
+    let channel = client
+        .post(format!("{base}/console/api/channel"))
+        .bearer_auth(&admin_jwt)
+        .json(&serde_json::json!({
+            "type": 1,
+            "key": "sk-upstream",
+            "name": "hop-channel",
+            "base_url": upstream,
+            "models": "hop-denied,hop-capped,hop-open",
+            "group": "default",
+            "weight": 1,
+            "priority": 0
+        }))
+        .send()
+        .await?;
+    assert_eq!(channel.status(), StatusCode::OK);
+
+    let created: Value = client
+        .post(format!("{base}/console/api/tokens"))
+        .bearer_auth(&user_jwt)
+        .json(&serde_json::json!({
+            "user_id": user_id,
+            "model_policy": {
+                "allow": ["hop-capped", "hop-open"],
+                "caps": [{ "model": "hop-capped", "daily": 1_000 }]
+            }
+        }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let api_key = created["data"]["token"].as_str().unwrap().to_string();
+
+    // Spend logged under the alias counts against the hop that served it
+    let mut logged = spend_log(&api_key, &user_id, "smart", 1_000);
+    logged.served_model = Some("hop-capped".to_string());
+    RouterDatabase::insert_log(&db, &logged).await?;
+
+    // The alias itself is not on the allow list; its hops are checked instead,
+    // and the refused and capped ones are never tried
+    let served = client
+        .post(format!("{base}/v1/chat/completions"))
+        .bearer_auth(&api_key)
+        .json(&chat("smart"))
+        .send()
+        .await?;
+    assert_eq!(served.status(), StatusCode::OK);
+    assert_eq!(seen.lock().unwrap().as_slice(), ["hop-open"]);
+
+    let locked = client
+        .post(format!("{base}/v1/chat/completions"))
+        .bearer_auth(&api_key)
+        .json(&chat("locked"))
+        .send()
+        .await?;
+    assert_eq!(locked.status(), StatusCode::FORBIDDEN);
+    assert!(locked.text().await?.contains("model_not_allowed"));
+    assert_eq!(seen.lock().unwrap().len(), 1);
+
+    Ok(())
+}
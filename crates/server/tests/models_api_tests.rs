@@ -0,0 +1,191 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::disallowed_types)]
+
+//! `/v1/models` and `/v1beta/models`: caller authentication, group and
+//! model-policy filtering, metadata and native Anthropic/Gemini formats.
+
+mod test_utils;
+
+use burncloud_database::Database;
+use burncloud_service_user::UserService;
+use reqwest::{Client, StatusCode};
+use serde_json::Value;
+
+const JWT_SECRET: &str = "burncloud-models-api-jwt-secret-2026";
+
+fn configure_env() {
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    std::env::set_var("SKIP_INITIAL_PRICE_SYNC", "1");
+}
+
+/// Register a user and return (user_id, jwt).
+async fn principal(db: &Database, username: &str) -> anyhow::Result<(String, String)> {
+    let service = UserService::new();
+    let user_id = service
+        .register_user(db, username, "test-password", None)
+        .await?;
+    let jwt = service.generate_token(&user_id, username)?.token;
+    Ok((user_id, jwt))
+}
+
+fn ids<'a>(list: &'a Value, field: &str, key: &str) -> Vec<&'a str> {
+    list[field]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m[key].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn models_are_filtered_by_group_and_policy_with_metadata() -> anyhow::Result<()> {
+    configure_env();
+    let db = test_utils::make_isolated_db().await;
+    let (_admin_id, admin_jwt) = principal(&db, "models-admin").await?;
+    let (user_id, user_jwt) = principal(&db, "models-user").await?;
+    // Loaded into the price cache when the server starts
+    db.execute_query(
+        "INSERT INTO billing_prices (model, currency, input_price, output_price, region, \
+         context_window, max_output_tokens, supports_vision, supports_function_calling, created_at) \
+         VALUES ('listed-mini', 'USD', 150000000, 600000000, '', 128000, 16384, 1, 1, 1700000000)",
+    )
+    .await?;
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let client = Client::new();
+
+    for (name, models, group) in [
+        ("default-channel", "listed-mini,listed-hidden", "default"),
+        ("vip-channel", "vip-only", "vip"),
+    ] {
+        let created = client
+            .post(format!("{base}/console/api/channel"))
+            .bearer_auth(&admin_jwt)
+            .json(&serde_json::json!({
+                "type": 1,
+                "key": "sk-upstream",
+                "name": name,
+                "base_url": "http://127.0.0.1:9",
+                "models": models,
+                "group": group,
+                "weight": 1,
+                "priority": 0
+            }))
+            .send()
+            .await?;
+        assert_eq!(created.status(), StatusCode::OK);
+    }
+
+    let token: Value = client
+        .post(format!("{base}/console/api/tokens"))
+        .bearer_auth(&user_jwt)
+        .json(&serde_json::json!({
+            "user_id": user_id,
+            "model_policy": { "deny": ["listed-hidden"] }
+        }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let api_key = token["data"]["token"].as_str().unwrap().to_string();
+
+    let anonymous = client.get(format!("{base}/v1/models")).send().await?;
+    assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+
+    // Default group, policy denies listed-hidden
+    let models: Value = client
+        .get(format!("{base}/v1/models"))
+        .bearer_auth(&api_key)
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(ids(&models, "data", "id"), ["listed-mini"]);
+    let mini = &models["data"][0];
+    assert_eq!(mini["created"], 1_700_000_000);
+    assert_eq!(mini["context_window"], 128_000);
+    assert_eq!(mini["max_output_tokens"], 16_384);
+    assert_eq!(mini["capabilities"]["function_calling"], true);
+    assert_eq!(mini["pricing"]["input"], 0.15);
+
+    let detail = client
+        .get(format!("{base}/v1/models/listed-mini"))
+        .bearer_auth(&api_key)
+        .send()
+        .await?;
+    assert_eq!(detail.status(), StatusCode::OK);
+    assert_eq!(detail.json::<Value>().await?["id"], "listed-mini");
+    for hidden in ["vip-only", "listed-hidden"] {
+        let missing = client
+            .get(format!("{base}/v1/models/{hidden}"))
+            .bearer_auth(&api_key)
+            .send()
+            .await?;
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND, "{hidden}");
+    }
+
+    // Anthropic SDKs authenticate with x-api-key and send anthropic-version
+    let anthropic: Value = client
+        .get(format!("{base}/v1/models"))
+        .header("x-api-key", &api_key)
+        .header("anthropic-version", "2023-06-01")
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(anthropic["data"][0]["type"], "model");
+    assert_eq!(anthropic["first_id"], "listed-mini");
+
+    let gemini: Value = client
+        .get(format!("{base}/v1beta/models"))
+        .header("x-goog-api-key", &api_key)
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(ids(&gemini, "models", "name"), ["models/listed-mini"]);
+    assert_eq!(gemini["models"][0]["inputTokenLimit"], 128_000);
+
+    // Generation calls under the same prefix still reach the proxy
+    let generate = client
+        .post(format!("{base}/v1beta/models/listed-mini:generateContent"))
+        .header("x-goog-api-key", &api_key)
+        .json(&serde_json::json!({ "contents": [{ "parts": [{ "text": "hi" }] }] }))
+        .send()
+        .await?;
+    assert_ne!(generate.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_ne!(generate.status(), StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_listing_fails_loudly_when_the_catalog_cannot_be_read() -> anyhow::Result<()> {
+    configure_env();
+    let db = test_utils::make_isolated_db().await;
+    let (user_id, jwt) = principal(&db, "catalog-user").await?;
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let client = Client::new();
+    let token: Value = client
+        .post(format!("{base}/console/api/tokens"))
+        .bearer_auth(&jwt)
+        .json(&serde_json::json!({ "user_id": user_id }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let api_key = token["data"]["token"].as_str().unwrap().to_string();
+    db.execute_query("DROP TABLE router_virtual_models").await?;
+
+    // An empty 200 would read as "no models" to clients
+    for path in ["/v1/models", "/v1/models/anything", "/v1beta/models"] {
+        let response = client
+            .get(format!("{base}{path}"))
+            .bearer_auth(&api_key)
+            .send()
+            .await?;
+        assert_eq!(
+            response.status(),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "{path}"
+        );
+    }
+    Ok(())
+}
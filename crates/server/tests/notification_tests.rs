@@ -0,0 +1,388 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::disallowed_types)]
+
+//! Low-balance and key quota thresholds: settings and thresholds through the
+//! console API, once-per-crossing email and signed webhook delivery on
+//! settlement, re-arming after a top-up, the settings in `/api/v1/usage`, and
+//! webhooks refused on internal addresses.
+
+mod test_utils;
+
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::routing::post;
+use axum::Router;
+use burncloud_common::NANO_PER_DOLLAR;
+use burncloud_database::Database;
+use burncloud_database_router::RouterDatabase;
+use burncloud_database_sys::MailOutboxModel;
+use burncloud_database_user::{UserDatabase, UserNotificationModel, UserNotificationSettings};
+use burncloud_service_notification::{
+    webhook, NewThreshold, NotificationError, NotificationService, SettingsUpdate,
+};
+use burncloud_service_token::{RouterToken, TokenService};
+use burncloud_service_user::UserService;
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const JWT_SECRET: &str = "burncloud-notification-test-jwt-secret-2026";
+const API_KEY: &str = "bc_live_notification_test_key_0001";
+
+/// Signature header and body of every webhook received
+#[derive(Clone, Default)]
+struct Receiver {
+    calls: Arc<Mutex<Vec<(String, String)>>>,
+}
+
+async fn receive(State(receiver): State<Receiver>, headers: HeaderMap, body: Bytes) {
+    let signature = headers[webhook::SIGNATURE_HEADER]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    receiver.calls.lock().unwrap().push((signature, body));
+}
+
+/// Wait for the spawned webhook deliveries to land
+async fn webhooks(receiver: &Receiver, expected: usize) -> Vec<(String, String)> {
+    for _ in 0..50 {
+        if receiver.calls.lock().unwrap().len() >= expected {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    receiver.calls.lock().unwrap().clone()
+}
+
+async fn mail_templates(db: &Database) -> Vec<String> {
+    let mut mails = MailOutboxModel::list(db, None, 50).await.unwrap();
+    mails.reverse();
+    mails.into_iter().map(|m| m.template).collect()
+}
+
+fn api_key(user_id: &str) -> RouterToken {
+    RouterToken {
+        token: API_KEY.to_string(),
+        user_id: user_id.to_string(),
+        status: "active".to_string(),
+        quota_limit: 10 * NANO_PER_DOLLAR,
+        used_quota: 0,
+        expired_time: -1,
+        accessed_time: 0,
+        key_version: 1,
+        old_key_hash: None,
+        old_key_expires_at: 0,
+        ip_whitelist: None,
+        key_prefix: "bc_live_".to_string(),
+        created_at: 0,
+        last_rotated_at: 0,
+        org_id: None,
+        model_policy: None,
+        rpm_limit: None,
+        tpm_limit: None,
+        max_concurrency: None,
+    }
+}
+
+#[tokio::test]
+async fn thresholds_notify_once_per_crossing() -> anyhow::Result<()> {
+    let receiver = Receiver::default();
+    let hook_base = test_utils::spawn_app(
+        Router::new()
+            .route("/hook", post(receive))
+            .with_state(receiver.clone()),
+    )
+    .await?;
+
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    std::env::set_var("SKIP_INITIAL_PRICE_SYNC", "1");
+    std::env::set_var("BASE_URL", "https://console.example");
+    // The receiver listens on loopback
+    std::env::set_var("NOTIFICATION_WEBHOOK_ALLOW_PRIVATE", "true");
+
+    let db = test_utils::make_isolated_db().await;
+    let service = UserService::new();
+    service
+        .register_user(&db, "notify-admin", "test-password", None)
+        .await?;
+    let customer_id = service
+        .register_user(
+            &db,
+            "notify-customer",
+            "test-password",
+            Some("customer@example.com".into()),
+        )
+        .await?;
+    let jwt = service
+        .generate_token(&customer_id, "notify-customer")?
+        .token;
+    TokenService::create(&db, &api_key(&customer_id)).await?;
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let client = Client::new();
+
+    // Email-only defaults before anything is saved
+    let defaults: Value = client
+        .get(format!("{base}/console/api/notifications"))
+        .bearer_auth(&jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(defaults["data"]["settings"]["email_enabled"], 1);
+    assert!(defaults["data"]["settings"]["webhook_url"].is_null());
+
+    let response = client
+        .put(format!("{base}/console/api/notifications"))
+        .bearer_auth(&jwt)
+        .json(&json!({ "webhook_url": "ftp://example.com/hook" }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let saved: Value = client
+        .put(format!("{base}/console/api/notifications"))
+        .bearer_auth(&jwt)
+        .json(&json!({ "email_enabled": true, "webhook_url": format!("{hook_base}/hook") }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let secret = saved["data"]["webhook_secret"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert!(secret.starts_with("whsec_"));
+
+    let key_id = format!("tok_{:x}", Sha256::digest(API_KEY.as_bytes()));
+    for (body, status) in [
+        (
+            json!({ "token": key_id, "kind": "percent", "value": 150 }),
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            json!({ "kind": "percent", "value": 20 }),
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            json!({ "currency": "EUR", "kind": "absolute", "value": NANO_PER_DOLLAR }),
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            json!({ "token": "tok_unknown", "kind": "percent", "value": 20 }),
+            StatusCode::NOT_FOUND,
+        ),
+    ] {
+        let response = client
+            .post(format!("{base}/console/api/notifications/thresholds"))
+            .bearer_auth(&jwt)
+            .json(&body)
+            .send()
+            .await?;
+        assert_eq!(response.status(), status, "{body}");
+    }
+
+    let key_threshold: Value = client
+        .post(format!("{base}/console/api/notifications/thresholds"))
+        .bearer_auth(&jwt)
+        .json(&json!({ "token": key_id, "kind": "percent", "value": 20 }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(key_threshold["data"]["token"], key_id.as_str());
+    assert_eq!(key_threshold["data"]["token_hint"], "bc_live_…0001");
+    let response = client
+        .post(format!("{base}/console/api/notifications/thresholds"))
+        .bearer_auth(&jwt)
+        .json(&json!({ "currency": "USD", "kind": "absolute", "value": NANO_PER_DOLLAR }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The customer starts with a $5 wallet and a $10 key quota
+    let account = UserDatabase::get_user_by_id(&db, &customer_id)
+        .await?
+        .unwrap();
+    UserDatabase::update_balance_usd(&db, &customer_id, 5 * NANO_PER_DOLLAR - account.balance_usd)
+        .await?;
+    let notifications = NotificationService::from_env();
+    let settle = |cost: i64| {
+        let (db, notifications, customer_id) = (db.clone(), &notifications, &customer_id);
+        async move {
+            RouterDatabase::deduct_quota(&db, customer_id, API_KEY, cost)
+                .await
+                .unwrap();
+            notifications.evaluate(&db, customer_id, API_KEY).await;
+        }
+    };
+
+    // 30% of the quota left: above both thresholds
+    settle(7 * NANO_PER_DOLLAR).await;
+    assert!(mail_templates(&db).await.is_empty());
+
+    // 15% left crosses the key threshold, once
+    settle(NANO_PER_DOLLAR + NANO_PER_DOLLAR / 2).await;
+    settle(NANO_PER_DOLLAR / 10).await;
+    assert_eq!(mail_templates(&db).await, ["quota_low"]);
+    let calls = webhooks(&receiver, 1).await;
+    assert_eq!(calls.len(), 1);
+    let (signature, body) = &calls[0];
+    let payload: Value = serde_json::from_str(body)?;
+    assert_eq!(payload["type"], webhook::EVENT_QUOTA_LOW);
+    assert_eq!(payload["remaining"], 15 * NANO_PER_DOLLAR / 10);
+    assert_eq!(payload["limit"], 10 * NANO_PER_DOLLAR);
+    assert_eq!(
+        *signature,
+        webhook::sign(
+            &secret,
+            payload["created_at"].as_i64().unwrap(),
+            body.as_bytes()
+        )
+    );
+
+    // The wallet drops to $0.50, then recovers and crosses again
+    UserDatabase::update_balance_usd(&db, &customer_id, -9 * NANO_PER_DOLLAR / 2).await?;
+    settle(NANO_PER_DOLLAR / 100).await;
+    settle(NANO_PER_DOLLAR / 100).await;
+    assert_eq!(mail_templates(&db).await, ["quota_low", "low_balance"]);
+    UserDatabase::update_balance_usd(&db, &customer_id, 2 * NANO_PER_DOLLAR).await?;
+    settle(NANO_PER_DOLLAR / 100).await;
+    UserDatabase::update_balance_usd(&db, &customer_id, -2 * NANO_PER_DOLLAR).await?;
+    settle(NANO_PER_DOLLAR / 100).await;
+    assert_eq!(
+        mail_templates(&db).await,
+        ["quota_low", "low_balance", "low_balance"]
+    );
+    let calls = webhooks(&receiver, 3).await;
+    assert_eq!(calls.len(), 3);
+    let balance_low: Vec<Value> = calls
+        .iter()
+        .map(|(_, body)| serde_json::from_str::<Value>(body).unwrap())
+        .filter(|payload| payload["type"] == webhook::EVENT_BALANCE_LOW)
+        .collect();
+    assert_eq!(balance_low.len(), 2);
+    for payload in balance_low {
+        assert_eq!(payload["currency"], "USD");
+        assert_eq!(payload["remaining"], NANO_PER_DOLLAR / 2);
+    }
+
+    let low_balance = &MailOutboxModel::list(&db, None, 1).await?[0];
+    assert_eq!(low_balance.recipient, "customer@example.com");
+    assert!(low_balance.body_text.contains("$0.50"));
+    assert!(low_balance.body_text.contains("$1.00"));
+
+    // The usage API shows the settings without the signing secret
+    let usage: Value = client
+        .get(format!("{base}/api/v1/usage"))
+        .bearer_auth(API_KEY)
+        .send()
+        .await?
+        .json()
+        .await?;
+    let shown = &usage["notifications"];
+    assert_eq!(
+        shown["settings"]["webhook_url"],
+        format!("{hook_base}/hook").as_str()
+    );
+    assert!(shown["settings"].get("webhook_secret").is_none());
+    assert_eq!(shown["thresholds"].as_array().unwrap().len(), 2);
+    assert!(!usage.to_string().contains(&secret));
+
+    // Deleting a threshold stops it, and only its owner can delete it
+    let threshold_id = key_threshold["data"]["id"].as_str().unwrap();
+    let response = client
+        .delete(format!(
+            "{base}/console/api/notifications/thresholds/{threshold_id}"
+        ))
+        .bearer_auth(&jwt)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client
+        .delete(format!(
+            "{base}/console/api/notifications/thresholds/{threshold_id}"
+        ))
+        .bearer_auth(&jwt)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn webhooks_only_reach_public_addresses() -> anyhow::Result<()> {
+    let receiver = Receiver::default();
+    let hook_base = test_utils::spawn_app(
+        Router::new()
+            .route("/hook", post(receive))
+            .with_state(receiver.clone()),
+    )
+    .await?;
+    let hook_url = format!("{hook_base}/hook");
+
+    let db = test_utils::make_isolated_db().await;
+    let user_id = UserService::new()
+        .register_user(&db, "notify-internal", "test-password", None)
+        .await?;
+    let service = NotificationService::new(None, "https://console.example");
+
+    // Loopback, private, metadata and names resolving to them are refused
+    for url in [
+        hook_url.as_str(),
+        "http://10.0.0.1/hook",
+        "http://169.254.169.254/latest/meta-data/",
+        "http://[::1]/hook",
+        "http://localhost/hook",
+    ] {
+        let update = SettingsUpdate {
+            webhook_url: Some(url.to_string()),
+            ..Default::default()
+        };
+        assert!(
+            matches!(
+                service.save_settings(&db, &user_id, update).await,
+                Err(NotificationError::InvalidInput(_))
+            ),
+            "{url}"
+        );
+    }
+
+    // A URL stored before the check is refused again when delivering
+    let mut settings = UserNotificationSettings::new(&user_id);
+    settings.email_enabled = 0;
+    settings.webhook_url = Some(hook_url.clone());
+    settings.webhook_secret = Some(webhook::generate_secret());
+    UserNotificationModel::save_settings(&db, &settings).await?;
+    NotificationService::create_threshold(
+        &db,
+        &user_id,
+        NewThreshold {
+            currency: Some("USD".to_string()),
+            kind: "absolute".to_string(),
+            value: 1_000_000 * NANO_PER_DOLLAR,
+            ..Default::default()
+        },
+    )
+    .await?;
+    service.evaluate(&db, &user_id, API_KEY).await;
+    let thresholds = NotificationService::list_thresholds(&db, &user_id).await?;
+    assert!(thresholds[0].triggered_at.is_some());
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(receiver.calls.lock().unwrap().is_empty());
+
+    // Operators can allow receivers on their own network
+    let allowed =
+        NotificationService::new(None, "https://console.example").allow_private_webhooks(true);
+    let update = SettingsUpdate {
+        webhook_url: Some(hook_url.clone()),
+        ..Default::default()
+    };
+    let saved = allowed.save_settings(&db, &user_id, update).await?;
+    assert_eq!(saved.webhook_url.as_deref(), Some(hook_url.as_str()));
+
+    Ok(())
+}
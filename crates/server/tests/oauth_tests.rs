@@ -0,0 +1,339 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::disallowed_types)]
+
+//! OAuth sign-in against a local mock IdP that speaks OIDC discovery and the
+//! GitHub OAuth app endpoints, including PKCE verification.
+
+mod test_utils;
+
+use axum::extract::{Form, Query, State};
+use axum::http::HeaderMap;
+use axum::response::Redirect;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use burncloud_service_user::UserService;
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+const JWT_SECRET: &str = "burncloud-oauth-jwt-secret-2026";
+
+#[derive(Clone, Default)]
+struct MockIdp {
+    base: String,
+    /// code -> (code_challenge, subject)
+    codes: Arc<Mutex<HashMap<String, (String, String)>>>,
+}
+
+async fn discovery(State(idp): State<MockIdp>) -> Json<Value> {
+    Json(json!({
+        "issuer": idp.base,
+        "authorization_endpoint": format!("{}/authorize", idp.base),
+        "token_endpoint": format!("{}/token", idp.base),
+        "userinfo_endpoint": format!("{}/userinfo", idp.base),
+    }))
+}
+
+/// Approves immediately as the subject named by the test's `login` parameter
+async fn authorize(
+    State(idp): State<MockIdp>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Redirect {
+    assert_eq!(query["code_challenge_method"], "S256");
+    let mut codes = idp.codes.lock().unwrap();
+    let code = format!("code-{}", codes.len());
+    codes.insert(
+        code.clone(),
+        (query["code_challenge"].clone(), query["login"].clone()),
+    );
+    Redirect::to(&format!(
+        "{}?code={code}&state={}",
+        query["redirect_uri"], query["state"]
+    ))
+}
+
+async fn token(
+    State(idp): State<MockIdp>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Json<Value> {
+    let Some((challenge, subject)) = idp.codes.lock().unwrap().remove(&form["code"]) else {
+        return Json(json!({ "error": "invalid_grant" }));
+    };
+    let computed = URL_SAFE_NO_PAD.encode(Sha256::digest(form["code_verifier"].as_bytes()));
+    if computed != challenge {
+        return Json(json!({ "error": "invalid_grant", "error_description": "PKCE mismatch" }));
+    }
+    Json(json!({ "access_token": format!("at-{subject}"), "token_type": "bearer" }))
+}
+
+fn subject(headers: &HeaderMap) -> String {
+    headers["authorization"]
+        .to_str()
+        .unwrap()
+        .trim_start_matches("Bearer at-")
+        .to_string()
+}
+
+async fn userinfo(headers: HeaderMap) -> Json<Value> {
+    let sub = subject(&headers);
+    Json(json!({
+        "sub": sub,
+        "email": format!("{sub}@example.com"),
+        "email_verified": "true",
+        "preferred_username": sub,
+    }))
+}
+
+async fn github_user(headers: HeaderMap) -> Json<Value> {
+    assert!(headers.contains_key("user-agent"));
+    Json(json!({ "id": subject(&headers).parse::<i64>().unwrap(), "login": "octo", "email": null }))
+}
+
+async fn github_emails() -> Json<Value> {
+    Json(json!([{ "email": "octo@example.com", "primary": true, "verified": true }]))
+}
+
+async fn spawn_idp() -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let idp = MockIdp {
+        base: base.clone(),
+        ..Default::default()
+    };
+    let app = Router::new()
+        .route("/.well-known/openid-configuration", get(discovery))
+        .route("/authorize", get(authorize))
+        .route("/token", post(token))
+        .route("/userinfo", get(userinfo))
+        .route("/login/oauth/authorize", get(authorize))
+        .route("/login/oauth/access_token", post(token))
+        .route("/api/user", get(github_user))
+        .route("/api/user/emails", get(github_emails))
+        .with_state(idp);
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    base
+}
+
+/// Follow an authorize URL through the mock IdP back to our callback
+async fn sign_in(client: &Client, authorize_url: &str, login: &str) -> reqwest::Response {
+    client
+        .get(format!("{authorize_url}&login={login}"))
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn start(client: &Client, base: &str, provider: &str) -> String {
+    let body: Value = client
+        .get(format!("{base}/api/auth/{provider}"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    body["data"]["url"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn oauth_login_link_and_unlink_against_mock_idp() -> anyhow::Result<()> {
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    std::env::set_var("SKIP_INITIAL_PRICE_SYNC", "1");
+    let db = test_utils::make_isolated_db().await;
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let idp = spawn_idp().await;
+    std::env::set_var(
+        "OIDC_DISCOVERY_URL",
+        format!("{idp}/.well-known/openid-configuration"),
+    );
+    std::env::set_var("OIDC_CLIENT_ID", "burncloud-test");
+    std::env::set_var("OIDC_CLIENT_SECRET", "shh");
+    std::env::set_var(
+        "OIDC_REDIRECT_URI",
+        format!("{base}/console/api/auth/oidc/callback"),
+    );
+    std::env::set_var("GITHUB_CLIENT_ID", "burncloud-gh");
+    std::env::set_var("GITHUB_URL", &idp);
+    std::env::set_var("GITHUB_API_URL", format!("{idp}/api"));
+    std::env::set_var(
+        "GITHUB_REDIRECT_URI",
+        format!("{base}/console/api/auth/github/callback"),
+    );
+    // A browser: keeps the state cookie from starting a flow to its callback
+    let client = Client::builder().cookie_store(true).build()?;
+    let no_redirect = Client::builder()
+        .cookie_store(true)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?;
+
+    // First sign-in creates an OAuth-only account
+    let url = start(&client, &base, "oidc").await;
+    let created = sign_in(&client, &url, "alice").await;
+    assert_eq!(created.status(), StatusCode::OK);
+    let created: Value = created.json().await?;
+    let alice_id = created["data"]["id"].as_str().unwrap().to_string();
+    assert_eq!(created["data"]["username"], "alice");
+    let alice_jwt = created["data"]["token"].as_str().unwrap().to_string();
+
+    // Second sign-in resolves the same account
+    let url = start(&client, &base, "oidc").await;
+    let again: Value = sign_in(&client, &url, "alice").await.json().await?;
+    assert_eq!(again["data"]["id"], alice_id.as_str());
+
+    // A state is single-use: replaying the callback is rejected
+    let url = start(&no_redirect, &base, "oidc").await;
+    let callback = no_redirect
+        .get(format!("{url}&login=alice"))
+        .send()
+        .await?
+        .headers()["location"]
+        .to_str()?
+        .to_string();
+    let completed = no_redirect.get(&callback).send().await?;
+    assert_eq!(completed.status(), StatusCode::OK);
+    let cleared = completed.headers()["set-cookie"].to_str()?;
+    assert!(cleared.starts_with("burncloud_oauth_state=;") && cleared.contains("Max-Age=0"));
+    assert_eq!(
+        no_redirect.get(&callback).send().await?.status(),
+        StatusCode::BAD_REQUEST
+    );
+
+    // The state cookie is HttpOnly, SameSite=Lax and scoped to the callback
+    let started = client.get(format!("{base}/api/auth/oidc")).send().await?;
+    let cookie = started.headers()["set-cookie"].to_str()?;
+    assert!(cookie.contains("HttpOnly"), "{cookie}");
+    assert!(cookie.contains("SameSite=Lax"), "{cookie}");
+    assert!(cookie.contains("Path=/console/api/auth"), "{cookie}");
+
+    // A callback from a browser that did not start the flow is rejected
+    let url = start(&client, &base, "oidc").await;
+    let stranger = Client::new();
+    let refused = sign_in(&stranger, &url, "alice").await;
+    assert_eq!(refused.status(), StatusCode::BAD_REQUEST);
+
+    // The only sign-in method of an OAuth-only account cannot be removed
+    let refused = client
+        .delete(format!("{base}/console/api/auth/oidc/link"))
+        .bearer_auth(&alice_jwt)
+        .send()
+        .await?;
+    assert_eq!(refused.status(), StatusCode::BAD_REQUEST);
+
+    // A verified email from the generic OIDC provider does not sign in to
+    // the existing account with that email ...
+    let service = UserService::new();
+    let carol_id = service
+        .register_user(
+            &db,
+            "carol",
+            "test-password",
+            Some("carol@example.com".into()),
+        )
+        .await?;
+    let carol_jwt = service.generate_token(&carol_id, "carol")?.token;
+    let url = start(&client, &base, "oidc").await;
+    let refused = sign_in(&client, &url, "carol").await;
+    assert_eq!(refused.status(), StatusCode::FORBIDDEN);
+    let refused: Value = refused.json().await?;
+    assert!(refused.get("data").is_none());
+
+    // ... the account links it from a signed-in session instead
+    let link: Value = client
+        .post(format!("{base}/console/api/auth/oidc/link"))
+        .bearer_auth(&carol_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    let linked: Value = sign_in(&client, link["data"]["url"].as_str().unwrap(), "carol")
+        .await
+        .json()
+        .await?;
+    assert_eq!(linked["data"]["id"], carol_id.as_str());
+    let url = start(&client, &base, "oidc").await;
+    let carol: Value = sign_in(&client, &url, "carol").await.json().await?;
+    assert_eq!(carol["data"]["id"], carol_id.as_str());
+
+    // GitHub vouches for its verified emails, so it links by email
+    let dave_id = service
+        .register_user(
+            &db,
+            "dave",
+            "test-password",
+            Some("octo@example.com".into()),
+        )
+        .await?;
+    let url = start(&client, &base, "github").await;
+    let dave: Value = sign_in(&client, &url, "777").await.json().await?;
+    assert_eq!(dave["data"]["id"], dave_id.as_str());
+
+    // Signed-in users link GitHub explicitly
+    let link: Value = client
+        .post(format!("{base}/console/api/auth/github/link"))
+        .bearer_auth(&carol_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    let url = link["data"]["url"].as_str().unwrap();
+    let linked: Value = sign_in(&client, url, "4242").await.json().await?;
+    assert_eq!(linked["data"]["id"], carol_id.as_str());
+
+    // A link URL sent to someone else cannot attach their identity to the
+    // account that started it, even when they hold a state cookie of their own
+    let link: Value = client
+        .post(format!("{base}/console/api/auth/oidc/link"))
+        .bearer_auth(&alice_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    let victim = Client::builder().cookie_store(true).build()?;
+    start(&victim, &base, "oidc").await;
+    let hijack = sign_in(&victim, link["data"]["url"].as_str().unwrap(), "mallory").await;
+    assert_eq!(hijack.status(), StatusCode::BAD_REQUEST);
+
+    // The same GitHub account cannot be linked to a second user
+    let link: Value = client
+        .post(format!("{base}/console/api/auth/github/link"))
+        .bearer_auth(&alice_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    let taken = sign_in(&client, link["data"]["url"].as_str().unwrap(), "4242").await;
+    assert_eq!(taken.status(), StatusCode::FORBIDDEN);
+
+    let identities: Value = client
+        .get(format!("{base}/console/api/auth/identities"))
+        .bearer_auth(&carol_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    let providers: Vec<&str> = identities["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|i| i["provider"].as_str().unwrap())
+        .collect();
+    assert_eq!(providers, ["github", "oidc"]);
+
+    // Carol still has a password, so both providers can be removed
+    for provider in ["github", "oidc"] {
+        let unlinked = client
+            .delete(format!("{base}/console/api/auth/{provider}/link"))
+            .bearer_auth(&carol_jwt)
+            .send()
+            .await?;
+        assert_eq!(unlinked.status(), StatusCode::OK, "{provider}");
+    }
+
+    // Unconfigured providers are reported, not panicked on
+    let unknown = client.get(format!("{base}/api/auth/okta")).send().await?;
+    assert_eq!(unknown.status(), StatusCode::SERVICE_UNAVAILABLE);
+    Ok(())
+}
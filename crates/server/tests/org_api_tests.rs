@@ -0,0 +1,270 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::disallowed_types)]
+
+mod test_utils;
+
+use burncloud_database::Database;
+use burncloud_database_router::RouterToken;
+use burncloud_service_token::TokenService;
+use burncloud_service_user::UserService;
+use reqwest::{Client, StatusCode};
+use serde_json::Value;
+
+const JWT_SECRET: &str = "burncloud-org-api-jwt-secret-2026";
+
+fn configure_env() {
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    std::env::set_var("SKIP_INITIAL_PRICE_SYNC", "1");
+}
+
+/// Register a user and return (user_id, jwt).
+async fn principal(db: &Database, username: &str) -> anyhow::Result<(String, String)> {
+    let service = UserService::new();
+    let user_id = service
+        .register_user(
+            db,
+            username,
+            "test-password",
+            Some(format!("{username}@example.com")),
+        )
+        .await?;
+    let jwt = service.generate_token(&user_id, username)?.token;
+    Ok((user_id, jwt))
+}
+
+fn org_token(token: &str, user_id: &str, org_id: &str) -> RouterToken {
+    RouterToken {
+        token: token.to_string(),
+        user_id: user_id.to_string(),
+        status: "active".to_string(),
+        quota_limit: -1,
+        used_quota: 0,
+        expired_time: -1,
+        accessed_time: 0,
+        key_version: 1,
+        old_key_hash: None,
+        old_key_expires_at: 0,
+        ip_whitelist: None,
+        key_prefix: "bc_live_".to_string(),
+        created_at: 0,
+        last_rotated_at: 0,
+        org_id: Some(org_id.to_string()),
+        model_policy: None,
+        rpm_limit: None,
+        tpm_limit: None,
+        max_concurrency: None,
+    }
+}
+
+#[tokio::test]
+async fn invitation_flow_enforces_member_roles() -> anyhow::Result<()> {
+    configure_env();
+    let db = test_utils::make_isolated_db().await;
+    let (_admin_id, _admin_jwt) = principal(&db, "org-admin").await?;
+    let (_owner_id, owner_jwt) = principal(&db, "org-owner").await?;
+    let (dev_id, dev_jwt) = principal(&db, "org-dev").await?;
+    let (_outsider_id, outsider_jwt) = principal(&db, "org-outsider").await?;
+    let base = test_utils::spawn_server(db).await?;
+    let client = Client::new();
+
+    let created: Value = client
+        .post(format!("{base}/console/api/orgs"))
+        .bearer_auth(&owner_jwt)
+        .json(&serde_json::json!({ "name": "Acme" }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(created["data"]["role"], "owner");
+    let org_id = created["data"]["id"].as_str().unwrap().to_string();
+
+    let invite: Value = client
+        .post(format!("{base}/console/api/orgs/{org_id}/invitations"))
+        .bearer_auth(&owner_jwt)
+        .json(&serde_json::json!({ "email": "org-dev@example.com", "role": "developer" }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let code = invite["data"]["code"].as_str().unwrap().to_string();
+
+    let listed = client
+        .get(format!("{base}/console/api/orgs/{org_id}/invitations"))
+        .bearer_auth(&owner_jwt)
+        .send()
+        .await?
+        .text()
+        .await?;
+    assert!(
+        !listed.contains(&code),
+        "invitation lists must not disclose accept codes"
+    );
+
+    let wrong_user = client
+        .post(format!("{base}/console/api/org-invitations/{code}/accept"))
+        .bearer_auth(&outsider_jwt)
+        .send()
+        .await?;
+    assert_eq!(
+        wrong_user.status(),
+        StatusCode::BAD_REQUEST,
+        "invitations are bound to the invited email"
+    );
+
+    let accepted = client
+        .post(format!("{base}/console/api/org-invitations/{code}/accept"))
+        .bearer_auth(&dev_jwt)
+        .send()
+        .await?;
+    assert_eq!(accepted.status(), StatusCode::OK);
+
+    let members: Value = client
+        .get(format!("{base}/console/api/orgs/{org_id}/members"))
+        .bearer_auth(&dev_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    let roles: Vec<&str> = members["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["role"].as_str().unwrap())
+        .collect();
+    assert_eq!(roles.len(), 2);
+    assert!(roles.contains(&"owner") && roles.contains(&"developer"));
+
+    let dev_invite = client
+        .post(format!("{base}/console/api/orgs/{org_id}/invitations"))
+        .bearer_auth(&dev_jwt)
+        .json(&serde_json::json!({ "email": "someone@example.com", "role": "developer" }))
+        .send()
+        .await?;
+    assert_eq!(dev_invite.status(), StatusCode::FORBIDDEN);
+
+    let dev_limit = client
+        .put(format!("{base}/console/api/orgs/{org_id}"))
+        .bearer_auth(&dev_jwt)
+        .json(&serde_json::json!({ "spend_limit": 0 }))
+        .send()
+        .await?;
+    assert_eq!(dev_limit.status(), StatusCode::FORBIDDEN);
+
+    let outsider_get = client
+        .get(format!("{base}/console/api/orgs/{org_id}"))
+        .bearer_auth(&outsider_jwt)
+        .send()
+        .await?;
+    assert_eq!(outsider_get.status(), StatusCode::FORBIDDEN);
+
+    let promote = client
+        .put(format!("{base}/console/api/orgs/{org_id}/members/{dev_id}"))
+        .bearer_auth(&owner_jwt)
+        .json(&serde_json::json!({ "role": "billing" }))
+        .send()
+        .await?;
+    assert_eq!(promote.status(), StatusCode::OK);
+
+    let billing_limit = client
+        .put(format!("{base}/console/api/orgs/{org_id}"))
+        .bearer_auth(&dev_jwt)
+        .json(&serde_json::json!({ "spend_limit": 5_000_000_000_i64 }))
+        .send()
+        .await?;
+    assert_eq!(billing_limit.status(), StatusCode::OK);
+    let billing_body: Value = billing_limit.json().await?;
+    assert_eq!(billing_body["data"]["spend_limit"], 5_000_000_000_i64);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn org_tokens_are_admitted_against_the_org_wallet() -> anyhow::Result<()> {
+    configure_env();
+    let db = test_utils::make_isolated_db().await;
+    let (_admin_id, admin_jwt) = principal(&db, "wallet-admin").await?;
+    let (owner_id, owner_jwt) = principal(&db, "wallet-owner").await?;
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let client = Client::new();
+
+    let created: Value = client
+        .post(format!("{base}/console/api/orgs"))
+        .bearer_auth(&owner_jwt)
+        .json(&serde_json::json!({ "name": "Wallet" }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let org_id = created["data"]["id"].as_str().unwrap().to_string();
+
+    let api_key = "bc_live_org_wallet_key";
+    TokenService::create(&db, &org_token(api_key, &owner_id, &org_id)).await?;
+    let body = serde_json::json!({
+        "model": "org-wallet-model",
+        "messages": [{"role": "user", "content": "hello"}]
+    });
+
+    let unfunded = client
+        .post(format!("{base}/v1/chat/completions"))
+        .bearer_auth(api_key)
+        .json(&body)
+        .send()
+        .await?;
+    assert_eq!(unfunded.status(), StatusCode::PAYMENT_REQUIRED);
+    assert!(unfunded.text().await?.contains("insufficient_balance"));
+
+    let owner_topup = client
+        .post(format!("{base}/console/api/orgs/{org_id}/topup"))
+        .bearer_auth(&owner_jwt)
+        .json(&serde_json::json!({ "amount": 1_000_000_000_i64 }))
+        .send()
+        .await?;
+    assert_eq!(
+        owner_topup.status(),
+        StatusCode::FORBIDDEN,
+        "members must not be able to mint organization balance"
+    );
+
+    let admin_topup = client
+        .post(format!("{base}/console/api/orgs/{org_id}/topup"))
+        .bearer_auth(&admin_jwt)
+        .json(&serde_json::json!({ "amount": 1_000_000_000_i64, "currency": "USD" }))
+        .send()
+        .await?;
+    assert_eq!(admin_topup.status(), StatusCode::OK);
+
+    let funded = client
+        .post(format!("{base}/v1/chat/completions"))
+        .bearer_auth(api_key)
+        .json(&body)
+        .send()
+        .await?;
+    assert_ne!(funded.status(), StatusCode::PAYMENT_REQUIRED);
+
+    let org_tokens = client
+        .get(format!("{base}/console/api/orgs/{org_id}/tokens"))
+        .bearer_auth(&owner_jwt)
+        .send()
+        .await?
+        .text()
+        .await?;
+    assert!(!org_tokens.contains(api_key));
+    assert!(org_tokens.contains(&org_id));
+
+    let disabled = client
+        .put(format!("{base}/console/api/orgs/{org_id}"))
+        .bearer_auth(&admin_jwt)
+        .json(&serde_json::json!({ "active": false }))
+        .send()
+        .await?;
+    assert_eq!(disabled.status(), StatusCode::OK);
+
+    let blocked = client
+        .post(format!("{base}/v1/chat/completions"))
+        .bearer_auth(api_key)
+        .json(&body)
+        .send()
+        .await?;
+    assert_eq!(blocked.status(), StatusCode::FORBIDDEN);
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::disallowed_types)]
+
+//! Forgot-password queues a reset email that the outbox worker delivers
+//! through the file-drop transport, and the emailed token resets the password.
+
+mod test_utils;
+
+use burncloud_service_user::UserService;
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const JWT_SECRET: &str = "burncloud-password-reset-mail-jwt-secret-2026";
+
+/// Undo quoted-printable soft line breaks and `=3D` escapes
+fn decode_qp(eml: &str) -> String {
+    eml.replace("=\r\n", "").replace("=3D", "=")
+}
+
+#[tokio::test]
+async fn forgot_password_emails_a_working_reset_link() -> anyhow::Result<()> {
+    let mail_dir = tempfile::tempdir()?;
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    std::env::set_var("SKIP_INITIAL_PRICE_SYNC", "1");
+    std::env::set_var("MAIL_TRANSPORT", "file");
+    std::env::set_var("MAIL_FILE_DIR", mail_dir.path());
+    std::env::set_var("MAIL_POLL_INTERVAL_SECS", "1");
+    std::env::set_var(
+        "PASSWORD_RESET_URL",
+        "https://console.example/reset?token={token}",
+    );
+
+    let db = test_utils::make_isolated_db().await;
+    UserService::new()
+        .register_user(&db, "dana", "old-password", Some("dana@example.com".into()))
+        .await?;
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let client = Client::new();
+
+    for email in ["dana@example.com", "nobody@example.com"] {
+        let response = client
+            .post(format!("{base}/api/auth/forgot-password"))
+            .json(&json!({ "email": email }))
+            .send()
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    // Only the existing account gets mail
+    let mut files = Vec::new();
+    for _ in 0..50 {
+        files = std::fs::read_dir(mail_dir.path())?
+            .map(|e| e.map(|e| e.path()))
+            .collect::<Result<_, _>>()?;
+        if !files.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(files.len(), 1);
+    let eml = decode_qp(&std::fs::read_to_string(&files[0])?);
+    assert!(eml.contains("To: dana@example.com"));
+    let token: String = eml
+        .split("https://console.example/reset?token=")
+        .nth(1)
+        .unwrap()
+        .chars()
+        .take_while(|c| c.is_ascii_hexdigit() || *c == '-')
+        .collect();
+    assert_eq!(token.len(), 36);
+
+    let reset: Value = client
+        .post(format!("{base}/api/auth/reset-password"))
+        .json(&json!({ "token": token, "new_password": "new-password" }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(reset["success"], true);
+    let login: Value = client
+        .post(format!("{base}/api/auth/login"))
+        .json(&json!({ "username": "dana", "password": "new-password" }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(login["success"], true);
+    Ok(())
+}
@@ -0,0 +1,623 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::disallowed_types)]
+
+//! Online top-up against a local mock of the Stripe API and signed webhook
+//! fixtures: checkouts, idempotent crediting, signature checks, refunds,
+//! cards saved for auto top-up and the Alipay notification flow.
+
+mod test_utils;
+
+use axum::extract::{Form, Path, Query, State};
+use axum::http::HeaderMap;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use burncloud_database::Database;
+use burncloud_database_user::{UserDatabase, UserPayment, UserPaymentModel, PAYMENT_PENDING};
+use burncloud_service_notification::{AutoTopup, NotificationService, SettingsUpdate};
+use burncloud_service_payment::{
+    alipay, signing, stripe, PaymentConfig, PaymentError, PaymentService, StripeConfig,
+    StripeProvider,
+};
+use burncloud_service_user::UserService;
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+const JWT_SECRET: &str = "burncloud-payment-test-jwt-secret-2026";
+const STRIPE_WEBHOOK_SECRET: &str = "whsec_burncloud_test";
+const ALIPAY_APP_ID: &str = "2021000000000001";
+const ALIPAY_APP_PRIVATE: &str = include_str!("fixtures/payment/alipay_app_private.pem");
+const ALIPAY_APP_PUBLIC: &str = include_str!("fixtures/payment/alipay_app_public.pem");
+const ALIPAY_PLATFORM_PRIVATE: &str = include_str!("fixtures/payment/alipay_platform_private.pem");
+const ALIPAY_PLATFORM_PUBLIC: &str = include_str!("fixtures/payment/alipay_platform_public.pem");
+
+/// Nanodollars per cent
+const NANO_PER_CENT: i64 = 10_000_000;
+
+#[derive(Clone, Default)]
+struct MockStripe {
+    /// Form of every checkout session request
+    sessions: Arc<Mutex<Vec<HashMap<String, String>>>>,
+    /// User id of every customer created
+    customers: Arc<Mutex<Vec<String>>>,
+    /// Form of every off-session payment intent
+    intents: Arc<Mutex<Vec<HashMap<String, String>>>>,
+    /// Form of every refund request
+    refunds: Arc<Mutex<Vec<HashMap<String, String>>>>,
+}
+
+async fn create_session(
+    State(stripe): State<MockStripe>,
+    headers: HeaderMap,
+    Form(form): Form<HashMap<String, String>>,
+) -> Json<Value> {
+    assert_eq!(headers["authorization"], "Bearer sk_test_burncloud");
+    assert_eq!(form["mode"], "payment");
+    let id = format!("cs_test_{}", form["client_reference_id"]);
+    stripe.sessions.lock().unwrap().push(form);
+    Json(json!({ "id": id, "url": format!("https://checkout.stripe.test/{id}") }))
+}
+
+async fn create_customer(
+    State(stripe): State<MockStripe>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Json<Value> {
+    let user_id = form["metadata[user_id]"].clone();
+    stripe.customers.lock().unwrap().push(user_id.clone());
+    Json(json!({ "id": format!("cus_{user_id}") }))
+}
+
+/// Every customer has one card, `pm_card_{customer}`
+async fn list_payment_methods(
+    Path(customer): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Json<Value> {
+    assert_eq!(query["type"], "card");
+    Json(json!({
+        "object": "list",
+        "data": [{
+            "id": format!("pm_card_{customer}"),
+            "type": "card",
+            "card": { "brand": "visa", "last4": "4242", "exp_month": 12, "exp_year": 2030 },
+        }],
+    }))
+}
+
+async fn create_intent(
+    State(stripe): State<MockStripe>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Json<Value> {
+    stripe.intents.lock().unwrap().push(form);
+    Json(json!({ "id": "pi_test_saved", "status": "processing" }))
+}
+
+async fn create_refund(
+    State(stripe): State<MockStripe>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Json<Value> {
+    stripe.refunds.lock().unwrap().push(form);
+    Json(json!({ "id": "re_test", "status": "succeeded" }))
+}
+
+async fn balances(db: &Database, user_id: &str) -> (i64, i64) {
+    let user = UserDatabase::get_user_by_id(db, user_id)
+        .await
+        .unwrap()
+        .unwrap();
+    (user.balance_usd, user.balance_cny)
+}
+
+fn stripe_event(kind: &str, object: Value) -> String {
+    json!({ "id": "evt_test", "type": kind, "data": { "object": object } }).to_string()
+}
+
+/// Signed Alipay notification, as form fields
+fn alipay_notification(fields: &[(&str, &str)]) -> BTreeMap<String, String> {
+    let key = signing::parse_private_key(ALIPAY_PLATFORM_PRIVATE).unwrap();
+    let mut params: BTreeMap<String, String> = fields
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .chain([("sign_type".to_string(), "RSA2".to_string())])
+        .collect();
+    let signature = signing::sign(&key, alipay::canonical_string(&params).as_bytes());
+    params.insert("sign".to_string(), signature);
+    params
+}
+
+#[tokio::test]
+async fn online_top_up_is_credited_once_and_refunded() -> anyhow::Result<()> {
+    let mock = MockStripe::default();
+    let stripe_base = test_utils::spawn_app(
+        Router::new()
+            .route("/v1/checkout/sessions", post(create_session))
+            .route("/v1/refunds", post(create_refund))
+            .with_state(mock.clone()),
+    )
+    .await?;
+
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    std::env::set_var("SKIP_INITIAL_PRICE_SYNC", "1");
+    std::env::set_var("STRIPE_SECRET_KEY", "sk_test_burncloud");
+    std::env::set_var("STRIPE_WEBHOOK_SECRET", STRIPE_WEBHOOK_SECRET);
+    std::env::set_var("STRIPE_API_BASE", &stripe_base);
+    std::env::set_var("ALIPAY_APP_ID", ALIPAY_APP_ID);
+    std::env::set_var("ALIPAY_PRIVATE_KEY", ALIPAY_APP_PRIVATE);
+    std::env::set_var("ALIPAY_PUBLIC_KEY", ALIPAY_PLATFORM_PUBLIC);
+    std::env::set_var("ALIPAY_GATEWAY", "https://openapi.alipay.test/gateway.do");
+
+    let db = test_utils::make_isolated_db().await;
+    let service = UserService::new();
+    let admin_id = service
+        .register_user(&db, "pay-admin", "test-password", None)
+        .await?;
+    let customer_id = service
+        .register_user(&db, "pay-customer", "test-password", None)
+        .await?;
+    let admin_jwt = service.generate_token(&admin_id, "pay-admin")?.token;
+    let customer_jwt = service.generate_token(&customer_id, "pay-customer")?.token;
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let client = Client::new();
+
+    let checkout = |body: Value| {
+        let request = client
+            .post(format!("{base}/console/api/payments/checkout"))
+            .bearer_auth(&customer_jwt)
+            .json(&body);
+        async move { request.send().await.unwrap() }
+    };
+    let stripe_webhook = |payload: String, timestamp: i64, secret: &str| {
+        let request = client
+            .post(format!("{base}/console/api/payments/webhook/stripe"))
+            .header(
+                stripe::SIGNATURE_HEADER,
+                stripe::signature_header(secret, timestamp, payload.as_bytes()),
+            )
+            .body(payload);
+        async move { request.send().await.unwrap().status() }
+    };
+    let refund = |jwt: &str, id: &str, body: Value| {
+        let request = client
+            .post(format!("{base}/console/api/payments/{id}/refund"))
+            .bearer_auth(jwt)
+            .json(&body);
+        async move { request.send().await.unwrap() }
+    };
+
+    let options: Value = client
+        .get(format!("{base}/console/api/payments/options"))
+        .bearer_auth(&customer_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    let providers: Vec<&str> = options["data"]["providers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(providers, ["stripe", "alipay"]);
+
+    // Checkouts are validated before anything reaches the provider
+    for (body, status) in [
+        (
+            json!({ "provider": "stripe", "amount": 50 }),
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            json!({ "provider": "alipay", "amount": 1000, "currency": "USD" }),
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            json!({ "provider": "paypal", "amount": 1000 }),
+            StatusCode::NOT_FOUND,
+        ),
+    ] {
+        assert_eq!(checkout(body).await.status(), status);
+    }
+
+    let created: Value = checkout(json!({ "provider": "stripe", "amount": 2000 }))
+        .await
+        .json()
+        .await?;
+    let payment_id = created["data"]["payment"]["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(created["data"]["payment"]["status"], "pending");
+    assert_eq!(created["data"]["payment"]["currency"], "USD");
+    assert_eq!(created["data"]["kind"], "redirect");
+    assert_eq!(
+        created["data"]["url"],
+        format!("https://checkout.stripe.test/cs_test_{payment_id}")
+    );
+
+    // The signed completion credits the wallet exactly once
+    let (usd_before, cny_before) = balances(&db, &customer_id).await;
+    let completed = stripe_event(
+        "checkout.session.completed",
+        json!({
+            "id": format!("cs_test_{payment_id}"),
+            "client_reference_id": payment_id,
+            "payment_intent": "pi_test_1",
+            "payment_status": "paid",
+            "amount_total": 2000,
+            "currency": "usd",
+        }),
+    );
+    let now = chrono::Utc::now().timestamp();
+    assert_eq!(
+        stripe_webhook(completed.clone(), now, STRIPE_WEBHOOK_SECRET).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        stripe_webhook(completed.clone(), now, STRIPE_WEBHOOK_SECRET).await,
+        StatusCode::OK
+    );
+    // Forged, stale and tampered deliveries are refused
+    assert_eq!(
+        stripe_webhook(completed.clone(), now, "whsec_forged").await,
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        stripe_webhook(completed.clone(), now - 3600, STRIPE_WEBHOOK_SECRET).await,
+        StatusCode::BAD_REQUEST
+    );
+    let response = client
+        .post(format!("{base}/console/api/payments/webhook/stripe"))
+        .header(
+            stripe::SIGNATURE_HEADER,
+            stripe::signature_header(STRIPE_WEBHOOK_SECRET, now, completed.as_bytes()),
+        )
+        .body(completed.replace("2000", "200000"))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let (usd, _) = balances(&db, &customer_id).await;
+    assert_eq!(usd, usd_before + 2000 * NANO_PER_CENT);
+
+    let history: Value = client
+        .get(format!("{base}/console/api/payments"))
+        .bearer_auth(&customer_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(history["data"][0]["status"], "paid");
+    assert_eq!(history["data"][0]["provider_payment_id"], "pi_test_1");
+    assert_eq!(history["data"][0]["credited"], 2000 * NANO_PER_CENT);
+
+    // Refunds need billing:topup, go to the provider and debit the wallet
+    let response = refund(&customer_jwt, &payment_id, json!({ "amount": 500 })).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = refund(
+        &admin_jwt,
+        &payment_id,
+        json!({ "amount": 500, "reason": "duplicate" }),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let refunded: Value = response.json().await?;
+    assert_eq!(refunded["data"]["refunded_amount"], 500);
+    assert_eq!(refunded["data"]["status"], "paid");
+    {
+        let refunds = mock.refunds.lock().unwrap();
+        assert_eq!(refunds.len(), 1);
+        assert_eq!(refunds[0]["payment_intent"], "pi_test_1");
+        assert_eq!(refunds[0]["amount"], "500");
+    }
+    let (usd, _) = balances(&db, &customer_id).await;
+    assert_eq!(usd, usd_before + 1500 * NANO_PER_CENT);
+
+    // Stripe reports refunded totals: the one already applied changes
+    // nothing, a refund made in the Stripe dashboard is debited
+    for amount_refunded in [500, 2000, 2000] {
+        let event = stripe_event(
+            "charge.refunded",
+            json!({ "id": "ch_test_1", "payment_intent": "pi_test_1", "amount_refunded": amount_refunded }),
+        );
+        assert_eq!(
+            stripe_webhook(event, now, STRIPE_WEBHOOK_SECRET).await,
+            StatusCode::OK
+        );
+    }
+    let (usd, _) = balances(&db, &customer_id).await;
+    assert_eq!(usd, usd_before);
+    let payment: Value = client
+        .get(format!("{base}/console/api/payments"))
+        .bearer_auth(&customer_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(payment["data"][0]["status"], "refunded");
+    assert_eq!(
+        refund(&admin_jwt, &payment_id, json!({})).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+
+    // Alipay: a signed page-pay URL, then a signed asynchronous notification
+    let created: Value = checkout(json!({ "provider": "alipay", "amount": 8800 }))
+        .await
+        .json()
+        .await?;
+    let alipay_id = created["data"]["payment"]["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(created["data"]["payment"]["currency"], "CNY");
+    let url = reqwest::Url::parse(created["data"]["url"].as_str().unwrap())?;
+    assert_eq!(url.host_str(), Some("openapi.alipay.test"));
+    let mut query: BTreeMap<String, String> = url.query_pairs().into_owned().collect();
+    assert_eq!(query["method"], "alipay.trade.page.pay");
+    assert!(query["notify_url"].ends_with("/console/api/payments/webhook/alipay"));
+    let signature = query.remove("sign").unwrap();
+    let signed = query
+        .iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>()
+        .join("&");
+    assert!(signing::verify(
+        &signing::parse_public_key(ALIPAY_APP_PUBLIC)?,
+        signed.as_bytes(),
+        &signature
+    ));
+
+    let notification = alipay_notification(&[
+        ("app_id", ALIPAY_APP_ID),
+        ("out_trade_no", &alipay_id),
+        ("trade_no", "2026101922001400001"),
+        ("trade_status", "TRADE_SUCCESS"),
+        ("total_amount", "88.00"),
+    ]);
+    let notify = |params: BTreeMap<String, String>| {
+        let request = client
+            .post(format!("{base}/console/api/payments/webhook/alipay"))
+            .form(&params);
+        async move { request.send().await.unwrap() }
+    };
+    let response = notify(notification.clone()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text().await?, "success");
+    assert_eq!(notify(notification.clone()).await.status(), StatusCode::OK);
+    let mut forged = notification.clone();
+    forged.insert("total_amount".to_string(), "8800.00".to_string());
+    assert_eq!(notify(forged).await.status(), StatusCode::BAD_REQUEST);
+    let (_, cny) = balances(&db, &customer_id).await;
+    assert_eq!(cny, cny_before + 8800 * NANO_PER_CENT);
+
+    // Admins see everyone's payments, the history of recharges shows them
+    let all: Value = client
+        .get(format!("{base}/console/api/payments?all=true"))
+        .bearer_auth(&admin_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(all["data"].as_array().unwrap().len(), 2);
+    let response = client
+        .get(format!("{base}/console/api/payments?all=true"))
+        .bearer_auth(&customer_jwt)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let recharges: Value = client
+        .get(format!("{base}/console/api/user/recharges"))
+        .bearer_auth(&customer_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    let online = recharges["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|r| {
+            r["description"]
+                .as_str()
+                .unwrap_or_default()
+                .starts_with("在线充值")
+        })
+        .count();
+    assert_eq!(online, 4);
+
+    let audit: Value = client
+        .get(format!(
+            "{base}/console/api/audit?action=payment.refund&target_id={payment_id}"
+        ))
+        .bearer_auth(&admin_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(audit["data"]["total"], 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn payment_status_and_wallet_change_together() -> anyhow::Result<()> {
+    let db = test_utils::make_isolated_db().await;
+    let user_id = UserService::new()
+        .register_user(&db, "pay-atomic", "test-password", None)
+        .await?;
+    let (usd, cny) = balances(&db, &user_id).await;
+    let payment = |id: &str, currency: &str| UserPayment {
+        id: id.to_string(),
+        user_id: user_id.clone(),
+        provider: "stripe".to_string(),
+        amount: 500,
+        currency: currency.to_string(),
+        credited: 0,
+        refunded_amount: 0,
+        status: PAYMENT_PENDING.to_string(),
+        provider_session_id: None,
+        provider_payment_id: None,
+        created_at: 0,
+        paid_at: None,
+        updated_at: 0,
+    };
+
+    // A wallet change that fails leaves the payment pending, so a webhook
+    // retry can still apply it
+    let unsupported = payment("pay-eur", "EUR");
+    UserPaymentModel::create(&db, &unsupported).await?;
+    assert!(UserPaymentModel::mark_paid(
+        &db,
+        &unsupported,
+        "pi_eur",
+        500 * NANO_PER_CENT,
+        "top-up"
+    )
+    .await
+    .is_err());
+    let stored = UserPaymentModel::get(&db, "pay-eur").await?.unwrap();
+    assert_eq!(stored.status, PAYMENT_PENDING);
+    assert_eq!(balances(&db, &user_id).await, (usd, cny));
+
+    // Paid and credited once, refunded and debited once
+    let paid = payment("pay-usd", "USD");
+    UserPaymentModel::create(&db, &paid).await?;
+    for expected in [true, false] {
+        assert_eq!(
+            UserPaymentModel::mark_paid(&db, &paid, "pi_usd", 500 * NANO_PER_CENT, "top-up")
+                .await?,
+            expected
+        );
+    }
+    assert_eq!(
+        balances(&db, &user_id).await,
+        (usd + 500 * NANO_PER_CENT, cny)
+    );
+    let paid = UserPaymentModel::get(&db, "pay-usd").await?.unwrap();
+    for expected in [true, false] {
+        assert_eq!(
+            UserPaymentModel::record_refund(&db, &paid, 200, "paid", 200 * NANO_PER_CENT, "refund")
+                .await?,
+            expected
+        );
+    }
+    assert_eq!(
+        balances(&db, &user_id).await,
+        (usd + 300 * NANO_PER_CENT, cny)
+    );
+    assert_eq!(UserDatabase::list_recharges(&db, &user_id).await?.len(), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn saved_cards_are_charged_only_for_their_owner() -> anyhow::Result<()> {
+    let mock = MockStripe::default();
+    let stripe_base = test_utils::spawn_app(
+        Router::new()
+            .route("/v1/checkout/sessions", post(create_session))
+            .route("/v1/customers", post(create_customer))
+            .route(
+                "/v1/customers/{customer}/payment_methods",
+                get(list_payment_methods),
+            )
+            .route("/v1/payment_intents", post(create_intent))
+            .with_state(mock.clone()),
+    )
+    .await?;
+    let provider = StripeProvider::new(StripeConfig {
+        secret_key: "sk_test_burncloud".to_string(),
+        webhook_secret: STRIPE_WEBHOOK_SECRET.to_string(),
+        api_base: stripe_base,
+        tolerance_secs: 300,
+    });
+    let payments = Arc::new(PaymentService::new(
+        PaymentConfig::default(),
+        vec![Arc::new(provider)],
+    ));
+    let notifications = NotificationService::new(Some(payments.clone()), "http://localhost:8080");
+
+    let db = test_utils::make_isolated_db().await;
+    let service = UserService::new();
+    let owner = service
+        .register_user(&db, "card-owner", "test-password", None)
+        .await?;
+    let other = service
+        .register_user(&db, "card-other", "test-password", None)
+        .await?;
+    let customer = format!("cus_{owner}");
+    let card = format!("pm_card_{customer}");
+
+    // Saving a card creates the owner's customer once and checks out under it
+    for _ in 0..2 {
+        payments
+            .create_checkout(&db, &owner, "stripe", 2000, None, true)
+            .await?;
+    }
+    payments
+        .create_checkout(&db, &owner, "stripe", 2000, None, false)
+        .await?;
+    assert_eq!(mock.customers.lock().unwrap().as_slice(), [owner.as_str()]);
+    {
+        let sessions = mock.sessions.lock().unwrap();
+        for session in &sessions[..2] {
+            assert_eq!(session["customer"], customer);
+            assert_eq!(
+                session["payment_intent_data[setup_future_usage]"],
+                "off_session"
+            );
+        }
+        assert!(!sessions[2].contains_key("customer"));
+    }
+    let methods = payments.saved_methods(&db, &owner, "stripe").await?;
+    assert_eq!(
+        methods.iter().map(|m| m.id.as_str()).collect::<Vec<_>>(),
+        [card.as_str()]
+    );
+    assert!(payments
+        .saved_methods(&db, &other, "stripe")
+        .await?
+        .is_empty());
+
+    // Cards not attached to the user's own customer are refused before
+    // anything reaches Stripe
+    for (user_id, method) in [(&other, card.as_str()), (&owner, "pm_card_cus_someone")] {
+        assert!(matches!(
+            payments
+                .charge_saved(&db, user_id, "stripe", method, 2000, "USD")
+                .await,
+            Err(PaymentError::InvalidInput(_))
+        ));
+    }
+    let topup = |method: &str| SettingsUpdate {
+        auto_topup: Some(AutoTopup {
+            provider: "stripe".to_string(),
+            method: method.to_string(),
+            amount: 2000,
+            currency: "USD".to_string(),
+        }),
+        ..Default::default()
+    };
+    assert!(notifications
+        .save_settings(&db, &other, topup(&card))
+        .await
+        .is_err());
+    let saved = notifications
+        .save_settings(&db, &owner, topup(&card))
+        .await?;
+    assert_eq!(saved.auto_topup_method.as_deref(), Some(card.as_str()));
+    assert!(mock.intents.lock().unwrap().is_empty());
+
+    // The owner's card is charged under the owner's customer
+    let payment = payments
+        .charge_saved(&db, &owner, "stripe", &card, 2000, "USD")
+        .await?;
+    assert_eq!(payment.status, PAYMENT_PENDING);
+    assert_eq!(
+        payment.provider_session_id.as_deref(),
+        Some("pi_test_saved")
+    );
+    let intents = mock.intents.lock().unwrap();
+    assert_eq!(intents.len(), 1);
+    assert_eq!(intents[0]["customer"], customer);
+    assert_eq!(intents[0]["payment_method"], card);
+    assert_eq!(intents[0]["off_session"], "true");
+
+    Ok(())
+}
@@ -0,0 +1,321 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::disallowed_types)]
+
+mod test_utils;
+
+use axum::routing::post;
+use axum::Router;
+use burncloud_database::Database;
+use burncloud_service_user::UserService;
+use reqwest::{Client, StatusCode};
+use serde_json::Value;
+use std::time::Duration;
+
+const JWT_SECRET: &str = "burncloud-rate-limit-jwt-secret-2026";
+
+fn configure_env() {
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    std::env::set_var("SKIP_INITIAL_PRICE_SYNC", "1");
+}
+
+/// Register a user and return (user_id, jwt).
+async fn principal(db: &Database, username: &str) -> anyhow::Result<(String, String)> {
+    let service = UserService::new();
+    let user_id = service
+        .register_user(db, username, "test-password", None)
+        .await?;
+    let jwt = service.generate_token(&user_id, username)?.token;
+    Ok((user_id, jwt))
+}
+
+fn chat() -> Value {
+    serde_json::json!({
+        "model": "limited-model",
+        "messages": [{"role": "user", "content": "hello"}]
+    })
+}
+
+async fn create_token(
+    client: &Client,
+    base: &str,
+    jwt: &str,
+    body: Value,
+) -> anyhow::Result<reqwest::Response> {
+    Ok(client
+        .post(format!("{base}/console/api/tokens"))
+        .bearer_auth(jwt)
+        .json(&body)
+        .send()
+        .await?)
+}
+
+#[tokio::test]
+async fn token_rpm_limit_returns_429_with_headers() -> anyhow::Result<()> {
+    configure_env();
+    let db = test_utils::make_isolated_db().await;
+    let (_admin_id, _admin_jwt) = principal(&db, "limit-admin").await?;
+    let (user_id, user_jwt) = principal(&db, "limit-user").await?;
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let client = Client::new();
+
+    let invalid = create_token(
+        &client,
+        &base,
+        &user_jwt,
+        serde_json::json!({ "user_id": user_id, "rpm_limit": 0 }),
+    )
+    .await?;
+    assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+
+    let created: Value = create_token(
+        &client,
+        &base,
+        &user_jwt,
+        serde_json::json!({ "user_id": user_id, "rpm_limit": 1, "tpm_limit": 100_000 }),
+    )
+    .await?
+    .json()
+    .await?;
+    let api_key = created["data"]["token"].as_str().unwrap().to_string();
+
+    let first = client
+        .post(format!("{base}/v1/chat/completions"))
+        .bearer_auth(&api_key)
+        .json(&chat())
+        .send()
+        .await?;
+    assert_ne!(first.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(first.headers()["x-ratelimit-limit-requests"], "1");
+    assert_eq!(first.headers()["x-ratelimit-remaining-requests"], "0");
+    assert_eq!(first.headers()["x-ratelimit-limit-tokens"], "100000");
+    assert!(first.headers().contains_key("x-ratelimit-reset-requests"));
+
+    let second = client
+        .post(format!("{base}/v1/chat/completions"))
+        .bearer_auth(&api_key)
+        .json(&chat())
+        .send()
+        .await?;
+    assert_eq!(second.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = second.headers()["retry-after"].to_str()?.parse()?;
+    assert!((1..=60).contains(&retry_after));
+    assert_eq!(second.headers()["x-ratelimit-remaining-requests"], "0");
+    assert!(second.text().await?.contains("rate_limit_exceeded"));
+
+    let summary: Value = client
+        .get(format!("{base}/console/api/tokens"))
+        .bearer_auth(&user_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(summary["data"][0]["rpm_limit"], 1);
+    assert_eq!(summary["data"][0]["tpm_limit"], 100_000);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn user_limits_are_admin_only_and_shared_across_tokens() -> anyhow::Result<()> {
+    configure_env();
+    let db = test_utils::make_isolated_db().await;
+    let (_admin_id, admin_jwt) = principal(&db, "user-limit-admin").await?;
+    let (user_id, user_jwt) = principal(&db, "user-limit-user").await?;
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let client = Client::new();
+
+    let limits = serde_json::json!({ "user_id": user_id, "rpm_limit": 1 });
+    let forbidden = client
+        .post(format!("{base}/console/api/user/rate-limits"))
+        .bearer_auth(&user_jwt)
+        .json(&limits)
+        .send()
+        .await?;
+    assert_eq!(forbidden.status(), StatusCode::FORBIDDEN);
+
+    let unknown = client
+        .post(format!("{base}/console/api/user/rate-limits"))
+        .bearer_auth(&admin_jwt)
+        .json(&serde_json::json!({ "user_id": "no-such-user", "rpm_limit": 1 }))
+        .send()
+        .await?;
+    assert_eq!(unknown.status(), StatusCode::NOT_FOUND);
+
+    let applied = client
+        .post(format!("{base}/console/api/user/rate-limits"))
+        .bearer_auth(&admin_jwt)
+        .json(&limits)
+        .send()
+        .await?;
+    assert_eq!(applied.status(), StatusCode::OK);
+
+    let mut keys = Vec::new();
+    for _ in 0..2 {
+        let created: Value = create_token(
+            &client,
+            &base,
+            &user_jwt,
+            serde_json::json!({ "user_id": user_id }),
+        )
+        .await?
+        .json()
+        .await?;
+        keys.push(created["data"]["token"].as_str().unwrap().to_string());
+    }
+
+    let first = client
+        .post(format!("{base}/v1/chat/completions"))
+        .bearer_auth(&keys[0])
+        .json(&chat())
+        .send()
+        .await?;
+    assert_ne!(first.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // The second key draws from the same per-user budget
+    let second = client
+        .post(format!("{base}/v1/chat/completions"))
+        .bearer_auth(&keys[1])
+        .json(&chat())
+        .send()
+        .await?;
+    assert_eq!(second.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(second.headers().contains_key("retry-after"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn configured_limit_replaces_the_default_bucket() -> anyhow::Result<()> {
+    configure_env();
+    let db = test_utils::make_isolated_db().await;
+    let (_admin_id, _admin_jwt) = principal(&db, "burst-admin").await?;
+    let (user_id, user_jwt) = principal(&db, "burst-user").await?;
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let client = Client::new();
+
+    let created: Value = create_token(
+        &client,
+        &base,
+        &user_jwt,
+        serde_json::json!({ "user_id": user_id, "rpm_limit": 1000 }),
+    )
+    .await?
+    .json()
+    .await?;
+    let api_key = created["data"]["token"].as_str().unwrap().to_string();
+    let send = || {
+        client
+            .post(format!("{base}/v1/chat/completions"))
+            .bearer_auth(&api_key)
+            .json(&chat())
+            .send()
+    };
+
+    // Twice as many requests at once as the default per-user bucket holds;
+    // each is admitted and reported against the configured limit only
+    let responses = futures::future::join_all((0..200).map(|_| send())).await;
+    for response in responses {
+        let response = response?;
+        assert_ne!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()["x-ratelimit-limit-requests"], "1000");
+    }
+
+    Ok(())
+}
+
+/// Upstream streaming one content chunk and a final usage chunk of 30 tokens
+async fn spawn_streaming_upstream() -> anyhow::Result<String> {
+    let app = Router::new().route(
+        "/chat/completions",
+        post(|| async {
+            let chunks = [
+                r#"{"id":"chatcmpl-s","object":"chat.completion.chunk","model":"stream-model","choices":[{"index":0,"delta":{"content":"hi"},"finish_reason":null}]}"#,
+                r#"{"id":"chatcmpl-s","object":"chat.completion.chunk","model":"stream-model","choices":[],"usage":{"prompt_tokens":10,"completion_tokens":20,"total_tokens":30}}"#,
+                "[DONE]",
+            ];
+            let body: String = chunks.iter().map(|c| format!("data: {c}\n\n")).collect();
+            ([("content-type", "text/event-stream")], body)
+        }),
+    );
+    test_utils::spawn_app(app).await
+}
+
+fn remaining_tokens(response: &reqwest::Response) -> i64 {
+    response.headers()["x-ratelimit-remaining-tokens"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn streaming_tpm_is_reconciled_with_actual_usage() -> anyhow::Result<()> {
+    configure_env();
+    let db = test_utils::make_isolated_db().await;
+    let (_admin_id, admin_jwt) = principal(&db, "stream-limit-admin").await?;
+    let (user_id, user_jwt) = principal(&db, "stream-limit-user").await?;
+    db.execute_query(
+        "INSERT INTO billing_prices (model, currency, input_price, output_price, region, created_at) \
+         VALUES ('stream-model', 'USD', 0, 0, '', 1700000000)",
+    )
+    .await?;
+    let upstream = spawn_streaming_upstream().await?;
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let client = Client::new();
+
+    let channel = client
+        .post(format!("{base}/console/api/channel"))
+        .bearer_auth(&admin_jwt)
+        .json(&serde_json::json!({
+            "type": 1,
+            "key": "sk-upstream",
+            "name": "stream-channel",
+            "base_url": upstream,
+            "models": "stream-model",
+            "group": "default",
+            "weight": 1,
+            "priority": 0
+        }))
+        .send()
+        .await?;
+    assert_eq!(channel.status(), StatusCode::OK);
+
+    let created: Value = create_token(
+        &client,
+        &base,
+        &user_jwt,
+        serde_json::json!({ "user_id": user_id, "tpm_limit": 10_000 }),
+    )
+    .await?
+    .json()
+    .await?;
+    let api_key = created["data"]["token"].as_str().unwrap().to_string();
+    let request = serde_json::json!({
+        "model": "stream-model",
+        "stream": true,
+        "max_tokens": 500,
+        "messages": [{"role": "user", "content": "hello"}]
+    });
+
+    let first = client
+        .post(format!("{base}/v1/chat/completions"))
+        .bearer_auth(&api_key)
+        .json(&request)
+        .send()
+        .await?;
+    assert_eq!(first.status(), StatusCode::OK);
+    let after_first = remaining_tokens(&first);
+    assert!(first.text().await?.contains("[DONE]"));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // The estimate is replaced by the 30 tokens the stream reported, not refunded
+    let second = client
+        .post(format!("{base}/v1/chat/completions"))
+        .bearer_auth(&api_key)
+        .json(&request)
+        .send()
+        .await?;
+    assert_eq!(remaining_tokens(&second), after_first - 30);
+    second.text().await?;
+
+    Ok(())
+}
@@ -0,0 +1,348 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::disallowed_types)]
+
+//! Console routes check the permissions granted by the caller's roles, so
+//! presets and custom roles open exactly the surfaces they list.
+
+mod test_utils;
+
+use burncloud_service_user::UserService;
+use reqwest::{Client, Method, StatusCode};
+use serde_json::{json, Value};
+
+const JWT_SECRET: &str = "burncloud-rbac-test-jwt-secret-2026";
+
+#[tokio::test]
+async fn roles_grant_only_the_permissions_they_list() -> anyhow::Result<()> {
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    std::env::set_var("SKIP_INITIAL_PRICE_SYNC", "1");
+
+    let db = test_utils::make_isolated_db().await;
+    let service = UserService::new();
+    let admin_id = service
+        .register_user(&db, "rbac-admin", "test-password", None)
+        .await?;
+    let staff_id = service
+        .register_user(&db, "rbac-staff", "test-password", None)
+        .await?;
+    let admin_jwt = service.generate_token(&admin_id, "rbac-admin")?.token;
+    let staff_jwt = service.generate_token(&staff_id, "rbac-staff")?.token;
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let client = Client::new();
+
+    let status = |method: Method, path: &str, jwt: &str, body: Value| {
+        let request = client
+            .request(method, format!("{base}{path}"))
+            .bearer_auth(jwt)
+            .json(&body);
+        async move { request.send().await.unwrap().status() }
+    };
+    let topup = json!({ "user_id": staff_id, "amount": 1_000_000_000 });
+
+    // Presets are listed with their permissions; admin holds everything
+    let roles: Value = client
+        .get(format!("{base}/console/api/roles"))
+        .bearer_auth(&admin_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    let roles = roles["data"].as_array().unwrap();
+    let preset = |name: &str| roles.iter().find(|r| r["name"] == name).unwrap().clone();
+    assert_eq!(preset("admin")["permissions"], json!(["*"]));
+    assert_eq!(preset("admin")["built_in"], true);
+    assert!(preset("support")["permissions"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("logs:read")));
+
+    // A plain user reaches no administrative surface
+    assert_eq!(
+        status(Method::GET, "/console/api/logs", &staff_jwt, json!({})).await,
+        StatusCode::FORBIDDEN
+    );
+
+    // Support reads logs and users but cannot top up or trip the breaker
+    let response = client
+        .post(format!("{base}/console/api/user/roles"))
+        .bearer_auth(&admin_jwt)
+        .json(&json!({ "user_id": staff_id, "roles": ["support"] }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        status(Method::GET, "/console/api/logs", &staff_jwt, json!({})).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        status(
+            Method::GET,
+            "/console/api/list_users",
+            &staff_jwt,
+            json!({})
+        )
+        .await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        status(
+            Method::POST,
+            "/console/api/user/topup",
+            &staff_jwt,
+            topup.clone()
+        )
+        .await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        status(
+            Method::POST,
+            "/console/api/monitor/security/emergency-circuit-break",
+            &staff_jwt,
+            json!({ "reason": "test" })
+        )
+        .await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        status(Method::GET, "/console/api/roles", &staff_jwt, json!({})).await,
+        StatusCode::FORBIDDEN
+    );
+
+    // Sign-in hands the client its permissions
+    let login: Value = client
+        .post(format!("{base}/api/auth/login"))
+        .json(&json!({ "username": "rbac-staff", "password": "test-password" }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(login["data"]["roles"], json!(["support"]));
+    assert!(login["data"]["permissions"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("logs:read")));
+
+    // A custom role adds top-ups on top of support
+    let created = client
+        .post(format!("{base}/console/api/roles"))
+        .bearer_auth(&admin_jwt)
+        .json(&json!({
+            "name": "billing-desk",
+            "description": "Top-ups only",
+            "permissions": ["billing:topup"]
+        }))
+        .send()
+        .await?;
+    assert_eq!(created.status(), StatusCode::OK);
+    client
+        .post(format!("{base}/console/api/user/roles"))
+        .bearer_auth(&admin_jwt)
+        .json(&json!({ "user_id": staff_id, "roles": ["support", "billing-desk"] }))
+        .send()
+        .await?;
+    assert_eq!(
+        status(
+            Method::POST,
+            "/console/api/user/topup",
+            &staff_jwt,
+            topup.clone()
+        )
+        .await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        status(
+            Method::POST,
+            "/console/api/channel",
+            &staff_jwt,
+            json!({ "type": 1, "key": "sk-x", "name": "x", "models": "m" })
+        )
+        .await,
+        StatusCode::FORBIDDEN
+    );
+
+    // Invalid definitions and presets are refused
+    for (method, path, body, expected) in [
+        (
+            Method::POST,
+            "/console/api/roles",
+            json!({ "name": "billing-desk", "permissions": [] }),
+            StatusCode::CONFLICT,
+        ),
+        (
+            Method::POST,
+            "/console/api/roles",
+            json!({ "name": "root-ish", "permissions": ["*"] }),
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            Method::POST,
+            "/console/api/roles",
+            json!({ "name": "typo", "permissions": ["logs:write"] }),
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            Method::PUT,
+            "/console/api/roles/admin",
+            json!({ "permissions": ["logs:read"] }),
+            StatusCode::FORBIDDEN,
+        ),
+        (
+            Method::POST,
+            "/console/api/user/roles",
+            json!({ "user_id": admin_id, "roles": ["user"] }),
+            StatusCode::FORBIDDEN,
+        ),
+    ] {
+        assert_eq!(status(method, path, &admin_jwt, body).await, expected);
+    }
+
+    // Deleting the custom role takes its permissions away
+    let response = client
+        .delete(format!("{base}/console/api/roles/billing-desk"))
+        .bearer_auth(&admin_jwt)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        status(Method::POST, "/console/api/user/topup", &staff_jwt, topup).await,
+        StatusCode::FORBIDDEN
+    );
+
+    let audit: Value = client
+        .get(format!("{base}/console/api/audit?action=role."))
+        .bearer_auth(&admin_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(audit["data"]["total"], 2);
+    let audit: Value = client
+        .get(format!("{base}/console/api/audit?action=user.roles"))
+        .bearer_auth(&admin_jwt)
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(audit["data"]["total"], 2);
+    assert_eq!(
+        audit["data"]["entries"][0]["changes"]["roles"]["after"],
+        json!(["billing-desk", "support"])
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn role_managers_cannot_grant_more_than_they_hold() -> anyhow::Result<()> {
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    std::env::set_var("SKIP_INITIAL_PRICE_SYNC", "1");
+
+    let db = test_utils::make_isolated_db().await;
+    let service = UserService::new();
+    let admin_id = service
+        .register_user(&db, "esc-admin", "test-password", None)
+        .await?;
+    let manager_id = service
+        .register_user(&db, "esc-manager", "test-password", None)
+        .await?;
+    let admin_jwt = service.generate_token(&admin_id, "esc-admin")?.token;
+    let manager_jwt = service.generate_token(&manager_id, "esc-manager")?.token;
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let client = Client::new();
+
+    let status = |method: Method, path: &str, jwt: &str, body: Value| {
+        let request = client
+            .request(method, format!("{base}{path}"))
+            .bearer_auth(jwt)
+            .json(&body);
+        async move { request.send().await.unwrap().status() }
+    };
+
+    // The manager may manage roles and read logs, nothing else
+    assert_eq!(
+        status(
+            Method::POST,
+            "/console/api/roles",
+            &admin_jwt,
+            json!({ "name": "role-desk", "permissions": ["roles:manage", "logs:read"] })
+        )
+        .await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        status(
+            Method::POST,
+            "/console/api/user/roles",
+            &admin_jwt,
+            json!({ "user_id": manager_id, "roles": ["role-desk"] })
+        )
+        .await,
+        StatusCode::OK
+    );
+
+    // Nothing outside that set can be granted, to anyone
+    for (method, path, body) in [
+        (
+            Method::POST,
+            "/console/api/roles",
+            json!({ "name": "all-in", "permissions": ["logs:read", "users:write"] }),
+        ),
+        (
+            Method::PUT,
+            "/console/api/roles/role-desk",
+            json!({ "permissions": ["roles:manage", "logs:read", "billing:topup"] }),
+        ),
+        (
+            Method::POST,
+            "/console/api/user/roles",
+            json!({ "user_id": manager_id, "roles": ["role-desk", "admin"] }),
+        ),
+        (
+            Method::POST,
+            "/console/api/user/roles",
+            json!({ "user_id": manager_id, "roles": ["role-desk", "finance"] }),
+        ),
+    ] {
+        assert_eq!(
+            status(method, path, &manager_jwt, body).await,
+            StatusCode::FORBIDDEN
+        );
+    }
+    let roles = service.get_user_roles(&db, &manager_id).await?;
+    assert_eq!(roles, vec!["role-desk".to_string()]);
+    assert_eq!(
+        status(
+            Method::POST,
+            "/console/api/user/topup",
+            &manager_jwt,
+            json!({ "user_id": manager_id, "amount": 1 })
+        )
+        .await,
+        StatusCode::FORBIDDEN
+    );
+
+    // Within the set it still works
+    assert_eq!(
+        status(
+            Method::POST,
+            "/console/api/roles",
+            &manager_jwt,
+            json!({ "name": "log-reader", "permissions": ["logs:read"] })
+        )
+        .await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        status(
+            Method::POST,
+            "/console/api/user/roles",
+            &manager_jwt,
+            json!({ "user_id": manager_id, "roles": ["role-desk", "log-reader"] })
+        )
+        .await,
+        StatusCode::OK
+    );
+
+    Ok(())
+}
@@ -12,7 +12,6 @@ use burncloud_service_token::TokenService;
 use burncloud_service_user::UserService;
 use reqwest::{Client, StatusCode};
 use serde_json::Value;
-use std::sync::Arc;
 
 const JWT_SECRET: &str = "burncloud-security-invariant-jwt-secret-2026";
 const INTERNAL_SECRET: &str = "burncloud-security-invariant-internal-secret";
@@ -23,18 +22,6 @@ fn configure_security_env() {
     std::env::set_var("SKIP_INITIAL_PRICE_SYNC", "1");
 }
 
-async fn spawn_server(db: Arc<Database>) -> anyhow::Result<String> {
-    let app = burncloud_server::create_app(db, false).await?;
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
-    let addr = listener.local_addr()?;
-    tokio::spawn(async move {
-        axum::serve(listener, app)
-            .await
-            .unwrap_or_else(|e| panic!("security invariant server failed: {e}"));
-    });
-    Ok(format!("http://{addr}"))
-}
-
 async fn create_principals(
     db: &Database,
 ) -> anyhow::Result<(String, String, String, String)> {
@@ -72,6 +59,11 @@ fn router_token(token: &str, user_id: &str) -> RouterToken {
         key_prefix: "bc_live_".to_string(),
         created_at: 0,
         last_rotated_at: 0,
+        org_id: None,
+        model_policy: None,
+        rpm_limit: None,
+        tpm_limit: None,
+        max_concurrency: None,
     }
 }
 
@@ -82,7 +74,7 @@ async fn console_jwt_cannot_authenticate_data_plane() -> anyhow::Result<()> {
     let (_admin_id, _admin_jwt, user_id, user_jwt) = create_principals(&db).await?;
     let api_key = "bc_live_security_data_plane_key";
     TokenService::create(&db, &router_token(api_key, &user_id)).await?;
-    let base = spawn_server(db).await?;
+    let base = test_utils::spawn_server(db).await?;
     let client = Client::new();
     let body = serde_json::json!({
         "model": "security-invariant-model",
@@ -121,7 +113,7 @@ async fn regular_users_cannot_execute_admin_management_actions() -> anyhow::Resu
     configure_security_env();
     let db = test_utils::make_isolated_db().await;
     let (_admin_id, admin_jwt, user_id, user_jwt) = create_principals(&db).await?;
-    let base = spawn_server(db).await?;
+    let base = test_utils::spawn_server(db).await?;
     let client = Client::new();
 
     let logs = client
@@ -170,7 +162,7 @@ async fn token_management_is_owner_scoped_and_redacted() -> anyhow::Result<()> {
     let user_key = "bc_live_user_secret_5678";
     TokenService::create(&db, &router_token(admin_key, &admin_id)).await?;
     TokenService::create(&db, &router_token(user_key, &user_id)).await?;
-    let base = spawn_server(db.clone()).await?;
+    let base = test_utils::spawn_server(db.clone()).await?;
     let client = Client::new();
 
     let user_list = client
@@ -223,7 +215,7 @@ async fn token_management_is_owner_scoped_and_redacted() -> anyhow::Result<()> {
 async fn sensitive_internal_mutations_require_internal_secret() -> anyhow::Result<()> {
     configure_security_env();
     let db = test_utils::make_isolated_db().await;
-    let base = spawn_server(db).await?;
+    let base = test_utils::spawn_server(db).await?;
     let client = Client::new();
     let url = format!("{base}/console/internal/circuit-breaker/trip-all");
 
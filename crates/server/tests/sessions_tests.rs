@@ -0,0 +1,301 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::disallowed_types)]
+
+//! Console sessions: refresh token rotation and reuse detection, logout,
+//! logout-all, revocation on password change and sessions opened by
+//! registration.
+
+mod test_utils;
+
+use burncloud_service_user::UserService;
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+
+const JWT_SECRET: &str = "burncloud-sessions-test-jwt-secret-2026-xyz";
+
+struct Pair {
+    token: String,
+    refresh_token: String,
+}
+
+fn pair(body: &Value) -> Pair {
+    let data = &body["data"];
+    assert!(data["expires_at"].as_i64().unwrap() > chrono::Utc::now().timestamp());
+    Pair {
+        token: data["token"].as_str().unwrap().to_string(),
+        refresh_token: data["refresh_token"].as_str().unwrap().to_string(),
+    }
+}
+
+async fn login(client: &Client, base: &str, password: &str) -> anyhow::Result<Pair> {
+    let body: Value = client
+        .post(format!("{base}/api/auth/login"))
+        .header("user-agent", "sessions-test")
+        .json(&json!({ "username": "erin", "password": password }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(body["success"], true, "{body}");
+    Ok(pair(&body))
+}
+
+async fn refresh(
+    client: &Client,
+    base: &str,
+    refresh_token: &str,
+) -> anyhow::Result<reqwest::Response> {
+    Ok(client
+        .post(format!("{base}/api/auth/refresh"))
+        .json(&json!({ "refresh_token": refresh_token }))
+        .send()
+        .await?)
+}
+
+async fn sessions_status(client: &Client, base: &str, token: &str) -> anyhow::Result<StatusCode> {
+    Ok(client
+        .get(format!("{base}/console/api/auth/sessions"))
+        .bearer_auth(token)
+        .send()
+        .await?
+        .status())
+}
+
+#[tokio::test]
+async fn refresh_tokens_rotate_and_sessions_can_be_revoked() -> anyhow::Result<()> {
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    std::env::set_var("SKIP_INITIAL_PRICE_SYNC", "1");
+
+    let db = test_utils::make_isolated_db().await;
+    let service = UserService::new();
+    let erin_id = service
+        .register_user(&db, "erin", "first-password", None)
+        .await?;
+    let frank_id = service
+        .register_user(&db, "frank", "frank-password", None)
+        .await?;
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let client = Client::new();
+
+    // Sign in and list the session
+    let first = login(&client, &base, "first-password").await?;
+    let body: Value = client
+        .get(format!("{base}/console/api/auth/sessions"))
+        .bearer_auth(&first.token)
+        .send()
+        .await?
+        .json()
+        .await?;
+    let sessions = body["data"].as_array().unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0]["current"], true);
+    assert_eq!(sessions[0]["user_agent"], "sessions-test");
+    assert!(sessions[0].get("refresh_hash").is_none());
+
+    // Rotation: the new refresh token works, replaying the old one revokes
+    // the whole session
+    let response = refresh(&client, &base, &first.refresh_token).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let second = pair(&response.json().await?);
+    assert_ne!(second.refresh_token, first.refresh_token);
+    assert_eq!(
+        sessions_status(&client, &base, &second.token).await?,
+        StatusCode::OK
+    );
+
+    let replay = refresh(&client, &base, &first.refresh_token).await?;
+    assert_eq!(replay.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        sessions_status(&client, &base, &second.token).await?,
+        StatusCode::UNAUTHORIZED
+    );
+    let response = refresh(&client, &base, &second.refresh_token).await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // Logout only ends the current session
+    let a = login(&client, &base, "first-password").await?;
+    let b = login(&client, &base, "first-password").await?;
+    let response = client
+        .post(format!("{base}/console/api/auth/logout"))
+        .bearer_auth(&a.token)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        sessions_status(&client, &base, &a.token).await?,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        sessions_status(&client, &base, &b.token).await?,
+        StatusCode::OK
+    );
+    assert_eq!(
+        refresh(&client, &base, &a.refresh_token).await?.status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    // Another user's session cannot be revoked
+    let body: Value = client
+        .get(format!("{base}/console/api/auth/sessions"))
+        .bearer_auth(&b.token)
+        .send()
+        .await?
+        .json()
+        .await?;
+    let b_id = body["data"][0]["id"].as_str().unwrap().to_string();
+    let frank_jwt = service.generate_token(&frank_id, "frank")?.token;
+    let response = client
+        .delete(format!("{base}/console/api/auth/sessions/{b_id}"))
+        .bearer_auth(&frank_jwt)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Logout-all also reaches sessionless tokens
+    let legacy = service.generate_token(&erin_id, "erin")?.token;
+    assert_eq!(
+        sessions_status(&client, &base, &legacy).await?,
+        StatusCode::OK
+    );
+    let body: Value = client
+        .post(format!("{base}/console/api/auth/logout-all"))
+        .bearer_auth(&b.token)
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(body["data"]["revoked"], 1);
+    assert_eq!(
+        sessions_status(&client, &base, &b.token).await?,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        sessions_status(&client, &base, &legacy).await?,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        sessions_status(&client, &base, &frank_jwt).await?,
+        StatusCode::OK
+    );
+
+    // Changing the password signs out every other session
+    let c = login(&client, &base, "first-password").await?;
+    let d = login(&client, &base, "first-password").await?;
+    let response = client
+        .post(format!("{base}/console/api/auth/change-password"))
+        .bearer_auth(&c.token)
+        .json(&json!({ "current_password": "wrong", "new_password": "second-password" }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = client
+        .post(format!("{base}/console/api/auth/change-password"))
+        .bearer_auth(&c.token)
+        .json(&json!({ "current_password": "first-password", "new_password": "second-password" }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let fresh = pair(&response.json().await?);
+    assert_eq!(
+        sessions_status(&client, &base, &c.token).await?,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        sessions_status(&client, &base, &d.token).await?,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        sessions_status(&client, &base, &fresh.token).await?,
+        StatusCode::OK
+    );
+    login(&client, &base, "second-password").await?;
+
+    // The legacy console login opens a session as well
+    let body: Value = client
+        .post(format!("{base}/console/api/user/login"))
+        .bearer_auth(&frank_jwt)
+        .json(&json!({ "username": "erin", "password": "second-password" }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let legacy_login = pair(&body);
+    let response = client
+        .post(format!("{base}/console/api/auth/logout"))
+        .bearer_auth(&legacy_login.token)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        sessions_status(&client, &base, &legacy_login.token).await?,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        refresh(&client, &base, &legacy_login.refresh_token)
+            .await?
+            .status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn registration_tokens_belong_to_a_revocable_session() -> anyhow::Result<()> {
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    std::env::set_var("SKIP_INITIAL_PRICE_SYNC", "1");
+
+    let db = test_utils::make_isolated_db().await;
+    let service = UserService::new();
+    let admin_id = service
+        .register_user(&db, "root", "root-password", None)
+        .await?;
+    let admin_jwt = service.generate_token(&admin_id, "root")?.token;
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let client = Client::new();
+
+    let body: Value = client
+        .post(format!("{base}/console/api/user/register"))
+        .bearer_auth(&admin_jwt)
+        .header("user-agent", "registration-test")
+        .json(&json!({ "username": "gina", "password": "gina-password" }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(body["success"], true, "{body}");
+    let registered = pair(&body);
+
+    // The registration token shows up in the sessions list ...
+    let body: Value = client
+        .get(format!("{base}/console/api/auth/sessions"))
+        .bearer_auth(&registered.token)
+        .send()
+        .await?
+        .json()
+        .await?;
+    let sessions = body["data"].as_array().unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0]["current"], true);
+    assert_eq!(sessions[0]["user_agent"], "registration-test");
+    let session_id = sessions[0]["id"].as_str().unwrap().to_string();
+
+    // ... and revoking that session ends it
+    let response = client
+        .delete(format!("{base}/console/api/auth/sessions/{session_id}"))
+        .bearer_auth(&registered.token)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        sessions_status(&client, &base, &registered.token).await?,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        refresh(&client, &base, &registered.refresh_token)
+            .await?
+            .status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    Ok(())
+}
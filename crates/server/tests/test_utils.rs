@@ -2,7 +2,8 @@
     clippy::unwrap_used,
     clippy::expect_used,
     clippy::let_and_return,
-    clippy::disallowed_types
+    clippy::disallowed_types,
+    dead_code
 )]
 
 use burncloud_database::{create_database_with_url, Database};
@@ -29,3 +30,23 @@ pub async fn make_isolated_db() -> Arc<Database> {
     UserDatabase::init(&db).await.expect("user db init");
     Arc::new(db)
 }
+
+/// Serve `app` on an ephemeral local port and return its base URL.
+pub async fn spawn_app(app: axum::Router) -> anyhow::Result<String> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+        )
+        .await
+        .unwrap_or_else(|e| panic!("test server failed: {e}"));
+    });
+    Ok(format!("http://{addr}"))
+}
+
+/// Serve the full BurnCloud app over `db` and return its base URL.
+pub async fn spawn_server(db: Arc<Database>) -> anyhow::Result<String> {
+    spawn_app(burncloud_server::create_app(db, false).await?).await
+}
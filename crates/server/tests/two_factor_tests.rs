@@ -0,0 +1,265 @@
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::disallowed_types)]
+
+//! TOTP two-factor authentication: enrollment, the second sign-in step,
+//! recovery codes, step-up on sensitive actions and admin enforcement.
+
+mod test_utils;
+
+use burncloud_service_user::{totp::totp_code, PermissionSet, RoleService, UserService};
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+
+const JWT_SECRET: &str = "burncloud-two-factor-test-jwt-secret-2026";
+
+/// Code for the time step `offset` steps from now. Every step is accepted
+/// once, so consecutive verifications in a test use increasing offsets.
+fn code(secret: &str, offset: i64) -> String {
+    totp_code(secret, chrono::Utc::now().timestamp() + offset * 30).unwrap()
+}
+
+async fn login(client: &Client, base: &str, username: &str) -> anyhow::Result<Value> {
+    let body: Value = client
+        .post(format!("{base}/api/auth/login"))
+        .json(&json!({ "username": username, "password": "test-password" }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(body["success"], true, "{body}");
+    Ok(body["data"].clone())
+}
+
+async fn login_two_factor(
+    client: &Client,
+    base: &str,
+    challenge: &str,
+    code: &str,
+) -> anyhow::Result<reqwest::Response> {
+    Ok(client
+        .post(format!("{base}/api/auth/login/2fa"))
+        .json(&json!({ "challenge": challenge, "code": code }))
+        .send()
+        .await?)
+}
+
+#[tokio::test]
+async fn two_factor_enrollment_login_and_step_up() -> anyhow::Result<()> {
+    std::env::set_var("JWT_SECRET", JWT_SECRET);
+    std::env::set_var("SKIP_INITIAL_PRICE_SYNC", "1");
+
+    let db = test_utils::make_isolated_db().await;
+    let service = UserService::new();
+    service
+        .register_user(&db, "tf-admin", "test-password", None)
+        .await?;
+    let bob_id = service
+        .register_user(&db, "tf-bob", "test-password", None)
+        .await?;
+    // Second administrator without two-factor authentication
+    let root_id = service
+        .register_user(&db, "tf-root", "test-password", None)
+        .await?;
+    let admin: PermissionSet = ["*"].into_iter().collect();
+    RoleService::set_user_roles(&db, &admin, &root_id, &["admin".to_string()]).await?;
+    let base = test_utils::spawn_server(db.clone()).await?;
+    let client = Client::new();
+
+    // Enroll: setup returns a provisioning URI and a QR code, the first code
+    // enables it and yields recovery codes
+    let token = login(&client, &base, "tf-admin").await?["token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let setup: Value = client
+        .post(format!("{base}/console/api/auth/2fa/setup"))
+        .bearer_auth(&token)
+        .send()
+        .await?
+        .json()
+        .await?;
+    let secret = setup["data"]["secret"].as_str().unwrap().to_string();
+    assert!(setup["data"]["otpauth_uri"]
+        .as_str()
+        .unwrap()
+        .starts_with("otpauth://totp/"));
+    assert!(setup["data"]["qr_svg"].as_str().unwrap().contains("<svg"));
+
+    let response = client
+        .post(format!("{base}/console/api/auth/2fa/enable"))
+        .bearer_auth(&token)
+        .json(&json!({ "code": "000000" }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let enabled: Value = client
+        .post(format!("{base}/console/api/auth/2fa/enable"))
+        .bearer_auth(&token)
+        .json(&json!({ "code": code(&secret, 0) }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let recovery: Vec<String> = enabled["data"]["recovery_codes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c.as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(recovery.len(), 10);
+    let status: Value = client
+        .get(format!("{base}/console/api/auth/2fa"))
+        .bearer_auth(&token)
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(status["data"]["enabled"], true);
+    assert_eq!(status["data"]["recovery_codes_remaining"], 10);
+
+    // Password alone now only yields a challenge
+    let pending = login(&client, &base, "tf-admin").await?;
+    assert_eq!(pending["two_factor_required"], true);
+    assert!(pending.get("token").is_none());
+    let challenge = pending["challenge"].as_str().unwrap().to_string();
+    let response = login_two_factor(&client, &base, &challenge, "123456").await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response =
+        login_two_factor(&client, &base, &challenge, &recovery[0].to_uppercase()).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = response.json().await?;
+    let token = body["data"]["token"].as_str().unwrap().to_string();
+
+    // Challenges and recovery codes work once
+    let response = login_two_factor(&client, &base, &challenge, &recovery[1]).await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let challenge = login(&client, &base, "tf-admin").await?["challenge"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let response = login_two_factor(&client, &base, &challenge, &recovery[0]).await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // The legacy login endpoint cannot bypass the second factor
+    let response = client
+        .post(format!("{base}/console/api/user/login"))
+        .bearer_auth(&token)
+        .json(&json!({ "username": "tf-admin", "password": "test-password" }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // Sensitive actions need a fresh code
+    let emergency = format!("{base}/console/api/monitor/security/emergency-circuit-break");
+    let response = client
+        .post(&emergency)
+        .bearer_auth(&token)
+        .json(&json!({ "reason": "drill" }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = client
+        .post(&emergency)
+        .bearer_auth(&token)
+        .header("x-totp-code", code(&secret, 1))
+        .json(&json!({ "reason": "drill" }))
+        .send()
+        .await?;
+    assert_ne!(response.status(), StatusCode::FORBIDDEN);
+
+    // So do changes to upstream keys and access control
+    let response = client
+        .post(format!("{base}/console/api/channel"))
+        .bearer_auth(&token)
+        .json(&json!({ "type": 1, "key": "sk-x", "name": "tf", "models": "m" }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = client
+        .post(format!("{base}/console/api/user/2fa-policy"))
+        .bearer_auth(&token)
+        .json(&json!({ "user_id": bob_id, "required": true }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let root = login(&client, &base, "tf-root").await?["token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // An administrator can require 2FA: until bob enrolls, only the auth
+    // endpoints are reachable, and the account cannot opt out afterwards
+    let response = client
+        .post(format!("{base}/console/api/user/2fa-policy"))
+        .bearer_auth(&root)
+        .json(&json!({ "user_id": bob_id, "required": true }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let bob = login(&client, &base, "tf-bob").await?["token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let response = client
+        .get(format!("{base}/console/api/user/recharges"))
+        .bearer_auth(&bob)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let status: Value = client
+        .get(format!("{base}/console/api/auth/2fa"))
+        .bearer_auth(&bob)
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(status["data"]["required"], true);
+    assert_eq!(status["data"]["enabled"], false);
+
+    let bob_secret = service
+        .begin_two_factor_setup(&db, &bob_id, "tf-bob")
+        .await?
+        .secret;
+    let bob_recovery = service
+        .enable_two_factor(&db, &bob_id, &code(&bob_secret, 0))
+        .await?;
+    let response = client
+        .get(format!("{base}/console/api/user/recharges"))
+        .bearer_auth(&bob)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = client
+        .post(format!("{base}/console/api/auth/2fa/disable"))
+        .bearer_auth(&bob)
+        .json(&json!({ "code": bob_recovery[0] }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // After an administrator reset the password is enough again
+    let response = client
+        .post(format!("{base}/console/api/user/2fa-reset"))
+        .bearer_auth(&bob)
+        .json(&json!({ "user_id": bob_id }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    client
+        .post(format!("{base}/console/api/user/2fa-policy"))
+        .bearer_auth(&root)
+        .json(&json!({ "user_id": bob_id, "required": false }))
+        .send()
+        .await?;
+    let body: Value = client
+        .post(format!("{base}/console/api/user/2fa-reset"))
+        .bearer_auth(&root)
+        .json(&json!({ "user_id": bob_id }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(body["data"]["reset"], true);
+    assert!(login(&client, &base, "tf-bob").await?["token"].is_string());
+
+    Ok(())
+}
@@ -1,23 +1,39 @@
-//! Email notification channel (stub implementation)
+//! Email notification channel
 
 use super::NotificationChannel;
 use crate::types::{Alert, AlertError};
 use async_trait::async_trait;
+use burncloud_service_mail::{MailService, MailTemplate};
 
-/// Email notification channel
+/// Email notification channel. Alerts are sent directly rather than through
+/// the outbox: a stale alert delivered after a restart is noise.
 pub struct EmailChannel {
-    smtp_config: Option<String>,
+    recipients: Vec<String>,
+    mailer: MailService,
 }
 
 impl EmailChannel {
-    /// Create a new email channel
-    pub fn new(smtp_config: Option<String>) -> Self {
-        Self { smtp_config }
+    /// Create a new email channel for comma-separated `recipients`, using the
+    /// mail transport configured in the environment
+    pub fn new(recipients: Option<String>) -> Self {
+        Self::with_mailer(recipients, MailService::from_env())
+    }
+
+    /// Create a channel with an explicit mail service
+    pub fn with_mailer(recipients: Option<String>, mailer: MailService) -> Self {
+        let recipients = recipients
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+            .map(str::to_string)
+            .collect();
+        Self { recipients, mailer }
     }
 
     /// Create from environment variable
     pub fn from_env() -> Self {
-        Self::new(std::env::var("ALERT_EMAIL_SMTP").ok())
+        Self::new(std::env::var("ALERT_EMAIL_TO").ok())
     }
 }
 
@@ -26,19 +42,37 @@ impl NotificationChannel for EmailChannel {
     async fn send(&self, alert: &Alert) -> Result<(), AlertError> {
         if !self.is_configured() {
             return Err(AlertError::ChannelUnavailable(
-                "Email SMTP not configured".to_string(),
+                "Email recipients or mail transport not configured".to_string(),
             ));
         }
 
-        // TODO: Implement actual email sending using lettre or similar
-        // For now, just log the alert
+        let mail = MailTemplate::Alert {
+            level: alert.level.to_string(),
+            title: alert.alert_type.to_string(),
+            message: format!(
+                "{}\n\nTriggered at {} ({} time(s)).",
+                alert.message,
+                alert.triggered_at.to_rfc3339(),
+                alert.trigger_count
+            ),
+        }
+        .render();
+
+        let mut failures = Vec::new();
+        for recipient in &self.recipients {
+            if let Err(e) = self.mailer.send_now(recipient, &mail).await {
+                failures.push(format!("{recipient}: {e}"));
+            }
+        }
+        if !failures.is_empty() {
+            return Err(AlertError::NotificationFailed(failures.join("; ")));
+        }
+
         log::info!(
-            "Email alert: [{}] {} - {}",
-            alert.level,
-            alert.alert_type,
-            alert.message
+            "Alert {} sent via email to {} recipient(s)",
+            alert.id,
+            self.recipients.len()
         );
-
         Ok(())
     }
 
@@ -47,6 +81,56 @@ impl NotificationChannel for EmailChannel {
     }
 
     fn is_configured(&self) -> bool {
-        self.smtp_config.is_some()
+        !self.recipients.is_empty() && self.mailer.is_enabled()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AlertLevel, AlertStatus, AlertType};
+    use burncloud_service_mail::{MailConfig, TransportConfig};
+
+    #[tokio::test]
+    async fn alert_is_written_for_every_recipient() {
+        let dir =
+            std::env::temp_dir().join(format!("burncloud-alert-mail-{}", uuid::Uuid::new_v4()));
+        let mailer = MailService::new(MailConfig {
+            transport: TransportConfig::File(dir.clone()),
+            ..MailConfig::default()
+        })
+        .unwrap_or_else(|e| panic!("mail service: {e}"));
+        let channel =
+            EmailChannel::with_mailer(Some("ops@example.com, oncall@example.com".into()), mailer);
+        assert!(channel.is_configured());
+
+        let alert = Alert {
+            id: "a1".to_string(),
+            alert_type: AlertType::MemoryHigh { usage_percent: 95 },
+            level: AlertLevel::Critical,
+            status: AlertStatus::Active,
+            message: "Memory usage at 95%".to_string(),
+            triggered_at: chrono::Utc::now(),
+            resolved_at: None,
+            trigger_count: 1,
+        };
+        channel
+            .send(&alert)
+            .await
+            .unwrap_or_else(|e| panic!("send: {e}"));
+        let written = std::fs::read_dir(&dir)
+            .unwrap_or_else(|e| panic!("read dir: {e}"))
+            .count();
+        assert_eq!(written, 2);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn unconfigured_without_recipients() {
+        assert!(!EmailChannel::with_mailer(
+            None,
+            MailService::new(MailConfig::default()).unwrap_or_else(|e| panic!("{e}"))
+        )
+        .is_configured());
     }
 }
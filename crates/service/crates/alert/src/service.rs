@@ -32,8 +32,8 @@ impl AlertService {
         if config.webhook_url.is_some() {
             channels.push(Box::new(WebhookChannel::new(config.webhook_url.clone())));
         }
-        if config.email_smtp.is_some() {
-            channels.push(Box::new(EmailChannel::new(config.email_smtp.clone())));
+        if config.email_to.is_some() {
+            channels.push(Box::new(EmailChannel::new(config.email_to.clone())));
         }
         if config.telegram_bot_token.is_some() && config.telegram_chat_id.is_some() {
             channels.push(Box::new(TelegramChannel::new(
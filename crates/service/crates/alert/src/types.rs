@@ -164,8 +164,8 @@ pub struct AlertRule {
 pub struct AlertConfig {
     /// Webhook URL for notifications
     pub webhook_url: Option<String>,
-    /// Email configuration
-    pub email_smtp: Option<String>,
+    /// Comma-separated email recipients; delivery uses the `MAIL_*`/`SMTP_*` transport
+    pub email_to: Option<String>,
     /// Telegram bot token
     pub telegram_bot_token: Option<String>,
     /// Telegram chat ID
@@ -183,7 +183,7 @@ impl Default for AlertConfig {
     fn default() -> Self {
         Self {
             webhook_url: std::env::var("ALERT_WEBHOOK_URL").ok(),
-            email_smtp: std::env::var("ALERT_EMAIL_SMTP").ok(),
+            email_to: std::env::var("ALERT_EMAIL_TO").ok(),
             telegram_bot_token: std::env::var("ALERT_TELEGRAM_BOT_TOKEN").ok(),
             telegram_chat_id: std::env::var("ALERT_TELEGRAM_CHAT_ID").ok(),
             slack_webhook: std::env::var("ALERT_SLACK_WEBHOOK").ok(),
@@ -0,0 +1,469 @@
+//! # BurnCloud Service Audit
+//!
+//! Append-only audit trail of administrative changes made through the
+//! console API, management API keys or the `burncloud` CLI. Every entry
+//! names the actor and where the change came from, the action and its
+//! target, and carries JSON snapshots of the target before and after
+//! together with the fields that changed. Secrets (API keys, passwords,
+//! token values) are redacted before anything is written, so the trail
+//! itself never holds credentials.
+
+// Snapshots and diffs cover arbitrary target types — Value required; no feasible typed alternative.
+#![allow(clippy::disallowed_types)]
+
+use burncloud_database::{Database, DatabaseError};
+use burncloud_database_sys::audit_log::{AuditLogModel, NewAuditLog, SysAuditLog};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+pub use burncloud_database_sys::audit_log::AuditLogFilter;
+
+type Result<T> = std::result::Result<T, DatabaseError>;
+
+pub const SOURCE_CONSOLE: &str = "console";
+pub const SOURCE_CLI: &str = "cli";
+pub const SOURCE_MANAGEMENT_KEY: &str = "management_key";
+
+/// Field names whose values never reach the audit trail
+const SECRET_FIELDS: &[&str] = &[
+    "key",
+    "api_key",
+    "token",
+    "password",
+    "password_hash",
+    "secret",
+    "client_secret",
+    "refresh_token",
+    "recovery_codes",
+];
+const REDACTED: &str = "[redacted]";
+
+/// Who made a change and from where
+#[derive(Debug, Clone, Default)]
+pub struct Actor {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub ip: Option<String>,
+    pub source: &'static str,
+}
+
+impl Actor {
+    /// A signed-in console user
+    pub fn console(id: &str, name: &str, ip: Option<String>) -> Self {
+        Self {
+            id: Some(id.to_string()),
+            name: Some(name.to_string()),
+            ip,
+            source: SOURCE_CONSOLE,
+        }
+    }
+
+    /// A user's management API key; `name` names the user and the key
+    pub fn management_key(user_id: &str, name: &str, ip: Option<String>) -> Self {
+        Self {
+            id: Some(user_id.to_string()),
+            name: Some(name.to_string()),
+            ip,
+            source: SOURCE_MANAGEMENT_KEY,
+        }
+    }
+
+    /// The operating-system user running the CLI
+    pub fn cli() -> Self {
+        let name = std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .ok()
+            .filter(|name| !name.is_empty());
+        Self {
+            id: None,
+            name,
+            ip: None,
+            source: SOURCE_CLI,
+        }
+    }
+}
+
+/// A change about to be recorded
+#[derive(Debug, Clone)]
+pub struct AuditEvent {
+    action: String,
+    target_type: String,
+    target_id: Option<String>,
+    before: Option<Value>,
+    after: Option<Value>,
+}
+
+impl AuditEvent {
+    /// `action` is a dotted verb such as `channel.update`; its first segment
+    /// is the target type.
+    pub fn new(action: &str) -> Self {
+        let target_type = action.split('.').next().unwrap_or(action);
+        Self {
+            action: action.to_string(),
+            target_type: target_type.to_string(),
+            target_id: None,
+            before: None,
+            after: None,
+        }
+    }
+
+    pub fn target(mut self, id: impl ToString) -> Self {
+        self.target_id = Some(id.to_string());
+        self
+    }
+
+    /// State of the target before the change. `None` (JSON null) records
+    /// no snapshot.
+    pub fn before(mut self, state: &impl Serialize) -> Self {
+        self.before = snapshot(state);
+        self
+    }
+
+    /// State of the target after the change, or the request that made it
+    pub fn after(mut self, state: &impl Serialize) -> Self {
+        self.after = snapshot(state);
+        self
+    }
+}
+
+fn snapshot(state: &impl Serialize) -> Option<Value> {
+    serde_json::to_value(state).ok().filter(|value| !value.is_null())
+}
+
+/// Per-field difference between two snapshots. Objects are compared field
+/// by field; anything else is reported as a single `value` change.
+pub fn diff(before: Option<&Value>, after: Option<&Value>) -> Map<String, Value> {
+    let empty = Map::new();
+    let (before_fields, after_fields) = match (before, after) {
+        (Some(Value::Object(b)), Some(Value::Object(a))) => (b, a),
+        (Some(Value::Object(b)), None) => (b, &empty),
+        (None, Some(Value::Object(a))) => (&empty, a),
+        _ => {
+            let mut changes = Map::new();
+            if before != after {
+                changes.insert("value".to_string(), change(before, after));
+            }
+            return changes;
+        }
+    };
+    let mut changes = Map::new();
+    for (field, old) in before_fields {
+        let new = after_fields.get(field);
+        if new != Some(old) {
+            changes.insert(field.clone(), change(Some(old), new));
+        }
+    }
+    for (field, new) in after_fields {
+        if !before_fields.contains_key(field) {
+            changes.insert(field.clone(), change(None, Some(new)));
+        }
+    }
+    for (field, value) in changes.iter_mut() {
+        if is_secret(field) {
+            // Record that a secret changed without recording it
+            *value = change(
+                Some(&Value::String(REDACTED.to_string())),
+                Some(&Value::String(REDACTED.to_string())),
+            );
+        }
+    }
+    changes
+}
+
+fn change(before: Option<&Value>, after: Option<&Value>) -> Value {
+    serde_json::json!({
+        "before": before.cloned().unwrap_or(Value::Null),
+        "after": after.cloned().unwrap_or(Value::Null),
+    })
+}
+
+fn is_secret(field: &str) -> bool {
+    SECRET_FIELDS.contains(&field.to_ascii_lowercase().as_str())
+}
+
+/// Replace secret fields, at any depth, with a placeholder
+pub fn redact(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (field, value) in fields.iter_mut() {
+                if is_secret(field) && !value.is_null() {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// An entry as returned by queries, with its JSON columns parsed
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub created_at: i64,
+    pub actor_id: Option<String>,
+    pub actor_name: Option<String>,
+    pub source: String,
+    pub ip: Option<String>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<String>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub changes: Option<Value>,
+}
+
+impl From<SysAuditLog> for AuditEntry {
+    fn from(row: SysAuditLog) -> Self {
+        let parse = |text: Option<String>| text.and_then(|t| serde_json::from_str(&t).ok());
+        Self {
+            id: row.id,
+            created_at: row.created_at,
+            actor_id: row.actor_id,
+            actor_name: row.actor_name,
+            source: row.source,
+            ip: row.ip,
+            action: row.action,
+            target_type: row.target_type,
+            target_id: row.target_id,
+            before: parse(row.before_state),
+            after: parse(row.after_state),
+            changes: parse(row.changes),
+        }
+    }
+}
+
+/// One page of entries and the number matching the filter
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditPage {
+    pub entries: Vec<AuditEntry>,
+    pub total: i64,
+}
+
+/// Export encodings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Jsonl,
+}
+
+const CSV_COLUMNS: &[&str] = &[
+    "id",
+    "created_at",
+    "actor_id",
+    "actor_name",
+    "source",
+    "ip",
+    "action",
+    "target_type",
+    "target_id",
+    "changes",
+];
+
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "jsonl" | "ndjson" => Some(Self::Jsonl),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Jsonl => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Jsonl => "jsonl",
+        }
+    }
+
+    /// Text written before the first row, if any.
+    pub fn header(&self) -> Option<String> {
+        match self {
+            Self::Csv => Some(format!("{}\n", CSV_COLUMNS.join(","))),
+            Self::Jsonl => None,
+        }
+    }
+
+    /// One entry rendered as a newline-terminated line.
+    pub fn row(&self, entry: &AuditEntry) -> std::result::Result<String, serde_json::Error> {
+        match self {
+            Self::Csv => Ok(csv_row(entry)),
+            Self::Jsonl => serde_json::to_string(entry).map(|line| line + "\n"),
+        }
+    }
+}
+
+fn csv_row(entry: &AuditEntry) -> String {
+    let text = |v: &Option<String>| csv_field(v.as_deref().unwrap_or(""));
+    let created_at = chrono::DateTime::from_timestamp(entry.created_at, 0)
+        .map(|t| t.to_rfc3339())
+        .unwrap_or_default();
+    let changes = entry
+        .changes
+        .as_ref()
+        .map(Value::to_string)
+        .unwrap_or_default();
+    let fields = [
+        entry.id.to_string(),
+        created_at,
+        text(&entry.actor_id),
+        text(&entry.actor_name),
+        csv_field(&entry.source),
+        text(&entry.ip),
+        csv_field(&entry.action),
+        csv_field(&entry.target_type),
+        text(&entry.target_id),
+        csv_field(&changes),
+    ];
+    format!("{}\n", fields.join(","))
+}
+
+/// Quote a CSV field when it holds a delimiter, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Records and queries the audit trail
+pub struct AuditService;
+
+impl AuditService {
+    /// Append an entry. Snapshots are redacted and diffed here.
+    pub async fn record(db: &Database, actor: &Actor, event: AuditEvent) -> Result<i64> {
+        let changes = diff(event.before.as_ref(), event.after.as_ref());
+        let snapshot = |value: Option<Value>| {
+            value.map(|mut value| {
+                redact(&mut value);
+                value.to_string()
+            })
+        };
+        let entry = NewAuditLog {
+            actor_id: actor.id.clone(),
+            actor_name: actor.name.clone(),
+            source: actor.source.to_string(),
+            ip: actor.ip.clone(),
+            action: event.action,
+            target_type: event.target_type,
+            target_id: event.target_id,
+            before_state: snapshot(event.before),
+            after_state: snapshot(event.after),
+            changes: (!changes.is_empty()).then(|| Value::Object(changes).to_string()),
+        };
+        AuditLogModel::append(db, &entry).await
+    }
+
+    /// [`record`](Self::record) for callers whose change has already been
+    /// committed: a failure is logged instead of returned.
+    pub async fn record_or_log(db: &Database, actor: &Actor, event: AuditEvent) {
+        let action = event.action.clone();
+        if let Err(e) = Self::record(db, actor, event).await {
+            tracing::error!(action, "Failed to write audit log entry: {}", e);
+        }
+    }
+
+    /// Newest first
+    pub async fn list(
+        db: &Database,
+        filter: &AuditLogFilter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<AuditPage> {
+        let entries = AuditLogModel::list(db, filter, limit, offset).await?;
+        Ok(AuditPage {
+            entries: entries.into_iter().map(AuditEntry::from).collect(),
+            total: AuditLogModel::count(db, filter).await?,
+        })
+    }
+
+    /// Newest first, without counting; used to page through exports
+    pub async fn entries(
+        db: &Database,
+        filter: &AuditLogFilter,
+        limit: i64,
+    ) -> Result<Vec<AuditEntry>> {
+        let entries = AuditLogModel::list(db, filter, limit, 0).await?;
+        Ok(entries.into_iter().map(AuditEntry::from).collect())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn diff_reports_changed_fields_and_hides_secrets() {
+        let before = json!({ "name": "a", "weight": 1, "key": "sk-old", "models": "x" });
+        let after = json!({ "name": "b", "weight": 1, "key": "sk-new", "group": "vip" });
+        let changes = diff(Some(&before), Some(&after));
+
+        assert_eq!(changes["name"], json!({ "before": "a", "after": "b" }));
+        assert_eq!(changes["models"], json!({ "before": "x", "after": null }));
+        assert_eq!(changes["group"], json!({ "before": null, "after": "vip" }));
+        assert_eq!(
+            changes["key"],
+            json!({ "before": "[redacted]", "after": "[redacted]" })
+        );
+        assert!(!changes.contains_key("weight"));
+        assert!(!Value::Object(changes).to_string().contains("sk-"));
+    }
+
+    #[test]
+    fn diff_of_scalars_and_creations() {
+        let changes = diff(None, Some(&json!({ "id": 3 })));
+        assert_eq!(changes["id"], json!({ "before": null, "after": 3 }));
+        let changes = diff(Some(&json!(true)), Some(&json!(false)));
+        assert_eq!(changes["value"], json!({ "before": true, "after": false }));
+        assert!(diff(Some(&json!(1)), Some(&json!(1))).is_empty());
+    }
+
+    #[test]
+    fn redaction_reaches_nested_values() {
+        let mut value = json!({
+            "token": "sk-live",
+            "owner": { "password": "hunter2", "name": "ops" },
+            "keys": [{ "api_key": "k" }],
+            "secret": null
+        });
+        redact(&mut value);
+        assert_eq!(value["token"], "[redacted]");
+        assert_eq!(value["owner"]["password"], "[redacted]");
+        assert_eq!(value["owner"]["name"], "ops");
+        assert_eq!(value["keys"][0]["api_key"], "[redacted]");
+        assert!(value["secret"].is_null());
+    }
+
+    #[test]
+    fn csv_rows_quote_json() {
+        let entry = AuditEntry {
+            id: 1,
+            created_at: 0,
+            actor_id: None,
+            actor_name: Some("ops".to_string()),
+            source: SOURCE_CLI.to_string(),
+            ip: None,
+            action: "price.set".to_string(),
+            target_type: "price".to_string(),
+            target_id: Some("gpt-4o".to_string()),
+            before: None,
+            after: None,
+            changes: Some(json!({ "input_price": { "before": 1, "after": 2 } })),
+        };
+        let row = ExportFormat::Csv.row(&entry).unwrap();
+        assert!(row.starts_with("1,1970-01-01T00:00:00+00:00,,ops,cli,,price.set,price,gpt-4o,\"{"));
+        assert!(row.contains("\"\"input_price\"\""));
+    }
+}
@@ -117,7 +117,14 @@ impl CostCalculator {
             opts.is_priority,
             opts.voice_id,
         );
-        Ok(CostResult::from_breakdown(breakdown))
+        let result = CostResult::from_breakdown(breakdown);
+        // Amounts come out in the currency the price is stored in
+        if price.currency.is_empty() || price.currency.eq_ignore_ascii_case("USD") {
+            Ok(result)
+        } else {
+            let total = result.usd_amount_nano;
+            Ok(result.with_local_currency(&price.currency, total))
+        }
     }
 }
 
@@ -544,6 +551,30 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_cost_carries_price_currency() {
+        let cache = PriceCache::empty();
+        let mut price = make_price(1_000_000_000, 0);
+        price.currency = "CNY".to_string();
+        cache
+            .inner
+            .write()
+            .await
+            .insert(("test-model".to_string(), "cn".to_string()), price);
+        let calc = CostCalculator::new(cache);
+        let usage = UnifiedUsage {
+            input_tokens: 1_000_000,
+            ..Default::default()
+        };
+
+        let result = calc
+            .calculate("test-model", &usage, "req-cny", false, false, Some("cn"))
+            .await
+            .unwrap();
+        assert_eq!(result.local_currency, "CNY");
+        assert_eq!(result.local_amount_nano, Some(1_000_000_000));
+    }
+
     /// B1 regression: voice_id must NOT cause double-counting of audio_output_tokens.
     /// When voice_id matches voices_pricing, total() == audio_input_cost + voice_cost
     /// (audio_output_tokens are NOT also billed at audio_output_price).
@@ -4,9 +4,14 @@
 //! including CRUD operations and ability synchronization.
 
 use burncloud_database::Database;
+use burncloud_database_channel::key_cipher;
 use burncloud_database_channel::ChannelProviderModel;
 
 pub use burncloud_common::types::Channel;
+pub use burncloud_database_channel::key_cipher::{
+    decrypt_key, encryption_config_error, is_masked_key, mask_key,
+};
+pub use burncloud_database_channel::KeyMigration;
 
 type Result<T> = std::result::Result<T, burncloud_database::DatabaseError>;
 
@@ -39,6 +44,28 @@ impl ChannelService {
         ChannelProviderModel::get_by_id(db, id).await
     }
 
+    /// Encrypt channel keys still stored in plaintext with the configured
+    /// master key. Run at startup, resumes wherever a previous run stopped.
+    /// Does nothing while encryption at rest is not enabled.
+    pub async fn encrypt_plaintext_keys(db: &Database) -> Result<KeyMigration> {
+        if !key_cipher::encryption_enabled() {
+            return Ok(KeyMigration::default());
+        }
+        ChannelProviderModel::reencrypt_keys(db, key_cipher::keyring()?, false).await
+    }
+
+    /// Whether any channel key is stored encrypted and so needs the master
+    /// key to be read
+    pub async fn has_encrypted_keys(db: &Database) -> Result<bool> {
+        Ok(ChannelProviderModel::count_encrypted_keys(db).await? > 0)
+    }
+
+    /// Re-wrap every channel key under the current master key after a
+    /// rotation, reading old values with `MASTER_KEY_PREVIOUS`.
+    pub async fn rekey(db: &Database) -> Result<KeyMigration> {
+        ChannelProviderModel::reencrypt_keys(db, key_cipher::keyring()?, true).await
+    }
+
     /// Sync model abilities for a channel
     pub async fn sync_abilities(db: &Database, channel: &Channel) -> Result<()> {
         ChannelProviderModel::sync_abilities(db, channel).await
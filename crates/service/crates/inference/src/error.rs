@@ -13,6 +13,18 @@ pub enum InferenceError {
 
     #[error("Configuration error: {0}")]
     ConfigError(String),
+
+    #[error("Instance not found: {0}")]
+    NotFound(String),
+
+    #[error("Download failed: {0}")]
+    DownloadFailed(String),
+
+    #[error("Checksum verification failed: {0}")]
+    ChecksumMismatch(String),
+
+    #[error("Instance unavailable: {0}")]
+    Unavailable(String),
 }
 
 pub type Result<T> = std::result::Result<T, InferenceError>;
@@ -3,27 +3,55 @@
 //! 本地推理服务管理模块，负责 `llama-server` 等推理后端的进程管理。
 
 mod error;
+pub mod lifecycle;
+pub mod supervisor;
 
 pub use error::{InferenceError, Result};
+pub use lifecycle::{
+    InstanceReport, ModelLifecycle, PullJob, PullRequest, PullState, PulledModel, ServeOptions,
+};
+pub use supervisor::{InstanceLease, SupervisorConfig};
 
 use burncloud_common::types::Channel;
 use burncloud_database::Database;
 use burncloud_database_channel::{ChannelAbilityInput, ChannelAbilityModel, ChannelProviderModel};
-use std::collections::HashMap;
+use burncloud_service_models::{ModelInstance, ModelInstanceModel};
+use burncloud_service_monitor::{InstanceMetrics, SystemMonitorService};
+use std::collections::{HashMap, VecDeque};
 use std::process::Stdio;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Instant;
+use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::{Child, Command};
 use tokio::sync::Mutex;
 
+/// 每个实例保留的 stderr 行数
+const LOG_TAIL_LINES: usize = 50;
+
 /// 推理实例状态
 #[derive(Debug, Clone, serde::Serialize, PartialEq)]
 pub enum InstanceStatus {
     Stopped,
     Starting,
     Running,
+    /// 空闲超时后已卸载，渠道保留，下一次请求时冷启动
+    Unloaded,
     Failed(String),
 }
 
+impl InstanceStatus {
+    /// `model_instances.status` 中使用的字符串形式
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InstanceStatus::Stopped => "stopped",
+            InstanceStatus::Starting => "starting",
+            InstanceStatus::Running => "running",
+            InstanceStatus::Unloaded => "unloaded",
+            InstanceStatus::Failed(_) => "failed",
+        }
+    }
+}
+
 /// 推理实例配置
 #[derive(Debug, Clone)]
 pub struct InferenceConfig {
@@ -34,12 +62,28 @@ pub struct InferenceConfig {
     pub gpu_layers: i32, // -1 for all
 }
 
+/// 由当前服务启动过的实例，供监督任务重启、卸载与冷启动使用
+#[derive(Debug, Clone)]
+pub(crate) struct ManagedInstance {
+    pub(crate) config: InferenceConfig,
+    pub(crate) channel_id: Option<i32>,
+    pub(crate) activity: Arc<StdMutex<supervisor::Activity>>,
+    pub(crate) running_since: Option<Instant>,
+    pub(crate) restarts: u32,
+    pub(crate) next_restart: Option<Instant>,
+}
+
 /// 推理服务管理器
 pub struct InferenceService {
     // 存储活跃的进程句柄: Map<ModelID, ChildProcess>
     processes: Arc<Mutex<HashMap<String, Child>>>,
     // 存储实例状态: Map<ModelID, Status>
     statuses: Arc<Mutex<HashMap<String, InstanceStatus>>>,
+    // 受监督的实例: Map<ModelID, ManagedInstance>
+    managed: Arc<Mutex<HashMap<String, ManagedInstance>>>,
+    // 最近的 stderr 输出: Map<ModelID, Lines>
+    logs: Arc<StdMutex<HashMap<String, VecDeque<String>>>>,
+    monitor: Option<Arc<SystemMonitorService>>,
 }
 
 impl Default for InferenceService {
@@ -53,29 +97,53 @@ impl InferenceService {
         Self {
             processes: Arc::new(Mutex::new(HashMap::new())),
             statuses: Arc::new(Mutex::new(HashMap::new())),
+            managed: Arc::new(Mutex::new(HashMap::new())),
+            logs: Arc::new(StdMutex::new(HashMap::new())),
+            monitor: None,
         }
     }
 
+    /// 设置监控服务，实例状态与内存占用将通过它上报
+    pub fn with_monitor(mut self, monitor: Arc<SystemMonitorService>) -> Self {
+        self.monitor = Some(monitor);
+        self
+    }
+
     /// 启动一个推理实例
     pub async fn start_instance(&self, db: &Database, config: InferenceConfig) -> Result<()> {
-        // 1. 检查是否已经在运行
+        // 1. 检查是否已经在运行，并占位为 Starting
         {
-            let statuses = self.statuses.lock().await;
+            let mut statuses = self.statuses.lock().await;
             if let Some(status) = statuses.get(&config.model_id) {
                 if *status == InstanceStatus::Running || *status == InstanceStatus::Starting {
                     return Ok(()); // Already running
                 }
             }
+            statuses.insert(config.model_id.clone(), InstanceStatus::Starting);
         }
 
-        // 2. 更新状态为 Starting
-        self.set_status(&config.model_id, InstanceStatus::Starting)
-            .await;
+        // 2. 启动进程并等待健康检查
+        self.launch(db, &config, None).await?;
+
+        // 3. 注册到 Router
+        let channel_id = self.register_upstream(db, &config).await?;
 
-        // 3. 查找 llama-server 可执行文件
+        self.mark_running(db, &config, channel_id).await
+    }
+
+    /// 启动进程、记录实例并等待健康检查通过
+    ///
+    /// 调用方需已将状态置为 `Starting`。失败时结束进程并把实例标记为 `Failed`。
+    pub(crate) async fn launch(
+        &self,
+        db: &Database,
+        config: &InferenceConfig,
+        channel_id: Option<i32>,
+    ) -> Result<()> {
+        // 查找 llama-server 可执行文件
         let server_bin = self.find_server_binary().await?;
 
-        // 4. 构建命令
+        // 构建命令
         // llama-server -m <model_path> --port <port> -c <ctx> -ngl <gpu_layers>
         let mut cmd = Command::new(server_bin);
         cmd.arg("-m")
@@ -89,66 +157,213 @@ impl InferenceService {
             // 禁用 web ui，只提供 API
             .arg("--nobrowser")
             .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
+            .stderr(Stdio::piped())
+            // 句柄被丢弃（例如 CLI 前台进程退出）时一并结束推理进程
+            .kill_on_drop(true);
 
         tracing::info!("Starting inference: {:?}", cmd);
 
-        match cmd.spawn() {
-            Ok(child) => {
-                // 保存进程句柄
-                let mut processes = self.processes.lock().await;
-                processes.insert(config.model_id.clone(), child);
-
-                // 等待健康检查成功后标记为 Running
-                let health_check_result = self
-                    .wait_for_health_check(&config.model_id, config.port)
-                    .await;
-
-                match health_check_result {
-                    Ok(()) => {
-                        self.set_status(&config.model_id, InstanceStatus::Running)
-                            .await;
-                    }
-                    Err(e) => {
-                        // 健康检查失败，移除进程并标记为 Failed
-                        processes.remove(&config.model_id);
-                        self.set_status(&config.model_id, InstanceStatus::Failed(e.to_string()))
-                            .await;
-                        return Err(e);
-                    }
-                }
-
-                // 5. 注册到 Router
-                self.register_upstream(db, &config).await?;
-
-                Ok(())
-            }
+        let mut child = match cmd.spawn() {
+            Ok(child) => child,
             Err(e) => {
                 let err_msg = format!("Failed to spawn process: {}", e);
                 self.set_status(&config.model_id, InstanceStatus::Failed(err_msg.clone()))
                     .await;
-                Err(InferenceError::ProcessSpawnFailed(err_msg))
+                ModelInstanceModel::update_status(db, &config.model_id, "failed", Some(&err_msg))
+                    .await?;
+                return Err(InferenceError::ProcessSpawnFailed(err_msg));
+            }
+        };
+        self.capture_output(&config.model_id, &mut child);
+
+        // 保存进程句柄，并记录实例，便于其他进程 (`burncloud model ps|stop`) 查看
+        let instance = ModelInstance {
+            model_id: config.model_id.clone(),
+            file_path: config.file_path.clone(),
+            port: i32::from(config.port),
+            pid: child.id().map(i64::from),
+            channel_id,
+            status: InstanceStatus::Starting.as_str().to_string(),
+            last_error: None,
+            started_at: Some(current_timestamp()),
+            updated_at: 0,
+        };
+        self.processes
+            .lock()
+            .await
+            .insert(config.model_id.clone(), child);
+        ModelInstanceModel::upsert(db, &instance).await?;
+
+        // 等待健康检查成功
+        if let Err(e) = self
+            .wait_for_health_check(&config.model_id, config.port)
+            .await
+        {
+            // 健康检查失败，结束进程并标记为 Failed
+            let owned = self.processes.lock().await.remove(&config.model_id);
+            if let Some(mut child) = owned {
+                let _ = child.kill().await;
             }
+            let err_msg = match self.recent_logs(&config.model_id).last() {
+                Some(line) => format!("{} ({})", e, line),
+                None => e.to_string(),
+            };
+            self.set_status(&config.model_id, InstanceStatus::Failed(err_msg.clone()))
+                .await;
+            ModelInstanceModel::update_status(db, &config.model_id, "failed", Some(&err_msg))
+                .await?;
+            return Err(InferenceError::ProcessSpawnFailed(err_msg));
         }
+
+        Ok(())
+    }
+
+    /// 标记实例为 Running，更新记录并纳入监督
+    pub(crate) async fn mark_running(
+        &self,
+        db: &Database,
+        config: &InferenceConfig,
+        channel_id: i32,
+    ) -> Result<()> {
+        self.set_status(&config.model_id, InstanceStatus::Running)
+            .await;
+        ModelInstanceModel::set_channel(db, &config.model_id, channel_id).await?;
+        ModelInstanceModel::update_status(db, &config.model_id, "running", None).await?;
+
+        let now = Instant::now();
+        {
+            let mut managed = self.managed.lock().await;
+            let entry = managed
+                .entry(config.model_id.clone())
+                .or_insert_with(|| ManagedInstance {
+                    config: config.clone(),
+                    channel_id: None,
+                    activity: Default::default(),
+                    running_since: None,
+                    restarts: 0,
+                    next_restart: None,
+                });
+            entry.config = config.clone();
+            entry.channel_id = Some(channel_id);
+            entry.touch();
+            entry.running_since = Some(now);
+            entry.next_restart = None;
+        }
+        self.report(&config.model_id).await;
+
+        Ok(())
     }
 
     /// 停止一个推理实例
+    ///
+    /// 进程由当前服务持有时直接结束句柄；否则按 `model_instances` 中记录的 pid
+    /// 结束由其他进程启动的实例。两种情况都会注销对应渠道。
     pub async fn stop_instance(&self, db: &Database, model_id: &str) -> Result<()> {
-        let mut processes = self.processes.lock().await;
-        if let Some(mut child) = processes.remove(model_id) {
+        // 先移出监督列表，避免被当作崩溃重新拉起
+        let was_managed = self.managed.lock().await.remove(model_id).is_some();
+        let owned = self.processes.lock().await.remove(model_id);
+        if let Some(mut child) = owned {
             // 尝试优雅停止
             child
                 .kill()
                 .await
                 .map_err(|e| InferenceError::ProcessKillFailed(e.to_string()))?;
-            self.set_status(model_id, InstanceStatus::Stopped).await;
+        } else {
+            match ModelInstanceModel::get(db, model_id).await? {
+                Some(instance @ ModelInstance { pid: Some(pid), .. }) if !was_managed => {
+                    if let Err(e) = kill_pid(pid, &instance) {
+                        // 进程可能已经退出，继续清理渠道与记录
+                        tracing::warn!("Failed to kill inference process {}: {}", pid, e);
+                    }
+                }
+                Some(_) => {}
+                None => return Err(InferenceError::NotFound(model_id.to_string())),
+            }
+        }
 
-            // 从 Router 注销
-            self.unregister_upstream(db, model_id).await?;
+        self.set_status(model_id, InstanceStatus::Stopped).await;
+        ModelInstanceModel::update_status(db, model_id, "stopped", None).await?;
+        if let Some(monitor) = &self.monitor {
+            monitor.remove_instance(model_id).await;
         }
+
+        // 从 Router 注销
+        self.unregister_upstream(db, model_id).await?;
         Ok(())
     }
 
+    /// 最近的 stderr 输出（最多保留 `LOG_TAIL_LINES` 行）
+    pub fn recent_logs(&self, model_id: &str) -> Vec<String> {
+        self.logs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(model_id)
+            .map(|lines| lines.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    // 将子进程的 stdout/stderr 转发到日志，并保留 stderr 尾部用于错误报告
+    fn capture_output(&self, model_id: &str, child: &mut Child) {
+        if let Some(stdout) = child.stdout.take() {
+            let model_id = model_id.to_string();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stdout).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    tracing::debug!(target: "llama_server", model = %model_id, "{}", line);
+                }
+            });
+        }
+        if let Some(stderr) = child.stderr.take() {
+            let model_id = model_id.to_string();
+            let logs = self.logs.clone();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    tracing::info!(target: "llama_server", model = %model_id, "{}", line);
+                    let mut logs = logs.lock().unwrap_or_else(|e| e.into_inner());
+                    let tail = logs.entry(model_id.clone()).or_default();
+                    if tail.len() >= LOG_TAIL_LINES {
+                        tail.pop_front();
+                    }
+                    tail.push_back(line);
+                }
+            });
+        }
+    }
+
+    /// 向监控服务上报实例状态
+    pub(crate) async fn report(&self, model_id: &str) {
+        let Some(monitor) = &self.monitor else {
+            return;
+        };
+        let (port, restarts, idle_secs) = match self.managed.lock().await.get(model_id) {
+            Some(m) => (
+                m.config.port,
+                m.restarts,
+                m.idle_for().unwrap_or_default().as_secs(),
+            ),
+            None => return,
+        };
+        let pid = self
+            .processes
+            .lock()
+            .await
+            .get(model_id)
+            .and_then(|child| child.id());
+        let status = self.get_status(model_id).await;
+        monitor
+            .report_instance(InstanceMetrics {
+                model_id: model_id.to_string(),
+                status: status.as_str().to_string(),
+                port,
+                pid,
+                memory_bytes: 0,
+                restarts,
+                idle_secs,
+            })
+            .await;
+    }
+
     /// 获取实例状态
     pub async fn get_status(&self, model_id: &str) -> InstanceStatus {
         let statuses = self.statuses.lock().await;
@@ -186,8 +401,11 @@ impl InferenceService {
         Ok("llama-server".to_string())
     }
 
-    // 注册本地模型到 channel_providers
-    async fn register_upstream(&self, db: &Database, config: &InferenceConfig) -> Result<()> {
+    // 注册本地模型到 channel_providers，返回渠道 ID
+    async fn register_upstream(&self, db: &Database, config: &InferenceConfig) -> Result<i32> {
+        // 重新启动时先清理上一次注册的渠道，避免重复
+        self.unregister_upstream(db, &config.model_id).await?;
+
         let channel_id_str = format!("local-{}", config.model_id);
         let base_url = format!("http://127.0.0.1:{}", config.port);
 
@@ -251,7 +469,7 @@ impl InferenceService {
             channel_id_str,
             channel_id
         );
-        Ok(())
+        Ok(channel_id)
     }
 
     async fn unregister_upstream(&self, db: &Database, model_id: &str) -> Result<()> {
@@ -294,6 +512,16 @@ impl InferenceService {
         while attempts < max_attempts {
             attempts += 1;
 
+            // 进程已经退出时无需继续等待
+            if let Some(child) = self.processes.lock().await.get_mut(model_id) {
+                if let Ok(Some(status)) = child.try_wait() {
+                    return Err(InferenceError::ProcessSpawnFailed(format!(
+                        "{} exited during startup ({})",
+                        model_id, status
+                    )));
+                }
+            }
+
             match client.get(&url).send().await {
                 Ok(resp) if resp.status().is_success() => {
                     tracing::info!(
@@ -332,3 +560,138 @@ impl InferenceService {
         )))
     }
 }
+
+/// 在本机上找一个空闲端口（由操作系统分配）
+pub fn find_free_port() -> Result<u16> {
+    let listener = std::net::TcpListener::bind(("127.0.0.1", 0))
+        .map_err(|e| InferenceError::ConfigError(format!("No free port available: {}", e)))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| InferenceError::ConfigError(format!("No free port available: {}", e)))?
+        .port();
+    Ok(port)
+}
+
+fn current_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// 按 pid 结束不由当前进程持有句柄的推理进程
+///
+/// pid 可能已被系统复用，因此先确认该进程的命令行仍是记录中的
+/// `llama-server`（同一模型文件与端口），否则拒绝发送信号。
+fn kill_pid(pid: i64, instance: &ModelInstance) -> Result<()> {
+    let cmdline = process_command_line(pid).ok_or_else(|| {
+        InferenceError::ProcessKillFailed(format!("process {} is not running", pid))
+    })?;
+    if !is_instance_process(&cmdline, instance) {
+        return Err(InferenceError::ProcessKillFailed(format!(
+            "process {} is not the inference server of {}",
+            pid, instance.model_id
+        )));
+    }
+
+    #[cfg(windows)]
+    let output = std::process::Command::new("taskkill")
+        .args(["/F", "/PID", &pid.to_string()])
+        .output();
+    #[cfg(not(windows))]
+    let output = std::process::Command::new("kill")
+        .arg(pid.to_string())
+        .output();
+
+    match output {
+        Ok(out) if out.status.success() => Ok(()),
+        Ok(out) => Err(InferenceError::ProcessKillFailed(
+            String::from_utf8_lossy(&out.stderr).trim().to_string(),
+        )),
+        Err(e) => Err(InferenceError::ProcessKillFailed(e.to_string())),
+    }
+}
+
+/// 进程的完整命令行；进程不存在时返回 None
+fn process_command_line(pid: i64) -> Option<String> {
+    #[cfg(windows)]
+    let output = std::process::Command::new("powershell")
+        .args([
+            "-NoProfile",
+            "-Command",
+            &format!(
+                "(Get-CimInstance Win32_Process -Filter \"ProcessId={}\").CommandLine",
+                pid
+            ),
+        ])
+        .output();
+    #[cfg(not(windows))]
+    let output = std::process::Command::new("ps")
+        .args(["-o", "args=", "-p", &pid.to_string()])
+        .output();
+
+    let out = output.ok().filter(|out| out.status.success())?;
+    let cmdline = String::from_utf8_lossy(&out.stdout).trim().to_string();
+    (!cmdline.is_empty()).then_some(cmdline)
+}
+
+/// 命令行是否带有实例记录中的模型文件与端口
+fn is_instance_process(cmdline: &str, instance: &ModelInstance) -> bool {
+    let args: Vec<&str> = cmdline.split_whitespace().collect();
+    let port = instance.port.to_string();
+    cmdline.contains(&instance.file_path)
+        && args
+            .windows(2)
+            .any(|pair| pair[0] == "--port" && pair[1] == port)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn instance(file_path: &str, port: i32, pid: Option<i64>) -> ModelInstance {
+        ModelInstance {
+            model_id: "Qwen/Qwen2.5-7B-Instruct-GGUF".to_string(),
+            file_path: file_path.to_string(),
+            port,
+            pid,
+            channel_id: None,
+            status: "running".to_string(),
+            last_error: None,
+            started_at: None,
+            updated_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_instance_process_matches_file_and_port() {
+        let inst = instance("/data/qwen/model-Q4_K_M.gguf", 18080, None);
+        assert!(is_instance_process(
+            "llama-server -m /data/qwen/model-Q4_K_M.gguf --port 18080 -c 4096",
+            &inst
+        ));
+        assert!(!is_instance_process(
+            "llama-server -m /data/qwen/model-Q4_K_M.gguf --port 18081 -c 4096",
+            &inst
+        ));
+        assert!(!is_instance_process("/usr/sbin/sshd -D", &inst));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_kill_pid_refuses_reused_pid() {
+        let mut other = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let pid = i64::from(other.id());
+
+        let err = kill_pid(pid, &instance("/data/model.gguf", 18080, Some(pid))).unwrap_err();
+        assert!(matches!(err, InferenceError::ProcessKillFailed(_)));
+        assert!(other.try_wait().unwrap().is_none());
+
+        other.kill().unwrap();
+        other.wait().unwrap();
+    }
+}
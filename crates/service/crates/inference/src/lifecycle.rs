@@ -0,0 +1,433 @@
+//! 模型生命周期：下载 → 校验 → 启动 `llama-server` → 注册渠道
+//!
+//! `burncloud model pull|serve|stop|ps` 与管理接口 `/console/api/models/*`
+//! 共用这里的实现，保证两条路径的行为一致。
+
+use crate::{find_free_port, InferenceConfig, InferenceError, InferenceService, Result};
+use burncloud_database::Database;
+use burncloud_download::DownloadManager;
+use burncloud_service_models::{ModelInstance, ModelInstanceModel, PullProgress};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+/// 默认上下文长度
+pub const DEFAULT_CONTEXT_SIZE: u32 = 4096;
+/// 默认 GPU 层数（-1 表示全部卸载到 GPU）
+pub const DEFAULT_GPU_LAYERS: i32 = -1;
+
+fn default_context_size() -> u32 {
+    DEFAULT_CONTEXT_SIZE
+}
+
+fn default_gpu_layers() -> i32 {
+    DEFAULT_GPU_LAYERS
+}
+
+/// 拉取请求
+#[derive(Debug, Clone, Deserialize)]
+pub struct PullRequest {
+    /// HuggingFace 模型 ID，例如 `Qwen/Qwen2.5-7B-Instruct-GGUF`
+    pub model_id: String,
+    /// 量化名称（如 `Q4_K_M`）或仓库内的完整文件路径
+    pub file: String,
+    /// 期望的 SHA-256；缺省时使用 HuggingFace 文件列表中的 LFS 摘要
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+/// 拉取完成的模型文件
+#[derive(Debug, Clone, Serialize)]
+pub struct PulledModel {
+    pub model_id: String,
+    /// 仓库内的文件路径
+    pub file: String,
+    /// 本地绝对路径
+    pub file_path: String,
+    pub sha256: String,
+    /// 是否与期望摘要比对通过（没有可比对的摘要时为 false）
+    pub verified: bool,
+}
+
+/// 后台拉取任务状态
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub enum PullState {
+    Downloading,
+    Verifying,
+    Completed,
+    Failed(String),
+}
+
+/// 后台拉取任务（管理接口使用）
+#[derive(Debug, Clone, Serialize)]
+pub struct PullJob {
+    pub id: String,
+    pub model_id: String,
+    pub file: String,
+    pub state: PullState,
+    pub progress: PullProgress,
+    pub result: Option<PulledModel>,
+}
+
+/// 启动参数
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServeOptions {
+    pub model_id: String,
+    /// 本地 GGUF 路径；缺省时使用 `pull` 下载到数据目录中的文件
+    #[serde(default)]
+    pub file_path: Option<String>,
+    /// 监听端口；缺省时自动选择空闲端口
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default = "default_context_size")]
+    pub context_size: u32,
+    #[serde(default = "default_gpu_layers")]
+    pub gpu_layers: i32,
+}
+
+/// `ps` 输出中的一行
+#[derive(Debug, Clone, Serialize)]
+pub struct InstanceReport {
+    #[serde(flatten)]
+    pub instance: ModelInstance,
+    /// 推理进程当前是否通过健康检查
+    pub healthy: bool,
+}
+
+/// 模型生命周期管理器
+pub struct ModelLifecycle {
+    inference: Arc<InferenceService>,
+    // aria2 守护进程只启动一次，所有拉取任务共用
+    downloader: OnceCell<Arc<DownloadManager>>,
+    pulls: Mutex<HashMap<String, PullJob>>,
+    next_pull_id: AtomicU64,
+}
+
+impl ModelLifecycle {
+    pub fn new(inference: Arc<InferenceService>) -> Self {
+        Self {
+            inference,
+            downloader: OnceCell::new(),
+            pulls: Mutex::new(HashMap::new()),
+            next_pull_id: AtomicU64::new(1),
+        }
+    }
+
+    pub fn inference(&self) -> &Arc<InferenceService> {
+        &self.inference
+    }
+
+    async fn downloader(&self) -> Result<Arc<DownloadManager>> {
+        self.downloader
+            .get_or_try_init(|| async {
+                DownloadManager::new()
+                    .await
+                    .map(Arc::new)
+                    .map_err(|e| InferenceError::DownloadFailed(e.to_string()))
+            })
+            .await
+            .cloned()
+    }
+
+    /// 下载选定的量化文件并校验，期间通过 `on_progress` 回报进度
+    pub async fn pull<F>(&self, req: &PullRequest, mut on_progress: F) -> Result<PulledModel>
+    where
+        F: FnMut(&PullProgress) + Send,
+    {
+        let files = burncloud_service_models::get_model_files(&req.model_id)
+            .await
+            .map_err(|e| InferenceError::DownloadFailed(e.to_string()))?;
+        let file =
+            burncloud_service_models::select_gguf_file(&files, &req.file).ok_or_else(|| {
+                InferenceError::NotFound(format!(
+                    "{} has no GGUF file matching '{}'",
+                    req.model_id, req.file
+                ))
+            })?;
+
+        let expected = req
+            .sha256
+            .clone()
+            .or_else(|| burncloud_service_models::lfs_sha256(&files, &file));
+
+        let url = burncloud_service_models::build_download_url(&req.model_id, &file)
+            .await
+            .map_err(|e| InferenceError::DownloadFailed(e.to_string()))?;
+        let dest = burncloud_service_models::local_model_path(&req.model_id, &file)
+            .await
+            .map_err(|e| InferenceError::DownloadFailed(e.to_string()))?;
+        let dir = dest
+            .parent()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|| ".".to_string());
+
+        let manager = self.downloader().await?;
+        let gid = manager
+            .add_download(&url, Some(&dir))
+            .await
+            .map_err(|e| InferenceError::DownloadFailed(e.to_string()))?;
+        tracing::info!("Pulling {} ({}) as {}", req.model_id, file, gid);
+
+        burncloud_service_models::wait_for_download(&manager, &gid, &mut on_progress)
+            .await
+            .map_err(|e| InferenceError::DownloadFailed(e.to_string()))?;
+
+        let (sha256, verified) = verify_checksum(&dest, &file, expected.as_deref()).await?;
+        if !verified {
+            tracing::warn!(
+                "No checksum recorded for {}, skipping verification of {}",
+                req.model_id,
+                file
+            );
+        }
+
+        let file_path = tokio::fs::canonicalize(&dest)
+            .await
+            .unwrap_or(dest)
+            .to_string_lossy()
+            .to_string();
+
+        Ok(PulledModel {
+            model_id: req.model_id.clone(),
+            file,
+            file_path,
+            sha256,
+            verified,
+        })
+    }
+
+    /// 在后台开始拉取，返回任务 ID；进度通过 [`Self::pull_job`] 查询
+    pub fn start_pull(self: &Arc<Self>, req: PullRequest) -> String {
+        let id = format!("pull-{}", self.next_pull_id.fetch_add(1, Ordering::Relaxed));
+        let job = PullJob {
+            id: id.clone(),
+            model_id: req.model_id.clone(),
+            file: req.file.clone(),
+            state: PullState::Downloading,
+            progress: PullProgress::default(),
+            result: None,
+        };
+        self.pulls
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id.clone(), job);
+
+        let this = Arc::clone(self);
+        let job_id = id.clone();
+        tokio::spawn(async move {
+            let result = this
+                .pull(&req, |progress| {
+                    this.update_job(&job_id, |job| {
+                        job.progress = progress.clone();
+                        if progress.status == "complete" {
+                            job.state = PullState::Verifying;
+                        }
+                    })
+                })
+                .await;
+
+            this.update_job(&job_id, |job| match result {
+                Ok(pulled) => {
+                    job.state = PullState::Completed;
+                    job.result = Some(pulled);
+                }
+                Err(e) => {
+                    tracing::error!("Pull {} failed: {}", job.id, e);
+                    job.state = PullState::Failed(e.to_string());
+                }
+            });
+        });
+
+        id
+    }
+
+    fn update_job(&self, id: &str, f: impl FnOnce(&mut PullJob)) {
+        let mut pulls = self.pulls.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(job) = pulls.get_mut(id) {
+            f(job);
+        }
+    }
+
+    pub fn pull_job(&self, id: &str) -> Option<PullJob> {
+        self.pulls
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(id)
+            .cloned()
+    }
+
+    pub fn list_pulls(&self) -> Vec<PullJob> {
+        let mut jobs: Vec<PullJob> = self
+            .pulls
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .cloned()
+            .collect();
+        jobs.sort_by(|a, b| a.id.cmp(&b.id));
+        jobs
+    }
+
+    /// 启动推理进程、等待健康检查并注册为路由渠道
+    pub async fn serve(&self, db: &Database, opts: ServeOptions) -> Result<ModelInstance> {
+        let file_path = match opts.file_path {
+            Some(path) => path,
+            None => find_local_gguf(&opts.model_id).await?,
+        };
+        let file_path = tokio::fs::canonicalize(&file_path)
+            .await
+            .map_err(|e| InferenceError::NotFound(format!("{}: {}", file_path, e)))?
+            .to_string_lossy()
+            .to_string();
+        let port = match opts.port {
+            Some(port) => port,
+            None => find_free_port()?,
+        };
+
+        let config = InferenceConfig {
+            model_id: opts.model_id.clone(),
+            file_path,
+            port,
+            context_size: opts.context_size,
+            gpu_layers: opts.gpu_layers,
+        };
+        self.inference.start_instance(db, config).await?;
+
+        ModelInstanceModel::get(db, &opts.model_id)
+            .await?
+            .ok_or_else(|| InferenceError::NotFound(opts.model_id.clone()))
+    }
+
+    /// 停止实例并注销渠道
+    pub async fn stop(&self, db: &Database, model_id: &str) -> Result<()> {
+        self.inference.stop_instance(db, model_id).await
+    }
+
+    /// 列出所有本地实例，并对标记为运行中的实例做一次健康检查
+    pub async fn ps(&self, db: &Database) -> Result<Vec<InstanceReport>> {
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(2))
+            .build()
+            .map_err(|e| InferenceError::ConfigError(format!("HTTP client error: {}", e)))?;
+
+        let mut reports = Vec::new();
+        for instance in ModelInstanceModel::list(db).await? {
+            let healthy = if instance.status == "running" {
+                let url = format!("http://127.0.0.1:{}/health", instance.port);
+                matches!(client.get(&url).send().await, Ok(resp) if resp.status().is_success())
+            } else {
+                false
+            };
+            reports.push(InstanceReport { instance, healthy });
+        }
+        Ok(reports)
+    }
+}
+
+/// 计算下载文件的摘要并与期望值比对，返回摘要及是否比对过
+///
+/// 不匹配时删除文件：损坏的文件不能留给 aria2 续传。
+async fn verify_checksum(
+    dest: &Path,
+    file: &str,
+    expected: Option<&str>,
+) -> Result<(String, bool)> {
+    let sha256 = burncloud_service_models::file_sha256(dest)
+        .await
+        .map_err(|e| InferenceError::DownloadFailed(e.to_string()))?;
+    match expected.map(str::trim) {
+        Some(exp) if !exp.eq_ignore_ascii_case(&sha256) => {
+            let _ = tokio::fs::remove_file(dest).await;
+            Err(InferenceError::ChecksumMismatch(format!(
+                "{}: expected {}, got {}",
+                file, exp, sha256
+            )))
+        }
+        Some(_) => Ok((sha256, true)),
+        None => Ok((sha256, false)),
+    }
+}
+
+/// 在数据目录中查找已拉取的 GGUF 文件（多个时取文件名排序后的第一个）
+async fn find_local_gguf(model_id: &str) -> Result<String> {
+    let base_dir = burncloud_service_models::get_data_dir()
+        .await
+        .map_err(|e| InferenceError::ConfigError(e.to_string()))?;
+    let model_dir = Path::new(&base_dir).join(model_id);
+
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    if let Ok(mut entries) = tokio::fs::read_dir(&model_dir).await {
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            let is_gguf = path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("gguf"));
+            if is_gguf {
+                candidates.push(path);
+            }
+        }
+    }
+    candidates.sort();
+
+    candidates
+        .into_iter()
+        .next()
+        .map(|p| p.to_string_lossy().to_string())
+        .ok_or_else(|| {
+            InferenceError::NotFound(format!(
+                "no GGUF file under {}, run `burncloud model pull` first",
+                model_dir.display()
+            ))
+        })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    // "hello" 的 SHA-256
+    const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    #[tokio::test]
+    async fn test_checksum_mismatch_removes_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("model.gguf");
+
+        tokio::fs::write(&dest, b"hello").await.unwrap();
+        let (sha256, verified) = verify_checksum(&dest, "model.gguf", Some(HELLO_SHA256))
+            .await
+            .unwrap();
+        assert_eq!(sha256, HELLO_SHA256);
+        assert!(verified);
+
+        let err = verify_checksum(&dest, "model.gguf", Some(&"0".repeat(64)))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, InferenceError::ChecksumMismatch(_)));
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn test_expected_sha_comes_from_lfs_oid() {
+        let items: Vec<burncloud_service_models::HfFileItem> = serde_json::from_str(&format!(
+            r#"[{{"type":"file","oid":"a5b1c0d9e8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3","size":5,
+                 "path":"model-Q4_K_M.gguf","lfs":{{"oid":"{HELLO_SHA256}","size":5,"pointerSize":130}}}},
+                {{"type":"file","oid":"b5b1c0d9e8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3","size":12,
+                 "path":"README.md"}}]"#
+        ))
+        .unwrap();
+        let files: Vec<Vec<String>> = items.into_iter().map(|item| item.into_row()).collect();
+        assert_eq!(
+            burncloud_service_models::lfs_sha256(&files, "model-Q4_K_M.gguf").as_deref(),
+            Some(HELLO_SHA256)
+        );
+        assert_eq!(
+            burncloud_service_models::lfs_sha256(&files, "README.md"),
+            None
+        );
+    }
+}
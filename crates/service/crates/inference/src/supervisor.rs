@@ -0,0 +1,668 @@
+//! 推理实例监督：崩溃重启、空闲卸载与按需冷启动
+//!
+//! 监督任务定期检查由当前服务启动的 `llama-server` 进程：
+//! - 进程意外退出时按指数退避重启，超过次数上限后保持 `Failed`
+//! - 超过空闲时间没有请求的实例被卸载，渠道保留
+//! - 路由转发到已卸载实例的渠道前调用 [`InferenceService::ensure_ready`] 冷启动并等待就绪，
+//!   返回的 [`InstanceLease`] 在请求结束前阻止实例被卸载
+
+use crate::{InferenceError, InferenceService, InstanceStatus, ManagedInstance, Result};
+use burncloud_database::Database;
+use burncloud_service_models::ModelInstanceModel;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// 等待实例就绪的最长时间（健康检查最多 60 秒，留出余量）
+const READY_TIMEOUT: Duration = Duration::from_secs(90);
+/// 等待其他请求触发的启动完成时的轮询间隔
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// 监督任务配置
+#[derive(Debug, Clone)]
+pub struct SupervisorConfig {
+    /// 检查间隔
+    pub check_interval: Duration,
+    /// 空闲多久后卸载；`None` 表示不卸载
+    pub idle_timeout: Option<Duration>,
+    /// 连续崩溃后的最大重启次数
+    pub max_restarts: u32,
+    /// 第一次重启前的等待时间，之后每次翻倍
+    pub backoff_base: Duration,
+    /// 重启等待时间上限；稳定运行超过该时间后重启计数清零
+    pub backoff_max: Duration,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            check_interval: Duration::from_secs(5),
+            idle_timeout: Some(Duration::from_secs(30 * 60)),
+            max_restarts: 5,
+            backoff_base: Duration::from_secs(2),
+            backoff_max: Duration::from_secs(60),
+        }
+    }
+}
+
+impl SupervisorConfig {
+    /// 从环境变量读取配置
+    ///
+    /// - `BURNCLOUD_INFERENCE_IDLE_TIMEOUT_SECS`: 空闲卸载时间，0 表示不卸载（默认 1800）
+    /// - `BURNCLOUD_INFERENCE_MAX_RESTARTS`: 最大重启次数（默认 5）
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Some(secs) = env_u64("BURNCLOUD_INFERENCE_IDLE_TIMEOUT_SECS") {
+            config.idle_timeout = (secs > 0).then(|| Duration::from_secs(secs));
+        }
+        if let Some(max) = env_u64("BURNCLOUD_INFERENCE_MAX_RESTARTS") {
+            config.max_restarts = max.min(u64::from(u32::MAX)) as u32;
+        }
+        config
+    }
+
+    /// 第 `attempt` 次重启前的等待时间（从 1 开始）
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.backoff_base
+            .saturating_mul(factor)
+            .min(self.backoff_max)
+    }
+}
+
+fn env_u64(key: &str) -> Option<u64> {
+    std::env::var(key).ok().and_then(|v| v.trim().parse().ok())
+}
+
+/// 实例的使用情况：正在处理的请求数与最近一次使用时间
+#[derive(Debug)]
+pub(crate) struct Activity {
+    in_flight: usize,
+    last_used: Instant,
+}
+
+impl Default for Activity {
+    fn default() -> Self {
+        Self {
+            in_flight: 0,
+            last_used: Instant::now(),
+        }
+    }
+}
+
+/// 一个正在使用本地实例的请求，释放前实例不会被空闲卸载
+///
+/// 释放时刷新实例的最近使用时间，空闲时间从请求结束算起。
+#[derive(Debug)]
+pub struct InstanceLease {
+    activity: Arc<StdMutex<Activity>>,
+}
+
+impl Drop for InstanceLease {
+    fn drop(&mut self) {
+        let mut activity = self.activity.lock().unwrap_or_else(|e| e.into_inner());
+        activity.in_flight = activity.in_flight.saturating_sub(1);
+        activity.last_used = Instant::now();
+    }
+}
+
+impl ManagedInstance {
+    fn activity(&self) -> std::sync::MutexGuard<'_, Activity> {
+        self.activity.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 刷新最近使用时间
+    pub(crate) fn touch(&self) {
+        self.activity().last_used = Instant::now();
+    }
+
+    /// 距最近一次使用的时间；有请求在处理时不算空闲，返回 `None`
+    pub(crate) fn idle_for(&self) -> Option<Duration> {
+        let activity = self.activity();
+        (activity.in_flight == 0).then(|| activity.last_used.elapsed())
+    }
+
+    fn is_idle(&self, timeout: Duration) -> bool {
+        self.idle_for().is_some_and(|idle| idle >= timeout)
+    }
+
+    // 占用实例直到返回的租约被释放；调用方须持有 `managed` 锁，
+    // 与 `unload` 的空闲判断互斥
+    fn lease(&self) -> InstanceLease {
+        let mut activity = self.activity();
+        activity.in_flight += 1;
+        activity.last_used = Instant::now();
+        InstanceLease {
+            activity: self.activity.clone(),
+        }
+    }
+}
+
+impl InferenceService {
+    /// 启动后台监督任务
+    pub fn start_supervisor(
+        self: &Arc<Self>,
+        db: Arc<Database>,
+        config: SupervisorConfig,
+    ) -> JoinHandle<()> {
+        let service = self.clone();
+        tokio::spawn(async move {
+            tracing::info!(
+                idle_timeout = ?config.idle_timeout,
+                max_restarts = config.max_restarts,
+                "Inference supervisor started"
+            );
+            let mut timer = tokio::time::interval(config.check_interval);
+            loop {
+                timer.tick().await;
+                service.supervise(&db, &config).await;
+            }
+        })
+    }
+
+    /// 确保渠道对应的本地实例可以处理请求
+    ///
+    /// 渠道不属于受监督实例时返回 `None`；实例已卸载时冷启动并等待健康检查通过。
+    /// 调用方在请求（包括流式响应）结束前持有返回的租约，期间实例不会被卸载。
+    pub async fn ensure_ready(
+        &self,
+        db: &Database,
+        channel_id: i32,
+    ) -> Result<Option<InstanceLease>> {
+        let (model_id, lease) = {
+            let managed = self.managed.lock().await;
+            match managed
+                .iter()
+                .find(|(_, m)| m.channel_id == Some(channel_id))
+            {
+                Some((model_id, m)) => (model_id.clone(), m.lease()),
+                None => return Ok(None),
+            }
+        };
+
+        let deadline = Instant::now() + READY_TIMEOUT;
+        loop {
+            match self.get_status(&model_id).await {
+                InstanceStatus::Running => return Ok(Some(lease)),
+                InstanceStatus::Unloaded => {
+                    tracing::info!("Cold-starting local model {}", model_id);
+                    self.resume(db, &model_id).await?;
+                }
+                InstanceStatus::Starting => {
+                    if Instant::now() >= deadline {
+                        return Err(InferenceError::Unavailable(format!(
+                            "{} did not become ready in {} seconds",
+                            model_id,
+                            READY_TIMEOUT.as_secs()
+                        )));
+                    }
+                    tokio::time::sleep(READY_POLL_INTERVAL).await;
+                }
+                InstanceStatus::Failed(msg) => {
+                    return Err(InferenceError::Unavailable(format!(
+                        "{}: {}",
+                        model_id, msg
+                    )))
+                }
+                InstanceStatus::Stopped => {
+                    return Err(InferenceError::Unavailable(format!(
+                        "{} is stopped",
+                        model_id
+                    )))
+                }
+            }
+        }
+    }
+
+    /// 用上一次的配置重新启动已卸载或失败的实例，沿用原渠道
+    ///
+    /// 实例已由其他调用方启动时返回 `Ok(false)`。
+    pub(crate) async fn resume(&self, db: &Database, model_id: &str) -> Result<bool> {
+        let (config, channel_id) = match self.managed.lock().await.get(model_id) {
+            Some(m) => (m.config.clone(), m.channel_id),
+            None => return Err(InferenceError::NotFound(model_id.to_string())),
+        };
+
+        {
+            let mut statuses = self.statuses.lock().await;
+            match statuses.get(model_id) {
+                Some(InstanceStatus::Unloaded) | Some(InstanceStatus::Failed(_)) => {}
+                _ => return Ok(false),
+            }
+            statuses.insert(model_id.to_string(), InstanceStatus::Starting);
+        }
+        self.report(model_id).await;
+
+        let result = self.launch(db, &config, channel_id).await;
+        self.report(model_id).await;
+        result?;
+
+        let channel_id = match channel_id {
+            Some(id) => id,
+            None => self.register_upstream(db, &config).await?,
+        };
+        self.mark_running(db, &config, channel_id).await?;
+        Ok(true)
+    }
+
+    /// 卸载空闲了至少 `idle_timeout` 的实例：结束进程，保留渠道以便下次请求时冷启动
+    ///
+    /// 空闲判断与状态切换都在 `managed` 锁内完成，`ensure_ready` 在同一把锁内
+    /// 占用实例，因此已放行的请求不会遇到被卸载的实例。
+    pub(crate) async fn unload(
+        &self,
+        db: &Database,
+        model_id: &str,
+        idle_timeout: Duration,
+    ) -> Result<()> {
+        {
+            let managed = self.managed.lock().await;
+            if managed
+                .get(model_id)
+                .is_none_or(|m| !m.is_idle(idle_timeout))
+            {
+                return Ok(());
+            }
+            let mut statuses = self.statuses.lock().await;
+            if statuses.get(model_id) != Some(&InstanceStatus::Running) {
+                return Ok(());
+            }
+            statuses.insert(model_id.to_string(), InstanceStatus::Unloaded);
+        }
+
+        let owned = self.processes.lock().await.remove(model_id);
+        if let Some(mut child) = owned {
+            child
+                .kill()
+                .await
+                .map_err(|e| InferenceError::ProcessKillFailed(e.to_string()))?;
+        }
+        if let Some(m) = self.managed.lock().await.get_mut(model_id) {
+            m.running_since = None;
+        }
+        ModelInstanceModel::update_status(db, model_id, "unloaded", None).await?;
+        tracing::info!("Unloaded idle local model {}", model_id);
+        self.report(model_id).await;
+        Ok(())
+    }
+
+    /// 执行一轮检查
+    async fn supervise(self: &Arc<Self>, db: &Arc<Database>, config: &SupervisorConfig) {
+        // 1. 崩溃检测：只检查已进入 Running 的进程，启动中的进程由健康检查负责
+        let running: HashSet<String> = self
+            .statuses
+            .lock()
+            .await
+            .iter()
+            .filter(|(_, status)| **status == InstanceStatus::Running)
+            .map(|(model_id, _)| model_id.clone())
+            .collect();
+        let exited: Vec<(String, String)> = {
+            let mut processes = self.processes.lock().await;
+            let mut exited = Vec::new();
+            processes.retain(|model_id, child| {
+                if !running.contains(model_id) {
+                    return true;
+                }
+                match child.try_wait() {
+                    Ok(Some(status)) => {
+                        exited.push((model_id.clone(), status.to_string()));
+                        false
+                    }
+                    _ => true,
+                }
+            });
+            exited
+        };
+
+        for (model_id, exit) in exited {
+            let msg = match self.recent_logs(&model_id).last() {
+                Some(line) => format!("process exited ({}): {}", exit, line),
+                None => format!("process exited ({})", exit),
+            };
+            tracing::error!("Local model {} crashed: {}", model_id, msg);
+            self.set_status(&model_id, InstanceStatus::Failed(msg.clone()))
+                .await;
+            if let Err(e) =
+                ModelInstanceModel::update_status(db, &model_id, "failed", Some(&msg)).await
+            {
+                tracing::error!("Failed to record crash of {}: {}", model_id, e);
+            }
+            if let Some(m) = self.managed.lock().await.get_mut(&model_id) {
+                m.running_since = None;
+            }
+            self.schedule_restart(&model_id, config).await;
+        }
+
+        // 2. 到期的重启、稳定运行后清零重启计数、空闲卸载
+        let now = Instant::now();
+        let mut due = Vec::new();
+        let mut idle = Vec::new();
+        {
+            let mut managed = self.managed.lock().await;
+            for (model_id, m) in managed.iter_mut() {
+                if m.next_restart.is_some_and(|at| at <= now) {
+                    m.next_restart = None;
+                    due.push(model_id.clone());
+                }
+                if let Some(since) = m.running_since {
+                    if m.restarts > 0 && since.elapsed() >= config.backoff_max {
+                        m.restarts = 0;
+                    }
+                    if config
+                        .idle_timeout
+                        .is_some_and(|timeout| m.is_idle(timeout))
+                    {
+                        idle.push(model_id.clone());
+                    }
+                }
+            }
+        }
+
+        for model_id in due {
+            let service = self.clone();
+            let db = db.clone();
+            let config = config.clone();
+            tokio::spawn(async move {
+                tracing::info!("Restarting local model {}", model_id);
+                if let Err(e) = service.resume(&db, &model_id).await {
+                    tracing::warn!("Restart of local model {} failed: {}", model_id, e);
+                    service.schedule_restart(&model_id, &config).await;
+                }
+            });
+        }
+
+        if let Some(idle_timeout) = config.idle_timeout {
+            for model_id in idle {
+                if let Err(e) = self.unload(db, &model_id, idle_timeout).await {
+                    tracing::warn!("Failed to unload local model {}: {}", model_id, e);
+                }
+            }
+        }
+
+        // 3. 上报状态
+        let model_ids: Vec<String> = self.managed.lock().await.keys().cloned().collect();
+        for model_id in model_ids {
+            self.report(&model_id).await;
+        }
+    }
+
+    // 按退避策略安排下一次重启，超过次数上限则放弃
+    async fn schedule_restart(&self, model_id: &str, config: &SupervisorConfig) {
+        let mut managed = self.managed.lock().await;
+        let Some(m) = managed.get_mut(model_id) else {
+            return;
+        };
+        if m.restarts >= config.max_restarts {
+            m.next_restart = None;
+            tracing::error!(
+                "Local model {} failed {} times, giving up",
+                model_id,
+                m.restarts
+            );
+            return;
+        }
+        m.restarts += 1;
+        let delay = config.backoff(m.restarts);
+        m.next_restart = Some(Instant::now() + delay);
+        tracing::warn!(
+            "Restarting local model {} in {}s (attempt {}/{})",
+            model_id,
+            delay.as_secs(),
+            m.restarts,
+            config.max_restarts
+        );
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::InferenceConfig;
+    use std::path::Path;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        let config = SupervisorConfig::default();
+        assert_eq!(config.backoff(1), Duration::from_secs(2));
+        assert_eq!(config.backoff(2), Duration::from_secs(4));
+        assert_eq!(config.backoff(3), Duration::from_secs(8));
+        assert_eq!(config.backoff(10), Duration::from_secs(60));
+        assert_eq!(config.backoff(40), Duration::from_secs(60));
+    }
+
+    /// 代替 `llama-server` 的脚本：每次启动在模型文件旁的 `.launches` 中追加一行
+    ///
+    /// 环境变量在测试间共享，脚本放在整个测试进程期间都存在的位置。
+    fn fake_server() -> String {
+        static SCRIPT: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+        SCRIPT
+            .get_or_init(|| {
+                let script = std::env::temp_dir().join(format!(
+                    "burncloud-fake-llama-server-{}",
+                    std::process::id()
+                ));
+                std::fs::write(
+                    &script,
+                    "#!/bin/sh\necho x >> \"$2.launches\"\nexec sleep 600\n",
+                )
+                .unwrap();
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))
+                        .unwrap();
+                }
+                script.to_string_lossy().to_string()
+            })
+            .clone()
+    }
+
+    fn launches(dir: &Path) -> usize {
+        std::fs::read_to_string(dir.join("model.gguf.launches"))
+            .map(|s| s.lines().count())
+            .unwrap_or(0)
+    }
+
+    /// 在本地端口上应答健康检查，返回端口
+    async fn health_endpoint() -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await;
+                let _ = socket
+                    .write_all(
+                        b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\n{}",
+                    )
+                    .await;
+            }
+        });
+        port
+    }
+
+    struct Fixture {
+        service: Arc<InferenceService>,
+        db: Arc<Database>,
+        dir: tempfile::TempDir,
+        model_id: String,
+        channel_id: i32,
+    }
+
+    async fn running_instance() -> Fixture {
+        let dir = tempfile::tempdir().unwrap();
+        std::env::set_var("BURNCLOUD_LLAMA_BIN", fake_server());
+        let url = format!("sqlite:{}?mode=rwc", dir.path().join("test.db").display());
+        let db = Arc::new(
+            burncloud_database::create_database_with_url(&url)
+                .await
+                .unwrap(),
+        );
+
+        let service = Arc::new(InferenceService::new());
+        let model_id = "test/supervised-model".to_string();
+        service
+            .start_instance(
+                &db,
+                InferenceConfig {
+                    model_id: model_id.clone(),
+                    file_path: dir.path().join("model.gguf").display().to_string(),
+                    port: health_endpoint().await,
+                    context_size: 512,
+                    gpu_layers: 0,
+                },
+            )
+            .await
+            .unwrap();
+        let channel_id = service.managed.lock().await[&model_id].channel_id.unwrap();
+        Fixture {
+            service,
+            db,
+            dir,
+            model_id,
+            channel_id,
+        }
+    }
+
+    fn test_config(idle_timeout: Option<Duration>) -> SupervisorConfig {
+        SupervisorConfig {
+            check_interval: Duration::from_millis(10),
+            idle_timeout,
+            max_restarts: 3,
+            backoff_base: Duration::from_millis(10),
+            backoff_max: Duration::from_secs(60),
+        }
+    }
+
+    async fn wait_for_status(service: &InferenceService, model_id: &str, status: InstanceStatus) {
+        for _ in 0..200 {
+            if service.get_status(model_id).await == status {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(25)).await;
+        }
+        panic!(
+            "{} never became {:?}, still {:?}",
+            model_id,
+            status,
+            service.get_status(model_id).await
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_crashed_instance_is_restarted() {
+        let f = running_instance().await;
+        let config = test_config(None);
+
+        let mut child = f
+            .service
+            .processes
+            .lock()
+            .await
+            .remove(&f.model_id)
+            .unwrap();
+        child.kill().await.unwrap();
+        f.service
+            .processes
+            .lock()
+            .await
+            .insert(f.model_id.clone(), child);
+
+        f.service.supervise(&f.db, &config).await;
+        assert!(matches!(
+            f.service.get_status(&f.model_id).await,
+            InstanceStatus::Failed(_)
+        ));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        f.service.supervise(&f.db, &config).await;
+
+        wait_for_status(&f.service, &f.model_id, InstanceStatus::Running).await;
+        assert_eq!(launches(f.dir.path()), 2);
+        let managed = f.service.managed.lock().await;
+        assert_eq!(managed[&f.model_id].restarts, 1);
+        assert_eq!(managed[&f.model_id].channel_id, Some(f.channel_id));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_idle_unload_waits_for_in_flight_requests() {
+        let f = running_instance().await;
+        let config = test_config(Some(Duration::ZERO));
+
+        let lease = f
+            .service
+            .ensure_ready(&f.db, f.channel_id)
+            .await
+            .unwrap()
+            .unwrap();
+        f.service.supervise(&f.db, &config).await;
+        assert_eq!(
+            f.service.get_status(&f.model_id).await,
+            InstanceStatus::Running
+        );
+
+        drop(lease);
+        f.service.supervise(&f.db, &config).await;
+        assert_eq!(
+            f.service.get_status(&f.model_id).await,
+            InstanceStatus::Unloaded
+        );
+        assert!(!f.service.processes.lock().await.contains_key(&f.model_id));
+
+        // 未受监督的渠道直接放行
+        assert!(f
+            .service
+            .ensure_ready(&f.db, f.channel_id + 1000)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_concurrent_cold_starts_launch_once() {
+        let f = running_instance().await;
+        f.service
+            .unload(&f.db, &f.model_id, Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(
+            f.service.get_status(&f.model_id).await,
+            InstanceStatus::Unloaded
+        );
+
+        let requests: Vec<_> = (0..8)
+            .map(|_| {
+                let (service, db) = (f.service.clone(), f.db.clone());
+                let channel_id = f.channel_id;
+                tokio::spawn(async move { service.ensure_ready(&db, channel_id).await })
+            })
+            .collect();
+        let mut leases = Vec::new();
+        for request in requests {
+            leases.push(request.await.unwrap().unwrap().unwrap());
+        }
+
+        assert_eq!(
+            f.service.get_status(&f.model_id).await,
+            InstanceStatus::Running
+        );
+        assert_eq!(launches(f.dir.path()), 2);
+
+        // 仍有请求占用时不会被卸载
+        f.service
+            .unload(&f.db, &f.model_id, Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(
+            f.service.get_status(&f.model_id).await,
+            InstanceStatus::Running
+        );
+        drop(leases);
+    }
+}
@@ -1,8 +1,10 @@
 #![allow(clippy::unwrap_used)]
 
 use burncloud_database::create_default_database;
+use burncloud_database_channel::ChannelProviderModel;
 use burncloud_database_router::RouterDatabase;
 use burncloud_service_inference::{InferenceConfig, InferenceService, InstanceStatus};
+use burncloud_service_models::ModelInstanceModel;
 use std::env;
 use std::path::PathBuf;
 use std::time::Duration;
@@ -57,14 +59,22 @@ async fn test_inference_lifecycle_and_db_registration() -> anyhow::Result<()> {
 
     // 4. 验证数据库注册 (Task 11.2 Key Validation)
     println!(">>> Verifying Database Registration...");
-    let upstream_id = format!("local-{}", model_id);
-    let upstream = RouterDatabase::get_upstream(&db, &upstream_id).await?;
-
-    assert!(upstream.is_some(), "Upstream should be registered in DB");
-    let u = upstream.unwrap_or_else(|| panic!("upstream should exist after is_some() check"));
-    assert_eq!(u.base_url, format!("http://127.0.0.1:{}", port));
-    assert_eq!(u.match_path, "/v1/chat/completions");
-    println!(">>> Database registration verified: {:?}", u);
+    let instance = ModelInstanceModel::get(&db, model_id).await?;
+    assert!(instance.is_some(), "Instance should be recorded in DB");
+    let instance =
+        instance.unwrap_or_else(|| panic!("instance should exist after is_some() check"));
+    assert_eq!(instance.status, "running");
+    assert_eq!(instance.port, i32::from(port));
+
+    let channel_id = instance
+        .channel_id
+        .unwrap_or_else(|| panic!("running instance should have a channel"));
+    let channel = ChannelProviderModel::get_by_id(&db, channel_id).await?;
+    assert!(channel.is_some(), "Channel should be registered in DB");
+    let c = channel.unwrap_or_else(|| panic!("channel should exist after is_some() check"));
+    assert_eq!(c.base_url, Some(format!("http://127.0.0.1:{}", port)));
+    assert_eq!(c.models, model_id);
+    println!(">>> Database registration verified: {}", c.name);
 
     // 稍微等待一下，模拟运行
     sleep(Duration::from_millis(500)).await;
@@ -83,10 +93,15 @@ async fn test_inference_lifecycle_and_db_registration() -> anyhow::Result<()> {
 
     // 6. 验证数据库清理 (Task 11.2 Key Validation)
     println!(">>> Verifying Database Cleanup...");
-    let upstream_after = RouterDatabase::get_upstream(&db, &upstream_id).await?;
+    let channel_after = ChannelProviderModel::get_by_id(&db, channel_id).await?;
     assert!(
-        upstream_after.is_none(),
-        "Upstream should be removed from DB after stop"
+        channel_after.is_none(),
+        "Channel should be removed from DB after stop"
+    );
+    let instance_after = ModelInstanceModel::get(&db, model_id).await?;
+    assert_eq!(
+        instance_after.map(|i| i.status),
+        Some("stopped".to_string())
     );
     println!(">>> Database cleanup verified.");
 
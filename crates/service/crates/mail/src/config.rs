@@ -0,0 +1,188 @@
+//! Mail transport and delivery settings read from the environment.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How the SMTP connection is secured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpSecurity {
+    /// Plain connection upgraded with STARTTLS (submission, port 587)
+    StartTls,
+    /// TLS from the first byte (SMTPS, port 465)
+    Tls,
+    /// No encryption, for local relays only
+    None,
+}
+
+impl SmtpSecurity {
+    pub fn default_port(self) -> u16 {
+        match self {
+            SmtpSecurity::StartTls => 587,
+            SmtpSecurity::Tls => 465,
+            SmtpSecurity::None => 25,
+        }
+    }
+}
+
+/// SMTP relay settings
+#[derive(Debug, Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub timeout: Duration,
+}
+
+/// Where messages go
+#[derive(Debug, Clone)]
+pub enum TransportConfig {
+    Smtp(SmtpConfig),
+    /// Write each message as an `.eml` file into a directory
+    File(PathBuf),
+    /// Mail is not configured: messages stay queued and nothing is sent
+    Disabled,
+}
+
+/// Complete mail configuration
+#[derive(Debug, Clone)]
+pub struct MailConfig {
+    pub transport: TransportConfig,
+    /// `From` header, e.g. `BurnCloud <noreply@example.com>`
+    pub from: String,
+    /// Attempts before a message is marked `failed`
+    pub max_attempts: u32,
+    /// First retry delay, doubled after every failed attempt
+    pub retry_base: Duration,
+    /// Longest delay between two attempts
+    pub retry_max: Duration,
+    /// How often the worker looks for due messages, `None` disables it
+    pub poll_interval: Option<Duration>,
+    /// Messages claimed per worker pass
+    pub batch_size: i64,
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self {
+            transport: TransportConfig::Disabled,
+            from: "BurnCloud <noreply@localhost>".to_string(),
+            max_attempts: 8,
+            retry_base: Duration::from_secs(60),
+            retry_max: Duration::from_secs(6 * 3600),
+            poll_interval: Some(Duration::from_secs(15)),
+            batch_size: 50,
+        }
+    }
+}
+
+fn env_var(key: &str) -> Option<String> {
+    std::env::var(key)
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+fn env_u64(key: &str) -> Option<u64> {
+    env_var(key).and_then(|v| v.parse().ok())
+}
+
+impl MailConfig {
+    /// Read the configuration from the environment, falling back to the defaults.
+    ///
+    /// - `MAIL_TRANSPORT`: `smtp`, `file` or `off` (default `smtp` when `SMTP_HOST` is set)
+    /// - `SMTP_HOST`, `SMTP_PORT`, `SMTP_USERNAME`, `SMTP_PASSWORD`
+    /// - `SMTP_SECURITY`: `starttls` (default), `tls` or `none`
+    /// - `MAIL_FILE_DIR`: target directory of the `file` transport (default `mail/`)
+    /// - `MAIL_FROM`: sender address
+    /// - `MAIL_MAX_ATTEMPTS` (default 8), `MAIL_RETRY_BASE_SECS` (default 60)
+    /// - `MAIL_POLL_INTERVAL_SECS`: outbox worker interval, `0` disables it (default 15)
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        let kind = env_var("MAIL_TRANSPORT")
+            .map(|v| v.to_ascii_lowercase())
+            .unwrap_or_else(|| {
+                if env_var("SMTP_HOST").is_some() {
+                    "smtp".to_string()
+                } else {
+                    "off".to_string()
+                }
+            });
+        config.transport = match kind.as_str() {
+            "smtp" => match env_var("SMTP_HOST") {
+                Some(host) => {
+                    let security = match env_var("SMTP_SECURITY")
+                        .map(|v| v.to_ascii_lowercase())
+                        .as_deref()
+                    {
+                        Some("tls") | Some("ssl") | Some("smtps") => SmtpSecurity::Tls,
+                        Some("none") | Some("plain") => SmtpSecurity::None,
+                        _ => SmtpSecurity::StartTls,
+                    };
+                    TransportConfig::Smtp(SmtpConfig {
+                        host,
+                        port: env_u64("SMTP_PORT")
+                            .and_then(|p| u16::try_from(p).ok())
+                            .unwrap_or_else(|| security.default_port()),
+                        security,
+                        username: env_var("SMTP_USERNAME"),
+                        password: env_var("SMTP_PASSWORD"),
+                        timeout: Duration::from_secs(30),
+                    })
+                }
+                None => {
+                    tracing::warn!("MAIL_TRANSPORT=smtp but SMTP_HOST is not set, mail disabled");
+                    TransportConfig::Disabled
+                }
+            },
+            "file" => TransportConfig::File(
+                env_var("MAIL_FILE_DIR")
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| PathBuf::from("mail")),
+            ),
+            _ => TransportConfig::Disabled,
+        };
+        if let Some(from) = env_var("MAIL_FROM") {
+            config.from = from;
+        }
+        if let Some(n) = env_u64("MAIL_MAX_ATTEMPTS").filter(|n| *n > 0) {
+            config.max_attempts = u32::try_from(n).unwrap_or(u32::MAX);
+        }
+        if let Some(secs) = env_u64("MAIL_RETRY_BASE_SECS").filter(|s| *s > 0) {
+            config.retry_base = Duration::from_secs(secs);
+        }
+        if let Some(secs) = env_u64("MAIL_POLL_INTERVAL_SECS") {
+            config.poll_interval = (secs > 0).then(|| Duration::from_secs(secs));
+        }
+        config
+    }
+
+    /// Delay before the attempt following `attempts` failed ones
+    pub fn retry_delay(&self, attempts: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+        self.retry_base
+            .checked_mul(factor)
+            .map_or(self.retry_max, |d| d.min(self.retry_max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delay_doubles_up_to_the_cap() {
+        let config = MailConfig::default();
+        assert_eq!(config.retry_delay(1), Duration::from_secs(60));
+        assert_eq!(config.retry_delay(2), Duration::from_secs(120));
+        assert_eq!(config.retry_delay(4), Duration::from_secs(480));
+        assert_eq!(config.retry_delay(40), config.retry_max);
+    }
+
+    #[test]
+    fn security_picks_the_conventional_port() {
+        assert_eq!(SmtpSecurity::StartTls.default_port(), 587);
+        assert_eq!(SmtpSecurity::Tls.default_port(), 465);
+    }
+}
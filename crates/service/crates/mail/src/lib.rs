@@ -0,0 +1,229 @@
+//! # BurnCloud Service Mail
+//!
+//! Outbound email. Messages are rendered from [`MailTemplate`]s and written
+//! to the `sys_mail_outbox` table first; a background worker delivers due
+//! messages through SMTP or a file-drop directory and retries failures with
+//! exponential backoff, so queued mail survives restarts.
+//!
+//! - [`config`] - transport and retry settings (`MailConfig::from_env`)
+//! - [`template`] - password reset, low balance, key quota, subscription expiry,
+//!   invoice and alert messages
+//! - [`transport`] - SMTP (STARTTLS, implicit TLS, plain) and file backends
+
+pub mod config;
+pub mod template;
+pub mod transport;
+
+use burncloud_database::Database;
+use burncloud_database_sys::mail_outbox::{MailOutboxModel, NewMail, SysMailOutbox};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::Message;
+use std::sync::Arc;
+
+pub use config::{MailConfig, SmtpConfig, SmtpSecurity, TransportConfig};
+pub use template::{MailTemplate, RenderedMail};
+pub use transport::MailTransport;
+
+/// Messages are leased for this long while a worker is sending them
+const SEND_LEASE_SECS: i64 = 300;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MailError {
+    #[error("Invalid email address: {0}")]
+    Address(String),
+
+    #[error("Failed to build message: {0}")]
+    Build(String),
+
+    #[error("Mail transport error: {0}")]
+    Transport(String),
+
+    #[error("Mail is not configured")]
+    Disabled,
+
+    #[error("Database error: {0}")]
+    Database(#[from] burncloud_database::DatabaseError),
+}
+
+pub type Result<T> = std::result::Result<T, MailError>;
+
+/// Outcome of one outbox pass
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DeliveryReport {
+    pub sent: usize,
+    /// Failed attempts that will be retried
+    pub retried: usize,
+    /// Messages that ran out of attempts
+    pub failed: usize,
+}
+
+/// Queues and delivers mail
+pub struct MailService {
+    config: MailConfig,
+    transport: Option<MailTransport>,
+}
+
+impl MailService {
+    pub fn new(config: MailConfig) -> Result<Self> {
+        let transport = MailTransport::from_config(&config.transport)?;
+        Ok(Self { config, transport })
+    }
+
+    /// Build from the environment. A broken configuration is logged and
+    /// leaves mail disabled rather than failing startup.
+    pub fn from_env() -> Self {
+        let config = MailConfig::from_env();
+        match MailTransport::from_config(&config.transport) {
+            Ok(transport) => Self { config, transport },
+            Err(e) => {
+                tracing::error!("Mail transport unavailable, outgoing mail stays queued: {e}");
+                Self {
+                    config,
+                    transport: None,
+                }
+            }
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.transport.is_some()
+    }
+
+    pub fn config(&self) -> &MailConfig {
+        &self.config
+    }
+
+    /// Render `template` and queue it for `recipient`. Works whether or not
+    /// a transport is configured, queued mail is sent once one is.
+    pub async fn enqueue(db: &Database, recipient: &str, template: &MailTemplate) -> Result<i64> {
+        let recipient = recipient.trim();
+        recipient
+            .parse::<Mailbox>()
+            .map_err(|e| MailError::Address(format!("{recipient}: {e}")))?;
+        let rendered = template.render();
+        let id = MailOutboxModel::enqueue(
+            db,
+            &NewMail {
+                recipient: recipient.to_string(),
+                subject: rendered.subject,
+                body_text: rendered.text,
+                body_html: Some(rendered.html),
+                template: template.name().to_string(),
+            },
+        )
+        .await?;
+        tracing::debug!(id, template = template.name(), "Mail queued");
+        Ok(id)
+    }
+
+    fn build_message(
+        &self,
+        recipient: &str,
+        subject: &str,
+        text: &str,
+        html: Option<&str>,
+    ) -> Result<Message> {
+        let from: Mailbox = self
+            .config
+            .from
+            .parse()
+            .map_err(|e| MailError::Address(format!("MAIL_FROM {}: {e}", self.config.from)))?;
+        let to: Mailbox = recipient
+            .parse()
+            .map_err(|e| MailError::Address(format!("{recipient}: {e}")))?;
+        let builder = Message::builder().from(from).to(to).subject(subject);
+        let message = match html {
+            Some(html) => builder.multipart(MultiPart::alternative_plain_html(
+                text.to_string(),
+                html.to_string(),
+            )),
+            None => builder.body(text.to_string()),
+        };
+        message.map_err(|e| MailError::Build(e.to_string()))
+    }
+
+    /// Send immediately without the outbox, for messages that are not worth
+    /// retrying later (operational alerts).
+    pub async fn send_now(&self, recipient: &str, mail: &RenderedMail) -> Result<()> {
+        let transport = self.transport.as_ref().ok_or(MailError::Disabled)?;
+        let message = self.build_message(recipient, &mail.subject, &mail.text, Some(&mail.html))?;
+        transport.send(message).await
+    }
+
+    async fn deliver(&self, transport: &MailTransport, mail: &SysMailOutbox) -> Result<()> {
+        let message = self.build_message(
+            &mail.recipient,
+            &mail.subject,
+            &mail.body_text,
+            mail.body_html.as_deref(),
+        )?;
+        transport.send(message).await
+    }
+
+    /// Deliver every due message once
+    pub async fn deliver_due(&self, db: &Database) -> Result<DeliveryReport> {
+        let transport = self.transport.as_ref().ok_or(MailError::Disabled)?;
+        let mut report = DeliveryReport::default();
+        let due = MailOutboxModel::claim_due(db, self.config.batch_size, SEND_LEASE_SECS).await?;
+        for mail in due {
+            match self.deliver(transport, &mail).await {
+                Ok(()) => {
+                    MailOutboxModel::mark_sent(db, mail.id).await?;
+                    report.sent += 1;
+                }
+                Err(e) => {
+                    let attempts = u32::try_from(mail.attempts).unwrap_or(0) + 1;
+                    // Bad addresses never start working, give up right away
+                    let permanent = matches!(e, MailError::Address(_) | MailError::Build(_));
+                    let retry_at = (!permanent && attempts < self.config.max_attempts).then(|| {
+                        chrono::Utc::now().timestamp()
+                            + i64::try_from(self.config.retry_delay(attempts).as_secs())
+                                .unwrap_or(i64::MAX / 2)
+                    });
+                    tracing::warn!(
+                        id = mail.id,
+                        attempts,
+                        transport = transport.name(),
+                        will_retry = retry_at.is_some(),
+                        "Mail delivery failed: {e}"
+                    );
+                    MailOutboxModel::mark_attempt_failed(db, mail.id, &e.to_string(), retry_at)
+                        .await?;
+                    if retry_at.is_some() {
+                        report.retried += 1;
+                    } else {
+                        report.failed += 1;
+                    }
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Start the outbox worker. Returns `None` when mail is disabled or
+    /// `MAIL_POLL_INTERVAL_SECS` is `0`.
+    pub fn start(self: Arc<Self>, db: Arc<Database>) -> Option<tokio::task::JoinHandle<()>> {
+        let interval = self.config.poll_interval?;
+        if !self.is_enabled() {
+            tracing::info!("Mail transport not configured, outgoing mail stays queued");
+            return None;
+        }
+        Some(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                match self.deliver_due(&db).await {
+                    Ok(report) if report != DeliveryReport::default() => tracing::info!(
+                        sent = report.sent,
+                        retried = report.retried,
+                        failed = report.failed,
+                        "Mail outbox pass finished"
+                    ),
+                    Ok(_) => {}
+                    Err(e) => tracing::error!("Mail outbox pass failed: {e}"),
+                }
+            }
+        }))
+    }
+}
@@ -0,0 +1,256 @@
+//! Built-in message templates. Every template renders a plain-text body and
+//! an HTML alternative; values are HTML-escaped in the latter.
+
+/// A message ready to be queued
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedMail {
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+}
+
+/// Transactional and notification messages
+#[derive(Debug, Clone)]
+pub enum MailTemplate {
+    PasswordReset {
+        username: String,
+        reset_url: String,
+        expires_minutes: i64,
+    },
+    LowBalance {
+        username: String,
+        /// Formatted amount, e.g. `$1.20`
+        balance: String,
+        threshold: String,
+        topup_url: String,
+    },
+    /// An API key is close to its spend quota
+    QuotaLow {
+        username: String,
+        /// Name or hint of the key
+        key: String,
+        /// Formatted quota left, e.g. `$0.40 of $10.00`
+        remaining: String,
+        threshold: String,
+        keys_url: String,
+    },
+    SubscriptionExpiry {
+        username: String,
+        plan: String,
+        /// Human readable date
+        expires_at: String,
+        renew_url: String,
+    },
+    Invoice {
+        username: String,
+        invoice_id: String,
+        period: String,
+        amount: String,
+        invoice_url: String,
+    },
+    /// Operational alert for administrators
+    Alert {
+        level: String,
+        title: String,
+        message: String,
+    },
+}
+
+const PRODUCT: &str = "BurnCloud";
+
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Wrap paragraphs and an optional call-to-action in the shared HTML layout
+fn html_layout(paragraphs: &[String], action: Option<(&str, &str)>) -> String {
+    let mut body = String::new();
+    for p in paragraphs {
+        body.push_str(&format!("<p>{}</p>\n", escape(p)));
+    }
+    if let Some((label, url)) = action {
+        body.push_str(&format!(
+            "<p><a href=\"{}\" style=\"display:inline-block;padding:10px 18px;\
+             background:#2563eb;color:#ffffff;text-decoration:none;border-radius:6px\">{}</a></p>\n",
+            escape(url),
+            escape(label)
+        ));
+    }
+    format!(
+        "<!DOCTYPE html>\n<html><body style=\"font-family:sans-serif;color:#111827\">\n{body}\
+         <p style=\"color:#6b7280;font-size:12px\">{PRODUCT}</p>\n</body></html>\n"
+    )
+}
+
+fn text_layout(paragraphs: &[String], action: Option<(&str, &str)>) -> String {
+    let mut text = paragraphs.join("\n\n");
+    if let Some((label, url)) = action {
+        text.push_str(&format!("\n\n{label}: {url}"));
+    }
+    text.push_str(&format!("\n\n-- \n{PRODUCT}\n"));
+    text
+}
+
+impl MailTemplate {
+    /// Stable name stored with queued messages
+    pub fn name(&self) -> &'static str {
+        match self {
+            MailTemplate::PasswordReset { .. } => "password_reset",
+            MailTemplate::LowBalance { .. } => "low_balance",
+            MailTemplate::QuotaLow { .. } => "quota_low",
+            MailTemplate::SubscriptionExpiry { .. } => "subscription_expiry",
+            MailTemplate::Invoice { .. } => "invoice",
+            MailTemplate::Alert { .. } => "alert",
+        }
+    }
+
+    pub fn render(&self) -> RenderedMail {
+        let (subject, paragraphs, action): (String, Vec<String>, Option<(&str, &str)>) = match self
+        {
+            MailTemplate::PasswordReset {
+                username,
+                reset_url,
+                expires_minutes,
+            } => (
+                format!("Reset your {PRODUCT} password"),
+                vec![
+                    format!("Hi {username},"),
+                    "We received a request to reset the password of your account. \
+                         Use the link below to choose a new one."
+                        .to_string(),
+                    format!(
+                        "The link expires in {expires_minutes} minutes. If you did not \
+                             ask for a reset you can ignore this email."
+                    ),
+                ],
+                Some(("Reset password", reset_url.as_str())),
+            ),
+            MailTemplate::LowBalance {
+                username,
+                balance,
+                threshold,
+                topup_url,
+            } => (
+                format!("Your {PRODUCT} balance is running low"),
+                vec![
+                    format!("Hi {username},"),
+                    format!(
+                        "Your balance is {balance}, below your alert threshold of {threshold}."
+                    ),
+                    "Requests will be rejected once the balance is used up. \
+                         Top up to keep your API keys working."
+                        .to_string(),
+                ],
+                Some(("Top up", topup_url.as_str())),
+            ),
+            MailTemplate::QuotaLow {
+                username,
+                key,
+                remaining,
+                threshold,
+                keys_url,
+            } => (
+                format!("Your {PRODUCT} API key {key} is running out of quota"),
+                vec![
+                    format!("Hi {username},"),
+                    format!(
+                        "The API key {key} has {remaining} quota left, below your alert \
+                         threshold of {threshold}."
+                    ),
+                    "Requests made with the key will be rejected once its quota is used up."
+                        .to_string(),
+                ],
+                Some(("Manage API keys", keys_url.as_str())),
+            ),
+            MailTemplate::SubscriptionExpiry {
+                username,
+                plan,
+                expires_at,
+                renew_url,
+            } => (
+                format!("Your {PRODUCT} {plan} subscription expires soon"),
+                vec![
+                    format!("Hi {username},"),
+                    format!("Your {plan} subscription expires on {expires_at}."),
+                    "Renew before then to keep your plan's limits and pricing.".to_string(),
+                ],
+                Some(("Renew subscription", renew_url.as_str())),
+            ),
+            MailTemplate::Invoice {
+                username,
+                invoice_id,
+                period,
+                amount,
+                invoice_url,
+            } => (
+                format!("{PRODUCT} invoice {invoice_id}"),
+                vec![
+                    format!("Hi {username},"),
+                    format!("Your invoice {invoice_id} for {period} is ready."),
+                    format!("Amount due: {amount}"),
+                ],
+                Some(("View invoice", invoice_url.as_str())),
+            ),
+            MailTemplate::Alert {
+                level,
+                title,
+                message,
+            } => (
+                format!("[{PRODUCT} {level}] {title}"),
+                vec![message.clone()],
+                None,
+            ),
+        };
+        RenderedMail {
+            subject,
+            text: text_layout(&paragraphs, action),
+            html: html_layout(&paragraphs, action),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn password_reset_contains_the_link_in_both_bodies() {
+        let mail = MailTemplate::PasswordReset {
+            username: "alice".to_string(),
+            reset_url: "https://cloud.example/reset?token=abc&x=1".to_string(),
+            expires_minutes: 60,
+        }
+        .render();
+        assert_eq!(mail.subject, "Reset your BurnCloud password");
+        assert!(mail
+            .text
+            .contains("Reset password: https://cloud.example/reset?token=abc&x=1"));
+        assert!(mail
+            .html
+            .contains("href=\"https://cloud.example/reset?token=abc&amp;x=1\""));
+    }
+
+    #[test]
+    fn user_values_are_escaped_in_html() {
+        let mail = MailTemplate::Alert {
+            level: "CRITICAL".to_string(),
+            title: "Channel down".to_string(),
+            message: "<script>alert(1)</script>".to_string(),
+        }
+        .render();
+        assert!(mail.html.contains("&lt;script&gt;"));
+        assert!(!mail.html.contains("<script>"));
+        assert!(mail.text.contains("<script>"));
+    }
+}
@@ -0,0 +1,67 @@
+//! Delivery backends built on `lettre`.
+
+use crate::config::{SmtpSecurity, TransportConfig};
+use crate::MailError;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncFileTransport, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+/// A ready-to-use transport
+pub enum MailTransport {
+    Smtp(AsyncSmtpTransport<Tokio1Executor>),
+    File(AsyncFileTransport<Tokio1Executor>),
+}
+
+impl MailTransport {
+    /// Build the transport, `None` when mail is disabled
+    pub fn from_config(config: &TransportConfig) -> Result<Option<Self>, MailError> {
+        let transport = match config {
+            TransportConfig::Disabled => return Ok(None),
+            TransportConfig::File(dir) => {
+                std::fs::create_dir_all(dir).map_err(|e| {
+                    MailError::Transport(format!("cannot create {}: {e}", dir.display()))
+                })?;
+                MailTransport::File(AsyncFileTransport::new(dir))
+            }
+            TransportConfig::Smtp(smtp) => {
+                let builder = match smtp.security {
+                    SmtpSecurity::StartTls => {
+                        AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&smtp.host)
+                    }
+                    SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&smtp.host),
+                    SmtpSecurity::None => Ok(
+                        AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&smtp.host),
+                    ),
+                }
+                .map_err(|e| MailError::Transport(e.to_string()))?;
+                let mut builder = builder.port(smtp.port).timeout(Some(smtp.timeout));
+                if let (Some(user), Some(password)) = (&smtp.username, &smtp.password) {
+                    builder = builder.credentials(Credentials::new(user.clone(), password.clone()));
+                }
+                MailTransport::Smtp(builder.build())
+            }
+        };
+        Ok(Some(transport))
+    }
+
+    pub async fn send(&self, message: Message) -> Result<(), MailError> {
+        match self {
+            MailTransport::Smtp(smtp) => smtp
+                .send(message)
+                .await
+                .map(|_| ())
+                .map_err(|e| MailError::Transport(e.to_string())),
+            MailTransport::File(file) => file
+                .send(message)
+                .await
+                .map(|_| ())
+                .map_err(|e| MailError::Transport(e.to_string())),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MailTransport::Smtp(_) => "smtp",
+            MailTransport::File(_) => "file",
+        }
+    }
+}
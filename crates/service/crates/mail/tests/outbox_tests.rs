@@ -0,0 +1,138 @@
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+//! Outbox delivery through the file-drop transport, and retry/give-up
+//! behaviour when the SMTP relay is unreachable.
+
+use burncloud_database::{create_database_with_url, Database};
+use burncloud_database_sys::mail_outbox::{MailOutboxModel, MAIL_FAILED, MAIL_PENDING, MAIL_SENT};
+use burncloud_service_mail::{
+    DeliveryReport, MailConfig, MailService, MailTemplate, SmtpConfig, SmtpSecurity,
+    TransportConfig,
+};
+use std::time::Duration;
+use tempfile::{NamedTempFile, TempDir};
+
+async fn create_test_db() -> (Database, NamedTempFile) {
+    let tmp = NamedTempFile::new().unwrap_or_else(|e| panic!("failed to create temp file: {e}"));
+    let url = format!("sqlite://{}?mode=rwc", tmp.path().display());
+    let db = create_database_with_url(&url)
+        .await
+        .unwrap_or_else(|e| panic!("failed to initialize test database: {e}"));
+    (db, tmp)
+}
+
+fn reset_mail() -> MailTemplate {
+    MailTemplate::PasswordReset {
+        username: "alice".to_string(),
+        reset_url: "https://cloud.example/reset?token=t0k3n".to_string(),
+        expires_minutes: 60,
+    }
+}
+
+#[tokio::test]
+async fn queued_mail_is_written_by_the_file_transport() {
+    let (db, _tmp) = create_test_db().await;
+    let dir = TempDir::new().unwrap();
+    let service = MailService::new(MailConfig {
+        transport: TransportConfig::File(dir.path().to_path_buf()),
+        from: "BurnCloud <noreply@cloud.example>".to_string(),
+        ..MailConfig::default()
+    })
+    .unwrap();
+
+    let id = MailService::enqueue(&db, "alice@example.com", &reset_mail())
+        .await
+        .unwrap();
+    assert!(MailService::enqueue(&db, "not an address", &reset_mail())
+        .await
+        .is_err());
+
+    let report = service.deliver_due(&db).await.unwrap();
+    assert_eq!(
+        report,
+        DeliveryReport {
+            sent: 1,
+            ..Default::default()
+        }
+    );
+    let mail = MailOutboxModel::get(&db, id).await.unwrap().unwrap();
+    assert_eq!(mail.status, MAIL_SENT);
+    assert_eq!(mail.template, "password_reset");
+    assert!(mail.sent_at.is_some());
+    // The reset link is not kept once delivered
+    assert!(!mail.body_text.contains("t0k3n"));
+    assert!(mail.body_html.is_none());
+
+    let files: Vec<_> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    assert_eq!(files.len(), 1);
+    let eml = std::fs::read_to_string(&files[0]).unwrap();
+    assert!(eml.contains("To: alice@example.com"));
+    assert!(eml.contains("Subject: Reset your BurnCloud password"));
+    assert!(eml.contains("t0k3n"));
+
+    // Nothing left to send
+    assert_eq!(
+        service.deliver_due(&db).await.unwrap(),
+        DeliveryReport::default()
+    );
+}
+
+#[tokio::test]
+async fn unreachable_relay_is_retried_then_given_up() {
+    let (db, _tmp) = create_test_db().await;
+    // Bound and immediately dropped, so connections are refused
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let service = MailService::new(MailConfig {
+        transport: TransportConfig::Smtp(SmtpConfig {
+            host: "127.0.0.1".to_string(),
+            port,
+            security: SmtpSecurity::None,
+            username: None,
+            password: None,
+            timeout: Duration::from_secs(2),
+        }),
+        max_attempts: 2,
+        retry_base: Duration::from_secs(60),
+        ..MailConfig::default()
+    })
+    .unwrap();
+
+    let id = MailService::enqueue(&db, "alice@example.com", &reset_mail())
+        .await
+        .unwrap();
+    let report = service.deliver_due(&db).await.unwrap();
+    assert_eq!(report.retried, 1);
+    let mail = MailOutboxModel::get(&db, id).await.unwrap().unwrap();
+    assert_eq!(mail.status, MAIL_PENDING);
+    assert_eq!(mail.attempts, 1);
+    assert!(mail.last_error.is_some());
+    assert!(mail.next_attempt_at > chrono::Utc::now().timestamp() + 30);
+
+    // Not due yet
+    assert_eq!(
+        service.deliver_due(&db).await.unwrap(),
+        DeliveryReport::default()
+    );
+
+    // Make it due again: the second failure exhausts max_attempts
+    db.execute_query("UPDATE sys_mail_outbox SET next_attempt_at = 0")
+        .await
+        .unwrap();
+    let report = service.deliver_due(&db).await.unwrap();
+    assert_eq!(report.failed, 1);
+    let mail = MailOutboxModel::get(&db, id).await.unwrap().unwrap();
+    assert_eq!(mail.status, MAIL_FAILED);
+    assert_eq!(mail.attempts, 2);
+
+    // Operators can requeue it
+    assert!(MailOutboxModel::retry(&db, id).await.unwrap());
+    let mail = MailOutboxModel::get(&db, id).await.unwrap().unwrap();
+    assert_eq!(mail.status, MAIL_PENDING);
+}
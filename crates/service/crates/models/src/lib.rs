@@ -4,7 +4,7 @@
 
 use burncloud_database_model::ModelDatabase;
 use burncloud_service_setting::{SettingDatabase, SettingService};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 type Result<T> = std::result::Result<T, burncloud_database_model::DatabaseError>;
 
@@ -36,6 +36,29 @@ pub struct HfFileItem {
     pub oid: String,
     pub size: i64,
     pub path: String,
+    /// LFS 存储的大文件（如 GGUF）才有
+    #[serde(default)]
+    pub lfs: Option<HfLfsInfo>,
+}
+
+impl HfFileItem {
+    /// [`get_model_files`] 返回的一行
+    pub fn into_row(self) -> Vec<String> {
+        vec![
+            self.file_type,
+            self.oid,
+            self.size.to_string(),
+            self.path,
+            self.lfs.map(|lfs| lfs.oid).unwrap_or_default(),
+        ]
+    }
+}
+
+/// LFS 指针信息，`oid` 是文件内容的 SHA-256
+#[derive(Debug, Clone, Deserialize)]
+pub struct HfLfsInfo {
+    pub oid: String,
+    pub size: i64,
 }
 
 /// 模型服务
@@ -184,15 +207,20 @@ pub async fn get_huggingface_host() -> std::result::Result<String, Box<dyn std::
 }
 
 /// 获取模型的所有文件列表（递归遍历）
+///
+/// 每行依次为类型、git oid、大小、路径、LFS SHA-256（非 LFS 文件为空）。
 pub async fn get_model_files(
     model_id: &str,
 ) -> std::result::Result<Vec<Vec<String>>, Box<dyn std::error::Error>> {
     let host = get_huggingface_host().await?;
     let mut result = Vec::new();
-    fetch_files_recursive(&host, model_id, "main", &mut result).await?;
+    fetch_files_recursive(&host, model_id, "main", &mut result)
+        .await
+        .map_err(|e| -> Box<dyn std::error::Error> { e })?;
     Ok(result)
 }
 
+// 返回 Send 的 future，便于在后台任务（tokio::spawn）中拉取模型
 #[allow(clippy::type_complexity)]
 fn fetch_files_recursive<'a>(
     host: &'a str,
@@ -200,7 +228,12 @@ fn fetch_files_recursive<'a>(
     path: &'a str,
     result: &'a mut Vec<Vec<String>>,
 ) -> std::pin::Pin<
-    Box<dyn std::future::Future<Output = std::result::Result<(), Box<dyn std::error::Error>>> + 'a>,
+    Box<
+        dyn std::future::Future<
+                Output = std::result::Result<(), Box<dyn std::error::Error + Send + Sync>>,
+            > + Send
+            + 'a,
+    >,
 > {
     Box::pin(async move {
         let url = format!("{}api/models/{}/tree/{}", host, model_id, path);
@@ -209,12 +242,7 @@ fn fetch_files_recursive<'a>(
 
         for item in items {
             if item.file_type == "file" {
-                result.push(vec![
-                    item.file_type.clone(),
-                    item.oid.clone(),
-                    item.size.to_string(),
-                    item.path.clone(),
-                ]);
+                result.push(item.into_row());
             } else if item.file_type == "directory" {
                 let sub_path = format!("{}/{}", path, item.path);
                 fetch_files_recursive(host, model_id, &sub_path, result).await?;
@@ -274,5 +302,118 @@ pub async fn download_model_file(
     Ok(gid)
 }
 
+/// 按量化名称（如 `Q4_K_M`）或完整路径，从 GGUF 文件列表中选出一个文件路径
+///
+/// 完整路径优先；否则按文件名不区分大小写地包含匹配，多个候选时取路径排序后的第一个。
+pub fn select_gguf_file(files: &[Vec<String>], selector: &str) -> Option<String> {
+    let ggufs = filter_gguf_files(files);
+    if let Some(exact) = ggufs.iter().find(|f| f[3] == selector) {
+        return Some(exact[3].clone());
+    }
+
+    let needle = selector.to_lowercase();
+    let mut matches: Vec<String> = ggufs
+        .iter()
+        .filter(|f| f[3].to_lowercase().contains(&needle))
+        .map(|f| f[3].clone())
+        .collect();
+    matches.sort();
+    matches.into_iter().next()
+}
+
+/// 文件列表中 `path` 的 LFS SHA-256，即下载内容应有的摘要
+///
+/// 行首的 git oid 是 LFS 指针文件的 SHA-1，不能用来校验下载内容。
+pub fn lfs_sha256(files: &[Vec<String>], path: &str) -> Option<String> {
+    files
+        .iter()
+        .find(|f| f.get(3).is_some_and(|p| p == path))
+        .and_then(|f| f.get(4))
+        .filter(|sha| !sha.is_empty())
+        .cloned()
+}
+
+/// 下载完成后模型文件在本地的路径：`<dir_data>/<model_id>/<文件名>`
+pub async fn local_model_path(
+    model_id: &str,
+    path: &str,
+) -> std::result::Result<std::path::PathBuf, Box<dyn std::error::Error>> {
+    let base_dir = get_data_dir().await?;
+    let filename = path.rsplit('/').next().unwrap_or(path);
+    Ok(std::path::Path::new(&base_dir)
+        .join(model_id)
+        .join(filename))
+}
+
+/// 下载进度快照
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PullProgress {
+    pub gid: String,
+    /// aria2 状态：active / waiting / paused / complete / error / removed
+    pub status: String,
+    pub total_bytes: i64,
+    pub completed_bytes: i64,
+    /// 字节/秒
+    pub download_speed: i64,
+}
+
+impl PullProgress {
+    /// 完成百分比（总大小未知时为 0）
+    pub fn percent(&self) -> f64 {
+        if self.total_bytes <= 0 {
+            0.0
+        } else {
+            self.completed_bytes as f64 * 100.0 / self.total_bytes as f64
+        }
+    }
+}
+
+/// 轮询下载任务直到完成，每次轮询都会回调 `on_progress`
+pub async fn wait_for_download<F>(
+    manager: &burncloud_download::DownloadManager,
+    gid: &str,
+    mut on_progress: F,
+) -> std::result::Result<PullProgress, Box<dyn std::error::Error + Send + Sync>>
+where
+    F: FnMut(&PullProgress),
+{
+    loop {
+        let status = manager.get_status(gid).await?;
+        let progress = PullProgress {
+            gid: gid.to_string(),
+            status: status.status.clone(),
+            total_bytes: status.total_length.parse().unwrap_or(0),
+            completed_bytes: status.completed_length.parse().unwrap_or(0),
+            download_speed: status.download_speed.parse().unwrap_or(0),
+        };
+        on_progress(&progress);
+
+        match progress.status.as_str() {
+            "complete" => return Ok(progress),
+            "error" | "removed" => {
+                return Err(format!("下载失败: {} ({})", gid, progress.status).into())
+            }
+            _ => tokio::time::sleep(std::time::Duration::from_secs(1)).await,
+        }
+    }
+}
+
+/// 计算文件的 SHA-256（小写十六进制）
+pub async fn file_sha256(
+    path: &std::path::Path,
+) -> std::result::Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    use sha2::{Digest, Sha256};
+
+    let path = path.to_path_buf();
+    let digest = tokio::task::spawn_blocking(move || -> std::io::Result<String> {
+        let mut file = std::fs::File::open(&path)?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut file, &mut hasher)?;
+        Ok(format!("{:x}", hasher.finalize()))
+    })
+    .await??;
+    Ok(digest)
+}
+
 /// 重新导出常用类型
-pub use burncloud_database_model::{DatabaseError, ModelInfo};
+pub use burncloud_database_model::{DatabaseError, ModelInfo, ModelInstance, ModelInstanceModel};
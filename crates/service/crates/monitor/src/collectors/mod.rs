@@ -1,7 +1,9 @@
 pub mod cpu;
 pub mod disk;
 pub mod memory;
+pub mod process;
 
 pub use cpu::CpuCollector;
 pub use disk::DiskCollector;
 pub use memory::{DetailedMemoryInfo, MemoryCollector};
+pub use process::ProcessCollector;
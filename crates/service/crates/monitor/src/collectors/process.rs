@@ -0,0 +1,86 @@
+use crate::types::MonitorError;
+
+#[cfg(windows)]
+use winapi::um::{
+    handleapi::CloseHandle,
+    processthreadsapi::OpenProcess,
+    psapi::{GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS},
+    winnt::PROCESS_QUERY_LIMITED_INFORMATION,
+};
+
+#[cfg(unix)]
+use std::fs;
+
+/// 进程数据收集器
+pub struct ProcessCollector;
+
+impl ProcessCollector {
+    /// 创建新的进程收集器
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// 获取进程的常驻内存 (字节)
+    pub async fn memory_usage(&self, pid: u32) -> Result<u64, MonitorError> {
+        #[cfg(windows)]
+        {
+            self.memory_usage_windows(pid)
+        }
+        #[cfg(unix)]
+        {
+            self.memory_usage_unix(pid)
+        }
+    }
+
+    #[cfg(windows)]
+    fn memory_usage_windows(&self, pid: u32) -> Result<u64, MonitorError> {
+        unsafe {
+            let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+            if handle.is_null() {
+                return Err(MonitorError::CollectionFailed(format!(
+                    "Failed to open process {}",
+                    pid
+                )));
+            }
+
+            let mut counters: PROCESS_MEMORY_COUNTERS = std::mem::zeroed();
+            let size = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+            let ok = GetProcessMemoryInfo(handle, &mut counters, size);
+            CloseHandle(handle);
+
+            if ok == 0 {
+                return Err(MonitorError::CollectionFailed(format!(
+                    "Failed to get memory info for process {}",
+                    pid
+                )));
+            }
+
+            Ok(counters.WorkingSetSize as u64)
+        }
+    }
+
+    #[cfg(unix)]
+    fn memory_usage_unix(&self, pid: u32) -> Result<u64, MonitorError> {
+        let status = fs::read_to_string(format!("/proc/{}/status", pid)).map_err(|e| {
+            MonitorError::CollectionFailed(format!("Failed to read /proc/{}/status: {}", pid, e))
+        })?;
+
+        for line in status.lines() {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() >= 2 && parts[0] == "VmRSS:" {
+                let kb = parts[1]
+                    .parse::<u64>()
+                    .map_err(|e| MonitorError::InvalidData(e.to_string()))?;
+                return Ok(kb * 1024);
+            }
+        }
+
+        Err(MonitorError::NotAvailable)
+    }
+}
+
+impl Default for ProcessCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
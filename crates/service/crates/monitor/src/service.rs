@@ -1,7 +1,8 @@
 use crate::{
-    collectors::{CpuCollector, DiskCollector, MemoryCollector},
-    types::{MonitorError, SystemMetrics},
+    collectors::{CpuCollector, DiskCollector, MemoryCollector, ProcessCollector},
+    types::{InstanceMetrics, MonitorError, SystemMetrics},
 };
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::{
     sync::{Mutex, RwLock},
@@ -13,6 +14,9 @@ pub struct SystemMonitorService {
     cpu_collector: Arc<Mutex<CpuCollector>>,
     memory_collector: Arc<MemoryCollector>,
     disk_collector: Arc<DiskCollector>,
+    process_collector: Arc<ProcessCollector>,
+    // 由推理服务上报的本地实例: Map<ModelID, InstanceMetrics>
+    instances: Arc<RwLock<HashMap<String, InstanceMetrics>>>,
     cached_metrics: Arc<RwLock<Option<SystemMetrics>>>,
     update_interval: Duration,
 }
@@ -24,6 +28,8 @@ impl SystemMonitorService {
             cpu_collector: Arc::new(Mutex::new(CpuCollector::new())),
             memory_collector: Arc::new(MemoryCollector::new()),
             disk_collector: Arc::new(DiskCollector::new()),
+            process_collector: Arc::new(ProcessCollector::new()),
+            instances: Arc::new(RwLock::new(HashMap::new())),
             cached_metrics: Arc::new(RwLock::new(None)),
             update_interval: Duration::from_secs(1), // 默认1秒更新间隔
         }
@@ -83,6 +89,8 @@ impl SystemMonitorService {
         let cpu_collector = self.cpu_collector.clone();
         let memory_collector = self.memory_collector.clone();
         let disk_collector = self.disk_collector.clone();
+        let process_collector = self.process_collector.clone();
+        let instances = self.instances.clone();
         let cached_metrics = self.cached_metrics.clone();
         let interval = self.update_interval;
 
@@ -97,6 +105,8 @@ impl SystemMonitorService {
                     &cpu_collector,
                     &memory_collector,
                     &disk_collector,
+                    &process_collector,
+                    &instances,
                 )
                 .await;
 
@@ -118,6 +128,8 @@ impl SystemMonitorService {
             &self.cpu_collector,
             &self.memory_collector,
             &self.disk_collector,
+            &self.process_collector,
+            &self.instances,
         )
         .await
     }
@@ -127,6 +139,8 @@ impl SystemMonitorService {
         cpu_collector: &Arc<Mutex<CpuCollector>>,
         memory_collector: &Arc<MemoryCollector>,
         disk_collector: &Arc<DiskCollector>,
+        process_collector: &Arc<ProcessCollector>,
+        instances: &Arc<RwLock<HashMap<String, InstanceMetrics>>>,
     ) -> Result<SystemMetrics, MonitorError> {
         // 并行收集各项指标
         let (cpu_result, memory_result, disk_result) = tokio::join!(
@@ -142,7 +156,43 @@ impl SystemMonitorService {
         let memory = memory_result?;
         let disks = disk_result?;
 
-        Ok(SystemMetrics::new(cpu, memory, disks))
+        let mut metrics = SystemMetrics::new(cpu, memory, disks);
+        metrics.instances = Self::collect_instances_internal(process_collector, instances).await;
+        Ok(metrics)
+    }
+
+    /// 内部方法：补充各实例进程的内存占用
+    async fn collect_instances_internal(
+        process_collector: &Arc<ProcessCollector>,
+        instances: &Arc<RwLock<HashMap<String, InstanceMetrics>>>,
+    ) -> Vec<InstanceMetrics> {
+        let mut list: Vec<InstanceMetrics> = instances.read().await.values().cloned().collect();
+        for instance in list.iter_mut() {
+            // 进程已退出或无法读取时记为 0
+            instance.memory_bytes = match instance.pid {
+                Some(pid) => process_collector.memory_usage(pid).await.unwrap_or(0),
+                None => 0,
+            };
+        }
+        list.sort_by(|a, b| a.model_id.cmp(&b.model_id));
+        list
+    }
+
+    /// 上报（或更新）一个本地推理实例
+    pub async fn report_instance(&self, instance: InstanceMetrics) {
+        let mut instances = self.instances.write().await;
+        instances.insert(instance.model_id.clone(), instance);
+    }
+
+    /// 移除一个本地推理实例
+    pub async fn remove_instance(&self, model_id: &str) {
+        let mut instances = self.instances.write().await;
+        instances.remove(model_id);
+    }
+
+    /// 获取本地推理实例的状态与内存占用
+    pub async fn get_instance_metrics(&self) -> Vec<InstanceMetrics> {
+        Self::collect_instances_internal(&self.process_collector, &self.instances).await
     }
 
     /// 获取CPU使用率
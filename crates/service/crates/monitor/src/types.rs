@@ -98,6 +98,25 @@ pub struct DiskInfo {
     pub mount_point: String,
 }
 
+/// 本地推理实例信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceMetrics {
+    /// 模型ID
+    pub model_id: String,
+    /// 实例状态 (starting / running / unloaded / failed ...)
+    pub status: String,
+    /// 监听端口
+    pub port: u16,
+    /// 进程ID (进程未运行时为空)
+    pub pid: Option<u32>,
+    /// 常驻内存 (字节)
+    pub memory_bytes: u64,
+    /// 崩溃后的重启次数
+    pub restarts: u32,
+    /// 距离上次请求的秒数
+    pub idle_secs: u64,
+}
+
 /// 系统监控数据
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SystemMetrics {
@@ -107,6 +126,9 @@ pub struct SystemMetrics {
     pub memory: MemoryInfo,
     /// 磁盘信息列表
     pub disks: Vec<DiskInfo>,
+    /// 本地推理实例列表
+    #[serde(default)]
+    pub instances: Vec<InstanceMetrics>,
     /// 数据采集时间戳 (Unix时间戳)
     pub timestamp: u64,
 }
@@ -122,6 +144,7 @@ impl SystemMetrics {
             cpu,
             memory,
             disks,
+            instances: Vec::new(),
             timestamp,
         }
     }
@@ -0,0 +1,678 @@
+//! # BurnCloud Service Notification
+//!
+//! Low-balance and quota alerts. A customer sets thresholds on a wallet
+//! balance or on an API key, either an absolute amount left or a percent of
+//! the key's quota. The router calls [`NotificationService::evaluate`] once a
+//! deduction has settled; a threshold fires the first time the level drops to
+//! or below it and re-arms when the level is back above it, so every crossing
+//! notifies once. A crossing emails the customer, posts a signed webhook to
+//! the URL they configured and, for wallet thresholds, can top the wallet up
+//! from a saved payment method. Webhooks are only delivered to public
+//! addresses, checked when the URL is saved and again on every delivery.
+//!
+//! - [`webhook`] - webhook payloads, their `X-BurnCloud-Signature` and the
+//!   addresses they may be delivered to
+
+pub mod webhook;
+
+use burncloud_common::{Currency, NANO_PER_DOLLAR};
+use burncloud_database::Database;
+use burncloud_database_router::{token_hash, RouterTokenModel, TokenQuota};
+use burncloud_database_user::{
+    UserAccount, UserDatabase, UserNotificationModel, UserNotificationSettings,
+    UserNotificationThreshold, THRESHOLD_ABSOLUTE, THRESHOLD_PERCENT,
+};
+use burncloud_service_mail::{MailService, MailTemplate};
+use burncloud_service_payment::{PaymentError, PaymentService};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use webhook::{WebhookPayload, EVENT_BALANCE_LOW, EVENT_QUOTA_LOW, SIGNATURE_HEADER};
+
+/// Webhook delivery timeout
+const WEBHOOK_TIMEOUT_SECS: u64 = 10;
+
+/// Thresholds one user may keep
+const MAX_THRESHOLDS: usize = 50;
+
+#[derive(Debug, thiserror::Error)]
+pub enum NotificationError {
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("Threshold not found")]
+    NotFound,
+
+    #[error("Database error: {0}")]
+    Database(#[from] burncloud_database::DatabaseError),
+}
+
+pub type Result<T> = std::result::Result<T, NotificationError>;
+
+/// Saved payment method charged when a wallet threshold is crossed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoTopup {
+    pub provider: String,
+    /// Saved method attached to the user's provider customer, e.g. `pm_...`
+    /// for Stripe
+    pub method: String,
+    /// Minor units of `currency`
+    pub amount: i64,
+    pub currency: String,
+}
+
+/// Settings a customer submits
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SettingsUpdate {
+    #[serde(default = "default_true")]
+    pub email_enabled: bool,
+    pub webhook_url: Option<String>,
+    /// Replace the webhook signing secret
+    #[serde(default)]
+    pub rotate_secret: bool,
+    pub auto_topup: Option<AutoTopup>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// A threshold to add. `token_hash` set watches that API key, otherwise the
+/// wallet in `currency`.
+#[derive(Debug, Clone, Default)]
+pub struct NewThreshold {
+    pub token_hash: Option<String>,
+    pub currency: Option<String>,
+    pub kind: String,
+    pub value: i64,
+}
+
+/// Settings and thresholds of a user, without secrets
+#[derive(Debug, Clone, Serialize)]
+pub struct NotificationView {
+    pub settings: UserNotificationSettings,
+    pub thresholds: Vec<UserNotificationThreshold>,
+}
+
+/// Sends threshold notifications and manages their settings
+pub struct NotificationService {
+    payments: Option<Arc<PaymentService>>,
+    topup_url: String,
+    keys_url: String,
+    allow_private_webhooks: bool,
+}
+
+impl NotificationService {
+    /// `payments` enables auto top-up; `base_url` is the console the emails
+    /// link to.
+    pub fn new(payments: Option<Arc<PaymentService>>, base_url: &str) -> Self {
+        let base_url = base_url.trim_end_matches('/');
+        let topup_url = payments
+            .as_ref()
+            .map(|p| p.config().return_url.clone())
+            .unwrap_or_else(|| format!("{base_url}/billing"));
+        Self {
+            payments,
+            topup_url,
+            keys_url: format!("{base_url}/keys"),
+            allow_private_webhooks: false,
+        }
+    }
+
+    /// Also deliver webhooks to loopback and private addresses, for
+    /// receivers on the same host or network. Off by default.
+    pub fn allow_private_webhooks(mut self, allow: bool) -> Self {
+        self.allow_private_webhooks = allow;
+        self
+    }
+
+    /// Build from the environment: `BASE_URL` for links, the payment
+    /// providers of `PaymentService::from_env` for auto top-up and
+    /// `NOTIFICATION_WEBHOOK_ALLOW_PRIVATE=true` for private webhook targets.
+    pub fn from_env() -> Self {
+        let base_url = std::env::var("BASE_URL")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .unwrap_or_else(|| "http://localhost:8080".to_string());
+        let payments = PaymentService::from_env();
+        let payments = (!payments.options().providers.is_empty()).then(|| Arc::new(payments));
+        let allow_private = std::env::var("NOTIFICATION_WEBHOOK_ALLOW_PRIVATE")
+            .is_ok_and(|v| v.trim().eq_ignore_ascii_case("true"));
+        Self::new(payments, &base_url).allow_private_webhooks(allow_private)
+    }
+
+    /// Saved settings, or the email-only defaults
+    pub async fn settings(db: &Database, user_id: &str) -> Result<UserNotificationSettings> {
+        Ok(UserNotificationModel::get_settings(db, user_id)
+            .await?
+            .unwrap_or_else(|| UserNotificationSettings::new(user_id)))
+    }
+
+    pub async fn view(db: &Database, user_id: &str) -> Result<NotificationView> {
+        Ok(NotificationView {
+            settings: Self::settings(db, user_id).await?,
+            thresholds: UserNotificationModel::list_thresholds(db, user_id).await?,
+        })
+    }
+
+    /// Validate and store settings. A webhook URL must resolve to public
+    /// addresses; it gets a signing secret the first time it is set and
+    /// whenever `rotate_secret` is requested.
+    pub async fn save_settings(
+        &self,
+        db: &Database,
+        user_id: &str,
+        update: SettingsUpdate,
+    ) -> Result<UserNotificationSettings> {
+        let mut settings = Self::settings(db, user_id).await?;
+        settings.email_enabled = i32::from(update.email_enabled);
+
+        let webhook_url = update
+            .webhook_url
+            .map(|u| u.trim().to_string())
+            .filter(|u| !u.is_empty());
+        match webhook_url {
+            Some(webhook_url) => {
+                webhook::resolve(&webhook_url, self.allow_private_webhooks).await?;
+                if settings.webhook_secret.is_none() || update.rotate_secret {
+                    settings.webhook_secret = Some(webhook::generate_secret());
+                }
+                settings.webhook_url = Some(webhook_url);
+            }
+            None => {
+                settings.webhook_url = None;
+                settings.webhook_secret = None;
+            }
+        }
+
+        match update.auto_topup {
+            Some(topup) => {
+                let currency = self.validate_auto_topup(db, user_id, &topup).await?;
+                settings.auto_topup_provider = Some(topup.provider);
+                settings.auto_topup_method = Some(topup.method.trim().to_string());
+                settings.auto_topup_amount = Some(topup.amount);
+                settings.auto_topup_currency = Some(currency.code().to_string());
+            }
+            None => {
+                settings.auto_topup_provider = None;
+                settings.auto_topup_method = None;
+                settings.auto_topup_amount = None;
+                settings.auto_topup_currency = None;
+            }
+        }
+
+        UserNotificationModel::save_settings(db, &settings).await?;
+        Self::settings(db, user_id).await
+    }
+
+    async fn validate_auto_topup(
+        &self,
+        db: &Database,
+        user_id: &str,
+        topup: &AutoTopup,
+    ) -> Result<Currency> {
+        let invalid = |msg: String| NotificationError::InvalidInput(msg);
+        let payments = self
+            .payments
+            .as_ref()
+            .ok_or_else(|| invalid("online payments are not enabled".to_string()))?;
+        let options = payments.options();
+        let provider = options
+            .providers
+            .iter()
+            .find(|p| p.name == topup.provider)
+            .ok_or_else(|| invalid(format!("unknown payment provider: {}", topup.provider)))?;
+        if !provider.saved_methods {
+            return Err(invalid(format!(
+                "{} cannot charge saved payment methods",
+                provider.name
+            )));
+        }
+        let currency: Currency = topup.currency.parse().map_err(invalid)?;
+        if !provider.currencies.contains(&currency.code()) {
+            return Err(invalid(format!(
+                "{} does not accept {currency}",
+                provider.name
+            )));
+        }
+        if topup.amount < options.min_amount || topup.amount > options.max_amount {
+            return Err(invalid(format!(
+                "auto top-up amount must be between {} and {} minor units",
+                options.min_amount, options.max_amount
+            )));
+        }
+        if topup.method.trim().is_empty() {
+            return Err(invalid(
+                "auto top-up needs a saved payment method".to_string(),
+            ));
+        }
+        payments
+            .check_saved_method(db, user_id, &topup.provider, topup.method.trim())
+            .await
+            .map_err(|e| match e {
+                PaymentError::InvalidInput(msg) => invalid(msg),
+                e => invalid(e.to_string()),
+            })?;
+        Ok(currency)
+    }
+
+    /// Add a threshold. Wallet thresholds are absolute, key thresholds are
+    /// absolute or a percent of the key quota.
+    pub async fn create_threshold(
+        db: &Database,
+        user_id: &str,
+        new: NewThreshold,
+    ) -> Result<UserNotificationThreshold> {
+        let invalid = |msg: &str| NotificationError::InvalidInput(msg.to_string());
+        if new.value <= 0 {
+            return Err(invalid("value must be positive"));
+        }
+        let currency = match (&new.token_hash, new.kind.as_str()) {
+            (None, THRESHOLD_ABSOLUTE) => {
+                let currency: Currency = new
+                    .currency
+                    .as_deref()
+                    .unwrap_or("USD")
+                    .parse()
+                    .map_err(NotificationError::InvalidInput)?;
+                if currency.wallet() != currency {
+                    return Err(invalid("wallet thresholds are in USD or CNY"));
+                }
+                Some(currency.code().to_string())
+            }
+            (None, THRESHOLD_PERCENT) => {
+                return Err(invalid("wallet thresholds are absolute"));
+            }
+            (Some(_), THRESHOLD_PERCENT) if new.value >= 100 => {
+                return Err(invalid("percent thresholds are between 1 and 99"));
+            }
+            (Some(_), THRESHOLD_PERCENT | THRESHOLD_ABSOLUTE) => None,
+            _ => return Err(invalid("kind must be percent or absolute")),
+        };
+        if UserNotificationModel::list_thresholds(db, user_id)
+            .await?
+            .len()
+            >= MAX_THRESHOLDS
+        {
+            return Err(NotificationError::InvalidInput(format!(
+                "at most {MAX_THRESHOLDS} thresholds per user"
+            )));
+        }
+
+        let threshold = UserNotificationThreshold {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            user_id: user_id.to_string(),
+            token_hash: new.token_hash,
+            currency,
+            kind: new.kind,
+            value: new.value,
+            triggered_at: None,
+            created_at: chrono::Utc::now().timestamp(),
+        };
+        UserNotificationModel::create_threshold(db, &threshold).await?;
+        Ok(threshold)
+    }
+
+    pub async fn list_thresholds(
+        db: &Database,
+        user_id: &str,
+    ) -> Result<Vec<UserNotificationThreshold>> {
+        Ok(UserNotificationModel::list_thresholds(db, user_id).await?)
+    }
+
+    pub async fn delete_threshold(db: &Database, user_id: &str, id: &str) -> Result<()> {
+        if UserNotificationModel::delete_threshold(db, user_id, id).await? {
+            Ok(())
+        } else {
+            Err(NotificationError::NotFound)
+        }
+    }
+
+    /// Keep the thresholds of a rotated API key on its new secret
+    pub async fn token_rotated(db: &Database, old_token: &str, new_token: &str) -> Result<()> {
+        Ok(
+            UserNotificationModel::rename_token(db, &token_hash(old_token), &token_hash(new_token))
+                .await?,
+        )
+    }
+
+    /// Drop the thresholds of a deleted API key
+    pub async fn token_deleted(db: &Database, token: &str) -> Result<()> {
+        Ok(UserNotificationModel::delete_token(db, &token_hash(token)).await?)
+    }
+
+    /// Check the thresholds of `user_id` after a deduction charged to
+    /// `token` settled. Failures are logged, never returned, so billing is
+    /// not affected.
+    pub async fn evaluate(&self, db: &Database, user_id: &str, token: &str) {
+        if let Err(e) = self.try_evaluate(db, user_id, token).await {
+            tracing::warn!(user_id, error = %e, "Failed to evaluate notification thresholds");
+        }
+    }
+
+    async fn try_evaluate(&self, db: &Database, user_id: &str, token: &str) -> Result<()> {
+        let thresholds = UserNotificationModel::list_thresholds(db, user_id).await?;
+        if thresholds.is_empty() {
+            return Ok(());
+        }
+        let account = UserDatabase::get_user_by_id(db, user_id).await?;
+        let quota = if thresholds.iter().any(|t| t.token_hash.is_some()) {
+            RouterTokenModel::quota_state(db, token).await?
+        } else {
+            None
+        };
+        let quota_hash = quota.as_ref().map(|q| token_hash(&q.token));
+
+        let mut settings = None;
+        for threshold in thresholds {
+            let level = match &threshold.token_hash {
+                None => match &account {
+                    Some(account) => wallet_level(account, &threshold),
+                    None => continue,
+                },
+                Some(hash) if Some(hash) == quota_hash.as_ref() => match &quota {
+                    Some(quota) => key_level(quota),
+                    None => continue,
+                },
+                Some(_) => continue,
+            };
+            let Some(level) = level else { continue };
+
+            if level.is_below(&threshold) {
+                if threshold.triggered_at.is_none()
+                    && UserNotificationModel::mark_triggered(db, &threshold.id).await?
+                {
+                    if settings.is_none() {
+                        settings = Some(Self::settings(db, user_id).await?);
+                    }
+                    if let Some(settings) = &settings {
+                        self.dispatch(db, settings, account.as_ref(), &threshold, &level)
+                            .await;
+                    }
+                }
+            } else if threshold.triggered_at.is_some() {
+                UserNotificationModel::clear_triggered(db, &threshold.id).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn dispatch(
+        &self,
+        db: &Database,
+        settings: &UserNotificationSettings,
+        account: Option<&UserAccount>,
+        threshold: &UserNotificationThreshold,
+        level: &Level,
+    ) {
+        tracing::info!(
+            user_id = %settings.user_id,
+            threshold_id = %threshold.id,
+            remaining = level.remaining,
+            "Notification threshold crossed"
+        );
+
+        if settings.email_enabled != 0 {
+            if let Some(account) = account {
+                if let Some(email) = account.email.as_deref().filter(|e| !e.is_empty()) {
+                    let template = self.mail_template(account, threshold, level);
+                    if let Err(e) = MailService::enqueue(db, email, &template).await {
+                        tracing::warn!(user_id = %account.id, error = %e, "Failed to queue threshold email");
+                    }
+                }
+            }
+        }
+
+        if let (Some(url), Some(secret)) = (&settings.webhook_url, &settings.webhook_secret) {
+            let payload = WebhookPayload {
+                kind: if level.limit.is_some() {
+                    EVENT_QUOTA_LOW
+                } else {
+                    EVENT_BALANCE_LOW
+                },
+                threshold_id: threshold.id.clone(),
+                threshold_kind: threshold.kind.clone(),
+                threshold_value: threshold.value,
+                currency: level.currency.code().to_string(),
+                remaining: level.remaining,
+                limit: level.limit,
+                key_hint: level.key_hint.clone(),
+                created_at: chrono::Utc::now().timestamp(),
+            };
+            self.post_webhook(url.clone(), secret, &payload);
+        }
+
+        if level.limit.is_none() {
+            self.auto_topup(db, settings, level.currency).await;
+        }
+    }
+
+    fn mail_template(
+        &self,
+        account: &UserAccount,
+        threshold: &UserNotificationThreshold,
+        level: &Level,
+    ) -> MailTemplate {
+        let threshold_text = match (threshold.kind.as_str(), level.limit) {
+            (THRESHOLD_PERCENT, _) => format!("{}% of the quota", threshold.value),
+            _ => format_amount(level.currency, threshold.value),
+        };
+        match level.limit {
+            Some(limit) => MailTemplate::QuotaLow {
+                username: account.username.clone(),
+                key: level.key_hint.clone().unwrap_or_default(),
+                remaining: format!(
+                    "{} of {}",
+                    format_amount(level.currency, level.remaining),
+                    format_amount(level.currency, limit)
+                ),
+                threshold: threshold_text,
+                keys_url: self.keys_url.clone(),
+            },
+            None => MailTemplate::LowBalance {
+                username: account.username.clone(),
+                balance: format_amount(level.currency, level.remaining),
+                threshold: threshold_text,
+                topup_url: self.topup_url.clone(),
+            },
+        }
+    }
+
+    /// Deliver in the background; receivers that fail or time out are
+    /// logged and not retried. The host is resolved and checked again for
+    /// every delivery, and redirects are not followed.
+    fn post_webhook(&self, url: String, secret: &str, payload: &WebhookPayload) {
+        let body = match serde_json::to_vec(payload) {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to encode threshold webhook");
+                return;
+            }
+        };
+        let signature = webhook::sign(secret, payload.created_at, &body);
+        let allow_private = self.allow_private_webhooks;
+        tokio::spawn(async move {
+            let (target, address) = match webhook::resolve(&url, allow_private).await {
+                Ok(resolved) => resolved,
+                Err(e) => {
+                    tracing::warn!(url, error = %e, "Threshold webhook refused");
+                    return;
+                }
+            };
+            let client = match webhook_client(&target, address) {
+                Ok(client) => client,
+                Err(e) => {
+                    tracing::warn!(url, error = %e, "Threshold webhook failed");
+                    return;
+                }
+            };
+            let request = client
+                .post(target)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(SIGNATURE_HEADER, signature)
+                .body(body);
+            match request.send().await {
+                Ok(response) if response.status().is_success() => {}
+                Ok(response) => {
+                    tracing::warn!(url, status = %response.status(), "Threshold webhook rejected");
+                }
+                Err(e) => tracing::warn!(url, error = %e, "Threshold webhook failed"),
+            }
+        });
+    }
+
+    /// Charge the saved payment method when its currency tops up `wallet`
+    async fn auto_topup(
+        &self,
+        db: &Database,
+        settings: &UserNotificationSettings,
+        wallet: Currency,
+    ) {
+        let (Some(payments), Some(provider), Some(method), Some(amount), Some(currency)) = (
+            &self.payments,
+            &settings.auto_topup_provider,
+            &settings.auto_topup_method,
+            settings.auto_topup_amount,
+            &settings.auto_topup_currency,
+        ) else {
+            return;
+        };
+        if currency.parse::<Currency>().map(|c| c.wallet()) != Ok(wallet) {
+            return;
+        }
+        match payments
+            .charge_saved(db, &settings.user_id, provider, method, amount, currency)
+            .await
+        {
+            Ok(payment) => tracing::info!(
+                user_id = %settings.user_id,
+                payment_id = %payment.id,
+                "Auto top-up started"
+            ),
+            Err(e) => tracing::warn!(user_id = %settings.user_id, error = %e, "Auto top-up failed"),
+        }
+    }
+}
+
+/// Client for one delivery to `target`: its host is pinned to the checked
+/// `address`, proxies are bypassed and redirects are returned, not followed
+fn webhook_client(target: &url::Url, address: SocketAddr) -> reqwest::Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder()
+        .timeout(Duration::from_secs(WEBHOOK_TIMEOUT_SECS))
+        .redirect(reqwest::redirect::Policy::none())
+        .no_proxy();
+    if let Some(url::Host::Domain(domain)) = target.host() {
+        builder = builder.resolve(domain, address);
+    }
+    builder.build()
+}
+
+/// A balance or key quota as seen by a threshold
+struct Level {
+    currency: Currency,
+    /// Nanodollars left
+    remaining: i64,
+    /// Key quota, `None` for wallets
+    limit: Option<i64>,
+    key_hint: Option<String>,
+}
+
+impl Level {
+    fn is_below(&self, threshold: &UserNotificationThreshold) -> bool {
+        match (threshold.kind.as_str(), self.limit) {
+            (THRESHOLD_PERCENT, Some(limit)) if limit > 0 => {
+                i128::from(self.remaining) * 100 <= i128::from(threshold.value) * i128::from(limit)
+            }
+            (THRESHOLD_PERCENT, _) => false,
+            _ => self.remaining <= threshold.value,
+        }
+    }
+}
+
+fn wallet_level(account: &UserAccount, threshold: &UserNotificationThreshold) -> Option<Level> {
+    let currency: Currency = threshold.currency.as_deref()?.parse().ok()?;
+    let remaining = match currency {
+        Currency::CNY => account.balance_cny,
+        _ => account.balance_usd,
+    };
+    Some(Level {
+        currency,
+        remaining,
+        limit: None,
+        key_hint: None,
+    })
+}
+
+/// Unlimited keys have no level to watch
+fn key_level(quota: &TokenQuota) -> Option<Level> {
+    let remaining = quota.remaining()?;
+    let suffix: String = {
+        let chars: Vec<char> = quota.token.chars().collect();
+        chars[chars.len().saturating_sub(4)..].iter().collect()
+    };
+    Some(Level {
+        currency: Currency::USD,
+        remaining,
+        limit: Some(quota.limit),
+        key_hint: Some(format!("…{suffix}")),
+    })
+}
+
+/// Nanodollars as `$1.20`
+fn format_amount(currency: Currency, nano: i64) -> String {
+    let sign = if nano < 0 { "-" } else { "" };
+    let cents =
+        (nano.unsigned_abs() + (NANO_PER_DOLLAR as u64 / 200)) / (NANO_PER_DOLLAR as u64 / 100);
+    format!(
+        "{sign}{}{}.{:02}",
+        currency.symbol(),
+        cents / 100,
+        cents % 100
+    )
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn threshold(kind: &str, value: i64) -> UserNotificationThreshold {
+        UserNotificationThreshold {
+            id: "t".to_string(),
+            user_id: "u".to_string(),
+            token_hash: Some("h".to_string()),
+            currency: None,
+            kind: kind.to_string(),
+            value,
+            triggered_at: None,
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn percent_thresholds_compare_against_the_quota() {
+        let quota = TokenQuota {
+            token: "bc_live_abcd".to_string(),
+            limit: 10 * NANO_PER_DOLLAR,
+            used: 9 * NANO_PER_DOLLAR,
+        };
+        let level = key_level(&quota).unwrap();
+        assert_eq!(level.key_hint.as_deref(), Some("…abcd"));
+        assert!(level.is_below(&threshold(THRESHOLD_PERCENT, 10)));
+        assert!(!level.is_below(&threshold(THRESHOLD_PERCENT, 9)));
+        assert!(level.is_below(&threshold(THRESHOLD_ABSOLUTE, NANO_PER_DOLLAR)));
+        assert!(!level.is_below(&threshold(THRESHOLD_ABSOLUTE, NANO_PER_DOLLAR - 1)));
+
+        let unlimited = TokenQuota { limit: -1, ..quota };
+        assert!(key_level(&unlimited).is_none());
+    }
+
+    #[test]
+    fn amounts_format_to_cents() {
+        assert_eq!(format_amount(Currency::USD, 1_204_999_999), "$1.20");
+        assert_eq!(format_amount(Currency::USD, 1_205_000_000), "$1.21");
+        assert_eq!(format_amount(Currency::CNY, 0), "¥0.00");
+        assert_eq!(format_amount(Currency::USD, -50_000_000), "-$0.05");
+    }
+}
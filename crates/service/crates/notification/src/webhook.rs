@@ -0,0 +1,227 @@
+use crate::{NotificationError, Result};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::Serialize;
+use sha2::Sha256;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use url::{Host, Url};
+
+/// Header carrying `t=<unix seconds>,v1=<hex HMAC-SHA256>`
+pub const SIGNATURE_HEADER: &str = "X-BurnCloud-Signature";
+
+/// Event type of a wallet threshold crossing
+pub const EVENT_BALANCE_LOW: &str = "balance.low";
+/// Event type of an API key threshold crossing
+pub const EVENT_QUOTA_LOW: &str = "quota.low";
+
+/// Body of a threshold webhook
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookPayload {
+    /// [`EVENT_BALANCE_LOW`] or [`EVENT_QUOTA_LOW`]
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub threshold_id: String,
+    /// `percent` or `absolute`
+    pub threshold_kind: String,
+    pub threshold_value: i64,
+    /// Wallet currency, or USD for API key quotas
+    pub currency: String,
+    /// Nanodollars left
+    pub remaining: i64,
+    /// Key quota in nanodollars, absent for wallet thresholds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    /// Last characters of the API key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_hint: Option<String>,
+    pub created_at: i64,
+}
+
+/// A new `whsec_` signing secret
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 24];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    format!("whsec_{}", hex::encode(bytes))
+}
+
+/// Signature header value for `body` sent at `timestamp`. Receivers
+/// recompute the HMAC over `"{t}.{body}"` with their secret.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .unwrap_or_else(|_| unreachable!("HMAC accepts keys of any length"));
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!(
+        "t={timestamp},v1={}",
+        hex::encode(mac.finalize().into_bytes())
+    )
+}
+
+/// Whether webhooks may be delivered to `ip`. Loopback, private, link-local
+/// (the 169.254.169.254 metadata service among them), shared (CGNAT),
+/// unspecified, broadcast, multicast, documentation and reserved addresses
+/// are refused, as are IPv6 addresses embedding one of them (mapped, NAT64,
+/// 6to4), so a webhook cannot reach services inside the network.
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => {
+            // IPv4-mapped and -compatible addresses reach the IPv4 host
+            if let Some(v4) = ip.to_ipv4() {
+                return !ip.is_loopback() && is_public_v4(v4);
+            }
+            let segments = ip.segments();
+            // NAT64 64:ff9b::/96 and 6to4 2002::/16 are forwarded to the
+            // IPv4 address they embed
+            let embedded = match segments {
+                [0x0064, 0xff9b, 0, 0, 0, 0, high, low] | [0x2002, high, low, ..] => {
+                    Some(Ipv4Addr::from(u32::from(high) << 16 | u32::from(low)))
+                }
+                _ => None,
+            };
+            if let Some(v4) = embedded {
+                return is_public_v4(v4);
+            }
+            let first = segments[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local fc00::/7, where cloud metadata lives too
+                || first & 0xfe00 == 0xfc00
+                // Link-local fe80::/10 and deprecated site-local fec0::/10
+                || first & 0xff80 == 0xfe80
+                // Documentation 2001:db8::/32
+                || segments[..2] == [0x2001, 0x0db8]
+                // Local-use NAT64 64:ff9b:1::/48, translated to any address
+                || segments[..3] == [0x0064, 0xff9b, 0x0001])
+        }
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        // "This network" 0.0.0.0/8 and reserved 240.0.0.0/4
+        || a == 0
+        || a >= 240
+        // Shared address space 100.64.0.0/10
+        || (a == 100 && (64..128).contains(&b))
+        // IETF protocol assignments 192.0.0.0/24
+        || (a == 192 && b == 0 && c == 0)
+        // Benchmarking 198.18.0.0/15
+        || (a == 198 && (b == 18 || b == 19)))
+}
+
+/// Parse a webhook URL and resolve its host. Fails unless the URL is http or
+/// https and every address of the host is public; `allow_private` lifts the
+/// address check. Deliveries connect to the returned address only, so the
+/// host cannot resolve elsewhere between the check and the request.
+pub async fn resolve(url: &str, allow_private: bool) -> Result<(Url, SocketAddr)> {
+    let invalid = |msg: String| NotificationError::InvalidInput(msg);
+    let parsed = Url::parse(url).map_err(|e| invalid(format!("invalid webhook_url: {e}")))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(invalid("webhook_url must be http or https".to_string()));
+    }
+    let port = parsed.port_or_known_default().unwrap_or(80);
+    let addresses: Vec<SocketAddr> = match parsed.host() {
+        Some(Host::Ipv4(ip)) => vec![SocketAddr::new(ip.into(), port)],
+        Some(Host::Ipv6(ip)) => vec![SocketAddr::new(ip.into(), port)],
+        Some(Host::Domain(domain)) => tokio::net::lookup_host((domain, port))
+            .await
+            .map_err(|e| invalid(format!("cannot resolve {domain}: {e}")))?
+            .collect(),
+        None => return Err(invalid("webhook_url needs a host".to_string())),
+    };
+    if !allow_private {
+        if let Some(address) = addresses.iter().find(|a| !is_public_address(a.ip())) {
+            return Err(invalid(format!(
+                "webhook_url resolves to {}, which is not a public address",
+                address.ip()
+            )));
+        }
+    }
+    let address = addresses
+        .first()
+        .copied()
+        .ok_or_else(|| invalid("webhook_url host has no address".to_string()))?;
+    Ok((parsed, address))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_covers_timestamp_and_body() {
+        let a = sign("whsec_test", 100, b"{}");
+        assert!(a.starts_with("t=100,v1="));
+        assert_eq!(a.len(), "t=100,v1=".len() + 64);
+        assert_ne!(a, sign("whsec_test", 101, b"{}"));
+        assert_ne!(a, sign("whsec_test", 100, b"{ }"));
+        assert_ne!(a, sign("whsec_other", 100, b"{}"));
+        assert!(generate_secret().starts_with("whsec_"));
+    }
+
+    #[test]
+    fn internal_addresses_are_not_public() {
+        for internal in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.100.100.200",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+            "fd00:ec2::254",
+            "fe80::1",
+            "64:ff9b::a9fe:a9fe",
+            "64:ff9b::7f00:1",
+            "64:ff9b:1::a00:1",
+            "2002:a9fe:a9fe::1",
+            "2002:c0a8:101::",
+        ] {
+            let ip: IpAddr = internal.parse().unwrap();
+            assert!(!is_public_address(ip), "{internal}");
+        }
+        for public in [
+            "93.184.216.34",
+            "8.8.8.8",
+            "2606:4700::1111",
+            "::ffff:8.8.8.8",
+            "64:ff9b::808:808",
+            "2002:808:808::1",
+        ] {
+            let ip: IpAddr = public.parse().unwrap();
+            assert!(is_public_address(ip), "{public}");
+        }
+    }
+
+    #[tokio::test]
+    async fn private_targets_are_refused_unless_allowed() {
+        for url in [
+            "http://127.0.0.1:8080/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]/hook",
+            "http://localhost/hook",
+        ] {
+            assert!(resolve(url, false).await.is_err(), "{url}");
+        }
+        let (url, address) = resolve("http://127.0.0.1:8080/hook", true).await.unwrap();
+        assert_eq!(url.path(), "/hook");
+        assert_eq!(address, "127.0.0.1:8080".parse().unwrap());
+        assert!(resolve("ftp://example.com/hook", true).await.is_err());
+    }
+}
@@ -0,0 +1,324 @@
+//! Alipay page payment.
+//!
+//! Requests are gateway parameters signed with RSA2 (SHA256withRSA) over
+//! their sorted `key=value` string. Checkout is a signed
+//! `alipay.trade.page.pay` URL the browser opens; the result arrives as a
+//! form-encoded asynchronous notification signed with the Alipay public key,
+//! which expects the literal body `success` in return. A notification that
+//! carries `refund_fee` reports the refunded total.
+
+use crate::config::AlipayConfig;
+use crate::provider::{
+    format_minor, parse_minor, Checkout, CheckoutKind, CheckoutOrder, PaymentProvider, PaymentRef,
+    RefundRequest, WebhookEvent,
+};
+use crate::signing::{self, RsaPrivateKey, RsaPublicKey};
+use crate::{PaymentError, Result};
+use async_trait::async_trait;
+use burncloud_common::Currency;
+use chrono::{FixedOffset, Utc};
+use http::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const CURRENCIES: [Currency; 1] = [Currency::CNY];
+
+/// The gateway expects timestamps in China Standard Time
+const CST_OFFSET_SECS: i32 = 8 * 3600;
+
+/// Result code of a successful gateway call
+const CODE_SUCCESS: &str = "10000";
+
+#[derive(Serialize)]
+struct PagePayContent<'a> {
+    out_trade_no: &'a str,
+    total_amount: String,
+    subject: &'a str,
+    product_code: &'static str,
+}
+
+#[derive(Serialize)]
+struct RefundContent<'a> {
+    out_trade_no: &'a str,
+    refund_amount: String,
+    out_request_no: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    refund_reason: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct RefundResponse {
+    alipay_trade_refund_response: GatewayResult,
+}
+
+#[derive(Deserialize)]
+struct GatewayResult {
+    code: String,
+    #[serde(default)]
+    msg: String,
+    #[serde(default)]
+    sub_msg: Option<String>,
+}
+
+/// `key=value` pairs sorted by key and joined with `&`, skipping empty
+/// values and the signature itself. This is the string Alipay signs.
+pub fn canonical_string(params: &BTreeMap<String, String>) -> String {
+    params
+        .iter()
+        .filter(|(k, v)| !v.is_empty() && k.as_str() != "sign" && k.as_str() != "sign_type")
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Add `sign` to gateway parameters (`sign_type` is part of what is signed)
+pub fn sign_params(key: &RsaPrivateKey, params: &mut BTreeMap<String, String>) {
+    let content = params
+        .iter()
+        .filter(|(k, v)| !v.is_empty() && k.as_str() != "sign")
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>()
+        .join("&");
+    let signature = signing::sign(key, content.as_bytes());
+    params.insert("sign".to_string(), signature);
+}
+
+pub struct AlipayProvider {
+    config: AlipayConfig,
+    private_key: RsaPrivateKey,
+    alipay_public_key: RsaPublicKey,
+    http: reqwest::Client,
+}
+
+impl AlipayProvider {
+    pub fn new(config: AlipayConfig) -> Result<Self> {
+        Ok(Self {
+            private_key: signing::parse_private_key(&config.private_key)?,
+            alipay_public_key: signing::parse_public_key(&config.alipay_public_key)?,
+            config,
+            http: reqwest::Client::new(),
+        })
+    }
+
+    /// Signed gateway parameters for `method`
+    fn gateway_params(
+        &self,
+        method: &str,
+        biz_content: String,
+        extra: &[(&str, &str)],
+    ) -> BTreeMap<String, String> {
+        let timestamp = Utc::now()
+            .with_timezone(
+                &FixedOffset::east_opt(CST_OFFSET_SECS).unwrap_or_else(|| unreachable!()),
+            )
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+        let mut params: BTreeMap<String, String> = [
+            ("app_id", self.config.app_id.as_str()),
+            ("method", method),
+            ("format", "JSON"),
+            ("charset", "utf-8"),
+            ("sign_type", "RSA2"),
+            ("timestamp", timestamp.as_str()),
+            ("version", "1.0"),
+            ("biz_content", biz_content.as_str()),
+        ]
+        .into_iter()
+        .chain(extra.iter().copied())
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        sign_params(&self.private_key, &mut params);
+        params
+    }
+
+    fn gateway_url(&self, params: &BTreeMap<String, String>) -> String {
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(params.iter())
+            .finish();
+        format!("{}?{query}", self.config.gateway)
+    }
+
+    /// Verify a notification and turn it into an event
+    fn decode_notification(&self, params: &BTreeMap<String, String>) -> Result<WebhookEvent> {
+        let signature = params.get("sign").ok_or(PaymentError::InvalidSignature)?;
+        if !signing::verify(
+            &self.alipay_public_key,
+            canonical_string(params).as_bytes(),
+            signature,
+        ) {
+            return Err(PaymentError::InvalidSignature);
+        }
+        let field = |name: &str| {
+            params
+                .get(name)
+                .filter(|v| !v.is_empty())
+                .cloned()
+                .ok_or_else(|| PaymentError::MalformedWebhook(format!("missing {name}")))
+        };
+        if field("app_id")? != self.config.app_id {
+            return Err(PaymentError::MalformedWebhook(
+                "notification for another app".into(),
+            ));
+        }
+        let payment_id = field("out_trade_no")?;
+        if let Some(refund_fee) = params.get("refund_fee").filter(|v| !v.is_empty()) {
+            let refunded_total = parse_minor(refund_fee)
+                .ok_or_else(|| PaymentError::MalformedWebhook("bad refund_fee".into()))?;
+            return Ok(WebhookEvent::Refunded {
+                payment: PaymentRef::Id(payment_id),
+                refunded_total,
+            });
+        }
+        match field("trade_status")?.as_str() {
+            "TRADE_SUCCESS" | "TRADE_FINISHED" => Ok(WebhookEvent::Paid {
+                payment_id,
+                provider_payment_id: field("trade_no")?,
+                amount: parse_minor(&field("total_amount")?)
+                    .ok_or_else(|| PaymentError::MalformedWebhook("bad total_amount".into()))?,
+                currency: Currency::CNY,
+            }),
+            "TRADE_CLOSED" => Ok(WebhookEvent::Closed { payment_id }),
+            _ => Ok(WebhookEvent::Ignored),
+        }
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for AlipayProvider {
+    fn name(&self) -> &'static str {
+        "alipay"
+    }
+
+    fn currencies(&self) -> &[Currency] {
+        &CURRENCIES
+    }
+
+    async fn create_checkout(&self, order: &CheckoutOrder) -> Result<Checkout> {
+        let content = serde_json::to_string(&PagePayContent {
+            out_trade_no: &order.payment_id,
+            total_amount: format_minor(order.amount),
+            subject: &order.subject,
+            product_code: "FAST_INSTANT_TRADE_PAY",
+        })
+        .map_err(|e| PaymentError::Provider(e.to_string()))?;
+        let params = self.gateway_params(
+            "alipay.trade.page.pay",
+            content,
+            &[
+                ("notify_url", &order.notify_url),
+                ("return_url", &order.return_url),
+            ],
+        );
+        Ok(Checkout {
+            session_id: None,
+            url: self.gateway_url(&params),
+            kind: CheckoutKind::Redirect,
+        })
+    }
+
+    fn verify_webhook(&self, _headers: &HeaderMap, body: &[u8]) -> Result<WebhookEvent> {
+        let params: BTreeMap<String, String> = url::form_urlencoded::parse(body)
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect();
+        self.decode_notification(&params)
+    }
+
+    async fn refund(&self, refund: &RefundRequest) -> Result<()> {
+        let content = serde_json::to_string(&RefundContent {
+            out_trade_no: &refund.payment_id,
+            refund_amount: format_minor(refund.amount),
+            out_request_no: &refund.refund_id,
+            refund_reason: refund.reason.as_deref(),
+        })
+        .map_err(|e| PaymentError::Provider(e.to_string()))?;
+        let params = self.gateway_params("alipay.trade.refund", content, &[]);
+        let response: RefundResponse = self
+            .http
+            .post(&self.config.gateway)
+            .form(&params)
+            .send()
+            .await
+            .map_err(|e| PaymentError::Provider(format!("alipay: {e}")))?
+            .json()
+            .await
+            .map_err(|e| PaymentError::Provider(format!("alipay: unexpected response: {e}")))?;
+        let result = response.alipay_trade_refund_response;
+        if result.code == CODE_SUCCESS {
+            Ok(())
+        } else {
+            Err(PaymentError::Provider(format!(
+                "alipay: {} {}",
+                result.msg,
+                result.sub_msg.unwrap_or_default()
+            )))
+        }
+    }
+
+    fn webhook_ack(&self) -> &'static str {
+        "success"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rsa::pkcs8::{EncodePrivateKey, EncodePublicKey, LineEnding};
+
+    fn key_pair() -> (String, String) {
+        let key =
+            RsaPrivateKey::new(&mut rand::thread_rng(), 1024).unwrap_or_else(|e| panic!("{e}"));
+        let private = key
+            .to_pkcs8_pem(LineEnding::LF)
+            .unwrap_or_else(|e| panic!("{e}"))
+            .to_string();
+        let public = RsaPublicKey::from(&key)
+            .to_public_key_pem(LineEnding::LF)
+            .unwrap_or_else(|e| panic!("{e}"));
+        (private, public)
+    }
+
+    #[test]
+    fn notifications_are_verified_with_the_alipay_key() {
+        let (merchant_private, _) = key_pair();
+        let (alipay_private, alipay_public) = key_pair();
+        let provider = AlipayProvider::new(AlipayConfig {
+            app_id: "2021000000000001".into(),
+            private_key: merchant_private,
+            // Single-line form with escaped newlines, as in an env file
+            alipay_public_key: alipay_public.replace('\n', "\\n"),
+            gateway: "http://127.0.0.1:9/gateway.do".into(),
+        })
+        .unwrap_or_else(|e| panic!("{e}"));
+        let alipay_key =
+            signing::parse_private_key(&alipay_private).unwrap_or_else(|e| panic!("{e}"));
+
+        let mut params: BTreeMap<String, String> = [
+            ("app_id", "2021000000000001"),
+            ("out_trade_no", "pay_1"),
+            ("trade_no", "2026101922001"),
+            ("trade_status", "TRADE_SUCCESS"),
+            ("total_amount", "88.00"),
+            ("sign_type", "RSA2"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let signature = signing::sign(&alipay_key, canonical_string(&params).as_bytes());
+        params.insert("sign".into(), signature);
+        assert_eq!(
+            provider.decode_notification(&params).ok(),
+            Some(WebhookEvent::Paid {
+                payment_id: "pay_1".into(),
+                provider_payment_id: "2026101922001".into(),
+                amount: 8800,
+                currency: Currency::CNY,
+            })
+        );
+
+        params.insert("total_amount".into(), "0.01".into());
+        assert!(matches!(
+            provider.decode_notification(&params),
+            Err(PaymentError::InvalidSignature)
+        ));
+    }
+}
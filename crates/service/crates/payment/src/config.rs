@@ -0,0 +1,203 @@
+//! Payment provider credentials and checkout limits read from the environment.
+
+/// Webhooks of provider `{name}` are posted to `{WEBHOOK_PATH}/{name}`
+pub const WEBHOOK_PATH: &str = "/console/api/payments/webhook";
+
+/// Stripe Checkout
+#[derive(Debug, Clone)]
+pub struct StripeConfig {
+    /// Secret API key (`sk_...`)
+    pub secret_key: String,
+    /// Signing secret of the webhook endpoint (`whsec_...`)
+    pub webhook_secret: String,
+    pub api_base: String,
+    /// Oldest webhook timestamp accepted, in seconds
+    pub tolerance_secs: i64,
+}
+
+/// Alipay page payment (`alipay.trade.page.pay`)
+#[derive(Debug, Clone)]
+pub struct AlipayConfig {
+    pub app_id: String,
+    /// Application private key, PEM or base64 DER
+    pub private_key: String,
+    /// Alipay public key used to verify notifications
+    pub alipay_public_key: String,
+    pub gateway: String,
+}
+
+/// WeChat Pay API v3 Native payment (QR code)
+#[derive(Debug, Clone)]
+pub struct WechatConfig {
+    pub mch_id: String,
+    pub app_id: String,
+    /// Serial number of the merchant API certificate
+    pub serial_no: String,
+    /// Merchant API private key, PEM or base64 DER
+    pub private_key: String,
+    /// WeChat Pay platform public key used to verify notifications
+    pub platform_public_key: String,
+    /// APIv3 key (32 bytes) that encrypts notification resources
+    pub api_v3_key: String,
+    pub api_base: String,
+    /// Oldest webhook timestamp accepted, in seconds
+    pub tolerance_secs: i64,
+}
+
+/// Complete payment configuration. A provider is enabled when its section
+/// is present.
+#[derive(Debug, Clone)]
+pub struct PaymentConfig {
+    /// Page the customer returns to after paying
+    pub return_url: String,
+    /// Public base URL providers post webhooks to
+    pub notify_base: String,
+    /// Line shown on the provider's payment page
+    pub subject: String,
+    /// Smallest checkout, in minor units
+    pub min_amount: i64,
+    /// Largest checkout, in minor units
+    pub max_amount: i64,
+    pub stripe: Option<StripeConfig>,
+    pub alipay: Option<AlipayConfig>,
+    pub wechat: Option<WechatConfig>,
+}
+
+impl Default for PaymentConfig {
+    fn default() -> Self {
+        Self {
+            return_url: "http://localhost:8080/billing".to_string(),
+            notify_base: "http://localhost:8080".to_string(),
+            subject: "BurnCloud top-up".to_string(),
+            min_amount: 100,
+            max_amount: 1_000_000,
+            stripe: None,
+            alipay: None,
+            wechat: None,
+        }
+    }
+}
+
+fn env_var(key: &str) -> Option<String> {
+    std::env::var(key)
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+fn env_i64(key: &str) -> Option<i64> {
+    env_var(key).and_then(|v| v.parse().ok())
+}
+
+impl PaymentConfig {
+    /// Read the configuration from the environment, falling back to the defaults.
+    ///
+    /// - `PAYMENT_RETURN_URL` (default `{BASE_URL}/billing`)
+    /// - `PAYMENT_NOTIFY_BASE_URL`: public URL of this server (default `BASE_URL`)
+    /// - `PAYMENT_SUBJECT`, `PAYMENT_MIN_AMOUNT` and `PAYMENT_MAX_AMOUNT` (minor units)
+    /// - Stripe: `STRIPE_SECRET_KEY`, `STRIPE_WEBHOOK_SECRET`, `STRIPE_API_BASE`
+    /// - Alipay: `ALIPAY_APP_ID`, `ALIPAY_PRIVATE_KEY`, `ALIPAY_PUBLIC_KEY`,
+    ///   `ALIPAY_GATEWAY`
+    /// - WeChat Pay: `WECHATPAY_MCH_ID`, `WECHATPAY_APP_ID`, `WECHATPAY_SERIAL_NO`,
+    ///   `WECHATPAY_PRIVATE_KEY`, `WECHATPAY_PUBLIC_KEY`, `WECHATPAY_API_V3_KEY`,
+    ///   `WECHATPAY_API_BASE`
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        let base = env_var("BASE_URL").map(|b| b.trim_end_matches('/').to_string());
+        if let Some(base) = &base {
+            config.return_url = format!("{base}/billing");
+            config.notify_base = base.clone();
+        }
+        if let Some(url) = env_var("PAYMENT_RETURN_URL") {
+            config.return_url = url;
+        }
+        if let Some(url) = env_var("PAYMENT_NOTIFY_BASE_URL") {
+            config.notify_base = url.trim_end_matches('/').to_string();
+        }
+        if let Some(subject) = env_var("PAYMENT_SUBJECT") {
+            config.subject = subject;
+        }
+        if let Some(min) = env_i64("PAYMENT_MIN_AMOUNT").filter(|n| *n > 0) {
+            config.min_amount = min;
+        }
+        if let Some(max) = env_i64("PAYMENT_MAX_AMOUNT").filter(|n| *n > 0) {
+            config.max_amount = max;
+        }
+
+        config.stripe = match (
+            env_var("STRIPE_SECRET_KEY"),
+            env_var("STRIPE_WEBHOOK_SECRET"),
+        ) {
+            (Some(secret_key), Some(webhook_secret)) => Some(StripeConfig {
+                secret_key,
+                webhook_secret,
+                api_base: env_var("STRIPE_API_BASE")
+                    .unwrap_or_else(|| "https://api.stripe.com".to_string()),
+                tolerance_secs: 300,
+            }),
+            (Some(_), None) => {
+                tracing::warn!(
+                    "STRIPE_SECRET_KEY is set without STRIPE_WEBHOOK_SECRET, Stripe disabled"
+                );
+                None
+            }
+            _ => None,
+        };
+        config.alipay = match (
+            env_var("ALIPAY_APP_ID"),
+            env_var("ALIPAY_PRIVATE_KEY"),
+            env_var("ALIPAY_PUBLIC_KEY"),
+        ) {
+            (Some(app_id), Some(private_key), Some(alipay_public_key)) => Some(AlipayConfig {
+                app_id,
+                private_key,
+                alipay_public_key,
+                gateway: env_var("ALIPAY_GATEWAY")
+                    .unwrap_or_else(|| "https://openapi.alipay.com/gateway.do".to_string()),
+            }),
+            (Some(_), _, _) => {
+                tracing::warn!("ALIPAY_APP_ID is set without both keys, Alipay disabled");
+                None
+            }
+            _ => None,
+        };
+        config.wechat = match (
+            env_var("WECHATPAY_MCH_ID"),
+            env_var("WECHATPAY_APP_ID"),
+            env_var("WECHATPAY_SERIAL_NO"),
+            env_var("WECHATPAY_PRIVATE_KEY"),
+            env_var("WECHATPAY_PUBLIC_KEY"),
+            env_var("WECHATPAY_API_V3_KEY"),
+        ) {
+            (
+                Some(mch_id),
+                Some(app_id),
+                Some(serial_no),
+                Some(private_key),
+                Some(platform_public_key),
+                Some(api_v3_key),
+            ) => Some(WechatConfig {
+                mch_id,
+                app_id,
+                serial_no,
+                private_key,
+                platform_public_key,
+                api_v3_key,
+                api_base: env_var("WECHATPAY_API_BASE")
+                    .unwrap_or_else(|| "https://api.mch.weixin.qq.com".to_string()),
+                tolerance_secs: 300,
+            }),
+            (Some(_), ..) => {
+                tracing::warn!("WECHATPAY_MCH_ID is set but the WeChat Pay configuration is incomplete, WeChat Pay disabled");
+                None
+            }
+            _ => None,
+        };
+        config
+    }
+
+    /// Webhook endpoint of `provider`
+    pub fn notify_url(&self, provider: &str) -> String {
+        format!("{}{WEBHOOK_PATH}/{provider}", self.notify_base)
+    }
+}
@@ -0,0 +1,576 @@
+//! # BurnCloud Service Payment
+//!
+//! Online top-up. A customer starts a checkout with one of the enabled
+//! [`PaymentProvider`]s and pays on the provider's page; the provider's
+//! signed webhook then credits the wallet.
+//! Payments are `user_payments` rows that webhooks only move forward with
+//! guarded updates, each in one transaction with its wallet change, so a
+//! redelivered or replayed event credits or debits the wallet exactly once. Refunds are sent to the provider and debited from the
+//! wallet, even when that leaves it negative.
+//! A checkout can save its payment method under the user's own provider
+//! customer, stored in `user_payment_customers`; only methods the provider
+//! reports attached to that customer are charged without the user present.
+//!
+//! - [`config`] - provider credentials and checkout limits (`PaymentConfig::from_env`)
+//! - [`provider`] - the adapter trait and the events webhooks decode to
+//! - [`stripe`], [`alipay`], [`wechat`] - Stripe Checkout, Alipay page payment
+//!   and WeChat Pay Native adapters
+//! - [`signing`] - RSA keys and SHA256withRSA signatures
+
+pub mod alipay;
+pub mod config;
+pub mod provider;
+pub mod signing;
+pub mod stripe;
+pub mod wechat;
+
+use burncloud_common::Currency;
+use burncloud_database::Database;
+use burncloud_database_user::{
+    UserPayment, UserPaymentModel, PAYMENT_PAID, PAYMENT_PENDING, PAYMENT_REFUNDED,
+};
+use http::HeaderMap;
+use provider::format_minor;
+use serde::Serialize;
+use std::sync::Arc;
+
+pub use alipay::AlipayProvider;
+pub use config::{AlipayConfig, PaymentConfig, StripeConfig, WechatConfig, WEBHOOK_PATH};
+pub use provider::{
+    Checkout, CheckoutKind, CheckoutOrder, PaymentProvider, PaymentRef, RefundRequest, SavedMethod,
+    WebhookEvent,
+};
+pub use stripe::StripeProvider;
+pub use wechat::WechatProvider;
+
+/// Nanodollars per minor unit (cent, fen) of a wallet currency
+const NANO_PER_MINOR: i64 = 10_000_000;
+
+/// Payments returned by one history request
+const LIST_LIMIT: i64 = 200;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PaymentError {
+    #[error("Payment provider is not enabled: {0}")]
+    UnknownProvider(String),
+
+    #[error("Payment not found")]
+    NotFound,
+
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("Invalid webhook signature")]
+    InvalidSignature,
+
+    #[error("Malformed webhook: {0}")]
+    MalformedWebhook(String),
+
+    #[error("Payment provider error: {0}")]
+    Provider(String),
+
+    #[error("Configuration error: {0}")]
+    Config(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] burncloud_database::DatabaseError),
+}
+
+pub type Result<T> = std::result::Result<T, PaymentError>;
+
+/// An enabled provider, as offered to customers
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderInfo {
+    pub name: &'static str,
+    pub currencies: Vec<&'static str>,
+    /// Can charge saved payment methods, for auto top-up
+    pub saved_methods: bool,
+}
+
+/// Checkout limits and enabled providers
+#[derive(Debug, Clone, Serialize)]
+pub struct PaymentOptions {
+    pub providers: Vec<ProviderInfo>,
+    /// Smallest checkout, in minor units
+    pub min_amount: i64,
+    /// Largest checkout, in minor units
+    pub max_amount: i64,
+}
+
+/// A started checkout
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckoutView {
+    pub payment: UserPayment,
+    pub url: String,
+    pub kind: CheckoutKind,
+    /// QR code of `url` for scan-to-pay providers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub qr_svg: Option<String>,
+}
+
+/// Starts checkouts, applies webhooks and issues refunds
+pub struct PaymentService {
+    config: PaymentConfig,
+    providers: Vec<Arc<dyn PaymentProvider>>,
+}
+
+impl PaymentService {
+    pub fn new(config: PaymentConfig, providers: Vec<Arc<dyn PaymentProvider>>) -> Self {
+        Self { config, providers }
+    }
+
+    /// Build from the environment. A provider with broken credentials is
+    /// logged and left disabled rather than failing startup.
+    pub fn from_env() -> Self {
+        let config = PaymentConfig::from_env();
+        let mut providers: Vec<Arc<dyn PaymentProvider>> = Vec::new();
+        if let Some(stripe) = &config.stripe {
+            providers.push(Arc::new(StripeProvider::new(stripe.clone())));
+        }
+        if let Some(alipay) = &config.alipay {
+            match AlipayProvider::new(alipay.clone()) {
+                Ok(provider) => providers.push(Arc::new(provider)),
+                Err(e) => tracing::error!("Alipay disabled: {e}"),
+            }
+        }
+        if let Some(wechat) = &config.wechat {
+            match WechatProvider::new(wechat.clone()) {
+                Ok(provider) => providers.push(Arc::new(provider)),
+                Err(e) => tracing::error!("WeChat Pay disabled: {e}"),
+            }
+        }
+        Self::new(config, providers)
+    }
+
+    pub fn config(&self) -> &PaymentConfig {
+        &self.config
+    }
+
+    pub fn options(&self) -> PaymentOptions {
+        PaymentOptions {
+            providers: self
+                .providers
+                .iter()
+                .map(|p| ProviderInfo {
+                    name: p.name(),
+                    currencies: p.currencies().iter().map(Currency::code).collect(),
+                    saved_methods: p.charges_saved(),
+                })
+                .collect(),
+            min_amount: self.config.min_amount,
+            max_amount: self.config.max_amount,
+        }
+    }
+
+    fn provider(&self, name: &str) -> Result<&dyn PaymentProvider> {
+        self.providers
+            .iter()
+            .find(|p| p.name() == name)
+            .map(|p| p.as_ref())
+            .ok_or_else(|| PaymentError::UnknownProvider(name.to_string()))
+    }
+
+    /// Validate a top-up and record it as pending. `currency` defaults to
+    /// the first currency the provider accepts.
+    async fn open_payment(
+        &self,
+        db: &Database,
+        user_id: &str,
+        provider: &str,
+        amount: i64,
+        currency: Option<&str>,
+    ) -> Result<(&dyn PaymentProvider, CheckoutOrder, UserPayment)> {
+        let provider = self.provider(provider)?;
+        let currency: Currency = match currency {
+            Some(code) => code.parse().map_err(PaymentError::InvalidInput)?,
+            None => provider.currencies().first().copied().unwrap_or_default(),
+        };
+        if !provider.currencies().contains(&currency) {
+            return Err(PaymentError::InvalidInput(format!(
+                "{} does not accept {currency}",
+                provider.name()
+            )));
+        }
+        if amount < self.config.min_amount || amount > self.config.max_amount {
+            return Err(PaymentError::InvalidInput(format!(
+                "amount must be between {} and {}",
+                format_minor(self.config.min_amount),
+                format_minor(self.config.max_amount)
+            )));
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        let payment = UserPayment {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            user_id: user_id.to_string(),
+            provider: provider.name().to_string(),
+            amount,
+            currency: currency.code().to_string(),
+            credited: 0,
+            refunded_amount: 0,
+            status: PAYMENT_PENDING.to_string(),
+            provider_session_id: None,
+            provider_payment_id: None,
+            created_at: now,
+            paid_at: None,
+            updated_at: now,
+        };
+        UserPaymentModel::create(db, &payment).await?;
+
+        let order = CheckoutOrder {
+            payment_id: payment.id.clone(),
+            amount,
+            currency,
+            subject: self.config.subject.clone(),
+            return_url: self.config.return_url.clone(),
+            notify_url: self.config.notify_url(provider.name()),
+            customer: None,
+        };
+        Ok((provider, order, payment))
+    }
+
+    /// The provider customer of a user, created at the provider and stored
+    /// on first use
+    async fn customer(&self, db: &Database, user_id: &str, provider: &str) -> Result<String> {
+        let provider = self.provider(provider)?;
+        if !provider.charges_saved() {
+            return Err(PaymentError::InvalidInput(format!(
+                "{} cannot save payment methods",
+                provider.name()
+            )));
+        }
+        if let Some(customer) = UserPaymentModel::customer(db, user_id, provider.name()).await? {
+            return Ok(customer);
+        }
+        let created = provider.create_customer(user_id).await?;
+        Ok(UserPaymentModel::set_customer(db, user_id, provider.name(), &created).await?)
+    }
+
+    /// Create a pending payment of `amount` minor units and the provider
+    /// checkout the customer completes it with. `currency` defaults to the
+    /// first currency the provider accepts. With `save_method` the payment
+    /// method is saved under the user's provider customer for auto top-up.
+    pub async fn create_checkout(
+        &self,
+        db: &Database,
+        user_id: &str,
+        provider: &str,
+        amount: i64,
+        currency: Option<&str>,
+        save_method: bool,
+    ) -> Result<CheckoutView> {
+        let customer = if save_method {
+            Some(self.customer(db, user_id, provider).await?)
+        } else {
+            None
+        };
+        let (provider, mut order, mut payment) = self
+            .open_payment(db, user_id, provider, amount, currency)
+            .await?;
+        order.customer = customer;
+        let checkout = match provider.create_checkout(&order).await {
+            Ok(checkout) => checkout,
+            Err(e) => {
+                UserPaymentModel::mark_closed(db, &payment.id).await?;
+                return Err(e);
+            }
+        };
+        if let Some(session_id) = &checkout.session_id {
+            UserPaymentModel::set_session(db, &payment.id, session_id).await?;
+            payment.provider_session_id = Some(session_id.clone());
+        }
+        let qr_svg = (checkout.kind == CheckoutKind::QrCode).then(|| qr_svg(&checkout.url));
+        Ok(CheckoutView {
+            payment,
+            url: checkout.url,
+            kind: checkout.kind,
+            qr_svg,
+        })
+    }
+
+    /// Payment methods saved under the user's customer at `provider`, none
+    /// before a checkout saved one
+    pub async fn saved_methods(
+        &self,
+        db: &Database,
+        user_id: &str,
+        provider: &str,
+    ) -> Result<Vec<SavedMethod>> {
+        let provider = self.provider(provider)?;
+        if !provider.charges_saved() {
+            return Ok(Vec::new());
+        }
+        match UserPaymentModel::customer(db, user_id, provider.name()).await? {
+            Some(customer) => provider.saved_methods(&customer).await,
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Fail unless `method` is attached to the user's customer at `provider`
+    pub async fn check_saved_method(
+        &self,
+        db: &Database,
+        user_id: &str,
+        provider: &str,
+        method: &str,
+    ) -> Result<()> {
+        let saved = self.saved_methods(db, user_id, provider).await?;
+        if saved.iter().any(|m| m.id == method) {
+            Ok(())
+        } else {
+            Err(PaymentError::InvalidInput(format!(
+                "{method} is not a payment method saved to this account"
+            )))
+        }
+    }
+
+    /// Charge `amount` minor units to a payment method the user saved at
+    /// `provider`, without them present. Only methods attached to the
+    /// user's own provider customer are charged. The payment stays pending
+    /// until the provider's webhook confirms it, which credits the wallet
+    /// as for a checkout.
+    pub async fn charge_saved(
+        &self,
+        db: &Database,
+        user_id: &str,
+        provider: &str,
+        method: &str,
+        amount: i64,
+        currency: &str,
+    ) -> Result<UserPayment> {
+        self.check_saved_method(db, user_id, provider, method).await?;
+        let customer = self.customer(db, user_id, provider).await?;
+        let (provider, order, mut payment) = self
+            .open_payment(db, user_id, provider, amount, Some(currency))
+            .await?;
+        match provider.charge_saved(&order, &customer, method).await {
+            Ok(session_id) => {
+                UserPaymentModel::set_session(db, &payment.id, &session_id).await?;
+                payment.provider_session_id = Some(session_id);
+                Ok(payment)
+            }
+            Err(e) => {
+                UserPaymentModel::mark_closed(db, &payment.id).await?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Authenticate a webhook of `provider` and apply it. Returns the payment
+    /// the event changed, `None` when it was already applied or had nothing
+    /// to do. Events for payments this server does not know are dropped.
+    pub async fn handle_webhook(
+        &self,
+        db: &Database,
+        provider: &str,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<Option<UserPayment>> {
+        let provider = self.provider(provider)?;
+        let event = provider.verify_webhook(headers, body)?;
+        let reference = match &event {
+            WebhookEvent::Paid { payment_id, .. } | WebhookEvent::Closed { payment_id } => {
+                PaymentRef::Id(payment_id.clone())
+            }
+            WebhookEvent::Refunded { payment, .. } => payment.clone(),
+            WebhookEvent::Ignored => return Ok(None),
+        };
+        let Some(payment) = find(db, provider.name(), &reference).await? else {
+            tracing::warn!(
+                provider = provider.name(),
+                "Webhook for unknown payment {reference:?}"
+            );
+            return Ok(None);
+        };
+        match event {
+            WebhookEvent::Paid {
+                provider_payment_id,
+                amount,
+                currency,
+                ..
+            } => credit(db, payment, &provider_payment_id, amount, currency).await,
+            WebhookEvent::Closed { .. } => {
+                if UserPaymentModel::mark_closed(db, &payment.id).await? {
+                    Ok(Some(reload(db, &payment.id).await?))
+                } else {
+                    Ok(None)
+                }
+            }
+            WebhookEvent::Refunded { refunded_total, .. } => {
+                apply_refund(db, payment, refunded_total).await
+            }
+            WebhookEvent::Ignored => Ok(None),
+        }
+    }
+
+    /// Response body `provider` expects for a processed webhook
+    pub fn webhook_ack(&self, provider: &str) -> Result<&'static str> {
+        Ok(self.provider(provider)?.webhook_ack())
+    }
+
+    /// Refund `amount` minor units of a paid payment, the whole remainder
+    /// when `None`, and debit the wallet once the provider accepted it
+    pub async fn refund(
+        &self,
+        db: &Database,
+        payment_id: &str,
+        amount: Option<i64>,
+        reason: Option<String>,
+    ) -> Result<UserPayment> {
+        let payment = reload(db, payment_id).await?;
+        if payment.status != PAYMENT_PAID {
+            return Err(PaymentError::InvalidInput(format!(
+                "a {} payment cannot be refunded",
+                payment.status
+            )));
+        }
+        let remaining = payment.amount - payment.refunded_amount;
+        let amount = amount.unwrap_or(remaining);
+        if amount <= 0 || amount > remaining {
+            return Err(PaymentError::InvalidInput(format!(
+                "refund must be between 0.01 and {}",
+                format_minor(remaining)
+            )));
+        }
+        let provider = self.provider(&payment.provider)?;
+        let total = payment.refunded_amount + amount;
+        provider
+            .refund(&RefundRequest {
+                payment_id: payment.id.clone(),
+                provider_payment_id: payment.provider_payment_id.clone().unwrap_or_default(),
+                // The same refund level maps to the same id, so a retry after
+                // a lost response is not refunded twice
+                refund_id: format!("{}-{total}", payment.id),
+                amount,
+                total: payment.amount,
+                currency: payment
+                    .currency
+                    .parse()
+                    .map_err(PaymentError::InvalidInput)?,
+                reason,
+            })
+            .await?;
+        match apply_refund(db, payment, total).await? {
+            Some(payment) => Ok(payment),
+            // A webhook applied it first
+            None => reload(db, payment_id).await,
+        }
+    }
+
+    /// Payment history of one user, or of everyone when `user_id` is `None`
+    pub async fn list(db: &Database, user_id: Option<&str>) -> Result<Vec<UserPayment>> {
+        Ok(UserPaymentModel::list(db, user_id, LIST_LIMIT).await?)
+    }
+
+    pub async fn get(db: &Database, id: &str) -> Result<UserPayment> {
+        reload(db, id).await
+    }
+}
+
+async fn reload(db: &Database, id: &str) -> Result<UserPayment> {
+    UserPaymentModel::get(db, id)
+        .await?
+        .ok_or(PaymentError::NotFound)
+}
+
+/// The payment a webhook of `provider` refers to. A payment started with
+/// another provider is not found.
+async fn find(
+    db: &Database,
+    provider: &str,
+    reference: &PaymentRef,
+) -> Result<Option<UserPayment>> {
+    let payment = match reference {
+        PaymentRef::Id(id) => UserPaymentModel::get(db, id).await?,
+        PaymentRef::Provider(id) => {
+            UserPaymentModel::find_by_provider_ref(db, provider, id).await?
+        }
+    };
+    Ok(payment.filter(|p| p.provider == provider))
+}
+
+async fn credit(
+    db: &Database,
+    payment: UserPayment,
+    provider_payment_id: &str,
+    amount: i64,
+    currency: Currency,
+) -> Result<Option<UserPayment>> {
+    if payment.amount != amount || payment.currency != currency.code() {
+        return Err(PaymentError::MalformedWebhook(format!(
+            "payment {} expects {} {} but {} {} was paid",
+            payment.id,
+            format_minor(payment.amount),
+            payment.currency,
+            format_minor(amount),
+            currency
+        )));
+    }
+    let credited = amount
+        .checked_mul(NANO_PER_MINOR)
+        .ok_or_else(|| PaymentError::InvalidInput("amount out of range".into()))?;
+    let description = format!("在线充值 ({} {provider_payment_id})", payment.provider);
+    if !UserPaymentModel::mark_paid(db, &payment, provider_payment_id, credited, &description)
+        .await?
+    {
+        return Ok(None);
+    }
+    tracing::info!(
+        payment_id = %payment.id,
+        user_id = %payment.user_id,
+        "Payment of {} {} credited",
+        format_minor(amount),
+        payment.currency
+    );
+    Ok(Some(reload(db, &payment.id).await?))
+}
+
+/// Raise the refunded total of `payment` to `total` minor units and debit
+/// the difference. Totals at or below what is recorded change nothing.
+async fn apply_refund(
+    db: &Database,
+    mut payment: UserPayment,
+    total: i64,
+) -> Result<Option<UserPayment>> {
+    let total = total.min(payment.amount);
+    loop {
+        let refundable = payment.status == PAYMENT_PAID || payment.status == PAYMENT_REFUNDED;
+        if !refundable || total <= payment.refunded_amount {
+            return Ok(None);
+        }
+        let status = if total >= payment.amount {
+            PAYMENT_REFUNDED
+        } else {
+            PAYMENT_PAID
+        };
+        let debited = (total - payment.refunded_amount) * NANO_PER_MINOR;
+        let description = format!(
+            "在线充值退款 ({} {})",
+            payment.provider,
+            payment.provider_payment_id.as_deref().unwrap_or_default()
+        );
+        if UserPaymentModel::record_refund(db, &payment, total, status, debited, &description)
+            .await?
+        {
+            tracing::info!(
+                payment_id = %payment.id,
+                "Refund of {} {} debited",
+                format_minor(total - payment.refunded_amount),
+                payment.currency
+            );
+            return Ok(Some(reload(db, &payment.id).await?));
+        }
+        // Another refund landed in between; retry on top of it
+        payment = reload(db, &payment.id).await?;
+    }
+}
+
+fn qr_svg(data: &str) -> String {
+    qrcode::QrCode::new(data.as_bytes())
+        .map(|code| {
+            code.render::<qrcode::render::svg::Color>()
+                .min_dimensions(200, 200)
+                .build()
+        })
+        .unwrap_or_default()
+}
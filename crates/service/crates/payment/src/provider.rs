@@ -0,0 +1,206 @@
+//! The interface every payment provider adapter implements.
+
+use crate::{PaymentError, Result};
+use async_trait::async_trait;
+use burncloud_common::Currency;
+use http::HeaderMap;
+
+/// A top-up the customer is about to pay
+#[derive(Debug, Clone)]
+pub struct CheckoutOrder {
+    /// Our payment id, sent as the merchant order number
+    pub payment_id: String,
+    /// Amount in the minor unit of `currency`
+    pub amount: i64,
+    pub currency: Currency,
+    /// Line shown on the provider's payment page
+    pub subject: String,
+    /// Where the customer's browser goes after paying
+    pub return_url: String,
+    /// Where the provider posts its webhooks
+    pub notify_url: String,
+    /// Provider customer to save the payment method under, so it can be
+    /// charged later without the customer present
+    pub customer: Option<String>,
+}
+
+/// How the customer completes a checkout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckoutKind {
+    /// Open `url` in the browser
+    Redirect,
+    /// Scan `url` with the provider's mobile app
+    QrCode,
+}
+
+/// A checkout created at the provider
+#[derive(Debug, Clone)]
+pub struct Checkout {
+    /// Provider session, when the provider has one
+    pub session_id: Option<String>,
+    pub url: String,
+    pub kind: CheckoutKind,
+}
+
+/// Identifies a payment in a webhook
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaymentRef {
+    /// Our payment id (merchant order number)
+    Id(String),
+    /// The provider's charge or trade id
+    Provider(String),
+}
+
+/// What a verified webhook reports
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebhookEvent {
+    /// The customer paid; the wallet is credited once
+    Paid {
+        payment_id: String,
+        provider_payment_id: String,
+        /// Amount in the minor unit of `currency`
+        amount: i64,
+        currency: Currency,
+    },
+    /// The checkout expired or was abandoned
+    Closed { payment_id: String },
+    /// Refunds of the payment now add up to `refunded_total` (minor units)
+    Refunded {
+        payment: PaymentRef,
+        refunded_total: i64,
+    },
+    /// Verified, but nothing to do
+    Ignored,
+}
+
+/// A payment method saved under a customer at the provider
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct SavedMethod {
+    pub id: String,
+    /// Card brand, e.g. `visa`
+    pub brand: String,
+    pub last4: String,
+    pub exp_month: u32,
+    pub exp_year: u32,
+}
+
+/// A refund to send to the provider
+#[derive(Debug, Clone)]
+pub struct RefundRequest {
+    pub payment_id: String,
+    pub provider_payment_id: String,
+    /// Unique per refund, so a retried request is not refunded twice
+    pub refund_id: String,
+    /// Amount to refund now, in minor units
+    pub amount: i64,
+    /// Amount originally paid, in minor units
+    pub total: i64,
+    pub currency: Currency,
+    pub reason: Option<String>,
+}
+
+/// A payment provider: creates checkouts, authenticates its webhooks and
+/// issues refunds. Adapters do not touch the database.
+#[async_trait]
+pub trait PaymentProvider: Send + Sync {
+    /// Name used in routes and stored on payments
+    fn name(&self) -> &'static str;
+
+    /// Wallet currencies the provider charges in
+    fn currencies(&self) -> &[Currency];
+
+    async fn create_checkout(&self, order: &CheckoutOrder) -> Result<Checkout>;
+
+    /// Authenticate a webhook and decode it. Fails with
+    /// [`PaymentError::InvalidSignature`](crate::PaymentError::InvalidSignature)
+    /// when the signature does not match.
+    fn verify_webhook(&self, headers: &HeaderMap, body: &[u8]) -> Result<WebhookEvent>;
+
+    /// Ask the provider to refund part or all of a payment. Succeeds once
+    /// the provider accepted the refund.
+    async fn refund(&self, refund: &RefundRequest) -> Result<()>;
+
+    /// Whether payment methods can be saved under a customer and charged
+    /// later: [`Self::create_customer`], [`Self::saved_methods`] and
+    /// [`Self::charge_saved`]
+    fn charges_saved(&self) -> bool {
+        false
+    }
+
+    /// Create the provider customer that keeps the saved payment methods of
+    /// one of our users. Returns the provider's customer id.
+    async fn create_customer(&self, user_id: &str) -> Result<String> {
+        let _ = user_id;
+        Err(saved_unsupported(self.name()))
+    }
+
+    /// Payment methods attached to `customer` at the provider
+    async fn saved_methods(&self, customer: &str) -> Result<Vec<SavedMethod>> {
+        let _ = customer;
+        Err(saved_unsupported(self.name()))
+    }
+
+    /// Charge a payment method saved under `customer`, without the customer
+    /// present. Returns the provider's reference for the attempt; the
+    /// webhook confirming it credits the wallet like a checkout.
+    async fn charge_saved(
+        &self,
+        order: &CheckoutOrder,
+        customer: &str,
+        method: &str,
+    ) -> Result<String> {
+        let _ = (order, customer, method);
+        Err(saved_unsupported(self.name()))
+    }
+
+    /// Response body the provider expects for a processed webhook
+    fn webhook_ack(&self) -> &'static str {
+        ""
+    }
+}
+
+fn saved_unsupported(provider: &str) -> PaymentError {
+    PaymentError::InvalidInput(format!("{provider} cannot charge saved payment methods"))
+}
+
+/// `1234` minor units as `"12.34"`, the format Alipay uses for amounts
+pub(crate) fn format_minor(amount: i64) -> String {
+    format!("{}.{:02}", amount / 100, amount % 100)
+}
+
+/// `"12.34"` as `1234` minor units. At most two decimals.
+pub(crate) fn parse_minor(amount: &str) -> Option<i64> {
+    let (whole, fraction) = amount.trim().split_once('.').unwrap_or((amount.trim(), ""));
+    if whole.is_empty()
+        || fraction.len() > 2
+        || !whole.bytes().all(|b| b.is_ascii_digit())
+        || !fraction.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let cents = format!("{fraction:0<2}").parse::<i64>().ok()?;
+    whole
+        .parse::<i64>()
+        .ok()?
+        .checked_mul(100)?
+        .checked_add(cents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minor_units_round_trip() {
+        assert_eq!(format_minor(1234), "12.34");
+        assert_eq!(format_minor(5), "0.05");
+        assert_eq!(parse_minor("12.34"), Some(1234));
+        assert_eq!(parse_minor("12.3"), Some(1230));
+        assert_eq!(parse_minor("12"), Some(1200));
+        assert_eq!(parse_minor("0.05"), Some(5));
+        assert_eq!(parse_minor("1.234"), None);
+        assert_eq!(parse_minor("-1.00"), None);
+        assert_eq!(parse_minor(""), None);
+    }
+}
@@ -0,0 +1,540 @@
+//! Stripe Checkout.
+//!
+//! A Checkout Session in `payment` mode carries our payment id as
+//! `client_reference_id` and in the PaymentIntent metadata. A checkout that
+//! saves its card runs under the user's Stripe Customer with
+//! `setup_future_usage=off_session`; cards attached to that customer are
+//! later charged with an off-session PaymentIntent carrying the same
+//! metadata. Webhooks are authenticated with the `Stripe-Signature` header:
+//! an HMAC-SHA256 over `{timestamp}.{body}` keyed with the endpoint's
+//! signing secret.
+
+use crate::config::StripeConfig;
+use crate::provider::{
+    Checkout, CheckoutKind, CheckoutOrder, PaymentProvider, PaymentRef, RefundRequest, SavedMethod,
+    WebhookEvent,
+};
+use crate::{PaymentError, Result};
+use async_trait::async_trait;
+use burncloud_common::Currency;
+use hmac::{Hmac, Mac};
+use http::HeaderMap;
+use serde::Deserialize;
+use sha2::Sha256;
+use std::collections::HashMap;
+
+pub const SIGNATURE_HEADER: &str = "stripe-signature";
+
+const CURRENCIES: [Currency; 2] = [Currency::USD, Currency::CNY];
+
+#[derive(Deserialize)]
+struct Session {
+    id: String,
+    url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Customer {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct List<T> {
+    data: Vec<T>,
+}
+
+#[derive(Deserialize)]
+struct PaymentMethod {
+    id: String,
+    card: Option<Card>,
+}
+
+#[derive(Deserialize)]
+struct Card {
+    brand: String,
+    last4: String,
+    exp_month: u32,
+    exp_year: u32,
+}
+
+#[derive(Deserialize)]
+struct Refund {
+    status: String,
+}
+
+#[derive(Deserialize)]
+struct PaymentIntent {
+    id: String,
+    status: String,
+}
+
+#[derive(Deserialize)]
+struct Event {
+    #[serde(rename = "type")]
+    kind: String,
+    data: EventData,
+}
+
+#[derive(Deserialize)]
+struct EventData {
+    object: EventObject,
+}
+
+/// The fields of a Checkout Session, PaymentIntent or Charge the adapter reads
+#[derive(Deserialize)]
+struct EventObject {
+    id: String,
+    #[serde(default)]
+    client_reference_id: Option<String>,
+    #[serde(default)]
+    payment_intent: Option<String>,
+    #[serde(default)]
+    payment_status: Option<String>,
+    #[serde(default)]
+    amount_total: Option<i64>,
+    #[serde(default)]
+    amount_received: Option<i64>,
+    #[serde(default)]
+    amount_refunded: Option<i64>,
+    #[serde(default)]
+    currency: Option<String>,
+    #[serde(default)]
+    metadata: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    error: ApiErrorBody,
+}
+
+#[derive(Deserialize)]
+struct ApiErrorBody {
+    message: String,
+}
+
+/// `Stripe-Signature` value for `payload` sent at `timestamp`
+pub fn signature_header(secret: &str, timestamp: i64, payload: &[u8]) -> String {
+    let mac = payload_mac(secret, timestamp, payload);
+    format!(
+        "t={timestamp},v1={}",
+        hex::encode(mac.finalize().into_bytes())
+    )
+}
+
+fn payload_mac(secret: &str, timestamp: i64, payload: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .unwrap_or_else(|_| unreachable!("HMAC accepts keys of any length"));
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(payload);
+    mac
+}
+
+pub struct StripeProvider {
+    config: StripeConfig,
+    http: reqwest::Client,
+}
+
+impl StripeProvider {
+    pub fn new(config: StripeConfig) -> Self {
+        Self {
+            config,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Accept `header` when one of its `v1` signatures matches and its
+    /// timestamp is within the tolerance of `now`
+    fn check_signature(&self, header: &str, payload: &[u8], now: i64) -> Result<()> {
+        let mut timestamp = None;
+        let mut signatures = Vec::new();
+        for part in header.split(',') {
+            match part.trim().split_once('=') {
+                Some(("t", t)) => timestamp = t.parse::<i64>().ok(),
+                Some(("v1", sig)) => signatures.extend(hex::decode(sig).ok()),
+                _ => {}
+            }
+        }
+        let timestamp = timestamp.ok_or(PaymentError::InvalidSignature)?;
+        if (now - timestamp).abs() > self.config.tolerance_secs {
+            return Err(PaymentError::InvalidSignature);
+        }
+        let matches = signatures.iter().any(|sig| {
+            payload_mac(&self.config.webhook_secret, timestamp, payload)
+                .verify_slice(sig)
+                .is_ok()
+        });
+        if matches {
+            Ok(())
+        } else {
+            Err(PaymentError::InvalidSignature)
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.config.api_base.trim_end_matches('/'))
+    }
+
+    async fn post<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        form: &[(String, String)],
+        idempotency_key: &str,
+    ) -> Result<T> {
+        let request = self
+            .http
+            .post(self.url(path))
+            .header("Idempotency-Key", idempotency_key)
+            .form(form);
+        self.send(request).await
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T> {
+        self.send(self.http.get(self.url(path)).query(query)).await
+    }
+
+    async fn send<T: serde::de::DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T> {
+        let response = request
+            .bearer_auth(&self.config.secret_key)
+            .send()
+            .await
+            .map_err(|e| PaymentError::Provider(format!("stripe: {e}")))?;
+        let status = response.status();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| PaymentError::Provider(format!("stripe: {e}")))?;
+        if !status.is_success() {
+            let message = serde_json::from_slice::<ApiError>(&body)
+                .map(|e| e.error.message)
+                .unwrap_or_else(|_| status.to_string());
+            return Err(PaymentError::Provider(format!("stripe: {message}")));
+        }
+        serde_json::from_slice(&body)
+            .map_err(|e| PaymentError::Provider(format!("stripe: unexpected response: {e}")))
+    }
+}
+
+fn decode_event(event: Event) -> Result<WebhookEvent> {
+    let object = event.data.object;
+    let payment_id = || {
+        object
+            .client_reference_id
+            .clone()
+            .or_else(|| object.metadata.get("payment_id").cloned())
+            .ok_or_else(|| PaymentError::MalformedWebhook("session without payment id".into()))
+    };
+    let currency = || {
+        object
+            .currency
+            .as_deref()
+            .unwrap_or_default()
+            .to_ascii_uppercase()
+            .parse::<Currency>()
+            .map_err(PaymentError::MalformedWebhook)
+    };
+    // Only our off-session charges are closed when they fail: a declined
+    // card in Checkout fails its intent while the customer can still retry
+    let off_session = object.metadata.get("off_session").map(String::as_str) == Some("true");
+    match event.kind.as_str() {
+        "checkout.session.completed" | "checkout.session.async_payment_succeeded" => {
+            // Delayed methods complete the session before the money arrives
+            if object.payment_status.as_deref() != Some("paid") {
+                return Ok(WebhookEvent::Ignored);
+            }
+            let currency = currency()?;
+            Ok(WebhookEvent::Paid {
+                payment_id: payment_id()?,
+                provider_payment_id: object.payment_intent.clone().unwrap_or(object.id.clone()),
+                amount: object.amount_total.unwrap_or_default(),
+                currency,
+            })
+        }
+        "checkout.session.expired" | "checkout.session.async_payment_failed" => {
+            Ok(WebhookEvent::Closed {
+                payment_id: payment_id()?,
+            })
+        }
+        "payment_intent.succeeded" if off_session => Ok(WebhookEvent::Paid {
+            payment_id: payment_id()?,
+            provider_payment_id: object.id.clone(),
+            amount: object.amount_received.unwrap_or_default(),
+            currency: currency()?,
+        }),
+        "payment_intent.payment_failed" if off_session => Ok(WebhookEvent::Closed {
+            payment_id: payment_id()?,
+        }),
+        "charge.refunded" => {
+            let payment = match object.metadata.get("payment_id") {
+                Some(id) => PaymentRef::Id(id.clone()),
+                None => PaymentRef::Provider(object.payment_intent.clone().ok_or_else(|| {
+                    PaymentError::MalformedWebhook("charge without payment intent".into())
+                })?),
+            };
+            Ok(WebhookEvent::Refunded {
+                payment,
+                refunded_total: object.amount_refunded.unwrap_or_default(),
+            })
+        }
+        _ => Ok(WebhookEvent::Ignored),
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for StripeProvider {
+    fn name(&self) -> &'static str {
+        "stripe"
+    }
+
+    fn currencies(&self) -> &[Currency] {
+        &CURRENCIES
+    }
+
+    fn charges_saved(&self) -> bool {
+        true
+    }
+
+    async fn create_checkout(&self, order: &CheckoutOrder) -> Result<Checkout> {
+        let mut form: Vec<(String, String)> = [
+            ("mode", "payment".to_string()),
+            ("success_url", order.return_url.clone()),
+            ("cancel_url", order.return_url.clone()),
+            ("client_reference_id", order.payment_id.clone()),
+            ("metadata[payment_id]", order.payment_id.clone()),
+            (
+                "payment_intent_data[metadata][payment_id]",
+                order.payment_id.clone(),
+            ),
+            (
+                "line_items[0][price_data][currency]",
+                order.currency.code().to_ascii_lowercase(),
+            ),
+            (
+                "line_items[0][price_data][unit_amount]",
+                order.amount.to_string(),
+            ),
+            (
+                "line_items[0][price_data][product_data][name]",
+                order.subject.clone(),
+            ),
+            ("line_items[0][quantity]", "1".to_string()),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+        if let Some(customer) = &order.customer {
+            form.push(("customer".to_string(), customer.clone()));
+            form.push((
+                "payment_intent_data[setup_future_usage]".to_string(),
+                "off_session".to_string(),
+            ));
+        }
+        let session: Session = self
+            .post("/v1/checkout/sessions", &form, &order.payment_id)
+            .await?;
+        let url = session
+            .url
+            .ok_or_else(|| PaymentError::Provider("stripe: session without url".into()))?;
+        Ok(Checkout {
+            session_id: Some(session.id),
+            url,
+            kind: CheckoutKind::Redirect,
+        })
+    }
+
+    fn verify_webhook(&self, headers: &HeaderMap, body: &[u8]) -> Result<WebhookEvent> {
+        let header = headers
+            .get(SIGNATURE_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .ok_or(PaymentError::InvalidSignature)?;
+        self.check_signature(header, body, chrono::Utc::now().timestamp())?;
+        let event: Event = serde_json::from_slice(body)
+            .map_err(|e| PaymentError::MalformedWebhook(e.to_string()))?;
+        decode_event(event)
+    }
+
+    async fn create_customer(&self, user_id: &str) -> Result<String> {
+        let form = vec![("metadata[user_id]".to_string(), user_id.to_string())];
+        // Retries within Stripe's idempotency window get the same customer
+        let customer: Customer = self
+            .post("/v1/customers", &form, &format!("customer-{user_id}"))
+            .await?;
+        Ok(customer.id)
+    }
+
+    async fn saved_methods(&self, customer: &str) -> Result<Vec<SavedMethod>> {
+        let methods: List<PaymentMethod> = self
+            .get(
+                &format!("/v1/customers/{customer}/payment_methods"),
+                &[("type", "card"), ("limit", "100")],
+            )
+            .await?;
+        Ok(methods
+            .data
+            .into_iter()
+            .filter_map(|method| {
+                let card = method.card?;
+                Some(SavedMethod {
+                    id: method.id,
+                    brand: card.brand,
+                    last4: card.last4,
+                    exp_month: card.exp_month,
+                    exp_year: card.exp_year,
+                })
+            })
+            .collect())
+    }
+
+    async fn charge_saved(
+        &self,
+        order: &CheckoutOrder,
+        customer: &str,
+        method: &str,
+    ) -> Result<String> {
+        let form: Vec<(String, String)> = [
+            ("amount", order.amount.to_string()),
+            ("currency", order.currency.code().to_ascii_lowercase()),
+            ("customer", customer.to_string()),
+            ("payment_method", method.to_string()),
+            ("off_session", "true".to_string()),
+            ("confirm", "true".to_string()),
+            ("description", order.subject.clone()),
+            ("metadata[payment_id]", order.payment_id.clone()),
+            ("metadata[off_session]", "true".to_string()),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+        let intent: PaymentIntent = self
+            .post("/v1/payment_intents", &form, &order.payment_id)
+            .await?;
+        match intent.status.as_str() {
+            "succeeded" | "processing" => Ok(intent.id),
+            status => Err(PaymentError::Provider(format!(
+                "stripe: off-session payment {status}"
+            ))),
+        }
+    }
+
+    async fn refund(&self, refund: &RefundRequest) -> Result<()> {
+        let mut form = vec![
+            (
+                "payment_intent".to_string(),
+                refund.provider_payment_id.clone(),
+            ),
+            ("amount".to_string(), refund.amount.to_string()),
+            (
+                "metadata[payment_id]".to_string(),
+                refund.payment_id.clone(),
+            ),
+        ];
+        if let Some(reason) = &refund.reason {
+            form.push(("metadata[reason]".to_string(), reason.clone()));
+        }
+        let created: Refund = self.post("/v1/refunds", &form, &refund.refund_id).await?;
+        match created.status.as_str() {
+            "failed" | "canceled" => Err(PaymentError::Provider(format!(
+                "stripe: refund {}",
+                created.status
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider() -> StripeProvider {
+        StripeProvider::new(StripeConfig {
+            secret_key: "sk_test".into(),
+            webhook_secret: "whsec_test".into(),
+            api_base: "http://127.0.0.1:9".into(),
+            tolerance_secs: 300,
+        })
+    }
+
+    #[test]
+    fn signatures_are_checked_against_secret_and_time() {
+        let stripe = provider();
+        let body = br#"{"type":"ping"}"#;
+        let header = signature_header("whsec_test", 1_000, body);
+        assert!(stripe.check_signature(&header, body, 1_100).is_ok());
+        // Tampered body, wrong secret, stale timestamp
+        assert!(stripe.check_signature(&header, b"{}", 1_100).is_err());
+        let forged = signature_header("whsec_other", 1_000, body);
+        assert!(stripe.check_signature(&forged, body, 1_100).is_err());
+        assert!(stripe.check_signature(&header, body, 2_000).is_err());
+        // Rotated secrets send several v1 signatures
+        let rotated = format!("{header},v1={}", "00".repeat(32));
+        assert!(stripe.check_signature(&rotated, body, 1_100).is_ok());
+    }
+
+    #[test]
+    fn completed_sessions_decode_to_paid() {
+        let event: Event = serde_json::from_str(
+            r#"{"type":"checkout.session.completed","data":{"object":{
+                "id":"cs_1","client_reference_id":"pay_1","payment_intent":"pi_1",
+                "payment_status":"paid","amount_total":1500,"currency":"usd"}}}"#,
+        )
+        .unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(
+            decode_event(event).ok(),
+            Some(WebhookEvent::Paid {
+                payment_id: "pay_1".into(),
+                provider_payment_id: "pi_1".into(),
+                amount: 1500,
+                currency: Currency::USD,
+            })
+        );
+        let unpaid: Event = serde_json::from_str(
+            r#"{"type":"checkout.session.completed","data":{"object":{
+                "id":"cs_2","client_reference_id":"pay_2","payment_status":"unpaid"}}}"#,
+        )
+        .unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(decode_event(unpaid).ok(), Some(WebhookEvent::Ignored));
+    }
+
+    #[test]
+    fn only_off_session_intents_are_decoded() {
+        let decode = |kind: &str, metadata: &str| {
+            let event: Event = serde_json::from_str(&format!(
+                r#"{{"type":"{kind}","data":{{"object":{{
+                    "id":"pi_9","amount_received":2500,"currency":"cny",
+                    "metadata":{metadata}}}}}}}"#
+            ))
+            .unwrap_or_else(|e| panic!("{e}"));
+            decode_event(event).ok()
+        };
+        let off_session = r#"{"payment_id":"pay_9","off_session":"true"}"#;
+        assert_eq!(
+            decode("payment_intent.succeeded", off_session),
+            Some(WebhookEvent::Paid {
+                payment_id: "pay_9".into(),
+                provider_payment_id: "pi_9".into(),
+                amount: 2500,
+                currency: Currency::CNY,
+            })
+        );
+        assert_eq!(
+            decode("payment_intent.payment_failed", off_session),
+            Some(WebhookEvent::Closed {
+                payment_id: "pay_9".into()
+            })
+        );
+        // Checkout intents are settled through their session
+        let checkout = r#"{"payment_id":"pay_9"}"#;
+        assert_eq!(
+            decode("payment_intent.payment_failed", checkout),
+            Some(WebhookEvent::Ignored)
+        );
+    }
+}
@@ -0,0 +1,426 @@
+//! WeChat Pay API v3, Native payment.
+//!
+//! Checkout returns a `weixin://` code URL the customer scans. Requests are
+//! signed with the merchant private key in the `Authorization` header.
+//! Notifications are signed with the platform key over
+//! `{timestamp}\n{nonce}\n{body}\n` and carry their payload AES-256-GCM
+//! encrypted with the APIv3 key. Refund notifications are acknowledged but
+//! not applied: they report a single refund rather than the refunded total,
+//! and refunds started from the console are applied when WeChat Pay accepts
+//! them.
+
+use crate::config::WechatConfig;
+use crate::provider::{
+    Checkout, CheckoutKind, CheckoutOrder, PaymentProvider, RefundRequest, WebhookEvent,
+};
+use crate::signing::{self, RsaPrivateKey, RsaPublicKey};
+use crate::{PaymentError, Result};
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use burncloud_common::Currency;
+use http::HeaderMap;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+pub const TIMESTAMP_HEADER: &str = "wechatpay-timestamp";
+pub const NONCE_HEADER: &str = "wechatpay-nonce";
+pub const SIGNATURE_HEADER: &str = "wechatpay-signature";
+
+const CURRENCIES: [Currency; 1] = [Currency::CNY];
+
+#[derive(Serialize)]
+struct NativeOrder<'a> {
+    appid: &'a str,
+    mchid: &'a str,
+    description: &'a str,
+    out_trade_no: &'a str,
+    notify_url: &'a str,
+    amount: OrderAmount,
+}
+
+#[derive(Serialize)]
+struct OrderAmount {
+    total: i64,
+    currency: &'static str,
+}
+
+#[derive(Deserialize)]
+struct NativeResponse {
+    code_url: String,
+}
+
+#[derive(Serialize)]
+struct RefundOrder<'a> {
+    out_trade_no: &'a str,
+    out_refund_no: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<&'a str>,
+    amount: RefundAmount,
+}
+
+#[derive(Serialize)]
+struct RefundAmount {
+    refund: i64,
+    total: i64,
+    currency: &'static str,
+}
+
+#[derive(Deserialize)]
+struct RefundResponse {
+    status: String,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    #[serde(default)]
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct Notification {
+    event_type: String,
+    resource: Resource,
+}
+
+/// Encrypted notification payload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Resource {
+    pub algorithm: String,
+    pub ciphertext: String,
+    #[serde(default)]
+    pub associated_data: String,
+    pub nonce: String,
+}
+
+#[derive(Deserialize)]
+struct Transaction {
+    out_trade_no: String,
+    transaction_id: String,
+    trade_state: String,
+    mchid: String,
+    amount: TransactionAmount,
+}
+
+#[derive(Deserialize)]
+struct TransactionAmount {
+    total: i64,
+    #[serde(default)]
+    currency: Option<String>,
+}
+
+/// Encrypt `plaintext` the way WeChat Pay encrypts notification resources
+pub fn encrypt_resource(
+    api_v3_key: &str,
+    nonce: &str,
+    associated_data: &str,
+    plaintext: &[u8],
+) -> Result<Resource> {
+    let cipher = resource_cipher(api_v3_key)?;
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(nonce_bytes(nonce)?),
+            Payload {
+                msg: plaintext,
+                aad: associated_data.as_bytes(),
+            },
+        )
+        .map_err(|_| PaymentError::Config("resource encryption failed".into()))?;
+    Ok(Resource {
+        algorithm: "AEAD_AES_256_GCM".to_string(),
+        ciphertext: STANDARD.encode(ciphertext),
+        associated_data: associated_data.to_string(),
+        nonce: nonce.to_string(),
+    })
+}
+
+fn resource_cipher(api_v3_key: &str) -> Result<Aes256Gcm> {
+    Aes256Gcm::new_from_slice(api_v3_key.as_bytes())
+        .map_err(|_| PaymentError::Config("WECHATPAY_API_V3_KEY must be 32 bytes".into()))
+}
+
+fn nonce_bytes(nonce: &str) -> Result<&[u8]> {
+    if nonce.len() == 12 {
+        Ok(nonce.as_bytes())
+    } else {
+        Err(PaymentError::MalformedWebhook(
+            "resource nonce must be 12 bytes".into(),
+        ))
+    }
+}
+
+/// The string both sides sign for a notification
+pub fn notification_message(timestamp: &str, nonce: &str, body: &[u8]) -> Vec<u8> {
+    let mut message = format!("{timestamp}\n{nonce}\n").into_bytes();
+    message.extend_from_slice(body);
+    message.push(b'\n');
+    message
+}
+
+pub struct WechatProvider {
+    config: WechatConfig,
+    private_key: RsaPrivateKey,
+    platform_key: RsaPublicKey,
+    cipher: Aes256Gcm,
+    http: reqwest::Client,
+}
+
+impl WechatProvider {
+    pub fn new(config: WechatConfig) -> Result<Self> {
+        Ok(Self {
+            private_key: signing::parse_private_key(&config.private_key)?,
+            platform_key: signing::parse_public_key(&config.platform_public_key)?,
+            cipher: resource_cipher(&config.api_v3_key)?,
+            config,
+            http: reqwest::Client::new(),
+        })
+    }
+
+    /// `Authorization` header of a request
+    fn authorization(&self, method: &str, path: &str, body: &str) -> String {
+        let timestamp = chrono::Utc::now().timestamp();
+        let nonce: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+        let message = format!("{method}\n{path}\n{timestamp}\n{nonce}\n{body}\n");
+        let signature = signing::sign(&self.private_key, message.as_bytes());
+        format!(
+            "WECHATPAY2-SHA256-RSA2048 mchid=\"{}\",nonce_str=\"{nonce}\",signature=\"{signature}\",timestamp=\"{timestamp}\",serial_no=\"{}\"",
+            self.config.mch_id, self.config.serial_no
+        )
+    }
+
+    async fn post<T: serde::de::DeserializeOwned>(&self, path: &str, body: String) -> Result<T> {
+        let response = self
+            .http
+            .post(format!(
+                "{}{path}",
+                self.config.api_base.trim_end_matches('/')
+            ))
+            .header("Authorization", self.authorization("POST", path, &body))
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/json")
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| PaymentError::Provider(format!("wechat: {e}")))?;
+        let status = response.status();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| PaymentError::Provider(format!("wechat: {e}")))?;
+        if !status.is_success() {
+            let message = serde_json::from_slice::<ApiError>(&body)
+                .map(|e| e.message)
+                .unwrap_or_else(|_| status.to_string());
+            return Err(PaymentError::Provider(format!("wechat: {message}")));
+        }
+        serde_json::from_slice(&body)
+            .map_err(|e| PaymentError::Provider(format!("wechat: unexpected response: {e}")))
+    }
+
+    fn decrypt(&self, resource: &Resource) -> Result<Vec<u8>> {
+        if resource.algorithm != "AEAD_AES_256_GCM" {
+            return Err(PaymentError::MalformedWebhook(format!(
+                "unsupported algorithm {}",
+                resource.algorithm
+            )));
+        }
+        let ciphertext = STANDARD
+            .decode(&resource.ciphertext)
+            .map_err(|e| PaymentError::MalformedWebhook(e.to_string()))?;
+        self.cipher
+            .decrypt(
+                Nonce::from_slice(nonce_bytes(&resource.nonce)?),
+                Payload {
+                    msg: &ciphertext,
+                    aad: resource.associated_data.as_bytes(),
+                },
+            )
+            .map_err(|_| PaymentError::InvalidSignature)
+    }
+
+    fn check_signature(&self, headers: &HeaderMap, body: &[u8], now: i64) -> Result<()> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .ok_or(PaymentError::InvalidSignature)
+        };
+        let timestamp = header(TIMESTAMP_HEADER)?;
+        let sent_at: i64 = timestamp
+            .parse()
+            .map_err(|_| PaymentError::InvalidSignature)?;
+        if (now - sent_at).abs() > self.config.tolerance_secs {
+            return Err(PaymentError::InvalidSignature);
+        }
+        let message = notification_message(timestamp, header(NONCE_HEADER)?, body);
+        if signing::verify(&self.platform_key, &message, header(SIGNATURE_HEADER)?) {
+            Ok(())
+        } else {
+            Err(PaymentError::InvalidSignature)
+        }
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for WechatProvider {
+    fn name(&self) -> &'static str {
+        "wechat"
+    }
+
+    fn currencies(&self) -> &[Currency] {
+        &CURRENCIES
+    }
+
+    async fn create_checkout(&self, order: &CheckoutOrder) -> Result<Checkout> {
+        let body = serde_json::to_string(&NativeOrder {
+            appid: &self.config.app_id,
+            mchid: &self.config.mch_id,
+            description: &order.subject,
+            out_trade_no: &order.payment_id,
+            notify_url: &order.notify_url,
+            amount: OrderAmount {
+                total: order.amount,
+                currency: "CNY",
+            },
+        })
+        .map_err(|e| PaymentError::Provider(e.to_string()))?;
+        let response: NativeResponse = self.post("/v3/pay/transactions/native", body).await?;
+        Ok(Checkout {
+            session_id: None,
+            url: response.code_url,
+            kind: CheckoutKind::QrCode,
+        })
+    }
+
+    fn verify_webhook(&self, headers: &HeaderMap, body: &[u8]) -> Result<WebhookEvent> {
+        self.check_signature(headers, body, chrono::Utc::now().timestamp())?;
+        let notification: Notification = serde_json::from_slice(body)
+            .map_err(|e| PaymentError::MalformedWebhook(e.to_string()))?;
+        if notification.event_type != "TRANSACTION.SUCCESS" {
+            return Ok(WebhookEvent::Ignored);
+        }
+        let plaintext = self.decrypt(&notification.resource)?;
+        let transaction: Transaction = serde_json::from_slice(&plaintext)
+            .map_err(|e| PaymentError::MalformedWebhook(e.to_string()))?;
+        if transaction.mchid != self.config.mch_id {
+            return Err(PaymentError::MalformedWebhook(
+                "notification for another merchant".into(),
+            ));
+        }
+        if transaction.trade_state != "SUCCESS" {
+            return Ok(WebhookEvent::Ignored);
+        }
+        Ok(WebhookEvent::Paid {
+            payment_id: transaction.out_trade_no,
+            provider_payment_id: transaction.transaction_id,
+            amount: transaction.amount.total,
+            currency: transaction
+                .amount
+                .currency
+                .as_deref()
+                .unwrap_or("CNY")
+                .parse()
+                .map_err(PaymentError::MalformedWebhook)?,
+        })
+    }
+
+    async fn refund(&self, refund: &RefundRequest) -> Result<()> {
+        let body = serde_json::to_string(&RefundOrder {
+            out_trade_no: &refund.payment_id,
+            out_refund_no: &refund.refund_id,
+            reason: refund.reason.as_deref(),
+            amount: RefundAmount {
+                refund: refund.amount,
+                total: refund.total,
+                currency: "CNY",
+            },
+        })
+        .map_err(|e| PaymentError::Provider(e.to_string()))?;
+        let response: RefundResponse = self.post("/v3/refund/domestic/refunds", body).await?;
+        match response.status.as_str() {
+            "SUCCESS" | "PROCESSING" => Ok(()),
+            other => Err(PaymentError::Provider(format!("wechat: refund {other}"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rsa::pkcs8::{EncodePrivateKey, EncodePublicKey, LineEnding};
+
+    const API_V3_KEY: &str = "0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn notifications_are_verified_and_decrypted() {
+        let rng = &mut rand::thread_rng();
+        let merchant = RsaPrivateKey::new(rng, 1024).unwrap_or_else(|e| panic!("{e}"));
+        let platform = RsaPrivateKey::new(rng, 1024).unwrap_or_else(|e| panic!("{e}"));
+        let provider = WechatProvider::new(WechatConfig {
+            mch_id: "1900000001".into(),
+            app_id: "wx0000000000000001".into(),
+            serial_no: "SERIAL".into(),
+            private_key: merchant
+                .to_pkcs8_pem(LineEnding::LF)
+                .unwrap_or_else(|e| panic!("{e}"))
+                .to_string(),
+            platform_public_key: RsaPublicKey::from(&platform)
+                .to_public_key_pem(LineEnding::LF)
+                .unwrap_or_else(|e| panic!("{e}")),
+            api_v3_key: API_V3_KEY.into(),
+            api_base: "http://127.0.0.1:9".into(),
+            tolerance_secs: 300,
+        })
+        .unwrap_or_else(|e| panic!("{e}"));
+
+        let transaction = br#"{"mchid":"1900000001","out_trade_no":"pay_1",
+            "transaction_id":"4200000001","trade_state":"SUCCESS",
+            "amount":{"total":2500,"currency":"CNY"}}"#;
+        let resource = encrypt_resource(API_V3_KEY, "nonce0000001", "transaction", transaction)
+            .unwrap_or_else(|e| panic!("{e}"));
+        let body = format!(
+            r#"{{"id":"evt_1","event_type":"TRANSACTION.SUCCESS","resource":{}}}"#,
+            serde_json::to_string(&resource).unwrap_or_default()
+        );
+        let timestamp = chrono::Utc::now().timestamp().to_string();
+        let signature = signing::sign(
+            &platform,
+            &notification_message(&timestamp, "n0nce", body.as_bytes()),
+        );
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            TIMESTAMP_HEADER,
+            timestamp.parse().unwrap_or_else(|_| unreachable!()),
+        );
+        headers.insert(
+            NONCE_HEADER,
+            "n0nce".parse().unwrap_or_else(|_| unreachable!()),
+        );
+        headers.insert(
+            SIGNATURE_HEADER,
+            signature.parse().unwrap_or_else(|_| unreachable!()),
+        );
+
+        assert_eq!(
+            provider.verify_webhook(&headers, body.as_bytes()).ok(),
+            Some(WebhookEvent::Paid {
+                payment_id: "pay_1".into(),
+                provider_payment_id: "4200000001".into(),
+                amount: 2500,
+                currency: Currency::CNY,
+            })
+        );
+        let tampered = body.replace("evt_1", "evt_2");
+        assert!(matches!(
+            provider.verify_webhook(&headers, tampered.as_bytes()),
+            Err(PaymentError::InvalidSignature)
+        ));
+    }
+}
@@ -1,14 +1,29 @@
 //! # BurnCloud Service Router Log
 //!
 //! Router log service layer providing business logic for router logs,
-//! usage statistics, and balance deductions.
+//! usage statistics, balance deductions, log search and export, log retention
+//! and usage rollups.
+
+pub mod query;
+pub mod retention;
+pub mod rollup;
 
 use burncloud_database::Database;
-use burncloud_database_router::{BalanceModel, RouterDatabase, RouterLogModel};
+use burncloud_database_router::{
+    BalanceModel, RouterDatabase, RouterLogModel, RouterRequestLogModel,
+};
 
 pub use burncloud_database_router::{
-    BillingModelSummary, BillingSummary, ModelUsageStats, RouterLog, UsageStats,
+    BillingModelSummary, BillingSummary, LogFilter, ModelUsageStats, RollupCounters,
+    RollupDimension, RollupGranularity, RollupQuery, RollupRebuildReport, RollupRow, RouterLog,
+    RouterRequestLog, UsageStats,
+};
+pub use query::{parse_log_time, parse_unix_time, ExportFormat};
+pub use retention::{
+    LogRetentionService, LogTable, RestoreReport, RetentionError, RetentionPolicy, RetentionReport,
+    RetentionStats, TableReport,
 };
+pub use rollup::{CompactionReport, RollupPolicy, UsageRollupService};
 
 type Result<T> = std::result::Result<T, burncloud_database::DatabaseError>;
 
@@ -38,6 +53,29 @@ impl RouterLogService {
         RouterLogModel::get_filtered(db, user_id, upstream_id, model, limit, offset).await
     }
 
+    /// Search logs newest first, continuing after the `before_id` cursor
+    pub async fn search(
+        db: &Database,
+        filter: &LogFilter,
+        before_id: Option<i64>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<RouterLog>> {
+        RouterLogModel::search(db, filter, before_id, limit, offset).await
+    }
+
+    /// Get a log and its stored request/response detail by request_id
+    pub async fn get_with_detail(
+        db: &Database,
+        request_id: &str,
+    ) -> Result<Option<(RouterLog, Option<RouterRequestLog>)>> {
+        let Some(log) = RouterLogModel::get_by_request_id(db, request_id).await? else {
+            return Ok(None);
+        };
+        let detail = RouterRequestLogModel::get_by_request_id(db, request_id).await?;
+        Ok(Some((log, detail)))
+    }
+
     /// Get total usage by user
     pub async fn get_usage_by_user(db: &Database, user_id: &str) -> Result<(i64, i64)> {
         RouterLogModel::get_usage_by_user(db, user_id).await
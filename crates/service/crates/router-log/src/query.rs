@@ -0,0 +1,156 @@
+//! Helpers for log search and export: time-bound parsing for [`LogFilter`]
+//! and rollup queries, and CSV / JSON Lines rendering of [`RouterLog`] rows.
+//!
+//! [`LogFilter`]: burncloud_database_router::LogFilter
+
+use burncloud_database_router::RouterLog;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+
+/// Format `created_at` is stored and compared in.
+const DB_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Normalize a user-supplied time bound to UTC `YYYY-MM-DD HH:MM:SS`.
+///
+/// Accepts RFC 3339 (`2026-05-10T08:00:00+08:00`), `YYYY-MM-DD HH:MM:SS` or
+/// `YYYY-MM-DDTHH:MM:SS` (taken as UTC), a bare date (midnight UTC), or unix
+/// seconds. Returns `None` for anything else.
+pub fn parse_log_time(input: &str) -> Option<String> {
+    parse_time(input).map(|t| t.format(DB_TIME_FORMAT).to_string())
+}
+
+/// [`parse_log_time`] as unix seconds.
+pub fn parse_unix_time(input: &str) -> Option<i64> {
+    parse_time(input).map(|t| t.and_utc().timestamp())
+}
+
+fn parse_time(input: &str) -> Option<NaiveDateTime> {
+    let input = input.trim();
+    if input.is_empty() {
+        return None;
+    }
+    if input.bytes().all(|b| b.is_ascii_digit()) {
+        let secs: i64 = input.parse().ok()?;
+        return DateTime::<Utc>::from_timestamp(secs, 0).map(|t| t.naive_utc());
+    }
+    if let Ok(t) = DateTime::parse_from_rfc3339(input) {
+        return Some(t.naive_utc());
+    }
+    for format in [DB_TIME_FORMAT, "%Y-%m-%dT%H:%M:%S"] {
+        if let Ok(t) = NaiveDateTime::parse_from_str(input, format) {
+            return Some(t);
+        }
+    }
+    NaiveDate::parse_from_str(input, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+}
+
+/// Output format of a log export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One header row, then the summary columns of each log.
+    Csv,
+    /// One JSON object per line with every field of the log.
+    Jsonl,
+}
+
+const CSV_COLUMNS: [&str; 21] = [
+    "id",
+    "request_id",
+    "created_at",
+    "user_id",
+    "token_hash",
+    "path",
+    "model",
+    "upstream_id",
+    "status_code",
+    "latency_ms",
+    "prompt_tokens",
+    "completion_tokens",
+    "cache_read_tokens",
+    "cache_write_tokens",
+    "reasoning_tokens",
+    "cost",
+    "cost_status",
+    "error_type",
+    "layer_decision",
+    "traffic_color",
+    "pricing_region",
+];
+
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "jsonl" | "ndjson" => Some(Self::Jsonl),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Jsonl => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Jsonl => "jsonl",
+        }
+    }
+
+    /// Text written before the first row, if any.
+    pub fn header(&self) -> Option<String> {
+        match self {
+            Self::Csv => Some(format!("{}\n", CSV_COLUMNS.join(","))),
+            Self::Jsonl => None,
+        }
+    }
+
+    /// One log rendered as a newline-terminated line.
+    pub fn row(&self, log: &RouterLog) -> Result<String, serde_json::Error> {
+        match self {
+            Self::Csv => Ok(csv_row(log)),
+            Self::Jsonl => serde_json::to_string(log).map(|line| line + "\n"),
+        }
+    }
+}
+
+fn csv_row(log: &RouterLog) -> String {
+    let text = |v: &Option<String>| csv_field(v.as_deref().unwrap_or(""));
+    let fields = [
+        log.id.to_string(),
+        csv_field(&log.request_id),
+        text(&log.created_at),
+        text(&log.user_id),
+        text(&log.token_hash),
+        csv_field(&log.path),
+        text(&log.model),
+        text(&log.upstream_id),
+        log.status_code.to_string(),
+        log.latency_ms.to_string(),
+        log.prompt_tokens.to_string(),
+        log.completion_tokens.to_string(),
+        log.cache_read_tokens.to_string(),
+        log.cache_write_tokens.to_string(),
+        log.reasoning_tokens.to_string(),
+        log.cost.to_string(),
+        text(&log.cost_status),
+        text(&log.error_type),
+        text(&log.layer_decision),
+        text(&log.traffic_color),
+        text(&log.pricing_region),
+    ];
+    format!("{}\n", fields.join(","))
+}
+
+/// Quote a CSV field when it holds a delimiter, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,534 @@
+//! Request-log retention.
+//!
+//! `router_logs` (request metadata) and `router_request_logs` (request and
+//! response bodies) have separate retention periods. Expired rows are written
+//! to gzip-compressed JSONL files in the archive directory, one file per batch,
+//! and only deleted once their file has been fully written and synced. Archives
+//! can be loaded back with [`LogRetentionService::restore`].
+//!
+//! Pruning is opt-in: both tables are kept forever until a retention period
+//! is configured.
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use burncloud_database::{Database, DatabaseError};
+use burncloud_database_router::{LogRetentionModel, RouterLog, RouterRequestLog};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+
+/// Suffix of archive files.
+pub const ARCHIVE_EXTENSION: &str = ".jsonl.gz";
+
+#[derive(Debug, thiserror::Error)]
+pub enum RetentionError {
+    #[error("database error: {0}")]
+    Database(#[from] DatabaseError),
+    #[error("archive I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("archive encoding error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("not a log archive: {0}")]
+    UnknownArchive(String),
+}
+
+pub type Result<T> = std::result::Result<T, RetentionError>;
+
+/// Which log table a retention pass or archive file covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogTable {
+    /// `router_logs`: per-request metadata, usage and cost.
+    RouterLogs,
+    /// `router_request_logs`: sanitized request/response bodies.
+    RequestLogs,
+}
+
+impl LogTable {
+    pub fn table_name(&self) -> &'static str {
+        match self {
+            LogTable::RouterLogs => "router_logs",
+            LogTable::RequestLogs => "router_request_logs",
+        }
+    }
+
+    /// Table of an archive file, from its `<table>-...` file name.
+    pub fn from_archive_name(name: &str) -> Option<Self> {
+        [LogTable::RequestLogs, LogTable::RouterLogs]
+            .into_iter()
+            .find(|t| {
+                name.strip_prefix(t.table_name())
+                    .is_some_and(|rest| rest.starts_with('-'))
+                    && name.ends_with(ARCHIVE_EXTENSION)
+            })
+    }
+}
+
+/// Retention settings for both log tables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Days `router_logs` rows are kept; `None` keeps them forever.
+    pub log_days: Option<u32>,
+    /// Days `router_request_logs` rows are kept; `None` keeps them forever.
+    pub request_log_days: Option<u32>,
+    /// Where expired rows are archived before purging; `None` purges without archiving.
+    pub archive_dir: Option<PathBuf>,
+    /// Time between scheduled passes; `None` disables the scheduler.
+    pub interval: Option<Duration>,
+    /// Rows per archive file / delete batch.
+    pub batch_size: i64,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            log_days: None,
+            request_log_days: None,
+            archive_dir: default_archive_dir(),
+            interval: Some(Duration::from_secs(6 * 3600)),
+            batch_size: 1000,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Read the policy from the environment, falling back to the defaults.
+    ///
+    /// - `BURNCLOUD_LOG_RETENTION_DAYS`: `router_logs` retention, `0` keeps forever (default)
+    /// - `BURNCLOUD_REQUEST_LOG_RETENTION_DAYS`: `router_request_logs` retention, `0` keeps
+    ///   forever (default)
+    /// - `BURNCLOUD_LOG_ARCHIVE_DIR`: archive directory, `off` purges without archiving
+    ///   (default `archive/` next to the default database)
+    /// - `BURNCLOUD_LOG_RETENTION_INTERVAL_SECS`: scheduler interval, `0` disables it (default 21600)
+    pub fn from_env() -> Self {
+        let mut policy = Self::default();
+        if let Some(days) = env_u32("BURNCLOUD_LOG_RETENTION_DAYS") {
+            policy.log_days = (days > 0).then_some(days);
+        }
+        if let Some(days) = env_u32("BURNCLOUD_REQUEST_LOG_RETENTION_DAYS") {
+            policy.request_log_days = (days > 0).then_some(days);
+        }
+        if let Ok(dir) = std::env::var("BURNCLOUD_LOG_ARCHIVE_DIR") {
+            let dir = dir.trim();
+            policy.archive_dir = if dir.is_empty() || dir.eq_ignore_ascii_case("off") {
+                None
+            } else {
+                Some(PathBuf::from(dir))
+            };
+        }
+        if let Some(secs) = env_u32("BURNCLOUD_LOG_RETENTION_INTERVAL_SECS") {
+            policy.interval = (secs > 0).then(|| Duration::from_secs(u64::from(secs)));
+        }
+        policy
+    }
+
+    /// Retention of `table`. Bodies never outlive their metadata row, so
+    /// request logs use the shorter of the two periods.
+    pub fn days_for(&self, table: LogTable) -> Option<u32> {
+        match table {
+            LogTable::RouterLogs => self.log_days,
+            LogTable::RequestLogs => match (self.request_log_days, self.log_days) {
+                (Some(body), Some(meta)) => Some(body.min(meta)),
+                (body, meta) => body.or(meta),
+            },
+        }
+    }
+}
+
+pub(crate) fn env_u32(name: &str) -> Option<u32> {
+    std::env::var(name).ok().and_then(|v| v.trim().parse().ok())
+}
+
+fn default_archive_dir() -> Option<PathBuf> {
+    burncloud_database::get_default_database_path()
+        .ok()
+        .and_then(|p| p.parent().map(|dir| dir.join("archive")))
+}
+
+/// `created_at` cutoff for rows older than `days`.
+pub fn cutoff_for_days(days: u32) -> String {
+    (chrono::Utc::now() - chrono::Duration::days(i64::from(days)))
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+/// Outcome of archiving and/or purging one table.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableReport {
+    pub rows_archived: u64,
+    pub rows_purged: u64,
+    pub archive_files: u64,
+    /// Compressed bytes written.
+    pub archive_bytes: u64,
+}
+
+impl TableReport {
+    fn add(&mut self, other: &TableReport) {
+        self.rows_archived += other.rows_archived;
+        self.rows_purged += other.rows_purged;
+        self.archive_files += other.archive_files;
+        self.archive_bytes += other.archive_bytes;
+    }
+}
+
+/// Outcome of one retention pass over both tables.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionReport {
+    pub router_logs: TableReport,
+    pub request_logs: TableReport,
+}
+
+/// Outcome of [`LogRetentionService::restore`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RestoreReport {
+    pub files: u64,
+    pub rows_restored: u64,
+    /// Rows whose `request_id` was already present.
+    pub rows_skipped: u64,
+}
+
+/// Retention state reported by the monitor API.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RetentionStats {
+    pub log_days: Option<u32>,
+    pub request_log_days: Option<u32>,
+    pub archive_dir: Option<String>,
+    /// Unix seconds of the last completed scheduled or manual pass.
+    pub last_run_at: Option<i64>,
+    pub last_error: Option<String>,
+    pub last_report: Option<RetentionReport>,
+    /// Totals since this process started.
+    pub rows_archived_total: u64,
+    pub rows_purged_total: u64,
+    /// Current contents of the archive directory.
+    pub archive_files: u64,
+    pub archive_bytes: u64,
+}
+
+/// Archives and purges expired request logs.
+pub struct LogRetentionService {
+    policy: RetentionPolicy,
+    stats: Mutex<RetentionStats>,
+}
+
+impl LogRetentionService {
+    pub fn new(policy: RetentionPolicy) -> Self {
+        Self {
+            policy,
+            stats: Mutex::new(RetentionStats::default()),
+        }
+    }
+
+    pub fn policy(&self) -> &RetentionPolicy {
+        &self.policy
+    }
+
+    /// Run the policy every `interval` in the background. Does nothing when
+    /// the scheduler is disabled or no table has a retention period.
+    pub fn start(self: Arc<Self>, db: Arc<Database>) -> Option<tokio::task::JoinHandle<()>> {
+        let interval = self.policy.interval?;
+        if self.policy.log_days.is_none() && self.policy.request_log_days.is_none() {
+            return None;
+        }
+        Some(tokio::spawn(async move {
+            // First pass one interval after startup, off the boot path
+            let mut ticker =
+                tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                match self.run(&db).await {
+                    Ok(report) => tracing::info!(
+                        logs_purged = report.router_logs.rows_purged,
+                        bodies_purged = report.request_logs.rows_purged,
+                        "Log retention pass finished"
+                    ),
+                    Err(e) => tracing::error!("Log retention pass failed: {e}"),
+                }
+            }
+        }))
+    }
+
+    /// One retention pass: bodies first, then metadata, so no body is
+    /// removed by cascade before it has been archived.
+    pub async fn run(&self, db: &Database) -> Result<RetentionReport> {
+        let result = self.run_pass(db).await;
+        self.record(&result);
+        result
+    }
+
+    async fn run_pass(&self, db: &Database) -> Result<RetentionReport> {
+        let mut report = RetentionReport::default();
+        let archive_dir = self.policy.archive_dir.as_deref();
+        if let Some(days) = self.policy.days_for(LogTable::RequestLogs) {
+            report.request_logs = prune(
+                db,
+                LogTable::RequestLogs,
+                days,
+                archive_dir,
+                self.policy.batch_size,
+            )
+            .await?;
+        }
+        if let Some(days) = self.policy.days_for(LogTable::RouterLogs) {
+            report.router_logs = prune(
+                db,
+                LogTable::RouterLogs,
+                days,
+                archive_dir,
+                self.policy.batch_size,
+            )
+            .await?;
+        }
+        Ok(report)
+    }
+
+    fn record(&self, result: &Result<RetentionReport>) {
+        let mut stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        stats.last_run_at = Some(chrono::Utc::now().timestamp());
+        match result {
+            Ok(report) => {
+                let mut total = report.router_logs.clone();
+                total.add(&report.request_logs);
+                stats.rows_archived_total += total.rows_archived;
+                stats.rows_purged_total += total.rows_purged;
+                stats.last_report = Some(report.clone());
+                stats.last_error = None;
+            }
+            Err(e) => stats.last_error = Some(e.to_string()),
+        }
+    }
+
+    /// Current policy, run history and archive directory usage.
+    pub fn stats(&self) -> RetentionStats {
+        let mut stats = self.stats.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        stats.log_days = self.policy.log_days;
+        stats.request_log_days = self.policy.request_log_days;
+        stats.archive_dir = self
+            .policy
+            .archive_dir
+            .as_ref()
+            .map(|d| d.display().to_string());
+        if let Some(dir) = self.policy.archive_dir.as_deref() {
+            let (files, bytes) = archive_usage(dir);
+            stats.archive_files = files;
+            stats.archive_bytes = bytes;
+        }
+        stats
+    }
+
+    /// Load archive files back into the database. `path` may be a single
+    /// archive or a directory of archives. Rows already present are skipped.
+    pub async fn restore(db: &Database, path: &Path) -> Result<RestoreReport> {
+        let mut files: Vec<(LogTable, PathBuf)> = if path.is_dir() {
+            let mut found = Vec::new();
+            for entry in std::fs::read_dir(path)? {
+                let file = entry?.path();
+                if let Some(table) = file
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .and_then(LogTable::from_archive_name)
+                {
+                    found.push((table, file));
+                }
+            }
+            found
+        } else {
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            let table = LogTable::from_archive_name(name)
+                .ok_or_else(|| RetentionError::UnknownArchive(path.display().to_string()))?;
+            vec![(table, path.to_path_buf())]
+        };
+        // Metadata before bodies (bodies reference router_logs.request_id)
+        files.sort_by_key(|(table, file)| (*table == LogTable::RequestLogs, file.clone()));
+
+        let mut report = RestoreReport::default();
+        for (table, file) in files {
+            let lines = read_archive(&file).await?;
+            for line in lines.iter().filter(|l| !l.trim().is_empty()) {
+                let restored = match table {
+                    LogTable::RouterLogs => {
+                        let row: RouterLog = serde_json::from_str(line)?;
+                        LogRetentionModel::restore_log(db, &row).await?
+                    }
+                    LogTable::RequestLogs => {
+                        let row: RouterRequestLog = serde_json::from_str(line)?;
+                        LogRetentionModel::restore_request_log(db, &row).await?
+                    }
+                };
+                if restored {
+                    report.rows_restored += 1;
+                } else {
+                    report.rows_skipped += 1;
+                }
+            }
+            report.files += 1;
+        }
+        Ok(report)
+    }
+}
+
+/// Number of rows of `table` older than `days` (for dry runs).
+pub async fn count_expired(db: &Database, table: LogTable, days: u32) -> Result<i64> {
+    let cutoff = cutoff_for_days(days);
+    let count = match table {
+        LogTable::RouterLogs => LogRetentionModel::count_expired_logs(db, &cutoff).await?,
+        LogTable::RequestLogs => LogRetentionModel::count_expired_request_logs(db, &cutoff).await?,
+    };
+    Ok(count)
+}
+
+/// Write rows of `table` older than `days` to `archive_dir` without deleting them.
+pub async fn archive(
+    db: &Database,
+    table: LogTable,
+    days: u32,
+    archive_dir: &Path,
+    batch_size: i64,
+) -> Result<TableReport> {
+    process(db, table, days, Some(archive_dir), batch_size, false).await
+}
+
+/// Archive (when `archive_dir` is set) and delete rows of `table` older than `days`.
+pub async fn prune(
+    db: &Database,
+    table: LogTable,
+    days: u32,
+    archive_dir: Option<&Path>,
+    batch_size: i64,
+) -> Result<TableReport> {
+    process(db, table, days, archive_dir, batch_size, true).await
+}
+
+/// Expired rows of one batch as ids and JSONL lines.
+struct Batch {
+    ids: Vec<i64>,
+    lines: Vec<String>,
+}
+
+async fn fetch_batch(
+    db: &Database,
+    table: LogTable,
+    cutoff: &str,
+    after_id: i64,
+    limit: i64,
+) -> Result<Batch> {
+    let mut batch = Batch {
+        ids: Vec::new(),
+        lines: Vec::new(),
+    };
+    match table {
+        LogTable::RouterLogs => {
+            for row in LogRetentionModel::expired_logs(db, cutoff, after_id, limit).await? {
+                batch.ids.push(row.id);
+                batch.lines.push(serde_json::to_string(&row)?);
+            }
+        }
+        LogTable::RequestLogs => {
+            for row in LogRetentionModel::expired_request_logs(db, cutoff, after_id, limit).await? {
+                batch.ids.push(row.id);
+                batch.lines.push(serde_json::to_string(&row)?);
+            }
+        }
+    }
+    Ok(batch)
+}
+
+async fn process(
+    db: &Database,
+    table: LogTable,
+    days: u32,
+    archive_dir: Option<&Path>,
+    batch_size: i64,
+    purge: bool,
+) -> Result<TableReport> {
+    let cutoff = cutoff_for_days(days);
+    let batch_size = batch_size.max(1);
+    let mut report = TableReport::default();
+    let mut after_id = 0;
+
+    loop {
+        let batch = fetch_batch(db, table, &cutoff, after_id, batch_size).await?;
+        let (Some(&first), Some(&last)) = (batch.ids.first(), batch.ids.last()) else {
+            break;
+        };
+        after_id = last;
+
+        if let Some(dir) = archive_dir {
+            let name = format!(
+                "{}-{}-{first}-{last}{ARCHIVE_EXTENSION}",
+                table.table_name(),
+                chrono::Utc::now().format("%Y%m%dT%H%M%S")
+            );
+            let bytes = write_archive(dir.join(name), batch.lines).await?;
+            report.rows_archived += batch.ids.len() as u64;
+            report.archive_files += 1;
+            report.archive_bytes += bytes;
+        }
+        if purge {
+            report.rows_purged += match table {
+                LogTable::RouterLogs => LogRetentionModel::delete_logs(db, &batch.ids).await?,
+                LogTable::RequestLogs => {
+                    LogRetentionModel::delete_request_logs(db, &batch.ids).await?
+                }
+            };
+        }
+        if (batch.ids.len() as i64) < batch_size {
+            break;
+        }
+    }
+    Ok(report)
+}
+
+/// Write a gzip JSONL file atomically (temp file, fsync, rename) and return its size.
+async fn write_archive(path: PathBuf, lines: Vec<String>) -> Result<u64> {
+    tokio::task::spawn_blocking(move || -> Result<u64> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("gz.tmp");
+        let file = std::fs::File::create(&tmp)?;
+        let mut encoder = GzEncoder::new(std::io::BufWriter::new(file), Compression::default());
+        for line in &lines {
+            encoder.write_all(line.as_bytes())?;
+            encoder.write_all(b"\n")?;
+        }
+        let file = encoder.finish()?.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(std::fs::metadata(&path)?.len())
+    })
+    .await
+    .map_err(|e| RetentionError::Io(std::io::Error::other(e)))?
+}
+
+async fn read_archive(path: &Path) -> Result<Vec<String>> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || -> Result<Vec<String>> {
+        let reader = BufReader::new(GzDecoder::new(std::fs::File::open(&path)?));
+        Ok(reader.lines().collect::<std::io::Result<Vec<_>>>()?)
+    })
+    .await
+    .map_err(|e| RetentionError::Io(std::io::Error::other(e)))?
+}
+
+/// (file count, total bytes) of the archives in `dir`.
+fn archive_usage(dir: &Path) -> (u64, u64) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return (0, 0);
+    };
+    entries
+        .flatten()
+        .filter(|e| {
+            e.file_name()
+                .to_str()
+                .and_then(LogTable::from_archive_name)
+                .is_some()
+        })
+        .filter_map(|e| e.metadata().ok())
+        .fold((0, 0), |(files, bytes), m| (files + 1, bytes + m.len()))
+}
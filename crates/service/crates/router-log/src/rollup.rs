@@ -0,0 +1,163 @@
+//! Usage rollups: backfill, queries and compaction.
+//!
+//! The router's logging task keeps minute, hour and day rollups current.
+//! Fine-grained rows are only useful for recent dashboards, so a scheduled
+//! compaction drops minute and hour rows past their retention while day
+//! rows are kept.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use burncloud_database::{Database, DatabaseError};
+use burncloud_database_router::{
+    RollupGranularity, RollupQuery, RollupRebuildReport, RollupRow, UsageRollupModel,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::retention::env_u32;
+
+type Result<T> = std::result::Result<T, DatabaseError>;
+
+/// How long each rollup granularity is kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RollupPolicy {
+    /// Days minute rows are kept; `None` keeps them forever.
+    pub minute_days: Option<u32>,
+    /// Days hour rows are kept; `None` keeps them forever.
+    pub hour_days: Option<u32>,
+    /// Days day rows are kept; `None` keeps them forever.
+    pub day_days: Option<u32>,
+    /// Time between compaction passes; `None` disables the scheduler.
+    pub interval: Option<Duration>,
+}
+
+impl Default for RollupPolicy {
+    fn default() -> Self {
+        Self {
+            minute_days: Some(2),
+            hour_days: Some(35),
+            day_days: None,
+            interval: Some(Duration::from_secs(3600)),
+        }
+    }
+}
+
+impl RollupPolicy {
+    /// Read the policy from the environment, falling back to the defaults.
+    ///
+    /// - `BURNCLOUD_ROLLUP_MINUTE_DAYS`: minute rows, `0` keeps forever (default 2)
+    /// - `BURNCLOUD_ROLLUP_HOUR_DAYS`: hour rows, `0` keeps forever (default 35)
+    /// - `BURNCLOUD_ROLLUP_DAY_DAYS`: day rows, `0` keeps forever (default 0)
+    /// - `BURNCLOUD_ROLLUP_COMPACT_INTERVAL_SECS`: scheduler interval, `0` disables it (default 3600)
+    pub fn from_env() -> Self {
+        let mut policy = Self::default();
+        let days = |name| env_u32(name).map(|d| (d > 0).then_some(d));
+        if let Some(d) = days("BURNCLOUD_ROLLUP_MINUTE_DAYS") {
+            policy.minute_days = d;
+        }
+        if let Some(d) = days("BURNCLOUD_ROLLUP_HOUR_DAYS") {
+            policy.hour_days = d;
+        }
+        if let Some(d) = days("BURNCLOUD_ROLLUP_DAY_DAYS") {
+            policy.day_days = d;
+        }
+        if let Some(secs) = env_u32("BURNCLOUD_ROLLUP_COMPACT_INTERVAL_SECS") {
+            policy.interval = (secs > 0).then(|| Duration::from_secs(u64::from(secs)));
+        }
+        policy
+    }
+
+    /// Retention of `granularity`.
+    pub fn days_for(&self, granularity: RollupGranularity) -> Option<u32> {
+        match granularity {
+            RollupGranularity::Minute => self.minute_days,
+            RollupGranularity::Hour => self.hour_days,
+            RollupGranularity::Day => self.day_days,
+        }
+    }
+}
+
+/// Rows removed by one compaction pass.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompactionReport {
+    pub minute_rows: u64,
+    pub hour_rows: u64,
+    pub day_rows: u64,
+}
+
+pub struct UsageRollupService {
+    policy: RollupPolicy,
+}
+
+impl UsageRollupService {
+    pub fn new(policy: RollupPolicy) -> Self {
+        Self { policy }
+    }
+
+    pub fn policy(&self) -> &RollupPolicy {
+        &self.policy
+    }
+
+    /// Run compaction every `interval` in the background. Does nothing when
+    /// the scheduler is disabled.
+    pub fn start(self: Arc<Self>, db: Arc<Database>) -> Option<tokio::task::JoinHandle<()>> {
+        let interval = self.policy.interval?;
+        Some(tokio::spawn(async move {
+            let mut ticker =
+                tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                match self.compact(&db, now()).await {
+                    Ok(report) => tracing::info!(
+                        minute_rows = report.minute_rows,
+                        hour_rows = report.hour_rows,
+                        "Usage rollup compaction finished"
+                    ),
+                    Err(e) => tracing::error!("Usage rollup compaction failed: {e}"),
+                }
+            }
+        }))
+    }
+
+    /// Drop rows older than their granularity's retention, measured from `now`
+    /// (unix seconds).
+    pub async fn compact(&self, db: &Database, now: i64) -> Result<CompactionReport> {
+        let mut report = CompactionReport::default();
+        for granularity in RollupGranularity::ALL {
+            let Some(days) = self.policy.days_for(granularity) else {
+                continue;
+            };
+            let cutoff = granularity.bucket_start(now - i64::from(days) * 86_400);
+            let rows = UsageRollupModel::delete_before(db, granularity, cutoff).await?;
+            match granularity {
+                RollupGranularity::Minute => report.minute_rows = rows,
+                RollupGranularity::Hour => report.hour_rows = rows,
+                RollupGranularity::Day => report.day_rows = rows,
+            }
+        }
+        Ok(report)
+    }
+
+    /// Rebuild rollups for `[since, until)` (unix seconds) from `router_logs`.
+    /// See [`UsageRollupModel::rebuild`] for when this is safe.
+    pub async fn backfill(db: &Database, since: i64, until: i64) -> Result<RollupRebuildReport> {
+        UsageRollupModel::rebuild(db, since, until).await
+    }
+
+    /// Rebuild rollups from every logged request when none exist yet.
+    /// See [`UsageRollupModel::backfill_if_empty`].
+    pub async fn backfill_if_empty(db: &Database) -> Result<Option<RollupRebuildReport>> {
+        UsageRollupModel::backfill_if_empty(db).await
+    }
+
+    /// Aggregate rollup rows.
+    pub async fn query(db: &Database, query: &RollupQuery) -> Result<Vec<RollupRow>> {
+        UsageRollupModel::query(db, query).await
+    }
+}
+
+/// Current unix time in seconds.
+pub fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
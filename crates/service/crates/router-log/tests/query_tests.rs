@@ -0,0 +1,89 @@
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+//! Time-bound parsing and export rendering for log search.
+
+use burncloud_service_router_log::{parse_log_time, parse_unix_time, ExportFormat, RouterLog};
+
+fn log() -> RouterLog {
+    RouterLog {
+        id: 7,
+        request_id: "req-7".to_string(),
+        user_id: Some("user, \"quoted\"".to_string()),
+        path: "/v1/chat/completions".to_string(),
+        upstream_id: None,
+        status_code: 200,
+        latency_ms: 12,
+        prompt_tokens: 3,
+        completion_tokens: 4,
+        cost: 900,
+        model: Some("gpt-4o".to_string()),
+        cache_read_tokens: 0,
+        reasoning_tokens: 0,
+        pricing_region: None,
+        video_tokens: 0,
+        cache_write_tokens: 0,
+        audio_input_tokens: 0,
+        audio_output_tokens: 0,
+        image_tokens: 0,
+        embedding_tokens: 0,
+        input_cost: 0,
+        output_cost: 0,
+        cache_read_cost: 0,
+        cache_write_cost: 0,
+        audio_cost: 0,
+        image_cost: 0,
+        video_cost: 0,
+        reasoning_cost: 0,
+        embedding_cost: 0,
+        layer_decision: None,
+        traffic_color: None,
+        cost_status: Some("ok".to_string()),
+        error_type: None,
+        token_hash: None,
+        served_model: None,
+        currency: None,
+        created_at: Some("2026-05-10 08:00:00".to_string()),
+    }
+}
+
+#[test]
+fn test_parse_log_time_normalizes_to_utc() {
+    let expected = Some("2026-05-10 00:00:00".to_string());
+    assert_eq!(parse_log_time("2026-05-10T08:00:00+08:00"), expected);
+    assert_eq!(parse_log_time("2026-05-10 00:00:00"), expected);
+    assert_eq!(parse_log_time("2026-05-10T00:00:00"), expected);
+    assert_eq!(parse_log_time("2026-05-10"), expected);
+    assert_eq!(parse_log_time("1778371200"), expected);
+    assert_eq!(parse_log_time("last week"), None);
+    assert_eq!(parse_log_time(""), None);
+    assert_eq!(
+        parse_unix_time("2026-05-10T08:00:00+08:00"),
+        Some(1778371200)
+    );
+    assert_eq!(parse_unix_time("last week"), None);
+}
+
+#[test]
+fn test_csv_export_quotes_fields() {
+    let format = ExportFormat::from_name("CSV").unwrap();
+    let header = format.header().unwrap();
+    let row = format.row(&log()).unwrap();
+
+    assert!(header.starts_with("id,request_id,created_at,user_id,"));
+    assert_eq!(header.trim_end().split(',').count(), 21);
+    assert!(row.starts_with("7,req-7,2026-05-10 08:00:00,\"user, \"\"quoted\"\"\",,"));
+    assert!(row.ends_with('\n'));
+}
+
+#[test]
+fn test_jsonl_export_has_one_object_per_line() {
+    let format = ExportFormat::from_name("jsonl").unwrap();
+    assert!(format.header().is_none());
+
+    let row = format.row(&log()).unwrap();
+    assert_eq!(row.matches('\n').count(), 1);
+    let parsed: RouterLog = serde_json::from_str(row.trim_end()).unwrap();
+    assert_eq!(parsed.request_id, "req-7");
+    assert_eq!(parsed.user_id.as_deref(), Some("user, \"quoted\""));
+    assert!(ExportFormat::from_name("xml").is_none());
+}
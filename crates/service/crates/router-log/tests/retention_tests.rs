@@ -0,0 +1,165 @@
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
+//! Archive-then-purge retention passes and restoring archives
+//! (`LogRetentionService`).
+use burncloud_database::{create_database_with_url, sqlx, Database};
+use burncloud_database_router::RouterDatabase;
+use burncloud_service_router_log::{LogRetentionService, RetentionPolicy};
+use tempfile::{NamedTempFile, TempDir};
+
+async fn create_test_db() -> (Database, NamedTempFile) {
+    let tmp = NamedTempFile::new().unwrap_or_else(|e| panic!("failed to create temp file: {e}"));
+    let url = format!("sqlite://{}?mode=rwc", tmp.path().display());
+    let db = create_database_with_url(&url)
+        .await
+        .unwrap_or_else(|e| panic!("failed to initialize test database: {e}"));
+    RouterDatabase::init(&db)
+        .await
+        .unwrap_or_else(|e| panic!("failed to initialize router tables: {e}"));
+    (db, tmp)
+}
+
+async fn insert_log(db: &Database, request_id: &str, created_at: &str) {
+    let conn = db.get_connection().unwrap();
+    sqlx::query(
+        r#"
+        INSERT INTO router_logs
+        (request_id, user_id, path, upstream_id, status_code, latency_ms,
+         prompt_tokens, completion_tokens, cost, model, token_hash, created_at)
+        VALUES (?, 'user-1', '/v1/chat/completions', 'up-1', 200, 100,
+                10, 20, 3000, 'gpt-4o', 'hash-1', ?)
+        "#,
+    )
+    .bind(request_id)
+    .bind(created_at)
+    .execute(conn.pool())
+    .await
+    .unwrap_or_else(|e| panic!("insert_log failed: {e}"));
+}
+
+async fn insert_body(db: &Database, request_id: &str, created_at: &str) {
+    let conn = db.get_connection().unwrap();
+    sqlx::query(
+        r#"
+        INSERT INTO router_request_logs
+        (request_id, request_body, response_body, storage_policy, created_at)
+        VALUES (?, '{"model":"gpt-4o"}', '{"id":"resp"}', 'full', ?)
+        "#,
+    )
+    .bind(request_id)
+    .bind(created_at)
+    .execute(conn.pool())
+    .await
+    .unwrap_or_else(|e| panic!("insert_body failed: {e}"));
+}
+
+async fn count(db: &Database, table: &str) -> i64 {
+    let conn = db.get_connection().unwrap();
+    sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {table}"))
+        .fetch_one(conn.pool())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_prune_archives_before_purging_and_restore_roundtrips() {
+    let (db, _tmp) = create_test_db().await;
+    let archive = TempDir::new().unwrap();
+    let recent = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let ten_days_ago = (chrono::Utc::now() - chrono::Duration::days(10))
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string();
+
+    insert_log(&db, "req-old-1", "2020-01-01 00:00:00").await;
+    insert_log(&db, "req-old-2", "2020-01-02 00:00:00").await;
+    insert_log(&db, "req-mid", &ten_days_ago).await;
+    insert_log(&db, "req-new", &recent).await;
+    insert_body(&db, "req-old-1", "2020-01-01 00:00:00").await;
+    insert_body(&db, "req-mid", &ten_days_ago).await;
+    insert_body(&db, "req-new", &recent).await;
+
+    let service = LogRetentionService::new(RetentionPolicy {
+        log_days: Some(30),
+        request_log_days: Some(7),
+        archive_dir: Some(archive.path().to_path_buf()),
+        interval: None,
+        batch_size: 1,
+    });
+    let report = service.run(&db).await.unwrap();
+
+    // Metadata older than 30 days, bodies older than 7 days; one file per batch
+    assert_eq!(report.router_logs.rows_archived, 2);
+    assert_eq!(report.router_logs.rows_purged, 2);
+    assert_eq!(report.router_logs.archive_files, 2);
+    assert_eq!(report.request_logs.rows_purged, 2);
+    assert_eq!(count(&db, "router_logs").await, 2);
+    assert_eq!(count(&db, "router_request_logs").await, 1);
+
+    let stats = service.stats();
+    assert_eq!(stats.rows_purged_total, 4);
+    assert_eq!(stats.archive_files, 4);
+    assert!(stats.archive_bytes > 0);
+    assert!(stats.last_error.is_none());
+
+    // A second pass finds nothing left to do
+    let again = service.run(&db).await.unwrap();
+    assert_eq!(again.router_logs.rows_purged, 0);
+    assert_eq!(again.request_logs.rows_purged, 0);
+
+    let restored = LogRetentionService::restore(&db, archive.path())
+        .await
+        .unwrap();
+    assert_eq!(restored.files, 4);
+    assert_eq!(restored.rows_restored, 4);
+    assert_eq!(count(&db, "router_logs").await, 4);
+    assert_eq!(count(&db, "router_request_logs").await, 3);
+
+    let conn = db.get_connection().unwrap();
+    let (created_at, cost, token_hash): (String, i64, Option<String>) = sqlx::query_as(
+        "SELECT created_at, cost, token_hash FROM router_logs WHERE request_id = 'req-old-1'",
+    )
+    .fetch_one(conn.pool())
+    .await
+    .unwrap();
+    assert_eq!(created_at, "2020-01-01 00:00:00");
+    assert_eq!(cost, 3000);
+    assert_eq!(token_hash.as_deref(), Some("hash-1"));
+
+    // Restoring twice never duplicates rows
+    let twice = LogRetentionService::restore(&db, archive.path())
+        .await
+        .unwrap();
+    assert_eq!(twice.rows_restored, 0);
+    assert_eq!(twice.rows_skipped, 4);
+    assert_eq!(count(&db, "router_logs").await, 4);
+}
+
+#[tokio::test]
+async fn test_prune_without_archive_dir_only_deletes() {
+    let (db, _tmp) = create_test_db().await;
+    insert_log(&db, "req-old", "2020-01-01 00:00:00").await;
+
+    let service = LogRetentionService::new(RetentionPolicy {
+        log_days: Some(30),
+        request_log_days: None,
+        archive_dir: None,
+        interval: None,
+        batch_size: 100,
+    });
+    let report = service.run(&db).await.unwrap();
+    assert_eq!(report.router_logs.rows_purged, 1);
+    assert_eq!(report.router_logs.rows_archived, 0);
+    assert_eq!(count(&db, "router_logs").await, 0);
+}
+
+#[tokio::test]
+async fn test_default_policy_keeps_everything() {
+    let (db, _tmp) = create_test_db().await;
+    insert_log(&db, "req-ancient", "2000-01-01 00:00:00").await;
+
+    let service = LogRetentionService::new(RetentionPolicy::default());
+    let report = service.run(&db).await.unwrap();
+    assert_eq!(report.router_logs.rows_purged, 0);
+    assert_eq!(report.request_logs.rows_purged, 0);
+    assert_eq!(count(&db, "router_logs").await, 1);
+}
@@ -3,6 +3,7 @@
 //! Token service layer providing business logic for API token management,
 //! including validation, quota tracking, CRUD operations, and key rotation.
 
+use burncloud_common::RateLimits;
 use burncloud_database::Database;
 use burncloud_database_router::token::{RouterTokenModel, TokenRotationResult};
 
@@ -95,6 +96,20 @@ impl TokenService {
         RouterTokenModel::set_ip_whitelist(db, token, ip_whitelist).await
     }
 
+    /// Set (or clear, with `None`) the model policy JSON of a token
+    pub async fn set_model_policy(
+        db: &Database,
+        token: &str,
+        model_policy: Option<&str>,
+    ) -> Result<bool> {
+        RouterTokenModel::set_model_policy(db, token, model_policy).await
+    }
+
+    /// Replace the RPM / TPM / concurrency limits of a token
+    pub async fn set_rate_limits(db: &Database, token: &str, limits: &RateLimits) -> Result<bool> {
+        RouterTokenModel::set_rate_limits(db, token, limits).await
+    }
+
     /// Check if IP is allowed for token
     pub async fn is_ip_allowed(db: &Database, token: &str, client_ip: &str) -> Result<bool> {
         RouterTokenModel::is_ip_allowed(db, token, client_ip).await
@@ -7,7 +7,7 @@
 
 use burncloud_database::create_default_database;
 use burncloud_database_user::UserDatabase;
-use burncloud_service_user::UserService;
+use burncloud_service_user::{SessionClient, UserService};
 use uuid::Uuid;
 
 #[tokio::main]
@@ -43,25 +43,29 @@ async fn main() -> anyhow::Result<()> {
 
     // Login
     println!("Logging in as '{}'...", username);
-    let auth_token = service
-        .login_user(&db, &username, "secure_password")
+    let session = service
+        .login_user(&db, &username, "secure_password", &SessionClient::default())
         .await?;
     println!("✓ Login successful!");
-    println!("  Token: {}", auth_token.token);
-    println!("  User ID: {}", auth_token.user_id);
-    println!("  Username: {}", auth_token.username);
-    println!("  Expires at: {}\n", auth_token.expires_at);
+    println!("  Token: {}", session.access_token);
+    println!("  Session ID: {}", session.session_id);
+    println!("  User ID: {}", session.user_id);
+    println!("  Username: {}", session.username);
+    println!("  Expires at: {}\n", session.expires_at);
 
     // Validate token
     println!("Validating token...");
-    let (validated_user_id, validated_username) = service.validate_token(&auth_token.token)?;
+    let (validated_user_id, validated_username) = service.validate_token(&session.access_token)?;
     println!("✓ Token is valid!");
     println!("  User ID: {}", validated_user_id);
     println!("  Username: {}\n", validated_username);
 
     // Demonstrate failed login
     println!("Attempting login with wrong password...");
-    match service.login_user(&db, &username, "wrong_password").await {
+    match service
+        .login_user(&db, &username, "wrong_password", &SessionClient::default())
+        .await
+    {
         Ok(_) => println!("✗ Should have failed!"),
         Err(e) => println!("✓ Login failed as expected: {}\n", e),
     }
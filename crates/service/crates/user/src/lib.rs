@@ -1,24 +1,54 @@
 //! # BurnCloud Service User
 //!
-//! User service layer providing register, login, and token management functionality.
+//! User service layer providing register, login, and token management functionality,
+//! plus organizations with shared wallets and member roles (see [`organization`]),
+//! console sessions with rotating refresh tokens (see [`session`]), TOTP
+//! two-factor authentication (see [`totp`]), role permissions (see [`role`]) and
+//! management API keys for automation (see [`management_key`]).
+
+pub mod management_key;
+pub mod oauth;
+pub mod organization;
+pub mod role;
+pub mod session;
+pub mod totp;
 
 use bcrypt::{hash, verify, DEFAULT_COST};
-use burncloud_common::TrafficColor;
+use burncloud_common::{
+    nano_to_dollars, scaled_to_rate, Currency, RateLimits, TrafficColor, RATE_SCALE,
+};
 use burncloud_database::Database;
+use burncloud_database_billing::BillingExchangeRateModel;
 use burncloud_database_user::PasswordResetDatabase;
 use burncloud_database_user::UserDatabase;
+use burncloud_service_mail::{MailService, MailTemplate};
 use dashmap::DashMap;
 
 // Re-export domain types so server can depend on service-user instead of database-user
-pub use burncloud_database_user::{UserAccount, UserRecharge};
+pub use burncloud_database_user::{UserAccount, UserOAuthIdentity, UserRecharge, UserSession};
+pub use management_key::{
+    CreatedManagementKey, IpRange, ManagementKeyPrincipal, ManagementKeyService,
+    ManagementKeyView, NewManagementKey,
+};
+pub use oauth::{OAuthAuthorization, OAuthLogin, OAuthService};
+pub use organization::{
+    OrgRole, OrganizationService, UserOrganization, UserOrganizationInvitation,
+    UserOrganizationMember,
+};
 use chrono::{Duration, Utc};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
+pub use role::{Permission, PermissionSet, RoleService, RoleView};
+pub use session::{SessionClient, SessionTokens};
+pub use totp::{LoginChallenge, TotpSetup, TwoFactorStatus};
 use thiserror::Error;
 use uuid::Uuid;
 
+/// Password reset tokens expire after this many hours
+const PASSWORD_RESET_TTL_HOURS: i64 = 1;
+
 /// Default user status when created
-const ACTIVE_STATUS: i32 = 1;
+pub(crate) const ACTIVE_STATUS: i32 = 1;
 
 /// Default signup bonus for new users (in nanodollars: $10 = 10_000_000_000)
 const SIGNUP_BONUS_NANO: i64 = 10_000_000_000;
@@ -52,10 +82,164 @@ pub enum UserServiceError {
 
     #[error("Configuration error: {0}")]
     ConfigError(String),
+
+    #[error("Organization not found")]
+    OrganizationNotFound,
+
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+
+    #[error("Invalid invitation: {0}")]
+    InvalidInvitation(String),
+
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("Role not found")]
+    RoleNotFound,
+
+    #[error("Role already exists")]
+    RoleExists,
+
+    #[error("Management key not found")]
+    ManagementKeyNotFound,
+
+    #[error("Session expired or revoked")]
+    SessionRevoked,
+
+    /// The password was right but the account needs a second factor; sign
+    /// in through a login challenge instead
+    #[error("Two-factor authentication required")]
+    TwoFactorRequired,
 }
 
 pub type Result<T> = std::result::Result<T, UserServiceError>;
 
+/// Result of [`UserService::topup`]
+#[derive(Debug, Clone, Serialize)]
+pub struct TopupReceipt {
+    /// Wallet balance after the top-up, in nanodollars
+    pub balance: i64,
+    /// Wallet the amount was credited to
+    pub wallet: Currency,
+    /// Amount credited to `wallet`, after conversion
+    pub credited: i64,
+}
+
+/// Express `amount` in the wallet `currency` is credited to. Returns the
+/// converted amount, the wallet and the scaled rate used, if any. Negative
+/// amounts are refused.
+pub(crate) async fn convert_to_wallet(
+    db: &Database,
+    amount: i64,
+    currency: Currency,
+) -> Result<(i64, Currency, Option<i64>)> {
+    if amount < 0 {
+        return Err(UserServiceError::InvalidInput(
+            "amount must not be negative".to_string(),
+        ));
+    }
+    let wallet = currency.wallet();
+    if wallet == currency {
+        return Ok((amount, wallet, None));
+    }
+    let (numerator, denominator) = exchange_rate(db, currency, wallet).await?;
+    let converted = (amount as i128 * numerator as i128 / denominator as i128)
+        .min(i64::MAX as i128) as i64;
+    let rate = (numerator as i128 * RATE_SCALE as i128 / denominator as i128) as i64;
+    Ok((converted, wallet, Some(rate)))
+}
+
+/// `from -> to` as a (numerator, denominator) pair of scaled rates, so the
+/// inverse of a `to -> from` row is applied without losing precision
+async fn exchange_rate(db: &Database, from: Currency, to: Currency) -> Result<(i64, i64)> {
+    if let Some(rate) = BillingExchangeRateModel::get(db, from.code(), to.code()).await? {
+        if rate > 0 {
+            return Ok((rate, RATE_SCALE));
+        }
+    }
+    if let Some(inverse) = BillingExchangeRateModel::get(db, to.code(), from.code()).await? {
+        if inverse > 0 {
+            return Ok((RATE_SCALE, inverse));
+        }
+    }
+    Err(UserServiceError::InvalidInput(format!(
+        "no exchange rate for {from} -> {to}"
+    )))
+}
+
+/// Link in the reset email. `PASSWORD_RESET_URL` may contain a `{token}`
+/// placeholder; otherwise the token is appended to the console reset page.
+fn password_reset_url(token: &str) -> String {
+    match std::env::var("PASSWORD_RESET_URL")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+    {
+        Some(template) if template.contains("{token}") => template.replace("{token}", token),
+        Some(base) => format!("{base}{token}"),
+        None => {
+            let base = std::env::var("BASE_URL")
+                .ok()
+                .filter(|v| !v.trim().is_empty())
+                .unwrap_or_else(|| "http://localhost:8080".to_string());
+            format!("{}/reset-password?token={token}", base.trim_end_matches('/'))
+        }
+    }
+}
+
+/// Insert an active account with the signup bonus and its default role.
+/// `password_hash` is `None` for accounts that only sign in through OAuth;
+/// the column is NOT NULL, so those are stored with an empty hash.
+pub(crate) async fn create_account(
+    db: &Database,
+    username: &str,
+    password_hash: Option<String>,
+    email: Option<String>,
+) -> Result<String> {
+    // Create user
+    let user = UserAccount {
+        id: Uuid::new_v4().to_string(),
+        username: username.to_string(),
+        email,
+        password_hash: Some(password_hash.unwrap_or_default()),
+        github_id: None,
+        status: ACTIVE_STATUS,
+        balance_usd: SIGNUP_BONUS_NANO,
+        balance_cny: 0,
+        preferred_currency: Some("USD".to_string()),
+    };
+
+    // First-user-is-admin: check BEFORE creating the user so that
+    // count_users() == 0 means this is truly the first real user.
+    // Uses count_users (excludes demo-user seed) instead of
+    // has_admin_user so the check is based on user count, not on
+    // whether a stale admin from a prior run still exists.
+    let is_first_admin = match UserDatabase::count_users(db).await {
+        Ok(count) => {
+            tracing::info!("First-user-is-admin check: user count = {count}");
+            count == 0
+        }
+        Err(e) => {
+            tracing::warn!("First-user-is-admin check failed: {}", e);
+            false
+        }
+    };
+    let default_role = if is_first_admin { "admin" } else { "user" };
+
+    UserDatabase::create_user(db, &user).await?;
+
+    if let Err(e) = UserDatabase::assign_role(db, &user.id, default_role).await {
+        tracing::warn!(
+            "Warning: Failed to assign {} role to user {}: {}",
+            default_role,
+            user.id,
+            e
+        );
+    }
+
+    Ok(user.id)
+}
+
 /// Authentication token structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthToken {
@@ -72,12 +256,17 @@ struct Claims {
     username: String, // Username
     exp: i64,         // Expiration time
     iat: i64,         // Issued at
+    /// Session the token belongs to, absent on sessionless tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sid: Option<String>,
 }
 
 /// User service providing business logic for user operations
 pub struct UserService {
     jwt_secret: String,
     token_expiration_hours: i64,
+    access_token_ttl: Duration,
+    refresh_token_ttl: Duration,
 }
 
 impl UserService {
@@ -97,18 +286,18 @@ impl UserService {
             }
         });
 
+        let (access_token_ttl, refresh_token_ttl) = session::ttls_from_env();
         Self {
             jwt_secret,
             token_expiration_hours: DEFAULT_TOKEN_EXPIRATION_HOURS,
+            access_token_ttl,
+            refresh_token_ttl,
         }
     }
 
     /// Create a new UserService instance with a custom JWT secret
     pub fn with_secret(jwt_secret: String) -> Self {
-        Self {
-            jwt_secret,
-            token_expiration_hours: DEFAULT_TOKEN_EXPIRATION_HOURS,
-        }
+        Self::with_config(jwt_secret, DEFAULT_TOKEN_EXPIRATION_HOURS)
     }
 
     /// Create a new UserService instance with custom JWT secret and token expiration
@@ -116,6 +305,8 @@ impl UserService {
         Self {
             jwt_secret,
             token_expiration_hours,
+            access_token_ttl: Duration::seconds(session::DEFAULT_ACCESS_TTL_SECS),
+            refresh_token_ttl: Duration::days(session::DEFAULT_REFRESH_TTL_DAYS),
         }
     }
 
@@ -193,89 +384,73 @@ impl UserService {
         let password_hash =
             hash(password, DEFAULT_COST).map_err(|e| UserServiceError::HashError(e.to_string()))?;
 
-        // Create user
-        let user = UserAccount {
-            id: Uuid::new_v4().to_string(),
-            username: username.to_string(),
-            email,
-            password_hash: Some(password_hash),
-            github_id: None,
-            status: ACTIVE_STATUS,
-            balance_usd: SIGNUP_BONUS_NANO,
-            balance_cny: 0,
-            preferred_currency: Some("USD".to_string()),
-        };
-
-        // First-user-is-admin: check BEFORE creating the user so that
-        // count_users() == 0 means this is truly the first real user.
-        // Uses count_users (excludes demo-user seed) instead of
-        // has_admin_user so the check is based on user count, not on
-        // whether a stale admin from a prior run still exists.
-        let is_first_admin = match UserDatabase::count_users(db).await {
-            Ok(count) => {
-                tracing::info!("First-user-is-admin check: user count = {count}");
-                count == 0
-            }
-            Err(e) => {
-                tracing::warn!("First-user-is-admin check failed: {}", e);
-                false
-            }
-        };
-        let default_role = if is_first_admin { "admin" } else { "user" };
-
-        UserDatabase::create_user(db, &user).await?;
-
-        if let Err(e) = UserDatabase::assign_role(db, &user.id, default_role).await {
-            tracing::warn!(
-                "Warning: Failed to assign {} role to user {}: {}",
-                default_role,
-                user.id,
-                e
-            );
-        }
-
-        Ok(user.id)
+        create_account(db, username, Some(password_hash), email).await
     }
 
-    /// Login user and return authentication token
+    /// Login user and open a session
     ///
     /// # Arguments
     /// * `db` - Database connection
     /// * `username` - Username
     /// * `password` - Plain text password
+    /// * `client` - Where the sign-in came from, kept with the session
     ///
     /// # Returns
-    /// * `Ok(AuthToken)` - Authentication token with user info
-    /// * `Err(UserServiceError)` - If login fails
+    /// * `Ok(SessionTokens)` - Short-lived access token bound to the new
+    ///   session, plus its refresh token
+    /// * `Err(UserServiceError)` - If login fails, `TwoFactorRequired` when
+    ///   the account has two-factor authentication enabled
     pub async fn login_user(
         &self,
         db: &Database,
         username: &str,
         password: &str,
-    ) -> Result<AuthToken> {
+        client: &SessionClient,
+    ) -> Result<SessionTokens> {
+        let user = self.authenticate(db, username, password).await?;
+        if self.two_factor_enabled(db, &user.id).await? {
+            return Err(UserServiceError::TwoFactorRequired);
+        }
+
+        self.start_session(db, &user.id, &user.username, client).await
+    }
+
+    /// Check a username and password without issuing a token
+    pub async fn authenticate(
+        &self,
+        db: &Database,
+        username: &str,
+        password: &str,
+    ) -> Result<UserAccount> {
         // Fetch user
         let user = UserDatabase::get_user_by_username(db, username)
             .await?
             .ok_or(UserServiceError::UserNotFound)?;
 
         // Verify password
+        // OAuth-only accounts have an empty hash
         let password_hash = user
             .password_hash
+            .as_deref()
+            .filter(|h| !h.is_empty())
             .ok_or(UserServiceError::InvalidCredentials)?;
 
-        let valid = verify(password, &password_hash)
+        let valid = verify(password, password_hash)
             .map_err(|e| UserServiceError::HashError(e.to_string()))?;
 
         if !valid {
             return Err(UserServiceError::InvalidCredentials);
         }
 
-        // Generate token
-        self.generate_token(&user.id, &user.username)
+        Ok(user)
     }
 
     /// Generate JWT token for a user
     ///
+    /// The token has no session, so it can't be revoked on its own and is
+    /// only accepted for [`session::SESSIONLESS_GRACE_SECS`]. Sign-ins go
+    /// through [`Self::start_session`] instead.
+    ///
     /// # Arguments
     /// * `user_id` - User ID
     /// * `username` - Username
@@ -284,14 +459,31 @@ impl UserService {
     /// * `Ok(AuthToken)` - Generated authentication token
     /// * `Err(UserServiceError)` - If token generation fails
     pub fn generate_token(&self, user_id: &str, username: &str) -> Result<AuthToken> {
+        self.sign_token(
+            user_id,
+            username,
+            None,
+            Duration::hours(self.token_expiration_hours),
+        )
+    }
+
+    /// Sign an access token, bound to session `sid` when given
+    pub(crate) fn sign_token(
+        &self,
+        user_id: &str,
+        username: &str,
+        sid: Option<&str>,
+        ttl: Duration,
+    ) -> Result<AuthToken> {
         let now = Utc::now();
-        let expiration = now + Duration::hours(self.token_expiration_hours);
+        let expiration = now + ttl;
 
         let claims = Claims {
             sub: user_id.to_string(),
             username: username.to_string(),
             exp: expiration.timestamp(),
             iat: now.timestamp(),
+            sid: sid.map(str::to_string),
         };
 
         let token = encode(
@@ -328,19 +520,35 @@ impl UserService {
     }
 
     /// Topup a user's balance and return the new balance in nanodollars.
+    ///
+    /// USD and CNY are credited to their own wallets. Any other supported
+    /// currency is converted into the USD wallet at the current rate in
+    /// `billing_exchange_rates`; the recharge description keeps the original
+    /// amount and rate.
     pub async fn topup(
         &self,
         db: &Database,
         user_id: &str,
         amount: i64,
         currency: &str,
-    ) -> Result<i64> {
+    ) -> Result<TopupReceipt> {
+        let currency: Currency = currency.parse().map_err(UserServiceError::InvalidInput)?;
+        let (credited, wallet, rate) = convert_to_wallet(db, amount, currency).await?;
+        let description = match rate {
+            None => "账户充值".to_string(),
+            Some(rate) => format!(
+                "账户充值 ({:.2} {} @ {:.6})",
+                nano_to_dollars(amount),
+                currency,
+                scaled_to_rate(rate)
+            ),
+        };
         let recharge = UserRecharge {
             id: 0,
             user_id: user_id.to_string(),
-            amount,
-            currency: Some(currency.to_string()),
-            description: Some("账户充值".to_string()),
+            amount: credited,
+            currency: Some(wallet.code().to_string()),
+            description: Some(description),
             created_at: None,
         };
         UserDatabase::create_recharge(db, &recharge)
@@ -348,10 +556,14 @@ impl UserService {
             .map_err(UserServiceError::DatabaseError)?;
 
         // create_recharge already updates the balance; read it back
-        let balance = UserDatabase::update_balance(db, user_id, 0, Some(currency))
+        let balance = UserDatabase::update_balance(db, user_id, 0, Some(wallet.code()))
             .await
             .unwrap_or(0);
-        Ok(balance)
+        Ok(TopupReceipt {
+            balance,
+            wallet,
+            credited,
+        })
     }
 
     /// List recharge history for a user
@@ -361,6 +573,29 @@ impl UserService {
             .map_err(Into::into)
     }
 
+    /// RPM / TPM / concurrency limits of a user, used by the router for
+    /// tokens whose validation row does not carry them.
+    pub async fn rate_limits(db: &Database, user_id: &str) -> Result<RateLimits> {
+        UserDatabase::get_rate_limits(db, user_id)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Replace the rate limits of a user. Fails with `UserNotFound` for unknown users.
+    pub async fn set_rate_limits(
+        &self,
+        db: &Database,
+        user_id: &str,
+        limits: &RateLimits,
+    ) -> Result<()> {
+        limits.validate().map_err(UserServiceError::InvalidInput)?;
+        if UserDatabase::set_rate_limits(db, user_id, limits).await? {
+            Ok(())
+        } else {
+            Err(UserServiceError::UserNotFound)
+        }
+    }
+
     /// Validate JWT token and extract user information
     ///
     /// # Arguments
@@ -380,15 +615,26 @@ impl UserService {
         Ok((token_data.claims.sub, token_data.claims.username))
     }
 
+    /// Create a reset token and queue the reset email. A mail that cannot
+    /// be queued is logged; the token is still valid.
     pub async fn request_password_reset(&self, db: &Database, email: &str) -> Result<String> {
         let user = UserDatabase::get_user_by_email(db, email)
             .await?
             .ok_or(UserServiceError::UserNotFound)?;
 
         let token = Uuid::new_v4().to_string();
-        let expires_at = Utc::now() + Duration::hours(1);
+        let expires_at = Utc::now() + Duration::hours(PASSWORD_RESET_TTL_HOURS);
         PasswordResetDatabase::create_token(db, &token, &user.id, &expires_at.to_rfc3339()).await?;
 
+        let mail = MailTemplate::PasswordReset {
+            username: user.username.clone(),
+            reset_url: password_reset_url(&token),
+            expires_minutes: PASSWORD_RESET_TTL_HOURS * 60,
+        };
+        if let Err(e) = MailService::enqueue(db, email, &mail).await {
+            tracing::warn!(user_id = %user.id, "Failed to queue password reset email: {e}");
+        }
+
         Ok(token)
     }
 
@@ -419,37 +665,10 @@ impl UserService {
 
         PasswordResetDatabase::mark_used(db, token).await?;
 
-        Ok(())
-    }
+        // Whoever knew the old password must not stay signed in
+        self.logout_all(db, &reset_token.user_id).await?;
 
-    pub fn oauth_url(provider: &str) -> Result<String> {
-        match provider {
-            "google" => {
-                let client_id = std::env::var("GOOGLE_CLIENT_ID")
-                    .map_err(|e| UserServiceError::ConfigError(e.to_string()))?;
-                let redirect_uri = std::env::var("GOOGLE_REDIRECT_URI").unwrap_or_else(|_| {
-                    "http://localhost:8080/console/api/auth/google/callback".to_string()
-                });
-                Ok(format!(
-                    "https://accounts.google.com/o/oauth2/v2/auth?client_id={}&redirect_uri={}&response_type=code&scope=openid%20email%20profile",
-                    client_id, redirect_uri
-                ))
-            }
-            "github" => {
-                let client_id = std::env::var("GITHUB_CLIENT_ID")
-                    .map_err(|e| UserServiceError::ConfigError(e.to_string()))?;
-                let redirect_uri = std::env::var("GITHUB_REDIRECT_URI").unwrap_or_else(|_| {
-                    "http://localhost:8080/console/api/auth/github/callback".to_string()
-                });
-                Ok(format!(
-                    "https://github.com/login/oauth/authorize?client_id={}&redirect_uri={}&scope=user:email",
-                    client_id, redirect_uri
-                ))
-            }
-            _ => Err(UserServiceError::ConfigError(format!(
-                "Unknown OAuth provider: {provider}"
-            ))),
-        }
+        Ok(())
     }
 }
 
@@ -536,12 +755,25 @@ mod tests {
             .register_user(&db, &username, password, None)
             .await?;
 
-        // Login should succeed
-        let token = service.login_user(&db, &username, password).await?;
+        // Login should succeed and open a session
+        let tokens = service
+            .login_user(&db, &username, password, &SessionClient::default())
+            .await?;
 
-        assert!(!token.token.is_empty());
-        assert_eq!(token.username, username);
-        assert!(token.expires_at > Utc::now().timestamp());
+        assert!(!tokens.access_token.is_empty());
+        assert_eq!(tokens.username, username);
+        assert!(tokens.expires_at > Utc::now().timestamp());
+        assert!(tokens.expires_at < tokens.refresh_expires_at);
+        UserService::check_access(&db, &tokens.user_id, Some(&tokens.session_id), 0).await?;
+
+        // Tokens without a session only last for the grace window
+        let now = Utc::now().timestamp();
+        UserService::check_access(&db, &tokens.user_id, None, now).await?;
+        let stale = now - session::SESSIONLESS_GRACE_SECS - 1;
+        assert!(matches!(
+            UserService::check_access(&db, &tokens.user_id, None, stale).await,
+            Err(UserServiceError::SessionRevoked)
+        ));
 
         Ok(())
     }
@@ -560,7 +792,9 @@ mod tests {
             .await?;
 
         // Login with wrong password should fail
-        let result = service.login_user(&db, &username, "wrongpassword").await;
+        let result = service
+            .login_user(&db, &username, "wrongpassword", &SessionClient::default())
+            .await;
 
         let Err(e) = result else {
             panic!("wrong password login should fail");
@@ -578,7 +812,9 @@ mod tests {
         let service = UserService::new();
 
         // Login non-existent user should fail
-        let result = service.login_user(&db, "nonexistent", "password").await;
+        let result = service
+            .login_user(&db, "nonexistent", "password", &SessionClient::default())
+            .await;
 
         let Err(e) = result else {
             panic!("nonexistent user login should fail");
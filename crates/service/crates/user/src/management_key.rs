@@ -0,0 +1,362 @@
+//! Management API keys: long-lived credentials for automation against the
+//! console API.
+//!
+//! A key belongs to a user and is limited to a set of permission scopes; a
+//! request made with it holds the scopes the owner still has through their
+//! roles, so removing a role narrows every key of that user too. Keys can be
+//! restricted to addresses or CIDR ranges, expire, and can be revoked. Only
+//! the SHA-256 of a key is stored; the key itself is shown once, on creation.
+
+use crate::oauth::random_token;
+use crate::role::{Permission, PermissionSet, RoleService, WILDCARD};
+use crate::{Result, UserServiceError, ACTIVE_STATUS};
+use burncloud_database::Database;
+use burncloud_database_user::{UserDatabase, UserManagementKey, UserManagementKeyModel};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::net::IpAddr;
+use uuid::Uuid;
+
+/// Every management key starts with this, which tells it apart from a JWT
+pub const KEY_PREFIX: &str = "bcm_";
+
+/// Characters of the key kept for display
+const DISPLAY_PREFIX_LEN: usize = 12;
+
+/// Longest key name
+const MAX_KEY_NAME_LEN: usize = 64;
+
+/// Whether a bearer credential is a management key
+pub fn is_management_key(credential: &str) -> bool {
+    credential.starts_with(KEY_PREFIX)
+}
+
+fn key_hash(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+/// Request to create a key
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewManagementKey {
+    pub name: String,
+    /// Permission names, or `*` for everything the owner holds
+    pub scopes: Vec<String>,
+    /// Addresses or CIDR ranges; empty allows any address
+    #[serde(default)]
+    pub allowed_ips: Vec<String>,
+    /// Unix seconds; `None` never expires
+    pub expires_at: Option<i64>,
+}
+
+/// A key as the console lists it, without the key itself
+#[derive(Debug, Clone, Serialize)]
+pub struct ManagementKeyView {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    pub allowed_ips: Vec<String>,
+    pub created_at: i64,
+    pub expires_at: Option<i64>,
+    pub last_used_at: Option<i64>,
+    pub last_used_ip: Option<String>,
+    pub revoked_at: Option<i64>,
+    /// Neither revoked nor expired
+    pub active: bool,
+}
+
+impl From<UserManagementKey> for ManagementKeyView {
+    fn from(key: UserManagementKey) -> Self {
+        let active = key.is_active(Utc::now().timestamp());
+        ManagementKeyView {
+            scopes: split_list(&key.scopes),
+            allowed_ips: key
+                .allowed_ips
+                .as_deref()
+                .map(split_list)
+                .unwrap_or_default(),
+            id: key.id,
+            user_id: key.user_id,
+            name: key.name,
+            prefix: key.prefix,
+            created_at: key.created_at,
+            expires_at: key.expires_at,
+            last_used_at: key.last_used_at,
+            last_used_ip: key.last_used_ip,
+            revoked_at: key.revoked_at,
+            active,
+        }
+    }
+}
+
+/// A freshly created key; `secret` is never shown again
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatedManagementKey {
+    #[serde(flatten)]
+    pub key: ManagementKeyView,
+    pub secret: String,
+}
+
+/// Principal behind a request authenticated with a management key
+#[derive(Debug, Clone)]
+pub struct ManagementKeyPrincipal {
+    pub key_id: String,
+    pub key_name: String,
+    pub user_id: String,
+    pub username: String,
+    pub scopes: PermissionSet,
+}
+
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// An address or CIDR range, such as one a key may be used from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpRange {
+    network: IpAddr,
+    prefix_len: u32,
+}
+
+impl IpRange {
+    pub fn parse(range: &str) -> Result<Self> {
+        let invalid =
+            || UserServiceError::InvalidInput(format!("invalid address or range: {range}"));
+        let (address, prefix_len) = match range.split_once('/') {
+            Some((address, len)) => (address, Some(len)),
+            None => (range, None),
+        };
+        let network: IpAddr = address.trim().parse().map_err(|_| invalid())?;
+        let max_len = if network.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(len) => len.trim().parse().map_err(|_| invalid())?,
+            None => max_len,
+        };
+        if prefix_len > max_len {
+            return Err(invalid());
+        }
+        Ok(IpRange {
+            network,
+            prefix_len,
+        })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            v4 => v4,
+        };
+        match (self.network, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix_len).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix_len).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Whether `ip` falls in one of the allowed ranges. A key with ranges is
+/// refused when the client address is unknown.
+fn ip_allowed(allowed: &[String], ip: Option<&str>) -> bool {
+    if allowed.is_empty() {
+        return true;
+    }
+    let Some(ip) = ip.and_then(|ip| ip.trim().parse::<IpAddr>().ok()) else {
+        return false;
+    };
+    allowed
+        .iter()
+        .filter_map(|range| IpRange::parse(range).ok())
+        .any(|range| range.contains(ip))
+}
+
+pub struct ManagementKeyService;
+
+impl ManagementKeyService {
+    /// Create a key for `user_id`. Scopes must be permissions the user holds
+    /// now, so a key never grants more than its owner.
+    pub async fn create(
+        db: &Database,
+        user_id: &str,
+        input: &NewManagementKey,
+    ) -> Result<CreatedManagementKey> {
+        let name = input.name.trim();
+        if name.is_empty() || name.chars().count() > MAX_KEY_NAME_LEN {
+            return Err(UserServiceError::InvalidInput(format!(
+                "key names are 1-{MAX_KEY_NAME_LEN} characters"
+            )));
+        }
+        let granted = RoleService::permissions(db, user_id).await?;
+        let scopes = validate_scopes(&input.scopes, &granted)?;
+        let mut allowed_ips = Vec::new();
+        for range in input
+            .allowed_ips
+            .iter()
+            .map(|r| r.trim())
+            .filter(|r| !r.is_empty())
+        {
+            IpRange::parse(range)?;
+            allowed_ips.push(range.to_string());
+        }
+        let now = Utc::now().timestamp();
+        if input.expires_at.is_some_and(|at| at <= now) {
+            return Err(UserServiceError::InvalidInput(
+                "expires_at must be in the future".to_string(),
+            ));
+        }
+
+        let secret = format!("{KEY_PREFIX}{}", random_token());
+        let key = UserManagementKey {
+            id: Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            name: name.to_string(),
+            prefix: secret.chars().take(DISPLAY_PREFIX_LEN).collect(),
+            key_hash: key_hash(&secret),
+            scopes: scopes.join(","),
+            allowed_ips: (!allowed_ips.is_empty()).then(|| allowed_ips.join(",")),
+            created_at: now,
+            expires_at: input.expires_at,
+            last_used_at: None,
+            last_used_ip: None,
+            revoked_at: None,
+        };
+        UserManagementKeyModel::create(db, &key).await?;
+        Ok(CreatedManagementKey {
+            key: key.into(),
+            secret,
+        })
+    }
+
+    /// Keys of one user, or of every user when `user_id` is `None`
+    pub async fn list(db: &Database, user_id: Option<&str>) -> Result<Vec<ManagementKeyView>> {
+        Ok(UserManagementKeyModel::list(db, user_id)
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    pub async fn get(db: &Database, id: &str) -> Result<ManagementKeyView> {
+        UserManagementKeyModel::get(db, id)
+            .await?
+            .map(Into::into)
+            .ok_or(UserServiceError::ManagementKeyNotFound)
+    }
+
+    /// Revoke a key; requests made with it fail from now on
+    pub async fn revoke(db: &Database, id: &str) -> Result<ManagementKeyView> {
+        if !UserManagementKeyModel::revoke(db, id).await? {
+            return Err(UserServiceError::ManagementKeyNotFound);
+        }
+        Self::get(db, id).await
+    }
+
+    /// Resolve a presented key. Unknown, revoked and expired keys and keys of
+    /// disabled users are invalid credentials; a key used from an address
+    /// outside its ranges is denied. Records the use on success.
+    pub async fn authenticate(
+        db: &Database,
+        secret: &str,
+        ip: Option<&str>,
+    ) -> Result<ManagementKeyPrincipal> {
+        let key = UserManagementKeyModel::find_by_hash(db, &key_hash(secret))
+            .await?
+            .filter(|key| key.is_active(Utc::now().timestamp()))
+            .ok_or(UserServiceError::InvalidCredentials)?;
+        let user = UserDatabase::get_user_by_id(db, &key.user_id)
+            .await?
+            .filter(|user| user.status == ACTIVE_STATUS)
+            .ok_or(UserServiceError::InvalidCredentials)?;
+        let allowed = key
+            .allowed_ips
+            .as_deref()
+            .map(split_list)
+            .unwrap_or_default();
+        if !ip_allowed(&allowed, ip) {
+            return Err(UserServiceError::PermissionDenied(format!(
+                "management key {} is not allowed from {}",
+                key.prefix,
+                ip.unwrap_or("an unknown address")
+            )));
+        }
+        UserManagementKeyModel::touch(db, &key.id, ip).await?;
+        Ok(ManagementKeyPrincipal {
+            scopes: split_list(&key.scopes).into_iter().collect(),
+            key_id: key.id,
+            key_name: key.name,
+            user_id: user.id,
+            username: user.username,
+        })
+    }
+}
+
+/// Known scopes, deduplicated, each held by `granted`
+fn validate_scopes(names: &[String], granted: &PermissionSet) -> Result<Vec<String>> {
+    let mut scopes = BTreeSet::new();
+    for name in names.iter().map(|n| n.trim()) {
+        let held = if name == WILDCARD {
+            granted.is_unrestricted()
+        } else {
+            granted.contains(name.parse::<Permission>()?)
+        };
+        if !held {
+            return Err(UserServiceError::PermissionDenied(format!(
+                "cannot grant {name}, which you do not hold"
+            )));
+        }
+        scopes.insert(name.to_string());
+    }
+    if scopes.is_empty() {
+        return Err(UserServiceError::InvalidInput(
+            "a key needs at least one scope".to_string(),
+        ));
+    }
+    Ok(scopes.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ip_ranges() {
+        let allowed = vec!["10.0.0.0/8".to_string(), "2001:db8::/32".to_string()];
+        assert!(ip_allowed(&allowed, Some("10.1.2.3")));
+        assert!(ip_allowed(&allowed, Some("::ffff:10.0.0.1")));
+        assert!(ip_allowed(&allowed, Some("2001:db8::1")));
+        assert!(!ip_allowed(&allowed, Some("192.168.0.1")));
+        assert!(!ip_allowed(&allowed, None));
+        assert!(ip_allowed(&[], None));
+        assert!(ip_allowed(&["0.0.0.0/0".to_string()], Some("8.8.8.8")));
+        assert!(ip_allowed(&["127.0.0.1".to_string()], Some("127.0.0.1")));
+        assert!(IpRange::parse("10.0.0.0/33").is_err());
+        assert!(IpRange::parse("example.com").is_err());
+    }
+
+    #[test]
+    fn test_scopes_are_bounded_by_the_owner() {
+        let support: PermissionSet = ["logs:read", "users:read"].into_iter().collect();
+        assert_eq!(
+            validate_scopes(&["logs:read".to_string()], &support).ok(),
+            Some(vec!["logs:read".to_string()])
+        );
+        assert!(validate_scopes(&["billing:topup".to_string()], &support).is_err());
+        assert!(validate_scopes(&["*".to_string()], &support).is_err());
+        assert!(validate_scopes(&[], &support).is_err());
+
+        let admin: PermissionSet = [WILDCARD].into_iter().collect();
+        assert!(validate_scopes(&["*".to_string()], &admin).is_ok());
+    }
+}
@@ -0,0 +1,653 @@
+//! OAuth 2.0 / OpenID Connect sign-in for Google, GitHub and a generic OIDC
+//! provider.
+//!
+//! [`OAuthService::authorize_url`] stores a random `state` and PKCE verifier
+//! in `user_oauth_states` and returns the provider's authorize URL with the
+//! state, which the caller binds to the browser that started the flow. The
+//! callback hands the `code` and `state` to [`OAuthService::complete`], which
+//! consumes the state, exchanges the code with the verifier, fetches the
+//! profile and then either signs the linked user in, links the identity to
+//! the user who started the flow, or creates a new account.
+//!
+//! Google and the generic provider are configured through OIDC discovery, so
+//! every endpoint can point at a local mock IdP in tests.
+
+use crate::{create_account, Result, UserServiceError, ACTIVE_STATUS};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use burncloud_database::Database;
+use burncloud_database_user::{UserDatabase, UserOAuthIdentity, UserOAuthModel, UserOAuthState};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// How long an authorization request may take before its callback
+pub const STATE_TTL_SECS: i64 = 600;
+/// Timeout for calls to the provider
+const PROVIDER_TIMEOUT_SECS: u64 = 10;
+/// Longest generated username
+const MAX_USERNAME_LEN: usize = 32;
+
+const GOOGLE_DISCOVERY_URL: &str = "https://accounts.google.com/.well-known/openid-configuration";
+const GITHUB_URL: &str = "https://github.com";
+const GITHUB_API_URL: &str = "https://api.github.com";
+
+/// Where a provider's endpoints come from
+#[derive(Debug, Clone)]
+enum ProviderKind {
+    /// Endpoints from an OpenID Connect discovery document
+    Oidc { discovery_url: String },
+    /// GitHub (or GitHub Enterprise) OAuth apps, which are not OIDC
+    GitHub { web_url: String, api_url: String },
+}
+
+/// A configured sign-in provider
+#[derive(Debug, Clone)]
+pub struct OAuthProvider {
+    /// `google`, `github` or `oidc`; stored as `user_oauth_identities.provider`
+    pub name: String,
+    client_id: String,
+    client_secret: Option<String>,
+    redirect_uri: String,
+    scopes: String,
+    kind: ProviderKind,
+}
+
+impl OAuthProvider {
+    /// Read a provider's configuration from the environment. Redirect URIs
+    /// default to `{BASE_URL}/console/api/auth/{name}/callback`.
+    ///
+    /// - `google`: `GOOGLE_CLIENT_ID`, `GOOGLE_CLIENT_SECRET`, `GOOGLE_REDIRECT_URI`,
+    ///   `GOOGLE_DISCOVERY_URL`
+    /// - `github`: `GITHUB_CLIENT_ID`, `GITHUB_CLIENT_SECRET`, `GITHUB_REDIRECT_URI`,
+    ///   `GITHUB_URL`, `GITHUB_API_URL`
+    /// - `oidc`: `OIDC_DISCOVERY_URL`, `OIDC_CLIENT_ID`, `OIDC_CLIENT_SECRET`,
+    ///   `OIDC_REDIRECT_URI`, `OIDC_SCOPES`
+    pub fn from_env(name: &str) -> Result<Self> {
+        let var = |key: &str| std::env::var(key).ok().filter(|v| !v.trim().is_empty());
+        let required = |key: &str| {
+            var(key).ok_or_else(|| UserServiceError::ConfigError(format!("{key} is not set")))
+        };
+        let redirect = |key: &str| {
+            var(key).unwrap_or_else(|| {
+                let base = var("BASE_URL").unwrap_or_else(|| "http://localhost:8080".to_string());
+                format!(
+                    "{}/console/api/auth/{name}/callback",
+                    base.trim_end_matches('/')
+                )
+            })
+        };
+        let provider = match name {
+            "google" => Self {
+                name: name.to_string(),
+                client_id: required("GOOGLE_CLIENT_ID")?,
+                client_secret: var("GOOGLE_CLIENT_SECRET"),
+                redirect_uri: redirect("GOOGLE_REDIRECT_URI"),
+                scopes: "openid email profile".to_string(),
+                kind: ProviderKind::Oidc {
+                    discovery_url: var("GOOGLE_DISCOVERY_URL")
+                        .unwrap_or_else(|| GOOGLE_DISCOVERY_URL.to_string()),
+                },
+            },
+            "github" => Self {
+                name: name.to_string(),
+                client_id: required("GITHUB_CLIENT_ID")?,
+                client_secret: var("GITHUB_CLIENT_SECRET"),
+                redirect_uri: redirect("GITHUB_REDIRECT_URI"),
+                scopes: "read:user user:email".to_string(),
+                kind: ProviderKind::GitHub {
+                    web_url: var("GITHUB_URL").unwrap_or_else(|| GITHUB_URL.to_string()),
+                    api_url: var("GITHUB_API_URL").unwrap_or_else(|| GITHUB_API_URL.to_string()),
+                },
+            },
+            "oidc" => Self {
+                name: name.to_string(),
+                client_id: required("OIDC_CLIENT_ID")?,
+                client_secret: var("OIDC_CLIENT_SECRET"),
+                redirect_uri: redirect("OIDC_REDIRECT_URI"),
+                scopes: var("OIDC_SCOPES").unwrap_or_else(|| "openid email profile".to_string()),
+                kind: ProviderKind::Oidc {
+                    discovery_url: required("OIDC_DISCOVERY_URL")?,
+                },
+            },
+            _ => {
+                return Err(UserServiceError::ConfigError(format!(
+                    "Unknown OAuth provider: {name}"
+                )))
+            }
+        };
+        Ok(provider)
+    }
+
+    /// Whether a verified email from this provider may sign in to an existing
+    /// account with that email. Only the built-in providers qualify; whoever
+    /// runs a generic OIDC IdP can assert any address.
+    fn trusts_email(&self) -> bool {
+        matches!(self.name.as_str(), "google" | "github")
+    }
+
+    async fn endpoints(&self, client: &reqwest::Client) -> Result<Endpoints> {
+        match &self.kind {
+            ProviderKind::Oidc { discovery_url } => {
+                let doc: Discovery = client
+                    .get(discovery_url)
+                    .send()
+                    .await
+                    .and_then(|r| r.error_for_status())
+                    .map_err(provider_error)?
+                    .json()
+                    .await
+                    .map_err(provider_error)?;
+                Ok(Endpoints {
+                    authorize: doc.authorization_endpoint,
+                    token: doc.token_endpoint,
+                    userinfo: doc.userinfo_endpoint.ok_or_else(|| {
+                        UserServiceError::ConfigError(
+                            "OIDC discovery document has no userinfo_endpoint".to_string(),
+                        )
+                    })?,
+                })
+            }
+            ProviderKind::GitHub { web_url, api_url } => {
+                let web_url = web_url.trim_end_matches('/');
+                Ok(Endpoints {
+                    authorize: format!("{web_url}/login/oauth/authorize"),
+                    token: format!("{web_url}/login/oauth/access_token"),
+                    userinfo: format!("{}/user", api_url.trim_end_matches('/')),
+                })
+            }
+        }
+    }
+
+    async fn fetch_profile(
+        &self,
+        client: &reqwest::Client,
+        endpoints: &Endpoints,
+        access_token: &str,
+    ) -> Result<Profile> {
+        let get = |url: String| {
+            client
+                .get(url)
+                .bearer_auth(access_token)
+                .header(reqwest::header::ACCEPT, "application/json")
+                // GitHub rejects requests without a User-Agent
+                .header(reqwest::header::USER_AGENT, "burncloud")
+                .send()
+        };
+        match &self.kind {
+            ProviderKind::Oidc { .. } => {
+                let info: OidcUserInfo = get(endpoints.userinfo.clone())
+                    .await
+                    .and_then(|r| r.error_for_status())
+                    .map_err(provider_error)?
+                    .json()
+                    .await
+                    .map_err(provider_error)?;
+                let username_hint = info
+                    .preferred_username
+                    .or(info.name)
+                    .or_else(|| {
+                        info.email
+                            .as_deref()
+                            .and_then(|e| e.split('@').next())
+                            .map(str::to_string)
+                    })
+                    .unwrap_or_default();
+                Ok(Profile {
+                    subject: info.sub,
+                    email_verified: info.email_verified.is_some_and(|f| f.is_true()),
+                    email: info.email,
+                    username_hint,
+                })
+            }
+            ProviderKind::GitHub { api_url, .. } => {
+                let user: GitHubUser = get(endpoints.userinfo.clone())
+                    .await
+                    .and_then(|r| r.error_for_status())
+                    .map_err(provider_error)?
+                    .json()
+                    .await
+                    .map_err(provider_error)?;
+                // The profile email is whatever the user made public; the
+                // emails API says which address is primary and verified.
+                let emails: Vec<GitHubEmail> =
+                    match get(format!("{}/user/emails", api_url.trim_end_matches('/'))).await {
+                        Ok(response) if response.status().is_success() => {
+                            response.json().await.unwrap_or_default()
+                        }
+                        _ => Vec::new(),
+                    };
+                let verified = emails.into_iter().find(|e| e.primary && e.verified);
+                Ok(Profile {
+                    subject: user.id.to_string(),
+                    email_verified: verified.is_some(),
+                    email: verified.map(|e| e.email).or(user.email),
+                    username_hint: user.login,
+                })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Endpoints {
+    authorize: String,
+    token: String,
+    userinfo: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Discovery {
+    authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+/// Some IdPs send `email_verified` as the string `"true"`
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Flag {
+    Bool(bool),
+    Text(String),
+}
+
+impl Flag {
+    fn is_true(&self) -> bool {
+        match self {
+            Flag::Bool(b) => *b,
+            Flag::Text(s) => s.eq_ignore_ascii_case("true"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OidcUserInfo {
+    sub: String,
+    email: Option<String>,
+    email_verified: Option<Flag>,
+    preferred_username: Option<String>,
+    name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubUser {
+    id: i64,
+    login: String,
+    email: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct GitHubEmail {
+    email: String,
+    primary: bool,
+    verified: bool,
+}
+
+/// Identity returned by a provider after the code exchange
+#[derive(Debug, Clone)]
+struct Profile {
+    subject: String,
+    email: Option<String>,
+    email_verified: bool,
+    username_hint: String,
+}
+
+/// A started flow, returned by [`OAuthService::authorize_url`]
+#[derive(Debug, Clone, Serialize)]
+pub struct OAuthAuthorization {
+    /// Provider authorize URL to send the browser to
+    pub url: String,
+    /// The `state` the callback must come back with
+    pub state: String,
+    /// Our callback the provider redirects to
+    pub redirect_uri: String,
+}
+
+/// Outcome of a completed callback
+#[derive(Debug, Clone, Serialize)]
+pub struct OAuthLogin {
+    pub user_id: String,
+    pub username: String,
+    pub provider: String,
+    /// A new account was created for this identity
+    pub created: bool,
+    /// The identity was linked to an account during this callback
+    pub linked: bool,
+}
+
+fn provider_error(e: reqwest::Error) -> UserServiceError {
+    UserServiceError::ConfigError(format!("OAuth provider request failed: {e}"))
+}
+
+pub(crate) fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// RFC 7636 S256 code challenge
+pub fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(PROVIDER_TIMEOUT_SECS))
+        .build()
+        .unwrap_or_default()
+}
+
+/// Reduce a provider display name to a valid username
+fn sanitize_username(hint: &str) -> String {
+    let cleaned: String = hint
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        .take(MAX_USERNAME_LEN)
+        .collect();
+    if cleaned.is_empty() {
+        "user".to_string()
+    } else {
+        cleaned
+    }
+}
+
+pub struct OAuthService;
+
+impl OAuthService {
+    /// Start an authorization request and return the URL to send the browser
+    /// to. `link_user_id` is the signed-in user when linking a provider.
+    pub async fn authorize_url(
+        db: &Database,
+        provider: &str,
+        link_user_id: Option<&str>,
+    ) -> Result<OAuthAuthorization> {
+        let provider = OAuthProvider::from_env(provider)?;
+        let endpoints = provider.endpoints(&http_client()).await?;
+        let state = UserOAuthState {
+            state: random_token(),
+            provider: provider.name.clone(),
+            code_verifier: random_token(),
+            link_user_id: link_user_id.map(str::to_string),
+            expires_at: chrono::Utc::now().timestamp() + STATE_TTL_SECS,
+        };
+        UserOAuthModel::create_state(db, &state).await?;
+
+        let mut url = reqwest::Url::parse(&endpoints.authorize).map_err(|e| {
+            UserServiceError::ConfigError(format!("invalid authorize endpoint: {e}"))
+        })?;
+        url.query_pairs_mut()
+            .append_pair("client_id", &provider.client_id)
+            .append_pair("redirect_uri", &provider.redirect_uri)
+            .append_pair("response_type", "code")
+            .append_pair("scope", &provider.scopes)
+            .append_pair("state", &state.state)
+            .append_pair("code_challenge", &pkce_challenge(&state.code_verifier))
+            .append_pair("code_challenge_method", "S256");
+        Ok(OAuthAuthorization {
+            url: url.into(),
+            state: state.state,
+            redirect_uri: provider.redirect_uri,
+        })
+    }
+
+    /// Finish the flow started by [`authorize_url`](Self::authorize_url).
+    ///
+    /// Login resolves the user in this order: an already linked identity, an
+    /// existing account with the same verified email (which gets linked, for
+    /// Google and GitHub only), or a new account. The generic OIDC provider
+    /// is refused when its email belongs to an account; that account links
+    /// it from a signed-in session instead. Link requests attach the
+    /// identity to the user who started the flow and fail if it belongs to
+    /// someone else.
+    pub async fn complete(
+        db: &Database,
+        provider: &str,
+        code: &str,
+        state: &str,
+    ) -> Result<OAuthLogin> {
+        let pending = UserOAuthModel::take_state(db, state)
+            .await?
+            .filter(|s| s.provider == provider)
+            .ok_or_else(|| {
+                UserServiceError::InvalidInput("invalid or already used OAuth state".to_string())
+            })?;
+        if pending.expires_at < chrono::Utc::now().timestamp() {
+            return Err(UserServiceError::InvalidInput(
+                "OAuth state expired, start the sign-in again".to_string(),
+            ));
+        }
+
+        let provider = OAuthProvider::from_env(provider)?;
+        let client = http_client();
+        let endpoints = provider.endpoints(&client).await?;
+        let access_token =
+            Self::exchange_code(&client, &provider, &endpoints, code, &pending).await?;
+        let profile = provider
+            .fetch_profile(&client, &endpoints, &access_token)
+            .await?;
+
+        let existing = UserOAuthModel::find_identity(db, &provider.name, &profile.subject).await?;
+        if let Some(link_user_id) = pending.link_user_id.as_deref() {
+            return Self::link(db, &provider.name, &profile, existing, link_user_id).await;
+        }
+
+        if let Some(identity) = existing {
+            let user = UserDatabase::get_user_by_id(db, &identity.user_id)
+                .await?
+                .ok_or(UserServiceError::UserNotFound)?;
+            if user.status != ACTIVE_STATUS {
+                return Err(UserServiceError::PermissionDenied(
+                    "account is disabled".to_string(),
+                ));
+            }
+            return Ok(OAuthLogin {
+                user_id: user.id,
+                username: user.username,
+                provider: provider.name,
+                created: false,
+                linked: false,
+            });
+        }
+
+        if let Some(email) = profile.email.as_deref().filter(|_| profile.email_verified) {
+            if let Some(user) = UserDatabase::get_user_by_email(db, email).await? {
+                if !provider.trusts_email() {
+                    return Err(UserServiceError::PermissionDenied(format!(
+                        "an account with this email already exists, sign in and link {} from its settings",
+                        provider.name
+                    )));
+                }
+                return Self::link(db, &provider.name, &profile, None, &user.id).await;
+            }
+        }
+
+        let username = Self::available_username(db, &profile.username_hint).await?;
+        let user_id = create_account(db, &username, None, profile.email.clone()).await?;
+        UserOAuthModel::link_identity(
+            db,
+            &provider.name,
+            &profile.subject,
+            &user_id,
+            profile.email.as_deref(),
+        )
+        .await?;
+        tracing::info!(provider = %provider.name, %user_id, "Created account from OAuth sign-in");
+        Ok(OAuthLogin {
+            user_id,
+            username,
+            provider: provider.name,
+            created: true,
+            linked: true,
+        })
+    }
+
+    async fn exchange_code(
+        client: &reqwest::Client,
+        provider: &OAuthProvider,
+        endpoints: &Endpoints,
+        code: &str,
+        pending: &UserOAuthState,
+    ) -> Result<String> {
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", provider.redirect_uri.as_str()),
+            ("client_id", provider.client_id.as_str()),
+            ("code_verifier", pending.code_verifier.as_str()),
+        ];
+        if let Some(secret) = provider.client_secret.as_deref() {
+            form.push(("client_secret", secret));
+        }
+        let response: TokenResponse = client
+            .post(&endpoints.token)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .form(&form)
+            .send()
+            .await
+            .map_err(provider_error)?
+            .json()
+            .await
+            .map_err(provider_error)?;
+        if let (Some(token), None) = (response.access_token, response.error.as_deref()) {
+            return Ok(token);
+        }
+        tracing::warn!(
+            provider = %provider.name,
+            error = response.error.as_deref().unwrap_or("missing access_token"),
+            description = response.error_description.as_deref().unwrap_or(""),
+            "OAuth code exchange failed"
+        );
+        Err(UserServiceError::InvalidCredentials)
+    }
+
+    async fn link(
+        db: &Database,
+        provider: &str,
+        profile: &Profile,
+        existing: Option<UserOAuthIdentity>,
+        user_id: &str,
+    ) -> Result<OAuthLogin> {
+        let user = UserDatabase::get_user_by_id(db, user_id)
+            .await?
+            .ok_or(UserServiceError::UserNotFound)?;
+        let linked = match existing {
+            Some(identity) if identity.user_id == user.id => false,
+            Some(_) => {
+                return Err(UserServiceError::PermissionDenied(format!(
+                    "this {provider} account is linked to another user"
+                )))
+            }
+            None => {
+                let already = UserOAuthModel::list_identities(db, &user.id)
+                    .await?
+                    .into_iter()
+                    .any(|i| i.provider == provider);
+                if already {
+                    return Err(UserServiceError::InvalidInput(format!(
+                        "a different {provider} account is already linked, unlink it first"
+                    )));
+                }
+                UserOAuthModel::link_identity(
+                    db,
+                    provider,
+                    &profile.subject,
+                    &user.id,
+                    profile.email.as_deref(),
+                )
+                .await?;
+                true
+            }
+        };
+        Ok(OAuthLogin {
+            user_id: user.id,
+            username: user.username,
+            provider: provider.to_string(),
+            created: false,
+            linked,
+        })
+    }
+
+    /// Username derived from the provider's hint, suffixed until unique
+    async fn available_username(db: &Database, hint: &str) -> Result<String> {
+        let base = sanitize_username(hint);
+        if UserDatabase::get_user_by_username(db, &base)
+            .await?
+            .is_none()
+        {
+            return Ok(base);
+        }
+        for _ in 0..5 {
+            let candidate = format!("{base}-{:04x}", rand::random::<u16>());
+            if UserDatabase::get_user_by_username(db, &candidate)
+                .await?
+                .is_none()
+            {
+                return Ok(candidate);
+            }
+        }
+        Err(UserServiceError::UserAlreadyExists)
+    }
+
+    pub async fn identities(db: &Database, user_id: &str) -> Result<Vec<UserOAuthIdentity>> {
+        UserOAuthModel::list_identities(db, user_id)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Remove a linked provider. Refused when it is the account's only way to
+    /// sign in (no password and no other provider).
+    pub async fn unlink(db: &Database, user_id: &str, provider: &str) -> Result<()> {
+        let user = UserDatabase::get_user_by_id(db, user_id)
+            .await?
+            .ok_or(UserServiceError::UserNotFound)?;
+        let identities = UserOAuthModel::list_identities(db, user_id).await?;
+        if !identities.iter().any(|i| i.provider == provider) {
+            return Err(UserServiceError::InvalidInput(format!(
+                "{provider} is not linked"
+            )));
+        }
+        let has_password = user.password_hash.is_some_and(|h| !h.is_empty());
+        if !has_password && identities.len() == 1 {
+            return Err(UserServiceError::InvalidInput(
+                "cannot unlink the only sign-in method, set a password first".to_string(),
+            ));
+        }
+        UserOAuthModel::unlink_identity(db, user_id, provider).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pkce_challenge_matches_rfc_7636_example() {
+        assert_eq!(
+            pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    #[test]
+    fn usernames_are_sanitized() {
+        assert_eq!(sanitize_username("Jane Doe!"), "JaneDoe");
+        assert_eq!(sanitize_username("李雷"), "user");
+        assert_eq!(sanitize_username(&"a".repeat(40)).len(), MAX_USERNAME_LEN);
+    }
+
+    #[test]
+    fn email_verified_accepts_strings() {
+        let info: OidcUserInfo =
+            serde_json::from_str(r#"{"sub":"1","email_verified":"true"}"#).unwrap();
+        assert!(info.email_verified.is_some_and(|f| f.is_true()));
+    }
+}
@@ -0,0 +1,475 @@
+//! Organizations: shared wallets, member roles and invitations.
+//!
+//! Every organization has exactly one `owner` (its creator). Other members
+//! join through an invitation and hold one of `admin`, `developer` or
+//! `billing`. Permission checks live here so the server only maps errors to
+//! HTTP statuses.
+
+use crate::{Result, UserServiceError};
+use burncloud_database::Database;
+use burncloud_database_user::{UserDatabase, UserOrganizationModel};
+pub use burncloud_database_user::{
+    UserOrganization, UserOrganizationInvitation, UserOrganizationMember,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// How long an invitation stays valid
+const INVITATION_TTL_SECS: i64 = 7 * 24 * 3600;
+
+/// Organization status
+const ORG_ACTIVE: i32 = 1;
+
+/// Member role within an organization
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrgRole {
+    /// Creator; full control, cannot be removed
+    Owner,
+    /// Manages members, invitations and tokens
+    Admin,
+    /// Creates and uses organization tokens
+    Developer,
+    /// Views the wallet and sets spending limits
+    Billing,
+}
+
+impl OrgRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrgRole::Owner => "owner",
+            OrgRole::Admin => "admin",
+            OrgRole::Developer => "developer",
+            OrgRole::Billing => "billing",
+        }
+    }
+
+    /// Invite, remove and change the role of members
+    pub fn can_manage_members(&self) -> bool {
+        matches!(self, OrgRole::Owner | OrgRole::Admin)
+    }
+
+    /// Change the spend limit and view wallet details
+    pub fn can_manage_billing(&self) -> bool {
+        matches!(self, OrgRole::Owner | OrgRole::Billing)
+    }
+
+    /// Create API tokens charged to the organization
+    pub fn can_create_tokens(&self) -> bool {
+        matches!(self, OrgRole::Owner | OrgRole::Admin | OrgRole::Developer)
+    }
+}
+
+impl fmt::Display for OrgRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for OrgRole {
+    type Err = UserServiceError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "owner" => Ok(OrgRole::Owner),
+            "admin" => Ok(OrgRole::Admin),
+            "developer" => Ok(OrgRole::Developer),
+            "billing" => Ok(OrgRole::Billing),
+            other => Err(UserServiceError::InvalidInput(format!(
+                "unknown organization role: {other}"
+            ))),
+        }
+    }
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+/// Organization operations with role-based permission checks
+pub struct OrganizationService;
+
+impl OrganizationService {
+    /// Create an organization owned by `owner_id`
+    pub async fn create(db: &Database, owner_id: &str, name: &str) -> Result<UserOrganization> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(UserServiceError::InvalidInput(
+                "organization name is required".to_string(),
+            ));
+        }
+        let org = UserOrganization {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            owner_id: owner_id.to_string(),
+            balance_usd: 0,
+            balance_cny: 0,
+            spend_limit: -1,
+            spent: 0,
+            status: ORG_ACTIVE,
+            created_at: now(),
+        };
+        UserOrganizationModel::create(db, &org).await?;
+        Ok(org)
+    }
+
+    /// Organizations the user belongs to, with the user's role in each
+    pub async fn list_for_user(
+        db: &Database,
+        user_id: &str,
+    ) -> Result<Vec<(UserOrganization, OrgRole)>> {
+        let rows = UserOrganizationModel::list_for_user(db, user_id).await?;
+        rows.into_iter()
+            .map(|(org, role)| Ok((org, role.parse()?)))
+            .collect()
+    }
+
+    /// All organizations (platform admin view)
+    pub async fn list_all(db: &Database) -> Result<Vec<UserOrganization>> {
+        Ok(UserOrganizationModel::list(db).await?)
+    }
+
+    pub async fn get(db: &Database, org_id: &str) -> Result<UserOrganization> {
+        UserOrganizationModel::get(db, org_id)
+            .await?
+            .ok_or(UserServiceError::OrganizationNotFound)
+    }
+
+    /// Role of `user_id` in the organization; `PermissionDenied` if not a member
+    pub async fn role(db: &Database, org_id: &str, user_id: &str) -> Result<OrgRole> {
+        match UserOrganizationModel::get_member_role(db, org_id, user_id).await? {
+            Some(role) => role.parse(),
+            None => {
+                // Distinguish a missing organization from a non-member
+                Self::get(db, org_id).await?;
+                Err(UserServiceError::PermissionDenied(
+                    "not a member of this organization".to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Require a role satisfying `check` and return it
+    pub async fn require(
+        db: &Database,
+        org_id: &str,
+        user_id: &str,
+        check: fn(&OrgRole) -> bool,
+    ) -> Result<OrgRole> {
+        let role = Self::role(db, org_id, user_id).await?;
+        if check(&role) {
+            Ok(role)
+        } else {
+            Err(UserServiceError::PermissionDenied(format!(
+                "role {role} is not allowed to perform this action"
+            )))
+        }
+    }
+
+    /// Rename the organization (owner/admin)
+    pub async fn rename(db: &Database, org_id: &str, actor: &str, name: &str) -> Result<()> {
+        Self::require(db, org_id, actor, OrgRole::can_manage_members).await?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(UserServiceError::InvalidInput(
+                "organization name is required".to_string(),
+            ));
+        }
+        UserOrganizationModel::update_name(db, org_id, name).await?;
+        Ok(())
+    }
+
+    /// Set the spend limit in nanodollars, `-1` for unlimited (owner/billing)
+    pub async fn set_spend_limit(
+        db: &Database,
+        org_id: &str,
+        actor: &str,
+        spend_limit: i64,
+    ) -> Result<()> {
+        Self::require(db, org_id, actor, OrgRole::can_manage_billing).await?;
+        if spend_limit < -1 {
+            return Err(UserServiceError::InvalidInput(
+                "spend_limit must be -1 (unlimited) or non-negative".to_string(),
+            ));
+        }
+        UserOrganizationModel::update_spend_limit(db, org_id, spend_limit).await?;
+        Ok(())
+    }
+
+    /// Enable or disable the organization (platform admin; permission checked by the caller)
+    pub async fn set_active(db: &Database, org_id: &str, active: bool) -> Result<()> {
+        let status = if active { ORG_ACTIVE } else { 0 };
+        if !UserOrganizationModel::update_status(db, org_id, status).await? {
+            return Err(UserServiceError::OrganizationNotFound);
+        }
+        Ok(())
+    }
+
+    /// Credit the organization's wallet and return the updated organization
+    /// (platform admin; permission checked by the caller). Currencies other
+    /// than USD and CNY are converted into the USD wallet.
+    pub async fn topup(
+        db: &Database,
+        org_id: &str,
+        amount_nano: i64,
+        currency: &str,
+    ) -> Result<UserOrganization> {
+        if amount_nano <= 0 {
+            return Err(UserServiceError::InvalidInput(
+                "amount must be positive".to_string(),
+            ));
+        }
+        let currency: burncloud_common::Currency =
+            currency.parse().map_err(UserServiceError::InvalidInput)?;
+        let (amount_nano, wallet, _) =
+            crate::convert_to_wallet(db, amount_nano, currency).await?;
+        if !UserOrganizationModel::topup(db, org_id, amount_nano, wallet.code()).await? {
+            return Err(UserServiceError::OrganizationNotFound);
+        }
+        Self::get(db, org_id).await
+    }
+
+    // ============== Members ==============
+
+    /// List members (any member)
+    pub async fn list_members(
+        db: &Database,
+        org_id: &str,
+        actor: &str,
+    ) -> Result<Vec<UserOrganizationMember>> {
+        Self::role(db, org_id, actor).await?;
+        Ok(UserOrganizationModel::list_members(db, org_id).await?)
+    }
+
+    /// Change a member's role (owner/admin). Ownership cannot be granted or taken away,
+    /// and only the owner may change another admin.
+    pub async fn update_member_role(
+        db: &Database,
+        org_id: &str,
+        actor: &str,
+        user_id: &str,
+        role: OrgRole,
+    ) -> Result<()> {
+        let actor_role = Self::require(db, org_id, actor, OrgRole::can_manage_members).await?;
+        if role == OrgRole::Owner {
+            return Err(UserServiceError::InvalidInput(
+                "the owner role cannot be assigned".to_string(),
+            ));
+        }
+        let current = Self::member_role(db, org_id, user_id).await?;
+        Self::check_can_modify(actor_role, current)?;
+        UserOrganizationModel::update_member_role(db, org_id, user_id, role.as_str()).await?;
+        Ok(())
+    }
+
+    /// Remove a member (owner/admin), or leave the organization (any non-owner member)
+    pub async fn remove_member(
+        db: &Database,
+        org_id: &str,
+        actor: &str,
+        user_id: &str,
+    ) -> Result<()> {
+        let actor_role = Self::role(db, org_id, actor).await?;
+        let current = Self::member_role(db, org_id, user_id).await?;
+        if current == OrgRole::Owner {
+            return Err(UserServiceError::InvalidInput(
+                "the owner cannot be removed".to_string(),
+            ));
+        }
+        if actor != user_id {
+            if !actor_role.can_manage_members() {
+                return Err(UserServiceError::PermissionDenied(format!(
+                    "role {actor_role} is not allowed to remove members"
+                )));
+            }
+            Self::check_can_modify(actor_role, current)?;
+        }
+        UserOrganizationModel::remove_member(db, org_id, user_id).await?;
+        Ok(())
+    }
+
+    async fn member_role(db: &Database, org_id: &str, user_id: &str) -> Result<OrgRole> {
+        UserOrganizationModel::get_member_role(db, org_id, user_id)
+            .await?
+            .ok_or(UserServiceError::UserNotFound)?
+            .parse()
+    }
+
+    fn check_can_modify(actor: OrgRole, target: OrgRole) -> Result<()> {
+        match target {
+            OrgRole::Owner => Err(UserServiceError::PermissionDenied(
+                "the owner cannot be modified".to_string(),
+            )),
+            OrgRole::Admin if actor != OrgRole::Owner => Err(UserServiceError::PermissionDenied(
+                "only the owner can modify an admin".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    // ============== Invitations ==============
+
+    /// Invite an email address to join with `role` (owner/admin)
+    pub async fn invite(
+        db: &Database,
+        org_id: &str,
+        actor: &str,
+        email: &str,
+        role: OrgRole,
+    ) -> Result<UserOrganizationInvitation> {
+        let actor_role = Self::require(db, org_id, actor, OrgRole::can_manage_members).await?;
+        if role == OrgRole::Owner {
+            return Err(UserServiceError::InvalidInput(
+                "the owner role cannot be assigned".to_string(),
+            ));
+        }
+        if role == OrgRole::Admin && actor_role != OrgRole::Owner {
+            return Err(UserServiceError::PermissionDenied(
+                "only the owner can invite admins".to_string(),
+            ));
+        }
+        let email = email.trim();
+        if !email.contains('@') {
+            return Err(UserServiceError::InvalidInput(
+                "a valid email is required".to_string(),
+            ));
+        }
+
+        let created_at = now();
+        let invitation = UserOrganizationInvitation {
+            code: Uuid::new_v4().simple().to_string(),
+            org_id: org_id.to_string(),
+            email: email.to_lowercase(),
+            role: role.as_str().to_string(),
+            invited_by: actor.to_string(),
+            status: "pending".to_string(),
+            expires_at: created_at + INVITATION_TTL_SECS,
+            created_at,
+        };
+        UserOrganizationModel::create_invitation(db, &invitation).await?;
+        Ok(invitation)
+    }
+
+    /// List invitations (owner/admin)
+    pub async fn list_invitations(
+        db: &Database,
+        org_id: &str,
+        actor: &str,
+    ) -> Result<Vec<UserOrganizationInvitation>> {
+        Self::require(db, org_id, actor, OrgRole::can_manage_members).await?;
+        Ok(UserOrganizationModel::list_invitations(db, org_id).await?)
+    }
+
+    /// Revoke a pending invitation (owner/admin)
+    pub async fn revoke_invitation(
+        db: &Database,
+        org_id: &str,
+        actor: &str,
+        code: &str,
+    ) -> Result<()> {
+        Self::require(db, org_id, actor, OrgRole::can_manage_members).await?;
+        if !UserOrganizationModel::revoke_invitation(db, org_id, code).await? {
+            return Err(UserServiceError::InvalidInvitation(
+                "invitation is not pending".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Accept an invitation as `user_id`. The user's email must match the invited address.
+    pub async fn accept_invitation(
+        db: &Database,
+        code: &str,
+        user_id: &str,
+    ) -> Result<UserOrganization> {
+        let invitation = UserOrganizationModel::get_invitation(db, code)
+            .await?
+            .ok_or_else(|| UserServiceError::InvalidInvitation("invitation not found".into()))?;
+        if invitation.status != "pending" {
+            return Err(UserServiceError::InvalidInvitation(format!(
+                "invitation is {}",
+                invitation.status
+            )));
+        }
+        if invitation.expires_at < now() {
+            return Err(UserServiceError::InvalidInvitation(
+                "invitation has expired".to_string(),
+            ));
+        }
+
+        let user = UserDatabase::get_user_by_id(db, user_id)
+            .await?
+            .ok_or(UserServiceError::UserNotFound)?;
+        let email_matches = user
+            .email
+            .as_deref()
+            .is_some_and(|email| email.eq_ignore_ascii_case(&invitation.email));
+        if !email_matches {
+            return Err(UserServiceError::InvalidInvitation(
+                "invitation was sent to a different email".to_string(),
+            ));
+        }
+        if UserOrganizationModel::get_member_role(db, &invitation.org_id, user_id)
+            .await?
+            .is_some()
+        {
+            return Err(UserServiceError::InvalidInvitation(
+                "already a member of this organization".to_string(),
+            ));
+        }
+
+        if !UserOrganizationModel::accept_invitation(db, &invitation, user_id).await? {
+            return Err(UserServiceError::InvalidInvitation(
+                "invitation is no longer pending".to_string(),
+            ));
+        }
+        Self::get(db, &invitation.org_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_permissions() {
+        assert!(OrgRole::Owner.can_manage_members());
+        assert!(OrgRole::Owner.can_manage_billing());
+        assert!(OrgRole::Owner.can_create_tokens());
+
+        assert!(OrgRole::Admin.can_manage_members());
+        assert!(!OrgRole::Admin.can_manage_billing());
+        assert!(OrgRole::Admin.can_create_tokens());
+
+        assert!(!OrgRole::Developer.can_manage_members());
+        assert!(!OrgRole::Developer.can_manage_billing());
+        assert!(OrgRole::Developer.can_create_tokens());
+
+        assert!(!OrgRole::Billing.can_manage_members());
+        assert!(OrgRole::Billing.can_manage_billing());
+        assert!(!OrgRole::Billing.can_create_tokens());
+    }
+
+    #[test]
+    fn test_role_round_trip() {
+        for role in [
+            OrgRole::Owner,
+            OrgRole::Admin,
+            OrgRole::Developer,
+            OrgRole::Billing,
+        ] {
+            assert!(matches!(role.as_str().parse::<OrgRole>(), Ok(r) if r == role));
+        }
+        assert!("superuser".parse::<OrgRole>().is_err());
+    }
+
+    #[test]
+    fn test_only_owner_modifies_admins() {
+        assert!(OrganizationService::check_can_modify(OrgRole::Owner, OrgRole::Admin).is_ok());
+        assert!(OrganizationService::check_can_modify(OrgRole::Admin, OrgRole::Admin).is_err());
+        assert!(OrganizationService::check_can_modify(OrgRole::Admin, OrgRole::Developer).is_ok());
+        assert!(OrganizationService::check_can_modify(OrgRole::Owner, OrgRole::Owner).is_err());
+    }
+}
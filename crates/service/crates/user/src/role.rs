@@ -0,0 +1,471 @@
+//! Roles and permissions for the console.
+//!
+//! A role grants a set of [`Permission`]s and a user holds the union of the
+//! permissions of their roles. The `admin` preset grants `*` (everything);
+//! `support`, `finance` and `operator` are narrower presets and `user` grants
+//! nothing beyond self-service. Presets are seeded by migrations and are
+//! read-only; custom roles can be created, edited and deleted.
+
+use crate::{Result, UserServiceError};
+use burncloud_database::Database;
+use burncloud_database_user::{UserDatabase, UserRole, UserRoleModel};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Grants every permission; held by the `admin` preset only
+pub const WILDCARD: &str = "*";
+
+/// Role whose last member cannot be removed
+const ADMIN_ROLE: &str = "admin";
+
+/// Longest custom role name
+const MAX_ROLE_NAME_LEN: usize = 32;
+
+/// Something a console user may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum Permission {
+    ChannelsRead,
+    ChannelsWrite,
+    ModelsRead,
+    ModelsWrite,
+    LogsRead,
+    MonitorRead,
+    UsersRead,
+    UsersWrite,
+    TokensRead,
+    TokensWrite,
+    OrgsRead,
+    OrgsWrite,
+    BillingTopup,
+    SecurityRead,
+    SecurityWrite,
+    SecurityBreak,
+    CacheWrite,
+    AuditRead,
+    RolesManage,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 19] = [
+        Permission::ChannelsRead,
+        Permission::ChannelsWrite,
+        Permission::ModelsRead,
+        Permission::ModelsWrite,
+        Permission::LogsRead,
+        Permission::MonitorRead,
+        Permission::UsersRead,
+        Permission::UsersWrite,
+        Permission::TokensRead,
+        Permission::TokensWrite,
+        Permission::OrgsRead,
+        Permission::OrgsWrite,
+        Permission::BillingTopup,
+        Permission::SecurityRead,
+        Permission::SecurityWrite,
+        Permission::SecurityBreak,
+        Permission::CacheWrite,
+        Permission::AuditRead,
+        Permission::RolesManage,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::ChannelsRead => "channels:read",
+            Permission::ChannelsWrite => "channels:write",
+            Permission::ModelsRead => "models:read",
+            Permission::ModelsWrite => "models:write",
+            Permission::LogsRead => "logs:read",
+            Permission::MonitorRead => "monitor:read",
+            Permission::UsersRead => "users:read",
+            Permission::UsersWrite => "users:write",
+            Permission::TokensRead => "tokens:read",
+            Permission::TokensWrite => "tokens:write",
+            Permission::OrgsRead => "orgs:read",
+            Permission::OrgsWrite => "orgs:write",
+            Permission::BillingTopup => "billing:topup",
+            Permission::SecurityRead => "security:read",
+            Permission::SecurityWrite => "security:write",
+            Permission::SecurityBreak => "security:break",
+            Permission::CacheWrite => "cache:write",
+            Permission::AuditRead => "audit:read",
+            Permission::RolesManage => "roles:manage",
+        }
+    }
+
+    /// One-line summary shown next to the permission in the console
+    pub fn description(&self) -> &'static str {
+        match self {
+            Permission::ChannelsRead => "View upstream channels",
+            Permission::ChannelsWrite => "Create, edit and delete channels",
+            Permission::ModelsRead => "View local model instances and virtual models",
+            Permission::ModelsWrite => "Pull, serve and stop models; edit virtual models",
+            Permission::LogsRead => "Read request logs and usage of every user",
+            Permission::MonitorRead => "View system metrics and cache statistics",
+            Permission::UsersRead => "List users and their balances",
+            Permission::UsersWrite => "Register users and change their limits and 2FA policy",
+            Permission::TokensRead => "View API tokens of every user",
+            Permission::TokensWrite => "Manage API tokens of every user",
+            Permission::OrgsRead => "View every organization",
+            Permission::OrgsWrite => "Enable and disable organizations",
+            Permission::BillingTopup => "Top up user and organization balances",
+            Permission::SecurityRead => "View security events and filters",
+            Permission::SecurityWrite => "Change security filters",
+            Permission::SecurityBreak => "Trip the emergency circuit breaker",
+            Permission::CacheWrite => "Clear the response cache",
+            Permission::AuditRead => "Read and export the audit log",
+            Permission::RolesManage => "Manage roles and assign them to users",
+        }
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Permission {
+    type Err = UserServiceError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Permission::ALL
+            .into_iter()
+            .find(|p| p.as_str() == s)
+            .ok_or_else(|| UserServiceError::InvalidInput(format!("unknown permission: {s}")))
+    }
+}
+
+impl From<Permission> for String {
+    fn from(p: Permission) -> Self {
+        p.as_str().to_string()
+    }
+}
+
+impl TryFrom<String> for Permission {
+    type Error = UserServiceError;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+/// Permissions a user holds through their roles
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct PermissionSet(BTreeSet<String>);
+
+impl PermissionSet {
+    pub fn contains(&self, permission: Permission) -> bool {
+        self.0.contains(WILDCARD) || self.0.contains(permission.as_str())
+    }
+
+    /// Holds `*`
+    pub fn is_unrestricted(&self) -> bool {
+        self.0.contains(WILDCARD)
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.0.iter().cloned().collect()
+    }
+
+    /// Whether a holder of this set may grant `name` to others. `*` and
+    /// names that are not permissions need `*`.
+    pub fn may_grant(&self, name: &str) -> bool {
+        match name.parse::<Permission>() {
+            Ok(permission) => self.contains(permission),
+            Err(_) => self.is_unrestricted(),
+        }
+    }
+
+    /// Permissions held both here and in `scopes`, with `*` on either side
+    /// standing for the other side
+    pub fn restrict(&self, scopes: &PermissionSet) -> PermissionSet {
+        if scopes.is_unrestricted() {
+            self.clone()
+        } else if self.is_unrestricted() {
+            scopes.clone()
+        } else {
+            PermissionSet(self.0.intersection(&scopes.0).cloned().collect())
+        }
+    }
+}
+
+impl<S: Into<String>> FromIterator<S> for PermissionSet {
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+        PermissionSet(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+/// A role with its permissions, as the console lists it
+#[derive(Debug, Clone, Serialize)]
+pub struct RoleView {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    /// Preset seeded by migrations; read-only
+    pub built_in: bool,
+    pub permissions: Vec<String>,
+    /// Users bound to the role
+    pub members: i64,
+}
+
+pub struct RoleService;
+
+impl RoleService {
+    /// Permissions the user holds now
+    pub async fn permissions(db: &Database, user_id: &str) -> Result<PermissionSet> {
+        Ok(UserRoleModel::user_permissions(db, user_id)
+            .await?
+            .into_iter()
+            .collect())
+    }
+
+    pub async fn list(db: &Database) -> Result<Vec<RoleView>> {
+        let grants = UserRoleModel::grants(db).await?;
+        let mut views = Vec::new();
+        for role in UserRoleModel::list(db).await? {
+            let permissions = grants
+                .iter()
+                .filter(|(role_id, _)| *role_id == role.id)
+                .map(|(_, permission)| permission.clone())
+                .collect();
+            let members = UserRoleModel::member_count(db, &role.id).await?;
+            views.push(RoleView {
+                built_in: role.built_in != 0,
+                id: role.id,
+                name: role.name,
+                description: role.description,
+                permissions,
+                members,
+            });
+        }
+        Ok(views)
+    }
+
+    pub async fn get(db: &Database, name: &str) -> Result<RoleView> {
+        Self::list(db)
+            .await?
+            .into_iter()
+            .find(|role| role.name == name)
+            .ok_or(UserServiceError::RoleNotFound)
+    }
+
+    /// Create a custom role. `granted` is what the caller holds; a role
+    /// never grants more.
+    pub async fn create(
+        db: &Database,
+        granted: &PermissionSet,
+        name: &str,
+        description: Option<&str>,
+        permissions: &[String],
+    ) -> Result<RoleView> {
+        validate_role_name(name)?;
+        let permissions = validate_permissions(permissions)?;
+        ensure_granted(granted, &permissions)?;
+        if UserRoleModel::get_by_name(db, name).await?.is_some() {
+            return Err(UserServiceError::RoleExists);
+        }
+        let role = UserRole {
+            id: format!("role-{}", Uuid::new_v4()),
+            name: name.to_string(),
+            description: description.map(str::to_string),
+            built_in: 0,
+        };
+        UserRoleModel::create(db, &role, &permissions).await?;
+        Self::get(db, name).await
+    }
+
+    /// Replace the description and permissions of a custom role, within
+    /// what the caller holds (`granted`)
+    pub async fn update(
+        db: &Database,
+        granted: &PermissionSet,
+        name: &str,
+        description: Option<&str>,
+        permissions: &[String],
+    ) -> Result<RoleView> {
+        let permissions = validate_permissions(permissions)?;
+        ensure_granted(granted, &permissions)?;
+        let role = custom_role(db, name).await?;
+        if !UserRoleModel::update(db, &role.id, description, &permissions).await? {
+            return Err(UserServiceError::RoleNotFound);
+        }
+        Self::get(db, name).await
+    }
+
+    /// Delete a custom role; its members simply lose it
+    pub async fn delete(db: &Database, name: &str) -> Result<()> {
+        let role = custom_role(db, name).await?;
+        if !UserRoleModel::delete(db, &role.id).await? {
+            return Err(UserServiceError::RoleNotFound);
+        }
+        Ok(())
+    }
+
+    /// Replace the roles of a user and return their names. Roles the user
+    /// gains may only grant what the caller holds (`granted`). The last
+    /// administrator cannot give up the `admin` role.
+    pub async fn set_user_roles(
+        db: &Database,
+        granted: &PermissionSet,
+        user_id: &str,
+        names: &[String],
+    ) -> Result<Vec<String>> {
+        if UserDatabase::get_user_by_id(db, user_id).await?.is_none() {
+            return Err(UserServiceError::UserNotFound);
+        }
+        let roles = UserRoleModel::list(db).await?;
+        let grants = UserRoleModel::grants(db).await?;
+        let current = UserDatabase::get_user_roles(db, user_id).await?;
+        let mut role_ids = Vec::new();
+        let mut assigned = BTreeSet::new();
+        for name in names {
+            let role = roles
+                .iter()
+                .find(|role| &role.name == name)
+                .ok_or_else(|| UserServiceError::InvalidInput(format!("unknown role: {name}")))?;
+            if !current.contains(&role.name) {
+                let permissions: Vec<String> = grants
+                    .iter()
+                    .filter(|(role_id, _)| *role_id == role.id)
+                    .map(|(_, permission)| permission.clone())
+                    .collect();
+                ensure_granted(granted, &permissions)?;
+            }
+            if assigned.insert(role.name.clone()) {
+                role_ids.push(role.id.clone());
+            }
+        }
+
+        if current.iter().any(|r| r == ADMIN_ROLE) && !assigned.contains(ADMIN_ROLE) {
+            let admin_id = roles
+                .iter()
+                .find(|role| role.name == ADMIN_ROLE)
+                .map(|role| role.id.as_str())
+                .unwrap_or_default();
+            if UserRoleModel::member_count(db, admin_id).await? <= 1 {
+                return Err(UserServiceError::PermissionDenied(
+                    "the last administrator cannot lose the admin role".to_string(),
+                ));
+            }
+        }
+
+        UserRoleModel::set_user_roles(db, user_id, &role_ids).await?;
+        Ok(assigned.into_iter().collect())
+    }
+}
+
+/// A custom role by name; presets are refused
+async fn custom_role(db: &Database, name: &str) -> Result<UserRole> {
+    let role = UserRoleModel::get_by_name(db, name)
+        .await?
+        .ok_or(UserServiceError::RoleNotFound)?;
+    if role.built_in != 0 {
+        return Err(UserServiceError::PermissionDenied(format!(
+            "built-in role {name} cannot be changed"
+        )));
+    }
+    Ok(role)
+}
+
+fn validate_role_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_ROLE_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(UserServiceError::InvalidInput(format!(
+            "role names are 1-{MAX_ROLE_NAME_LEN} lowercase letters, digits, '-' or '_'"
+        )))
+    }
+}
+
+/// Known permission names, deduplicated. `*` is reserved for the admin preset.
+fn validate_permissions(names: &[String]) -> Result<Vec<String>> {
+    let mut permissions = BTreeSet::new();
+    for name in names {
+        if name == WILDCARD {
+            return Err(UserServiceError::InvalidInput(
+                "'*' is reserved for the admin role".to_string(),
+            ));
+        }
+        permissions.insert(name.parse::<Permission>()?);
+    }
+    Ok(permissions.into_iter().map(String::from).collect())
+}
+
+/// Refuse permissions the caller does not hold, so nobody can hand out (or
+/// take for themselves) more than they have
+fn ensure_granted(granted: &PermissionSet, permissions: &[String]) -> Result<()> {
+    match permissions.iter().find(|name| !granted.may_grant(name)) {
+        Some(name) => Err(UserServiceError::PermissionDenied(format!(
+            "cannot grant {name}, which you do not hold"
+        ))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_permission_round_trip() {
+        for permission in Permission::ALL {
+            assert_eq!(
+                permission.as_str().parse::<Permission>().ok(),
+                Some(permission)
+            );
+        }
+        assert!("channels:delete".parse::<Permission>().is_err());
+    }
+
+    #[test]
+    fn test_wildcard_grants_everything() {
+        let admin: PermissionSet = [WILDCARD].into_iter().collect();
+        assert!(admin.is_unrestricted());
+        assert!(Permission::ALL.iter().all(|p| admin.contains(*p)));
+
+        let support: PermissionSet = ["logs:read", "users:read"].into_iter().collect();
+        assert!(support.contains(Permission::LogsRead));
+        assert!(!support.contains(Permission::BillingTopup));
+        assert!(!support.is_unrestricted());
+
+        let logs_only: PermissionSet = ["logs:read", "billing:topup"].into_iter().collect();
+        assert_eq!(admin.restrict(&logs_only), logs_only);
+        assert_eq!(support.restrict(&admin), support);
+        assert_eq!(support.restrict(&logs_only).names(), vec!["logs:read"]);
+    }
+
+    #[test]
+    fn test_grants_stay_within_the_caller() {
+        let admin: PermissionSet = [WILDCARD].into_iter().collect();
+        let manager: PermissionSet = ["roles:manage", "logs:read"].into_iter().collect();
+        assert!(manager.may_grant("logs:read"));
+        assert!(!manager.may_grant("billing:topup"));
+        assert!(!manager.may_grant(WILDCARD));
+        assert!(admin.may_grant(WILDCARD));
+        assert!(ensure_granted(&manager, &["logs:read".to_string()]).is_ok());
+        assert!(matches!(
+            ensure_granted(&manager, &["users:write".to_string()]),
+            Err(UserServiceError::PermissionDenied(_))
+        ));
+    }
+
+    #[test]
+    fn test_custom_role_validation() {
+        assert!(validate_role_name("billing-desk").is_ok());
+        assert!(validate_role_name("Billing Desk").is_err());
+        assert!(validate_role_name("").is_err());
+        assert!(validate_permissions(&["*".to_string()]).is_err());
+        let permissions = validate_permissions(&["logs:read".to_string(), "logs:read".to_string()]);
+        assert_eq!(permissions.ok(), Some(vec!["logs:read".to_string()]));
+    }
+}
@@ -0,0 +1,260 @@
+//! Console sessions: short-lived access tokens paired with rotating refresh
+//! tokens stored server-side.
+//!
+//! Every sign-in creates a [`UserSession`] row and returns an access JWT that
+//! carries the session id (`sid`) plus an opaque refresh token. Refreshing
+//! swaps the refresh token for a new one; presenting an already rotated token
+//! again means it leaked, so the whole session is revoked. Access tokens are
+//! rejected as soon as their session is revoked or expired.
+//!
+//! Tokens without a session (`UserService::generate_token`) can't be listed
+//! or revoked one by one, so they are only accepted for
+//! [`SESSIONLESS_GRACE_SECS`] after issue and must also postdate the
+//! account's `tokens_valid_after`, which "log out all sessions" and password
+//! changes move forward.
+//!
+//! Settings: `JWT_ACCESS_TTL_SECS` (default 900) and `JWT_REFRESH_TTL_DAYS`
+//! (default 30).
+
+use crate::oauth::random_token;
+use crate::{Result, UserService, UserServiceError, ACTIVE_STATUS};
+use bcrypt::{hash, verify, DEFAULT_COST};
+use burncloud_database::Database;
+use burncloud_database_user::{UserDatabase, UserSession, UserSessionModel};
+use chrono::{Duration, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+pub(crate) const DEFAULT_ACCESS_TTL_SECS: i64 = 900;
+pub(crate) const DEFAULT_REFRESH_TTL_DAYS: i64 = 30;
+
+/// How long a token without a session is accepted after it was issued
+pub const SESSIONLESS_GRACE_SECS: i64 = DEFAULT_ACCESS_TTL_SECS;
+
+/// Longest user agent kept with a session
+const MAX_USER_AGENT_LEN: usize = 256;
+
+/// Access and refresh token lifetimes from the environment
+pub(crate) fn ttls_from_env() -> (Duration, Duration) {
+    let positive = |key: &str| {
+        std::env::var(key)
+            .ok()
+            .and_then(|v| v.trim().parse::<i64>().ok())
+            .filter(|v| *v > 0)
+    };
+    (
+        Duration::seconds(positive("JWT_ACCESS_TTL_SECS").unwrap_or(DEFAULT_ACCESS_TTL_SECS)),
+        Duration::days(positive("JWT_REFRESH_TTL_DAYS").unwrap_or(DEFAULT_REFRESH_TTL_DAYS)),
+    )
+}
+
+/// Tokens handed out by sign-in and refresh
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionTokens {
+    pub session_id: String,
+    pub user_id: String,
+    pub username: String,
+    pub access_token: String,
+    /// Expiry of the access token (Unix seconds)
+    pub expires_at: i64,
+    pub refresh_token: String,
+    pub refresh_expires_at: i64,
+}
+
+/// Where a sign-in came from, shown in the session list
+#[derive(Debug, Clone, Default)]
+pub struct SessionClient {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+fn refresh_hash(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+impl UserService {
+    fn session_tokens(
+        &self,
+        session_id: &str,
+        user_id: &str,
+        username: &str,
+        refresh_token: String,
+        refresh_expires_at: i64,
+    ) -> Result<SessionTokens> {
+        let access = self.sign_token(user_id, username, Some(session_id), self.access_token_ttl)?;
+        Ok(SessionTokens {
+            session_id: session_id.to_string(),
+            user_id: user_id.to_string(),
+            username: username.to_string(),
+            access_token: access.token,
+            expires_at: access.expires_at,
+            refresh_token,
+            refresh_expires_at,
+        })
+    }
+
+    /// Open a session for an authenticated user
+    pub async fn start_session(
+        &self,
+        db: &Database,
+        user_id: &str,
+        username: &str,
+        client: &SessionClient,
+    ) -> Result<SessionTokens> {
+        let now = Utc::now().timestamp();
+        let refresh_token = random_token();
+        let session = UserSession {
+            id: Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            refresh_hash: refresh_hash(&refresh_token),
+            previous_hash: None,
+            user_agent: client
+                .user_agent
+                .as_ref()
+                .map(|ua| ua.chars().take(MAX_USER_AGENT_LEN).collect()),
+            ip: client.ip.clone(),
+            created_at: now,
+            last_used_at: now,
+            expires_at: now + self.refresh_token_ttl.num_seconds(),
+            revoked_at: None,
+        };
+        UserSessionModel::create(db, &session).await?;
+        self.session_tokens(
+            &session.id,
+            user_id,
+            username,
+            refresh_token,
+            session.expires_at,
+        )
+    }
+
+    /// Exchange a refresh token for a new token pair. The presented token
+    /// stops working; replaying it later revokes the session.
+    pub async fn refresh_session(
+        &self,
+        db: &Database,
+        refresh_token: &str,
+    ) -> Result<SessionTokens> {
+        let old_hash = refresh_hash(refresh_token);
+        let Some(session) = UserSessionModel::find_by_refresh_hash(db, &old_hash).await? else {
+            if let Some(session) = UserSessionModel::find_by_previous_hash(db, &old_hash).await? {
+                tracing::warn!(
+                    user_id = %session.user_id,
+                    session_id = %session.id,
+                    "Rotated refresh token presented again, revoking session"
+                );
+                UserSessionModel::revoke(db, &session.user_id, &session.id).await?;
+            }
+            return Err(UserServiceError::SessionRevoked);
+        };
+        if !session.is_active(Utc::now().timestamp()) {
+            return Err(UserServiceError::SessionRevoked);
+        }
+        let user = UserDatabase::get_user_by_id(db, &session.user_id)
+            .await?
+            .filter(|u| u.status == ACTIVE_STATUS)
+            .ok_or(UserServiceError::SessionRevoked)?;
+
+        let new_token = random_token();
+        let expires_at = Utc::now().timestamp() + self.refresh_token_ttl.num_seconds();
+        let rotated = UserSessionModel::rotate(
+            db,
+            &session.id,
+            &old_hash,
+            &refresh_hash(&new_token),
+            expires_at,
+        )
+        .await?;
+        if !rotated {
+            // A concurrent refresh won with the same token
+            return Err(UserServiceError::SessionRevoked);
+        }
+        self.session_tokens(&session.id, &user.id, &user.username, new_token, expires_at)
+    }
+
+    /// Revoke one session of `user_id`. Returns `false` when there was no
+    /// such active session.
+    pub async fn logout(&self, db: &Database, user_id: &str, session_id: &str) -> Result<bool> {
+        Ok(UserSessionModel::revoke(db, user_id, session_id).await?)
+    }
+
+    /// Revoke every session of `user_id` and every sessionless token issued
+    /// so far. Returns the number of sessions that were active.
+    pub async fn logout_all(&self, db: &Database, user_id: &str) -> Result<u64> {
+        let revoked = UserSessionModel::revoke_all(db, user_id).await?;
+        UserSessionModel::set_tokens_valid_after(db, user_id, Utc::now().timestamp()).await?;
+        Ok(revoked)
+    }
+
+    pub async fn list_sessions(&self, db: &Database, user_id: &str) -> Result<Vec<UserSession>> {
+        Ok(UserSessionModel::list_active(db, user_id).await?)
+    }
+
+    /// Check that a verified access token has not been revoked. Session
+    /// tokens need a live session owned by the user; sessionless tokens
+    /// must be younger than [`SESSIONLESS_GRACE_SECS`] and issued after the
+    /// account's `tokens_valid_after`.
+    pub async fn check_access(
+        db: &Database,
+        user_id: &str,
+        session_id: Option<&str>,
+        issued_at: i64,
+    ) -> Result<()> {
+        match session_id {
+            Some(sid) => {
+                let session = UserSessionModel::get(db, sid).await?;
+                match session {
+                    Some(s) if s.user_id == user_id && s.is_active(Utc::now().timestamp()) => {
+                        Ok(())
+                    }
+                    _ => Err(UserServiceError::SessionRevoked),
+                }
+            }
+            None if Utc::now().timestamp() - issued_at > SESSIONLESS_GRACE_SECS => {
+                Err(UserServiceError::SessionRevoked)
+            }
+            None => match UserSessionModel::tokens_valid_after(db, user_id).await? {
+                // Same-second tokens are rejected too, a revocation must not
+                // miss a token minted just before it
+                Some(valid_after) if valid_after > 0 && issued_at <= valid_after => {
+                    Err(UserServiceError::SessionRevoked)
+                }
+                Some(_) => Ok(()),
+                None => Err(UserServiceError::UserNotFound),
+            },
+        }
+    }
+
+    /// Change the password of a signed-in user and sign out everywhere.
+    /// Accounts created through OAuth have no password yet and may set one
+    /// without `current_password`.
+    pub async fn change_password(
+        &self,
+        db: &Database,
+        user_id: &str,
+        current_password: &str,
+        new_password: &str,
+    ) -> Result<()> {
+        if new_password.is_empty() {
+            return Err(UserServiceError::InvalidInput(
+                "New password must not be empty".to_string(),
+            ));
+        }
+        let user = UserDatabase::get_user_by_id(db, user_id)
+            .await?
+            .ok_or(UserServiceError::UserNotFound)?;
+        if let Some(current_hash) = user.password_hash.filter(|h| !h.is_empty()) {
+            let valid = verify(current_password, &current_hash)
+                .map_err(|e| UserServiceError::HashError(e.to_string()))?;
+            if !valid {
+                return Err(UserServiceError::InvalidCredentials);
+            }
+        }
+        let password_hash = hash(new_password, DEFAULT_COST)
+            .map_err(|e| UserServiceError::HashError(e.to_string()))?;
+        UserDatabase::update_password_hash(db, user_id, &password_hash).await?;
+        self.logout_all(db, user_id).await?;
+        Ok(())
+    }
+}
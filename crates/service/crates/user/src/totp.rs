@@ -0,0 +1,431 @@
+//! TOTP two-factor authentication (RFC 6238, SHA-1, 6 digits, 30 s steps).
+//!
+//! Enrollment stores a pending secret and returns an `otpauth://` URI (plus
+//! a QR code of it); the first valid code enables it and hands out single-use
+//! recovery codes, stored hashed. Once enabled, password sign-in returns a
+//! short-lived challenge that is exchanged for a session with a TOTP or
+//! recovery code. Accepted time steps are recorded so a code works once.
+//!
+//! Administrators can make 2FA mandatory for an account (`totp_required`);
+//! such accounts cannot disable it and are limited to the auth endpoints
+//! until they enroll.
+//!
+//! Settings: `TOTP_ISSUER` (default `BurnCloud`), shown in authenticator apps.
+
+use crate::oauth::random_token;
+use crate::{Result, UserService, UserServiceError};
+use burncloud_database::Database;
+use burncloud_database_user::{UserAccount, UserDatabase, UserMfaChallenge, UserTotpModel};
+use chrono::Utc;
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::{Rng, RngCore};
+use serde::Serialize;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+const TOTP_STEP_SECS: i64 = 30;
+const TOTP_DIGITS: u32 = 6;
+/// Accepted clock drift, in steps either side of now
+const TOTP_SKEW_STEPS: i64 = 1;
+/// 160-bit secrets, as recommended by RFC 4226
+const SECRET_BYTES: usize = 20;
+const RECOVERY_CODE_COUNT: usize = 10;
+/// Unambiguous lowercase alphabet for recovery codes
+const RECOVERY_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+/// A login challenge must be answered within this many seconds
+const CHALLENGE_TTL_SECS: i64 = 300;
+/// Wrong codes accepted per challenge before it is dropped
+const CHALLENGE_MAX_ATTEMPTS: i32 = 5;
+
+/// What the account page shows
+#[derive(Debug, Clone, Serialize)]
+pub struct TwoFactorStatus {
+    pub enabled: bool,
+    /// Enrollment started but not confirmed yet
+    pub pending: bool,
+    /// Made mandatory by an administrator
+    pub required: bool,
+    pub recovery_codes_remaining: i64,
+}
+
+/// A started enrollment, scanned into an authenticator app
+#[derive(Debug, Clone, Serialize)]
+pub struct TotpSetup {
+    /// Base32 secret for manual entry
+    pub secret: String,
+    pub otpauth_uri: String,
+    /// QR code of `otpauth_uri` as an SVG document
+    pub qr_svg: String,
+}
+
+/// Returned instead of a session when the second factor is still missing
+#[derive(Debug, Clone, Serialize)]
+pub struct LoginChallenge {
+    pub challenge: String,
+    pub expires_at: i64,
+}
+
+fn issuer() -> String {
+    std::env::var("TOTP_ISSUER")
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| "BurnCloud".to_string())
+}
+
+fn hash_hex(value: &str) -> String {
+    format!("{:x}", Sha256::digest(value.as_bytes()))
+}
+
+/// RFC 4226 HOTP value for `counter`
+fn hotp(secret: &[u8], counter: u64) -> u32 {
+    // HMAC accepts keys of any length
+    let Ok(mut mac) = Hmac::<Sha1>::new_from_slice(secret) else {
+        return u32::MAX;
+    };
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    let offset = usize::from(digest[digest.len() - 1] & 0x0f);
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    binary % 10u32.pow(TOTP_DIGITS)
+}
+
+/// Time step of a valid `code` around `now`, if any
+fn verify_code(secret_b32: &str, code: &str, now: i64) -> Option<i64> {
+    let code = code.trim().replace(' ', "");
+    if code.len() != TOTP_DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let expected: u32 = code.parse().ok()?;
+    let secret = BASE32_NOPAD.decode(secret_b32.as_bytes()).ok()?;
+    let current = now.div_euclid(TOTP_STEP_SECS);
+    (current - TOTP_SKEW_STEPS..=current + TOTP_SKEW_STEPS)
+        .filter(|step| *step >= 0)
+        .find(|step| hotp(&secret, *step as u64) == expected)
+}
+
+/// Current code for a secret, used by tests and tooling
+pub fn totp_code(secret_b32: &str, now: i64) -> Option<String> {
+    let secret = BASE32_NOPAD.decode(secret_b32.as_bytes()).ok()?;
+    let step = now.div_euclid(TOTP_STEP_SECS) as u64;
+    Some(format!(
+        "{:0width$}",
+        hotp(&secret, step),
+        width = TOTP_DIGITS as usize
+    ))
+}
+
+fn otpauth_uri(secret: &str, username: &str) -> String {
+    let issuer = issuer();
+    // Path encoding: form encoding would turn spaces into `+`
+    let encode = |v: &str| {
+        url::form_urlencoded::byte_serialize(v.as_bytes())
+            .collect::<String>()
+            .replace('+', "%20")
+    };
+    let label = format!("{}:{}", encode(&issuer), encode(username));
+    let query = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("secret", secret)
+        .append_pair("issuer", &issuer)
+        .append_pair("algorithm", "SHA1")
+        .append_pair("digits", &TOTP_DIGITS.to_string())
+        .append_pair("period", &TOTP_STEP_SECS.to_string())
+        .finish();
+    format!("otpauth://totp/{label}?{query}")
+}
+
+fn qr_svg(data: &str) -> String {
+    qrcode::QrCode::new(data.as_bytes())
+        .map(|code| {
+            code.render::<qrcode::render::svg::Color>()
+                .min_dimensions(200, 200)
+                .build()
+        })
+        .unwrap_or_default()
+}
+
+/// Normalize user input: case, spaces and dashes do not matter
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn generate_recovery_codes() -> Vec<String> {
+    let mut rng = rand::thread_rng();
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let chars: String = (0..10)
+                .map(|_| char::from(RECOVERY_ALPHABET[rng.gen_range(0..RECOVERY_ALPHABET.len())]))
+                .collect();
+            format!("{}-{}", &chars[..5], &chars[5..])
+        })
+        .collect()
+}
+
+impl UserService {
+    pub async fn two_factor_status(&self, db: &Database, user_id: &str) -> Result<TwoFactorStatus> {
+        let totp = UserTotpModel::get(db, user_id).await?;
+        let enabled = totp.as_ref().is_some_and(|t| t.enabled_at.is_some());
+        Ok(TwoFactorStatus {
+            enabled,
+            pending: totp.is_some() && !enabled,
+            required: UserTotpModel::is_required(db, user_id).await?,
+            recovery_codes_remaining: if enabled {
+                UserTotpModel::remaining_recovery_codes(db, user_id).await?
+            } else {
+                0
+            },
+        })
+    }
+
+    pub async fn two_factor_enabled(&self, db: &Database, user_id: &str) -> Result<bool> {
+        Ok(UserTotpModel::get(db, user_id)
+            .await?
+            .is_some_and(|t| t.enabled_at.is_some()))
+    }
+
+    /// An administrator requires 2FA but the user has not enrolled yet
+    pub async fn two_factor_setup_pending(&self, db: &Database, user_id: &str) -> Result<bool> {
+        Ok(UserTotpModel::is_required(db, user_id).await?
+            && !self.two_factor_enabled(db, user_id).await?)
+    }
+
+    /// Start an enrollment with a fresh secret. Calling it again replaces
+    /// an unconfirmed secret.
+    pub async fn begin_two_factor_setup(
+        &self,
+        db: &Database,
+        user_id: &str,
+        username: &str,
+    ) -> Result<TotpSetup> {
+        let mut bytes = [0u8; SECRET_BYTES];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let secret = BASE32_NOPAD.encode(&bytes);
+        if !UserTotpModel::begin(db, user_id, &secret).await? {
+            return Err(UserServiceError::InvalidInput(
+                "Two-factor authentication is already enabled".to_string(),
+            ));
+        }
+        let otpauth_uri = otpauth_uri(&secret, username);
+        Ok(TotpSetup {
+            qr_svg: qr_svg(&otpauth_uri),
+            secret,
+            otpauth_uri,
+        })
+    }
+
+    /// Confirm an enrollment with its first code. Returns the recovery
+    /// codes; they are shown once and only their hashes are kept.
+    pub async fn enable_two_factor(
+        &self,
+        db: &Database,
+        user_id: &str,
+        code: &str,
+    ) -> Result<Vec<String>> {
+        let totp = UserTotpModel::get(db, user_id).await?.ok_or_else(|| {
+            UserServiceError::InvalidInput("Start two-factor setup first".to_string())
+        })?;
+        if totp.enabled_at.is_some() {
+            return Err(UserServiceError::InvalidInput(
+                "Two-factor authentication is already enabled".to_string(),
+            ));
+        }
+        let step = verify_code(&totp.secret, code, Utc::now().timestamp())
+            .ok_or(UserServiceError::InvalidCredentials)?;
+        if !UserTotpModel::consume_step(db, user_id, step).await? {
+            return Err(UserServiceError::InvalidCredentials);
+        }
+        UserTotpModel::enable(db, user_id).await?;
+        self.issue_recovery_codes(db, user_id).await
+    }
+
+    async fn issue_recovery_codes(&self, db: &Database, user_id: &str) -> Result<Vec<String>> {
+        let codes = generate_recovery_codes();
+        let hashes: Vec<String> = codes
+            .iter()
+            .map(|c| hash_hex(&normalize_recovery_code(c)))
+            .collect();
+        UserTotpModel::replace_recovery_codes(db, user_id, &hashes).await?;
+        Ok(codes)
+    }
+
+    /// Check a TOTP code of an enabled enrollment (recovery codes are not
+    /// accepted). Used for step-up verification of sensitive actions.
+    pub async fn verify_totp(&self, db: &Database, user_id: &str, code: &str) -> Result<()> {
+        let totp = UserTotpModel::get(db, user_id)
+            .await?
+            .filter(|t| t.enabled_at.is_some())
+            .ok_or(UserServiceError::InvalidCredentials)?;
+        let step = verify_code(&totp.secret, code, Utc::now().timestamp())
+            .ok_or(UserServiceError::InvalidCredentials)?;
+        if !UserTotpModel::consume_step(db, user_id, step).await? {
+            return Err(UserServiceError::InvalidCredentials);
+        }
+        Ok(())
+    }
+
+    /// Check a TOTP code or consume a recovery code
+    pub async fn verify_second_factor(
+        &self,
+        db: &Database,
+        user_id: &str,
+        code: &str,
+    ) -> Result<()> {
+        let normalized = normalize_recovery_code(code);
+        if normalized.len() > TOTP_DIGITS as usize {
+            return if UserTotpModel::use_recovery_code(db, user_id, &hash_hex(&normalized)).await? {
+                tracing::info!(user_id, "Recovery code used");
+                Ok(())
+            } else {
+                Err(UserServiceError::InvalidCredentials)
+            };
+        }
+        self.verify_totp(db, user_id, code).await
+    }
+
+    /// Replace the recovery codes, proving possession with a TOTP code
+    pub async fn regenerate_recovery_codes(
+        &self,
+        db: &Database,
+        user_id: &str,
+        code: &str,
+    ) -> Result<Vec<String>> {
+        self.verify_totp(db, user_id, code).await?;
+        self.issue_recovery_codes(db, user_id).await
+    }
+
+    pub async fn disable_two_factor(&self, db: &Database, user_id: &str, code: &str) -> Result<()> {
+        if UserTotpModel::is_required(db, user_id).await? {
+            return Err(UserServiceError::PermissionDenied(
+                "Two-factor authentication is required for this account".to_string(),
+            ));
+        }
+        self.verify_second_factor(db, user_id, code).await?;
+        UserTotpModel::delete(db, user_id).await?;
+        Ok(())
+    }
+
+    /// Administrator: make 2FA mandatory (or optional) for an account
+    pub async fn set_two_factor_required(
+        &self,
+        db: &Database,
+        user_id: &str,
+        required: bool,
+    ) -> Result<()> {
+        if !UserTotpModel::set_required(db, user_id, required).await? {
+            return Err(UserServiceError::UserNotFound);
+        }
+        Ok(())
+    }
+
+    /// Administrator: remove a user's enrollment, e.g. after a lost device.
+    /// Returns `false` when none existed.
+    pub async fn reset_two_factor(&self, db: &Database, user_id: &str) -> Result<bool> {
+        Ok(UserTotpModel::delete(db, user_id).await?)
+    }
+
+    /// Password step done, wait for the second factor
+    pub async fn create_login_challenge(
+        &self,
+        db: &Database,
+        user_id: &str,
+    ) -> Result<LoginChallenge> {
+        let token = random_token();
+        let expires_at = Utc::now().timestamp() + CHALLENGE_TTL_SECS;
+        UserTotpModel::create_challenge(
+            db,
+            &UserMfaChallenge {
+                id: hash_hex(&token),
+                user_id: user_id.to_string(),
+                expires_at,
+                attempts: 0,
+            },
+        )
+        .await?;
+        Ok(LoginChallenge {
+            challenge: token,
+            expires_at,
+        })
+    }
+
+    /// Answer a login challenge with a TOTP or recovery code and return the
+    /// signed-in user. The challenge is dropped once answered, expired or
+    /// after too many wrong codes.
+    pub async fn complete_login_challenge(
+        &self,
+        db: &Database,
+        challenge: &str,
+        code: &str,
+    ) -> Result<UserAccount> {
+        let id = hash_hex(challenge);
+        let found = UserTotpModel::get_challenge(db, &id)
+            .await?
+            .ok_or(UserServiceError::InvalidCredentials)?;
+        if found.expires_at < Utc::now().timestamp() || found.attempts >= CHALLENGE_MAX_ATTEMPTS {
+            UserTotpModel::delete_challenge(db, &id).await?;
+            return Err(UserServiceError::InvalidCredentials);
+        }
+        if let Err(e) = self.verify_second_factor(db, &found.user_id, code).await {
+            UserTotpModel::fail_challenge(db, &id).await?;
+            return Err(e);
+        }
+        if !UserTotpModel::delete_challenge(db, &id).await? {
+            return Err(UserServiceError::InvalidCredentials);
+        }
+        UserDatabase::get_user_by_id(db, &found.user_id)
+            .await?
+            .ok_or(UserServiceError::UserNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hotp_matches_rfc_4226_vectors() {
+        let secret = b"12345678901234567890";
+        let expected = [755224, 287082, 359152, 969429, 338314];
+        for (counter, value) in expected.iter().enumerate() {
+            assert_eq!(hotp(secret, counter as u64), *value);
+        }
+    }
+
+    #[test]
+    fn totp_matches_rfc_6238_vector_and_allows_one_step_of_drift() {
+        let secret = BASE32_NOPAD.encode(b"12345678901234567890");
+        // RFC 6238 appendix B, T = 59, truncated to 6 digits
+        assert_eq!(totp_code(&secret, 59).as_deref(), Some("287082"));
+        assert_eq!(verify_code(&secret, "287082", 59), Some(1));
+        assert_eq!(verify_code(&secret, "287 082", 80), Some(1));
+        assert_eq!(verify_code(&secret, "287082", 59 + 90), None);
+        assert_eq!(verify_code(&secret, "28708", 59), None);
+    }
+
+    #[test]
+    fn recovery_codes_are_normalized_before_hashing() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert!(codes
+            .iter()
+            .all(|c| c.len() == 11 && c.as_bytes()[5] == b'-'));
+        assert_eq!(normalize_recovery_code(" AbCde-fGh23 "), "abcdefgh23");
+    }
+
+    #[test]
+    fn provisioning_uri_carries_issuer_and_secret() {
+        let uri = otpauth_uri("JBSWY3DPEHPK3PXP", "alice");
+        assert!(uri.starts_with("otpauth://totp/BurnCloud:alice?"));
+        assert!(uri.contains("secret=JBSWY3DPEHPK3PXP"));
+        assert!(uri.contains("issuer=BurnCloud"));
+        assert!(qr_svg(&uri).contains("<svg"));
+    }
+}
@@ -6,11 +6,14 @@
 //! - show: Show channel details
 //! - update: Update a channel
 //! - delete: Delete a channel
+//! - rekey: Re-encrypt channel keys after a master key rotation
 
 use anyhow::{anyhow, Result};
 use burncloud_common::types::{Channel, ChannelType};
 use burncloud_database::Database;
+use burncloud_database_channel::key_cipher::{self, mask_key};
 use burncloud_database_channel::ChannelProviderModel;
+use burncloud_service_audit::{Actor, AuditEvent, AuditService};
 use clap::ArgMatches;
 use std::io::{self, Write};
 
@@ -218,6 +221,9 @@ pub async fn cmd_channel_add(db: &Database, args: &ArgMatches) -> Result<()> {
     // Save to database
     let id = ChannelProviderModel::create(db, &mut channel).await?;
     println!("Channel created with ID: {}", id);
+    channel.id = id;
+    let event = AuditEvent::new("channel.create").target(id).after(&channel);
+    AuditService::record_or_log(db, &Actor::cli(), event).await;
 
     Ok(())
 }
@@ -229,7 +235,10 @@ pub async fn cmd_channel_list(db: &Database, args: &ArgMatches) -> Result<()> {
         .map(|s| s.as_str())
         .unwrap_or("table");
 
-    let channels = ChannelProviderModel::list(db, 100, 0).await?;
+    let mut channels = ChannelProviderModel::list(db, 100, 0).await?;
+    for channel in &mut channels {
+        channel.key = mask_key(&channel.key);
+    }
 
     if channels.is_empty() {
         println!("No channels found");
@@ -288,13 +297,6 @@ pub async fn cmd_channel_show(db: &Database, args: &ArgMatches) -> Result<()> {
         .await?
         .ok_or_else(|| anyhow!("Channel with ID {} not found", id))?;
 
-    // Mask the API key (show only first 8 characters)
-    let masked_key = if channel.key.len() > 8 {
-        format!("{}{}", &channel.key[..8], "*".repeat(channel.key.len() - 8))
-    } else {
-        channel.key.clone()
-    };
-
     let type_name = get_channel_type_name(ChannelType::from(channel.type_));
     let status = if channel.status == 1 {
         "Active"
@@ -307,7 +309,7 @@ pub async fn cmd_channel_show(db: &Database, args: &ArgMatches) -> Result<()> {
     println!("  Name:        {}", channel.name);
     println!("  Type:        {}", type_name);
     println!("  Status:      {}", status);
-    println!("  Key:         {}", masked_key);
+    println!("  Key:         {}", mask_key(&channel.key));
     println!("  Models:      {}", channel.models);
     println!(
         "  Base URL:    {}",
@@ -332,6 +334,7 @@ pub async fn cmd_channel_update(db: &Database, args: &ArgMatches) -> Result<()>
     let mut channel = ChannelProviderModel::get_by_id(db, id)
         .await?
         .ok_or_else(|| anyhow!("Channel with ID {} not found", id))?;
+    let before = channel.clone();
 
     // Update fields only if provided
     if let Some(name) = args.get_one::<String>("name") {
@@ -406,6 +409,12 @@ pub async fn cmd_channel_update(db: &Database, args: &ArgMatches) -> Result<()>
 
     // Save updates
     ChannelProviderModel::update(db, &channel).await?;
+    let after = ChannelProviderModel::get_by_id(db, id).await?;
+    let event = AuditEvent::new("channel.update")
+        .target(id)
+        .before(&before)
+        .after(&after);
+    AuditService::record_or_log(db, &Actor::cli(), event).await;
 
     println!("Channel {} updated successfully", id);
 
@@ -441,11 +450,41 @@ pub async fn cmd_channel_delete(db: &Database, args: &ArgMatches) -> Result<()>
 
     // Delete channel
     ChannelProviderModel::delete(db, id).await?;
+    let event = AuditEvent::new("channel.delete").target(id).before(&channel);
+    AuditService::record_or_log(db, &Actor::cli(), event).await;
     println!("Channel {} deleted", id);
 
     Ok(())
 }
 
+/// Handle channel rekey command
+pub async fn cmd_channel_rekey(db: &Database) -> Result<()> {
+    let keyring = key_cipher::keyring()?;
+    let report = ChannelProviderModel::reencrypt_keys(db, keyring, true).await?;
+    let event = AuditEvent::new("channel.rekey").after(&serde_json::json!({
+        "master_key": keyring.current_id(),
+        "rewrapped": report.rewrapped,
+        "encrypted": report.encrypted,
+        "failed": report.failed,
+    }));
+    AuditService::record_or_log(db, &Actor::cli(), event).await;
+
+    println!(
+        "Channel keys now use master key {}: {} re-wrapped, {} encrypted",
+        keyring.current_id(),
+        report.rewrapped,
+        report.encrypted
+    );
+    if report.failed > 0 {
+        return Err(anyhow!(
+            "{} channel keys could not be decrypted, add the master key that wrote them to MASTER_KEY_PREVIOUS and run rekey again",
+            report.failed
+        ));
+    }
+
+    Ok(())
+}
+
 /// Handle channel command routing
 pub async fn handle_channel_command(db: &Database, matches: &ArgMatches) -> Result<()> {
     match matches.subcommand() {
@@ -454,6 +493,7 @@ pub async fn handle_channel_command(db: &Database, matches: &ArgMatches) -> Resu
         Some(("show", sub_m)) => cmd_channel_show(db, sub_m).await,
         Some(("update", sub_m)) => cmd_channel_update(db, sub_m).await,
         Some(("delete", sub_m)) => cmd_channel_delete(db, sub_m).await,
+        Some(("rekey", _)) => cmd_channel_rekey(db).await,
         _ => {
             println!("Channel management commands:");
             println!("  add     Add a new channel");
@@ -461,6 +501,7 @@ pub async fn handle_channel_command(db: &Database, matches: &ArgMatches) -> Resu
             println!("  show    Show channel details");
             println!("  update  Update a channel");
             println!("  delete  Delete a channel");
+            println!("  rekey   Re-encrypt channel keys with the current master key");
             println!("\nRun 'burncloud channel <command> --help' for more information.");
             Ok(())
         }
@@ -9,6 +9,7 @@ use super::channel::handle_channel_command;
 use super::currency::handle_currency_command;
 use super::install::handle_install_command;
 use super::log::handle_log_command;
+use super::model::handle_model_command;
 use super::monitor::handle_monitor_command;
 use super::price::{handle_price_command, handle_tiered_command};
 use super::protocol::handle_protocol_command;
@@ -278,6 +279,15 @@ pub async fn handle_command(args: &[String]) -> Result<()> {
                                 .long("reservation-red")
                                 .help("L2 Shaper red reservation share (0.0-1.0)"),
                         ),
+                )
+                .subcommand(
+                    Command::new("rekey")
+                        .about("Re-encrypt channel keys with the current master key")
+                        .long_about(
+                            "Re-encrypt every channel API key with the current MASTER_KEY. \
+                             Keys wrapped by a retired master key are read with MASTER_KEY_PREVIOUS, \
+                             plaintext keys are encrypted. Safe to interrupt and run again.",
+                        ),
                 ),
         )
         .subcommand(
@@ -839,13 +849,13 @@ pub async fn handle_command(args: &[String]) -> Result<()> {
                             Arg::new("from")
                                 .long("from")
                                 .required(true)
-                                .help("Source currency (USD, CNY, EUR)"),
+                                .help("Source currency (USD, CNY, EUR, JPY, GBP, HKD, ...)"),
                         )
                         .arg(
                             Arg::new("to")
                                 .long("to")
                                 .required(true)
-                                .help("Target currency (USD, CNY, EUR)"),
+                                .help("Target currency (USD, CNY, EUR, JPY, GBP, HKD, ...)"),
                         )
                         .arg(
                             Arg::new("rate")
@@ -856,7 +866,29 @@ pub async fn handle_command(args: &[String]) -> Result<()> {
                 )
                 .subcommand(
                     Command::new("refresh")
-                        .about("Refresh exchange rates from external API"),
+                        .about("Refresh exchange rates from the provider in EXCHANGE_RATE_PROVIDER"),
+                )
+                .subcommand(
+                    Command::new("history")
+                        .about("Show applied and refused rates for a currency pair")
+                        .arg(
+                            Arg::new("from")
+                                .long("from")
+                                .required(true)
+                                .help("Source currency (USD, CNY, EUR, JPY, GBP, HKD, ...)"),
+                        )
+                        .arg(
+                            Arg::new("to")
+                                .long("to")
+                                .required(true)
+                                .help("Target currency (USD, CNY, EUR, JPY, GBP, HKD, ...)"),
+                        )
+                        .arg(
+                            Arg::new("limit")
+                                .long("limit")
+                                .default_value("20")
+                                .help("Number of entries to show"),
+                        ),
                 )
                 .subcommand(
                     Command::new("convert")
@@ -870,13 +902,13 @@ pub async fn handle_command(args: &[String]) -> Result<()> {
                             Arg::new("from")
                                 .long("from")
                                 .required(true)
-                                .help("Source currency (USD, CNY, EUR)"),
+                                .help("Source currency (USD, CNY, EUR, JPY, GBP, HKD, ...)"),
                         )
                         .arg(
                             Arg::new("to")
                                 .long("to")
                                 .required(true)
-                                .help("Target currency (USD, CNY, EUR)"),
+                                .help("Target currency (USD, CNY, EUR, JPY, GBP, HKD, ...)"),
                         ),
                 ),
         )
@@ -1023,6 +1055,82 @@ pub async fn handle_command(args: &[String]) -> Result<()> {
                                 .long("model")
                                 .help("Filter by model name"),
                         )
+                        .arg(
+                            Arg::new("token")
+                                .long("token")
+                                .help("Filter by API token key (e.g. sk-xxx)"),
+                        )
+                        .arg(
+                            Arg::new("status")
+                                .long("status")
+                                .value_parser(clap::value_parser!(i32))
+                                .help("Filter by HTTP status code"),
+                        )
+                        .arg(
+                            Arg::new("error-type")
+                                .long("error-type")
+                                .help("Filter by error type (e.g. upstream_error, timeout, rate_limit)"),
+                        )
+                        .arg(
+                            Arg::new("layer-decision")
+                                .long("layer-decision")
+                                .help("Filter by routing decision (e.g. affinity_hit, failover_1)"),
+                        )
+                        .arg(
+                            Arg::new("traffic-color")
+                                .long("traffic-color")
+                                .help("Filter by traffic color"),
+                        )
+                        .arg(
+                            Arg::new("cost-status")
+                                .long("cost-status")
+                                .help("Filter by cost status (ok, price_missing, calc_error, no_model)"),
+                        )
+                        .arg(
+                            Arg::new("since")
+                                .long("since")
+                                .help("Only logs at or after this time (RFC 3339, YYYY-MM-DD[ HH:MM:SS] UTC, or unix seconds)"),
+                        )
+                        .arg(
+                            Arg::new("until")
+                                .long("until")
+                                .help("Only logs before this time (same formats as --since)"),
+                        )
+                        .arg(
+                            Arg::new("min-latency")
+                                .long("min-latency")
+                                .value_parser(clap::value_parser!(i64))
+                                .help("Minimum latency in milliseconds"),
+                        )
+                        .arg(
+                            Arg::new("max-latency")
+                                .long("max-latency")
+                                .value_parser(clap::value_parser!(i64))
+                                .help("Maximum latency in milliseconds"),
+                        )
+                        .arg(
+                            Arg::new("min-cost")
+                                .long("min-cost")
+                                .value_parser(clap::value_parser!(f64))
+                                .help("Minimum cost in dollars"),
+                        )
+                        .arg(
+                            Arg::new("max-cost")
+                                .long("max-cost")
+                                .value_parser(clap::value_parser!(f64))
+                                .help("Maximum cost in dollars"),
+                        )
+                        .arg(
+                            Arg::new("search")
+                                .long("search")
+                                .help("Match a substring of the request ID, path or model"),
+                        )
+                        .arg(
+                            Arg::new("cursor")
+                                .long("cursor")
+                                .value_parser(clap::value_parser!(i64))
+                                .help("Only logs with an ID below this one (the last ID of the previous page)"),
+                        )
                         .arg(
                             Arg::new("limit")
                                 .long("limit")
@@ -1070,6 +1178,91 @@ pub async fn handle_command(args: &[String]) -> Result<()> {
                                 .value_parser(["table", "json"])
                                 .help("Output format (table or json)"),
                         ),
+                )
+                .subcommand(
+                    Command::new("prune")
+                        .about("Archive and delete request logs past their retention period")
+                        .arg(
+                            Arg::new("table")
+                                .long("table")
+                                .default_value("all")
+                                .value_parser(["logs", "bodies", "all"])
+                                .help("Which logs to prune: logs (metadata), bodies or all"),
+                        )
+                        .arg(
+                            Arg::new("days")
+                                .long("days")
+                                .value_parser(clap::value_parser!(u32))
+                                .help("Retention in days (default: configured policy)"),
+                        )
+                        .arg(
+                            Arg::new("dir")
+                                .long("dir")
+                                .help("Archive directory (default: configured policy)"),
+                        )
+                        .arg(
+                            Arg::new("no-archive")
+                                .long("no-archive")
+                                .action(clap::ArgAction::SetTrue)
+                                .help("Delete without writing an archive first"),
+                        )
+                        .arg(
+                            Arg::new("dry-run")
+                                .long("dry-run")
+                                .action(clap::ArgAction::SetTrue)
+                                .help("Only report how many rows would be pruned"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("archive")
+                        .about("Export request logs past their retention period without deleting them")
+                        .arg(
+                            Arg::new("table")
+                                .long("table")
+                                .default_value("all")
+                                .value_parser(["logs", "bodies", "all"])
+                                .help("Which logs to archive: logs (metadata), bodies or all"),
+                        )
+                        .arg(
+                            Arg::new("days")
+                                .long("days")
+                                .value_parser(clap::value_parser!(u32))
+                                .help("Retention in days (default: configured policy)"),
+                        )
+                        .arg(
+                            Arg::new("dir")
+                                .long("dir")
+                                .help("Archive directory (default: configured policy)"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("restore")
+                        .about("Re-import archived request logs")
+                        .arg(
+                            Arg::new("path")
+                                .required(true)
+                                .help("Archive file (.jsonl.gz) or directory of archives"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("rollup")
+                        .about("Manage minute/hour/day usage rollups")
+                        .subcommand_required(true)
+                        .subcommand(
+                            Command::new("backfill")
+                                .about("Rebuild usage rollups from request logs")
+                                .arg(
+                                    Arg::new("since")
+                                        .long("since")
+                                        .required(true)
+                                        .help("First day to rebuild (UTC date or time)"),
+                                )
+                                .arg(
+                                    Arg::new("until")
+                                        .long("until")
+                                        .help("End of the range, exclusive (default: start of today, UTC)"),
+                                ),
+                        ),
                 ),
         )
         .subcommand(
@@ -1105,6 +1298,80 @@ pub async fn handle_command(args: &[String]) -> Result<()> {
                                 .value_parser(clap::value_parser!(usize)),
                         ),
                 ),
+        )
+        .subcommand(
+            Command::new("model")
+                .about("Manage local models (download, serve with llama-server)")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("pull")
+                        .about("Download a GGUF quantization from HuggingFace")
+                        .arg(
+                            Arg::new("model")
+                                .required(true)
+                                .help("HuggingFace model ID (e.g., Qwen/Qwen2.5-7B-Instruct-GGUF)"),
+                        )
+                        .arg(
+                            Arg::new("file")
+                                .required(true)
+                                .help("Quantization (e.g., Q4_K_M) or file path inside the repository"),
+                        )
+                        .arg(
+                            Arg::new("sha256")
+                                .long("sha256")
+                                .help("Expected SHA-256 (defaults to the checksum recorded for the model)"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("serve")
+                        .about("Start a model with llama-server and register it as a channel")
+                        .arg(
+                            Arg::new("model")
+                                .required(true)
+                                .help("Model ID (also the model name clients request)"),
+                        )
+                        .arg(
+                            Arg::new("file")
+                                .long("file")
+                                .help("GGUF file to serve (defaults to the pulled file)"),
+                        )
+                        .arg(
+                            Arg::new("port")
+                                .long("port")
+                                .help("Port for llama-server (defaults to a free port)"),
+                        )
+                        .arg(
+                            Arg::new("ctx-size")
+                                .long("ctx-size")
+                                .help("Context size in tokens (default: 4096)"),
+                        )
+                        .arg(
+                            Arg::new("gpu-layers")
+                                .long("gpu-layers")
+                                .help("Layers to offload to the GPU (default: -1, all)")
+                                .allow_hyphen_values(true),
+                        ),
+                )
+                .subcommand(
+                    Command::new("stop")
+                        .about("Stop a served model and remove its channel")
+                        .arg(
+                            Arg::new("model")
+                                .required(true)
+                                .help("Model ID to stop"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("ps")
+                        .about("List local model instances")
+                        .arg(
+                            Arg::new("format")
+                                .long("format")
+                                .default_value("table")
+                                .value_parser(["table", "json"])
+                                .help("Output format (table or json)"),
+                        ),
+                ),
         );
 
     let matches = app.try_get_matches_from(
@@ -1172,6 +1439,11 @@ pub async fn handle_command(args: &[String]) -> Result<()> {
             handle_monitor_command(&db, sub_m).await?;
             db.close().await?;
         }
+        Some(("model", sub_m)) => {
+            let db = Database::new().await?;
+            handle_model_command(&db, sub_m).await?;
+            db.close().await?;
+        }
         _ => {
             show_help();
         }
@@ -1256,6 +1528,12 @@ pub fn show_help() {
     println!("  burncloud tiered add-tier     - Add tiered pricing");
     println!("  burncloud tiered import-tiered - Import tiered pricing JSON");
     println!();
+    println!("Local Models:");
+    println!("  burncloud model pull <model> <quant> - Download a GGUF quantization");
+    println!("  burncloud model serve <model>        - Serve a pulled model as a channel");
+    println!("  burncloud model stop <model>         - Stop a served model");
+    println!("  burncloud model ps                   - List local model instances");
+    println!();
     println!("Examples:");
     println!("  burncloud client");
     println!("  burncloud update --check-only");
@@ -6,8 +6,13 @@ use anyhow::Result;
 use burncloud_common::{rate_to_scaled, scaled_to_rate, Currency};
 use burncloud_database::sqlx;
 use burncloud_database::Database;
+use burncloud_database_billing::BillingExchangeRateModel;
+use burncloud_router::exchange_rate::ExchangeRateService;
+use burncloud_router::exchange_rate_provider::ExchangeRateConfig;
+use burncloud_service_audit::{Actor, AuditEvent, AuditService};
 use clap::ArgMatches;
 use std::str::FromStr;
+use std::sync::Arc;
 
 /// Handle currency subcommands
 pub async fn handle_currency_command(db: &Database, matches: &ArgMatches) -> Result<()> {
@@ -28,6 +33,20 @@ pub async fn handle_currency_command(db: &Database, matches: &ArgMatches) -> Res
             cmd_set_rate(db, from, to, rate).await
         }
         Some(("refresh", _)) => cmd_refresh_rates(db).await,
+        Some(("history", sub_m)) => {
+            let from = sub_m
+                .get_one::<String>("from")
+                .ok_or_else(|| anyhow::anyhow!("from argument is required"))?;
+            let to = sub_m
+                .get_one::<String>("to")
+                .ok_or_else(|| anyhow::anyhow!("to argument is required"))?;
+            let limit: i64 = sub_m
+                .get_one::<String>("limit")
+                .and_then(|s| s.parse().ok())
+                .ok_or_else(|| anyhow::anyhow!("Invalid limit value"))?;
+
+            cmd_history(db, from, to, limit).await
+        }
         Some(("convert", sub_m)) => {
             let amount: f64 = sub_m
                 .get_one::<String>("amount")
@@ -103,43 +122,24 @@ async fn cmd_set_rate(db: &Database, from: &str, to: &str, rate: f64) -> Result<
         return Err(anyhow::anyhow!("Rate must be positive"));
     }
 
-    let conn = db.get_connection()?;
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map_err(|e| anyhow::anyhow!("Time error: {}", e))?
-        .as_secs() as i64;
-
-    // Convert f64 rate to i64 scaled value for storage
+    // Convert f64 rate to i64 scaled value for storage; recorded in the
+    // rate history with source "manual"
     let rate_nano = rate_to_scaled(rate);
-
-    let sql = match db.kind().as_str() {
-        "postgres" => {
-            r#"
-            INSERT INTO billing_exchange_rates (from_currency, to_currency, rate, updated_at)
-            VALUES ($1, $2, $3, $4)
-            ON CONFLICT(from_currency, to_currency) DO UPDATE SET
-                rate = EXCLUDED.rate,
-                updated_at = EXCLUDED.updated_at
-        "#
-        }
-        _ => {
-            r#"
-            INSERT INTO billing_exchange_rates (from_currency, to_currency, rate, updated_at)
-            VALUES (?, ?, ?, ?)
-            ON CONFLICT(from_currency, to_currency) DO UPDATE SET
-                rate = excluded.rate,
-                updated_at = excluded.updated_at
-        "#
-        }
-    };
-
-    sqlx::query(sql)
-        .bind(from_currency.code())
-        .bind(to_currency.code())
-        .bind(rate_nano) // Store as BIGINT (scaled i64)
-        .bind(now)
-        .execute(conn.pool())
-        .await?;
+    let before =
+        BillingExchangeRateModel::get(db, from_currency.code(), to_currency.code()).await?;
+    BillingExchangeRateModel::upsert(
+        db,
+        from_currency.code(),
+        to_currency.code(),
+        rate_nano,
+        "manual",
+    )
+    .await?;
+    let event = AuditEvent::new("exchange_rate.set")
+        .target(format!("{}/{}", from_currency.code(), to_currency.code()))
+        .before(&before.map(|rate| serde_json::json!({ "rate": rate })))
+        .after(&serde_json::json!({ "rate": rate_nano }));
+    AuditService::record_or_log(db, &Actor::cli(), event).await;
 
     println!(
         "✓ Exchange rate set: {} → {} = {:.6}",
@@ -157,16 +157,85 @@ async fn cmd_set_rate(db: &Database, from: &str, to: &str, rate: f64) -> Result<
     Ok(())
 }
 
-/// Refresh exchange rates (placeholder for external API integration)
-async fn cmd_refresh_rates(_db: &Database) -> Result<()> {
-    println!("Exchange rate refresh is not yet implemented.");
-    println!();
-    println!("Future versions will support automatic rate updates from:");
-    println!("  - ExchangeRate-API (exchangerate-api.com)");
-    println!("  - Fixer.io");
-    println!();
-    println!("For now, manually set rates using:");
-    println!("  burncloud currency set-rate --from USD --to CNY --rate <value>");
+/// Refresh exchange rates once from the configured provider
+async fn cmd_refresh_rates(db: &Database) -> Result<()> {
+    let config = ExchangeRateConfig::from_env();
+    if config.provider.is_none() {
+        println!("No exchange rate provider configured.");
+        println!();
+        println!("Set EXCHANGE_RATE_PROVIDER to one of:");
+        println!("  open-er-api       open.er-api.com (no key)");
+        println!("  frankfurter       api.frankfurter.app (ECB reference rates)");
+        println!("  exchangerate-api  v6.exchangerate-api.com (EXCHANGE_RATE_API_KEY)");
+        println!("  file              local JSON file (EXCHANGE_RATE_FILE)");
+        println!();
+        println!("Or set rates manually using:");
+        println!("  burncloud currency set-rate --from USD --to CNY --rate <value>");
+        return Ok(());
+    }
+
+    // The service holds a shared handle, so it opens its own connection
+    let service = ExchangeRateService::new(Arc::new(Database::new().await?)).with_config(config);
+    // Current rates are the reference for the sanity bound
+    service.load_rates_from_db().await?;
+    let report = service.refresh_once().await?;
+    let event = AuditEvent::new("exchange_rate.refresh").after(&serde_json::json!({
+        "applied": report.applied,
+        "rejected": report.rejected,
+    }));
+    AuditService::record_or_log(db, &Actor::cli(), event).await;
+
+    println!(
+        "✓ Exchange rates refreshed: {} applied, {} refused",
+        report.applied, report.rejected
+    );
+    if report.rejected > 0 {
+        println!();
+        println!("Refused rates moved beyond EXCHANGE_RATE_MAX_CHANGE_PCT.");
+        println!("Inspect them with 'burncloud currency history', or set them with 'set-rate'.");
+    }
+
+    Ok(())
+}
+
+/// Show the rate history of a currency pair
+async fn cmd_history(db: &Database, from: &str, to: &str, limit: i64) -> Result<()> {
+    let from_currency =
+        Currency::from_str(from).map_err(|e| anyhow::anyhow!("Invalid 'from' currency: {}", e))?;
+    let to_currency =
+        Currency::from_str(to).map_err(|e| anyhow::anyhow!("Invalid 'to' currency: {}", e))?;
+
+    let rows =
+        BillingExchangeRateModel::history(db, from_currency.code(), to_currency.code(), limit)
+            .await?;
+    if rows.is_empty() {
+        println!("No rate history for {} → {}.", from_currency, to_currency);
+        return Ok(());
+    }
+
+    println!(
+        "{:<20} {:>15} {:<18} {:<9} Note",
+        "Recorded", "Rate", "Source", "Status"
+    );
+    println!("{}", "-".repeat(80));
+    for row in rows {
+        let recorded = chrono::DateTime::from_timestamp(row.recorded_at, 0)
+            .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "Unknown".to_string());
+        let status = if row.applied != 0 {
+            "applied"
+        } else {
+            "refused"
+        };
+        println!(
+            "{:<20} {:>15.6} {:<18} {:<9} {}",
+            recorded,
+            scaled_to_rate(row.rate),
+            row.source,
+            status,
+            row.note.unwrap_or_default()
+        );
+    }
 
     Ok(())
 }
@@ -261,6 +330,10 @@ mod tests {
             Currency::from_str("EUR").unwrap_or_else(|e| panic!("EUR should parse: {e}")),
             Currency::EUR
         );
-        assert!(Currency::from_str("GBP").is_err());
+        assert_eq!(
+            Currency::from_str("GBP").unwrap_or_else(|e| panic!("GBP should parse: {e}")),
+            Currency::GBP
+        );
+        assert!(Currency::from_str("XYZ").is_err());
     }
 }
@@ -2,11 +2,16 @@ use anyhow::Result;
 use burncloud_common::scaled_to_rate;
 use burncloud_database::sqlx;
 use burncloud_database::Database;
-use burncloud_database_router::{
-    get_usage_stats, get_usage_stats_by_token, RouterDatabase, RouterLog,
+use burncloud_database_router::{get_usage_stats, get_usage_stats_by_token, token_hash, RouterLog};
+use burncloud_service_audit::{Actor, AuditEvent, AuditService};
+use burncloud_service_router_log::retention::{self, LogTable, TableReport};
+use burncloud_service_router_log::{
+    parse_log_time, parse_unix_time, LogFilter, LogRetentionService, RetentionPolicy,
+    RollupGranularity, RouterLogService, UsageRollupService,
 };
 use clap::ArgMatches;
 use serde::Serialize;
+use std::path::{Path, PathBuf};
 
 /// Log list item for JSON output
 #[derive(Debug, Clone, Serialize)]
@@ -74,14 +79,44 @@ fn extract_model_from_path(path: &str) -> String {
 
 /// Handle log list command
 pub async fn cmd_log_list(db: &Database, matches: &ArgMatches) -> Result<()> {
-    let user_id = matches.get_one::<String>("user-id").map(|s| s.as_str());
-    let channel_id = matches.get_one::<String>("channel-id").map(|s| s.as_str());
-    let model = matches.get_one::<String>("model").map(|s| s.as_str());
-    let limit: i32 = matches
+    let text = |name: &str| matches.get_one::<String>(name).cloned();
+    let time = |name: &str| -> Result<Option<String>> {
+        match matches.get_one::<String>(name) {
+            Some(v) => parse_log_time(v)
+                .map(Some)
+                .ok_or_else(|| anyhow::anyhow!("Invalid --{}: {}", name, v)),
+            None => Ok(None),
+        }
+    };
+    let nano = |name: &str| {
+        matches
+            .get_one::<f64>(name)
+            .map(|dollars| (dollars * 1_000_000_000.0).round() as i64)
+    };
+    let filter = LogFilter {
+        user_id: text("user-id"),
+        token_hash: text("token").map(|t| token_hash(&t)),
+        model: text("model"),
+        upstream_id: text("channel-id"),
+        status_code: matches.get_one::<i32>("status").copied(),
+        error_type: text("error-type"),
+        layer_decision: text("layer-decision"),
+        traffic_color: text("traffic-color"),
+        cost_status: text("cost-status"),
+        since: time("since")?,
+        until: time("until")?,
+        min_latency_ms: matches.get_one::<i64>("min-latency").copied(),
+        max_latency_ms: matches.get_one::<i64>("max-latency").copied(),
+        min_cost: nano("min-cost"),
+        max_cost: nano("max-cost"),
+        search: text("search"),
+    };
+    let cursor = matches.get_one::<i64>("cursor").copied();
+    let limit: i64 = matches
         .get_one::<String>("limit")
         .and_then(|s| s.parse().ok())
         .unwrap_or(100);
-    let offset: i32 = matches
+    let offset: i64 = matches
         .get_one::<String>("offset")
         .and_then(|s| s.parse().ok())
         .unwrap_or(0);
@@ -91,14 +126,19 @@ pub async fn cmd_log_list(db: &Database, matches: &ArgMatches) -> Result<()> {
         .unwrap_or("table");
 
     // Fetch logs with optional filtering
-    let logs =
-        RouterDatabase::get_logs_filtered(db, user_id, channel_id, model, limit, offset).await?;
+    let logs = RouterLogService::search(db, &filter, cursor, limit, offset).await?;
 
     if logs.is_empty() {
         println!("No logs found");
         return Ok(());
     }
 
+    let next_cursor = if logs.len() as i64 == limit {
+        logs.last().map(|log| log.id)
+    } else {
+        None
+    };
+
     // Convert to list items
     let list_items: Vec<LogListItem> = logs.into_iter().map(LogListItem::from).collect();
 
@@ -142,6 +182,9 @@ pub async fn cmd_log_list(db: &Database, matches: &ArgMatches) -> Result<()> {
                     timestamp
                 );
             }
+            if let Some(cursor) = next_cursor {
+                println!("\nMore logs available: --cursor {}", cursor);
+            }
         }
     }
 
@@ -157,8 +200,21 @@ pub async fn handle_log_command(db: &Database, matches: &ArgMatches) -> Result<(
         Some(("usage", sub_m)) => {
             cmd_log_usage(db, sub_m).await?;
         }
+        Some(("prune", sub_m)) => {
+            cmd_log_prune(db, sub_m).await?;
+        }
+        Some(("archive", sub_m)) => {
+            cmd_log_archive(db, sub_m).await?;
+        }
+        Some(("restore", sub_m)) => {
+            cmd_log_restore(db, sub_m).await?;
+        }
+        Some(("rollup", sub_m)) => match sub_m.subcommand() {
+            Some(("backfill", backfill_m)) => cmd_log_rollup_backfill(db, backfill_m).await?,
+            _ => println!("Usage: burncloud log rollup backfill --since <DATE>"),
+        },
         _ => {
-            println!("Usage: burncloud log <list|usage>");
+            println!("Usage: burncloud log <list|usage|prune|archive|restore|rollup>");
             println!("Run 'burncloud log --help' for more information.");
         }
     }
@@ -305,3 +361,168 @@ async fn get_usd_to_cny_rate(db: &Database) -> Result<f64> {
     // Default rate if not found
     Ok(7.24)
 }
+
+/// Tables selected by `--table`, bodies first so the cascade from
+/// `router_logs` never removes bodies before they are archived.
+fn selected_tables(matches: &ArgMatches) -> Vec<LogTable> {
+    match matches.get_one::<String>("table").map(|s| s.as_str()) {
+        Some("logs") => vec![LogTable::RouterLogs],
+        Some("bodies") => vec![LogTable::RequestLogs],
+        _ => vec![LogTable::RequestLogs, LogTable::RouterLogs],
+    }
+}
+
+/// Retention days for `table`: `--days` or the configured policy.
+fn retention_days(policy: &RetentionPolicy, table: LogTable, matches: &ArgMatches) -> Option<u32> {
+    matches
+        .get_one::<u32>("days")
+        .copied()
+        .or_else(|| policy.days_for(table))
+}
+
+fn print_table_report(action: &str, table: LogTable, days: u32, report: &TableReport) {
+    println!(
+        "{}: {} rows older than {} days {}, {} archived to {} file(s) ({} bytes)",
+        table.table_name(),
+        report.rows_purged.max(report.rows_archived),
+        days,
+        action,
+        report.rows_archived,
+        report.archive_files,
+        report.archive_bytes
+    );
+}
+
+/// Handle log prune command
+pub async fn cmd_log_prune(db: &Database, matches: &ArgMatches) -> Result<()> {
+    let policy = RetentionPolicy::from_env();
+    let dry_run = matches.get_flag("dry-run");
+    let archive_dir: Option<PathBuf> = if matches.get_flag("no-archive") {
+        None
+    } else {
+        matches
+            .get_one::<String>("dir")
+            .map(PathBuf::from)
+            .or_else(|| policy.archive_dir.clone())
+    };
+
+    for table in selected_tables(matches) {
+        let Some(days) = retention_days(&policy, table, matches) else {
+            println!("{}: retention disabled, skipping", table.table_name());
+            continue;
+        };
+
+        if dry_run {
+            let count = retention::count_expired(db, table, days).await?;
+            println!(
+                "{}: {} rows older than {} days would be pruned",
+                table.table_name(),
+                count,
+                days
+            );
+            continue;
+        }
+
+        let report =
+            retention::prune(db, table, days, archive_dir.as_deref(), policy.batch_size).await?;
+        let event = AuditEvent::new("log.prune")
+            .target(table.table_name())
+            .after(&serde_json::json!({
+                "days": days,
+                "archive_dir": archive_dir,
+                "report": report,
+            }));
+        AuditService::record_or_log(db, &Actor::cli(), event).await;
+        print_table_report("purged", table, days, &report);
+    }
+
+    if !dry_run {
+        match &archive_dir {
+            Some(dir) => println!("Archive directory: {}", dir.display()),
+            None => println!("Rows were deleted without an archive."),
+        }
+    }
+
+    Ok(())
+}
+
+/// Handle log archive command
+pub async fn cmd_log_archive(db: &Database, matches: &ArgMatches) -> Result<()> {
+    let policy = RetentionPolicy::from_env();
+    let Some(archive_dir) = matches
+        .get_one::<String>("dir")
+        .map(PathBuf::from)
+        .or_else(|| policy.archive_dir.clone())
+    else {
+        anyhow::bail!("No archive directory configured; pass --dir");
+    };
+
+    for table in selected_tables(matches) {
+        let Some(days) = retention_days(&policy, table, matches) else {
+            println!("{}: retention disabled, skipping", table.table_name());
+            continue;
+        };
+        let report = retention::archive(db, table, days, &archive_dir, policy.batch_size).await?;
+        let event = AuditEvent::new("log.archive")
+            .target(table.table_name())
+            .after(&serde_json::json!({
+                "days": days,
+                "archive_dir": archive_dir,
+                "report": report,
+            }));
+        AuditService::record_or_log(db, &Actor::cli(), event).await;
+        print_table_report("exported", table, days, &report);
+    }
+    println!("Archive directory: {}", archive_dir.display());
+
+    Ok(())
+}
+
+/// Handle log restore command
+pub async fn cmd_log_restore(db: &Database, matches: &ArgMatches) -> Result<()> {
+    let Some(path) = matches.get_one::<String>("path") else {
+        anyhow::bail!("Archive path is required");
+    };
+    let report = LogRetentionService::restore(db, Path::new(path)).await?;
+    let event = AuditEvent::new("log.restore").target(path).after(&report);
+    AuditService::record_or_log(db, &Actor::cli(), event).await;
+    println!(
+        "Restored {} rows from {} file(s), skipped {} already present",
+        report.rows_restored, report.files, report.rows_skipped
+    );
+
+    Ok(())
+}
+
+/// Handle log rollup backfill command
+pub async fn cmd_log_rollup_backfill(db: &Database, matches: &ArgMatches) -> Result<()> {
+    let time = |name: &str| -> Result<Option<i64>> {
+        match matches.get_one::<String>(name) {
+            Some(v) => parse_unix_time(v)
+                .map(Some)
+                .ok_or_else(|| anyhow::anyhow!("Invalid --{}: {}", name, v)),
+            None => Ok(None),
+        }
+    };
+    let Some(since) = time("since")? else {
+        anyhow::bail!("--since is required");
+    };
+    let now = chrono::Utc::now().timestamp();
+    let until = time("until")?.unwrap_or_else(|| RollupGranularity::Day.bucket_start(now));
+    if until <= since {
+        anyhow::bail!("--until must be after --since");
+    }
+    if until > RollupGranularity::Day.bucket_start(now) {
+        println!(
+            "Warning: the range includes today; requests logged while rebuilding may be miscounted"
+        );
+    }
+
+    let report = UsageRollupService::backfill(db, since, until).await?;
+    println!(
+        "Rebuilt usage rollups from {} logs ({} old rollup rows replaced)",
+        report.logs_aggregated, report.rows_deleted
+    );
+
+    Ok(())
+}
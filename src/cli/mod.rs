@@ -4,6 +4,7 @@ pub mod commands;
 pub mod currency;
 pub mod install;
 pub mod log;
+pub mod model;
 pub mod monitor;
 pub mod price;
 pub mod protocol;
@@ -0,0 +1,259 @@
+//! Local model CLI commands
+//!
+//! This module ties the HuggingFace download, `llama-server` and channel
+//! registration together:
+//! - pull: Download a GGUF quantization and verify its checksum
+//! - serve: Start the inference server and register it as a routed channel
+//! - stop: Stop a served model and remove its channel
+//! - ps: List local model instances
+
+use anyhow::{anyhow, Result};
+use burncloud_database::Database;
+use burncloud_service_audit::{Actor, AuditEvent, AuditService};
+use burncloud_service_inference::lifecycle::{DEFAULT_CONTEXT_SIZE, DEFAULT_GPU_LAYERS};
+use burncloud_service_inference::{
+    InferenceService, ModelLifecycle, PullRequest, ServeOptions, SupervisorConfig,
+};
+use clap::ArgMatches;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+fn lifecycle() -> ModelLifecycle {
+    ModelLifecycle::new(Arc::new(InferenceService::new()))
+}
+
+/// Format a byte count as a human readable size
+fn format_bytes(bytes: i64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes.max(0) as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Handle model pull command
+pub async fn cmd_model_pull(db: &Database, args: &ArgMatches) -> Result<()> {
+    let model_id = args
+        .get_one::<String>("model")
+        .ok_or_else(|| anyhow!("Model ID is required"))?;
+    let file = args
+        .get_one::<String>("file")
+        .ok_or_else(|| anyhow!("Quantization or file path is required"))?;
+
+    let req = PullRequest {
+        model_id: model_id.clone(),
+        file: file.clone(),
+        sha256: args.get_one::<String>("sha256").cloned(),
+    };
+
+    println!("Pulling {} ({})...", model_id, file);
+    let pulled = lifecycle()
+        .pull(&req, |p| {
+            print!(
+                "\r  {:>5.1}%  {} / {}  {}/s    ",
+                p.percent(),
+                format_bytes(p.completed_bytes),
+                format_bytes(p.total_bytes),
+                format_bytes(p.download_speed)
+            );
+            let _ = io::stdout().flush();
+        })
+        .await?;
+    println!();
+    let event = AuditEvent::new("model.pull")
+        .target(model_id)
+        .after(&serde_json::json!({
+            "model_id": model_id,
+            "file": file,
+            "sha256": pulled.sha256,
+        }));
+    AuditService::record_or_log(db, &Actor::cli(), event).await;
+
+    println!("✓ Downloaded {}", pulled.file_path);
+    println!("  SHA-256: {}", pulled.sha256);
+    if pulled.verified {
+        println!("  Checksum verified");
+    } else {
+        println!("  No checksum recorded for this model, verification skipped");
+    }
+    println!();
+    println!("Run 'burncloud model serve {}' to start it.", model_id);
+
+    Ok(())
+}
+
+/// Handle model serve command
+///
+/// Runs in the foreground: the inference server is restarted if it crashes,
+/// and stopped with its channel removed when the command is interrupted with
+/// Ctrl+C. Idle unloading only applies to models served by `burncloud server`,
+/// whose router can cold-start them again.
+pub async fn cmd_model_serve(db: &Database, args: &ArgMatches) -> Result<()> {
+    let model_id = args
+        .get_one::<String>("model")
+        .ok_or_else(|| anyhow!("Model ID is required"))?;
+    let port = args
+        .get_one::<String>("port")
+        .map(|s| s.parse::<u16>())
+        .transpose()
+        .map_err(|_| anyhow!("Invalid port"))?;
+    let context_size = args
+        .get_one::<String>("ctx-size")
+        .map(|s| s.parse::<u32>())
+        .transpose()
+        .map_err(|_| anyhow!("Invalid context size"))?
+        .unwrap_or(DEFAULT_CONTEXT_SIZE);
+    let gpu_layers = args
+        .get_one::<String>("gpu-layers")
+        .map(|s| s.parse::<i32>())
+        .transpose()
+        .map_err(|_| anyhow!("Invalid GPU layer count"))?
+        .unwrap_or(DEFAULT_GPU_LAYERS);
+
+    let opts = ServeOptions {
+        model_id: model_id.clone(),
+        file_path: args.get_one::<String>("file").cloned(),
+        port,
+        context_size,
+        gpu_layers,
+    };
+
+    let inference = Arc::new(InferenceService::new());
+    let lifecycle = ModelLifecycle::new(inference.clone());
+    println!("Starting {}...", model_id);
+    let instance = lifecycle.serve(db, opts).await?;
+    let event = AuditEvent::new("model.serve")
+        .target(model_id)
+        .after(&instance);
+    AuditService::record_or_log(db, &Actor::cli(), event).await;
+
+    let supervisor = inference.start_supervisor(
+        Arc::new(Database::new().await?),
+        SupervisorConfig {
+            idle_timeout: None,
+            ..SupervisorConfig::from_env()
+        },
+    );
+
+    println!("✓ {} is ready", model_id);
+    println!("  File:    {}", instance.file_path);
+    println!("  URL:     http://127.0.0.1:{}", instance.port);
+    if let Some(channel_id) = instance.channel_id {
+        println!("  Channel: {}", channel_id);
+    }
+    println!();
+    println!("Press Ctrl+C to stop.");
+
+    tokio::signal::ctrl_c().await?;
+    supervisor.abort();
+    println!();
+    println!("Stopping {}...", model_id);
+    lifecycle.stop(db, model_id).await?;
+    let event = AuditEvent::new("model.stop").target(model_id);
+    AuditService::record_or_log(db, &Actor::cli(), event).await;
+    println!("✓ Stopped");
+
+    Ok(())
+}
+
+/// Handle model stop command
+pub async fn cmd_model_stop(db: &Database, args: &ArgMatches) -> Result<()> {
+    let model_id = args
+        .get_one::<String>("model")
+        .ok_or_else(|| anyhow!("Model ID is required"))?;
+
+    lifecycle().stop(db, model_id).await?;
+    let event = AuditEvent::new("model.stop").target(model_id);
+    AuditService::record_or_log(db, &Actor::cli(), event).await;
+    println!("✓ Stopped {}", model_id);
+
+    Ok(())
+}
+
+/// Handle model ps command
+pub async fn cmd_model_ps(db: &Database, args: &ArgMatches) -> Result<()> {
+    let format = args
+        .get_one::<String>("format")
+        .map(|s| s.as_str())
+        .unwrap_or("table");
+
+    let reports = lifecycle().ps(db).await?;
+
+    if format == "json" {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+        return Ok(());
+    }
+
+    if reports.is_empty() {
+        println!("No local models");
+        return Ok(());
+    }
+
+    println!(
+        "{:<40} {:<10} {:<8} {:<8} {:<8} {:<8}",
+        "Model", "Status", "Health", "Port", "PID", "Channel"
+    );
+    println!("{}", "-".repeat(88));
+    for report in reports {
+        let instance = report.instance;
+        let health = match instance.status.as_str() {
+            "running" if report.healthy => "ok",
+            "running" => "down",
+            _ => "-",
+        };
+        println!(
+            "{:<40} {:<10} {:<8} {:<8} {:<8} {:<8}",
+            instance.model_id,
+            instance.status,
+            health,
+            instance.port,
+            instance
+                .pid
+                .map(|p| p.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            instance
+                .channel_id
+                .map(|c| c.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+        );
+        if let Some(err) = instance.last_error {
+            println!("  last error: {}", err);
+        }
+    }
+
+    Ok(())
+}
+
+/// Route model subcommands to their handlers
+pub async fn handle_model_command(db: &Database, matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        Some(("pull", sub_m)) => cmd_model_pull(db, sub_m).await,
+        Some(("serve", sub_m)) => cmd_model_serve(db, sub_m).await,
+        Some(("stop", sub_m)) => cmd_model_stop(db, sub_m).await,
+        Some(("ps", sub_m)) => cmd_model_ps(db, sub_m).await,
+        _ => {
+            println!("Local model commands:");
+            println!("  pull    Download a GGUF quantization");
+            println!("  serve   Start a model and register it as a channel");
+            println!("  stop    Stop a served model");
+            println!("  ps      List local model instances");
+            println!("\nRun 'burncloud model <command> --help' for more information.");
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(0), "0.0 B");
+        assert_eq!(format_bytes(1536), "1.5 KB");
+        assert_eq!(format_bytes(4 * 1024 * 1024 * 1024), "4.0 GB");
+    }
+}
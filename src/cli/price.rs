@@ -13,6 +13,7 @@ use burncloud_database::Database;
 use burncloud_database_billing::{
     BillingPriceModel, BillingTieredPriceModel, PriceInput, TieredPriceInput,
 };
+use burncloud_service_audit::{Actor, AuditEvent, AuditService};
 use chrono::Utc;
 use clap::ArgMatches;
 use std::collections::HashMap;
@@ -172,7 +173,16 @@ pub async fn handle_price_command(db: &Database, matches: &ArgMatches) -> Result
                 model_type: None,
             };
 
+            let before =
+                BillingPriceModel::get(db, &model, &currency, input.region.as_deref()).await?;
             BillingPriceModel::upsert(db, &input).await?;
+            let after =
+                BillingPriceModel::get(db, &model, &currency, input.region.as_deref()).await?;
+            let event = AuditEvent::new("price.set")
+                .target(&model)
+                .before(&before)
+                .after(&after);
+            AuditService::record_or_log(db, &Actor::cli(), event).await;
 
             let region_str = input.region.as_deref().unwrap_or("");
             let region_display = if region_str.is_empty() {
@@ -214,6 +224,10 @@ pub async fn handle_price_command(db: &Database, matches: &ArgMatches) -> Result
                 BillingPriceModel::delete_all_for_model(db, model).await?;
                 println!("✓ All prices deleted for '{}'", model);
             }
+            let event = AuditEvent::new("price.delete")
+                .target(model)
+                .before(&serde_json::json!({ "region": region }));
+            AuditService::record_or_log(db, &Actor::cli(), event).await;
         }
         Some(("get", sub_m)) => {
             let model = sub_m
@@ -871,6 +885,15 @@ pub async fn handle_price_command(db: &Database, matches: &ArgMatches) -> Result
                 }
             }
 
+            let event = AuditEvent::new("price.import").after(&serde_json::json!({
+                "file": file_path,
+                "override": override_mode,
+                "prices": prices_imported,
+                "tiers": tiers_imported,
+                "errors": errors.len(),
+            }));
+            AuditService::record_or_log(db, &Actor::cli(), event).await;
+
             println!("✓ Import complete:");
             println!("  Prices imported: {}", prices_imported);
             println!("  Tiered entries imported: {}", tiers_imported);
@@ -1205,6 +1228,12 @@ pub async fn handle_price_command(db: &Database, matches: &ArgMatches) -> Result
             }
 
             println!("✅ Sync complete: {} upserted, {} errors", ok, err);
+            let event = AuditEvent::new("price.sync").after(&serde_json::json!({
+                "url": url,
+                "upserted": ok,
+                "errors": err,
+            }));
+            AuditService::record_or_log(db, &Actor::cli(), event).await;
 
             // Notify running server to refresh its in-memory price cache.
             // If the server is not running this is a no-op (warning only).
@@ -1313,6 +1342,10 @@ pub async fn handle_tiered_command(db: &Database, matches: &ArgMatches) -> Resul
             };
 
             BillingTieredPriceModel::upsert_tier(db, &input).await?;
+            let event = AuditEvent::new("price.tier_add")
+                .target(&model)
+                .after(&input);
+            AuditService::record_or_log(db, &Actor::cli(), event).await;
             println!(
                 "✓ Tier added for '{}': {}-{} tokens at ${:.4}/${:.4} per 1M",
                 model,
@@ -1338,6 +1371,11 @@ pub async fn handle_tiered_command(db: &Database, matches: &ArgMatches) -> Resul
                 }
             }
 
+            let event = AuditEvent::new("price.tier_import").after(&serde_json::json!({
+                "file": file_path,
+                "tiers": count,
+            }));
+            AuditService::record_or_log(db, &Actor::cli(), event).await;
             println!("✓ Imported {} tiered pricing entries", count);
         }
         Some(("delete-tiers", sub_m)) => {
@@ -1347,6 +1385,10 @@ pub async fn handle_tiered_command(db: &Database, matches: &ArgMatches) -> Resul
             let region = sub_m.get_one::<String>("region").map(|s| s.as_str());
 
             BillingTieredPriceModel::delete_tiers(db, model, region).await?;
+            let event = AuditEvent::new("price.tier_delete")
+                .target(model)
+                .before(&serde_json::json!({ "region": region }));
+            AuditService::record_or_log(db, &Actor::cli(), event).await;
             println!("✓ Deleted tiered pricing for '{}'", model);
         }
         Some(("check-tiered", sub_m)) => {
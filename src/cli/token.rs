@@ -1,6 +1,7 @@
 use anyhow::Result;
 use burncloud_database::Database;
 use burncloud_database_user::{UserApiKeyInput, UserApiKeyModel, UserApiKeyUpdateInput};
+use burncloud_service_audit::{Actor, AuditEvent, AuditService};
 use clap::ArgMatches;
 use std::io::{self, Write};
 
@@ -88,6 +89,10 @@ pub async fn handle_token_command(db: &Database, matches: &ArgMatches) -> Result
             };
 
             let token = UserApiKeyModel::create(db, &input).await?;
+            let event = AuditEvent::new("token.create")
+                .target(token.id)
+                .after(&token);
+            AuditService::record_or_log(db, &Actor::cli(), event).await;
             println!("✓ Token created successfully!");
             println!("Key: {}", token.key);
         }
@@ -110,7 +115,16 @@ pub async fn handle_token_command(db: &Database, matches: &ArgMatches) -> Result
                 expired_time: None,
             };
 
+            let before = UserApiKeyModel::get_by_key(db, key).await?;
             let updated = UserApiKeyModel::update(db, key, &input).await?;
+            if let (true, Some(before)) = (updated, &before) {
+                let after = UserApiKeyModel::get_by_key(db, key).await?;
+                let event = AuditEvent::new("token.update")
+                    .target(before.id)
+                    .before(before)
+                    .after(&after);
+                AuditService::record_or_log(db, &Actor::cli(), event).await;
+            }
             if updated {
                 println!("✓ Token '{}' updated successfully!", key);
             } else {
@@ -135,7 +149,14 @@ pub async fn handle_token_command(db: &Database, matches: &ArgMatches) -> Result
                 }
             }
 
+            let before = UserApiKeyModel::get_by_key(db, key).await?;
             let deleted = UserApiKeyModel::delete(db, key).await?;
+            if let (true, Some(before)) = (deleted, &before) {
+                let event = AuditEvent::new("token.delete")
+                    .target(before.id)
+                    .before(before);
+                AuditService::record_or_log(db, &Actor::cli(), event).await;
+            }
             if deleted {
                 println!("✓ Token deleted successfully!");
             } else {
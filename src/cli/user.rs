@@ -5,6 +5,7 @@ use anyhow::Result;
 use bcrypt::{hash, verify, DEFAULT_COST};
 use burncloud_database::Database;
 use burncloud_database_user::{UserAccount, UserDatabase};
+use burncloud_service_audit::{Actor, AuditEvent, AuditService};
 use clap::ArgMatches;
 use uuid::Uuid;
 
@@ -127,6 +128,14 @@ pub async fn cmd_user_register(db: &Database, matches: &ArgMatches) -> Result<()
 
     // Assign default 'user' role
     UserDatabase::assign_role(db, &user_id, "user").await?;
+    let event = AuditEvent::new("user.register")
+        .target(&user_id)
+        .after(&serde_json::json!({
+            "username": user.username,
+            "email": user.email,
+            "role": "user",
+        }));
+    AuditService::record_or_log(db, &Actor::cli(), event).await;
 
     println!("User '{}' registered successfully!", username);
     println!("User ID: {}", user_id);
@@ -343,6 +352,15 @@ pub async fn cmd_user_topup(db: &Database, matches: &ArgMatches) -> Result<()> {
 
     // Convert nanodollars to display format
     let new_balance = new_balance_nano as f64 / 1_000_000_000.0;
+    let event = AuditEvent::new("user.topup")
+        .target(user_id)
+        .after(&serde_json::json!({
+            "amount": amount_nano,
+            "currency": currency,
+            "balance": new_balance_nano,
+            "recharge_id": recharge_id,
+        }));
+    AuditService::record_or_log(db, &Actor::cli(), event).await;
 
     // Output success message
     println!("Topup successful!");
@@ -1,4 +1,5 @@
 use anyhow::Result;
+use burncloud_database_channel::ChannelProviderModel;
 use std::env;
 use std::path::Path;
 
@@ -9,7 +10,7 @@ fn main() -> Result<()> {
     dotenvy::dotenv().ok();
 
     // Auto-generate MASTER_KEY if missing
-    ensure_master_key();
+    ensure_master_key()?;
 
     let args: Vec<String> = env::args().collect();
 
@@ -98,9 +99,21 @@ fn is_valid_master_key() -> bool {
 
 /// Ensure MASTER_KEY exists and is valid: if missing or malformed, generate a
 /// 32-byte random key, write it to `.env`, and set it in the process environment.
-fn ensure_master_key() {
-    if is_valid_master_key() {
-        return;
+/// Fails instead when channel keys are already encrypted, since a new key
+/// could never read them.
+fn ensure_master_key() -> Result<()> {
+    // A key file configured by the operator takes the place of the env var
+    if is_valid_master_key() || env::var("MASTER_KEY_FILE").is_ok_and(|p| !p.trim().is_empty()) {
+        return Ok(());
+    }
+
+    let encrypted = encrypted_channel_keys()?;
+    if encrypted > 0 {
+        anyhow::bail!(
+            "MASTER_KEY is missing or malformed, but {encrypted} channel keys in the database \
+             are encrypted. Restore the master key that encrypted them (MASTER_KEY or \
+             MASTER_KEY_FILE); a newly generated key could not read them."
+        );
     }
 
     // Generate 32 random bytes as hex (64 chars)
@@ -147,6 +160,17 @@ fn ensure_master_key() {
     }
 
     env::set_var("MASTER_KEY", &hex_key);
+    Ok(())
+}
+
+/// Number of channel keys stored encrypted under some master key
+fn encrypted_channel_keys() -> Result<i64> {
+    tokio::runtime::Runtime::new()?.block_on(async {
+        let db = burncloud_database::create_default_database().await?;
+        let count = ChannelProviderModel::count_encrypted_keys(&db).await;
+        db.close().await?;
+        Ok(count?)
+    })
 }
 
 #[tokio::main]